@@ -0,0 +1,134 @@
+package io.github.gedgygedgy.rust.android.app;
+
+import android.content.ContentProvider;
+import android.content.ContentValues;
+import android.database.Cursor;
+import android.net.Uri;
+
+import io.github.gedgygedgy.rust.ops.FnFunction;
+
+import java.util.Collections;
+import java.util.HashMap;
+import java.util.Map;
+
+/**
+ * Base class for {@link ContentProvider}s that are implemented in Rust.
+ * Extend this class and register its methods with
+ * {@code android_utils::app::register_content_provider()}.
+ *
+ * <p>Content providers are created before the application, so the Rust
+ * provider is only created when the provider is first used, and must have
+ * been registered by then.
+ */
+public class RustContentProvider extends ContentProvider {
+    private static class QueryArguments {
+        public Uri uri;
+        public String[] projection;
+        public String selection;
+        public String[] selectionArgs;
+        public String sortOrder;
+    }
+
+    private static class InsertArguments {
+        public Uri uri;
+        public Map<String, Object> values;
+    }
+
+    private static class UpdateArguments {
+        public Uri uri;
+        public Map<String, Object> values;
+        public String selection;
+        public String[] selectionArgs;
+    }
+
+    private static class DeleteArguments {
+        public Uri uri;
+        public String selection;
+        public String[] selectionArgs;
+    }
+
+    private static final Map<Class<? extends RustContentProvider>, FnFunction<RustContentProvider, Void>> onCreateHooks =
+        Collections.synchronizedMap(new HashMap<>());
+
+    private boolean created = false;
+    private FnFunction<QueryArguments, Cursor> queryHook;
+    private FnFunction<Uri, String> getTypeHook;
+    private FnFunction<InsertArguments, Uri> insertHook;
+    private FnFunction<UpdateArguments, Integer> updateHook;
+    private FnFunction<DeleteArguments, Integer> deleteHook;
+
+    private static Map<String, Object> valuesToMap(ContentValues values) {
+        HashMap<String, Object> map = new HashMap<>();
+        if (values != null) {
+            for (Map.Entry<String, Object> entry : values.valueSet()) {
+                map.put(entry.getKey(), entry.getValue());
+            }
+        }
+        return map;
+    }
+
+    private synchronized void ensureCreated() {
+        if (this.created) {
+            return;
+        }
+        FnFunction<RustContentProvider, Void> hook = onCreateHooks.get(this.getClass());
+        if (hook == null) {
+            throw new IllegalStateException("No Rust content provider registered for " + this.getClass().getName());
+        }
+        hook.apply(this);
+        this.created = true;
+    }
+
+    @Override
+    public boolean onCreate() {
+        return true;
+    }
+
+    @Override
+    public Cursor query(Uri uri, String[] projection, String selection, String[] selectionArgs, String sortOrder) {
+        this.ensureCreated();
+        QueryArguments args = new QueryArguments();
+        args.uri = uri;
+        args.projection = projection;
+        args.selection = selection;
+        args.selectionArgs = selectionArgs;
+        args.sortOrder = sortOrder;
+        return this.queryHook.apply(args);
+    }
+
+    @Override
+    public String getType(Uri uri) {
+        this.ensureCreated();
+        return this.getTypeHook.apply(uri);
+    }
+
+    @Override
+    public Uri insert(Uri uri, ContentValues values) {
+        this.ensureCreated();
+        InsertArguments args = new InsertArguments();
+        args.uri = uri;
+        args.values = valuesToMap(values);
+        return this.insertHook.apply(args);
+    }
+
+    @Override
+    public int update(Uri uri, ContentValues values, String selection, String[] selectionArgs) {
+        this.ensureCreated();
+        UpdateArguments args = new UpdateArguments();
+        args.uri = uri;
+        args.values = valuesToMap(values);
+        args.selection = selection;
+        args.selectionArgs = selectionArgs;
+        return this.updateHook.apply(args);
+    }
+
+    @Override
+    public int delete(Uri uri, String selection, String[] selectionArgs) {
+        this.ensureCreated();
+        DeleteArguments args = new DeleteArguments();
+        args.uri = uri;
+        args.selection = selection;
+        args.selectionArgs = selectionArgs;
+        return this.deleteHook.apply(args);
+    }
+}
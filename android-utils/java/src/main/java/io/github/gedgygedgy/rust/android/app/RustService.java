@@ -2,6 +2,7 @@ package io.github.gedgygedgy.rust.android.app;
 
 import android.app.Service;
 import android.content.Intent;
+import android.content.res.Configuration;
 import android.os.IBinder;
 
 import io.github.gedgygedgy.rust.ops.FnFunction;
@@ -27,10 +28,24 @@ public class RustService extends Service {
     private FnFunction<Intent, IBinder> onBindHook;
     private FnFunction<Intent, Boolean> onUnbindHook;
     private FnFunction<Intent, Void> onRebindHook;
+    private FnFunction<Void, Void> onDestroyHook;
+    private FnFunction<Intent, Void> onTaskRemovedHook;
+    private FnFunction<Integer, Void> onTrimMemoryHook;
+    private FnFunction<Void, Void> onLowMemoryHook;
+    private FnFunction<Configuration, Void> onConfigurationChangedHook;
+
+    private static native boolean nativeRegisterService(Class<? extends RustService> cls);
 
     @Override
     public void onCreate() {
-        onCreateHooks.get(this.getClass()).apply(this);
+        FnFunction<RustService, Void> hook = onCreateHooks.get(this.getClass());
+        if (hook == null && nativeRegisterService(this.getClass())) {
+            hook = onCreateHooks.get(this.getClass());
+        }
+        if (hook == null) {
+            throw new IllegalStateException("No Rust service registered for " + this.getClass().getName());
+        }
+        hook.apply(this);
     }
 
     @Override
@@ -57,11 +72,37 @@ public class RustService extends Service {
         this.onRebindHook.apply(intent);
     }
 
+    @Override
+    public void onTaskRemoved(Intent rootIntent) {
+        this.onTaskRemovedHook.apply(rootIntent);
+    }
+
+    @Override
+    public void onTrimMemory(int level) {
+        this.onTrimMemoryHook.apply(level);
+    }
+
+    @Override
+    public void onLowMemory() {
+        this.onLowMemoryHook.apply(null);
+    }
+
+    @Override
+    public void onConfigurationChanged(Configuration newConfig) {
+        this.onConfigurationChangedHook.apply(newConfig);
+    }
+
     @Override
     public void onDestroy() {
+        this.onDestroyHook.apply(null);
         this.onStartCommandHook.close();
         this.onBindHook.close();
         this.onUnbindHook.close();
         this.onRebindHook.close();
+        this.onDestroyHook.close();
+        this.onTaskRemovedHook.close();
+        this.onTrimMemoryHook.close();
+        this.onLowMemoryHook.close();
+        this.onConfigurationChangedHook.close();
     }
 }
@@ -0,0 +1,45 @@
+package io.github.gedgygedgy.rust.android.bluetooth;
+
+import android.content.BroadcastReceiver;
+import android.content.Context;
+import android.content.Intent;
+import android.content.IntentFilter;
+
+import io.github.gedgygedgy.rust.stream.QueueStream;
+import io.github.gedgygedgy.rust.stream.Stream;
+
+final class RustBluetoothConnectionStateReceiver extends BroadcastReceiver {
+    private final QueueStream<Intent> stream = new QueueStream<>();
+    private final Context context;
+    private boolean closed = false;
+
+    public RustBluetoothConnectionStateReceiver(Context context, String action) {
+        this.context = context;
+        context.registerReceiver(this, new IntentFilter(action));
+    }
+
+    public Stream<Intent> getIntentStream() {
+        return this.stream;
+    }
+
+    @Override
+    public void onReceive(Context context, Intent intent) {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.stream.add(intent);
+        }
+    }
+
+    public void close() {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.closed = true;
+            this.stream.finish();
+        }
+        this.context.unregisterReceiver(this);
+    }
+}
@@ -0,0 +1,29 @@
+package io.github.gedgygedgy.rust.android.bluetooth;
+
+import android.bluetooth.BluetoothProfile;
+
+import io.github.gedgygedgy.rust.future.Future;
+import io.github.gedgygedgy.rust.future.SimpleFuture;
+
+final class RustBluetoothProfileServiceListener implements BluetoothProfile.ServiceListener {
+    private final SimpleFuture<BluetoothProfile> future = new SimpleFuture<>();
+    private boolean connected = false;
+
+    public Future<BluetoothProfile> getProxyFuture() {
+        return this.future;
+    }
+
+    @Override
+    public void onServiceConnected(int profile, BluetoothProfile proxy) {
+        synchronized (this) {
+            if (this.connected) {
+                return;
+            }
+            this.connected = true;
+        }
+        this.future.wake(proxy);
+    }
+
+    @Override
+    public void onServiceDisconnected(int profile) {}
+}
@@ -0,0 +1,65 @@
+package io.github.gedgygedgy.rust.android.content;
+
+import android.content.BroadcastReceiver;
+import android.content.Context;
+import android.content.Intent;
+import android.content.IntentFilter;
+
+import io.github.gedgygedgy.rust.stream.QueueStream;
+import io.github.gedgygedgy.rust.stream.Stream;
+
+final class RustBroadcastReceiver extends BroadcastReceiver {
+    public static class Event {
+        public final Context context;
+        public final Intent intent;
+
+        public Event(Context context, Intent intent) {
+            this.context = context;
+            this.intent = intent;
+        }
+    }
+
+    private final QueueStream<Event> stream = new QueueStream<>();
+    private final Context context;
+    private boolean closed = false;
+
+    public RustBroadcastReceiver() {
+        this.context = null;
+    }
+
+    public RustBroadcastReceiver(Context context, String[] actions) {
+        this.context = context;
+        IntentFilter filter = new IntentFilter();
+        for (String action : actions) {
+            filter.addAction(action);
+        }
+        context.registerReceiver(this, filter);
+    }
+
+    public Stream<Event> getEventStream() {
+        return this.stream;
+    }
+
+    @Override
+    public void onReceive(Context context, Intent intent) {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.stream.add(new Event(context, intent));
+        }
+    }
+
+    public void close() {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.closed = true;
+            this.stream.finish();
+        }
+        if (this.context != null) {
+            this.context.unregisterReceiver(this);
+        }
+    }
+}
@@ -0,0 +1,13 @@
+package io.github.gedgygedgy.rust.android.hardware;
+
+final class RustSensorEvent {
+    public final int sensorType;
+    public final long timestamp;
+    public final float[] values;
+
+    public RustSensorEvent(int sensorType, long timestamp, float[] values) {
+        this.sensorType = sensorType;
+        this.timestamp = timestamp;
+        this.values = values.clone();
+    }
+}
@@ -0,0 +1,48 @@
+package io.github.gedgygedgy.rust.android.hardware;
+
+import android.hardware.Sensor;
+import android.hardware.SensorEvent;
+import android.hardware.SensorEventListener;
+import android.hardware.SensorManager;
+
+import io.github.gedgygedgy.rust.future.Future;
+import io.github.gedgygedgy.rust.future.SimpleFuture;
+
+final class RustSensorSnapshotListener implements SensorEventListener {
+    private final SimpleFuture<RustSensorEvent> future = new SimpleFuture<>();
+    private final SensorManager manager;
+    private boolean done = false;
+
+    public RustSensorSnapshotListener(SensorManager manager) {
+        this.manager = manager;
+    }
+
+    public Future<RustSensorEvent> getEventFuture() {
+        return this.future;
+    }
+
+    @Override
+    public void onSensorChanged(SensorEvent event) {
+        synchronized (this) {
+            if (this.done) {
+                return;
+            }
+            this.done = true;
+        }
+        this.manager.unregisterListener(this);
+        this.future.wake(new RustSensorEvent(event.sensor.getType(), event.timestamp, event.values));
+    }
+
+    @Override
+    public void onAccuracyChanged(Sensor sensor, int accuracy) {}
+
+    public void close() {
+        synchronized (this) {
+            if (this.done) {
+                return;
+            }
+            this.done = true;
+        }
+        this.manager.unregisterListener(this);
+    }
+}
@@ -0,0 +1,46 @@
+package io.github.gedgygedgy.rust.android.hardware;
+
+import android.hardware.Sensor;
+import android.hardware.SensorManager;
+import android.hardware.TriggerEvent;
+import android.hardware.TriggerEventListener;
+
+import io.github.gedgygedgy.rust.future.Future;
+import io.github.gedgygedgy.rust.future.SimpleFuture;
+
+final class RustTriggerEventListener extends TriggerEventListener {
+    private final SimpleFuture<RustSensorEvent> future = new SimpleFuture<>();
+    private final SensorManager manager;
+    private final Sensor sensor;
+    private boolean done = false;
+
+    public RustTriggerEventListener(SensorManager manager, Sensor sensor) {
+        this.manager = manager;
+        this.sensor = sensor;
+    }
+
+    public Future<RustSensorEvent> getEventFuture() {
+        return this.future;
+    }
+
+    @Override
+    public void onTrigger(TriggerEvent event) {
+        synchronized (this) {
+            if (this.done) {
+                return;
+            }
+            this.done = true;
+        }
+        this.future.wake(new RustSensorEvent(event.sensor.getType(), event.timestamp, event.values));
+    }
+
+    public void close() {
+        synchronized (this) {
+            if (this.done) {
+                return;
+            }
+            this.done = true;
+        }
+        this.manager.cancelTriggerSensor(this, this.sensor);
+    }
+}
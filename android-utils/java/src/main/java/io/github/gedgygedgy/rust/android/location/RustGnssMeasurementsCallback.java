@@ -0,0 +1,101 @@
+package io.github.gedgygedgy.rust.android.location;
+
+import android.annotation.TargetApi;
+import android.content.Context;
+import android.location.GnssClock;
+import android.location.GnssMeasurement;
+import android.location.GnssMeasurementsEvent;
+import android.location.LocationManager;
+import android.os.Build;
+import android.os.Handler;
+import android.os.Looper;
+
+import io.github.gedgygedgy.rust.stream.QueueStream;
+import io.github.gedgygedgy.rust.stream.Stream;
+
+import java.util.Collection;
+
+@TargetApi(Build.VERSION_CODES.N)
+final class RustGnssMeasurementsCallback extends GnssMeasurementsEvent.Callback {
+    public static class Event {
+        public final long timeNanos;
+        public final long fullBiasNanos;
+        public final double biasNanos;
+        public final int[] svid;
+        public final int[] constellationType;
+        public final int[] state;
+        public final double[] cn0DbHz;
+        public final double[] timeOffsetNanos;
+        public final long[] receivedSvTimeNanos;
+        public final double[] pseudorangeRateMetersPerSecond;
+        public final double[] accumulatedDeltaRangeMeters;
+
+        public Event(GnssMeasurementsEvent event) {
+            GnssClock clock = event.getClock();
+            this.timeNanos = clock.getTimeNanos();
+            this.fullBiasNanos = clock.hasFullBiasNanos() ? clock.getFullBiasNanos() : 0;
+            this.biasNanos = clock.hasBiasNanos() ? clock.getBiasNanos() : 0.0;
+
+            Collection<GnssMeasurement> measurements = event.getMeasurements();
+            int count = measurements.size();
+            this.svid = new int[count];
+            this.constellationType = new int[count];
+            this.state = new int[count];
+            this.cn0DbHz = new double[count];
+            this.timeOffsetNanos = new double[count];
+            this.receivedSvTimeNanos = new long[count];
+            this.pseudorangeRateMetersPerSecond = new double[count];
+            this.accumulatedDeltaRangeMeters = new double[count];
+            int i = 0;
+            for (GnssMeasurement measurement : measurements) {
+                this.svid[i] = measurement.getSvid();
+                this.constellationType[i] = measurement.getConstellationType();
+                this.state[i] = measurement.getState();
+                this.cn0DbHz[i] = measurement.getCn0DbHz();
+                this.timeOffsetNanos[i] = measurement.getTimeOffsetNanos();
+                this.receivedSvTimeNanos[i] = measurement.getReceivedSvTimeNanos();
+                this.pseudorangeRateMetersPerSecond[i] = measurement.getPseudorangeRateMetersPerSecond();
+                this.accumulatedDeltaRangeMeters[i] = measurement.getAccumulatedDeltaRangeMeters();
+                i++;
+            }
+        }
+    }
+
+    private final QueueStream<Event> stream = new QueueStream<>();
+    private final LocationManager manager;
+    private boolean closed = false;
+
+    public RustGnssMeasurementsCallback(Context context) {
+        this.manager = (LocationManager) context.getSystemService(Context.LOCATION_SERVICE);
+    }
+
+    public boolean register() {
+        return this.manager.registerGnssMeasurementsCallback(this, new Handler(Looper.getMainLooper()));
+    }
+
+    public Stream<Event> getEventStream() {
+        return this.stream;
+    }
+
+    @Override
+    public void onGnssMeasurementsReceived(GnssMeasurementsEvent event) {
+        Event e = new Event(event);
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.stream.add(e);
+        }
+    }
+
+    public void close() {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.closed = true;
+            this.stream.finish();
+        }
+        this.manager.unregisterGnssMeasurementsCallback(this);
+    }
+}
@@ -0,0 +1,118 @@
+package io.github.gedgygedgy.rust.android.location;
+
+import android.annotation.TargetApi;
+import android.content.Context;
+import android.location.GnssStatus;
+import android.location.LocationManager;
+import android.os.Build;
+import android.os.Handler;
+import android.os.Looper;
+
+import io.github.gedgygedgy.rust.stream.QueueStream;
+import io.github.gedgygedgy.rust.stream.Stream;
+
+@TargetApi(Build.VERSION_CODES.N)
+final class RustGnssStatusCallback extends GnssStatus.Callback {
+    public static class Event {}
+
+    public static class StartedEvent extends Event {}
+
+    public static class StoppedEvent extends Event {}
+
+    public static class FirstFixEvent extends Event {
+        public final int ttffMillis;
+
+        public FirstFixEvent(int ttffMillis) {
+            this.ttffMillis = ttffMillis;
+        }
+    }
+
+    public static class SatelliteStatusEvent extends Event {
+        public final int[] svid;
+        public final int[] constellationType;
+        public final float[] cn0DbHz;
+        public final float[] elevationDegrees;
+        public final float[] azimuthDegrees;
+        public final boolean[] usedInFix;
+        public final boolean[] hasAlmanacData;
+        public final boolean[] hasEphemerisData;
+
+        public SatelliteStatusEvent(GnssStatus status) {
+            int count = status.getSatelliteCount();
+            this.svid = new int[count];
+            this.constellationType = new int[count];
+            this.cn0DbHz = new float[count];
+            this.elevationDegrees = new float[count];
+            this.azimuthDegrees = new float[count];
+            this.usedInFix = new boolean[count];
+            this.hasAlmanacData = new boolean[count];
+            this.hasEphemerisData = new boolean[count];
+            for (int i = 0; i < count; i++) {
+                this.svid[i] = status.getSvid(i);
+                this.constellationType[i] = status.getConstellationType(i);
+                this.cn0DbHz[i] = status.getCn0DbHz(i);
+                this.elevationDegrees[i] = status.getElevationDegrees(i);
+                this.azimuthDegrees[i] = status.getAzimuthDegrees(i);
+                this.usedInFix[i] = status.usedInFix(i);
+                this.hasAlmanacData[i] = status.hasAlmanacData(i);
+                this.hasEphemerisData[i] = status.hasEphemerisData(i);
+            }
+        }
+    }
+
+    private final QueueStream<Event> stream = new QueueStream<>();
+    private final LocationManager manager;
+    private boolean closed = false;
+
+    public RustGnssStatusCallback(Context context) {
+        this.manager = (LocationManager) context.getSystemService(Context.LOCATION_SERVICE);
+    }
+
+    public boolean register() {
+        return this.manager.registerGnssStatusCallback(this, new Handler(Looper.getMainLooper()));
+    }
+
+    public Stream<Event> getEventStream() {
+        return this.stream;
+    }
+
+    private void add(Event event) {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.stream.add(event);
+        }
+    }
+
+    @Override
+    public void onStarted() {
+        this.add(new StartedEvent());
+    }
+
+    @Override
+    public void onStopped() {
+        this.add(new StoppedEvent());
+    }
+
+    @Override
+    public void onFirstFix(int ttffMillis) {
+        this.add(new FirstFixEvent(ttffMillis));
+    }
+
+    @Override
+    public void onSatelliteStatusChanged(GnssStatus status) {
+        this.add(new SatelliteStatusEvent(status));
+    }
+
+    public void close() {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.closed = true;
+            this.stream.finish();
+        }
+        this.manager.unregisterGnssStatusCallback(this);
+    }
+}
@@ -0,0 +1,75 @@
+package io.github.gedgygedgy.rust.android.location;
+
+import android.app.PendingIntent;
+import android.content.BroadcastReceiver;
+import android.content.Context;
+import android.content.Intent;
+import android.content.IntentFilter;
+import android.location.LocationManager;
+
+import io.github.gedgygedgy.rust.stream.QueueStream;
+import io.github.gedgygedgy.rust.stream.Stream;
+
+import java.util.concurrent.atomic.AtomicInteger;
+
+final class RustProximityAlertReceiver extends BroadcastReceiver {
+    private static final String ACTION_PREFIX = "io.github.gedgygedgy.rust.android.location.PROXIMITY_ALERT.";
+    // PendingIntent.FLAG_MUTABLE, which is required on API 31+ so that the
+    // system can fill in KEY_PROXIMITY_ENTERING.
+    private static final int FLAG_MUTABLE = 0x02000000;
+    private static final AtomicInteger nextId = new AtomicInteger();
+
+    private final QueueStream<Boolean> stream = new QueueStream<>();
+    private final Context context;
+    private final LocationManager manager;
+    private final PendingIntent pendingIntent;
+    private boolean closed = false;
+
+    public RustProximityAlertReceiver(Context context, double latitude, double longitude, float radius, long expiration) {
+        this.context = context;
+        this.manager = (LocationManager) context.getSystemService(Context.LOCATION_SERVICE);
+
+        String action = ACTION_PREFIX + nextId.getAndIncrement();
+        context.registerReceiver(this, new IntentFilter(action));
+
+        Intent intent = new Intent(action);
+        intent.setPackage(context.getPackageName());
+        this.pendingIntent = PendingIntent.getBroadcast(context, 0, intent, PendingIntent.FLAG_UPDATE_CURRENT | FLAG_MUTABLE);
+
+        try {
+            this.manager.addProximityAlert(latitude, longitude, radius, expiration, this.pendingIntent);
+        } catch (RuntimeException ex) {
+            this.context.unregisterReceiver(this);
+            this.pendingIntent.cancel();
+            throw ex;
+        }
+    }
+
+    public Stream<Boolean> getEventStream() {
+        return this.stream;
+    }
+
+    @Override
+    public void onReceive(Context context, Intent intent) {
+        boolean entering = intent.getBooleanExtra(LocationManager.KEY_PROXIMITY_ENTERING, false);
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.stream.add(entering);
+        }
+    }
+
+    public void close() {
+        synchronized (this) {
+            if (this.closed) {
+                return;
+            }
+            this.closed = true;
+            this.stream.finish();
+        }
+        this.manager.removeProximityAlert(this.pendingIntent);
+        this.context.unregisterReceiver(this);
+        this.pendingIntent.cancel();
+    }
+}
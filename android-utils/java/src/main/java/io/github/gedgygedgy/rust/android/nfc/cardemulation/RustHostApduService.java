@@ -0,0 +1,43 @@
+package io.github.gedgygedgy.rust.android.nfc.cardemulation;
+
+import android.nfc.cardemulation.HostApduService;
+import android.os.Bundle;
+
+import io.github.gedgygedgy.rust.ops.FnFunction;
+
+import java.util.HashMap;
+
+/**
+ * Base class for {@link HostApduService}s that are implemented in Rust.
+ * Extend this class and register its methods with
+ * {@code android_utils::nfc::register_host_apdu_service()}.
+ */
+public class RustHostApduService extends HostApduService {
+    private static final HashMap<Class<? extends RustHostApduService>, FnFunction<RustHostApduService, Void>> onCreateHooks = new HashMap<>();
+
+    private FnFunction<byte[], byte[]> processCommandApduHook;
+    private FnFunction<Integer, Void> onDeactivatedHook;
+
+    @Override
+    public void onCreate() {
+        super.onCreate();
+        onCreateHooks.get(this.getClass()).apply(this);
+    }
+
+    @Override
+    public byte[] processCommandApdu(byte[] commandApdu, Bundle extras) {
+        return this.processCommandApduHook.apply(commandApdu);
+    }
+
+    @Override
+    public void onDeactivated(int reason) {
+        this.onDeactivatedHook.apply(reason);
+    }
+
+    @Override
+    public void onDestroy() {
+        this.processCommandApduHook.close();
+        this.onDeactivatedHook.close();
+        super.onDestroy();
+    }
+}
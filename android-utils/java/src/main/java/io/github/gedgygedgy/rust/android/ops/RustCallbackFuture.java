@@ -0,0 +1,61 @@
+package io.github.gedgygedgy.rust.android.ops;
+
+import io.github.gedgygedgy.rust.future.Future;
+import io.github.gedgygedgy.rust.future.SimpleFuture;
+
+import java.lang.reflect.InvocationHandler;
+import java.lang.reflect.Method;
+import java.lang.reflect.Proxy;
+
+final class RustCallbackFuture implements InvocationHandler {
+    public static class Outcome {
+        public final boolean error;
+        public final Object value;
+
+        public Outcome(boolean error, Object value) {
+            this.error = error;
+            this.value = value;
+        }
+    }
+
+    private final String errorMethod;
+    private final SimpleFuture<Outcome> future = new SimpleFuture<>();
+    private boolean completed = false;
+
+    public RustCallbackFuture(String errorMethod) {
+        this.errorMethod = errorMethod;
+    }
+
+    public Future<Outcome> getOutcomeFuture() {
+        return this.future;
+    }
+
+    public Object newProxy(Class<?> iface) {
+        return Proxy.newProxyInstance(iface.getClassLoader(), new Class<?>[] {iface}, this);
+    }
+
+    @Override
+    public Object invoke(Object proxy, Method method, Object[] args) {
+        if (method.getDeclaringClass() == Object.class) {
+            switch (method.getName()) {
+            case "equals":
+                return proxy == args[0];
+            case "hashCode":
+                return System.identityHashCode(proxy);
+            case "toString":
+                return "RustCallbackFuture@" + Integer.toHexString(System.identityHashCode(proxy));
+            }
+        }
+
+        synchronized (this) {
+            if (this.completed) {
+                return null;
+            }
+            this.completed = true;
+        }
+        boolean error = method.getName().equals(this.errorMethod);
+        Object value = args != null && args.length > 0 ? args[0] : null;
+        this.future.wake(new Outcome(error, value));
+        return null;
+    }
+}
@@ -0,0 +1,10 @@
+package io.github.gedgygedgy.rust.android.ops;
+
+import java.util.concurrent.Executor;
+
+final class RustDirectExecutor implements Executor {
+    @Override
+    public void execute(Runnable command) {
+        command.run();
+    }
+}
@@ -0,0 +1,68 @@
+package io.github.gedgygedgy.rust.android.ops;
+
+import io.github.gedgygedgy.rust.stream.QueueStream;
+import io.github.gedgygedgy.rust.stream.Stream;
+
+import java.lang.reflect.InvocationHandler;
+import java.lang.reflect.Method;
+import java.lang.reflect.Proxy;
+
+final class RustListenerProxy implements InvocationHandler {
+    public static class Call {
+        public final String method;
+        public final Object[] args;
+
+        public Call(String method, Object[] args) {
+            this.method = method;
+            this.args = args;
+        }
+    }
+
+    private final QueueStream<Call> stream = new QueueStream<>();
+
+    public Stream<Call> getCallStream() {
+        return this.stream;
+    }
+
+    public Object newProxy(Class<?> iface) {
+        return Proxy.newProxyInstance(iface.getClassLoader(), new Class<?>[] {iface}, this);
+    }
+
+    @Override
+    public Object invoke(Object proxy, Method method, Object[] args) {
+        if (method.getDeclaringClass() == Object.class) {
+            switch (method.getName()) {
+            case "equals":
+                return proxy == args[0];
+            case "hashCode":
+                return System.identityHashCode(proxy);
+            case "toString":
+                return "RustListenerProxy@" + Integer.toHexString(System.identityHashCode(proxy));
+            }
+        }
+
+        this.stream.add(new Call(method.getName(), args != null ? args : new Object[0]));
+        return defaultValue(method.getReturnType());
+    }
+
+    private static Object defaultValue(Class<?> type) {
+        if (type == boolean.class) {
+            return false;
+        } else if (type == char.class) {
+            return '\0';
+        } else if (type == byte.class) {
+            return (byte) 0;
+        } else if (type == short.class) {
+            return (short) 0;
+        } else if (type == int.class) {
+            return 0;
+        } else if (type == long.class) {
+            return 0L;
+        } else if (type == float.class) {
+            return 0.0f;
+        } else if (type == double.class) {
+            return 0.0;
+        }
+        return null;
+    }
+}
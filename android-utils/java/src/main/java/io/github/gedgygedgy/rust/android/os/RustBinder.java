@@ -0,0 +1,43 @@
+package io.github.gedgygedgy.rust.android.os;
+
+import android.os.Binder;
+import android.os.Parcel;
+import android.os.RemoteException;
+
+import io.github.gedgygedgy.rust.ops.FnFunction;
+
+import java.io.Closeable;
+
+final class RustBinder extends Binder implements Closeable {
+    private static class TransactArguments {
+        public int code;
+        public Parcel data;
+        public Parcel reply;
+        public int flags;
+    }
+
+    private final FnFunction<TransactArguments, Boolean> onTransactHook;
+
+    public RustBinder(FnFunction<TransactArguments, Boolean> onTransactHook) {
+        this.onTransactHook = onTransactHook;
+    }
+
+    @Override
+    protected boolean onTransact(int code, Parcel data, Parcel reply, int flags) throws RemoteException {
+        TransactArguments args = new TransactArguments();
+        args.code = code;
+        args.data = data;
+        args.reply = reply;
+        args.flags = flags;
+        Boolean handled = this.onTransactHook.apply(args);
+        if (handled != null && handled) {
+            return true;
+        }
+        return super.onTransact(code, data, reply, flags);
+    }
+
+    @Override
+    public void close() {
+        this.onTransactHook.close();
+    }
+}
@@ -8,10 +8,21 @@ import io.github.gedgygedgy.rust.stream.Stream;
 
 final class RustHandlerCallback implements Handler.Callback {
     private final QueueStream<Message> stream = new QueueStream<>();
+    private final boolean copy;
+
+    public RustHandlerCallback() {
+        this(false);
+    }
+
+    public RustHandlerCallback(boolean copy) {
+        this.copy = copy;
+    }
 
     @Override
     public boolean handleMessage(Message message) {
-        this.stream.add(message);
+        // Messages dispatched by a Looper are recycled once they have been
+        // handled, so they have to be copied to outlive the callback.
+        this.stream.add(this.copy ? Message.obtain(message) : message);
         return false;
     }
 
@@ -0,0 +1,34 @@
+package io.github.gedgygedgy.rust.android.os;
+
+import android.os.MessageQueue;
+
+import io.github.gedgygedgy.rust.ops.FnFunction;
+
+import java.io.Closeable;
+
+final class RustIdleHandler implements MessageQueue.IdleHandler, Closeable {
+    private final MessageQueue queue;
+    private final FnFunction<Void, Boolean> function;
+
+    public RustIdleHandler(MessageQueue queue, FnFunction<Void, Boolean> function) {
+        this.queue = queue;
+        this.function = function;
+        queue.addIdleHandler(this);
+    }
+
+    @Override
+    public boolean queueIdle() {
+        Boolean keep = this.function.apply(null);
+        if (keep == null || !keep) {
+            this.function.close();
+            return false;
+        }
+        return true;
+    }
+
+    @Override
+    public void close() {
+        this.queue.removeIdleHandler(this);
+        this.function.close();
+    }
+}
@@ -0,0 +1,136 @@
+package io.github.gedgygedgy.rust.android.telecom;
+
+import android.telecom.CallAudioState;
+import android.telecom.Connection;
+
+import io.github.gedgygedgy.rust.stream.QueueStream;
+import io.github.gedgygedgy.rust.stream.Stream;
+
+final class RustConnection extends Connection {
+    public static class Event {}
+
+    public static class StateChangedEvent extends Event {
+        public final int state;
+
+        public StateChangedEvent(int state) {
+            this.state = state;
+        }
+    }
+
+    public static class AnswerEvent extends Event {
+        public final int videoState;
+
+        public AnswerEvent(int videoState) {
+            this.videoState = videoState;
+        }
+    }
+
+    public static class RejectEvent extends Event {}
+
+    public static class DisconnectEvent extends Event {}
+
+    public static class AbortEvent extends Event {}
+
+    public static class HoldEvent extends Event {}
+
+    public static class UnholdEvent extends Event {}
+
+    public static class ShowIncomingCallUiEvent extends Event {}
+
+    public static class PlayDtmfToneEvent extends Event {
+        public final char c;
+
+        public PlayDtmfToneEvent(char c) {
+            this.c = c;
+        }
+    }
+
+    public static class StopDtmfToneEvent extends Event {}
+
+    public static class CallAudioStateChangedEvent extends Event {
+        public final int route;
+        public final int supportedRouteMask;
+        public final boolean muted;
+
+        public CallAudioStateChangedEvent(CallAudioState state) {
+            this.route = state.getRoute();
+            this.supportedRouteMask = state.getSupportedRouteMask();
+            this.muted = state.isMuted();
+        }
+    }
+
+    private final QueueStream<Event> stream = new QueueStream<>();
+    private boolean finished = false;
+
+    public Stream<Event> getEventStream() {
+        return this.stream;
+    }
+
+    private void add(Event event) {
+        synchronized (this) {
+            if (this.finished) {
+                return;
+            }
+            this.stream.add(event);
+            if (event instanceof StateChangedEvent && ((StateChangedEvent) event).state == STATE_DISCONNECTED) {
+                this.finished = true;
+                this.stream.finish();
+            }
+        }
+    }
+
+    @Override
+    public void onStateChanged(int state) {
+        this.add(new StateChangedEvent(state));
+    }
+
+    @Override
+    public void onAnswer(int videoState) {
+        this.add(new AnswerEvent(videoState));
+    }
+
+    @Override
+    public void onReject() {
+        this.add(new RejectEvent());
+    }
+
+    @Override
+    public void onDisconnect() {
+        this.add(new DisconnectEvent());
+    }
+
+    @Override
+    public void onAbort() {
+        this.add(new AbortEvent());
+    }
+
+    @Override
+    public void onHold() {
+        this.add(new HoldEvent());
+    }
+
+    @Override
+    public void onUnhold() {
+        this.add(new UnholdEvent());
+    }
+
+    @Override
+    public void onShowIncomingCallUi() {
+        this.add(new ShowIncomingCallUiEvent());
+    }
+
+    @Override
+    public void onPlayDtmfTone(char c) {
+        this.add(new PlayDtmfToneEvent(c));
+    }
+
+    @Override
+    public void onStopDtmfTone() {
+        this.add(new StopDtmfToneEvent());
+    }
+
+    @Override
+    public void onCallAudioStateChanged(CallAudioState state) {
+        this.add(new CallAudioStateChangedEvent(state));
+    }
+}
@@ -0,0 +1,79 @@
+package io.github.gedgygedgy.rust.android.telecom;
+
+import android.telecom.Connection;
+import android.telecom.ConnectionRequest;
+import android.telecom.ConnectionService;
+import android.telecom.DisconnectCause;
+import android.telecom.PhoneAccountHandle;
+
+import io.github.gedgygedgy.rust.ops.FnFunction;
+
+import java.util.HashMap;
+
+/**
+ * Base class for {@link ConnectionService}s that are implemented in Rust.
+ * Extend this class and register its methods with
+ * {@code android_utils::telecom::register_connection_service()}.
+ */
+public class RustConnectionService extends ConnectionService {
+    private static class CreateConnectionArguments {
+        public PhoneAccountHandle phoneAccount;
+        public ConnectionRequest request;
+    }
+
+    private static final HashMap<Class<? extends RustConnectionService>, FnFunction<RustConnectionService, Void>> onCreateHooks = new HashMap<>();
+
+    private FnFunction<CreateConnectionArguments, Connection> onCreateIncomingConnectionHook;
+    private FnFunction<CreateConnectionArguments, Connection> onCreateOutgoingConnectionHook;
+    private FnFunction<CreateConnectionArguments, Void> onCreateIncomingConnectionFailedHook;
+    private FnFunction<CreateConnectionArguments, Void> onCreateOutgoingConnectionFailedHook;
+
+    private static CreateConnectionArguments createArguments(PhoneAccountHandle phoneAccount, ConnectionRequest request) {
+        CreateConnectionArguments args = new CreateConnectionArguments();
+        args.phoneAccount = phoneAccount;
+        args.request = request;
+        return args;
+    }
+
+    private static Connection orFailed(Connection connection) {
+        if (connection == null) {
+            return Connection.createFailedConnection(new DisconnectCause(DisconnectCause.ERROR));
+        }
+        return connection;
+    }
+
+    @Override
+    public void onCreate() {
+        super.onCreate();
+        onCreateHooks.get(this.getClass()).apply(this);
+    }
+
+    @Override
+    public Connection onCreateIncomingConnection(PhoneAccountHandle phoneAccount, ConnectionRequest request) {
+        return orFailed(this.onCreateIncomingConnectionHook.apply(createArguments(phoneAccount, request)));
+    }
+
+    @Override
+    public Connection onCreateOutgoingConnection(PhoneAccountHandle phoneAccount, ConnectionRequest request) {
+        return orFailed(this.onCreateOutgoingConnectionHook.apply(createArguments(phoneAccount, request)));
+    }
+
+    @Override
+    public void onCreateIncomingConnectionFailed(PhoneAccountHandle phoneAccount, ConnectionRequest request) {
+        this.onCreateIncomingConnectionFailedHook.apply(createArguments(phoneAccount, request));
+    }
+
+    @Override
+    public void onCreateOutgoingConnectionFailed(PhoneAccountHandle phoneAccount, ConnectionRequest request) {
+        this.onCreateOutgoingConnectionFailedHook.apply(createArguments(phoneAccount, request));
+    }
+
+    @Override
+    public void onDestroy() {
+        this.onCreateIncomingConnectionHook.close();
+        this.onCreateOutgoingConnectionHook.close();
+        this.onCreateIncomingConnectionFailedHook.close();
+        this.onCreateOutgoingConnectionFailedHook.close();
+        super.onDestroy();
+    }
+}
@@ -0,0 +1,105 @@
+package io.github.gedgygedgy.rust.android.util;
+
+import android.util.Log;
+
+/**
+ * Drop-in replacement for {@link Log} which also passes each message to the
+ * handler set with {@code android_utils::log::bridge::set_handler()}, so that
+ * Rust code can process Java logs along with its own.
+ */
+public final class RustLog {
+    private static volatile boolean bridged = false;
+
+    private RustLog() {}
+
+    private static native void nativeLog(int priority, String tag, String msg, Throwable tr);
+
+    private static void mirror(int priority, String tag, String msg, Throwable tr) {
+        if (bridged) {
+            nativeLog(priority, tag, msg, tr);
+        }
+    }
+
+    public static int v(String tag, String msg) {
+        mirror(Log.VERBOSE, tag, msg, null);
+        return Log.v(tag, msg);
+    }
+
+    public static int v(String tag, String msg, Throwable tr) {
+        mirror(Log.VERBOSE, tag, msg, tr);
+        return Log.v(tag, msg, tr);
+    }
+
+    public static int d(String tag, String msg) {
+        mirror(Log.DEBUG, tag, msg, null);
+        return Log.d(tag, msg);
+    }
+
+    public static int d(String tag, String msg, Throwable tr) {
+        mirror(Log.DEBUG, tag, msg, tr);
+        return Log.d(tag, msg, tr);
+    }
+
+    public static int i(String tag, String msg) {
+        mirror(Log.INFO, tag, msg, null);
+        return Log.i(tag, msg);
+    }
+
+    public static int i(String tag, String msg, Throwable tr) {
+        mirror(Log.INFO, tag, msg, tr);
+        return Log.i(tag, msg, tr);
+    }
+
+    public static int w(String tag, String msg) {
+        mirror(Log.WARN, tag, msg, null);
+        return Log.w(tag, msg);
+    }
+
+    public static int w(String tag, String msg, Throwable tr) {
+        mirror(Log.WARN, tag, msg, tr);
+        return Log.w(tag, msg, tr);
+    }
+
+    public static int w(String tag, Throwable tr) {
+        mirror(Log.WARN, tag, "", tr);
+        return Log.w(tag, tr);
+    }
+
+    public static int e(String tag, String msg) {
+        mirror(Log.ERROR, tag, msg, null);
+        return Log.e(tag, msg);
+    }
+
+    public static int e(String tag, String msg, Throwable tr) {
+        mirror(Log.ERROR, tag, msg, tr);
+        return Log.e(tag, msg, tr);
+    }
+
+    public static int wtf(String tag, String msg) {
+        mirror(Log.ASSERT, tag, msg, null);
+        return Log.wtf(tag, msg);
+    }
+
+    public static int wtf(String tag, String msg, Throwable tr) {
+        mirror(Log.ASSERT, tag, msg, tr);
+        return Log.wtf(tag, msg, tr);
+    }
+
+    public static int wtf(String tag, Throwable tr) {
+        mirror(Log.ASSERT, tag, "", tr);
+        return Log.wtf(tag, tr);
+    }
+
+    public static int println(int priority, String tag, String msg) {
+        mirror(priority, tag, msg, null);
+        return Log.println(priority, tag, msg);
+    }
+
+    public static boolean isLoggable(String tag, int level) {
+        return Log.isLoggable(tag, level);
+    }
+
+    public static String getStackTraceString(Throwable tr) {
+        return Log.getStackTraceString(tr);
+    }
+}
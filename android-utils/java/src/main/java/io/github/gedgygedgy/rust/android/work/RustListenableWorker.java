@@ -0,0 +1,134 @@
+package io.github.gedgygedgy.rust.android.work;
+
+import android.content.Context;
+
+import androidx.annotation.NonNull;
+import androidx.concurrent.futures.CallbackToFutureAdapter;
+import androidx.work.Data;
+import androidx.work.ListenableWorker;
+import androidx.work.WorkerParameters;
+
+import com.google.common.util.concurrent.ListenableFuture;
+
+import io.github.gedgygedgy.rust.ops.FnFunction;
+
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+
+/**
+ * Base class for {@link ListenableWorker}s that are implemented in Rust.
+ * Extend this class and register it with
+ * {@code android_utils::work::register_worker()}. Requires the
+ * {@code androidx.work:work-runtime} library.
+ */
+public class RustListenableWorker extends ListenableWorker {
+    private static final HashMap<Class<? extends RustListenableWorker>, FnFunction<RustListenableWorker, Void>> startWorkHooks = new HashMap<>();
+
+    private CallbackToFutureAdapter.Completer<Result> completer;
+    private FnFunction<Void, Void> onStoppedHook;
+
+    public RustListenableWorker(@NonNull Context context, @NonNull WorkerParameters params) {
+        super(context, params);
+    }
+
+    @NonNull
+    @Override
+    public ListenableFuture<Result> startWork() {
+        return CallbackToFutureAdapter.getFuture(completer -> {
+            synchronized (this) {
+                this.completer = completer;
+            }
+            FnFunction<RustListenableWorker, Void> hook = startWorkHooks.get(this.getClass());
+            if (hook == null) {
+                this.complete(Result.failure());
+            } else {
+                hook.apply(this);
+            }
+            return this.getClass().getName();
+        });
+    }
+
+    @Override
+    public void onStopped() {
+        FnFunction<Void, Void> hook;
+        synchronized (this) {
+            hook = this.onStoppedHook;
+            this.onStoppedHook = null;
+        }
+        if (hook != null) {
+            hook.apply(null);
+            hook.close();
+        }
+    }
+
+    private void complete(Result result) {
+        FnFunction<Void, Void> hook;
+        synchronized (this) {
+            if (this.completer != null) {
+                this.completer.set(result);
+                this.completer = null;
+            }
+            hook = this.onStoppedHook;
+            this.onStoppedHook = null;
+        }
+        if (hook != null) {
+            hook.close();
+        }
+    }
+
+    private static Data toData(Map<String, Object> map) {
+        Data.Builder builder = new Data.Builder();
+        for (Map.Entry<String, Object> entry : map.entrySet()) {
+            Object value = entry.getValue();
+            if (value instanceof List) {
+                value = listToArray((List<?>) value);
+            }
+            builder.put(entry.getKey(), value);
+        }
+        return builder.build();
+    }
+
+    private static Object listToArray(List<?> list) {
+        boolean allBoolean = true, allInteger = true, allLong = true, allNumber = true;
+        for (Object item : list) {
+            allBoolean &= item instanceof Boolean;
+            allInteger &= item instanceof Integer;
+            allLong &= item instanceof Integer || item instanceof Long;
+            allNumber &= item instanceof Number;
+        }
+        int size = list.size();
+        if (size > 0 && allBoolean) {
+            boolean[] array = new boolean[size];
+            for (int i = 0; i < size; i++) {
+                array[i] = (Boolean) list.get(i);
+            }
+            return array;
+        } else if (size > 0 && allInteger) {
+            int[] array = new int[size];
+            for (int i = 0; i < size; i++) {
+                array[i] = (Integer) list.get(i);
+            }
+            return array;
+        } else if (size > 0 && allLong) {
+            long[] array = new long[size];
+            for (int i = 0; i < size; i++) {
+                array[i] = ((Number) list.get(i)).longValue();
+            }
+            return array;
+        } else if (size > 0 && allNumber) {
+            double[] array = new double[size];
+            for (int i = 0; i < size; i++) {
+                array[i] = ((Number) list.get(i)).doubleValue();
+            }
+            return array;
+        } else {
+            String[] array = new String[size];
+            for (int i = 0; i < size; i++) {
+                Object item = list.get(i);
+                array[i] = item == null ? null : item.toString();
+            }
+            return array;
+        }
+    }
+}
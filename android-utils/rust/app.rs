@@ -0,0 +1,349 @@
+use crate::{
+    content::JContext,
+    ops::listener_stream,
+    util::{sdk_int, RemoveListenerOnDrop},
+};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{JList, JObject, JString},
+    sys::{jint, jsize},
+    JNIEnv,
+};
+
+mod pending_intent;
+mod provider;
+pub mod usage;
+
+pub use pending_intent::{
+    JPendingIntent, Mutability, FLAG_CANCEL_CURRENT, FLAG_IMMUTABLE, FLAG_MUTABLE, FLAG_NO_CREATE,
+    FLAG_ONE_SHOT, FLAG_UPDATE_CURRENT,
+};
+pub use provider::{
+    register_content_provider, ContentProviderRegistration, RustContentProvider, Selection,
+};
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_FOREGROUND`.
+pub const IMPORTANCE_FOREGROUND: jint = 100;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_FOREGROUND_SERVICE`.
+pub const IMPORTANCE_FOREGROUND_SERVICE: jint = 125;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_VISIBLE`.
+pub const IMPORTANCE_VISIBLE: jint = 200;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_PERCEPTIBLE`.
+pub const IMPORTANCE_PERCEPTIBLE: jint = 230;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_SERVICE`.
+pub const IMPORTANCE_SERVICE: jint = 300;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_TOP_SLEEPING`.
+pub const IMPORTANCE_TOP_SLEEPING: jint = 325;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_CANT_SAVE_STATE`.
+pub const IMPORTANCE_CANT_SAVE_STATE: jint = 350;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_CACHED`.
+pub const IMPORTANCE_CACHED: jint = 400;
+
+/// `android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_GONE`.
+pub const IMPORTANCE_GONE: jint = 1000;
+
+/// `android.app.ApplicationExitInfo.REASON_UNKNOWN`.
+pub const REASON_UNKNOWN: jint = 0;
+
+/// `android.app.ApplicationExitInfo.REASON_EXIT_SELF`.
+pub const REASON_EXIT_SELF: jint = 1;
+
+/// `android.app.ApplicationExitInfo.REASON_SIGNALED`.
+pub const REASON_SIGNALED: jint = 2;
+
+/// `android.app.ApplicationExitInfo.REASON_LOW_MEMORY`.
+pub const REASON_LOW_MEMORY: jint = 3;
+
+/// `android.app.ApplicationExitInfo.REASON_CRASH`.
+pub const REASON_CRASH: jint = 4;
+
+/// `android.app.ApplicationExitInfo.REASON_CRASH_NATIVE`.
+pub const REASON_CRASH_NATIVE: jint = 5;
+
+/// `android.app.ApplicationExitInfo.REASON_ANR`.
+pub const REASON_ANR: jint = 6;
+
+/// `android.app.ApplicationExitInfo.REASON_INITIALIZATION_FAILURE`.
+pub const REASON_INITIALIZATION_FAILURE: jint = 7;
+
+/// `android.app.ApplicationExitInfo.REASON_PERMISSION_CHANGE`.
+pub const REASON_PERMISSION_CHANGE: jint = 8;
+
+/// `android.app.ApplicationExitInfo.REASON_EXCESSIVE_RESOURCE_USAGE`.
+pub const REASON_EXCESSIVE_RESOURCE_USAGE: jint = 9;
+
+/// `android.app.ApplicationExitInfo.REASON_USER_REQUESTED`.
+pub const REASON_USER_REQUESTED: jint = 10;
+
+/// `android.app.ApplicationExitInfo.REASON_USER_STOPPED`.
+pub const REASON_USER_STOPPED: jint = 11;
+
+/// `android.app.ApplicationExitInfo.REASON_DEPENDENCY_DIED`.
+pub const REASON_DEPENDENCY_DIED: jint = 12;
+
+/// `android.app.ApplicationExitInfo.REASON_OTHER`.
+pub const REASON_OTHER: jint = 13;
+
+/// `android.app.ApplicationExitInfo.REASON_FREEZER`.
+pub const REASON_FREEZER: jint = 14;
+
+/// `android.app.ApplicationExitInfo.REASON_PACKAGE_STATE_CHANGE`.
+pub const REASON_PACKAGE_STATE_CHANGE: jint = 15;
+
+/// `android.app.ApplicationExitInfo.REASON_PACKAGE_UPDATED`.
+pub const REASON_PACKAGE_UPDATED: jint = 16;
+
+/// State of the current process. Obtained from [`process_state`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProcessState {
+    /// Importance of the process. One of the `IMPORTANCE_*` constants.
+    pub importance: jint,
+    /// Whether the user has restricted the app's background activity. Always
+    /// `false` before API level 28.
+    pub background_restricted: bool,
+    /// Whether the device is considered a low-RAM device.
+    pub low_ram_device: bool,
+}
+
+impl ProcessState {
+    /// Whether the process is cached, and any work it does is likely to be
+    /// cut short.
+    pub fn is_cached(&self) -> bool {
+        self.importance >= IMPORTANCE_CACHED
+    }
+}
+
+/// Change in the importance of a uid. Produced by the stream returned from
+/// [`uid_importance_stream`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UidImportance {
+    /// Uid whose importance changed.
+    pub uid: jint,
+    /// New importance of the uid. One of the `IMPORTANCE_*` constants.
+    pub importance: jint,
+}
+
+/// Record of why a previous process of the app died. Obtained from
+/// [`exit_reasons`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExitInfo {
+    /// Why the process died. One of the `REASON_*` constants.
+    pub reason: jint,
+    /// Exit status or signal number, depending on the reason.
+    pub status: jint,
+    /// Importance of the process when it died. One of the `IMPORTANCE_*`
+    /// constants.
+    pub importance: jint,
+    /// Time at which the process died, in milliseconds since the epoch.
+    pub timestamp: i64,
+    /// Pid of the process.
+    pub pid: jint,
+    /// Name of the process.
+    pub process_name: String,
+    /// Human-readable description of the exit, if any.
+    pub description: Option<String>,
+    /// ANR traces for [`REASON_ANR`], or the tombstone for
+    /// [`REASON_CRASH_NATIVE`], if the system kept one.
+    pub trace: Option<Vec<u8>>,
+}
+
+fn activity_manager<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service("activity")
+}
+
+/// Get the importance of the current process, and whether the app is
+/// background-restricted or running on a low-RAM device.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ActivityManager` from.
+pub fn process_state<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<ProcessState> {
+    let manager = activity_manager(env, context)?;
+
+    let info = env.new_object(
+        "android/app/ActivityManager$RunningAppProcessInfo",
+        "()V",
+        &[],
+    )?;
+    env.call_static_method(
+        "android/app/ActivityManager",
+        "getMyMemoryState",
+        "(Landroid/app/ActivityManager$RunningAppProcessInfo;)V",
+        &[info.into()],
+    )?;
+    let importance = env.get_field(info, "importance", "I")?.i()?;
+
+    let background_restricted = if sdk_int(env)? >= 28 {
+        env.call_method(manager, "isBackgroundRestricted", "()Z", &[])?
+            .z()?
+    } else {
+        false
+    };
+    let low_ram_device = env
+        .call_method(manager, "isLowRamDevice", "()Z", &[])?
+        .z()?;
+
+    Ok(ProcessState {
+        importance,
+        background_restricted,
+        low_ram_device,
+    })
+}
+
+/// Get a stream of importance changes of all uids, using the system API
+/// `ActivityManager.addOnUidImportanceListener()`. Only changes which cross
+/// `importance_cutpoint` are reported. The listener is removed when the
+/// stream is dropped.
+///
+/// This requires the `android.permission.PACKAGE_USAGE_STATS` permission,
+/// and returns an error with a pending `java.lang.SecurityException` if the
+/// app doesn't have it.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ActivityManager` from.
+/// * `importance_cutpoint` - Importance to report changes across. One of the
+///   `IMPORTANCE_*` constants.
+pub fn uid_importance_stream<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    importance_cutpoint: jint,
+) -> Result<impl Stream<Item = Result<UidImportance>> + Send> {
+    let manager = activity_manager(env, context)?;
+    let interface = env.find_class("android/app/ActivityManager$OnUidImportanceListener")?;
+    let (listener, stream) = listener_stream(env, interface)?;
+    env.call_method(
+        manager,
+        "addOnUidImportanceListener",
+        "(Landroid/app/ActivityManager$OnUidImportanceListener;I)V",
+        &[listener.into(), importance_cutpoint.into()],
+    )?;
+
+    let guard = RemoveListenerOnDrop::new(
+        env,
+        manager,
+        listener,
+        "removeOnUidImportanceListener",
+        "(Landroid/app/ActivityManager$OnUidImportanceListener;)V",
+    )?;
+
+    let vm = env.get_java_vm()?;
+    Ok(stream.map(move |call| {
+        let _ = &guard;
+        let call = call?;
+        let env = vm.get_env()?;
+        let int = |i: usize| -> Result<jint> {
+            env.call_method(call.args[i].as_obj(), "intValue", "()I", &[])?
+                .i()
+        };
+        Ok(UidImportance {
+            uid: int(0)?,
+            importance: int(1)?,
+        })
+    }))
+}
+
+fn read_input_stream<'a: 'b, 'b>(env: &'b JNIEnv<'a>, stream: JObject<'a>) -> Result<Vec<u8>> {
+    const CHUNK: jsize = 8192;
+
+    let buffer = env.new_byte_array(CHUNK)?;
+    let mut chunk = vec![0; CHUNK as usize];
+    let mut result = Vec::new();
+    loop {
+        let len = env
+            .call_method(stream, "read", "([B)I", &[JObject::from(buffer).into()])?
+            .i()?;
+        if len < 0 {
+            break;
+        }
+        env.get_byte_array_region(buffer, 0, &mut chunk[..len as usize])?;
+        result.extend(chunk[..len as usize].iter().map(|b| *b as u8));
+    }
+    env.delete_local_ref(buffer.into())?;
+    Ok(result)
+}
+
+fn exit_info<'a: 'b, 'b>(env: &'b JNIEnv<'a>, info: JObject<'a>) -> Result<ExitInfo> {
+    let int = |method: &str| -> Result<jint> { env.call_method(info, method, "()I", &[])?.i() };
+    let string = |method: &str| -> Result<Option<String>> {
+        let s = env
+            .call_method(info, method, "()Ljava/lang/String;", &[])?
+            .l()?;
+        Ok(if s.is_null() {
+            None
+        } else {
+            Some(env.get_string(JString::from(s))?.into())
+        })
+    };
+
+    let stream = env
+        .call_method(info, "getTraceInputStream", "()Ljava/io/InputStream;", &[])?
+        .l()?;
+    let trace = if stream.is_null() {
+        None
+    } else {
+        let trace = read_input_stream(env, stream);
+        env.call_method(stream, "close", "()V", &[])?;
+        Some(trace?)
+    };
+
+    Ok(ExitInfo {
+        reason: int("getReason")?,
+        status: int("getStatus")?,
+        importance: int("getImportance")?,
+        timestamp: env.call_method(info, "getTimestamp", "()J", &[])?.j()?,
+        pid: int("getPid")?,
+        process_name: string("getProcessName")?.unwrap_or_default(),
+        description: string("getDescription")?,
+        trace,
+    })
+}
+
+/// Get the reasons previous processes of the app died, most recent first,
+/// using `ActivityManager.getHistoricalProcessExitReasons()`. Returns an
+/// empty list before API level 30.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ActivityManager` from.
+/// * `max` - Maximum number of records to return, or `0` for all of them.
+pub fn exit_reasons<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    max: jint,
+) -> Result<Vec<ExitInfo>> {
+    if sdk_int(env)? < 30 {
+        return Ok(Vec::new());
+    }
+
+    let manager = activity_manager(env, context)?;
+    let list = env
+        .call_method(
+            manager,
+            "getHistoricalProcessExitReasons",
+            "(Ljava/lang/String;II)Ljava/util/List;",
+            &[JObject::null().into(), 0.into(), max.into()],
+        )?
+        .l()?;
+
+    let mut result = Vec::new();
+    for info in JList::from_env(env, list)?.iter()? {
+        result.push(exit_info(env, info)?);
+        env.delete_local_ref(info)?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,279 @@
+use crate::util::sdk_int;
+use jni::{
+    errors::{Error, Result},
+    objects::{JObject, JString},
+    sys::jint,
+    JNIEnv,
+};
+use std::ops::Deref;
+
+/// `android.app.PendingIntent.FLAG_ONE_SHOT`.
+pub const FLAG_ONE_SHOT: jint = 1 << 30;
+
+/// `android.app.PendingIntent.FLAG_NO_CREATE`.
+pub const FLAG_NO_CREATE: jint = 1 << 29;
+
+/// `android.app.PendingIntent.FLAG_CANCEL_CURRENT`.
+pub const FLAG_CANCEL_CURRENT: jint = 1 << 28;
+
+/// `android.app.PendingIntent.FLAG_UPDATE_CURRENT`.
+pub const FLAG_UPDATE_CURRENT: jint = 1 << 27;
+
+/// `android.app.PendingIntent.FLAG_IMMUTABLE`. Requires API level 23. Set
+/// through [`Mutability::Immutable`] rather than directly.
+pub const FLAG_IMMUTABLE: jint = 1 << 26;
+
+/// `android.app.PendingIntent.FLAG_MUTABLE`. Requires API level 31. Set
+/// through [`Mutability::Mutable`] rather than directly.
+pub const FLAG_MUTABLE: jint = 1 << 25;
+
+/// Whether the sender of a [`JPendingIntent`] may fill in unset fields of
+/// its intent. One of them must be chosen on API level 31 and above.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutability {
+    /// [`FLAG_IMMUTABLE`], on API level 23 and above. Prefer this unless
+    /// the sender needs to fill in the intent, such as for inline replies.
+    Immutable,
+    /// [`FLAG_MUTABLE`], on API level 31 and above. Before that, pending
+    /// intents are mutable unless they are immutable.
+    Mutable,
+}
+
+impl Mutability {
+    fn flags<'a: 'b, 'b>(self, env: &'b JNIEnv<'a>) -> Result<jint> {
+        let sdk_int = sdk_int(env)?;
+        Ok(match self {
+            Self::Immutable if sdk_int >= 23 => FLAG_IMMUTABLE,
+            Self::Mutable if sdk_int >= 31 => FLAG_MUTABLE,
+            _ => 0,
+        })
+    }
+}
+
+/// Wrapper for [`JObject`]s that contain `android.app.PendingIntent`, which
+/// lets another app, such as the alarm or notification manager, perform an
+/// action with this app's identity.
+pub struct JPendingIntent<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JPendingIntent<'a, 'b> {
+    /// Create a [`JPendingIntent`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    fn get(
+        env: &'b JNIEnv<'a>,
+        method: &str,
+        context: JObject<'a>,
+        request_code: jint,
+        intent: JObject<'a>,
+        flags: jint,
+        mutability: Mutability,
+    ) -> Result<Option<Self>> {
+        let flags = flags & !(FLAG_IMMUTABLE | FLAG_MUTABLE) | mutability.flags(env)?;
+        let obj = env
+            .call_static_method(
+                "android/app/PendingIntent",
+                method,
+                "(Landroid/content/Context;ILandroid/content/Intent;I)Landroid/app/PendingIntent;",
+                &[
+                    context.into(),
+                    request_code.into(),
+                    intent.into(),
+                    flags.into(),
+                ],
+            )?
+            .l()?;
+        if obj.is_null() {
+            return Ok(None);
+        }
+        Self::from_env(env, obj).map(Some)
+    }
+
+    /// Get a pending intent which starts an activity with
+    /// `PendingIntent.getActivity()`. Returns [`None`] only if
+    /// [`FLAG_NO_CREATE`] is passed and there is no matching pending intent.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `context` - `android.content.Context` to start the activity from.
+    /// * `request_code` - Code which distinguishes pending intents whose
+    ///   intents are otherwise equal.
+    /// * `intent` - `android.content.Intent` of the activity.
+    /// * `flags` - Combination of the `FLAG_*` flags, other than
+    ///   [`FLAG_IMMUTABLE`] and [`FLAG_MUTABLE`].
+    /// * `mutability` - Whether the intent may be filled in by the sender.
+    pub fn get_activity(
+        env: &'b JNIEnv<'a>,
+        context: JObject<'a>,
+        request_code: jint,
+        intent: JObject<'a>,
+        flags: jint,
+        mutability: Mutability,
+    ) -> Result<Option<Self>> {
+        Self::get(
+            env,
+            "getActivity",
+            context,
+            request_code,
+            intent,
+            flags,
+            mutability,
+        )
+    }
+
+    /// Get a pending intent which starts a service with
+    /// `PendingIntent.getService()`. See [`get_activity`](Self::get_activity)
+    /// for the arguments.
+    pub fn get_service(
+        env: &'b JNIEnv<'a>,
+        context: JObject<'a>,
+        request_code: jint,
+        intent: JObject<'a>,
+        flags: jint,
+        mutability: Mutability,
+    ) -> Result<Option<Self>> {
+        Self::get(
+            env,
+            "getService",
+            context,
+            request_code,
+            intent,
+            flags,
+            mutability,
+        )
+    }
+
+    /// Get a pending intent which sends a broadcast with
+    /// `PendingIntent.getBroadcast()`. See
+    /// [`get_activity`](Self::get_activity) for the arguments.
+    pub fn get_broadcast(
+        env: &'b JNIEnv<'a>,
+        context: JObject<'a>,
+        request_code: jint,
+        intent: JObject<'a>,
+        flags: jint,
+        mutability: Mutability,
+    ) -> Result<Option<Self>> {
+        Self::get(
+            env,
+            "getBroadcast",
+            context,
+            request_code,
+            intent,
+            flags,
+            mutability,
+        )
+    }
+
+    fn catch_canceled(&self, result: Result<()>) -> Result<bool> {
+        match result {
+            Ok(()) => Ok(true),
+            Err(Error::JavaException) => {
+                let exception = self.env.exception_occurred()?;
+                self.env.exception_clear()?;
+                if !self
+                    .env
+                    .is_instance_of(exception, "android/app/PendingIntent$CanceledException")?
+                {
+                    self.env.throw(exception)?;
+                    return Err(Error::JavaException);
+                }
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Perform the action with `PendingIntent.send()`. Returns `false` if
+    /// the pending intent has been cancelled.
+    pub fn send(&self) -> Result<bool> {
+        let result = self
+            .env
+            .call_method(self.internal, "send", "()V", &[])
+            .map(|_| ());
+        self.catch_canceled(result)
+    }
+
+    /// Perform the action with `PendingIntent.send()`, filling in the unset
+    /// fields of its intent from another intent. Only fills in mutable
+    /// pending intents. Returns `false` if the pending intent has been
+    /// cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - `android.content.Context` to send from.
+    /// * `code` - Result code to supply to the target.
+    /// * `intent` - `android.content.Intent` to fill in from, or null.
+    pub fn send_with(&self, context: JObject<'a>, code: jint, intent: JObject<'a>) -> Result<bool> {
+        let result = self
+            .env
+            .call_method(
+                self.internal,
+                "send",
+                "(Landroid/content/Context;ILandroid/content/Intent;)V",
+                &[context.into(), code.into(), intent.into()],
+            )
+            .map(|_| ());
+        self.catch_canceled(result)
+    }
+
+    /// Cancel the pending intent with `PendingIntent.cancel()`.
+    pub fn cancel(&self) -> Result<()> {
+        self.env.call_method(self.internal, "cancel", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Get the package which created the pending intent with
+    /// `PendingIntent.getCreatorPackage()`.
+    pub fn creator_package(&self) -> Result<Option<String>> {
+        let package = self
+            .env
+            .call_method(
+                self.internal,
+                "getCreatorPackage",
+                "()Ljava/lang/String;",
+                &[],
+            )?
+            .l()?;
+        if package.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(self.env.get_string(JString::from(package))?.into()))
+    }
+
+    /// Check whether the pending intent is immutable with
+    /// `PendingIntent.isImmutable()`. Returns [`None`] before API level 31.
+    pub fn is_immutable(&self) -> Result<Option<bool>> {
+        if sdk_int(self.env)? < 31 {
+            return Ok(None);
+        }
+        self.env
+            .call_method(self.internal, "isImmutable", "()Z", &[])?
+            .z()
+            .map(Some)
+    }
+}
+
+impl<'a: 'b, 'b> From<JPendingIntent<'a, 'b>> for JObject<'a> {
+    fn from(pending_intent: JPendingIntent<'a, 'b>) -> Self {
+        pending_intent.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JPendingIntent<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,385 @@
+use crate::os::bundle::{value_from_object, BundleValue};
+use jni::{
+    descriptors::Desc,
+    errors::Result,
+    objects::{GlobalRef, JClass, JObject, JString},
+    sys::{jint, jobjectArray, jsize},
+    JNIEnv, JavaVM,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Selection passed to [`RustContentProvider::query`],
+/// [`RustContentProvider::update`] and [`RustContentProvider::delete`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// `WHERE` clause, with `?` placeholders for the arguments, or [`None`]
+    /// to select everything.
+    pub selection: Option<String>,
+    /// Arguments which replace the `?` placeholders, in order.
+    pub args: Vec<String>,
+}
+
+/// Trait for Rust implementations of `android.content.ContentProvider`.
+/// Register your Rust content provider using [`register_content_provider`].
+///
+/// Each callback receives the `ContentProvider` object, whose
+/// `getContext()` method returns its `android.content.Context`. Callbacks
+/// are called on binder threads, and may be called concurrently.
+#[allow(unused_variables)]
+pub trait RustContentProvider: Send + Sync {
+    /// Called by `ContentProvider.query()`. Returns an
+    /// `android.database.Cursor` over the results, such as an
+    /// `android.database.MatrixCursor`, or null.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - URI to query.
+    /// * `projection` - Columns to return, or [`None`] for all columns.
+    /// * `selection` - Rows to return.
+    /// * `sort_order` - `ORDER BY` clause, or [`None`] for the default order.
+    fn query<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        provider: JObject<'a>,
+        uri: &str,
+        projection: Option<&[String]>,
+        selection: &Selection,
+        sort_order: Option<&str>,
+    ) -> JObject<'a>;
+
+    /// Called by `ContentProvider.getType()`. Returns the MIME type of the
+    /// data at the URI, if any.
+    fn get_type<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        provider: JObject<'a>,
+        uri: &str,
+    ) -> Option<String>;
+
+    /// Called by `ContentProvider.insert()`. Returns the URI of the new
+    /// row, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - URI to insert into.
+    /// * `values` - Values of the new row, by column.
+    fn insert<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        provider: JObject<'a>,
+        uri: &str,
+        values: HashMap<String, BundleValue>,
+    ) -> Option<String> {
+        None
+    }
+
+    /// Called by `ContentProvider.update()`. Returns the number of rows
+    /// updated.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - URI to update.
+    /// * `values` - New values, by column.
+    /// * `selection` - Rows to update.
+    fn update<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        provider: JObject<'a>,
+        uri: &str,
+        values: HashMap<String, BundleValue>,
+        selection: &Selection,
+    ) -> jint {
+        0
+    }
+
+    /// Called by `ContentProvider.delete()`. Returns the number of rows
+    /// deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - URI to delete from.
+    /// * `selection` - Rows to delete.
+    fn delete<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        provider: JObject<'a>,
+        uri: &str,
+        selection: &Selection,
+    ) -> jint {
+        0
+    }
+}
+
+fn optional_string<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Option<String>> {
+    if obj.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(env.get_string(JString::from(obj))?.into()))
+}
+
+fn string_field<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+    name: &str,
+) -> Result<Option<String>> {
+    optional_string(env, env.get_field(obj, name, "Ljava/lang/String;")?.l()?)
+}
+
+fn string_array_field<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+    name: &str,
+) -> Result<Option<Vec<String>>> {
+    let array = env.get_field(obj, name, "[Ljava/lang/String;")?.l()?;
+    if array.is_null() {
+        return Ok(None);
+    }
+    let array = array.into_inner() as jobjectArray;
+    let len = env.get_array_length(array)?;
+    let mut result = Vec::with_capacity(len as usize);
+    for i in 0..len as jsize {
+        let s = env.get_object_array_element(array, i)?;
+        result.extend(optional_string(env, s)?);
+        env.delete_local_ref(s)?;
+    }
+    Ok(Some(result))
+}
+
+fn uri_field<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<String> {
+    let uri = env.get_field(obj, "uri", "Landroid/net/Uri;")?.l()?;
+    uri_string(env, uri)
+}
+
+fn uri_string<'a: 'b, 'b>(env: &'b JNIEnv<'a>, uri: JObject<'a>) -> Result<String> {
+    let s = env
+        .call_method(uri, "toString", "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok(env.get_string(JString::from(s))?.into())
+}
+
+fn selection_fields<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Selection> {
+    Ok(Selection {
+        selection: string_field(env, obj, "selection")?,
+        args: string_array_field(env, obj, "selectionArgs")?.unwrap_or_default(),
+    })
+}
+
+fn values_field<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+) -> Result<HashMap<String, BundleValue>> {
+    let values = env.get_field(obj, "values", "Ljava/util/Map;")?.l()?;
+    Ok(match value_from_object(env, values)? {
+        BundleValue::Map(map) => map,
+        _ => HashMap::new(),
+    })
+}
+
+fn provider_obj<'a: 'b, 'b>(env: &'b JNIEnv<'a>, provider: &GlobalRef) -> JObject<'a> {
+    env.new_local_ref::<JObject>(provider.as_obj().into_inner().into())
+        .unwrap()
+}
+
+fn set_hook<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+    name: &str,
+    hook: JObject<'a>,
+) -> Result<()> {
+    let hook = env.auto_local(hook);
+    env.set_field(
+        obj,
+        name,
+        "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+        (&hook).into(),
+    )
+}
+
+fn on_create_hooks<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<JObject<'a>> {
+    env.get_static_field(
+        "io/github/gedgygedgy/rust/android/app/RustContentProvider",
+        "onCreateHooks",
+        "Ljava/util/Map;",
+    )?
+    .l()
+}
+
+fn set_hooks<'a: 'b, 'b, T: RustContentProvider + 'static>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+    provider: Arc<T>,
+) -> Result<()> {
+    let handle = env.new_global_ref(obj)?;
+
+    let (provider_clone, handle_clone) = (provider.clone(), handle.clone());
+    let hook = jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+        let uri = uri_field(env, arg).unwrap();
+        let projection = string_array_field(env, arg, "projection").unwrap();
+        let selection = selection_fields(env, arg).unwrap();
+        let sort_order = string_field(env, arg, "sortOrder").unwrap();
+        provider_clone.query(
+            env,
+            provider_obj(env, &handle_clone),
+            &uri,
+            projection.as_deref(),
+            &selection,
+            sort_order.as_deref(),
+        )
+    })?;
+    set_hook(env, obj, "queryHook", hook)?;
+
+    let (provider_clone, handle_clone) = (provider.clone(), handle.clone());
+    let hook = jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+        let uri = uri_string(env, arg).unwrap();
+        match provider_clone.get_type(env, provider_obj(env, &handle_clone), &uri) {
+            Some(ty) => env.new_string(ty).unwrap().into(),
+            None => JObject::null(),
+        }
+    })?;
+    set_hook(env, obj, "getTypeHook", hook)?;
+
+    let (provider_clone, handle_clone) = (provider.clone(), handle.clone());
+    let hook = jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+        let uri = uri_field(env, arg).unwrap();
+        let values = values_field(env, arg).unwrap();
+        match provider_clone.insert(env, provider_obj(env, &handle_clone), &uri, values) {
+            Some(uri) => {
+                let uri = env.new_string(uri).unwrap();
+                env.call_static_method(
+                    "android/net/Uri",
+                    "parse",
+                    "(Ljava/lang/String;)Landroid/net/Uri;",
+                    &[uri.into()],
+                )
+                .unwrap()
+                .l()
+                .unwrap()
+            }
+            None => JObject::null(),
+        }
+    })?;
+    set_hook(env, obj, "insertHook", hook)?;
+
+    let (provider_clone, handle_clone) = (provider.clone(), handle.clone());
+    let hook = jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+        let uri = uri_field(env, arg).unwrap();
+        let values = values_field(env, arg).unwrap();
+        let selection = selection_fields(env, arg).unwrap();
+        let result = provider_clone.update(
+            env,
+            provider_obj(env, &handle_clone),
+            &uri,
+            values,
+            &selection,
+        );
+        env.new_object("java/lang/Integer", "(I)V", &[result.into()])
+            .unwrap()
+    })?;
+    set_hook(env, obj, "updateHook", hook)?;
+
+    let hook = jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+        let uri = uri_field(env, arg).unwrap();
+        let selection = selection_fields(env, arg).unwrap();
+        let result = provider.delete(env, provider_obj(env, &handle), &uri, &selection);
+        env.new_object("java/lang/Integer", "(I)V", &[result.into()])
+            .unwrap()
+    })?;
+    set_hook(env, obj, "deleteHook", hook)
+}
+
+/// Register a content provider as an
+/// `io.github.gedgygedgy.rust.android.app.RustContentProvider`. Since
+/// content providers are created before the application, the `factory`
+/// closure is not called by `ContentProvider.onCreate()`, but when the
+/// provider is first used. The provider must be registered by then, for
+/// example from `Application.onCreate()`.
+///
+/// The provider stays registered until the returned
+/// [`ContentProviderRegistration`] is dropped. Providers which have already
+/// been used keep the object created by the factory.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `class` - Subclass of `RustContentProvider` to register.
+/// * `factory` - Function which creates the Rust provider for a
+///   `ContentProvider` object.
+pub fn register_content_provider<'a: 'b, 'b, T: RustContentProvider + 'static>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
+) -> Result<ContentProviderRegistration> {
+    let class = env.auto_local(class.lookup(env)?);
+
+    let on_create_hook =
+        env.auto_local(jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+            let provider = Arc::new(factory(env, arg));
+            set_hooks(env, arg, provider).unwrap();
+            JObject::null()
+        })?);
+
+    let on_create_hooks = env.auto_local(on_create_hooks(env)?);
+    env.call_method(
+        &on_create_hooks,
+        "put",
+        "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        &[(&class).into(), (&on_create_hook).into()],
+    )?;
+
+    Ok(ContentProviderRegistration {
+        class: env.new_global_ref(&class)?,
+        hook: env.new_global_ref(&on_create_hook)?,
+        vm: env.get_java_vm()?,
+    })
+}
+
+/// Registration of a content provider, returned by
+/// [`register_content_provider`]. The provider is unregistered when this is
+/// dropped, unless another provider has been registered for the same class
+/// since.
+#[must_use = "the content provider is unregistered when the registration is dropped"]
+pub struct ContentProviderRegistration {
+    class: GlobalRef,
+    hook: GlobalRef,
+    vm: JavaVM,
+}
+
+impl ContentProviderRegistration {
+    /// Keep the content provider registered for the rest of the process'
+    /// lifetime.
+    pub fn forget(self) {
+        std::mem::forget(self);
+    }
+
+    fn unregister(&self, env: &JNIEnv) -> Result<()> {
+        let on_create_hooks = env.auto_local(on_create_hooks(env)?);
+        let current_hook = env
+            .call_method(
+                &on_create_hooks,
+                "get",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[self.class.as_obj().into()],
+            )?
+            .l()?;
+        if env.is_same_object(current_hook, self.hook.as_obj())? {
+            env.call_method(
+                &on_create_hooks,
+                "remove",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[self.class.as_obj().into()],
+            )?;
+        }
+        env.call_method(self.hook.as_obj(), "close", "()V", &[])?;
+        Ok(())
+    }
+}
+
+impl Drop for ContentProviderRegistration {
+    fn drop(&mut self) {
+        if let Ok(env) = self.vm.attach_current_thread() {
+            if self.unregister(&env).is_err() {
+                let _ = env.exception_clear();
+            }
+        }
+    }
+}
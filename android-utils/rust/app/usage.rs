@@ -0,0 +1,234 @@
+use crate::{content::JContext, util::sdk_int};
+use jni::{
+    errors::Result,
+    objects::{JList, JObject, JString},
+    sys::jint,
+    JNIEnv,
+};
+
+/// `android.app.usage.UsageStatsManager.STANDBY_BUCKET_ACTIVE`.
+pub const STANDBY_BUCKET_ACTIVE: jint = 10;
+
+/// `android.app.usage.UsageStatsManager.STANDBY_BUCKET_WORKING_SET`.
+pub const STANDBY_BUCKET_WORKING_SET: jint = 20;
+
+/// `android.app.usage.UsageStatsManager.STANDBY_BUCKET_FREQUENT`.
+pub const STANDBY_BUCKET_FREQUENT: jint = 30;
+
+/// `android.app.usage.UsageStatsManager.STANDBY_BUCKET_RARE`.
+pub const STANDBY_BUCKET_RARE: jint = 40;
+
+/// `android.app.usage.UsageStatsManager.STANDBY_BUCKET_RESTRICTED`.
+pub const STANDBY_BUCKET_RESTRICTED: jint = 45;
+
+/// `android.app.usage.UsageStatsManager.INTERVAL_DAILY`.
+pub const INTERVAL_DAILY: jint = 0;
+
+/// `android.app.usage.UsageStatsManager.INTERVAL_WEEKLY`.
+pub const INTERVAL_WEEKLY: jint = 1;
+
+/// `android.app.usage.UsageStatsManager.INTERVAL_MONTHLY`.
+pub const INTERVAL_MONTHLY: jint = 2;
+
+/// `android.app.usage.UsageStatsManager.INTERVAL_YEARLY`.
+pub const INTERVAL_YEARLY: jint = 3;
+
+/// `android.app.usage.UsageStatsManager.INTERVAL_BEST`.
+pub const INTERVAL_BEST: jint = 4;
+
+/// Usage of a package over a time interval. Obtained from [`usage_stats`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsageStats {
+    /// Name of the package.
+    pub package_name: String,
+    /// Start of the interval, in milliseconds since the epoch.
+    pub first_timestamp: i64,
+    /// End of the interval, in milliseconds since the epoch.
+    pub last_timestamp: i64,
+    /// Last time the package was used, in milliseconds since the epoch.
+    pub last_time_used: i64,
+    /// Total time the package was in the foreground, in milliseconds.
+    pub total_time_in_foreground: i64,
+}
+
+/// Usage event of a package. Obtained from [`usage_events`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsageEvent {
+    /// Name of the package.
+    pub package_name: String,
+    /// Name of the class, if the event concerns a component.
+    pub class_name: Option<String>,
+    /// Time of the event, in milliseconds since the epoch.
+    pub timestamp: i64,
+    /// Type of the event. One of the `android.app.usage.UsageEvents.Event`
+    /// constants.
+    pub event_type: jint,
+}
+
+fn usage_stats_manager<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service("usagestats")
+}
+
+fn string<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+    method: &str,
+) -> Result<Option<String>> {
+    let s = env
+        .call_method(obj, method, "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok(if s.is_null() {
+        None
+    } else {
+        Some(env.get_string(JString::from(s))?.into())
+    })
+}
+
+/// Get the standby bucket the system has placed the app in. Returns one of
+/// the `STANDBY_BUCKET_*` constants, or [`None`] before API level 28.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `UsageStatsManager`
+///   from.
+pub fn standby_bucket<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<jint>> {
+    if sdk_int(env)? < 28 {
+        return Ok(None);
+    }
+
+    let manager = usage_stats_manager(env, context)?;
+    Ok(Some(
+        env.call_method(manager, "getAppStandbyBucket", "()I", &[])?
+            .i()?,
+    ))
+}
+
+/// Check whether a package is currently inactive. Apps can always query
+/// their own package; other packages require the
+/// `android.permission.PACKAGE_USAGE_STATS` permission.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `UsageStatsManager`
+///   from.
+/// * `package_name` - Name of the package to check.
+pub fn is_app_inactive<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    package_name: &str,
+) -> Result<bool> {
+    let manager = usage_stats_manager(env, context)?;
+    let package_name = env.new_string(package_name)?;
+    env.call_method(
+        manager,
+        "isAppInactive",
+        "(Ljava/lang/String;)Z",
+        &[package_name.into()],
+    )?
+    .z()
+}
+
+/// Get usage statistics of all packages over a time range, aggregated by
+/// `interval_type`. This requires the `android.permission.PACKAGE_USAGE_STATS`
+/// permission, and returns an empty list without it.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `UsageStatsManager`
+///   from.
+/// * `interval_type` - One of the `INTERVAL_*` constants.
+/// * `begin` - Start of the range, in milliseconds since the epoch.
+/// * `end` - End of the range, in milliseconds since the epoch.
+pub fn usage_stats<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    interval_type: jint,
+    begin: i64,
+    end: i64,
+) -> Result<Vec<UsageStats>> {
+    let manager = usage_stats_manager(env, context)?;
+    let list = env
+        .call_method(
+            manager,
+            "queryUsageStats",
+            "(IJJ)Ljava/util/List;",
+            &[interval_type.into(), begin.into(), end.into()],
+        )?
+        .l()?;
+    if list.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let mut result = Vec::new();
+    for stats in JList::from_env(env, list)?.iter()? {
+        let long =
+            |method: &str| -> Result<i64> { env.call_method(stats, method, "()J", &[])?.j() };
+        result.push(UsageStats {
+            package_name: string(env, stats, "getPackageName")?.unwrap_or_default(),
+            first_timestamp: long("getFirstTimeStamp")?,
+            last_timestamp: long("getLastTimeStamp")?,
+            last_time_used: long("getLastTimeUsed")?,
+            total_time_in_foreground: long("getTotalTimeInForeground")?,
+        });
+        env.delete_local_ref(stats)?;
+    }
+    Ok(result)
+}
+
+/// Get usage events of all packages over a time range. This requires the
+/// `android.permission.PACKAGE_USAGE_STATS` permission, and returns an empty
+/// list without it.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `UsageStatsManager`
+///   from.
+/// * `begin` - Start of the range, in milliseconds since the epoch.
+/// * `end` - End of the range, in milliseconds since the epoch.
+pub fn usage_events<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    begin: i64,
+    end: i64,
+) -> Result<Vec<UsageEvent>> {
+    let manager = usage_stats_manager(env, context)?;
+    let events = env
+        .call_method(
+            manager,
+            "queryEvents",
+            "(JJ)Landroid/app/usage/UsageEvents;",
+            &[begin.into(), end.into()],
+        )?
+        .l()?;
+    if events.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let event = env.new_object("android/app/usage/UsageEvents$Event", "()V", &[])?;
+    let mut result = Vec::new();
+    while env.call_method(events, "hasNextEvent", "()Z", &[])?.z()? {
+        env.call_method(
+            events,
+            "getNextEvent",
+            "(Landroid/app/usage/UsageEvents$Event;)Z",
+            &[event.into()],
+        )?;
+        result.push(UsageEvent {
+            package_name: string(env, event, "getPackageName")?.unwrap_or_default(),
+            class_name: string(env, event, "getClassName")?,
+            timestamp: env.call_method(event, "getTimeStamp", "()J", &[])?.j()?,
+            event_type: env.call_method(event, "getEventType", "()I", &[])?.i()?,
+        });
+    }
+    Ok(result)
+}
@@ -0,0 +1,179 @@
+use futures::channel::oneshot;
+use jni::JNIEnv;
+use once_cell::sync::OnceCell;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+/// Configuration of the blocking pool used by [`spawn_blocking`]. Set it with
+/// [`configure_blocking_pool`] before the first call to [`spawn_blocking`].
+#[derive(Clone, Debug)]
+pub struct BlockingPoolConfig {
+    /// Number of threads in the pool.
+    pub threads: usize,
+    /// Prefix of the thread names. Each thread is named with this prefix
+    /// followed by its index.
+    pub name_prefix: String,
+    /// Priority to set on each thread with
+    /// `android.os.Process.setThreadPriority()`, if any.
+    pub priority: Option<i32>,
+}
+
+impl Default for BlockingPoolConfig {
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            name_prefix: "android-utils-blocking-".into(),
+            priority: None,
+        }
+    }
+}
+
+/// Snapshot of the counters of the blocking pool. Obtained by calling
+/// [`blocking_pool_metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockingPoolMetrics {
+    /// Number of threads in the pool. Zero if the pool hasn't been started.
+    pub threads: usize,
+    /// Number of jobs waiting for a thread.
+    pub queued: usize,
+    /// Number of jobs currently running.
+    pub running: usize,
+    /// Number of jobs that have completed.
+    pub completed: usize,
+}
+
+type Job = Box<dyn FnOnce(&JNIEnv) + Send>;
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    running: AtomicUsize,
+    completed: AtomicUsize,
+}
+
+struct Pool {
+    sender: Mutex<Sender<Job>>,
+    threads: usize,
+    counters: Arc<Counters>,
+}
+
+static CONFIG: OnceCell<BlockingPoolConfig> = OnceCell::new();
+static POOL: OnceCell<Pool> = OnceCell::new();
+
+fn worker(receiver: Arc<Mutex<Receiver<Job>>>, counters: Arc<Counters>, priority: Option<i32>) {
+    let env = match crate::env() {
+        Ok(env) => env,
+        Err(_) => return,
+    };
+    if let Some(priority) = priority {
+        let _ = crate::os::process::set_thread_priority(&env, priority);
+    }
+
+    loop {
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        counters.queued.fetch_sub(1, Ordering::Relaxed);
+        counters.running.fetch_add(1, Ordering::Relaxed);
+        job(&env);
+        counters.running.fetch_sub(1, Ordering::Relaxed);
+        counters.completed.fetch_add(1, Ordering::Relaxed);
+        crate::refs::flush_refs(&env);
+    }
+}
+
+fn pool() -> &'static Pool {
+    POOL.get_or_init(|| {
+        let config = CONFIG.get_or_init(Default::default);
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let counters = Arc::new(Counters::default());
+        let threads = config.threads.max(1);
+        for i in 0..threads {
+            let receiver = receiver.clone();
+            let counters = counters.clone();
+            let priority = config.priority;
+            std::thread::Builder::new()
+                .name(format!("{}{}", config.name_prefix, i))
+                .spawn(move || worker(receiver, counters, priority))
+                .unwrap();
+        }
+        Pool {
+            sender: Mutex::new(sender),
+            threads,
+            counters,
+        }
+    })
+}
+
+/// Configure the blocking pool. This must be called before the first call to
+/// [`spawn_blocking`]. Returns `false` if the pool has already been
+/// configured or started, in which case the configuration is ignored.
+///
+/// # Arguments
+///
+/// * `config` - Configuration to use.
+pub fn configure_blocking_pool(config: BlockingPoolConfig) -> bool {
+    POOL.get().is_none() && CONFIG.set(config).is_ok()
+}
+
+/// Future returned by [`spawn_blocking`]. Resolves to the result of the
+/// job, or [`None`] if the job panicked.
+pub struct BlockingTask<T>(oneshot::Receiver<T>);
+
+impl<T> Future for BlockingTask<T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|result| result.ok())
+    }
+}
+
+/// Run a blocking job on the crate's blocking pool, whose threads are
+/// attached to the Java VM. The returned future wakes its task when the job
+/// completes, so when it is awaited on a task spawned by a
+/// [`JHandlerSpawn`](crate::os::JHandlerSpawn), the result is delivered back
+/// on the `Handler`'s thread. The pool is started with the configuration set
+/// by [`configure_blocking_pool`], or the default one, on first use.
+/// [`init()`](crate::init) must have been called first.
+///
+/// # Arguments
+///
+/// * `f` - Job to run. It is passed the Java environment of the pool thread.
+pub fn spawn_blocking<T: Send + 'static>(
+    f: impl FnOnce(&JNIEnv) -> T + Send + 'static,
+) -> BlockingTask<T> {
+    let (sender, receiver) = oneshot::channel();
+    let pool = pool();
+    pool.counters.queued.fetch_add(1, Ordering::Relaxed);
+    let job: Job = Box::new(move |env| {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(env)));
+        if let Ok(result) = result {
+            let _ = sender.send(result);
+        }
+    });
+    pool.sender.lock().unwrap().send(job).unwrap();
+    BlockingTask(receiver)
+}
+
+/// Get a snapshot of the counters of the blocking pool.
+pub fn blocking_pool_metrics() -> BlockingPoolMetrics {
+    match POOL.get() {
+        Some(pool) => BlockingPoolMetrics {
+            threads: pool.threads,
+            queued: pool.counters.queued.load(Ordering::Relaxed),
+            running: pool.counters.running.load(Ordering::Relaxed),
+            completed: pool.counters.completed.load(Ordering::Relaxed),
+        },
+        None => Default::default(),
+    }
+}
@@ -0,0 +1,329 @@
+use crate::util::{CloseOnDrop, CloseOnDropStream};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{GlobalRef, JObject},
+    sys::jint,
+    JNIEnv, JavaVM,
+};
+use jni_utils::{
+    future::{JFuture, JSendFuture},
+    stream::{JSendStream, JStream},
+    task::JPollResult,
+};
+use std::{convert::TryFrom, future::Future};
+
+/// `android.bluetooth.BluetoothProfile.STATE_DISCONNECTED`.
+pub const STATE_DISCONNECTED: jint = 0;
+
+/// `android.bluetooth.BluetoothProfile.STATE_CONNECTING`.
+pub const STATE_CONNECTING: jint = 1;
+
+/// `android.bluetooth.BluetoothProfile.STATE_CONNECTED`.
+pub const STATE_CONNECTED: jint = 2;
+
+/// `android.bluetooth.BluetoothProfile.STATE_DISCONNECTING`.
+pub const STATE_DISCONNECTING: jint = 3;
+
+/// Bluetooth profiles whose proxies can be obtained with
+/// [`get_profile_proxy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// `android.bluetooth.BluetoothProfile.HEADSET`.
+    Headset,
+    /// `android.bluetooth.BluetoothProfile.A2DP`.
+    A2dp,
+}
+
+impl Profile {
+    /// Get the `android.bluetooth.BluetoothProfile` constant for this
+    /// profile.
+    pub fn id(self) -> jint {
+        match self {
+            Self::Headset => 1,
+            Self::A2dp => 2,
+        }
+    }
+
+    fn connection_state_changed_action(self) -> &'static str {
+        match self {
+            Self::Headset => "android.bluetooth.headset.profile.action.CONNECTION_STATE_CHANGED",
+            Self::A2dp => "android.bluetooth.a2dp.profile.action.CONNECTION_STATE_CHANGED",
+        }
+    }
+}
+
+/// Connection state change of a device, as broadcast by
+/// `ACTION_CONNECTION_STATE_CHANGED` of the profile.
+pub struct ConnectionStateChange {
+    /// The `android.bluetooth.BluetoothDevice` whose state changed.
+    pub device: GlobalRef,
+    /// New state of the device. One of the `STATE_*` constants.
+    pub state: jint,
+    /// Previous state of the device. One of the `STATE_*` constants.
+    pub previous_state: jint,
+}
+
+/// Proxy object for a Bluetooth profile, obtained by calling
+/// [`get_profile_proxy`]. The proxy is closed with
+/// `BluetoothAdapter.closeProfileProxy()` when this object is dropped.
+pub struct BluetoothProfileProxy {
+    profile: Profile,
+    adapter: GlobalRef,
+    proxy: GlobalRef,
+    vm: JavaVM,
+}
+
+impl BluetoothProfileProxy {
+    /// Get the profile of this proxy.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Get the `android.bluetooth.BluetoothDevice`s that are currently
+    /// connected to this profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn connected_devices<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>) -> Result<Vec<JObject<'a>>> {
+        let proxy = JObject::from(self.proxy.as_obj().into_inner());
+        let list = env
+            .call_method(proxy, "getConnectedDevices", "()Ljava/util/List;", &[])?
+            .l()?;
+        let list = env.get_list(list)?;
+        let devices = list.iter()?.collect();
+        Ok(devices)
+    }
+
+    /// Get the connection state of a device for this profile. Returns one of
+    /// the `STATE_*` constants.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `device` - `android.bluetooth.BluetoothDevice` to query.
+    pub fn connection_state<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        device: JObject<'a>,
+    ) -> Result<jint> {
+        env.call_method(
+            self.proxy.as_obj(),
+            "getConnectionState",
+            "(Landroid/bluetooth/BluetoothDevice;)I",
+            &[device.into()],
+        )?
+        .i()
+    }
+
+    /// Check whether audio is currently being streamed to a device. Only
+    /// valid for [`Profile::A2dp`] proxies.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `device` - `android.bluetooth.BluetoothDevice` to query.
+    pub fn is_a2dp_playing<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        device: JObject<'a>,
+    ) -> Result<bool> {
+        env.call_method(
+            self.proxy.as_obj(),
+            "isA2dpPlaying",
+            "(Landroid/bluetooth/BluetoothDevice;)Z",
+            &[device.into()],
+        )?
+        .z()
+    }
+
+    /// Check whether a SCO audio connection is open to a device. Only valid
+    /// for [`Profile::Headset`] proxies.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `device` - `android.bluetooth.BluetoothDevice` to query.
+    pub fn is_audio_connected<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        device: JObject<'a>,
+    ) -> Result<bool> {
+        env.call_method(
+            self.proxy.as_obj(),
+            "isAudioConnected",
+            "(Landroid/bluetooth/BluetoothDevice;)Z",
+            &[device.into()],
+        )?
+        .z()
+    }
+
+    /// Create a stream of [`ConnectionStateChange`]s for this profile. A
+    /// broadcast receiver is registered on `context` and unregistered when
+    /// the stream is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `context` - `android.content.Context` to register the receiver on.
+    pub fn connection_state_changes<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        context: JObject<'a>,
+    ) -> Result<impl Stream<Item = Result<ConnectionStateChange>> + Send> {
+        let action = env.new_string(self.profile.connection_state_changed_action())?;
+        let receiver = env.new_object(
+            "io/github/gedgygedgy/rust/android/bluetooth/RustBluetoothConnectionStateReceiver",
+            "(Landroid/content/Context;Ljava/lang/String;)V",
+            &[context.into(), action.into()],
+        )?;
+        let stream = env
+            .call_method(
+                receiver,
+                "getIntentStream",
+                "()Lio/github/gedgygedgy/rust/stream/Stream;",
+                &[],
+            )?
+            .l()?;
+        let stream = JSendStream::try_from(JStream::from_env(env, stream)?)?;
+
+        let vm = env.get_java_vm()?;
+        let mapped_stream = stream.map(move |item| -> Result<_> {
+            let item = item?;
+            let intent = item.as_obj();
+            let env = vm.get_env()?;
+
+            let state_name = env.new_string("android.bluetooth.profile.extra.STATE")?;
+            let state = env
+                .call_method(
+                    intent,
+                    "getIntExtra",
+                    "(Ljava/lang/String;I)I",
+                    &[state_name.into(), (-1).into()],
+                )?
+                .i()?;
+            let previous_state_name =
+                env.new_string("android.bluetooth.profile.extra.PREVIOUS_STATE")?;
+            let previous_state = env
+                .call_method(
+                    intent,
+                    "getIntExtra",
+                    "(Ljava/lang/String;I)I",
+                    &[previous_state_name.into(), (-1).into()],
+                )?
+                .i()?;
+            let device_name = env.new_string("android.bluetooth.device.extra.DEVICE")?;
+            let device = env.new_global_ref(
+                env.call_method(
+                    intent,
+                    "getParcelableExtra",
+                    "(Ljava/lang/String;)Landroid/os/Parcelable;",
+                    &[device_name.into()],
+                )?
+                .l()?,
+            )?;
+
+            Ok(ConnectionStateChange {
+                device,
+                state,
+                previous_state,
+            })
+        });
+
+        Ok(CloseOnDropStream::new(
+            mapped_stream,
+            CloseOnDrop::new(env, receiver)?,
+        ))
+    }
+}
+
+impl ::std::ops::Deref for BluetoothProfileProxy {
+    type Target = GlobalRef;
+
+    fn deref(&self) -> &Self::Target {
+        &self.proxy
+    }
+}
+
+impl Drop for BluetoothProfileProxy {
+    fn drop(&mut self) {
+        if let Ok(env) = self.vm.attach_current_thread() {
+            let _ = env.call_method(
+                self.adapter.as_obj(),
+                "closeProfileProxy",
+                "(ILandroid/bluetooth/BluetoothProfile;)V",
+                &[self.profile.id().into(), self.proxy.as_obj().into()],
+            );
+        }
+    }
+}
+
+/// Request a proxy object for a Bluetooth profile from the default
+/// `android.bluetooth.BluetoothAdapter`. The returned future resolves once
+/// the proxy has been connected. Returns [`None`] if the device has no
+/// Bluetooth adapter or the proxy could not be requested.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to request the proxy with.
+/// * `profile` - Profile to request a proxy for.
+pub fn get_profile_proxy<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    profile: Profile,
+) -> Result<Option<impl Future<Output = Result<BluetoothProfileProxy>> + Send>> {
+    let adapter = env
+        .call_static_method(
+            "android/bluetooth/BluetoothAdapter",
+            "getDefaultAdapter",
+            "()Landroid/bluetooth/BluetoothAdapter;",
+            &[],
+        )?
+        .l()?;
+    if adapter.is_null() {
+        return Ok(None);
+    }
+
+    let listener = env.new_object(
+        "io/github/gedgygedgy/rust/android/bluetooth/RustBluetoothProfileServiceListener",
+        "()V",
+        &[],
+    )?;
+    let future = env
+        .call_method(
+            listener,
+            "getProxyFuture",
+            "()Lio/github/gedgygedgy/rust/future/Future;",
+            &[],
+        )?
+        .l()?;
+    let future = JSendFuture::try_from(JFuture::from_env(env, future)?)?;
+
+    let requested = env
+        .call_method(
+            adapter,
+            "getProfileProxy",
+            "(Landroid/content/Context;Landroid/bluetooth/BluetoothProfile$ServiceListener;I)Z",
+            &[context.into(), listener.into(), profile.id().into()],
+        )?
+        .z()?;
+    if !requested {
+        return Ok(None);
+    }
+
+    let vm = env.get_java_vm()?;
+    let adapter = env.new_global_ref(adapter)?;
+    Ok(Some(async move {
+        let result = future.await?;
+        let env = vm.get_env()?;
+        let proxy = env.new_global_ref(JPollResult::from_env(&env, result.as_obj())?.get()?)?;
+        Ok(BluetoothProfileProxy {
+            profile,
+            adapter,
+            proxy,
+            vm,
+        })
+    }))
+}
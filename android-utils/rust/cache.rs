@@ -0,0 +1,218 @@
+use jni::{
+    errors::Result,
+    objects::{GlobalRef, JMethodID},
+    sys::jmethodID,
+    JNIEnv,
+};
+use once_cell::sync::OnceCell;
+
+static CACHE: OnceCell<Cache> = OnceCell::new();
+
+/// Class references and method IDs resolved once by [`init()`](crate::init)
+/// and shared by every wrapper afterwards.
+struct Cache {
+    // Keeps the class loaded so that the method IDs stay valid.
+    _handler_class: GlobalRef,
+    handler: HandlerIds,
+    _context_class: GlobalRef,
+    context: ContextIds,
+    service_connection: ServiceConnectionClasses,
+}
+
+/// Method ID which can be stored in a `static`. Method IDs stay valid as long
+/// as their class is loaded, which is guaranteed by holding a [`GlobalRef`]
+/// to the class.
+#[derive(Clone, Copy)]
+pub(crate) struct MethodId(jmethodID);
+
+unsafe impl Send for MethodId {}
+unsafe impl Sync for MethodId {}
+
+impl MethodId {
+    fn new<'a: 'b, 'b>(
+        env: &'b JNIEnv<'a>,
+        class: &GlobalRef,
+        name: &str,
+        sig: &str,
+    ) -> Result<Self> {
+        Ok(Self(env.get_method_id(class, name, sig)?.into_inner()))
+    }
+
+    pub(crate) fn get<'a>(self) -> JMethodID<'a> {
+        self.0.into()
+    }
+}
+
+/// Method IDs of `android.os.Handler`.
+#[derive(Clone, Copy)]
+pub(crate) struct HandlerIds {
+    pub(crate) post: MethodId,
+    pub(crate) post_delayed: MethodId,
+    pub(crate) post_at_time: MethodId,
+    pub(crate) send_message: MethodId,
+    pub(crate) send_empty_message: MethodId,
+    pub(crate) send_message_delayed: MethodId,
+}
+
+impl HandlerIds {
+    fn new<'a: 'b, 'b>(env: &'b JNIEnv<'a>, class: &GlobalRef) -> Result<Self> {
+        Ok(Self {
+            post: MethodId::new(env, class, "post", "(Ljava/lang/Runnable;)Z")?,
+            post_delayed: MethodId::new(env, class, "postDelayed", "(Ljava/lang/Runnable;J)Z")?,
+            post_at_time: MethodId::new(env, class, "postAtTime", "(Ljava/lang/Runnable;J)Z")?,
+            send_message: MethodId::new(env, class, "sendMessage", "(Landroid/os/Message;)Z")?,
+            send_empty_message: MethodId::new(env, class, "sendEmptyMessage", "(I)Z")?,
+            send_message_delayed: MethodId::new(
+                env,
+                class,
+                "sendMessageDelayed",
+                "(Landroid/os/Message;J)Z",
+            )?,
+        })
+    }
+}
+
+/// Method IDs of `android.content.Context`.
+#[derive(Clone, Copy)]
+pub(crate) struct ContextIds {
+    pub(crate) start_service: MethodId,
+    pub(crate) stop_service: MethodId,
+    pub(crate) bind_service: MethodId,
+    pub(crate) start_activity: MethodId,
+    pub(crate) send_broadcast: MethodId,
+    pub(crate) get_system_service: MethodId,
+    pub(crate) get_application_context: MethodId,
+    pub(crate) check_self_permission: MethodId,
+}
+
+impl ContextIds {
+    fn new<'a: 'b, 'b>(env: &'b JNIEnv<'a>, class: &GlobalRef) -> Result<Self> {
+        Ok(Self {
+            start_service: MethodId::new(
+                env,
+                class,
+                "startService",
+                "(Landroid/content/Intent;)Landroid/content/ComponentName;",
+            )?,
+            stop_service: MethodId::new(env, class, "stopService", "(Landroid/content/Intent;)Z")?,
+            bind_service: MethodId::new(
+                env,
+                class,
+                "bindService",
+                "(Landroid/content/Intent;Landroid/content/ServiceConnection;I)Z",
+            )?,
+            start_activity: MethodId::new(
+                env,
+                class,
+                "startActivity",
+                "(Landroid/content/Intent;)V",
+            )?,
+            send_broadcast: MethodId::new(
+                env,
+                class,
+                "sendBroadcast",
+                "(Landroid/content/Intent;)V",
+            )?,
+            get_system_service: MethodId::new(
+                env,
+                class,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+            )?,
+            get_application_context: MethodId::new(
+                env,
+                class,
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+            )?,
+            check_self_permission: MethodId::new(
+                env,
+                class,
+                "checkSelfPermission",
+                "(Ljava/lang/String;)I",
+            )?,
+        })
+    }
+}
+
+/// Event classes of `RustServiceConnection`.
+#[derive(Clone)]
+pub(crate) struct ServiceConnectionClasses {
+    pub(crate) binding_died: GlobalRef,
+    pub(crate) null_binding: GlobalRef,
+    pub(crate) service_connected: GlobalRef,
+    pub(crate) service_disconnected: GlobalRef,
+}
+
+impl ServiceConnectionClasses {
+    fn new<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<Self> {
+        Ok(Self {
+            binding_died: class(
+                env,
+                "io/github/gedgygedgy/rust/android/content/RustServiceConnection$BindingDiedEvent",
+            )?,
+            null_binding: class(
+                env,
+                "io/github/gedgygedgy/rust/android/content/RustServiceConnection$NullBindingEvent",
+            )?,
+            service_connected: class(
+                env,
+                "io/github/gedgygedgy/rust/android/content/RustServiceConnection$ServiceConnectedEvent",
+            )?,
+            service_disconnected: class(
+                env,
+                "io/github/gedgygedgy/rust/android/content/RustServiceConnection$ServiceDisconnectedEvent",
+            )?,
+        })
+    }
+}
+
+fn class<'a: 'b, 'b>(env: &'b JNIEnv<'a>, name: &str) -> Result<GlobalRef> {
+    let class = env.auto_local(env.find_class(name)?);
+    env.new_global_ref(&class)
+}
+
+pub(crate) fn init<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<()> {
+    if CACHE.get().is_none() {
+        let handler_class = class(env, "android/os/Handler")?;
+        let context_class = class(env, "android/content/Context")?;
+        let cache = Cache {
+            handler: HandlerIds::new(env, &handler_class)?,
+            _handler_class: handler_class,
+            context: ContextIds::new(env, &context_class)?,
+            _context_class: context_class,
+            service_connection: ServiceConnectionClasses::new(env)?,
+        };
+        let _ = CACHE.set(cache);
+    }
+    Ok(())
+}
+
+/// Get the method IDs of `android.os.Handler`, looking them up if
+/// [`init()`](crate::init) has not been called.
+pub(crate) fn handler_ids<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<HandlerIds> {
+    match CACHE.get() {
+        Some(cache) => Ok(cache.handler),
+        None => HandlerIds::new(env, &class(env, "android/os/Handler")?),
+    }
+}
+
+/// Get the method IDs of `android.content.Context`, looking them up if
+/// [`init()`](crate::init) has not been called.
+pub(crate) fn context_ids<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<ContextIds> {
+    match CACHE.get() {
+        Some(cache) => Ok(cache.context),
+        None => ContextIds::new(env, &class(env, "android/content/Context")?),
+    }
+}
+
+/// Get the event classes of `RustServiceConnection`, looking them up if
+/// [`init()`](crate::init) has not been called.
+pub(crate) fn service_connection_classes<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+) -> Result<ServiceConnectionClasses> {
+    match CACHE.get() {
+        Some(cache) => Ok(cache.service_connection.clone()),
+        None => ServiceConnectionClasses::new(env),
+    }
+}
@@ -0,0 +1,495 @@
+use crate::{
+    refs::DeferredGlobalRef,
+    util::{sdk_int, CloseOnDrop, CloseOnDropStream, RemoveListenerOnDrop},
+};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{GlobalRef, JObject, JString},
+    sys::{jint, jsize},
+    JNIEnv,
+};
+use jni_utils::stream::{JSendStream, JStream};
+use std::convert::TryFrom;
+
+pub mod clipboard;
+mod context;
+mod intent;
+mod intent_filter;
+pub mod pm;
+pub mod router;
+mod shared_preferences;
+pub mod storage;
+
+pub use crate::os::JBundle;
+pub use context::{JContext, PERMISSION_GRANTED};
+pub use intent::JIntent;
+pub use intent_filter::JIntentFilter;
+pub use shared_preferences::{JSharedPreferences, JSharedPreferencesEditor, MODE_PRIVATE};
+
+/// `android.content.Intent.URI_INTENT_SCHEME`.
+pub const URI_INTENT_SCHEME: jint = 1 << 0;
+
+/// `android.content.Intent.URI_ANDROID_APP_SCHEME`.
+pub const URI_ANDROID_APP_SCHEME: jint = 1 << 1;
+
+/// `android.content.Intent.URI_ALLOW_UNSAFE`.
+pub const URI_ALLOW_UNSAFE: jint = 1 << 2;
+
+/// `android.content.Intent.FLAG_ACTIVITY_NEW_TASK`.
+pub const FLAG_ACTIVITY_NEW_TASK: jint = 0x10000000;
+
+/// `android.content.Intent.FLAG_ACTIVITY_CLEAR_TOP`.
+pub const FLAG_ACTIVITY_CLEAR_TOP: jint = 0x04000000;
+
+/// `android.content.Intent.FLAG_ACTIVITY_SINGLE_TOP`.
+pub const FLAG_ACTIVITY_SINGLE_TOP: jint = 0x20000000;
+
+/// `android.content.Intent.FLAG_RECEIVER_FOREGROUND`.
+pub const FLAG_RECEIVER_FOREGROUND: jint = 0x10000000;
+
+/// `android.content.IntentFilter.SYSTEM_HIGH_PRIORITY`.
+pub const SYSTEM_HIGH_PRIORITY: jint = 1000;
+
+/// `android.content.IntentFilter.SYSTEM_LOW_PRIORITY`.
+pub const SYSTEM_LOW_PRIORITY: jint = -1000;
+
+/// `android.content.Context.RECEIVER_VISIBLE_TO_INSTANT_APPS`.
+pub const RECEIVER_VISIBLE_TO_INSTANT_APPS: jint = 0x1;
+
+/// `android.content.Context.RECEIVER_EXPORTED`. Requires API level 33.
+pub const RECEIVER_EXPORTED: jint = 0x2;
+
+/// `android.content.Context.RECEIVER_NOT_EXPORTED`. Requires API level 33.
+pub const RECEIVER_NOT_EXPORTED: jint = 0x4;
+
+/// `android.content.Intent.FLAG_GRANT_READ_URI_PERMISSION`.
+pub const FLAG_GRANT_READ_URI_PERMISSION: jint = 0x1;
+
+/// `android.content.Intent.FLAG_GRANT_WRITE_URI_PERMISSION`.
+pub const FLAG_GRANT_WRITE_URI_PERMISSION: jint = 0x2;
+
+/// `android.content.Intent.FLAG_GRANT_PERSISTABLE_URI_PERMISSION`.
+pub const FLAG_GRANT_PERSISTABLE_URI_PERMISSION: jint = 0x40;
+
+/// `android.content.Intent.FLAG_GRANT_PREFIX_URI_PERMISSION`.
+pub const FLAG_GRANT_PREFIX_URI_PERMISSION: jint = 0x80;
+
+const GRANT_FLAGS: jint = FLAG_GRANT_READ_URI_PERMISSION
+    | FLAG_GRANT_WRITE_URI_PERMISSION
+    | FLAG_GRANT_PERSISTABLE_URI_PERMISSION
+    | FLAG_GRANT_PREFIX_URI_PERMISSION;
+
+/// Reason an `android.content.Intent` parsed from an untrusted URI was
+/// rejected by [`check_intent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnsafeIntent {
+    /// The intent names an explicit component, and could be used to launch
+    /// a component which is not exported.
+    Component,
+    /// The intent has a selector which names an explicit component or
+    /// package, bypassing the categories and actions of the intent.
+    SelectorTarget,
+    /// The intent has a selector, which is not allowed together with an
+    /// explicit package.
+    SelectorWithPackage,
+    /// The intent carries `FLAG_GRANT_*_URI_PERMISSION` flags, and would
+    /// grant the receiver access to the sender's content.
+    GrantFlags,
+}
+
+/// Parse an `android.content.Intent` from a URI with `Intent.parseUri()`.
+/// Returns an error with a pending `java.net.URISyntaxException` if the URI
+/// can't be parsed.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `uri` - URI to parse.
+/// * `flags` - Combination of the `URI_*` constants.
+pub fn intent_from_uri<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    uri: &str,
+    flags: jint,
+) -> Result<JObject<'a>> {
+    let uri = env.new_string(uri)?;
+    env.call_static_method(
+        "android/content/Intent",
+        "parseUri",
+        "(Ljava/lang/String;I)Landroid/content/Intent;",
+        &[uri.into(), flags.into()],
+    )?
+    .l()
+}
+
+/// Format an `android.content.Intent` as a URI with `Intent.toUri()`. The
+/// result can be turned back into an equivalent intent with
+/// [`intent_from_uri`] and the same flags.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `intent` - Intent to format.
+/// * `flags` - Combination of the `URI_*` constants.
+pub fn intent_to_uri<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    intent: JObject<'a>,
+    flags: jint,
+) -> Result<String> {
+    let uri = env
+        .call_method(intent, "toUri", "(I)Ljava/lang/String;", &[flags.into()])?
+        .l()?;
+    Ok(env.get_string(uri.into())?.into())
+}
+
+fn has_target<'a: 'b, 'b>(env: &'b JNIEnv<'a>, intent: JObject<'a>) -> Result<(bool, bool)> {
+    let component = env
+        .call_method(
+            intent,
+            "getComponent",
+            "()Landroid/content/ComponentName;",
+            &[],
+        )?
+        .l()?;
+    let package = env
+        .call_method(intent, "getPackage", "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok((!component.is_null(), !package.is_null()))
+}
+
+fn selector<'a: 'b, 'b>(env: &'b JNIEnv<'a>, intent: JObject<'a>) -> Result<JObject<'a>> {
+    env.call_method(intent, "getSelector", "()Landroid/content/Intent;", &[])?
+        .l()
+}
+
+/// Check whether an `android.content.Intent` which came from an untrusted
+/// source, such as [`intent_from_uri`] on a deep link, is safe to launch.
+/// Returns the first problem found, if any.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `intent` - Intent to check.
+pub fn check_intent<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    intent: JObject<'a>,
+) -> Result<std::result::Result<(), UnsafeIntent>> {
+    let (component, package) = has_target(env, intent)?;
+    if component {
+        return Ok(Err(UnsafeIntent::Component));
+    }
+
+    let selector = selector(env, intent)?;
+    if !selector.is_null() {
+        if package {
+            return Ok(Err(UnsafeIntent::SelectorWithPackage));
+        }
+        let (component, package) = has_target(env, selector)?;
+        if component || package {
+            return Ok(Err(UnsafeIntent::SelectorTarget));
+        }
+    }
+
+    let flags = env.call_method(intent, "getFlags", "()I", &[])?.i()?;
+    if flags & GRANT_FLAGS != 0 {
+        return Ok(Err(UnsafeIntent::GrantFlags));
+    }
+
+    Ok(Ok(()))
+}
+
+/// Strip everything [`check_intent`] would reject from an
+/// `android.content.Intent`: the component, the selector and the
+/// `FLAG_GRANT_*_URI_PERMISSION` flags. The intent is also given the
+/// `android.intent.category.BROWSABLE` category, so that it only resolves to
+/// activities which accept links from a browser.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `intent` - Intent to sanitize.
+pub fn sanitize_intent<'a: 'b, 'b>(env: &'b JNIEnv<'a>, intent: JObject<'a>) -> Result<()> {
+    env.call_method(
+        intent,
+        "setComponent",
+        "(Landroid/content/ComponentName;)Landroid/content/Intent;",
+        &[JObject::null().into()],
+    )?;
+    env.call_method(
+        intent,
+        "setSelector",
+        "(Landroid/content/Intent;)V",
+        &[JObject::null().into()],
+    )?;
+    let flags = env.call_method(intent, "getFlags", "()I", &[])?.i()?;
+    env.call_method(
+        intent,
+        "setFlags",
+        "(I)Landroid/content/Intent;",
+        &[(flags & !GRANT_FLAGS).into()],
+    )?;
+    let category = env.new_string("android.intent.category.BROWSABLE")?;
+    env.call_method(
+        intent,
+        "addCategory",
+        "(Ljava/lang/String;)Landroid/content/Intent;",
+        &[category.into()],
+    )?;
+    Ok(())
+}
+
+/// Represents a broadcast received by a receiver created by
+/// [`async_broadcast_receiver`].
+pub struct BroadcastEvent {
+    /// `android.content.Context` the receiver is running in.
+    pub context: DeferredGlobalRef,
+    /// `android.content.Intent` that was received.
+    pub intent: DeferredGlobalRef,
+}
+
+fn broadcast_event_stream<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    receiver: JObject<'a>,
+) -> Result<JSendStream> {
+    let stream = env
+        .call_method(
+            receiver,
+            "getEventStream",
+            "()Lio/github/gedgygedgy/rust/stream/Stream;",
+            &[],
+        )?
+        .l()?;
+    JSendStream::try_from(JStream::from_env(env, stream)?)
+}
+
+fn event_field<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    event: JObject<'a>,
+    name: &str,
+    ty: &str,
+) -> Result<DeferredGlobalRef> {
+    let obj = env.get_field(event, name, ty)?.l()?;
+    Ok(DeferredGlobalRef::new(env.new_global_ref(obj)?))
+}
+
+/// Creates an `android.content.BroadcastReceiver` and an accompanying stream
+/// of [`BroadcastEvent`]s captured by it. The receiver is not registered;
+/// pass it to `Context.registerReceiver()`, or declare it for a
+/// `PendingIntent`. The stream finishes when the receiver's `close()` method
+/// is called.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn async_broadcast_receiver<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+) -> Result<(
+    JObject<'a>,
+    impl Stream<Item = Result<BroadcastEvent>> + Send,
+)> {
+    let vm = env.get_java_vm()?;
+    let receiver = env.new_object(
+        "io/github/gedgygedgy/rust/android/content/RustBroadcastReceiver",
+        "()V",
+        &[],
+    )?;
+    let stream = broadcast_event_stream(env, receiver)?;
+
+    let mapped_stream = stream.map(move |item| -> Result<_> {
+        let item = item?;
+        let item = item.as_obj();
+        let env = vm.get_env()?;
+
+        Ok(BroadcastEvent {
+            context: event_field(&env, item, "context", "Landroid/content/Context;")?,
+            intent: event_field(&env, item, "intent", "Landroid/content/Intent;")?,
+        })
+    });
+
+    Ok((receiver, mapped_stream))
+}
+
+/// Keeps a broadcast receiver registered by [`register_receiver`]. When
+/// dropped, the receiver is unregistered and its stream of
+/// [`BroadcastEvent`]s finishes.
+pub struct ReceiverRegistration {
+    _unregister: RemoveListenerOnDrop,
+    _close: CloseOnDrop,
+}
+
+/// Create a receiver with [`async_broadcast_receiver`] and register it with
+/// [`JContext::register_receiver`]. Returns a guard which unregisters the
+/// receiver when dropped, and the stream of broadcasts it receives.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the receiver on.
+/// * `filter` - Filter of broadcasts to receive.
+/// * `flags` - Combination of the `RECEIVER_*` flags.
+pub fn register_receiver<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    filter: &JIntentFilter<'a, '_>,
+    flags: jint,
+) -> Result<(
+    ReceiverRegistration,
+    impl Stream<Item = Result<BroadcastEvent>> + Send,
+)> {
+    let (receiver, stream) = async_broadcast_receiver(env)?;
+    let close = CloseOnDrop::new(env, receiver)?;
+    JContext::from_env(env, context)?.register_receiver(receiver, **filter, flags)?;
+    let registration = ReceiverRegistration {
+        _unregister: RemoveListenerOnDrop::new(
+            env,
+            context,
+            receiver,
+            "unregisterReceiver",
+            "(Landroid/content/BroadcastReceiver;)V",
+        )?,
+        _close: close,
+    };
+    Ok((registration, stream))
+}
+
+/// Register a `android.content.BroadcastReceiver` for a set of actions, and
+/// get a stream of the `android.content.Intent`s it receives. The receiver is
+/// unregistered when the stream is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the receiver on.
+/// * `actions` - Actions to receive.
+pub fn broadcast_stream<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    actions: &[&str],
+) -> Result<impl Stream<Item = Result<GlobalRef>> + Send> {
+    let vm = env.get_java_vm()?;
+    let action_array =
+        env.new_object_array(actions.len() as jsize, "java/lang/String", JObject::null())?;
+    for (i, action) in actions.iter().enumerate() {
+        let action = env.new_string(action)?;
+        env.set_object_array_element(action_array, i as jsize, action)?;
+    }
+    let receiver = env.new_object(
+        "io/github/gedgygedgy/rust/android/content/RustBroadcastReceiver",
+        "(Landroid/content/Context;[Ljava/lang/String;)V",
+        &[context.into(), JObject::from(action_array).into()],
+    )?;
+    let stream = broadcast_event_stream(env, receiver)?.map(move |item| {
+        let item = item?;
+        let env = vm.get_env()?;
+        let intent = env
+            .get_field(item.as_obj(), "intent", "Landroid/content/Intent;")?
+            .l()?;
+        env.new_global_ref(intent)
+    });
+
+    Ok(CloseOnDropStream::new(
+        stream,
+        CloseOnDrop::new(env, receiver)?,
+    ))
+}
+
+fn locale_tag<'a: 'b, 'b>(env: &'b JNIEnv<'a>, locale: JObject<'a>) -> Result<String> {
+    let tag = env
+        .call_method(locale, "toLanguageTag", "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok(env.get_string(JString::from(tag))?.into())
+}
+
+/// Get the user's preferred locales, most preferred first, as BCP-47
+/// language tags such as `en-US`. Before API level 24, only the primary
+/// locale is returned.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the configuration from.
+pub fn locales<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<Vec<String>> {
+    let resources = env
+        .call_method(
+            context,
+            "getResources",
+            "()Landroid/content/res/Resources;",
+            &[],
+        )?
+        .l()?;
+    let configuration = env
+        .call_method(
+            resources,
+            "getConfiguration",
+            "()Landroid/content/res/Configuration;",
+            &[],
+        )?
+        .l()?;
+
+    if sdk_int(env)? < 24 {
+        let locale = env
+            .get_field(configuration, "locale", "Ljava/util/Locale;")?
+            .l()?;
+        return Ok(vec![locale_tag(env, locale)?]);
+    }
+
+    let list = env
+        .call_method(
+            configuration,
+            "getLocales",
+            "()Landroid/os/LocaleList;",
+            &[],
+        )?
+        .l()?;
+    let size = env.call_method(list, "size", "()I", &[])?.i()?;
+    let mut result = Vec::with_capacity(size as usize);
+    for i in 0..size {
+        let locale = env
+            .call_method(list, "get", "(I)Ljava/util/Locale;", &[i.into()])?
+            .l()?;
+        result.push(locale_tag(env, locale)?);
+        env.delete_local_ref(locale)?;
+    }
+    Ok(result)
+}
+
+/// Like [`locales`], but parses the tags into
+/// [`LanguageIdentifier`](unic_langid::LanguageIdentifier)s. Tags which
+/// can't be parsed are skipped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the configuration from.
+#[cfg(feature = "unic-langid")]
+pub fn language_identifiers<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Vec<unic_langid::LanguageIdentifier>> {
+    Ok(locales(env, context)?
+        .iter()
+        .filter_map(|tag| tag.parse().ok())
+        .collect())
+}
+
+/// Get a stream of the user's preferred locales, as returned by
+/// [`locales`], each time `android.intent.action.LOCALE_CHANGED` is
+/// received. The receiver is unregistered when the stream is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the receiver on.
+pub fn locale_changes<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<impl Stream<Item = Result<Vec<String>>> + Send> {
+    let stream = broadcast_stream(env, context, &["android.intent.action.LOCALE_CHANGED"])?;
+    let context = env.new_global_ref(context)?;
+    let vm = env.get_java_vm()?;
+    Ok(stream.map(move |intent| {
+        intent?;
+        let env = vm.get_env()?;
+        locales(&env, context.as_obj())
+    }))
+}
@@ -0,0 +1,346 @@
+//! Access to the primary clip of `android.content.ClipboardManager`.
+//!
+//! Since API level 29, only the app with input focus or the default input
+//! method can read the clipboard. Other apps get [`None`] from
+//! [`primary_clip`], but can still write to it and be notified of changes.
+
+use crate::{
+    content::JContext,
+    ops::listener_stream,
+    util::{sdk_int, RemoveListenerOnDrop},
+};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    JNIEnv,
+};
+
+fn clipboard_manager<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service("clipboard")
+}
+
+fn string<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Option<String>> {
+    if obj.is_null() {
+        return Ok(None);
+    }
+    let s = env
+        .call_method(obj, "toString", "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok(Some(env.get_string(JString::from(s))?.into()))
+}
+
+fn parse_uri<'a: 'b, 'b>(env: &'b JNIEnv<'a>, uri: &str) -> Result<JObject<'a>> {
+    let uri = env.new_string(uri)?;
+    env.call_static_method(
+        "android/net/Uri",
+        "parse",
+        "(Ljava/lang/String;)Landroid/net/Uri;",
+        &[uri.into()],
+    )?
+    .l()
+}
+
+/// Item of a [`Clip`], from `android.content.ClipData.Item`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClipItem {
+    /// Result of `ClipData.Item.getText()`, with any styling removed.
+    pub text: Option<String>,
+    /// Result of `ClipData.Item.getHtmlText()`.
+    pub html_text: Option<String>,
+    /// Result of `ClipData.Item.getUri()`.
+    pub uri: Option<String>,
+    /// Result of `ClipData.Item.coerceToText()`: the text, the contents of
+    /// a text URI, or the URI or intent itself as text.
+    pub coerced_text: String,
+}
+
+/// Contents of the clipboard, from `android.content.ClipData`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Clip {
+    /// Label of the clip, from `ClipDescription.getLabel()`.
+    pub label: Option<String>,
+    /// MIME types of the clip, from `ClipDescription.getMimeType()`.
+    pub mime_types: Vec<String>,
+    /// Result of `ClipDescription.isStyledText()`, or [`None`] before API
+    /// level 31.
+    pub styled_text: Option<bool>,
+    /// Items of the clip.
+    pub items: Vec<ClipItem>,
+}
+
+fn clip_item<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    item: JObject<'a>,
+) -> Result<ClipItem> {
+    let text = env
+        .call_method(item, "getText", "()Ljava/lang/CharSequence;", &[])?
+        .l()?;
+    let html_text = env
+        .call_method(item, "getHtmlText", "()Ljava/lang/String;", &[])?
+        .l()?;
+    let uri = env
+        .call_method(item, "getUri", "()Landroid/net/Uri;", &[])?
+        .l()?;
+    let coerced_text = env
+        .call_method(
+            item,
+            "coerceToText",
+            "(Landroid/content/Context;)Ljava/lang/CharSequence;",
+            &[context.into()],
+        )?
+        .l()?;
+    Ok(ClipItem {
+        text: string(env, text)?,
+        html_text: string(env, html_text)?,
+        uri: string(env, uri)?,
+        coerced_text: string(env, coerced_text)?.unwrap_or_default(),
+    })
+}
+
+/// Check whether there is a primary clip with
+/// `ClipboardManager.hasPrimaryClip()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ClipboardManager`
+///   from.
+pub fn has_primary_clip<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<bool> {
+    let manager = clipboard_manager(env, context)?;
+    env.call_method(manager, "hasPrimaryClip", "()Z", &[])?.z()
+}
+
+/// Get the primary clip with `ClipboardManager.getPrimaryClip()`, or
+/// [`None`] if there is none or the app may not read it.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ClipboardManager`
+///   from, and to coerce items to text with.
+pub fn primary_clip<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<Option<Clip>> {
+    let manager = clipboard_manager(env, context)?;
+    let clip = env
+        .call_method(
+            manager,
+            "getPrimaryClip",
+            "()Landroid/content/ClipData;",
+            &[],
+        )?
+        .l()?;
+    if clip.is_null() {
+        return Ok(None);
+    }
+
+    let description = env
+        .call_method(
+            clip,
+            "getDescription",
+            "()Landroid/content/ClipDescription;",
+            &[],
+        )?
+        .l()?;
+    let label = env
+        .call_method(description, "getLabel", "()Ljava/lang/CharSequence;", &[])?
+        .l()?;
+    let mime_type_count = env
+        .call_method(description, "getMimeTypeCount", "()I", &[])?
+        .i()?;
+    let mut mime_types = Vec::with_capacity(mime_type_count as usize);
+    for i in 0..mime_type_count {
+        let mime_type = env
+            .call_method(
+                description,
+                "getMimeType",
+                "(I)Ljava/lang/String;",
+                &[i.into()],
+            )?
+            .l()?;
+        mime_types.extend(string(env, mime_type)?);
+    }
+    let styled_text = if sdk_int(env)? >= 31 {
+        Some(
+            env.call_method(description, "isStyledText", "()Z", &[])?
+                .z()?,
+        )
+    } else {
+        None
+    };
+
+    let item_count = env.call_method(clip, "getItemCount", "()I", &[])?.i()?;
+    let mut items = Vec::with_capacity(item_count as usize);
+    for i in 0..item_count {
+        let item = env
+            .call_method(
+                clip,
+                "getItemAt",
+                "(I)Landroid/content/ClipData$Item;",
+                &[i.into()],
+            )?
+            .l()?;
+        items.push(clip_item(env, context, item)?);
+        env.delete_local_ref(item)?;
+    }
+
+    Ok(Some(Clip {
+        label: string(env, label)?,
+        mime_types,
+        styled_text,
+        items,
+    }))
+}
+
+/// Get the primary clip as text, using `ClipData.Item.coerceToText()` on
+/// its first item. Returns [`None`] if there is no primary clip or the app
+/// may not read it.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ClipboardManager`
+///   from, and to coerce the item to text with.
+pub fn primary_clip_text<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<String>> {
+    Ok(primary_clip(env, context)?
+        .and_then(|clip| clip.items.into_iter().next())
+        .map(|item| item.coerced_text))
+}
+
+fn set_primary_clip<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    clip: JObject<'a>,
+) -> Result<()> {
+    let manager = clipboard_manager(env, context)?;
+    env.call_method(
+        manager,
+        "setPrimaryClip",
+        "(Landroid/content/ClipData;)V",
+        &[clip.into()],
+    )?;
+    Ok(())
+}
+
+/// Set the primary clip to plain text, using `ClipData.newPlainText()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ClipboardManager`
+///   from.
+/// * `label` - User-visible label of the clip.
+/// * `text` - Text to copy.
+pub fn set_primary_clip_text<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    label: &str,
+    text: &str,
+) -> Result<()> {
+    let label = env.new_string(label)?;
+    let text = env.new_string(text)?;
+    let clip = env
+        .call_static_method(
+            "android/content/ClipData",
+            "newPlainText",
+            "(Ljava/lang/CharSequence;Ljava/lang/CharSequence;)Landroid/content/ClipData;",
+            &[label.into(), text.into()],
+        )?
+        .l()?;
+    set_primary_clip(env, context, clip)
+}
+
+/// Set the primary clip to a URI, using `ClipData.newUri()`, which gets the
+/// MIME types of `content:` URIs from their content provider.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ClipboardManager`
+///   and `ContentResolver` from.
+/// * `label` - User-visible label of the clip.
+/// * `uri` - URI to copy, parsed with `Uri.parse()`.
+pub fn set_primary_clip_uri<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    label: &str,
+    uri: &str,
+) -> Result<()> {
+    let resolver = env
+        .call_method(
+            context,
+            "getContentResolver",
+            "()Landroid/content/ContentResolver;",
+            &[],
+        )?
+        .l()?;
+    let label = env.new_string(label)?;
+    let uri = parse_uri(env, uri)?;
+    let clip = env
+        .call_static_method(
+            "android/content/ClipData",
+            "newUri",
+            "(Landroid/content/ContentResolver;Ljava/lang/CharSequence;Landroid/net/Uri;)Landroid/content/ClipData;",
+            &[resolver.into(), label.into(), uri.into()],
+        )?
+        .l()?;
+    set_primary_clip(env, context, clip)
+}
+
+/// Clear the primary clip with `ClipboardManager.clearPrimaryClip()`.
+/// Returns `false` without doing anything before API level 28.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ClipboardManager`
+///   from.
+pub fn clear_primary_clip<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<bool> {
+    if sdk_int(env)? < 28 {
+        return Ok(false);
+    }
+    let manager = clipboard_manager(env, context)?;
+    env.call_method(manager, "clearPrimaryClip", "()V", &[])?;
+    Ok(true)
+}
+
+/// Get a stream which yields each time the primary clip changes, using
+/// `ClipboardManager.addPrimaryClipChangedListener()`. Read the new clip
+/// with [`primary_clip`]. The listener is removed when the stream is
+/// dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `ClipboardManager`
+///   from.
+pub fn clip_changes<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<impl Stream<Item = Result<()>> + Send> {
+    let manager = clipboard_manager(env, context)?;
+    let interface =
+        env.find_class("android/content/ClipboardManager$OnPrimaryClipChangedListener")?;
+    let (listener, stream) = listener_stream(env, interface)?;
+    env.call_method(
+        manager,
+        "addPrimaryClipChangedListener",
+        "(Landroid/content/ClipboardManager$OnPrimaryClipChangedListener;)V",
+        &[listener.into()],
+    )?;
+    let guard = RemoveListenerOnDrop::new(
+        env,
+        manager,
+        listener,
+        "removePrimaryClipChangedListener",
+        "(Landroid/content/ClipboardManager$OnPrimaryClipChangedListener;)V",
+    )?;
+
+    Ok(stream.map(move |call| {
+        let _ = &guard;
+        call.map(|_| ())
+    }))
+}
@@ -0,0 +1,280 @@
+use crate::util::sdk_int;
+use jni::{
+    errors::Result,
+    objects::{JMethodID, JObject},
+    signature::{JavaType, Primitive},
+    sys::jint,
+    JNIEnv,
+};
+
+/// `android.content.pm.PackageManager.PERMISSION_GRANTED`.
+pub const PERMISSION_GRANTED: jint = 0;
+
+/// Wrapper for [`JObject`]s that contain `android.content.Context`. Provides
+/// methods to start and bind to services, start activities, send broadcasts,
+/// and get system services.
+///
+/// Uses the method IDs cached by [`init()`](crate::init), or looks them up on
+/// creation if it has not been called, rather than for every method call.
+pub struct JContext<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    start_service: JMethodID<'a>,
+    stop_service: JMethodID<'a>,
+    bind_service: JMethodID<'a>,
+    start_activity: JMethodID<'a>,
+    send_broadcast: JMethodID<'a>,
+    get_system_service: JMethodID<'a>,
+    get_application_context: JMethodID<'a>,
+    check_self_permission: JMethodID<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JContext<'a, 'b> {
+    /// Create a [`JContext`] from the environment and an object. This gets
+    /// the necessary method IDs to call all of the methods on it so that extra
+    /// work doesn't need to be done on every method call.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        let ids = crate::cache::context_ids(env)?;
+        Ok(Self {
+            internal: obj,
+            start_service: ids.start_service.get(),
+            stop_service: ids.stop_service.get(),
+            bind_service: ids.bind_service.get(),
+            start_activity: ids.start_activity.get(),
+            send_broadcast: ids.send_broadcast.get(),
+            get_system_service: ids.get_system_service.get(),
+            get_application_context: ids.get_application_context.get(),
+            check_self_permission: ids.check_self_permission.get(),
+            env,
+        })
+    }
+
+    /// Start a service with `Context.startService()`. Returns the
+    /// `android.content.ComponentName` of the service, or null if it doesn't
+    /// exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `intent` - `android.content.Intent` identifying the service.
+    pub fn start_service(&self, intent: JObject<'a>) -> Result<JObject<'a>> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.start_service,
+                JavaType::Object("android/content/ComponentName".into()),
+                &[intent.into()],
+            )?
+            .l()
+    }
+
+    /// Stop a service with `Context.stopService()`. Returns whether the
+    /// service was running.
+    ///
+    /// # Arguments
+    ///
+    /// * `intent` - `android.content.Intent` identifying the service.
+    pub fn stop_service(&self, intent: JObject<'a>) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.stop_service,
+                JavaType::Primitive(Primitive::Boolean),
+                &[intent.into()],
+            )?
+            .z()
+    }
+
+    /// Bind to a service with `Context.bindService()`. Returns whether the
+    /// binding was made. See [`bind_service`](crate::service::bind_service).
+    ///
+    /// # Arguments
+    ///
+    /// * `intent` - `android.content.Intent` identifying the service.
+    /// * `conn` - `android.content.ServiceConnection` to notify.
+    /// * `flags` - Combination of the `BIND_*` flags.
+    pub fn bind_service(
+        &self,
+        intent: JObject<'a>,
+        conn: JObject<'a>,
+        flags: jint,
+    ) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.bind_service,
+                JavaType::Primitive(Primitive::Boolean),
+                &[intent.into(), conn.into(), flags.into()],
+            )?
+            .z()
+    }
+
+    /// Register a broadcast receiver with `Context.registerReceiver()`.
+    /// Returns the current sticky intent matching the filter, if any, which
+    /// is null otherwise. See [`register_receiver`](super::register_receiver)
+    /// for a version which unregisters the receiver when dropped.
+    ///
+    /// The `RECEIVER_*` flags are only passed on API level 26 and above, and
+    /// [`RECEIVER_EXPORTED`](super::RECEIVER_EXPORTED) and
+    /// [`RECEIVER_NOT_EXPORTED`](super::RECEIVER_NOT_EXPORTED) only on API
+    /// level 33 and above, where one of them is required for receivers of
+    /// non-system broadcasts.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` - `android.content.BroadcastReceiver` to register.
+    /// * `filter` - `android.content.IntentFilter` of broadcasts to receive.
+    /// * `flags` - Combination of the `RECEIVER_*` flags.
+    pub fn register_receiver(
+        &self,
+        receiver: JObject<'a>,
+        filter: JObject<'a>,
+        flags: jint,
+    ) -> Result<JObject<'a>> {
+        let sdk_int = sdk_int(self.env)?;
+        let flags = if sdk_int >= 33 {
+            flags
+        } else {
+            flags & !(super::RECEIVER_EXPORTED | super::RECEIVER_NOT_EXPORTED)
+        };
+        if sdk_int >= 26 {
+            self.env.call_method(
+                self.internal,
+                "registerReceiver",
+                "(Landroid/content/BroadcastReceiver;Landroid/content/IntentFilter;I)Landroid/content/Intent;",
+                &[receiver.into(), filter.into(), flags.into()],
+            )
+        } else {
+            self.env.call_method(
+                self.internal,
+                "registerReceiver",
+                "(Landroid/content/BroadcastReceiver;Landroid/content/IntentFilter;)Landroid/content/Intent;",
+                &[receiver.into(), filter.into()],
+            )
+        }?
+        .l()
+    }
+
+    /// Unregister a broadcast receiver with `Context.unregisterReceiver()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` - `android.content.BroadcastReceiver` to unregister.
+    pub fn unregister_receiver(&self, receiver: JObject<'a>) -> Result<()> {
+        self.env.call_method(
+            self.internal,
+            "unregisterReceiver",
+            "(Landroid/content/BroadcastReceiver;)V",
+            &[receiver.into()],
+        )?;
+        Ok(())
+    }
+
+    /// Start an activity with `Context.startActivity()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `intent` - `android.content.Intent` identifying the activity.
+    pub fn start_activity(&self, intent: JObject<'a>) -> Result<()> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.start_activity,
+                JavaType::Primitive(Primitive::Void),
+                &[intent.into()],
+            )?
+            .v()
+    }
+
+    /// Send a broadcast with `Context.sendBroadcast()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `intent` - `android.content.Intent` to broadcast.
+    pub fn send_broadcast(&self, intent: JObject<'a>) -> Result<()> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.send_broadcast,
+                JavaType::Primitive(Primitive::Void),
+                &[intent.into()],
+            )?
+            .v()
+    }
+
+    /// Get a system service with `Context.getSystemService()`, or null if
+    /// there is no such service.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the service, such as `"power"`.
+    pub fn system_service(&self, name: &str) -> Result<JObject<'a>> {
+        let name = self.env.new_string(name)?;
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.get_system_service,
+                JavaType::Object("java/lang/Object".into()),
+                &[name.into()],
+            )?
+            .l()
+    }
+
+    /// Get the application's `Context` with
+    /// `Context.getApplicationContext()`, which lives as long as the process
+    /// and can be kept without leaking an activity or service.
+    pub fn application_context(&self) -> Result<Self> {
+        let obj = self
+            .env
+            .call_method_unchecked(
+                self.internal,
+                self.get_application_context,
+                JavaType::Object("android/content/Context".into()),
+                &[],
+            )?
+            .l()?;
+        Ok(Self {
+            internal: obj,
+            ..*self
+        })
+    }
+
+    /// Check whether the app has been granted a permission with
+    /// `Context.checkSelfPermission()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `permission` - Name of the permission, such as
+    ///   `"android.permission.CAMERA"`.
+    pub fn check_self_permission(&self, permission: &str) -> Result<bool> {
+        let permission = self.env.new_string(permission)?;
+        let result = self
+            .env
+            .call_method_unchecked(
+                self.internal,
+                self.check_self_permission,
+                JavaType::Primitive(Primitive::Int),
+                &[permission.into()],
+            )?
+            .i()?;
+        Ok(result == PERMISSION_GRANTED)
+    }
+}
+
+impl<'a: 'b, 'b> From<JContext<'a, 'b>> for JObject<'a> {
+    fn from(context: JContext<'a, 'b>) -> Self {
+        context.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JContext<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
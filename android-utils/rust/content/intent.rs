@@ -0,0 +1,422 @@
+use crate::os::bundle::{
+    bundle_to_map, map_to_bundle, object_from_value, value_from_object, BundleValue, JBundle,
+};
+use jni::{
+    errors::Result,
+    objects::{JObject, JString, JValue},
+    sys::{jint, jlong, jsize},
+    JNIEnv,
+};
+use std::{collections::HashMap, ops::Deref};
+
+/// Wrapper for [`JObject`]s that contain `android.content.Intent`. Intents
+/// can be built by chaining the `with_*` methods, and received intents read
+/// with the accessors:
+///
+/// ```no_run
+/// # use android_utils::{content::JIntent, os::bundle::BundleValue};
+/// # fn example(env: &jni::JNIEnv) -> jni::errors::Result<()> {
+/// let intent = JIntent::new(env)?
+///     .with_action("com.example.SYNC")?
+///     .with_component("com.example", "com.example.SyncService")?
+///     .with_extra("attempt", &BundleValue::I32(1))?;
+/// assert_eq!(intent.action()?.as_deref(), Some("com.example.SYNC"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct JIntent<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JIntent<'a, 'b> {
+    /// Create a [`JIntent`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Create a new, empty `Intent`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn new(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env.new_object("android/content/Intent", "()V", &[])?;
+        Self::from_env(env, obj)
+    }
+
+    fn call(&self, method: &str, sig: &str, args: &[JValue<'a>]) -> Result<()> {
+        self.env.call_method(self.internal, method, sig, args)?;
+        Ok(())
+    }
+
+    fn get_string(&self, method: &str) -> Result<Option<String>> {
+        let s = self
+            .env
+            .call_method(self.internal, method, "()Ljava/lang/String;", &[])?
+            .l()?;
+        string(self.env, s)
+    }
+
+    /// Set the action with `Intent.setAction()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Action to set, such as `"android.intent.action.VIEW"`.
+    pub fn with_action(self, action: &str) -> Result<Self> {
+        let action = self.env.new_string(action)?;
+        self.call(
+            "setAction",
+            "(Ljava/lang/String;)Landroid/content/Intent;",
+            &[action.into()],
+        )?;
+        Ok(self)
+    }
+
+    /// Set the data URI with `Intent.setData()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - URI to set, parsed with `Uri.parse()`.
+    pub fn with_data(self, uri: &str) -> Result<Self> {
+        let uri = self.env.new_string(uri)?;
+        let uri = self
+            .env
+            .call_static_method(
+                "android/net/Uri",
+                "parse",
+                "(Ljava/lang/String;)Landroid/net/Uri;",
+                &[uri.into()],
+            )?
+            .l()?;
+        self.call(
+            "setData",
+            "(Landroid/net/Uri;)Landroid/content/Intent;",
+            &[uri.into()],
+        )?;
+        Ok(self)
+    }
+
+    /// Make this an explicit intent for a component with
+    /// `Intent.setClassName()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - Package which contains the component.
+    /// * `class` - Fully qualified class name of the component.
+    pub fn with_component(self, package: &str, class: &str) -> Result<Self> {
+        let package = self.env.new_string(package)?;
+        let class = self.env.new_string(class)?;
+        self.call(
+            "setClassName",
+            "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/Intent;",
+            &[package.into(), class.into()],
+        )?;
+        Ok(self)
+    }
+
+    /// Add a category with `Intent.addCategory()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `category` - Category to add, such as
+    ///   `"android.intent.category.DEFAULT"`.
+    pub fn with_category(self, category: &str) -> Result<Self> {
+        let category = self.env.new_string(category)?;
+        self.call(
+            "addCategory",
+            "(Ljava/lang/String;)Landroid/content/Intent;",
+            &[category.into()],
+        )?;
+        Ok(self)
+    }
+
+    /// Add flags with `Intent.addFlags()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `flags` - Combination of the `FLAG_*` flags, such as
+    ///   [`FLAG_ACTIVITY_NEW_TASK`](super::FLAG_ACTIVITY_NEW_TASK).
+    pub fn with_flags(self, flags: jint) -> Result<Self> {
+        self.call("addFlags", "(I)Landroid/content/Intent;", &[flags.into()])?;
+        Ok(self)
+    }
+
+    /// Put an extra with the `Intent.putExtra()` overload for its type.
+    /// Lists are put as `java.util.ArrayList`s of boxed values, and maps as
+    /// nested `android.os.Bundle`s.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn with_extra(self, key: &str, value: &BundleValue) -> Result<Self> {
+        let env = self.env;
+        let key = env.new_string(key)?;
+        let (sig, value): (&str, JValue) = match value {
+            BundleValue::Null => ("Ljava/lang/String;", JObject::null().into()),
+            BundleValue::Bool(b) => ("Z", (*b).into()),
+            BundleValue::I32(i) => ("I", (*i as jint).into()),
+            BundleValue::I64(l) => ("J", (*l as jlong).into()),
+            BundleValue::F64(d) => ("D", (*d).into()),
+            BundleValue::String(s) => ("Ljava/lang/String;", env.new_string(s)?.into()),
+            BundleValue::Bytes(b) => ("[B", JObject::from(env.byte_array_from_slice(b)?).into()),
+            BundleValue::List(_) => (
+                "Ljava/io/Serializable;",
+                object_from_value(env, value)?.into(),
+            ),
+            BundleValue::Map(map) => ("Landroid/os/Bundle;", map_to_bundle(env, map)?.into()),
+        };
+        self.call(
+            "putExtra",
+            &format!("(Ljava/lang/String;{})Landroid/content/Intent;", sig),
+            &[key.into(), value],
+        )?;
+        Ok(self)
+    }
+
+    /// Copy all values of a [`JBundle`] into the extras with
+    /// `Intent.putExtras()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `extras` - `Bundle` to copy the extras from.
+    pub fn with_extras(self, extras: &JBundle<'a, '_>) -> Result<Self> {
+        self.call(
+            "putExtras",
+            "(Landroid/os/Bundle;)Landroid/content/Intent;",
+            &[(**extras).into()],
+        )?;
+        Ok(self)
+    }
+
+    /// Get the action with `Intent.getAction()`.
+    pub fn action(&self) -> Result<Option<String>> {
+        self.get_string("getAction")
+    }
+
+    /// Get the data URI with `Intent.getDataString()`.
+    pub fn data(&self) -> Result<Option<String>> {
+        self.get_string("getDataString")
+    }
+
+    /// Get the package and class name of the component with
+    /// `Intent.getComponent()`, if this is an explicit intent.
+    pub fn component(&self) -> Result<Option<(String, String)>> {
+        let component = self
+            .env
+            .call_method(
+                self.internal,
+                "getComponent",
+                "()Landroid/content/ComponentName;",
+                &[],
+            )?
+            .l()?;
+        if component.is_null() {
+            return Ok(None);
+        }
+        let package = self
+            .env
+            .call_method(component, "getPackageName", "()Ljava/lang/String;", &[])?
+            .l()?;
+        let class = self
+            .env
+            .call_method(component, "getClassName", "()Ljava/lang/String;", &[])?
+            .l()?;
+        Ok(string(self.env, package)?.zip(string(self.env, class)?))
+    }
+
+    /// Get the categories with `Intent.getCategories()`.
+    pub fn categories(&self) -> Result<Vec<String>> {
+        let categories = self
+            .env
+            .call_method(self.internal, "getCategories", "()Ljava/util/Set;", &[])?
+            .l()?;
+        if categories.is_null() {
+            return Ok(Vec::new());
+        }
+        let array = self
+            .env
+            .call_method(categories, "toArray", "()[Ljava/lang/Object;", &[])?
+            .l()?
+            .into_inner();
+        let len = self.env.get_array_length(array)?;
+        let mut result = Vec::with_capacity(len as usize);
+        for i in 0..len as jsize {
+            let category = self.env.get_object_array_element(array, i)?;
+            result.extend(string(self.env, category)?);
+            self.env.delete_local_ref(category)?;
+        }
+        Ok(result)
+    }
+
+    /// Get the flags with `Intent.getFlags()`.
+    pub fn flags(&self) -> Result<jint> {
+        self.env
+            .call_method(self.internal, "getFlags", "()I", &[])?
+            .i()
+    }
+
+    /// Get the extras with `Intent.getExtras()`, converted with
+    /// [`bundle_to_map`]. Returns an empty map if there are no extras.
+    pub fn extras(&self) -> Result<HashMap<String, BundleValue>> {
+        let extras = self.extras_bundle()?;
+        if extras.is_null() {
+            return Ok(HashMap::new());
+        }
+        bundle_to_map(self.env, extras)
+    }
+
+    /// Get the extras as a [`JBundle`] with `Intent.getExtras()`, or
+    /// [`None`] if there are no extras. The `Bundle` is a copy, so changes to
+    /// it do not affect the intent.
+    pub fn bundle_extras(&self) -> Result<Option<JBundle<'a, 'b>>> {
+        let extras = self.extras_bundle()?;
+        if extras.is_null() {
+            return Ok(None);
+        }
+        JBundle::from_env(self.env, extras).map(Some)
+    }
+
+    fn extras_bundle(&self) -> Result<JObject<'a>> {
+        self.env
+            .call_method(self.internal, "getExtras", "()Landroid/os/Bundle;", &[])?
+            .l()
+    }
+
+    /// Get a single extra, or [`None`] if there is no extra with the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the extra.
+    pub fn extra(&self, key: &str) -> Result<Option<BundleValue>> {
+        let extras = self.extras_bundle()?;
+        if extras.is_null() {
+            return Ok(None);
+        }
+        let key = self.env.new_string(key)?;
+        let contains = self
+            .env
+            .call_method(
+                extras,
+                "containsKey",
+                "(Ljava/lang/String;)Z",
+                &[key.into()],
+            )?
+            .z()?;
+        if !contains {
+            return Ok(None);
+        }
+        let value = self
+            .env
+            .call_method(
+                extras,
+                "get",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[key.into()],
+            )?
+            .l()?;
+        value_from_object(self.env, value).map(Some)
+    }
+
+    /// Get a string extra with `Intent.getStringExtra()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the extra.
+    pub fn string_extra(&self, key: &str) -> Result<Option<String>> {
+        let key = self.env.new_string(key)?;
+        let value = self
+            .env
+            .call_method(
+                self.internal,
+                "getStringExtra",
+                "(Ljava/lang/String;)Ljava/lang/String;",
+                &[key.into()],
+            )?
+            .l()?;
+        string(self.env, value)
+    }
+
+    /// Get an int extra with `Intent.getIntExtra()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the extra.
+    /// * `default` - Value to return if there is no int extra with the key.
+    pub fn int_extra(&self, key: &str, default: jint) -> Result<jint> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(
+                self.internal,
+                "getIntExtra",
+                "(Ljava/lang/String;I)I",
+                &[key.into(), default.into()],
+            )?
+            .i()
+    }
+
+    /// Get a long extra with `Intent.getLongExtra()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the extra.
+    /// * `default` - Value to return if there is no long extra with the key.
+    pub fn long_extra(&self, key: &str, default: jlong) -> Result<jlong> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(
+                self.internal,
+                "getLongExtra",
+                "(Ljava/lang/String;J)J",
+                &[key.into(), default.into()],
+            )?
+            .j()
+    }
+
+    /// Get a boolean extra with `Intent.getBooleanExtra()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the extra.
+    /// * `default` - Value to return if there is no boolean extra with the
+    ///   key.
+    pub fn bool_extra(&self, key: &str, default: bool) -> Result<bool> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(
+                self.internal,
+                "getBooleanExtra",
+                "(Ljava/lang/String;Z)Z",
+                &[key.into(), default.into()],
+            )?
+            .z()
+    }
+}
+
+fn string<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Option<String>> {
+    if obj.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(env.get_string(JString::from(obj))?.into()))
+    }
+}
+
+impl<'a: 'b, 'b> From<JIntent<'a, 'b>> for JObject<'a> {
+    fn from(intent: JIntent<'a, 'b>) -> Self {
+        intent.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JIntent<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
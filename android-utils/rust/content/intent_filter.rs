@@ -0,0 +1,158 @@
+use jni::{
+    errors::Result,
+    objects::{JObject, JString, JValue},
+    sys::jint,
+    JNIEnv,
+};
+use std::ops::Deref;
+
+/// Wrapper for [`JObject`]s that contain `android.content.IntentFilter`.
+/// Filters are built by chaining the `with_*` methods, and passed to
+/// [`register_receiver`](super::register_receiver):
+///
+/// ```no_run
+/// # use android_utils::content::{JIntentFilter, SYSTEM_HIGH_PRIORITY};
+/// # fn example(env: &jni::JNIEnv) -> jni::errors::Result<()> {
+/// let filter = JIntentFilter::new(env)?
+///     .with_action("android.intent.action.VIEW")?
+///     .with_category("android.intent.category.DEFAULT")?
+///     .with_data_scheme("https")?
+///     .with_priority(SYSTEM_HIGH_PRIORITY - 1)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct JIntentFilter<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JIntentFilter<'a, 'b> {
+    /// Create a [`JIntentFilter`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Create a new, empty `IntentFilter`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn new(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env.new_object("android/content/IntentFilter", "()V", &[])?;
+        Self::from_env(env, obj)
+    }
+
+    fn add_string(&self, method: &str, value: &str) -> Result<()> {
+        let value = self.env.new_string(value)?;
+        self.env.call_method(
+            self.internal,
+            method,
+            "(Ljava/lang/String;)V",
+            &[JValue::from(value)],
+        )?;
+        Ok(())
+    }
+
+    fn strings(&self, count: &str, get: &str) -> Result<Vec<String>> {
+        let len = self
+            .env
+            .call_method(self.internal, count, "()I", &[])?
+            .i()?;
+        let mut result = Vec::with_capacity(len as usize);
+        for i in 0..len {
+            let s = self
+                .env
+                .call_method(self.internal, get, "(I)Ljava/lang/String;", &[i.into()])?
+                .l()?;
+            result.push(self.env.get_string(JString::from(s))?.into());
+            self.env.delete_local_ref(s)?;
+        }
+        Ok(result)
+    }
+
+    /// Add an action to match with `IntentFilter.addAction()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Action to match, such as
+    ///   `"android.intent.action.BATTERY_CHANGED"`.
+    pub fn with_action(self, action: &str) -> Result<Self> {
+        self.add_string("addAction", action)?;
+        Ok(self)
+    }
+
+    /// Add a category to match with `IntentFilter.addCategory()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `category` - Category to match.
+    pub fn with_category(self, category: &str) -> Result<Self> {
+        self.add_string("addCategory", category)?;
+        Ok(self)
+    }
+
+    /// Add a data URI scheme to match with `IntentFilter.addDataScheme()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `scheme` - Scheme to match, such as `"package"` or `"https"`.
+    pub fn with_data_scheme(self, scheme: &str) -> Result<Self> {
+        self.add_string("addDataScheme", scheme)?;
+        Ok(self)
+    }
+
+    /// Set the priority with `IntentFilter.setPriority()`. Receivers with a
+    /// higher priority receive ordered broadcasts first.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority` - Priority between
+    ///   [`SYSTEM_LOW_PRIORITY`](super::SYSTEM_LOW_PRIORITY) and
+    ///   [`SYSTEM_HIGH_PRIORITY`](super::SYSTEM_HIGH_PRIORITY), exclusive.
+    pub fn with_priority(self, priority: jint) -> Result<Self> {
+        self.env
+            .call_method(self.internal, "setPriority", "(I)V", &[priority.into()])?;
+        Ok(self)
+    }
+
+    /// Get the actions with `IntentFilter.getAction()`.
+    pub fn actions(&self) -> Result<Vec<String>> {
+        self.strings("countActions", "getAction")
+    }
+
+    /// Get the categories with `IntentFilter.getCategory()`.
+    pub fn categories(&self) -> Result<Vec<String>> {
+        self.strings("countCategories", "getCategory")
+    }
+
+    /// Get the data URI schemes with `IntentFilter.getDataScheme()`.
+    pub fn data_schemes(&self) -> Result<Vec<String>> {
+        self.strings("countDataSchemes", "getDataScheme")
+    }
+
+    /// Get the priority with `IntentFilter.getPriority()`.
+    pub fn priority(&self) -> Result<jint> {
+        self.env
+            .call_method(self.internal, "getPriority", "()I", &[])?
+            .i()
+    }
+}
+
+impl<'a: 'b, 'b> From<JIntentFilter<'a, 'b>> for JObject<'a> {
+    fn from(filter: JIntentFilter<'a, 'b>) -> Self {
+        filter.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JIntentFilter<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,394 @@
+//! Queries of `android.content.pm.PackageManager`, for detecting the
+//! capabilities of the device and of other installed packages from Rust.
+//!
+//! Since API level 30, only packages declared in the `<queries>` element of
+//! the manifest, or matching its intent filters, are visible to the app.
+//! Other packages are treated as not installed.
+
+use super::PERMISSION_GRANTED;
+use crate::util::sdk_int;
+use jni::{
+    errors::{Error, Result},
+    objects::{JObject, JString},
+    sys::{jint, jintArray, jlong, jobjectArray},
+    JNIEnv,
+};
+
+/// `android.content.pm.PackageManager.GET_ACTIVITIES`.
+pub const GET_ACTIVITIES: jint = 0x00000001;
+
+/// `android.content.pm.PackageManager.GET_SERVICES`.
+pub const GET_SERVICES: jint = 0x00000004;
+
+/// `android.content.pm.PackageManager.GET_META_DATA`.
+pub const GET_META_DATA: jint = 0x00000080;
+
+/// `android.content.pm.PackageManager.GET_PERMISSIONS`. Fills in
+/// [`PackageInfo::requested_permissions`].
+pub const GET_PERMISSIONS: jint = 0x00001000;
+
+/// `android.content.pm.PackageManager.MATCH_DEFAULT_ONLY`. Only resolve to
+/// components which have the `android.intent.category.DEFAULT` category.
+pub const MATCH_DEFAULT_ONLY: jint = 0x00010000;
+
+const REQUESTED_PERMISSION_GRANTED: jint = 2;
+
+/// Permission and whether it is granted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PermissionState {
+    /// Name of the permission, such as `android.permission.CAMERA`.
+    pub name: String,
+    /// Whether the permission is granted.
+    pub granted: bool,
+}
+
+/// Information about an installed package, from
+/// `android.content.pm.PackageInfo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageInfo {
+    /// `packageName`.
+    pub package_name: String,
+    /// `versionName`.
+    pub version_name: Option<String>,
+    /// `getLongVersionCode()`, or `versionCode` before API level 28.
+    pub version_code: i64,
+    /// `firstInstallTime`, in milliseconds since the epoch.
+    pub first_install_time: i64,
+    /// `lastUpdateTime`, in milliseconds since the epoch.
+    pub last_update_time: i64,
+    /// `requestedPermissions` and whether each is granted, from
+    /// `requestedPermissionsFlags`. Empty unless [`GET_PERMISSIONS`] was
+    /// passed.
+    pub requested_permissions: Vec<PermissionState>,
+}
+
+/// Component an intent resolves to, from
+/// `android.content.pm.ResolveInfo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolveInfo {
+    /// Package which contains the component.
+    pub package_name: String,
+    /// Class name of the component.
+    pub class_name: String,
+    /// `priority` of the matching intent filter.
+    pub priority: jint,
+    /// `isDefault`, whether the matching intent filter has the
+    /// `android.intent.category.DEFAULT` category.
+    pub is_default: bool,
+}
+
+fn package_manager<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<JObject<'a>> {
+    env.call_method(
+        context,
+        "getPackageManager",
+        "()Landroid/content/pm/PackageManager;",
+        &[],
+    )?
+    .l()
+}
+
+fn string<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Option<String>> {
+    if obj.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(env.get_string(JString::from(obj))?.into()))
+}
+
+fn string_field<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+    name: &str,
+) -> Result<Option<String>> {
+    string(env, env.get_field(obj, name, "Ljava/lang/String;")?.l()?)
+}
+
+fn package_info<'a: 'b, 'b>(env: &'b JNIEnv<'a>, info: JObject<'a>) -> Result<PackageInfo> {
+    let version_code = if sdk_int(env)? >= 28 {
+        env.call_method(info, "getLongVersionCode", "()J", &[])?
+            .j()?
+    } else {
+        env.get_field(info, "versionCode", "I")?.i()? as jlong
+    };
+
+    let mut requested_permissions = Vec::new();
+    let names = env
+        .get_field(info, "requestedPermissions", "[Ljava/lang/String;")?
+        .l()?;
+    if !names.is_null() {
+        let names = names.into_inner() as jobjectArray;
+        let flags = env
+            .get_field(info, "requestedPermissionsFlags", "[I")?
+            .l()?;
+        let len = env.get_array_length(names)?;
+        let mut flag_values = vec![0; len as usize];
+        if !flags.is_null() {
+            env.get_int_array_region(flags.into_inner() as jintArray, 0, &mut flag_values)?;
+        }
+        for (i, flags) in flag_values.into_iter().enumerate() {
+            let name = env.get_object_array_element(names, i as jint)?;
+            if let Some(name) = string(env, name)? {
+                requested_permissions.push(PermissionState {
+                    name,
+                    granted: flags & REQUESTED_PERMISSION_GRANTED != 0,
+                });
+            }
+            env.delete_local_ref(name)?;
+        }
+    }
+
+    Ok(PackageInfo {
+        package_name: string_field(env, info, "packageName")?.unwrap_or_default(),
+        version_name: string_field(env, info, "versionName")?,
+        version_code,
+        first_install_time: env.get_field(info, "firstInstallTime", "J")?.j()?,
+        last_update_time: env.get_field(info, "lastUpdateTime", "J")?.j()?,
+        requested_permissions,
+    })
+}
+
+/// Get information about an installed package with
+/// `PackageManager.getPackageInfo()`. Returns [`None`] if the package is not
+/// installed, or not visible to the app.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PackageManager` from.
+/// * `package_name` - Name of the package.
+/// * `flags` - Combination of the `GET_*` flags, such as
+///   [`GET_PERMISSIONS`].
+pub fn get_package_info<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    package_name: &str,
+    flags: jint,
+) -> Result<Option<PackageInfo>> {
+    let manager = package_manager(env, context)?;
+    let package_name = env.new_string(package_name)?;
+    let result = env.call_method(
+        manager,
+        "getPackageInfo",
+        "(Ljava/lang/String;I)Landroid/content/pm/PackageInfo;",
+        &[package_name.into(), flags.into()],
+    );
+    match result {
+        Ok(info) => package_info(env, info.l()?).map(Some),
+        Err(Error::JavaException) => {
+            let exception = env.exception_occurred()?;
+            env.exception_clear()?;
+            if !env.is_instance_of(
+                exception,
+                "android/content/pm/PackageManager$NameNotFoundException",
+            )? {
+                env.throw(exception)?;
+                return Err(Error::JavaException);
+            }
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Get information about all installed packages which are visible to the
+/// app with `PackageManager.getInstalledPackages()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PackageManager` from.
+/// * `flags` - Combination of the `GET_*` flags, such as
+///   [`GET_PERMISSIONS`].
+pub fn get_installed_packages<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    flags: jint,
+) -> Result<Vec<PackageInfo>> {
+    let manager = package_manager(env, context)?;
+    let list = env
+        .call_method(
+            manager,
+            "getInstalledPackages",
+            "(I)Ljava/util/List;",
+            &[flags.into()],
+        )?
+        .l()?;
+    let size = env.call_method(list, "size", "()I", &[])?.i()?;
+    let mut result = Vec::with_capacity(size as usize);
+    for i in 0..size {
+        let info = env
+            .call_method(list, "get", "(I)Ljava/lang/Object;", &[i.into()])?
+            .l()?;
+        result.push(package_info(env, info)?);
+        env.delete_local_ref(info)?;
+    }
+    Ok(result)
+}
+
+fn resolve<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    method: &str,
+    field: &str,
+    field_ty: &str,
+    intent: JObject<'a>,
+    flags: jint,
+) -> Result<Option<ResolveInfo>> {
+    let manager = package_manager(env, context)?;
+    let info = env
+        .call_method(
+            manager,
+            method,
+            "(Landroid/content/Intent;I)Landroid/content/pm/ResolveInfo;",
+            &[intent.into(), flags.into()],
+        )?
+        .l()?;
+    if info.is_null() {
+        return Ok(None);
+    }
+    let component = env.get_field(info, field, field_ty)?.l()?;
+    if component.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(ResolveInfo {
+        package_name: string_field(env, component, "packageName")?.unwrap_or_default(),
+        class_name: string_field(env, component, "name")?.unwrap_or_default(),
+        priority: env.get_field(info, "priority", "I")?.i()?,
+        is_default: env.get_field(info, "isDefault", "Z")?.z()?,
+    }))
+}
+
+/// Find the activity an `android.content.Intent` would start with
+/// `PackageManager.resolveActivity()`. Returns [`None`] if no activity
+/// matches. If several activities match and the user has not chosen a
+/// default, this is the system's chooser activity.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PackageManager` from.
+/// * `intent` - Intent to resolve.
+/// * `flags` - Combination of flags such as [`MATCH_DEFAULT_ONLY`].
+pub fn resolve_activity<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    intent: JObject<'a>,
+    flags: jint,
+) -> Result<Option<ResolveInfo>> {
+    resolve(
+        env,
+        context,
+        "resolveActivity",
+        "activityInfo",
+        "Landroid/content/pm/ActivityInfo;",
+        intent,
+        flags,
+    )
+}
+
+/// Find the service an `android.content.Intent` would start with
+/// `PackageManager.resolveService()`. Returns [`None`] if no service
+/// matches.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PackageManager` from.
+/// * `intent` - Intent to resolve.
+/// * `flags` - Additional option flags.
+pub fn resolve_service<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    intent: JObject<'a>,
+    flags: jint,
+) -> Result<Option<ResolveInfo>> {
+    resolve(
+        env,
+        context,
+        "resolveService",
+        "serviceInfo",
+        "Landroid/content/pm/ServiceInfo;",
+        intent,
+        flags,
+    )
+}
+
+/// Check whether the device has a feature with
+/// `PackageManager.hasSystemFeature()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PackageManager` from.
+/// * `name` - Name of the feature, such as
+///   `"android.hardware.camera.any"`.
+pub fn has_system_feature<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    name: &str,
+) -> Result<bool> {
+    let manager = package_manager(env, context)?;
+    let name = env.new_string(name)?;
+    env.call_method(
+        manager,
+        "hasSystemFeature",
+        "(Ljava/lang/String;)Z",
+        &[name.into()],
+    )?
+    .z()
+}
+
+/// Check whether a package has been granted a permission with
+/// `PackageManager.checkPermission()`. To check the app itself, use
+/// [`JContext::check_self_permission`](super::JContext::check_self_permission).
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PackageManager` from.
+/// * `permission` - Name of the permission.
+/// * `package_name` - Name of the package.
+pub fn check_permission<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    permission: &str,
+    package_name: &str,
+) -> Result<bool> {
+    let manager = package_manager(env, context)?;
+    let permission = env.new_string(permission)?;
+    let package_name = env.new_string(package_name)?;
+    let result = env
+        .call_method(
+            manager,
+            "checkPermission",
+            "(Ljava/lang/String;Ljava/lang/String;)I",
+            &[permission.into(), package_name.into()],
+        )?
+        .i()?;
+    Ok(result == PERMISSION_GRANTED)
+}
+
+/// Check whether a package has been granted each of a set of permissions,
+/// with [`check_permission`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PackageManager` from.
+/// * `permissions` - Names of the permissions.
+/// * `package_name` - Name of the package.
+pub fn check_permissions<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    permissions: &[&str],
+    package_name: &str,
+) -> Result<Vec<PermissionState>> {
+    permissions
+        .iter()
+        .map(|permission| {
+            Ok(PermissionState {
+                name: permission.to_string(),
+                granted: check_permission(env, context, permission, package_name)?,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,291 @@
+use jni::{
+    errors::Result,
+    objects::{JList, JObject, JString},
+    JNIEnv,
+};
+use std::collections::HashMap;
+
+/// Value of a parameter captured from the path of a deep link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RouteValue {
+    /// Parameter declared as `{name}`.
+    Str(String),
+    /// Parameter declared as `{name:int}`.
+    Int(i64),
+}
+
+/// Parameters of a deep link matched by a [`Router`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RouteParams {
+    params: HashMap<String, RouteValue>,
+    query: HashMap<String, String>,
+}
+
+impl RouteParams {
+    /// Get a path parameter.
+    pub fn get(&self, name: &str) -> Option<&RouteValue> {
+        self.params.get(name)
+    }
+
+    /// Get a path parameter declared as `{name}`.
+    pub fn str(&self, name: &str) -> Option<&str> {
+        match self.params.get(name) {
+            Some(RouteValue::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get a path parameter declared as `{name:int}`.
+    pub fn int(&self, name: &str) -> Option<i64> {
+        match self.params.get(name) {
+            Some(RouteValue::Int(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Get a query parameter of the deep link.
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query.get(name).map(String::as_str)
+    }
+}
+
+enum Segment {
+    Literal(String),
+    Str(String),
+    Int(String),
+}
+
+impl Segment {
+    fn parse(segment: &str) -> Self {
+        match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(param) => match param.split_once(':') {
+                Some((name, "int")) => Self::Int(name.to_string()),
+                Some((_, ty)) => panic!("Unknown route parameter type: {}", ty),
+                None => Self::Str(param.to_string()),
+            },
+            None => Self::Literal(segment.to_string()),
+        }
+    }
+}
+
+type Handler =
+    Box<dyn for<'a, 'b> Fn(&'b JNIEnv<'a>, JObject<'a>, &RouteParams) -> Result<()> + Send + Sync>;
+
+struct Route {
+    scheme: Option<String>,
+    host: Option<String>,
+    path: Vec<Segment>,
+    handler: Handler,
+}
+
+impl Route {
+    fn matches(&self, uri: &Uri) -> Option<HashMap<String, RouteValue>> {
+        fn component(pattern: &Option<String>, value: &Option<String>) -> bool {
+            match (pattern, value) {
+                (None, _) => true,
+                (Some(pattern), Some(value)) => pattern.eq_ignore_ascii_case(value),
+                (Some(_), None) => false,
+            }
+        }
+
+        if !component(&self.scheme, &uri.scheme)
+            || !component(&self.host, &uri.host)
+            || self.path.len() != uri.path.len()
+        {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (segment, value) in self.path.iter().zip(&uri.path) {
+            match segment {
+                Segment::Literal(literal) if literal == value => {}
+                Segment::Literal(_) => return None,
+                Segment::Str(name) => {
+                    params.insert(name.clone(), RouteValue::Str(value.clone()));
+                }
+                Segment::Int(name) => {
+                    params.insert(name.clone(), RouteValue::Int(value.parse().ok()?));
+                }
+            }
+        }
+        Some(params)
+    }
+}
+
+struct Uri {
+    scheme: Option<String>,
+    host: Option<String>,
+    path: Vec<String>,
+    query: HashMap<String, String>,
+}
+
+impl Uri {
+    fn from_env<'a: 'b, 'b>(env: &'b JNIEnv<'a>, uri: JObject<'a>) -> Result<Self> {
+        let string = |method: &str| -> Result<Option<String>> {
+            let s = env
+                .call_method(uri, method, "()Ljava/lang/String;", &[])?
+                .l()?;
+            Ok(if s.is_null() {
+                None
+            } else {
+                Some(env.get_string(JString::from(s))?.into())
+            })
+        };
+        let scheme = string("getScheme")?;
+        let host = string("getHost")?;
+
+        let segments = env
+            .call_method(uri, "getPathSegments", "()Ljava/util/List;", &[])?
+            .l()?;
+        let mut path = Vec::new();
+        for segment in JList::from_env(env, segments)?.iter()? {
+            path.push(env.get_string(JString::from(segment))?.into());
+            env.delete_local_ref(segment)?;
+        }
+
+        let mut query = HashMap::new();
+        let opaque = env.call_method(uri, "isOpaque", "()Z", &[])?.z()?;
+        if !opaque {
+            let names = env
+                .call_method(uri, "getQueryParameterNames", "()Ljava/util/Set;", &[])?
+                .l()?;
+            let iter = env
+                .call_method(names, "iterator", "()Ljava/util/Iterator;", &[])?
+                .l()?;
+            while env.call_method(iter, "hasNext", "()Z", &[])?.z()? {
+                let name = env
+                    .call_method(iter, "next", "()Ljava/lang/Object;", &[])?
+                    .l()?;
+                let value = env
+                    .call_method(
+                        uri,
+                        "getQueryParameter",
+                        "(Ljava/lang/String;)Ljava/lang/String;",
+                        &[name.into()],
+                    )?
+                    .l()?;
+                if !value.is_null() {
+                    query.insert(
+                        env.get_string(JString::from(name))?.into(),
+                        env.get_string(JString::from(value))?.into(),
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            scheme,
+            host,
+            path,
+            query,
+        })
+    }
+}
+
+/// Table of deep link routes, dispatching incoming `android.content.Intent`s
+/// to Rust handlers based on their data URI.
+///
+/// Routes are patterns of the form `scheme://host/path`, where the scheme
+/// and host may be `*` to match anything, and each path segment is either a
+/// literal, `{name}` to capture it as a string, or `{name:int}` to capture
+/// it as an integer. Schemes and hosts are matched case-insensitively, and
+/// path segments must all match. Routes are tried in the order they were
+/// added.
+///
+/// # Example
+///
+/// ```no_run
+/// # use android_utils::content::router::Router;
+/// let router = Router::new()
+///     .route("myapp://users/{id:int}", |_env, _intent, params| {
+///         let _id = params.int("id");
+///         Ok(())
+///     })
+///     .route("https://example.com/posts/{slug}", |_env, _intent, params| {
+///         let _slug = params.str("slug");
+///         Ok(())
+///     });
+/// ```
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    /// Create an empty [`Router`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a route.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Pattern to match the data URI of the intent against.
+    /// * `handler` - Handler to call with the intent and the captured
+    ///   parameters when the pattern matches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern has no scheme or uses an unknown parameter type.
+    pub fn route(
+        mut self,
+        pattern: &str,
+        handler: impl for<'a, 'b> Fn(&'b JNIEnv<'a>, JObject<'a>, &RouteParams) -> Result<()>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        let (scheme, rest) = pattern
+            .split_once("://")
+            .unwrap_or_else(|| panic!("Route pattern has no scheme: {}", pattern));
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let wildcard = |s: &str| match s {
+            "*" => None,
+            s => Some(s.to_string()),
+        };
+
+        self.routes.push(Route {
+            scheme: wildcard(scheme),
+            host: wildcard(host),
+            path: path
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(Segment::parse)
+                .collect(),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Dispatch an intent to the first route matching its data URI. Returns
+    /// `false` if the intent has no data URI or no route matched, or the
+    /// result of the handler otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `intent` - `android.content.Intent` to dispatch, such as the one
+    ///   passed to `onCreate()` or `onNewIntent()`.
+    pub fn dispatch<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, intent: JObject<'a>) -> Result<bool> {
+        let data = env
+            .call_method(intent, "getData", "()Landroid/net/Uri;", &[])?
+            .l()?;
+        if data.is_null() {
+            return Ok(false);
+        }
+
+        let uri = Uri::from_env(env, data)?;
+        for route in &self.routes {
+            if let Some(params) = route.matches(&uri) {
+                let params = RouteParams {
+                    params,
+                    query: uri.query,
+                };
+                (route.handler)(env, intent, &params)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,463 @@
+use crate::{
+    ops::listener_stream,
+    os::bundle::{value_from_object, BundleValue},
+    util::RemoveListenerOnDrop,
+};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{JObject, JString, JValue},
+    sys::{jfloat, jint, jlong},
+    JNIEnv,
+};
+use std::{collections::HashMap, ops::Deref};
+
+/// `android.content.Context.MODE_PRIVATE`.
+pub const MODE_PRIVATE: jint = 0;
+
+fn string<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Option<String>> {
+    if obj.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(env.get_string(JString::from(obj))?.into()))
+}
+
+/// Wrapper for [`JObject`]s that contain
+/// `android.content.SharedPreferences`, with typed getters. Preferences are
+/// changed through [`edit`](Self::edit):
+///
+/// ```no_run
+/// # use android_utils::content::{JSharedPreferences, MODE_PRIVATE};
+/// # fn example(env: &jni::JNIEnv, context: jni::objects::JObject) -> jni::errors::Result<()> {
+/// let prefs = JSharedPreferences::new(env, context, "settings", MODE_PRIVATE)?;
+/// let launches = prefs.get_int("launches", 0)?;
+/// prefs
+///     .edit()?
+///     .put_int("launches", launches + 1)?
+///     .put_string("last_version", "1.2.0")?
+///     .apply()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct JSharedPreferences<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JSharedPreferences<'a, 'b> {
+    /// Create a [`JSharedPreferences`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Get a preferences file with `Context.getSharedPreferences()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `context` - `android.content.Context` to get the preferences from.
+    /// * `name` - Name of the preferences file.
+    /// * `mode` - Operating mode, normally [`MODE_PRIVATE`].
+    pub fn new(env: &'b JNIEnv<'a>, context: JObject<'a>, name: &str, mode: jint) -> Result<Self> {
+        let name = env.new_string(name)?;
+        let obj = env
+            .call_method(
+                context,
+                "getSharedPreferences",
+                "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
+                &[name.into(), mode.into()],
+            )?
+            .l()?;
+        Self::from_env(env, obj)
+    }
+
+    fn get(&self, method: &str, sig: &str, key: &str, default: JValue<'a>) -> Result<JValue<'a>> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(self.internal, method, sig, &[key.into(), default])
+    }
+
+    /// Get a string with `SharedPreferences.getString()`, or [`None`] if
+    /// there is no preference with the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    pub fn get_string(&self, key: &str) -> Result<Option<String>> {
+        let value = self
+            .get(
+                "getString",
+                "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+                key,
+                JObject::null().into(),
+            )?
+            .l()?;
+        string(self.env, value)
+    }
+
+    /// Get a string set with `SharedPreferences.getStringSet()`, or
+    /// [`None`] if there is no preference with the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    pub fn get_string_set(&self, key: &str) -> Result<Option<Vec<String>>> {
+        let value = self
+            .get(
+                "getStringSet",
+                "(Ljava/lang/String;Ljava/util/Set;)Ljava/util/Set;",
+                key,
+                JObject::null().into(),
+            )?
+            .l()?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        let array = self
+            .env
+            .call_method(value, "toArray", "()[Ljava/lang/Object;", &[])?
+            .l()?
+            .into_inner();
+        let len = self.env.get_array_length(array)?;
+        let mut result = Vec::with_capacity(len as usize);
+        for i in 0..len {
+            let s = self.env.get_object_array_element(array, i)?;
+            result.extend(string(self.env, s)?);
+            self.env.delete_local_ref(s)?;
+        }
+        Ok(Some(result))
+    }
+
+    /// Get an int with `SharedPreferences.getInt()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `default` - Value to return if there is no preference with the key.
+    pub fn get_int(&self, key: &str, default: jint) -> Result<jint> {
+        self.get("getInt", "(Ljava/lang/String;I)I", key, default.into())?
+            .i()
+    }
+
+    /// Get a long with `SharedPreferences.getLong()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `default` - Value to return if there is no preference with the key.
+    pub fn get_long(&self, key: &str, default: jlong) -> Result<jlong> {
+        self.get("getLong", "(Ljava/lang/String;J)J", key, default.into())?
+            .j()
+    }
+
+    /// Get a float with `SharedPreferences.getFloat()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `default` - Value to return if there is no preference with the key.
+    pub fn get_float(&self, key: &str, default: jfloat) -> Result<jfloat> {
+        self.get("getFloat", "(Ljava/lang/String;F)F", key, default.into())?
+            .f()
+    }
+
+    /// Get a boolean with `SharedPreferences.getBoolean()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `default` - Value to return if there is no preference with the key.
+    pub fn get_bool(&self, key: &str, default: bool) -> Result<bool> {
+        self.get("getBoolean", "(Ljava/lang/String;Z)Z", key, default.into())?
+            .z()
+    }
+
+    /// Check whether there is a preference with a key with
+    /// `SharedPreferences.contains()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to check.
+    pub fn contains(&self, key: &str) -> Result<bool> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(
+                self.internal,
+                "contains",
+                "(Ljava/lang/String;)Z",
+                &[key.into()],
+            )?
+            .z()
+    }
+
+    /// Get all preferences with `SharedPreferences.getAll()`. String sets
+    /// are converted to [`BundleValue::List`]s, and floats to
+    /// [`BundleValue::F64`]s.
+    pub fn all(&self) -> Result<HashMap<String, BundleValue>> {
+        let all = self
+            .env
+            .call_method(self.internal, "getAll", "()Ljava/util/Map;", &[])?
+            .l()?;
+        Ok(match value_from_object(self.env, all)? {
+            BundleValue::Map(map) => map,
+            _ => HashMap::new(),
+        })
+    }
+
+    /// Start changing preferences with `SharedPreferences.edit()`. The
+    /// changes are made when [`JSharedPreferencesEditor::apply`] or
+    /// [`JSharedPreferencesEditor::commit`] is called.
+    pub fn edit(&self) -> Result<JSharedPreferencesEditor<'a, 'b>> {
+        let editor = self
+            .env
+            .call_method(
+                self.internal,
+                "edit",
+                "()Landroid/content/SharedPreferences$Editor;",
+                &[],
+            )?
+            .l()?;
+        JSharedPreferencesEditor::from_env(self.env, editor)
+    }
+
+    /// Get a stream of the keys of preferences which are changed, using
+    /// `SharedPreferences.registerOnSharedPreferenceChangeListener()`. The
+    /// key is [`None`] when the preferences are cleared, on API level 30 and
+    /// above. The listener is unregistered when the stream is dropped.
+    ///
+    /// `SharedPreferences` only keeps a weak reference to its listeners, so
+    /// the stream keeps a strong reference to the listener for as long as it
+    /// is alive.
+    pub fn changes(&self) -> Result<impl Stream<Item = Result<Option<String>>> + Send> {
+        let interface = self
+            .env
+            .find_class("android/content/SharedPreferences$OnSharedPreferenceChangeListener")?;
+        let (listener, stream) = listener_stream(self.env, interface)?;
+        self.env.call_method(
+            self.internal,
+            "registerOnSharedPreferenceChangeListener",
+            "(Landroid/content/SharedPreferences$OnSharedPreferenceChangeListener;)V",
+            &[listener.into()],
+        )?;
+        let guard = RemoveListenerOnDrop::new(
+            self.env,
+            self.internal,
+            listener,
+            "unregisterOnSharedPreferenceChangeListener",
+            "(Landroid/content/SharedPreferences$OnSharedPreferenceChangeListener;)V",
+        )?;
+
+        let vm = self.env.get_java_vm()?;
+        Ok(stream.map(move |call| {
+            let _ = &guard;
+            let call = call?;
+            let env = vm.get_env()?;
+            string(&env, call.args[1].as_obj())
+        }))
+    }
+}
+
+impl<'a: 'b, 'b> From<JSharedPreferences<'a, 'b>> for JObject<'a> {
+    fn from(prefs: JSharedPreferences<'a, 'b>) -> Self {
+        prefs.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JSharedPreferences<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
+
+/// Wrapper for [`JObject`]s that contain
+/// `android.content.SharedPreferences.Editor`, obtained from
+/// [`JSharedPreferences::edit`]. Changes are chained with the `put_*`
+/// methods, and made all at once by [`apply`](Self::apply) or
+/// [`commit`](Self::commit).
+pub struct JSharedPreferencesEditor<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JSharedPreferencesEditor<'a, 'b> {
+    /// Create a [`JSharedPreferencesEditor`] from the environment and an
+    /// object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    fn put(self, method: &str, sig: &str, key: &str, value: JValue<'a>) -> Result<Self> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(self.internal, method, sig, &[key.into(), value])?;
+        Ok(self)
+    }
+
+    /// Set a string with `Editor.putString()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `value` - New value.
+    pub fn put_string(self, key: &str, value: &str) -> Result<Self> {
+        let value = self.env.new_string(value)?;
+        self.put(
+            "putString",
+            "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;",
+            key,
+            value.into(),
+        )
+    }
+
+    /// Set a string set with `Editor.putStringSet()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `value` - New value.
+    pub fn put_string_set(self, key: &str, value: &[&str]) -> Result<Self> {
+        let set = self.env.new_object("java/util/HashSet", "()V", &[])?;
+        for s in value {
+            let s = self.env.new_string(s)?;
+            self.env
+                .call_method(set, "add", "(Ljava/lang/Object;)Z", &[s.into()])?;
+            self.env.delete_local_ref(*s)?;
+        }
+        self.put(
+            "putStringSet",
+            "(Ljava/lang/String;Ljava/util/Set;)Landroid/content/SharedPreferences$Editor;",
+            key,
+            set.into(),
+        )
+    }
+
+    /// Set an int with `Editor.putInt()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `value` - New value.
+    pub fn put_int(self, key: &str, value: jint) -> Result<Self> {
+        self.put(
+            "putInt",
+            "(Ljava/lang/String;I)Landroid/content/SharedPreferences$Editor;",
+            key,
+            value.into(),
+        )
+    }
+
+    /// Set a long with `Editor.putLong()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `value` - New value.
+    pub fn put_long(self, key: &str, value: jlong) -> Result<Self> {
+        self.put(
+            "putLong",
+            "(Ljava/lang/String;J)Landroid/content/SharedPreferences$Editor;",
+            key,
+            value.into(),
+        )
+    }
+
+    /// Set a float with `Editor.putFloat()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `value` - New value.
+    pub fn put_float(self, key: &str, value: jfloat) -> Result<Self> {
+        self.put(
+            "putFloat",
+            "(Ljava/lang/String;F)Landroid/content/SharedPreferences$Editor;",
+            key,
+            value.into(),
+        )
+    }
+
+    /// Set a boolean with `Editor.putBoolean()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    /// * `value` - New value.
+    pub fn put_bool(self, key: &str, value: bool) -> Result<Self> {
+        self.put(
+            "putBoolean",
+            "(Ljava/lang/String;Z)Landroid/content/SharedPreferences$Editor;",
+            key,
+            value.into(),
+        )
+    }
+
+    /// Remove a preference with `Editor.remove()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the preference.
+    pub fn remove(self, key: &str) -> Result<Self> {
+        let key = self.env.new_string(key)?;
+        self.env.call_method(
+            self.internal,
+            "remove",
+            "(Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;",
+            &[key.into()],
+        )?;
+        Ok(self)
+    }
+
+    /// Remove all preferences with `Editor.clear()`. The preferences are
+    /// cleared before any others are set, regardless of the order the
+    /// methods are called in.
+    pub fn clear(self) -> Result<Self> {
+        self.env.call_method(
+            self.internal,
+            "clear",
+            "()Landroid/content/SharedPreferences$Editor;",
+            &[],
+        )?;
+        Ok(self)
+    }
+
+    /// Make the changes with `Editor.apply()`, which changes the preferences
+    /// in memory immediately and writes them to disk in the background.
+    pub fn apply(self) -> Result<()> {
+        self.env.call_method(self.internal, "apply", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Make the changes with `Editor.commit()`, which writes them to disk
+    /// before returning. Returns whether they were written successfully.
+    pub fn commit(self) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "commit", "()Z", &[])?
+            .z()
+    }
+}
+
+impl<'a: 'b, 'b> From<JSharedPreferencesEditor<'a, 'b>> for JObject<'a> {
+    fn from(editor: JSharedPreferencesEditor<'a, 'b>) -> Self {
+        editor.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JSharedPreferencesEditor<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,142 @@
+//! Paths of the app's storage directories, as [`PathBuf`]s which can be
+//! used directly with [`std::fs`].
+//!
+//! Internal directories are private to the app and always available.
+//! External directories live on shared storage, which may be missing or
+//! unmounted, so their functions return [`None`] in that case.
+
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    JNIEnv,
+};
+use std::path::PathBuf;
+
+fn file_path<'a: 'b, 'b>(env: &'b JNIEnv<'a>, file: JObject<'a>) -> Result<Option<PathBuf>> {
+    if file.is_null() {
+        return Ok(None);
+    }
+    let path = env
+        .call_method(file, "getAbsolutePath", "()Ljava/lang/String;", &[])?
+        .l()?;
+    let path: String = env.get_string(JString::from(path))?.into();
+    Ok(Some(PathBuf::from(path)))
+}
+
+fn dir<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>, method: &str) -> Result<PathBuf> {
+    let file = env
+        .call_method(context, method, "()Ljava/io/File;", &[])?
+        .l()?;
+    Ok(file_path(env, file)?.unwrap_or_default())
+}
+
+fn external_dir<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    method: &str,
+) -> Result<Option<PathBuf>> {
+    let file = env
+        .call_method(context, method, "()Ljava/io/File;", &[])?
+        .l()?;
+    file_path(env, file)
+}
+
+/// Get `Context.getFilesDir()`, the directory for private files.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the directory of.
+pub fn files_dir<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<PathBuf> {
+    dir(env, context, "getFilesDir")
+}
+
+/// Get `Context.getCacheDir()`, the directory for private cache files,
+/// which the system may delete when storage is low.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the directory of.
+pub fn cache_dir<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<PathBuf> {
+    dir(env, context, "getCacheDir")
+}
+
+/// Get `Context.getCodeCacheDir()`, the directory for cached compiled code,
+/// which is cleared when the app is updated.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the directory of.
+pub fn code_cache_dir<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<PathBuf> {
+    dir(env, context, "getCodeCacheDir")
+}
+
+/// Get `Context.getNoBackupFilesDir()`, the directory for private files
+/// which are excluded from backups.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the directory of.
+pub fn no_backup_files_dir<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<PathBuf> {
+    dir(env, context, "getNoBackupFilesDir")
+}
+
+/// Get `Context.getExternalFilesDir()`, the app's directory on shared
+/// storage. Returns [`None`] if shared storage is not available.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the directory of.
+/// * `kind` - Subdirectory to get, such as
+///   `android.os.Environment.DIRECTORY_PICTURES`, or [`None`] for the root.
+pub fn external_files_dir<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    kind: Option<&str>,
+) -> Result<Option<PathBuf>> {
+    let kind = match kind {
+        Some(kind) => env.new_string(kind)?.into(),
+        None => JObject::null(),
+    };
+    let file = env
+        .call_method(
+            context,
+            "getExternalFilesDir",
+            "(Ljava/lang/String;)Ljava/io/File;",
+            &[kind.into()],
+        )?
+        .l()?;
+    file_path(env, file)
+}
+
+/// Get `Context.getExternalCacheDir()`, the app's cache directory on shared
+/// storage. Returns [`None`] if shared storage is not available.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the directory of.
+pub fn external_cache_dir<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<PathBuf>> {
+    external_dir(env, context, "getExternalCacheDir")
+}
+
+/// Get `Context.getObbDir()`, the directory for the app's expansion files.
+/// Returns [`None`] if shared storage is not available.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the directory of.
+pub fn obb_dir<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<Option<PathBuf>> {
+    external_dir(env, context, "getObbDir")
+}
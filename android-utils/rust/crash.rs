@@ -0,0 +1,401 @@
+use crate::content::storage::files_dir;
+use jni::{
+    errors::{Error, Result},
+    objects::JObject,
+    JNIEnv,
+};
+use once_cell::sync::OnceCell;
+use std::{
+    backtrace::Backtrace,
+    cell::UnsafeCell,
+    ffi::CStr,
+    fmt::{self, Write as _},
+    fs, io, mem,
+    os::{
+        raw::{c_char, c_int, c_void},
+        unix::ffi::OsStrExt,
+    },
+    panic,
+    path::PathBuf,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SIGNALS: [c_int; 6] = [
+    libc::SIGSEGV,
+    libc::SIGABRT,
+    libc::SIGBUS,
+    libc::SIGFPE,
+    libc::SIGILL,
+    libc::SIGTRAP,
+];
+
+const TAG: &str = "RustCrash";
+
+// Room for the longest file name the signal handler appends to the
+// directory: "/crash-<millis>-<tid>.txt" and a NUL.
+const FILE_NAME_LEN: usize = 64;
+const PATH_LEN: usize = libc::PATH_MAX as usize;
+const REPORT_LEN: usize = 4096;
+
+struct CrashState {
+    dir: PathBuf,
+    old_actions: Vec<(c_int, libc::sigaction)>,
+}
+
+// Buffers the signal handler formats into, since it may not allocate. Only
+// the thread which sets HANDLING uses them.
+struct SignalBuffers {
+    path: UnsafeCell<[u8; PATH_LEN]>,
+    report: UnsafeCell<[u8; REPORT_LEN]>,
+}
+
+unsafe impl Sync for SignalBuffers {}
+
+static STATE: OnceCell<CrashState> = OnceCell::new();
+static HANDLING: AtomicBool = AtomicBool::new(false);
+static BUFFERS: SignalBuffers = SignalBuffers {
+    path: UnsafeCell::new([0; PATH_LEN]),
+    report: UnsafeCell::new([0; REPORT_LEN]),
+};
+
+// Writes into a fixed buffer, cutting off whatever doesn't fit and keeping
+// room for a NUL. Formatting with core::fmt into it neither allocates nor
+// locks, so it can be used from the signal handler.
+struct FixedWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> FixedWriter<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        let len = bytes.len().min(self.buf.len() - 1 - self.len);
+        self.buf[self.len..self.len + len].copy_from_slice(&bytes[..len]);
+        self.len += len;
+    }
+
+    // Terminate the contents with a NUL.
+    fn as_c_str(&mut self) -> *const c_char {
+        self.buf[self.len] = 0;
+        self.buf.as_ptr() as *const c_char
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl fmt::Write for FixedWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+fn signal_name(signal: c_int) -> &'static str {
+    match signal {
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        _ => "?",
+    }
+}
+
+fn log_report(report: &str) {
+    #[cfg(target_os = "android")]
+    {
+        const ANDROID_LOG_FATAL: c_int = 7;
+        let tag = std::ffi::CString::new(TAG).unwrap();
+        for line in report.lines() {
+            if let Ok(line) = std::ffi::CString::new(line) {
+                unsafe {
+                    crate::log::__android_log_write(ANDROID_LOG_FATAL, tag.as_ptr(), line.as_ptr())
+                };
+            }
+        }
+    }
+    #[cfg(not(target_os = "android"))]
+    eprintln!("{}: {}", TAG, report);
+}
+
+// Log a report from the signal handler in a single call, without
+// allocating.
+fn log_report_raw(report: &mut FixedWriter) {
+    #[cfg(target_os = "android")]
+    unsafe {
+        const ANDROID_LOG_FATAL: c_int = 7;
+        crate::log::__android_log_write(
+            ANDROID_LOG_FATAL,
+            b"RustCrash\0".as_ptr() as *const c_char,
+            report.as_c_str(),
+        );
+    }
+    #[cfg(not(target_os = "android"))]
+    write_all(libc::STDERR_FILENO, report.as_bytes());
+}
+
+fn write_all(fd: c_int, mut buf: &[u8]) {
+    while !buf.is_empty() {
+        let written = unsafe { libc::write(fd, buf.as_ptr() as *const c_void, buf.len()) };
+        if written <= 0 {
+            break;
+        }
+        buf = &buf[written as usize..];
+    }
+}
+
+fn write_thread_name(out: &mut FixedWriter) {
+    let mut name = [0 as c_char; 16];
+    let result = unsafe { libc::prctl(libc::PR_GET_NAME, name.as_mut_ptr()) };
+    let name = unsafe { CStr::from_ptr(name.as_ptr()) };
+    match (result, std::str::from_utf8(name.to_bytes())) {
+        (0, Ok(name)) => out.push_bytes(name.as_bytes()),
+        _ => out.push_bytes(b"<unknown>"),
+    }
+}
+
+#[cfg(all(target_os = "android", target_arch = "aarch64"))]
+fn write_registers(report: &mut FixedWriter, context: *mut c_void) {
+    let context = unsafe { &*(context as *const libc::ucontext_t) };
+    let mcontext = &context.uc_mcontext;
+    for (i, reg) in mcontext.regs.iter().enumerate() {
+        let _ = write!(report, "x{:<2} {:016x}", i, reg);
+        report.push_bytes(if i % 4 == 3 { b"\n" } else { b" " });
+    }
+    let _ = writeln!(
+        report,
+        "\nsp  {:016x} pc  {:016x} pstate {:016x}",
+        mcontext.sp, mcontext.pc, mcontext.pstate
+    );
+}
+
+#[cfg(all(
+    any(target_os = "android", target_os = "linux"),
+    target_arch = "x86_64"
+))]
+fn write_registers(report: &mut FixedWriter, context: *mut c_void) {
+    let context = unsafe { &*(context as *const libc::ucontext_t) };
+    let gregs = &context.uc_mcontext.gregs;
+    let _ = writeln!(
+        report,
+        "rip {:016x} rsp {:016x}",
+        gregs[libc::REG_RIP as usize],
+        gregs[libc::REG_RSP as usize]
+    );
+}
+
+#[cfg(not(any(
+    all(target_os = "android", target_arch = "aarch64"),
+    all(
+        any(target_os = "android", target_os = "linux"),
+        target_arch = "x86_64"
+    )
+)))]
+fn write_registers(report: &mut FixedWriter, _context: *mut c_void) {
+    report.push_bytes(b"registers unavailable on this architecture\n");
+}
+
+// Everything called from here must be async-signal-safe: the signal may
+// have been raised inside malloc() or while a lock was held, so allocating
+// or locking could deadlock instead of reaching the previous handler.
+extern "C" fn handle_signal(signal: c_int, info: *mut libc::siginfo_t, context: *mut c_void) {
+    let state = match STATE.get() {
+        Some(state) => state,
+        None => return,
+    };
+
+    // A fault while reporting a fault is not reported again.
+    if !HANDLING.swap(true, Ordering::SeqCst) {
+        let tid = unsafe { libc::gettid() };
+        let report_buf = unsafe { &mut *BUFFERS.report.get() };
+        let mut report = FixedWriter::new(report_buf);
+        let _ = writeln!(report, "*** Rust native crash ***");
+        let _ = writeln!(report, "signal {} ({})", signal, signal_name(signal));
+        if !info.is_null() {
+            let _ = writeln!(report, "fault address {:p}", unsafe { (*info).si_addr() });
+        }
+        report.push_bytes(b"thread ");
+        write_thread_name(&mut report);
+        let _ = writeln!(report, " (tid {})", tid);
+        write_registers(&mut report, context);
+
+        log_report_raw(&mut report);
+
+        let mut now: libc::timespec = unsafe { mem::zeroed() };
+        unsafe { libc::clock_gettime(libc::CLOCK_REALTIME, &mut now) };
+        let millis = now.tv_sec as i64 * 1000 + now.tv_nsec as i64 / 1_000_000;
+        let path_buf = unsafe { &mut *BUFFERS.path.get() };
+        let mut path = FixedWriter::new(path_buf);
+        path.push_bytes(state.dir.as_os_str().as_bytes());
+        let _ = write!(path, "/crash-{}-{}.txt", millis, tid);
+        unsafe {
+            let fd = libc::open(
+                path.as_c_str(),
+                libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC | libc::O_CLOEXEC,
+                0o600,
+            );
+            if fd >= 0 {
+                write_all(fd, report.as_bytes());
+                libc::close(fd);
+            }
+        }
+    }
+
+    // Hand the signal over to whoever handled it before us, such as the
+    // platform's debuggerd handler, so that the usual tombstone is still
+    // written.
+    unsafe {
+        if let Some((_, old)) = state.old_actions.iter().find(|(s, _)| *s == signal) {
+            libc::sigaction(signal, old, ptr::null_mut());
+        } else {
+            libc::signal(signal, libc::SIG_DFL);
+        }
+        libc::raise(signal);
+    }
+}
+
+fn install_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(state) = STATE.get() {
+            let mut report = String::new();
+            let _ = writeln!(report, "*** Rust panic ***");
+            let _ = writeln!(
+                report,
+                "thread {} (tid {})",
+                std::thread::current().name().unwrap_or("<unnamed>"),
+                unsafe { libc::gettid() }
+            );
+            let _ = writeln!(report, "{}", info);
+            let _ = writeln!(report, "backtrace:\n{}", Backtrace::force_capture());
+
+            // The panic hook of the log module logs panics already.
+            if !crate::log::panic_hook_installed() {
+                log_report(&report);
+            }
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default();
+            let path = state.dir.join(format!("panic-{}-{}.txt", millis, unsafe {
+                libc::gettid()
+            }));
+            let _ = fs::write(path, report);
+        }
+        previous(info);
+    }));
+}
+
+/// Install handlers which report native crashes and Rust panics, writing
+/// each report to a file in `dir`, and to logcat unless
+/// [`log::install_panic_hook`](crate::log::install_panic_hook) already logs
+/// panics. Crash reports include the signal, fault address, registers where
+/// supported and the thread name. Panic reports include the panic message
+/// and a Rust backtrace. Previously installed signal handlers and panic hooks
+/// are still called afterwards.
+///
+/// Crash reports have no backtrace, since capturing one is not
+/// async-signal-safe. The tombstone written by the platform's handler has a
+/// native backtrace instead.
+///
+/// Only the first call installs the handlers. Later calls return an error of
+/// kind [`io::ErrorKind::AlreadyExists`].
+///
+/// # Arguments
+///
+/// * `dir` - Directory to write reports to. It is created if it doesn't
+///   exist.
+pub fn install_with_dir(dir: impl Into<PathBuf>) -> io::Result<()> {
+    let dir = dir.into();
+    fs::create_dir_all(&dir)?;
+    if dir.as_os_str().len() + FILE_NAME_LEN > PATH_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "crash report directory path is too long",
+        ));
+    }
+
+    let mut installed = false;
+    STATE.get_or_try_init(|| -> io::Result<_> {
+        let mut old_actions = Vec::new();
+        for signal in SIGNALS.iter() {
+            unsafe {
+                let mut action: libc::sigaction = mem::zeroed();
+                action.sa_sigaction = handle_signal as *const () as usize;
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut old: libc::sigaction = mem::zeroed();
+                if libc::sigaction(*signal, &action, &mut old) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                old_actions.push((*signal, old));
+            }
+        }
+        installed = true;
+        Ok(CrashState {
+            dir: dir.clone(),
+            old_actions,
+        })
+    })?;
+
+    if !installed {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "crash handlers are already installed",
+        ));
+    }
+    install_panic_hook();
+    Ok(())
+}
+
+fn crash_dir<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<PathBuf> {
+    Ok(files_dir(env, context)?.join("rust-crashes"))
+}
+
+fn throw_io_error(env: &JNIEnv, error: io::Error) -> Error {
+    let _ = env.throw_new("java/io/IOException", error.to_string());
+    Error::JavaException
+}
+
+/// Like [`install_with_dir`], but writes reports to the `rust-crashes`
+/// directory in the app's private files directory. Returns an error with a
+/// pending `java.io.IOException` if the handlers could not be installed.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the files directory from.
+pub fn install<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<()> {
+    let dir = crash_dir(env, context)?;
+    install_with_dir(dir).map_err(|e| throw_io_error(env, e))
+}
+
+/// Take the reports written by previous processes, for example to upload
+/// them on the next start. The report files are deleted. Returns an empty
+/// list if the handlers have not been installed.
+pub fn take_reports() -> io::Result<Vec<String>> {
+    let state = match STATE.get() {
+        Some(state) => state,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut entries = fs::read_dir(&state.dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    let mut reports = Vec::with_capacity(entries.len());
+    for path in entries {
+        reports.push(fs::read_to_string(&path)?);
+        fs::remove_file(&path)?;
+    }
+    Ok(reports)
+}
@@ -0,0 +1,203 @@
+use crate::{
+    content::JContext,
+    util::{float_array_field, CloseOnDrop},
+};
+use jni::{errors::Result, objects::JObject, sys::jint, JNIEnv};
+use jni_utils::{
+    future::{JFuture, JSendFuture},
+    task::JPollResult,
+};
+use std::{convert::TryFrom, future::Future};
+
+/// `android.hardware.Sensor.TYPE_SIGNIFICANT_MOTION`.
+pub const TYPE_SIGNIFICANT_MOTION: jint = 17;
+
+/// `android.hardware.Sensor.TYPE_STEP_DETECTOR`.
+pub const TYPE_STEP_DETECTOR: jint = 18;
+
+/// `android.hardware.Sensor.TYPE_STEP_COUNTER`.
+pub const TYPE_STEP_COUNTER: jint = 19;
+
+/// `android.hardware.Sensor.TYPE_STATIONARY_DETECT`.
+pub const TYPE_STATIONARY_DETECT: jint = 29;
+
+/// `android.hardware.Sensor.TYPE_MOTION_DETECT`.
+pub const TYPE_MOTION_DETECT: jint = 30;
+
+/// `android.hardware.Sensor.TYPE_LOW_LATENCY_OFFBODY_DETECT`.
+pub const TYPE_LOW_LATENCY_OFFBODY_DETECT: jint = 34;
+
+const SENSOR_DELAY_NORMAL: jint = 3;
+
+/// Event produced by a one-shot trigger sensor. Obtained by awaiting the
+/// future returned from [`trigger`].
+pub struct TriggerEvent {
+    /// Type of the sensor that triggered. One of the `TYPE_*` constants.
+    pub sensor_type: jint,
+    /// Time at which the event happened, in nanoseconds.
+    pub timestamp: i64,
+    /// Values of the event. Their meaning depends on the sensor type.
+    pub values: Vec<f32>,
+}
+
+/// Snapshot of the step counter. Obtained by awaiting the future returned
+/// from [`step_count`].
+pub struct StepCount {
+    /// Number of steps taken since the last reboot while the step counter
+    /// was activated.
+    pub steps: u64,
+    /// Time at which the count was reported, in nanoseconds.
+    pub timestamp: i64,
+}
+
+struct RawEvent {
+    sensor_type: jint,
+    timestamp: i64,
+    values: Vec<f32>,
+}
+
+fn sensor_manager<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service("sensor")
+}
+
+fn default_sensor<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    manager: JObject<'a>,
+    sensor_type: jint,
+) -> Result<JObject<'a>> {
+    env.call_method(
+        manager,
+        "getDefaultSensor",
+        "(I)Landroid/hardware/Sensor;",
+        &[sensor_type.into()],
+    )?
+    .l()
+}
+
+fn event_future<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    listener: JObject<'a>,
+) -> Result<impl Future<Output = Result<RawEvent>> + Send> {
+    let future = env
+        .call_method(
+            listener,
+            "getEventFuture",
+            "()Lio/github/gedgygedgy/rust/future/Future;",
+            &[],
+        )?
+        .l()?;
+    let future = JSendFuture::try_from(JFuture::from_env(env, future)?)?;
+    let guard = CloseOnDrop::new(env, listener)?;
+
+    Ok(async move {
+        let result = future.await?;
+        let env = guard.vm().get_env()?;
+        let event = JPollResult::from_env(&env, result.as_obj())?.get()?;
+
+        let sensor_type = env.get_field(event, "sensorType", "I")?.i()?;
+        let timestamp = env.get_field(event, "timestamp", "J")?.j()?;
+        let values = float_array_field(&env, event, "values")?;
+
+        Ok(RawEvent {
+            sensor_type,
+            timestamp,
+            values,
+        })
+    })
+}
+
+/// Wait for a one-shot trigger sensor, such as
+/// [`TYPE_SIGNIFICANT_MOTION`], to fire. The sensor is requested with
+/// `SensorManager.requestTriggerSensor()`, and the request is cancelled if
+/// the returned future is dropped before it resolves. Returns [`None`] if the
+/// device has no such sensor or the request failed.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `SensorManager` from.
+/// * `sensor_type` - Type of trigger sensor to wait for.
+pub fn trigger<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    sensor_type: jint,
+) -> Result<Option<impl Future<Output = Result<TriggerEvent>> + Send>> {
+    let manager = sensor_manager(env, context)?;
+    let sensor = default_sensor(env, manager, sensor_type)?;
+    if sensor.is_null() {
+        return Ok(None);
+    }
+
+    let listener = env.new_object(
+        "io/github/gedgygedgy/rust/android/hardware/RustTriggerEventListener",
+        "(Landroid/hardware/SensorManager;Landroid/hardware/Sensor;)V",
+        &[manager.into(), sensor.into()],
+    )?;
+    let future = event_future(env, listener)?;
+    let requested = env
+        .call_method(
+            manager,
+            "requestTriggerSensor",
+            "(Landroid/hardware/TriggerEventListener;Landroid/hardware/Sensor;)Z",
+            &[listener.into(), sensor.into()],
+        )?
+        .z()?;
+    if !requested {
+        return Ok(None);
+    }
+
+    Ok(Some(async move {
+        let event = future.await?;
+        Ok(TriggerEvent {
+            sensor_type: event.sensor_type,
+            timestamp: event.timestamp,
+            values: event.values,
+        })
+    }))
+}
+
+/// Get a snapshot of the step counter. A listener is registered for
+/// [`TYPE_STEP_COUNTER`] and unregistered as soon as the first value is
+/// reported, or when the returned future is dropped. Returns [`None`] if the
+/// device has no step counter or the listener could not be registered.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `SensorManager` from.
+pub fn step_count<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<impl Future<Output = Result<StepCount>> + Send>> {
+    let manager = sensor_manager(env, context)?;
+    let sensor = default_sensor(env, manager, TYPE_STEP_COUNTER)?;
+    if sensor.is_null() {
+        return Ok(None);
+    }
+
+    let listener = env.new_object(
+        "io/github/gedgygedgy/rust/android/hardware/RustSensorSnapshotListener",
+        "(Landroid/hardware/SensorManager;)V",
+        &[manager.into()],
+    )?;
+    let future = event_future(env, listener)?;
+    let registered = env
+        .call_method(
+            manager,
+            "registerListener",
+            "(Landroid/hardware/SensorEventListener;Landroid/hardware/Sensor;I)Z",
+            &[listener.into(), sensor.into(), SENSOR_DELAY_NORMAL.into()],
+        )?
+        .z()?;
+    if !registered {
+        return Ok(None);
+    }
+
+    Ok(Some(async move {
+        let event = future.await?;
+        Ok(StepCount {
+            steps: event.values.first().copied().unwrap_or_default() as u64,
+            timestamp: event.timestamp,
+        })
+    }))
+}
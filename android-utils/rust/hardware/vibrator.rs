@@ -0,0 +1,304 @@
+use crate::{content::JContext, util::sdk_int};
+use jni::{
+    errors::Result,
+    objects::{JObject, JValue},
+    sys::{jint, jlong},
+    JNIEnv,
+};
+use std::time::Duration;
+
+/// `android.os.VibrationEffect.DEFAULT_AMPLITUDE`.
+const DEFAULT_AMPLITUDE: jint = -1;
+
+/// Effect with a fixed pattern defined by the device, such as a click.
+/// Played with [`vibrate_effect`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PredefinedEffect {
+    /// `VibrationEffect.EFFECT_CLICK`.
+    Click,
+    /// `VibrationEffect.EFFECT_DOUBLE_CLICK`.
+    DoubleClick,
+    /// `VibrationEffect.EFFECT_TICK`.
+    Tick,
+    /// `VibrationEffect.EFFECT_HEAVY_CLICK`.
+    HeavyClick,
+}
+
+impl PredefinedEffect {
+    fn id(self) -> jint {
+        match self {
+            Self::Click => 0,
+            Self::DoubleClick => 1,
+            Self::Tick => 2,
+            Self::HeavyClick => 5,
+        }
+    }
+
+    // Played instead of the effect on API levels older than 29, which don't
+    // support predefined effects.
+    fn fallback(self) -> VibrationEffect {
+        match self {
+            Self::Click | Self::DoubleClick => VibrationEffect::one_shot(Duration::from_millis(20)),
+            Self::Tick => VibrationEffect::one_shot(Duration::from_millis(10)),
+            Self::HeavyClick => VibrationEffect::one_shot(Duration::from_millis(40)),
+        }
+    }
+}
+
+/// Vibration to play with [`vibrate_effect`]. Mirrors
+/// `android.os.VibrationEffect`.
+///
+/// Amplitudes range from 1 to 255, and [`None`] uses the device's default
+/// strength. They are ignored on devices without amplitude control, see
+/// [`has_amplitude_control`], and on API levels older than 26.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VibrationEffect {
+    /// Vibrate once for a duration.
+    OneShot {
+        /// How long to vibrate for.
+        duration: Duration,
+        /// Strength of the vibration.
+        amplitude: Option<u8>,
+    },
+    /// Vibrate in a pattern.
+    Waveform {
+        /// Durations of alternating off and on segments, starting with off.
+        timings: Vec<Duration>,
+        /// Strength of each segment. Must have the same length as
+        /// `timings` if given. Segments with an amplitude of zero are off.
+        amplitudes: Option<Vec<u8>>,
+        /// Index into `timings` to repeat from, or [`None`] to play the
+        /// pattern once. Repeating patterns play until [`cancel`] is called.
+        repeat: Option<usize>,
+    },
+    /// Play an effect defined by the device. Requires API level 29; older
+    /// versions play a short one-shot vibration instead.
+    Predefined(PredefinedEffect),
+}
+
+impl VibrationEffect {
+    /// Create a [`VibrationEffect::OneShot`] with the default amplitude.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - How long to vibrate for.
+    pub fn one_shot(duration: Duration) -> Self {
+        Self::OneShot {
+            duration,
+            amplitude: None,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> jlong {
+    duration.as_millis().min(jlong::MAX as u128) as jlong
+}
+
+fn repeat_index(repeat: Option<usize>) -> jint {
+    repeat.map_or(-1, |index| index as jint)
+}
+
+fn vibrator<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service("vibrator")
+}
+
+fn long_array<'a: 'b, 'b>(env: &'b JNIEnv<'a>, values: &[Duration]) -> Result<JObject<'a>> {
+    let values = values.iter().copied().map(millis).collect::<Vec<_>>();
+    let array = env.new_long_array(values.len() as jint)?;
+    env.set_long_array_region(array, 0, &values)?;
+    Ok(array.into())
+}
+
+fn create_effect<'a: 'b, 'b>(env: &'b JNIEnv<'a>, effect: &VibrationEffect) -> Result<JObject<'a>> {
+    let class = "android/os/VibrationEffect";
+    match effect {
+        VibrationEffect::OneShot {
+            duration,
+            amplitude,
+        } => env
+            .call_static_method(
+                class,
+                "createOneShot",
+                "(JI)Landroid/os/VibrationEffect;",
+                &[
+                    millis(*duration).into(),
+                    amplitude.map_or(DEFAULT_AMPLITUDE, jint::from).into(),
+                ],
+            )?
+            .l(),
+        VibrationEffect::Waveform {
+            timings,
+            amplitudes: None,
+            repeat,
+        } => env
+            .call_static_method(
+                class,
+                "createWaveform",
+                "([JI)Landroid/os/VibrationEffect;",
+                &[
+                    long_array(env, timings)?.into(),
+                    repeat_index(*repeat).into(),
+                ],
+            )?
+            .l(),
+        VibrationEffect::Waveform {
+            timings,
+            amplitudes: Some(amplitudes),
+            repeat,
+        } => {
+            let values = amplitudes
+                .iter()
+                .copied()
+                .map(jint::from)
+                .collect::<Vec<_>>();
+            let amplitudes = env.new_int_array(values.len() as jint)?;
+            env.set_int_array_region(amplitudes, 0, &values)?;
+            env.call_static_method(
+                class,
+                "createWaveform",
+                "([J[II)Landroid/os/VibrationEffect;",
+                &[
+                    long_array(env, timings)?.into(),
+                    JObject::from(amplitudes).into(),
+                    repeat_index(*repeat).into(),
+                ],
+            )?
+            .l()
+        }
+        VibrationEffect::Predefined(effect) => env
+            .call_static_method(
+                class,
+                "createPredefined",
+                "(I)Landroid/os/VibrationEffect;",
+                &[effect.id().into()],
+            )?
+            .l(),
+    }
+}
+
+/// Check whether the device has a vibrator with `Vibrator.hasVibrator()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `Vibrator` from.
+pub fn has_vibrator<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<bool> {
+    let vibrator = vibrator(env, context)?;
+    if vibrator.is_null() {
+        return Ok(false);
+    }
+    env.call_method(vibrator, "hasVibrator", "()Z", &[])?.z()
+}
+
+/// Check whether the vibrator supports amplitudes with
+/// `Vibrator.hasAmplitudeControl()`. Returns `false` before API level 26.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `Vibrator` from.
+pub fn has_amplitude_control<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<bool> {
+    if sdk_int(env)? < 26 {
+        return Ok(false);
+    }
+    let vibrator = vibrator(env, context)?;
+    if vibrator.is_null() {
+        return Ok(false);
+    }
+    env.call_method(vibrator, "hasAmplitudeControl", "()Z", &[])?
+        .z()
+}
+
+/// Vibrate once for a duration at the default amplitude. Requires the
+/// `android.permission.VIBRATE` permission.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `Vibrator` from.
+/// * `duration` - How long to vibrate for.
+pub fn vibrate<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    duration: Duration,
+) -> Result<()> {
+    vibrate_effect(env, context, &VibrationEffect::one_shot(duration))
+}
+
+/// Play a [`VibrationEffect`]. Uses `Vibrator.vibrate(VibrationEffect)` on
+/// API level 26 and later, and the deprecated `long` and pattern overloads
+/// on older versions. Requires the `android.permission.VIBRATE` permission.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `Vibrator` from.
+/// * `effect` - Effect to play.
+pub fn vibrate_effect<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    effect: &VibrationEffect,
+) -> Result<()> {
+    let sdk_int = sdk_int(env)?;
+    let fallback;
+    let effect = match effect {
+        VibrationEffect::Predefined(predefined) if sdk_int < 29 => {
+            fallback = predefined.fallback();
+            &fallback
+        }
+        _ => effect,
+    };
+
+    let vibrator = vibrator(env, context)?;
+    if vibrator.is_null() {
+        return Ok(());
+    }
+
+    if sdk_int >= 26 {
+        let effect = create_effect(env, effect)?;
+        env.call_method(
+            vibrator,
+            "vibrate",
+            "(Landroid/os/VibrationEffect;)V",
+            &[effect.into()],
+        )?;
+        return Ok(());
+    }
+
+    match effect {
+        VibrationEffect::OneShot { duration, .. } => {
+            env.call_method(vibrator, "vibrate", "(J)V", &[millis(*duration).into()])?;
+        }
+        VibrationEffect::Waveform {
+            timings, repeat, ..
+        } => {
+            let pattern: JValue = long_array(env, timings)?.into();
+            env.call_method(
+                vibrator,
+                "vibrate",
+                "([JI)V",
+                &[pattern, repeat_index(*repeat).into()],
+            )?;
+        }
+        // Replaced with its fallback above.
+        VibrationEffect::Predefined(_) => {}
+    }
+    Ok(())
+}
+
+/// Stop any ongoing vibration with `Vibrator.cancel()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `Vibrator` from.
+pub fn cancel<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<()> {
+    let vibrator = vibrator(env, context)?;
+    if !vibrator.is_null() {
+        env.call_method(vibrator, "cancel", "()V", &[])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use crate::util::sdk_int;
+use jni::{
+    errors::Result,
+    objects::{JObject, JValue},
+    sys::jint,
+    JNIEnv,
+};
+/// `android.security.keystore.KeyProperties.PURPOSE_SIGN`.
+pub const PURPOSE_SIGN: jint = 4;
+
+/// `android.security.keystore.KeyProperties.PURPOSE_VERIFY`.
+pub const PURPOSE_VERIFY: jint = 8;
+
+/// DER encoding of the key attestation extension OID,
+/// `1.3.6.1.4.1.11129.2.1.17`, including its tag and length.
+const ATTESTATION_OID: [u8; 12] = [
+    0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x01, 0x11,
+];
+
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_ENUMERATED: u8 = 0x0a;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// Security level of an attestation or of the keymaster which created the
+/// key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecurityLevel {
+    /// The key is stored and used in software.
+    Software,
+    /// The key is stored and used in a trusted execution environment.
+    TrustedEnvironment,
+    /// The key is stored and used in a StrongBox secure element.
+    StrongBox,
+    /// Security level not known to this crate.
+    Unknown(i64),
+}
+
+impl From<i64> for SecurityLevel {
+    fn from(level: i64) -> Self {
+        match level {
+            0 => Self::Software,
+            1 => Self::TrustedEnvironment,
+            2 => Self::StrongBox,
+            level => Self::Unknown(level),
+        }
+    }
+}
+
+/// Basic fields of the key attestation extension of an attestation
+/// certificate. The authorization lists are not parsed; send the full
+/// certificate chain to a verification server for those.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttestationRecord {
+    /// Version of the attestation format.
+    pub attestation_version: i64,
+    /// Security level of the attestation.
+    pub attestation_security_level: SecurityLevel,
+    /// Version of the keymaster or KeyMint implementation.
+    pub keymaster_version: i64,
+    /// Security level of the keymaster or KeyMint implementation.
+    pub keymaster_security_level: SecurityLevel,
+    /// Challenge passed when generating the key.
+    pub attestation_challenge: Vec<u8>,
+    /// Unique id of the device, if requested. Usually empty.
+    pub unique_id: Vec<u8>,
+}
+
+/// Attestation of a newly generated key. Obtained from [`attest_new_key`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attestation {
+    /// Certificate chain of the key, as DER-encoded certificates.
+    pub chain: Vec<Vec<u8>>,
+    /// Parsed attestation extension of the key's certificate, if it has one.
+    pub record: Option<AttestationRecord>,
+}
+
+struct Der<'d>(&'d [u8]);
+
+impl<'d> Der<'d> {
+    fn read(&mut self) -> Option<(u8, &'d [u8])> {
+        let (&tag, rest) = self.0.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let len = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return None;
+            }
+            let len = rest[..count]
+                .iter()
+                .fold(0usize, |len, b| (len << 8) | *b as usize);
+            rest = &rest[count..];
+            len
+        };
+        if rest.len() < len {
+            return None;
+        }
+        let (content, rest) = rest.split_at(len);
+        self.0 = rest;
+        Some((tag, content))
+    }
+
+    fn expect(&mut self, tag: u8) -> Option<&'d [u8]> {
+        match self.read()? {
+            (t, content) if t == tag => Some(content),
+            _ => None,
+        }
+    }
+
+    fn integer(&mut self, tag: u8) -> Option<i64> {
+        let content = self.expect(tag)?;
+        if content.is_empty() || content.len() > 8 {
+            return None;
+        }
+        let fill = if content[0] & 0x80 != 0 { 0xff } else { 0 };
+        let mut bytes = [fill; 8];
+        bytes[8 - content.len()..].copy_from_slice(content);
+        Some(i64::from_be_bytes(bytes))
+    }
+}
+
+impl AttestationRecord {
+    /// Parse the attestation extension from a DER-encoded X.509 certificate,
+    /// usually the first certificate of the chain returned by
+    /// [`generate_attested_key`]. Returns [`None`] if the certificate has no
+    /// attestation extension or it is malformed.
+    ///
+    /// # Arguments
+    ///
+    /// * `certificate` - DER-encoded certificate.
+    pub fn from_certificate(certificate: &[u8]) -> Option<Self> {
+        let start = certificate
+            .windows(ATTESTATION_OID.len())
+            .position(|w| w == ATTESTATION_OID)?
+            + ATTESTATION_OID.len();
+
+        // Extension ::= SEQUENCE { extnID, critical BOOLEAN DEFAULT FALSE,
+        // extnValue OCTET STRING }
+        let mut der = Der(&certificate[start..]);
+        let value = match der.read()? {
+            (TAG_BOOLEAN, _) => der.expect(TAG_OCTET_STRING)?,
+            (TAG_OCTET_STRING, value) => value,
+            _ => return None,
+        };
+        Self::from_extension(value)
+    }
+
+    /// Parse the DER-encoded value of an attestation extension, as a
+    /// `KeyDescription` sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `extension` - DER-encoded `KeyDescription`.
+    pub fn from_extension(extension: &[u8]) -> Option<Self> {
+        let mut der = Der(Der(extension).expect(TAG_SEQUENCE)?);
+        Some(Self {
+            attestation_version: der.integer(TAG_INTEGER)?,
+            attestation_security_level: der.integer(TAG_ENUMERATED)?.into(),
+            keymaster_version: der.integer(TAG_INTEGER)?,
+            keymaster_security_level: der.integer(TAG_ENUMERATED)?.into(),
+            attestation_challenge: der.expect(TAG_OCTET_STRING)?.to_vec(),
+            unique_id: der.expect(TAG_OCTET_STRING)?.to_vec(),
+        })
+    }
+}
+
+fn byte_array_to_vec(env: &JNIEnv, array: JObject) -> Result<Vec<u8>> {
+    env.convert_byte_array(array.into_inner())
+}
+
+/// Get the certificate chain of a key in the Android KeyStore, as
+/// DER-encoded certificates starting with the key's own certificate. Returns
+/// [`None`] if there is no such key.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `alias` - Alias of the key.
+pub fn certificate_chain(env: &JNIEnv, alias: &str) -> Result<Option<Vec<Vec<u8>>>> {
+    let provider = env.new_string("AndroidKeyStore")?;
+    let keystore = env
+        .call_static_method(
+            "java/security/KeyStore",
+            "getInstance",
+            "(Ljava/lang/String;)Ljava/security/KeyStore;",
+            &[provider.into()],
+        )?
+        .l()?;
+    env.call_method(
+        keystore,
+        "load",
+        "(Ljava/security/KeyStore$LoadStoreParameter;)V",
+        &[JObject::null().into()],
+    )?;
+
+    let alias = env.new_string(alias)?;
+    let chain = env
+        .call_method(
+            keystore,
+            "getCertificateChain",
+            "(Ljava/lang/String;)[Ljava/security/cert/Certificate;",
+            &[alias.into()],
+        )?
+        .l()?;
+    if chain.is_null() {
+        return Ok(None);
+    }
+
+    let len = env.get_array_length(chain.into_inner())?;
+    let mut result = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let certificate = env.get_object_array_element(chain.into_inner(), i)?;
+        let encoded = env
+            .call_method(certificate, "getEncoded", "()[B", &[])?
+            .l()?;
+        result.push(byte_array_to_vec(env, encoded)?);
+        env.delete_local_ref(encoded)?;
+        env.delete_local_ref(certificate)?;
+    }
+    Ok(Some(result))
+}
+
+/// Generate an EC signing key in the Android KeyStore with an attestation
+/// challenge, and return its attestation certificate chain as DER-encoded
+/// certificates. The first certificate carries the attestation extension,
+/// which [`AttestationRecord::from_certificate`] can parse. Any existing key
+/// with the same alias is replaced.
+///
+/// Returns [`None`] before API level 24, which is when attestation was
+/// added. Returns an error with a pending Java exception if the key could
+/// not be generated.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `alias` - Alias of the key to generate.
+/// * `challenge` - Challenge from the verification server, which is
+///   included in the attestation.
+pub fn generate_attested_key(
+    env: &JNIEnv,
+    alias: &str,
+    challenge: &[u8],
+) -> Result<Option<Vec<Vec<u8>>>> {
+    if sdk_int(env)? < 24 {
+        return Ok(None);
+    }
+
+    let algorithm = env.new_string("EC")?;
+    let provider = env.new_string("AndroidKeyStore")?;
+    let generator = env
+        .call_static_method(
+            "java/security/KeyPairGenerator",
+            "getInstance",
+            "(Ljava/lang/String;Ljava/lang/String;)Ljava/security/KeyPairGenerator;",
+            &[algorithm.into(), provider.into()],
+        )?
+        .l()?;
+
+    let alias_string = env.new_string(alias)?;
+    let builder = env.new_object(
+        "android/security/keystore/KeyGenParameterSpec$Builder",
+        "(Ljava/lang/String;I)V",
+        &[alias_string.into(), (PURPOSE_SIGN | PURPOSE_VERIFY).into()],
+    )?;
+    let digests = env.new_object_array(1, "java/lang/String", env.new_string("SHA-256")?)?;
+    env.call_method(
+        builder,
+        "setDigests",
+        "([Ljava/lang/String;)Landroid/security/keystore/KeyGenParameterSpec$Builder;",
+        &[JObject::from(digests).into()],
+    )?;
+    let challenge_array = env.byte_array_from_slice(challenge)?;
+    env.call_method(
+        builder,
+        "setAttestationChallenge",
+        "([B)Landroid/security/keystore/KeyGenParameterSpec$Builder;",
+        &[JValue::Object(challenge_array.into())],
+    )?;
+    let spec = env
+        .call_method(
+            builder,
+            "build",
+            "()Landroid/security/keystore/KeyGenParameterSpec;",
+            &[],
+        )?
+        .l()?;
+
+    env.call_method(
+        generator,
+        "initialize",
+        "(Ljava/security/spec/AlgorithmParameterSpec;)V",
+        &[spec.into()],
+    )?;
+    env.call_method(
+        generator,
+        "generateKeyPair",
+        "()Ljava/security/KeyPair;",
+        &[],
+    )?;
+
+    certificate_chain(env, alias)
+}
+
+/// Like [`generate_attested_key`], but parses the attestation extension of
+/// the key's certificate as well.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `alias` - Alias of the key to generate.
+/// * `challenge` - Challenge from the verification server.
+pub fn attest_new_key(env: &JNIEnv, alias: &str, challenge: &[u8]) -> Result<Option<Attestation>> {
+    Ok(generate_attested_key(env, alias, challenge)?.map(|chain| {
+        let record = chain
+            .first()
+            .and_then(|c| AttestationRecord::from_certificate(c));
+        Attestation { chain, record }
+    }))
+}
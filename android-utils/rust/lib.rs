@@ -1,15 +1,113 @@
-use jni::{errors::Result, JNIEnv};
+use jni::{
+    errors::{Error, Result},
+    JNIEnv, JavaVM,
+};
+use once_cell::sync::OnceCell;
+use std::{cell::Cell, ptr};
 
+pub mod app;
+pub mod blocking;
+pub mod bluetooth;
+pub mod content;
+pub mod crash;
+pub mod hardware;
+pub mod keystore;
+pub mod location;
+pub mod log;
+pub mod nfc;
+pub mod ops;
 pub mod os;
+pub mod refs;
 pub mod service;
+pub mod telecom;
+#[cfg(feature = "work")]
+pub mod work;
 
-/// Initialize [`android-utils`](crate). This currently does nothing, but it
-/// may initialize some JNI functions in the future. This should be called
-/// before using [`android-utils`](crate).
+mod cache;
+mod util;
+
+static JAVA_VM: OnceCell<JavaVM> = OnceCell::new();
+
+thread_local! {
+    static THREAD_ENV: Cell<*mut jni::sys::JNIEnv> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// Initialize [`android-utils`](crate). This stores the [`JavaVM`] for use
+/// by [`env()`], reads the [`os::build`] information, and looks up and
+/// caches the classes and method IDs used by the wrappers so that they don't
+/// need to be looked up on every use, and registers the native methods of
+/// the Java classes. This should be called before using
+/// [`android-utils`](crate), preferably from `JNI_OnLoad()` or a thread
+/// created by Java so that the app's classes can be found.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn init<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<()> {
+    if JAVA_VM.get().is_none() {
+        let _ = JAVA_VM.set(env.get_java_vm()?);
+    }
+    os::build::init(env)?;
+    cache::init(env)?;
+    log::bridge::init(env)?;
+    service::init(env)
+}
+
+/// Like [`init()`], but also installs the logger built from `logger` as the
+/// global logger of the [`log`](::log) crate. Returns an error with a pending
+/// `java.lang.IllegalStateException` if the logger could not be installed.
 ///
 /// # Arguments
 ///
 /// * `env` - Java environment to use.
-pub fn init<'a: 'b, 'b>(_env: &'b JNIEnv<'a>) -> Result<()> {
-    Ok(())
+/// * `logger` - Options of the logger to install.
+pub fn init_with_logger<'a: 'b, 'b>(env: &'b JNIEnv<'a>, logger: log::LoggerBuilder) -> Result<()> {
+    init(env)?;
+    logger.init().map_err(|err| {
+        let _ = env.throw_new("java/lang/IllegalStateException", err.to_string());
+        Error::JavaException
+    })
+}
+
+/// Get the [`JavaVM`] stored by [`init()`] or captured from a wrapper such
+/// as [`JHandler`](os::JHandler), or [`None`] if neither has happened yet.
+pub fn java_vm() -> Option<&'static JavaVM> {
+    JAVA_VM.get()
+}
+
+// Store the JavaVM for env() if init() hasn't, so that tasks spawned on a
+// JHandler can still be woken from other threads without init().
+pub(crate) fn capture_java_vm(env: &JNIEnv) {
+    if JAVA_VM.get().is_none() {
+        if let Ok(vm) = env.get_java_vm() {
+            let _ = JAVA_VM.set(vm);
+        }
+    }
+}
+
+/// Get a [`JNIEnv`] for the current thread. If the thread is not attached to
+/// the Java VM yet, it is attached as a daemon thread on first use and
+/// detached automatically when it exits. The resulting [`JNIEnv`] is cached
+/// in a thread-local, so subsequent calls on the same thread are cheap.
+///
+/// Unlike [`JavaVM::get_env()`], this works on native threads that have
+/// never been attached. It needs the [`JavaVM`], which is stored by
+/// [`init()`], or captured when a [`JHandler`](os::JHandler) is created.
+pub fn env() -> Result<JNIEnv<'static>> {
+    THREAD_ENV.with(|cell| {
+        let cached = cell.get();
+        if !cached.is_null() {
+            return unsafe { JNIEnv::from_raw(cached) };
+        }
+
+        let vm = JAVA_VM.get().ok_or(Error::NullPtr("JavaVM"))?;
+        if let Ok(env) = vm.get_env() {
+            // The thread was attached by someone else, who may detach it
+            // again, so don't cache it.
+            return Ok(env);
+        }
+        let env = vm.attach_current_thread_as_daemon()?;
+        cell.set(env.get_native_interface());
+        Ok(env)
+    })
 }
@@ -0,0 +1,385 @@
+use crate::util::{
+    boolean_array_field, double_array_field, float_array_field, int_array_field, long_array_field,
+    sdk_int, CloseOnDrop, CloseOnDropStream,
+};
+use futures::{Stream, StreamExt};
+use jni::{errors::Result, objects::JObject, sys::jint, JNIEnv};
+use jni_utils::stream::{JSendStream, JStream};
+use std::{convert::TryFrom, time::Duration};
+
+/// Events produced by a proximity alert added with [`add_proximity_alert`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeofenceEvent {
+    /// The device entered the alert region.
+    Entered,
+    /// The device exited the alert region.
+    Exited,
+}
+
+/// Add a proximity alert with `LocationManager.addProximityAlert()` and
+/// create a stream of [`GeofenceEvent`]s for it. The alert is delivered to an
+/// internal `PendingIntent` and broadcast receiver, all of which are removed
+/// when the stream is dropped.
+///
+/// The caller must hold the `ACCESS_FINE_LOCATION` permission.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the alert with.
+/// * `latitude` - Latitude of the center of the alert region.
+/// * `longitude` - Longitude of the center of the alert region.
+/// * `radius` - Radius of the alert region in meters.
+/// * `expiration` - Time after which the alert is removed, or [`None`] for
+///   no expiration.
+pub fn add_proximity_alert<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    latitude: f64,
+    longitude: f64,
+    radius: f32,
+    expiration: Option<Duration>,
+) -> Result<impl Stream<Item = Result<GeofenceEvent>> + Send> {
+    let expiration = expiration.map_or(-1, |d| d.as_millis() as i64);
+    let receiver = env.new_object(
+        "io/github/gedgygedgy/rust/android/location/RustProximityAlertReceiver",
+        "(Landroid/content/Context;DDFJ)V",
+        &[
+            context.into(),
+            latitude.into(),
+            longitude.into(),
+            radius.into(),
+            expiration.into(),
+        ],
+    )?;
+    let stream = env
+        .call_method(
+            receiver,
+            "getEventStream",
+            "()Lio/github/gedgygedgy/rust/stream/Stream;",
+            &[],
+        )?
+        .l()?;
+    let stream = JSendStream::try_from(JStream::from_env(env, stream)?)?;
+
+    let vm = env.get_java_vm()?;
+    let mapped_stream = stream.map(move |item| -> Result<_> {
+        let item = item?;
+        let env = vm.get_env()?;
+        let entering = env
+            .call_method(item.as_obj(), "booleanValue", "()Z", &[])?
+            .z()?;
+        Ok(if entering {
+            GeofenceEvent::Entered
+        } else {
+            GeofenceEvent::Exited
+        })
+    });
+
+    Ok(CloseOnDropStream::new(
+        mapped_stream,
+        CloseOnDrop::new(env, receiver)?,
+    ))
+}
+
+/// `android.location.GnssStatus.CONSTELLATION_UNKNOWN`.
+pub const CONSTELLATION_UNKNOWN: jint = 0;
+
+/// `android.location.GnssStatus.CONSTELLATION_GPS`.
+pub const CONSTELLATION_GPS: jint = 1;
+
+/// `android.location.GnssStatus.CONSTELLATION_SBAS`.
+pub const CONSTELLATION_SBAS: jint = 2;
+
+/// `android.location.GnssStatus.CONSTELLATION_GLONASS`.
+pub const CONSTELLATION_GLONASS: jint = 3;
+
+/// `android.location.GnssStatus.CONSTELLATION_QZSS`.
+pub const CONSTELLATION_QZSS: jint = 4;
+
+/// `android.location.GnssStatus.CONSTELLATION_BEIDOU`.
+pub const CONSTELLATION_BEIDOU: jint = 5;
+
+/// `android.location.GnssStatus.CONSTELLATION_GALILEO`.
+pub const CONSTELLATION_GALILEO: jint = 6;
+
+/// `android.location.GnssStatus.CONSTELLATION_IRNSS`.
+pub const CONSTELLATION_IRNSS: jint = 7;
+
+/// Information about a single satellite, as reported by
+/// `android.location.GnssStatus`.
+#[derive(Clone, Debug)]
+pub struct Satellite {
+    /// Identification number of the satellite.
+    pub svid: jint,
+    /// Constellation of the satellite. One of the `CONSTELLATION_*`
+    /// constants.
+    pub constellation_type: jint,
+    /// Carrier-to-noise density at the antenna, in dB-Hz.
+    pub cn0_dbhz: f32,
+    /// Elevation of the satellite, in degrees.
+    pub elevation_degrees: f32,
+    /// Azimuth of the satellite, in degrees.
+    pub azimuth_degrees: f32,
+    /// Whether the satellite was used in the most recent position fix.
+    pub used_in_fix: bool,
+    /// Whether the receiver has almanac data for the satellite.
+    pub has_almanac_data: bool,
+    /// Whether the receiver has ephemeris data for the satellite.
+    pub has_ephemeris_data: bool,
+}
+
+/// Events captured by an `android.location.GnssStatus.Callback`. Obtained
+/// from the stream returned by [`gnss_status`].
+#[derive(Clone, Debug)]
+pub enum GnssStatus {
+    /// Created by `GnssStatus.Callback.onStarted()`.
+    Started,
+    /// Created by `GnssStatus.Callback.onStopped()`.
+    Stopped,
+    /// Created by `GnssStatus.Callback.onFirstFix()`.
+    FirstFix {
+        /// Time from the start of the GNSS engine to the first fix.
+        ttff: Duration,
+    },
+    /// Created by `GnssStatus.Callback.onSatelliteStatusChanged()`.
+    SatelliteStatus { satellites: Vec<Satellite> },
+}
+
+impl GnssStatus {
+    /// Get the number of satellites in a [`GnssStatus::SatelliteStatus`]
+    /// event, or [`None`] for other events.
+    pub fn satellite_count(&self) -> Option<usize> {
+        match self {
+            Self::SatelliteStatus { satellites } => Some(satellites.len()),
+            _ => None,
+        }
+    }
+
+    /// Get the number of satellites used in the most recent fix in a
+    /// [`GnssStatus::SatelliteStatus`] event, or [`None`] for other events.
+    pub fn used_in_fix_count(&self) -> Option<usize> {
+        match self {
+            Self::SatelliteStatus { satellites } => {
+                Some(satellites.iter().filter(|s| s.used_in_fix).count())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Raw measurement of a single satellite signal, as reported by
+/// `android.location.GnssMeasurement`.
+#[derive(Clone, Debug)]
+pub struct GnssMeasurement {
+    /// Identification number of the satellite.
+    pub svid: jint,
+    /// Constellation of the satellite. One of the `CONSTELLATION_*`
+    /// constants.
+    pub constellation_type: jint,
+    /// Synchronization state of the measurement.
+    pub state: jint,
+    /// Carrier-to-noise density at the antenna, in dB-Hz.
+    pub cn0_dbhz: f64,
+    /// Time offset at which the measurement was taken, in nanoseconds.
+    pub time_offset_nanos: f64,
+    /// Received GNSS satellite time, in nanoseconds.
+    pub received_sv_time_nanos: i64,
+    /// Pseudorange rate, in meters per second.
+    pub pseudorange_rate_meters_per_second: f64,
+    /// Accumulated delta range since the last channel reset, in meters.
+    pub accumulated_delta_range_meters: f64,
+}
+
+/// Set of raw GNSS measurements together with the receiver clock, as
+/// reported by `android.location.GnssMeasurementsEvent`.
+#[derive(Clone, Debug)]
+pub struct GnssMeasurements {
+    /// Receiver clock time, in nanoseconds.
+    pub time_nanos: i64,
+    /// Difference between the receiver clock and GPS time, in nanoseconds,
+    /// or 0 if unavailable.
+    pub full_bias_nanos: i64,
+    /// Sub-nanosecond bias of the receiver clock, or 0 if unavailable.
+    pub bias_nanos: f64,
+    /// Measurements of the individual satellite signals.
+    pub measurements: Vec<GnssMeasurement>,
+}
+
+fn register_gnss_callback<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    class: &str,
+    context: JObject<'a>,
+) -> Result<Option<(JSendStream, CloseOnDrop)>> {
+    if sdk_int(env)? < 24 {
+        return Ok(None);
+    }
+
+    let callback = env.new_object(class, "(Landroid/content/Context;)V", &[context.into()])?;
+    if !env.call_method(callback, "register", "()Z", &[])?.z()? {
+        return Ok(None);
+    }
+    let guard = CloseOnDrop::new(env, callback)?;
+    let stream = env
+        .call_method(
+            callback,
+            "getEventStream",
+            "()Lio/github/gedgygedgy/rust/stream/Stream;",
+            &[],
+        )?
+        .l()?;
+    let stream = JSendStream::try_from(JStream::from_env(env, stream)?)?;
+    Ok(Some((stream, guard)))
+}
+
+/// Register an `android.location.GnssStatus.Callback` and create a stream of
+/// the [`GnssStatus`] events captured by it. The callback is unregistered
+/// when the stream is dropped. Returns [`None`] on devices older than API 24
+/// or if the callback could not be registered.
+///
+/// The caller must hold the `ACCESS_FINE_LOCATION` permission.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `LocationManager`
+///   from.
+pub fn gnss_status<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<impl Stream<Item = Result<GnssStatus>> + Send>> {
+    let (stream, guard) = match register_gnss_callback(
+        env,
+        "io/github/gedgygedgy/rust/android/location/RustGnssStatusCallback",
+        context,
+    )? {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+
+    let vm = env.get_java_vm()?;
+    let mapped_stream = stream.map(move |item| -> Result<_> {
+        let item = item?;
+        let item = item.as_obj();
+        let env = vm.get_env()?;
+
+        if env.is_instance_of(
+            item,
+            "io/github/gedgygedgy/rust/android/location/RustGnssStatusCallback$StartedEvent",
+        )? {
+            Ok(GnssStatus::Started)
+        } else if env.is_instance_of(
+            item,
+            "io/github/gedgygedgy/rust/android/location/RustGnssStatusCallback$StoppedEvent",
+        )? {
+            Ok(GnssStatus::Stopped)
+        } else if env.is_instance_of(
+            item,
+            "io/github/gedgygedgy/rust/android/location/RustGnssStatusCallback$FirstFixEvent",
+        )? {
+            let ttff = env.get_field(item, "ttffMillis", "I")?.i()?;
+            Ok(GnssStatus::FirstFix {
+                ttff: Duration::from_millis(ttff as u64),
+            })
+        } else if env.is_instance_of(
+            item,
+            "io/github/gedgygedgy/rust/android/location/RustGnssStatusCallback$SatelliteStatusEvent",
+        )? {
+            let svid = int_array_field(&env, item, "svid")?;
+            let constellation_type = int_array_field(&env, item, "constellationType")?;
+            let cn0_dbhz = float_array_field(&env, item, "cn0DbHz")?;
+            let elevation_degrees = float_array_field(&env, item, "elevationDegrees")?;
+            let azimuth_degrees = float_array_field(&env, item, "azimuthDegrees")?;
+            let used_in_fix = boolean_array_field(&env, item, "usedInFix")?;
+            let has_almanac_data = boolean_array_field(&env, item, "hasAlmanacData")?;
+            let has_ephemeris_data = boolean_array_field(&env, item, "hasEphemerisData")?;
+
+            let satellites = (0..svid.len())
+                .map(|i| Satellite {
+                    svid: svid[i],
+                    constellation_type: constellation_type[i],
+                    cn0_dbhz: cn0_dbhz[i],
+                    elevation_degrees: elevation_degrees[i],
+                    azimuth_degrees: azimuth_degrees[i],
+                    used_in_fix: used_in_fix[i] != 0,
+                    has_almanac_data: has_almanac_data[i] != 0,
+                    has_ephemeris_data: has_ephemeris_data[i] != 0,
+                })
+                .collect();
+            Ok(GnssStatus::SatelliteStatus { satellites })
+        } else {
+            panic!("Unknown Event class");
+        }
+    });
+
+    Ok(Some(CloseOnDropStream::new(mapped_stream, guard)))
+}
+
+/// Register an `android.location.GnssMeasurementsEvent.Callback` and create
+/// a stream of the raw [`GnssMeasurements`] captured by it. The callback is
+/// unregistered when the stream is dropped. Returns [`None`] on devices older
+/// than API 24 or if raw measurements are not supported.
+///
+/// The caller must hold the `ACCESS_FINE_LOCATION` permission.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `LocationManager`
+///   from.
+pub fn gnss_measurements<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<impl Stream<Item = Result<GnssMeasurements>> + Send>> {
+    let (stream, guard) = match register_gnss_callback(
+        env,
+        "io/github/gedgygedgy/rust/android/location/RustGnssMeasurementsCallback",
+        context,
+    )? {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+
+    let vm = env.get_java_vm()?;
+    let mapped_stream = stream.map(move |item| -> Result<_> {
+        let item = item?;
+        let item = item.as_obj();
+        let env = vm.get_env()?;
+
+        let time_nanos = env.get_field(item, "timeNanos", "J")?.j()?;
+        let full_bias_nanos = env.get_field(item, "fullBiasNanos", "J")?.j()?;
+        let bias_nanos = env.get_field(item, "biasNanos", "D")?.d()?;
+
+        let svid = int_array_field(&env, item, "svid")?;
+        let constellation_type = int_array_field(&env, item, "constellationType")?;
+        let state = int_array_field(&env, item, "state")?;
+        let cn0_dbhz = double_array_field(&env, item, "cn0DbHz")?;
+        let time_offset_nanos = double_array_field(&env, item, "timeOffsetNanos")?;
+        let received_sv_time_nanos = long_array_field(&env, item, "receivedSvTimeNanos")?;
+        let pseudorange_rate_meters_per_second =
+            double_array_field(&env, item, "pseudorangeRateMetersPerSecond")?;
+        let accumulated_delta_range_meters =
+            double_array_field(&env, item, "accumulatedDeltaRangeMeters")?;
+
+        let measurements = (0..svid.len())
+            .map(|i| GnssMeasurement {
+                svid: svid[i],
+                constellation_type: constellation_type[i],
+                state: state[i],
+                cn0_dbhz: cn0_dbhz[i],
+                time_offset_nanos: time_offset_nanos[i],
+                received_sv_time_nanos: received_sv_time_nanos[i],
+                pseudorange_rate_meters_per_second: pseudorange_rate_meters_per_second[i],
+                accumulated_delta_range_meters: accumulated_delta_range_meters[i],
+            })
+            .collect();
+        Ok(GnssMeasurements {
+            time_nanos,
+            full_bias_nanos,
+            bias_nanos,
+            measurements,
+        })
+    });
+
+    Ok(Some(CloseOnDropStream::new(mapped_stream, guard)))
+}
@@ -0,0 +1,1186 @@
+//! Logging to logcat through `android.util.Log`.
+//!
+//! Besides plain messages, errors can be logged along with a
+//! `java.lang.Throwable`, using the three-argument `Log` overloads, so that
+//! logcat shows the stack trace and chain of causes instead of a flattened
+//! message. Rust errors are converted into a chain of `Throwable`s, one for
+//! each error in their [`source`](std::error::Error::source) chain.
+//!
+//! [`AndroidLogger`] forwards the records of the [`log`] crate to
+//! logcat. By default it goes through `android.util.Log`, which works
+//! anywhere Java does, including Robolectric tests. With the `liblog`
+//! feature, it can instead write to liblog directly, which avoids JNI calls
+//! and doesn't need the thread to be attached. With the `tracing` feature,
+//! [`tracing::AndroidLayer`] does the same for events of the `tracing`
+//! crate.
+//!
+//! No logger is installed by default. Configure one with [`LoggerBuilder`],
+//! and install it with [`LoggerBuilder::init`] or
+//! [`crate::init_with_logger`].
+//!
+//! To keep JNI calls off hot paths, a logger can also queue its records in a
+//! buffer, which is written to logcat from a dedicated thread. See
+//! [`AndroidLogger::with_buffer`].
+//!
+//! Which records are written can be set by module with a [`Filter`], which
+//! can be read from a system property so that verbose dependencies can be
+//! silenced without recompiling.
+//!
+//! With the `kv` feature, the key-values of records are written after their
+//! message, as `key=value` pairs or JSON, so that logcat output can be parsed
+//! by other tools. See [`KeyValueFormat`].
+//!
+//! In the other direction, [`bridge`] passes the messages Java code logs with
+//! `RustLog` to Rust.
+
+use ::log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use jni::{
+    errors::Result,
+    objects::{JObject, JThrowable},
+    sys::jint,
+    JNIEnv,
+};
+#[cfg(any(feature = "liblog", target_os = "android"))]
+use std::ffi::CString;
+#[cfg(target_os = "android")]
+use std::os::raw::c_char;
+#[cfg(target_os = "android")]
+use std::os::raw::c_int;
+use std::{
+    backtrace::Backtrace,
+    borrow::Cow,
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Write as _},
+    io, panic,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Arc,
+    },
+    thread,
+};
+
+pub mod bridge;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+
+const LOG_CLASS: &str = "android/util/Log";
+
+/// Priority of a log message, from the constants of `android.util.Log`.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// `Log.VERBOSE`.
+    Verbose = 2,
+    /// `Log.DEBUG`.
+    Debug = 3,
+    /// `Log.INFO`.
+    Info = 4,
+    /// `Log.WARN`.
+    Warn = 5,
+    /// `Log.ERROR`.
+    Error = 6,
+    /// `Log.ASSERT`.
+    Assert = 7,
+}
+
+impl Priority {
+    // Name of the Log method which logs at this priority.
+    fn method(self) -> &'static str {
+        match self {
+            Self::Verbose => "v",
+            Self::Debug => "d",
+            Self::Info => "i",
+            Self::Warn => "w",
+            Self::Error => "e",
+            Self::Assert => "wtf",
+        }
+    }
+}
+
+impl From<Priority> for jint {
+    fn from(priority: Priority) -> Self {
+        priority as jint
+    }
+}
+
+impl TryFrom<jint> for Priority {
+    type Error = jint;
+
+    fn try_from(raw: jint) -> std::result::Result<Self, jint> {
+        match raw {
+            2 => Ok(Self::Verbose),
+            3 => Ok(Self::Debug),
+            4 => Ok(Self::Info),
+            5 => Ok(Self::Warn),
+            6 => Ok(Self::Error),
+            7 => Ok(Self::Assert),
+            _ => Err(raw),
+        }
+    }
+}
+
+impl From<Priority> for ::log::Level {
+    fn from(priority: Priority) -> Self {
+        match priority {
+            Priority::Verbose => Self::Trace,
+            Priority::Debug => Self::Debug,
+            Priority::Info => Self::Info,
+            Priority::Warn => Self::Warn,
+            Priority::Error | Priority::Assert => Self::Error,
+        }
+    }
+}
+
+impl From<::log::Level> for Priority {
+    fn from(level: ::log::Level) -> Self {
+        match level {
+            ::log::Level::Error => Self::Error,
+            ::log::Level::Warn => Self::Warn,
+            ::log::Level::Info => Self::Info,
+            ::log::Level::Debug => Self::Debug,
+            ::log::Level::Trace => Self::Verbose,
+        }
+    }
+}
+
+/// Log a message with `Log.println()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `priority` - Priority of the message.
+/// * `tag` - Tag of the message.
+/// * `msg` - Message to log.
+pub fn println<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    priority: Priority,
+    tag: &str,
+    msg: &str,
+) -> Result<()> {
+    // The env may belong to a native thread which never returns to Java, so
+    // free the local references here rather than leaving them to the caller.
+    env.with_local_frame(4, || {
+        let tag = env.new_string(tag)?;
+        let msg = env.new_string(msg)?;
+        env.call_static_method(
+            LOG_CLASS,
+            "println",
+            "(ILjava/lang/String;Ljava/lang/String;)I",
+            &[(priority as i32).into(), tag.into(), msg.into()],
+        )?;
+        Ok(JObject::null())
+    })?;
+    Ok(())
+}
+
+/// Log a message along with a `java.lang.Throwable`, whose stack trace is
+/// logged after the message. Uses the `Log` method for the priority, such as
+/// `Log.e(String, String, Throwable)` for [`Priority::Error`], and
+/// `Log.wtf()` for [`Priority::Assert`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `priority` - Priority of the message.
+/// * `tag` - Tag of the message.
+/// * `msg` - Message to log.
+/// * `throwable` - `Throwable` to log.
+pub fn log_throwable<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    priority: Priority,
+    tag: &str,
+    msg: &str,
+    throwable: JThrowable<'a>,
+) -> Result<()> {
+    let tag = env.new_string(tag)?;
+    let msg = env.new_string(msg)?;
+    env.call_static_method(
+        LOG_CLASS,
+        priority.method(),
+        "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/Throwable;)I",
+        &[tag.into(), msg.into(), throwable.into()],
+    )?;
+    Ok(())
+}
+
+/// Log a message and a `java.lang.Throwable` at [`Priority::Error`] with
+/// `Log.e()`. See [`log_throwable`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `tag` - Tag of the message.
+/// * `msg` - Message to log.
+/// * `throwable` - `Throwable` to log.
+pub fn error_throwable<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    tag: &str,
+    msg: &str,
+    throwable: JThrowable<'a>,
+) -> Result<()> {
+    log_throwable(env, Priority::Error, tag, msg, throwable)
+}
+
+/// Log a message and a `java.lang.Throwable` at [`Priority::Warn`] with
+/// `Log.w()`. See [`log_throwable`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `tag` - Tag of the message.
+/// * `msg` - Message to log.
+/// * `throwable` - `Throwable` to log.
+pub fn warn_throwable<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    tag: &str,
+    msg: &str,
+    throwable: JThrowable<'a>,
+) -> Result<()> {
+    log_throwable(env, Priority::Warn, tag, msg, throwable)
+}
+
+/// Convert a Rust error into a `java.lang.Throwable`, whose cause is the
+/// `Throwable` of the error's [`source`](Error::source), and so on. The
+/// `Throwable`s have no stack trace, since it would only show the JNI call
+/// that created them.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `err` - Error to convert.
+pub fn error_to_throwable<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    err: &(dyn Error + 'static),
+) -> Result<JThrowable<'a>> {
+    let mut chain = vec![err];
+    let mut source = err.source();
+    while let Some(err) = source {
+        chain.push(err);
+        source = err.source();
+    }
+
+    let empty_stack_trace =
+        env.new_object_array(0, "java/lang/StackTraceElement", JObject::null())?;
+    let mut cause = JObject::null();
+    for err in chain.into_iter().rev() {
+        let msg = env.new_string(err.to_string())?;
+        let throwable = env.new_object(
+            "java/lang/Throwable",
+            "(Ljava/lang/String;Ljava/lang/Throwable;)V",
+            &[msg.into(), cause.into()],
+        )?;
+        env.call_method(
+            throwable,
+            "setStackTrace",
+            "([Ljava/lang/StackTraceElement;)V",
+            &[JObject::from(empty_stack_trace).into()],
+        )?;
+        cause = throwable;
+    }
+    Ok(cause.into())
+}
+
+/// Log a message along with a Rust error, converted into a chain of
+/// `java.lang.Throwable`s with [`error_to_throwable`], so that each error in
+/// the chain shows up as a `Caused by:` line. See [`log_throwable`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `priority` - Priority of the message.
+/// * `tag` - Tag of the message.
+/// * `msg` - Message to log.
+/// * `err` - Error to log.
+pub fn log_error<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    priority: Priority,
+    tag: &str,
+    msg: &str,
+    err: &(dyn Error + 'static),
+) -> Result<()> {
+    let throwable = error_to_throwable(env, err)?;
+    log_throwable(env, priority, tag, msg, throwable)
+}
+
+#[cfg(target_os = "android")]
+#[link(name = "log")]
+extern "C" {
+    pub(crate) fn __android_log_write(
+        prio: c_int,
+        tag: *const c_char,
+        text: *const c_char,
+    ) -> c_int;
+}
+
+#[cfg(feature = "liblog")]
+fn liblog_write(priority: Priority, tag: &str, msg: &str) {
+    // liblog stops at the first NUL, so replace any in the strings rather
+    // than dropping the record.
+    let tag = CString::new(tag.replace('\0', "\u{fffd}")).unwrap();
+    let msg = CString::new(msg.replace('\0', "\u{fffd}")).unwrap();
+    #[cfg(target_os = "android")]
+    unsafe {
+        __android_log_write(priority as c_int, tag.as_ptr(), msg.as_ptr());
+    }
+    #[cfg(not(target_os = "android"))]
+    eprintln!(
+        "{:?} {}: {}",
+        priority,
+        tag.to_string_lossy(),
+        msg.to_string_lossy()
+    );
+}
+
+/// Where an [`AndroidLogger`] writes its records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Check `Log.isLoggable()`, unless disabled with
+    /// [`AndroidLogger::with_is_loggable`], and write with `Log.println()`,
+    /// through JNI.
+    /// The logging thread is attached to the Java VM if needed, which
+    /// requires [`init`](crate::init) to have been called.
+    Jni,
+    /// Write with `__android_log_write()` from liblog, without JNI. Records
+    /// are always written, and filtered by logcat instead of
+    /// `Log.isLoggable()`. Outside of Android, records are written to
+    /// standard error. Requires the `liblog` feature.
+    #[cfg(feature = "liblog")]
+    Liblog,
+}
+
+fn log_jni(priority: Priority, tag: &str, msg: &str, is_loggable: bool) -> Result<()> {
+    let env = crate::env()?;
+    if !is_loggable {
+        return println(&env, priority, tag, msg);
+    }
+    // The thread is usually attached as a daemon and never returns to Java,
+    // so local references would otherwise pile up until the table overflows.
+    let mut loggable = true;
+    env.with_local_frame(2, || {
+        let jtag = env.new_string(tag)?;
+        let result = env.call_static_method(
+            LOG_CLASS,
+            "isLoggable",
+            "(Ljava/lang/String;I)Z",
+            &[jtag.into(), (priority as i32).into()],
+        );
+        match result.and_then(|loggable| loggable.z()) {
+            Ok(result) => loggable = result,
+            // Tags longer than 23 characters throw before API level 26.
+            Err(jni::errors::Error::JavaException) => env.exception_clear()?,
+            Err(err) => return Err(err),
+        }
+        Ok(JObject::null())
+    })?;
+    if loggable {
+        println(&env, priority, tag, msg)?;
+    }
+    Ok(())
+}
+
+impl Backend {
+    fn write(self, priority: Priority, tag: &str, msg: &str) {
+        self.write_checked(priority, tag, msg, true)
+    }
+
+    // Like write(), but lets the Jni backend skip Log.isLoggable().
+    fn write_checked(self, priority: Priority, tag: &str, msg: &str, is_loggable: bool) {
+        match self {
+            Self::Jni => {
+                // There's nowhere else to report the failure, and logging it
+                // would only recurse.
+                let _ = log_jni(priority, tag, msg, is_loggable);
+            }
+            #[cfg(feature = "liblog")]
+            Self::Liblog => liblog_write(priority, tag, msg),
+        }
+    }
+}
+
+/// Maximum length of a tag. Longer tags make `Log.isLoggable()` throw
+/// before API level 26, and are cut off by logcat.
+pub const MAX_TAG_LEN: usize = 23;
+
+/// How the tag of a record is derived from its target, which is usually a
+/// module path such as `my_crate::deep::module`. Whatever the policy, tags
+/// are truncated to [`MAX_TAG_LEN`] characters.
+#[derive(Clone, Default)]
+pub enum TagPolicy {
+    /// Use the target as the tag.
+    #[default]
+    Target,
+    /// Use the first component of the target, which is the name of the
+    /// crate for module paths.
+    Crate,
+    /// Use the same tag for every record.
+    Fixed(String),
+    /// Map the target to a tag with a function.
+    Custom(Arc<dyn Fn(&str) -> String + Send + Sync>),
+}
+
+impl TagPolicy {
+    /// Get the tag for a target, truncated to [`MAX_TAG_LEN`] characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Target of the record.
+    pub fn tag<'a>(&'a self, target: &'a str) -> Cow<'a, str> {
+        truncate_tag(match self {
+            Self::Target => Cow::Borrowed(target),
+            Self::Crate => Cow::Borrowed(target.split("::").next().unwrap_or(target)),
+            Self::Fixed(tag) => Cow::Borrowed(tag.as_str()),
+            Self::Custom(f) => Cow::Owned(f(target)),
+        })
+    }
+}
+
+fn truncate_tag(tag: Cow<str>) -> Cow<str> {
+    match tag.char_indices().nth(MAX_TAG_LEN) {
+        Some((end, _)) => match tag {
+            Cow::Borrowed(tag) => Cow::Borrowed(&tag[..end]),
+            Cow::Owned(mut tag) => {
+                tag.truncate(end);
+                Cow::Owned(tag)
+            }
+        },
+        None => tag,
+    }
+}
+
+/// Error returned when parsing a [`Filter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseFilterError {
+    directive: String,
+}
+
+impl Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid log filter directive: {:?}", self.directive)
+    }
+}
+
+impl Error for ParseFilterError {}
+
+/// Per-module level filter, made of `env_logger`-style directives separated
+/// by commas, such as `my_crate=debug,noisy_dep=warn`. A directive applies to
+/// the targets which are its module, or are inside it. When several
+/// directives apply, the one with the longest module wins. A directive
+/// without a module, such as `info`, sets the level of the targets which
+/// no other directive applies to, which is otherwise
+/// [`LevelFilter::Trace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Filter {
+    default: LevelFilter,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// Read a filter from an Android system property, which can be set with
+    /// `adb shell setprop`. Returns [`None`] if the property isn't set, or
+    /// outside of Android.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the property.
+    pub fn from_property(name: &str) -> std::result::Result<Option<Self>, ParseFilterError> {
+        match system_property(name) {
+            Some(value) if !value.is_empty() => value.parse().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the level enabled for a target.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Target of the record.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .filter(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// Get the most verbose level enabled for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            default: LevelFilter::Trace,
+            directives: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseFilterError;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let mut filter = Self::default();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let err = || ParseFilterError {
+                directive: directive.to_string(),
+            };
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let module = module.trim();
+                    if module.is_empty() {
+                        return Err(err());
+                    }
+                    let level = level.trim().parse().map_err(|_| err())?;
+                    filter.directives.retain(|(m, _)| m != module);
+                    filter.directives.push((module.to_string(), level));
+                }
+                None => filter.default = directive.parse().map_err(|_| err())?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(target_os = "android")]
+fn system_property(name: &str) -> Option<String> {
+    let name = CString::new(name).ok()?;
+    let mut value = [0 as c_char; libc::PROP_VALUE_MAX as usize];
+    let len = unsafe { libc::__system_property_get(name.as_ptr(), value.as_mut_ptr()) };
+    if len <= 0 {
+        return None;
+    }
+    let value = unsafe { std::ffi::CStr::from_ptr(value.as_ptr()) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "android"))]
+fn system_property(_name: &str) -> Option<String> {
+    None
+}
+
+/// How the key-values of records, or the fields of `tracing` events, are
+/// written after the message, so that they can be parsed by other tools.
+/// Requires the `kv` or `tracing` feature.
+#[cfg(any(feature = "kv", feature = "tracing"))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyValueFormat {
+    /// `key=value` pairs separated by spaces, with strings in quotes, such as
+    /// `id=3 name="foo"`.
+    #[default]
+    KeyValue,
+    /// A JSON object, such as `{"id":3,"name":"foo"}`.
+    Json,
+}
+
+// Writes key-values in a KeyValueFormat.
+#[cfg(any(feature = "kv", feature = "tracing"))]
+struct KeyValueWriter {
+    format: KeyValueFormat,
+    out: String,
+}
+
+#[cfg(any(feature = "kv", feature = "tracing"))]
+impl KeyValueWriter {
+    fn new(format: KeyValueFormat) -> Self {
+        Self {
+            format,
+            out: String::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.out.is_empty()
+    }
+
+    fn key(&mut self, key: &str) {
+        match self.format {
+            KeyValueFormat::KeyValue => {
+                if !self.out.is_empty() {
+                    self.out.push(' ');
+                }
+                self.out.push_str(key);
+                self.out.push('=');
+            }
+            KeyValueFormat::Json => {
+                if !self.out.is_empty() {
+                    self.out.push(',');
+                }
+                write_json_string(&mut self.out, key);
+                self.out.push(':');
+            }
+        }
+    }
+
+    fn str(&mut self, key: &str, value: &str) {
+        self.key(key);
+        match self.format {
+            KeyValueFormat::KeyValue => {
+                let _ = write!(self.out, "{:?}", value);
+            }
+            KeyValueFormat::Json => write_json_string(&mut self.out, value),
+        }
+    }
+
+    // Values which aren't numbers, booleans or strings are written without
+    // quotes as key-values, and as strings in JSON.
+    fn other(&mut self, key: &str, value: impl Display) {
+        match self.format {
+            KeyValueFormat::KeyValue => {
+                self.key(key);
+                let _ = write!(self.out, "{}", value);
+            }
+            KeyValueFormat::Json => self.str(key, &value.to_string()),
+        }
+    }
+
+    fn number(&mut self, key: &str, value: impl Display) {
+        self.key(key);
+        let _ = write!(self.out, "{}", value);
+    }
+
+    fn float(&mut self, key: &str, value: f64) {
+        if value.is_finite() {
+            self.number(key, value);
+        } else {
+            self.other(key, value);
+        }
+    }
+
+    fn output(&self) -> Cow<'_, str> {
+        match self.format {
+            KeyValueFormat::KeyValue => Cow::Borrowed(&self.out),
+            KeyValueFormat::Json => Cow::Owned(format!("{{{}}}", self.out)),
+        }
+    }
+}
+
+#[cfg(any(feature = "kv", feature = "tracing"))]
+fn write_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(feature = "kv")]
+struct KeyValueVisitor<'a>(&'a mut KeyValueWriter);
+
+#[cfg(feature = "kv")]
+impl<'kvs> ::log::kv::VisitSource<'kvs> for KeyValueVisitor<'_> {
+    fn visit_pair(
+        &mut self,
+        key: ::log::kv::Key<'kvs>,
+        value: ::log::kv::Value<'kvs>,
+    ) -> std::result::Result<(), ::log::kv::Error> {
+        value.visit(ValueVisitor {
+            writer: self.0,
+            key: key.as_str(),
+        })
+    }
+}
+
+#[cfg(feature = "kv")]
+struct ValueVisitor<'a, 'k> {
+    writer: &'a mut KeyValueWriter,
+    key: &'k str,
+}
+
+#[cfg(feature = "kv")]
+impl<'v> ::log::kv::VisitValue<'v> for ValueVisitor<'_, '_> {
+    fn visit_any(&mut self, value: ::log::kv::Value) -> std::result::Result<(), ::log::kv::Error> {
+        self.writer.other(self.key, value);
+        Ok(())
+    }
+
+    fn visit_u64(&mut self, value: u64) -> std::result::Result<(), ::log::kv::Error> {
+        self.writer.number(self.key, value);
+        Ok(())
+    }
+
+    fn visit_i64(&mut self, value: i64) -> std::result::Result<(), ::log::kv::Error> {
+        self.writer.number(self.key, value);
+        Ok(())
+    }
+
+    fn visit_f64(&mut self, value: f64) -> std::result::Result<(), ::log::kv::Error> {
+        self.writer.float(self.key, value);
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, value: bool) -> std::result::Result<(), ::log::kv::Error> {
+        self.writer.number(self.key, value);
+        Ok(())
+    }
+
+    fn visit_str(&mut self, value: &str) -> std::result::Result<(), ::log::kv::Error> {
+        self.writer.str(self.key, value);
+        Ok(())
+    }
+}
+
+/// Implementation of [`log::Log`] which writes to logcat. Each
+/// record is logged with a tag derived from its target by a
+/// [`TagPolicy`]. Install it as the global logger with [`init`], or call it
+/// directly.
+pub struct AndroidLogger {
+    backend: Backend,
+    tag_policy: TagPolicy,
+    filter: Filter,
+    is_loggable: bool,
+    #[cfg(feature = "kv")]
+    key_values: KeyValueFormat,
+    buffer: Option<Buffer>,
+}
+
+/// What a buffered [`AndroidLogger`] does with a record when its buffer is
+/// full. See [`AndroidLogger::with_buffer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Block the logging thread until there is room in the buffer.
+    Block,
+    /// Drop the record. The number of dropped records is logged once there
+    /// is room again.
+    Drop,
+}
+
+enum Message {
+    Record {
+        priority: Priority,
+        tag: String,
+        msg: String,
+        is_loggable: bool,
+    },
+    Flush(SyncSender<()>),
+}
+
+struct Buffer {
+    sender: SyncSender<Message>,
+    policy: OverflowPolicy,
+    dropped: Arc<AtomicUsize>,
+}
+
+fn drain_buffer(backend: Backend, receiver: mpsc::Receiver<Message>, dropped: Arc<AtomicUsize>) {
+    for message in receiver {
+        let dropped = dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            backend.write(
+                Priority::Warn,
+                "android_utils",
+                &format!("Dropped {} log records", dropped),
+            );
+        }
+        match message {
+            Message::Record {
+                priority,
+                tag,
+                msg,
+                is_loggable,
+            } => backend.write_checked(priority, &tag, &msg, is_loggable),
+            Message::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+impl AndroidLogger {
+    /// Create a logger, which uses [`TagPolicy::Target`].
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Where to write records.
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            tag_policy: TagPolicy::default(),
+            filter: Filter::default(),
+            is_loggable: true,
+            #[cfg(feature = "kv")]
+            key_values: KeyValueFormat::default(),
+            buffer: None,
+        }
+    }
+
+    /// Set whether the logger checks `Log.isLoggable()` for the tag of each
+    /// record, which lets `adb shell setprop log.tag.<tag>` silence it, but
+    /// costs a JNI call. Only applies to [`Backend::Jni`]. Enabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_loggable` - Whether to check `Log.isLoggable()`.
+    pub fn with_is_loggable(mut self, is_loggable: bool) -> Self {
+        self.is_loggable = is_loggable;
+        self
+    }
+
+    /// Set how the key-values of records are written after their message.
+    /// Requires the `kv` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format to write key-values in.
+    #[cfg(feature = "kv")]
+    pub fn with_key_values(mut self, format: KeyValueFormat) -> Self {
+        self.key_values = format;
+        self
+    }
+
+    /// Set which records the logger writes, by module. Records which the
+    /// filter disables are discarded in [`Log::enabled`], before any JNI
+    /// call.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Filter to apply to records.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Write records from a dedicated thread instead of the logging thread,
+    /// so that JNI calls stay off hot paths. Records are formatted on the
+    /// logging thread and queued in a bounded buffer. [`Log::flush`] waits
+    /// until the records logged before it have been written.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Number of records the buffer can hold.
+    /// * `policy` - What to do with a record when the buffer is full.
+    pub fn with_buffer(mut self, capacity: usize, policy: OverflowPolicy) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let backend = self.backend;
+        let worker_dropped = dropped.clone();
+        thread::Builder::new()
+            .name("android-utils-log".to_string())
+            .spawn(move || drain_buffer(backend, receiver, worker_dropped))?;
+        self.buffer = Some(Buffer {
+            sender,
+            policy,
+            dropped,
+        });
+        Ok(self)
+    }
+
+    /// Set how the logger derives tags from the targets of records.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_policy` - Policy to derive tags with.
+    pub fn with_tag_policy(mut self, tag_policy: TagPolicy) -> Self {
+        self.tag_policy = tag_policy;
+        self
+    }
+}
+
+impl Log for AndroidLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        #[allow(unused_mut)]
+        let mut msg = record.args().to_string();
+        #[cfg(feature = "kv")]
+        {
+            let mut writer = KeyValueWriter::new(self.key_values);
+            let _ = record.key_values().visit(&mut KeyValueVisitor(&mut writer));
+            if !writer.is_empty() {
+                msg.push(' ');
+                msg.push_str(&writer.output());
+            }
+        }
+        let tag = self.tag_policy.tag(record.target());
+        let priority = record.level().into();
+        if let Some(buffer) = &self.buffer {
+            let message = Message::Record {
+                priority,
+                tag: tag.into_owned(),
+                msg,
+                is_loggable: self.is_loggable,
+            };
+            let message = match buffer.policy {
+                OverflowPolicy::Block => match buffer.sender.send(message) {
+                    Ok(()) => return,
+                    Err(err) => err.0,
+                },
+                OverflowPolicy::Drop => match buffer.sender.try_send(message) {
+                    Ok(()) => return,
+                    Err(TrySendError::Full(_)) => {
+                        buffer.dropped.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    Err(TrySendError::Disconnected(message)) => message,
+                },
+            };
+            // The thread is gone, so write the record here instead.
+            if let Message::Record {
+                priority, tag, msg, ..
+            } = message
+            {
+                self.backend
+                    .write_checked(priority, &tag, &msg, self.is_loggable);
+            }
+        } else {
+            self.backend
+                .write_checked(priority, &tag, &msg, self.is_loggable);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(buffer) = &self.buffer {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            if buffer.sender.send(Message::Flush(sender)).is_ok() {
+                let _ = receiver.recv();
+            }
+        }
+    }
+}
+
+/// Install an [`AndroidLogger`] as the global logger of the
+/// [`log`] crate, and enable all levels. Fails if a global logger
+/// has already been installed.
+///
+/// # Arguments
+///
+/// * `backend` - Where to write records.
+pub fn init(backend: Backend) -> std::result::Result<(), SetLoggerError> {
+    init_logger(AndroidLogger::new(backend))
+}
+
+/// Install a configured [`AndroidLogger`] as the global logger of the
+/// [`log`] crate, and set the maximum level to the most verbose one its
+/// [`Filter`] enables. Fails if a global logger has already been installed.
+///
+/// # Arguments
+///
+/// * `logger` - Logger to install.
+pub fn init_logger(logger: AndroidLogger) -> std::result::Result<(), SetLoggerError> {
+    let max_level = logger.filter.max_level();
+    ::log::set_logger(Box::leak(Box::new(logger)))?;
+    ::log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Error returned by [`LoggerBuilder::init`].
+#[derive(Debug)]
+pub enum InitLoggerError {
+    /// The thread which writes buffered records could not be spawned.
+    Io(io::Error),
+    /// A global logger has already been installed.
+    SetLogger(SetLoggerError),
+}
+
+impl Display for InitLoggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not spawn logging thread: {}", err),
+            Self::SetLogger(err) => write!(f, "could not install logger: {}", err),
+        }
+    }
+}
+
+impl Error for InitLoggerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::SetLogger(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for InitLoggerError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<SetLoggerError> for InitLoggerError {
+    fn from(err: SetLoggerError) -> Self {
+        Self::SetLogger(err)
+    }
+}
+
+/// Options for an [`AndroidLogger`], which can be built with
+/// [`build`](Self::build), or installed as the global logger with
+/// [`init`](Self::init) or [`crate::init_with_logger`].
+pub struct LoggerBuilder {
+    backend: Backend,
+    filter: Filter,
+    tag_policy: TagPolicy,
+    is_loggable: bool,
+    #[cfg(feature = "kv")]
+    key_values: KeyValueFormat,
+    buffer: Option<(usize, OverflowPolicy)>,
+}
+
+impl LoggerBuilder {
+    /// Create options with the defaults of [`AndroidLogger::new`], which
+    /// writes records of all levels, unbuffered.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Where to write records.
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            filter: Filter::default(),
+            tag_policy: TagPolicy::default(),
+            is_loggable: true,
+            #[cfg(feature = "kv")]
+            key_values: KeyValueFormat::default(),
+            buffer: None,
+        }
+    }
+
+    /// Set the level of the targets which no directive of the filter
+    /// applies to. See [`Filter`].
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - Level to enable by default.
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.filter.default = level;
+        self
+    }
+
+    /// Set the filter of the logger, replacing the level set with
+    /// [`with_level`](Self::with_level). See [`AndroidLogger::with_filter`].
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Filter to apply to records.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// See [`AndroidLogger::with_tag_policy`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_policy` - Policy to derive tags with.
+    pub fn with_tag_policy(mut self, tag_policy: TagPolicy) -> Self {
+        self.tag_policy = tag_policy;
+        self
+    }
+
+    /// See [`AndroidLogger::with_is_loggable`].
+    ///
+    /// # Arguments
+    ///
+    /// * `is_loggable` - Whether to check `Log.isLoggable()`.
+    pub fn with_is_loggable(mut self, is_loggable: bool) -> Self {
+        self.is_loggable = is_loggable;
+        self
+    }
+
+    /// See [`AndroidLogger::with_key_values`]. Requires the `kv` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format to write key-values in.
+    #[cfg(feature = "kv")]
+    pub fn with_key_values(mut self, format: KeyValueFormat) -> Self {
+        self.key_values = format;
+        self
+    }
+
+    /// See [`AndroidLogger::with_buffer`].
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Number of records the buffer can hold.
+    /// * `policy` - What to do with a record when the buffer is full.
+    pub fn with_buffer(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.buffer = Some((capacity, policy));
+        self
+    }
+
+    /// Build the logger. Fails if it is buffered and the thread which
+    /// writes its records could not be spawned.
+    pub fn build(self) -> io::Result<AndroidLogger> {
+        let logger = AndroidLogger::new(self.backend)
+            .with_filter(self.filter)
+            .with_tag_policy(self.tag_policy)
+            .with_is_loggable(self.is_loggable);
+        #[cfg(feature = "kv")]
+        let logger = logger.with_key_values(self.key_values);
+        match self.buffer {
+            Some((capacity, policy)) => logger.with_buffer(capacity, policy),
+            None => Ok(logger),
+        }
+    }
+
+    /// Build the logger and install it as the global logger with
+    /// [`init_logger`].
+    pub fn init(self) -> std::result::Result<(), InitLoggerError> {
+        init_logger(self.build()?)?;
+        Ok(())
+    }
+}
+
+static PANIC_HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+// Whether install_panic_hook() has been called, so that the crash module
+// can leave logging panics to it instead of logging each one twice.
+pub(crate) fn panic_hook_installed() -> bool {
+    PANIC_HOOK_INSTALLED.load(Ordering::SeqCst)
+}
+
+/// Install a panic hook which logs panics at [`Priority::Error`], along with
+/// the name of the panicking thread and a backtrace, so that they aren't lost
+/// when standard error isn't visible. Each line is logged as a separate
+/// message, since logcat cuts off long messages. The previously installed
+/// panic hook is still called afterwards.
+///
+/// # Arguments
+///
+/// * `tag` - Tag to log panics with, truncated to [`MAX_TAG_LEN`]
+///   characters.
+/// * `backend` - Where to write panics.
+pub fn install_panic_hook(tag: &str, backend: Backend) {
+    PANIC_HOOK_INSTALLED.store(true, Ordering::SeqCst);
+    let tag = truncate_tag(Cow::Borrowed(tag)).into_owned();
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "thread '{}' {}",
+            std::thread::current().name().unwrap_or("<unnamed>"),
+            info
+        );
+        let _ = write!(report, "backtrace:\n{}", Backtrace::force_capture());
+        for line in report.lines() {
+            backend.write(Priority::Error, &tag, line);
+        }
+        previous(info);
+    }));
+}
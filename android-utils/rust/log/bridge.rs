@@ -0,0 +1,189 @@
+//! Bridge from Java logs to Rust, for apps which want to process the logs of
+//! both languages in one place, for example to filter or upload them. Java
+//! code which logs with `io.github.gedgygedgy.rust.android.util.RustLog`
+//! instead of `android.util.Log` has its messages written to logcat as
+//! usual, and also passed to the handler set with [`set_handler`]. Messages
+//! logged directly with `android.util.Log`, including by [`AndroidLogger`],
+//! are not passed to the handler, so forwarding them to the [`log`] crate
+//! with [`forward_to_log`] doesn't recurse.
+//!
+//! [`AndroidLogger`]: super::AndroidLogger
+
+use super::Priority;
+use futures::{channel::mpsc, Stream};
+use jni::{
+    errors::Result,
+    objects::{JClass, JObject, JString, JThrowable},
+    sys::jint,
+    JNIEnv, NativeMethod,
+};
+use std::{
+    convert::TryFrom,
+    ffi::c_void,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+
+const RUST_LOG_CLASS: &str = "io/github/gedgygedgy/rust/android/util/RustLog";
+
+/// Message logged with `RustLog`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JavaLogRecord {
+    /// Priority of the message. Priorities which are not one of the `Log`
+    /// constants are reported as [`Priority::Info`].
+    pub priority: Priority,
+    /// Tag of the message.
+    pub tag: String,
+    /// Message that was logged.
+    pub msg: String,
+    /// Stack trace of the `java.lang.Throwable` logged with the message, as
+    /// formatted by `Log.getStackTraceString()`.
+    pub stack_trace: Option<String>,
+}
+
+type Handler = Arc<dyn Fn(JavaLogRecord) + Send + Sync>;
+
+static HANDLER: Mutex<Option<Handler>> = Mutex::new(None);
+
+fn set_bridged<'a: 'b, 'b>(env: &'b JNIEnv<'a>, bridged: bool) -> Result<()> {
+    let field = env.get_static_field_id(RUST_LOG_CLASS, "bridged", "Z")?;
+    env.set_static_field(RUST_LOG_CLASS, field, bridged.into())
+}
+
+/// Pass the messages logged with `RustLog` to a handler, replacing the
+/// previous one. The handler is called synchronously on the thread which
+/// logged the message.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `handler` - Function to call with each message.
+pub fn set_handler<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    handler: impl Fn(JavaLogRecord) + Send + Sync + 'static,
+) -> Result<()> {
+    *HANDLER.lock().unwrap() = Some(Arc::new(handler));
+    set_bridged(env, true)
+}
+
+/// Stop passing the messages logged with `RustLog` to the handler set with
+/// [`set_handler`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn clear_handler<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<()> {
+    set_bridged(env, false)?;
+    *HANDLER.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Set a handler which forwards the messages logged with `RustLog` to the
+/// global logger of the [`log`] crate, with their tag as the target and the
+/// stack trace, if any, on the lines after the message.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn forward_to_log<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<()> {
+    set_handler(env, |record| {
+        let level = record.priority.into();
+        if level > ::log::max_level() {
+            return;
+        }
+        let msg = match &record.stack_trace {
+            Some(stack_trace) => format!("{}\n{}", record.msg, stack_trace),
+            None => record.msg,
+        };
+        ::log::logger().log(
+            &::log::Record::builder()
+                .args(format_args!("{}", msg))
+                .level(level)
+                .target(&record.tag)
+                .build(),
+        );
+    })
+}
+
+/// Set a handler which sends the messages logged with `RustLog` to a
+/// [`Stream`]. Dropping the stream doesn't remove the handler, so call
+/// [`clear_handler`] when it is no longer needed.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn stream<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<impl Stream<Item = JavaLogRecord> + Send> {
+    let (sender, receiver) = mpsc::unbounded();
+    set_handler(env, move |record| {
+        let _ = sender.unbounded_send(record);
+    })?;
+    Ok(receiver)
+}
+
+fn get_string<'a: 'b, 'b>(env: &'b JNIEnv<'a>, s: JString<'a>) -> Result<String> {
+    if s.is_null() {
+        Ok(String::new())
+    } else {
+        Ok(env.get_string(s)?.into())
+    }
+}
+
+fn java_log_record<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    priority: jint,
+    tag: JString<'a>,
+    msg: JString<'a>,
+    throwable: JThrowable<'a>,
+) -> Result<JavaLogRecord> {
+    let stack_trace = if throwable.is_null() {
+        None
+    } else {
+        let stack_trace = env
+            .call_static_method(
+                super::LOG_CLASS,
+                "getStackTraceString",
+                "(Ljava/lang/Throwable;)Ljava/lang/String;",
+                &[JObject::from(throwable).into()],
+            )?
+            .l()?;
+        Some(get_string(env, stack_trace.into())?)
+    };
+    Ok(JavaLogRecord {
+        priority: Priority::try_from(priority).unwrap_or(Priority::Info),
+        tag: get_string(env, tag)?,
+        msg: get_string(env, msg)?,
+        stack_trace,
+    })
+}
+
+extern "system" fn native_log<'a>(
+    env: JNIEnv<'a>,
+    _class: JClass<'a>,
+    priority: jint,
+    tag: JString<'a>,
+    msg: JString<'a>,
+    throwable: JThrowable<'a>,
+) {
+    let handler = match HANDLER.lock().unwrap().clone() {
+        Some(handler) => handler,
+        None => return,
+    };
+    let record = match java_log_record(&env, priority, tag, msg, throwable) {
+        Ok(record) => record,
+        Err(_) => return,
+    };
+    // Logging shouldn't crash the app, so a panicking handler only loses
+    // the message.
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(record)));
+}
+
+pub(crate) fn init<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<()> {
+    env.register_native_methods(
+        RUST_LOG_CLASS,
+        &[NativeMethod {
+            name: "nativeLog".into(),
+            sig: "(ILjava/lang/String;Ljava/lang/String;Ljava/lang/Throwable;)V".into(),
+            fn_ptr: native_log as *mut c_void,
+        }],
+    )
+}
@@ -0,0 +1,193 @@
+//! [`tracing_subscriber`] layer which writes events to logcat, for crates
+//! which use [`tracing`] rather than [`log`]. Requires the `tracing`
+//! feature.
+//!
+//! Each event is logged with a tag derived from its target by a
+//! [`TagPolicy`], and a message made of the spans it is in, from the
+//! outermost to the innermost, followed by the event's message and fields:
+//!
+//! ```text
+//! connect{host="example.com"}: request{id=3}: sending body len=42
+//! ```
+//!
+//! With [`KeyValueFormat::Json`], fields are written as JSON objects instead:
+//!
+//! ```text
+//! connect{"host":"example.com"}: request{"id":3}: sending body {"len":42}
+//! ```
+
+use super::{Backend, KeyValueFormat, KeyValueWriter, Priority, TagPolicy};
+use ::tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Level, Subscriber,
+};
+use std::fmt::{self, Write as _};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+impl From<Level> for Priority {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::ERROR => Self::Error,
+            Level::WARN => Self::Warn,
+            Level::INFO => Self::Info,
+            Level::DEBUG => Self::Debug,
+            Level::TRACE => Self::Verbose,
+        }
+    }
+}
+
+// Fields of a span, stored in its extensions.
+struct SpanFields(FieldFormatter);
+
+// Formats fields with a KeyValueWriter. The `message` field of events is kept
+// separately, since it is logged without its key.
+struct FieldFormatter {
+    split_message: bool,
+    message: String,
+    fields: KeyValueWriter,
+}
+
+impl FieldFormatter {
+    fn new(format: KeyValueFormat, split_message: bool) -> Self {
+        Self {
+            split_message,
+            message: String::new(),
+            fields: KeyValueWriter::new(format),
+        }
+    }
+
+    fn is_message(&self, field: &Field) -> bool {
+        self.split_message && field.name() == "message"
+    }
+}
+
+impl Visit for FieldFormatter {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.fields.float(field.name(), value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.number(field.name(), value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.number(field.name(), value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.number(field.name(), value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if self.is_message(field) {
+            self.message.push_str(value);
+        } else {
+            self.fields.str(field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if self.is_message(field) {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            self.fields.other(field.name(), format_args!("{:?}", value));
+        }
+    }
+}
+
+/// [`Layer`] which writes events to logcat. Spans are not logged by
+/// themselves, but prefix the events inside them.
+pub struct AndroidLayer {
+    backend: Backend,
+    tag_policy: TagPolicy,
+    key_values: KeyValueFormat,
+}
+
+impl AndroidLayer {
+    /// Create a layer, which uses [`TagPolicy::Target`].
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Where to write events.
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            tag_policy: TagPolicy::default(),
+            key_values: KeyValueFormat::default(),
+        }
+    }
+
+    /// Set how the layer derives tags from the targets of events.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_policy` - Policy to derive tags with.
+    pub fn with_tag_policy(mut self, tag_policy: TagPolicy) -> Self {
+        self.tag_policy = tag_policy;
+        self
+    }
+
+    /// Set how the fields of events and spans are written.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format to write fields in.
+    pub fn with_key_values(mut self, format: KeyValueFormat) -> Self {
+        self.key_values = format;
+        self
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for AndroidLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut fields = FieldFormatter::new(self.key_values, false);
+            attrs.record(&mut fields);
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+                values.record(fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut msg = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                msg.push_str(span.name());
+                if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                    if !fields.fields.is_empty() {
+                        match self.key_values {
+                            KeyValueFormat::KeyValue => {
+                                let _ = write!(msg, "{{{}}}", fields.fields.output());
+                            }
+                            KeyValueFormat::Json => msg.push_str(&fields.fields.output()),
+                        }
+                    }
+                }
+                msg.push_str(": ");
+            }
+        }
+
+        let mut fields = FieldFormatter::new(self.key_values, true);
+        event.record(&mut fields);
+        msg.push_str(&fields.message);
+        if !fields.fields.is_empty() {
+            if !fields.message.is_empty() {
+                msg.push(' ');
+            }
+            msg.push_str(&fields.fields.output());
+        }
+
+        let metadata = event.metadata();
+        let tag = self.tag_policy.tag(metadata.target());
+        self.backend.write((*metadata.level()).into(), &tag, &msg);
+    }
+}
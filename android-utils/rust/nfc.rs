@@ -0,0 +1,133 @@
+use jni::{
+    descriptors::Desc,
+    errors::Result,
+    objects::{JClass, JObject},
+    sys::jint,
+    JNIEnv,
+};
+use std::sync::Arc;
+
+/// `android.nfc.cardemulation.HostApduService.DEACTIVATION_LINK_LOSS`.
+pub const DEACTIVATION_LINK_LOSS: jint = 0;
+
+/// `android.nfc.cardemulation.HostApduService.DEACTIVATION_DESELECTED`.
+pub const DEACTIVATION_DESELECTED: jint = 1;
+
+/// Trait for Rust implementations of
+/// `android.nfc.cardemulation.HostApduService`. Register your Rust service
+/// using [`register_host_apdu_service`].
+#[allow(unused_variables)]
+pub trait RustHostApduService: Send + Sync {
+    /// Called by `HostApduService.processCommandApdu()`. Returns the response
+    /// APDU to send back to the reader.
+    fn process_command_apdu<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, apdu: &[u8]) -> Vec<u8>;
+
+    /// Called by `HostApduService.onDeactivated()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` - One of the `DEACTIVATION_*` constants.
+    fn on_deactivated<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, reason: jint) {}
+}
+
+/// Register a service as an
+/// `io.github.gedgygedgy.rust.android.nfc.cardemulation.RustHostApduService`.
+/// The `factory` closure is called when `Service.onCreate()` is called, and
+/// the object created by it is dropped when `Service.onDestroy()` is called.
+pub fn register_host_apdu_service<'a: 'b, 'b, T: RustHostApduService + 'static>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
+) -> Result<()> {
+    let class = env.auto_local(class.lookup(env)?);
+
+    let on_create_hook =
+        env.auto_local(jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+            let service = Arc::new(factory(env, arg));
+
+            let service_clone = service.clone();
+            let process_command_apdu_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    let apdu = env.convert_byte_array(arg.into_inner()).unwrap();
+                    let response = service_clone.process_command_apdu(env, &apdu);
+                    env.byte_array_from_slice(&response).unwrap().into()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "processCommandApduHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&process_command_apdu_hook).into(),
+            )
+            .unwrap();
+
+            let on_deactivated_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    let reason = env
+                        .call_method(arg, "intValue", "()I", &[])
+                        .unwrap()
+                        .i()
+                        .unwrap();
+                    service.on_deactivated(env, reason);
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onDeactivatedHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_deactivated_hook).into(),
+            )
+            .unwrap();
+
+            JObject::null()
+        })?);
+
+    let on_create_hooks = env.auto_local(
+        env.get_static_field(
+            "io/github/gedgygedgy/rust/android/nfc/cardemulation/RustHostApduService",
+            "onCreateHooks",
+            "Ljava/util/HashMap;",
+        )?
+        .l()?,
+    );
+    env.call_method(
+        &on_create_hooks,
+        "put",
+        "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        &[(&class).into(), (&on_create_hook).into()],
+    )?;
+
+    Ok(())
+}
+
+/// Unregister a service as an
+/// `io.github.gedgygedgy.rust.android.nfc.cardemulation.RustHostApduService`.
+pub fn unregister_host_apdu_service<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+) -> Result<()> {
+    let class = env.auto_local(class.lookup(env)?);
+
+    let on_create_hooks = env.auto_local(
+        env.get_static_field(
+            "io/github/gedgygedgy/rust/android/nfc/cardemulation/RustHostApduService",
+            "onCreateHooks",
+            "Ljava/util/HashMap;",
+        )?
+        .l()?,
+    );
+    let on_create_hook = env
+        .call_method(
+            &on_create_hooks,
+            "remove",
+            "(Ljava/lang/Object;)Ljava/lang/Object;",
+            &[(&class).into()],
+        )?
+        .l()?;
+    env.call_method(on_create_hook, "close", "()V", &[])?;
+
+    Ok(())
+}
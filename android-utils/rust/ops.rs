@@ -0,0 +1,330 @@
+use futures::{future, Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{GlobalRef, JClass, JObject, JString},
+    sys::jint,
+    JNIEnv,
+};
+use jni_utils::{
+    future::{JFuture, JSendFuture},
+    stream::{JSendStream, JStream},
+    task::JPollResult,
+};
+use std::{
+    convert::TryFrom,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Call made on a listener created by [`listener_stream`].
+pub struct ListenerCall {
+    /// Name of the method that was called.
+    pub method: String,
+    /// Arguments the method was called with. Primitive arguments are boxed,
+    /// and `null` arguments are null references.
+    pub args: Vec<GlobalRef>,
+}
+
+/// Create an object implementing a Java listener interface and an
+/// accompanying stream of [`ListenerCall`]s made on it.
+///
+/// The object is a `java.lang.reflect.Proxy`. Methods with a return value
+/// return `null`, `false` or `0`. The `Object` methods `equals()`,
+/// `hashCode()` and `toString()` use identity and are not put on the stream.
+///
+/// See [`listener_stream!`](crate::listener_stream!) for a typed version.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `interface` - Listener interface to implement.
+pub fn listener_stream<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    interface: JClass<'a>,
+) -> Result<(JObject<'a>, impl Stream<Item = Result<ListenerCall>> + Send)> {
+    let handler = env.new_object(
+        "io/github/gedgygedgy/rust/android/ops/RustListenerProxy",
+        "()V",
+        &[],
+    )?;
+    let proxy = env
+        .call_method(
+            handler,
+            "newProxy",
+            "(Ljava/lang/Class;)Ljava/lang/Object;",
+            &[interface.into()],
+        )?
+        .l()?;
+    let stream = env
+        .call_method(
+            handler,
+            "getCallStream",
+            "()Lio/github/gedgygedgy/rust/stream/Stream;",
+            &[],
+        )?
+        .l()?;
+    let stream = JSendStream::try_from(JStream::from_env(env, stream)?)?;
+
+    let vm = env.get_java_vm()?;
+    let mapped_stream = stream.map(move |item| -> Result<_> {
+        let item = item?;
+        let item = item.as_obj();
+        let env = vm.get_env()?;
+
+        let method = JString::from(env.get_field(item, "method", "Ljava/lang/String;")?.l()?);
+        let method = env.get_string(method)?.into();
+        let arg_array = env.get_field(item, "args", "[Ljava/lang/Object;")?.l()?;
+        let len = env.get_array_length(arg_array.into_inner())?;
+        let mut args = Vec::with_capacity(len as usize);
+        for i in 0..len {
+            let arg = env.get_object_array_element(arg_array.into_inner(), i)?;
+            args.push(env.new_global_ref(arg)?);
+            env.delete_local_ref(arg)?;
+        }
+
+        Ok(ListenerCall { method, args })
+    });
+
+    Ok((proxy, mapped_stream))
+}
+
+/// Stream of typed listener events. Returned by the `listener()` function
+/// generated by [`listener_stream!`](crate::listener_stream!).
+pub struct ListenerStream<T>(Pin<Box<dyn Stream<Item = Result<T>> + Send>>);
+
+impl<T> Stream for ListenerStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.as_mut().poll_next(cx)
+    }
+}
+
+#[doc(hidden)]
+pub fn typed_listener_stream<'a: 'b, 'b, T: Send + 'static>(
+    env: &'b JNIEnv<'a>,
+    interface: &str,
+    map: fn(ListenerCall) -> Option<T>,
+) -> Result<(JObject<'a>, ListenerStream<T>)> {
+    let interface = env.find_class(interface)?;
+    let (proxy, stream) = listener_stream(env, interface)?;
+    let stream = stream.filter_map(move |call| future::ready(call.map(map).transpose()));
+    Ok((proxy, ListenerStream(Box::pin(stream))))
+}
+
+/// Define an enum of events for a Java listener interface, along with a
+/// `listener()` function which creates an object implementing the interface
+/// and a [`ListenerStream`] of the events.
+///
+/// Each variant names the method it is created by, and the fields it takes
+/// from the method's arguments, in order. Each field is a
+/// [`GlobalRef`](jni::objects::GlobalRef). Calls to methods which are not
+/// listed are ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// android_utils::listener_stream! {
+///     /// Events of `android.location.LocationListener`.
+///     pub enum LocationEvent: "android/location/LocationListener" {
+///         /// `onLocationChanged()`.
+///         LocationChanged = "onLocationChanged"(location),
+///         /// `onProviderDisabled()`.
+///         ProviderDisabled = "onProviderDisabled"(provider),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! listener_stream {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident: $interface:literal {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $method:literal($($field:ident),* $(,)?)
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant { $($field: ::jni::objects::GlobalRef),* },
+            )*
+        }
+
+        impl $name {
+            #[doc = concat!("Create an object implementing `", $interface, "` and an")]
+            /// accompanying stream of events captured by it.
+            #[allow(dead_code)]
+            $vis fn listener<'a: 'b, 'b>(
+                env: &'b ::jni::JNIEnv<'a>,
+            ) -> ::jni::errors::Result<(
+                ::jni::objects::JObject<'a>,
+                $crate::ops::ListenerStream<Self>,
+            )> {
+                #[allow(unused_mut, unused_variables)]
+                fn map(call: $crate::ops::ListenerCall) -> ::std::option::Option<$name> {
+                    let mut args = call.args.into_iter();
+                    match call.method.as_str() {
+                        $(
+                            $method => ::std::option::Option::Some($name::$variant {
+                                $($field: args.next()?),*
+                            }),
+                        )*
+                        _ => ::std::option::Option::None,
+                    }
+                }
+
+                $crate::ops::typed_listener_stream(env, $interface, map)
+            }
+        }
+    };
+}
+
+fn outcome_future<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    interface: JClass<'a>,
+    error_method: Option<&str>,
+) -> Result<(
+    JObject<'a>,
+    impl Future<Output = Result<std::result::Result<GlobalRef, GlobalRef>>> + Send,
+)> {
+    let error_method = match error_method {
+        Some(name) => env.new_string(name)?.into(),
+        None => JObject::null(),
+    };
+    let handler = env.new_object(
+        "io/github/gedgygedgy/rust/android/ops/RustCallbackFuture",
+        "(Ljava/lang/String;)V",
+        &[error_method.into()],
+    )?;
+    let callback = env
+        .call_method(
+            handler,
+            "newProxy",
+            "(Ljava/lang/Class;)Ljava/lang/Object;",
+            &[interface.into()],
+        )?
+        .l()?;
+    let future = env
+        .call_method(
+            handler,
+            "getOutcomeFuture",
+            "()Lio/github/gedgygedgy/rust/future/Future;",
+            &[],
+        )?
+        .l()?;
+    let future = JSendFuture::try_from(JFuture::from_env(env, future)?)?;
+
+    let vm = env.get_java_vm()?;
+    Ok((callback, async move {
+        let result = future.await?;
+        let env = vm.get_env()?;
+        let outcome = JPollResult::from_env(&env, result.as_obj())?.get()?;
+        let error = env.get_field(outcome, "error", "Z")?.z()?;
+        let value =
+            env.new_global_ref(env.get_field(outcome, "value", "Ljava/lang/Object;")?.l()?)?;
+        Ok(if error { Err(value) } else { Ok(value) })
+    }))
+}
+
+/// Create an object implementing a single-completion Java callback
+/// interface, such as `java.util.function.Consumer`, and a future which
+/// resolves to the first argument of the first call made on it. If the
+/// method has no arguments, the future resolves to a null reference.
+/// Primitive arguments are boxed. Subsequent calls are ignored.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `interface` - Callback interface to implement.
+pub fn callback_future<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    interface: JClass<'a>,
+) -> Result<(JObject<'a>, impl Future<Output = Result<GlobalRef>> + Send)> {
+    let (callback, future) = outcome_future(env, interface, None)?;
+    Ok((callback, async move {
+        Ok(match future.await? {
+            Ok(value) => value,
+            Err(value) => value,
+        })
+    }))
+}
+
+/// Like [`callback_future`], but for callbacks which take an `int`, such as
+/// `java.util.function.IntConsumer`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `interface` - Callback interface to implement.
+pub fn int_callback_future<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    interface: JClass<'a>,
+) -> Result<(JObject<'a>, impl Future<Output = Result<jint>> + Send)> {
+    let vm = env.get_java_vm()?;
+    let (callback, future) = callback_future(env, interface)?;
+    Ok((callback, async move {
+        let value = future.await?;
+        let env = vm.get_env()?;
+        env.call_method(value.as_obj(), "intValue", "()I", &[])?.i()
+    }))
+}
+
+/// Like [`callback_future`], but for callbacks which take a `boolean`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `interface` - Callback interface to implement.
+pub fn bool_callback_future<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    interface: JClass<'a>,
+) -> Result<(JObject<'a>, impl Future<Output = Result<bool>> + Send)> {
+    let vm = env.get_java_vm()?;
+    let (callback, future) = callback_future(env, interface)?;
+    Ok((callback, async move {
+        let value = future.await?;
+        let env = vm.get_env()?;
+        env.call_method(value.as_obj(), "booleanValue", "()Z", &[])?
+            .z()
+    }))
+}
+
+/// Like [`callback_future`], but for callbacks which take no arguments, such
+/// as `java.lang.Runnable`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `interface` - Callback interface to implement.
+pub fn void_callback_future<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    interface: JClass<'a>,
+) -> Result<(JObject<'a>, impl Future<Output = Result<()>> + Send)> {
+    let (callback, future) = callback_future(env, interface)?;
+    Ok((callback, async move {
+        future.await?;
+        Ok(())
+    }))
+}
+
+/// Create an `android.os.OutcomeReceiver` and a future which resolves to
+/// `Ok` with the result passed to `onResult()`, or `Err` with the
+/// `java.lang.Throwable` passed to `onError()`. `OutcomeReceiver` was added
+/// in API level 31, so this returns an error on older versions.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn outcome_receiver_future<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+) -> Result<(
+    JObject<'a>,
+    impl Future<Output = Result<std::result::Result<GlobalRef, GlobalRef>>> + Send,
+)> {
+    let interface = env.find_class("android/os/OutcomeReceiver")?;
+    outcome_future(env, interface, Some("onError"))
+}
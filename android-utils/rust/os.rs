@@ -1,53 +1,192 @@
-use futures::task::{FutureObj, LocalFutureObj, LocalSpawn, Spawn, SpawnError};
+use crate::{
+    refs::{flush_refs, DeferredGlobalRef},
+    util::sdk_int,
+};
+use futures::{
+    channel::oneshot,
+    task::{FutureObj, LocalFutureObj, LocalSpawn, Spawn, SpawnError},
+};
 use jni::{
     errors::Result,
     objects::{GlobalRef, JMethodID, JObject},
     signature::{JavaType, Primitive},
-    JNIEnv, JavaVM,
+    sys::{jint, jlong},
+    JNIEnv,
 };
 use jni_utils::stream::JStream;
 use once_cell::sync::OnceCell;
 use std::{
+    cell::Cell,
     future::Future,
     pin::Pin,
     sync::{
-        mpsc::{channel, Sender},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc, Mutex, Weak,
     },
     task::{Context, Poll, Wake, Waker},
+    time::{Duration, Instant},
+};
+
+pub mod battery;
+mod binder;
+pub mod build;
+pub mod bundle;
+#[cfg(feature = "serde")]
+pub mod bundle_serde;
+mod clock;
+pub mod debug;
+mod delay;
+pub mod fd;
+mod global_handler;
+mod looper;
+#[cfg(feature = "tokio")]
+mod looper_runtime;
+mod memory_file;
+mod message;
+mod message_queue;
+mod messenger;
+mod parcel;
+#[cfg(feature = "serde")]
+pub mod parcel_serde;
+pub mod perf_hint;
+mod persistable_bundle;
+pub mod process;
+mod screen;
+mod shared_memory;
+pub mod strict_mode;
+pub mod system_clock;
+pub mod thermal;
+pub mod trace;
+
+pub use binder::{
+    rust_binder, JBinder, RustBinder, FIRST_CALL_TRANSACTION, FLAG_ONEWAY, LAST_CALL_TRANSACTION,
 };
+pub use bundle::JBundle;
+#[cfg(feature = "chrono")]
+pub use clock::chrono_now;
+#[cfg(feature = "time")]
+pub use clock::time_now;
+pub use clock::{current_time_millis, time_events, time_zone_id, time_zone_offset, TimeEvent};
+pub use delay::{delay, Delay};
+pub use global_handler::GlobalHandler;
+pub use looper::JLooper;
+#[cfg(feature = "tokio")]
+pub use looper_runtime::LooperRuntime;
+pub use memory_file::{FdMapping, JMemoryFile};
+pub use message::JMessage;
+pub use message_queue::JMessageQueue;
+pub use messenger::{async_messenger, JMessenger};
+pub use parcel::JParcel;
+pub use persistable_bundle::JPersistableBundle;
+pub use screen::{screen_events, screen_state, ScreenEvent, ScreenState};
+pub use shared_memory::{
+    JSharedMemory, SharedMemoryMapping, PROT_EXEC, PROT_NONE, PROT_READ, PROT_WRITE,
+};
+pub use system_clock::uptime;
 
 /// Wrapper for [`JObject`]s that contain `android.os.Handler`. Provides method
 /// to post `java.lang.Runnable`s to the `Handler`.
 ///
-/// Looks up the class and method IDs on creation rather than for every method
-/// call.
+/// Uses the method IDs cached by [`init()`](crate::init), or looks them up on
+/// creation if it has not been called, rather than for every method call.
 pub struct JHandler<'a: 'b, 'b> {
     internal: JObject<'a>,
     post: JMethodID<'a>,
+    post_delayed: JMethodID<'a>,
+    post_at_time: JMethodID<'a>,
+    send_message: JMethodID<'a>,
+    send_empty_message: JMethodID<'a>,
+    send_message_delayed: JMethodID<'a>,
+    asynchronous: bool,
     env: &'b JNIEnv<'a>,
 }
 
 impl<'a: 'b, 'b> JHandler<'a, 'b> {
-    /// Create a [`JHandler`] from the environment and an object. This looks up
-    /// the necessary class and method IDs to call all of the methods on it so
-    /// that extra work doesn't need to be done on every method call.
+    /// Create a [`JHandler`] from the environment and an object. This gets
+    /// the necessary method IDs to call all of the methods on it so that extra
+    /// work doesn't need to be done on every method call.
     ///
     /// # Arguments
     ///
     /// * `env` - Java environment to use.
     /// * `obj` - Object to wrap.
     pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
-        let class = env.auto_local(env.find_class("android/os/Handler")?);
-
-        let post = env.get_method_id(&class, "post", "(Ljava/lang/Runnable;)Z")?;
+        crate::capture_java_vm(env);
+        let ids = crate::cache::handler_ids(env)?;
         Ok(Self {
             internal: obj,
-            post,
+            post: ids.post.get(),
+            post_delayed: ids.post_delayed.get(),
+            post_at_time: ids.post_at_time.get(),
+            send_message: ids.send_message.get(),
+            send_empty_message: ids.send_empty_message.get(),
+            send_message_delayed: ids.send_message_delayed.get(),
+            asynchronous: false,
             env,
         })
     }
 
+    /// Create an asynchronous `Handler` for a `Looper`. Messages sent to an
+    /// asynchronous `Handler` are not held back by synchronization barriers,
+    /// such as the ones posted while a frame is being drawn.
+    ///
+    /// Uses `Handler.createAsync()` on API level 28 and above, and the hidden
+    /// asynchronous constructor on older versions, like `HandlerCompat` does.
+    /// If that constructor is not accessible, a plain `Handler` is created
+    /// instead, and only tasks spawned through [`spawner`](Self::spawner) are
+    /// marked as asynchronous.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `looper` - `android.os.Looper` to create the `Handler` for.
+    pub fn create_async(env: &'b JNIEnv<'a>, looper: JObject<'a>) -> Result<Self> {
+        let obj = if sdk_int(env)? >= 28 {
+            env.call_static_method(
+                "android/os/Handler",
+                "createAsync",
+                "(Landroid/os/Looper;)Landroid/os/Handler;",
+                &[looper.into()],
+            )?
+            .l()?
+        } else {
+            match env.new_object(
+                "android/os/Handler",
+                "(Landroid/os/Looper;Landroid/os/Handler$Callback;Z)V",
+                &[looper.into(), JObject::null().into(), true.into()],
+            ) {
+                Ok(obj) => obj,
+                Err(_) => {
+                    env.exception_clear()?;
+                    env.new_object(
+                        "android/os/Handler",
+                        "(Landroid/os/Looper;)V",
+                        &[looper.into()],
+                    )?
+                }
+            }
+        };
+        Ok(Self::from_env(env, obj)?.with_async(true))
+    }
+
+    /// Mark this [`JHandler`] as wrapping an asynchronous `Handler`. Tasks
+    /// spawned through [`spawner`](Self::spawner) are then posted with
+    /// [`SpawnOptions::asynchronous`] set.
+    ///
+    /// # Arguments
+    ///
+    /// * `asynchronous` - Whether the `Handler` is asynchronous.
+    pub fn with_async(mut self, asynchronous: bool) -> Self {
+        self.asynchronous = asynchronous;
+        self
+    }
+
+    /// Check whether this [`JHandler`] is marked as asynchronous.
+    pub fn is_async(&self) -> bool {
+        self.asynchronous
+    }
+
     /// Post a `java.lang.Runnable` to the `Handler`.
     ///
     /// # Arguments
@@ -64,13 +203,158 @@ impl<'a: 'b, 'b> JHandler<'a, 'b> {
             .z()
     }
 
+    /// Post a `java.lang.Runnable` to the `Handler`, to be run after a delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `obj` - `Runnable` to post.
+    /// * `delay` - Time to wait before running the `Runnable`.
+    pub fn post_delayed(&self, obj: JObject<'a>, delay: Duration) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.post_delayed,
+                JavaType::Primitive(Primitive::Boolean),
+                &[obj.into(), duration_millis(delay).into()],
+            )?
+            .z()
+    }
+
+    /// Post a `java.lang.Runnable` to the `Handler`, to be run at a given
+    /// time on the `android.os.SystemClock.uptimeMillis()` time base. Use
+    /// [`uptime`] to get the current time on that base.
+    ///
+    /// # Arguments
+    ///
+    /// * `obj` - `Runnable` to post.
+    /// * `uptime` - Time at which to run the `Runnable`.
+    pub fn post_at_time(&self, obj: JObject<'a>, uptime: Duration) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.post_at_time,
+                JavaType::Primitive(Primitive::Boolean),
+                &[obj.into(), duration_millis(uptime).into()],
+            )?
+            .z()
+    }
+
+    /// Send an `android.os.Message` to the `Handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - Message to send.
+    pub fn send_message(&self, msg: &JMessage<'a, '_>) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.send_message,
+                JavaType::Primitive(Primitive::Boolean),
+                &[(**msg).into()],
+            )?
+            .z()
+    }
+
+    /// Send a message with only the `what` field set to the `Handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `what` - Value of the `what` field.
+    pub fn send_empty_message(&self, what: jint) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.send_empty_message,
+                JavaType::Primitive(Primitive::Boolean),
+                &[what.into()],
+            )?
+            .z()
+    }
+
+    /// Send an `android.os.Message` to the `Handler`, to be delivered after a
+    /// delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - Message to send.
+    /// * `delay` - Time to wait before delivering the message.
+    pub fn send_message_delayed(&self, msg: &JMessage<'a, '_>, delay: Duration) -> Result<bool> {
+        self.env
+            .call_method_unchecked(
+                self.internal,
+                self.send_message_delayed,
+                JavaType::Primitive(Primitive::Boolean),
+                &[(**msg).into(), duration_millis(delay).into()],
+            )?
+            .z()
+    }
+
+    /// Post a `java.lang.Runnable` to the `Handler`, optionally after a delay,
+    /// and get a [`PostToken`] which can remove it before it runs. Returns
+    /// [`None`] if the `Runnable` could not be posted.
+    ///
+    /// # Arguments
+    ///
+    /// * `obj` - `Runnable` to post.
+    /// * `delay` - Time to wait before running the `Runnable`.
+    pub fn post_cancellable(&self, obj: JObject<'a>, delay: Duration) -> Result<Option<PostToken>> {
+        if !self.post_delayed(obj, delay)? {
+            return Ok(None);
+        }
+        Ok(Some(PostToken {
+            handler: DeferredGlobalRef::new(self.env.new_global_ref(self.internal)?),
+            runnable: DeferredGlobalRef::new(self.env.new_global_ref(obj)?),
+        }))
+    }
+
+    /// Remove all pending posts of a `java.lang.Runnable` from the `Handler`
+    /// with `Handler.removeCallbacks()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `obj` - `Runnable` to remove.
+    pub fn remove_callbacks(&self, obj: JObject<'a>) -> Result<()> {
+        self.env.call_method(
+            self.internal,
+            "removeCallbacks",
+            "(Ljava/lang/Runnable;)V",
+            &[obj.into()],
+        )?;
+        Ok(())
+    }
+
+    /// Remove all pending messages with a given `what` from the `Handler`
+    /// with `Handler.removeMessages()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `what` - Value of the `what` field of the messages to remove.
+    pub fn remove_messages(&self, what: jint) -> Result<()> {
+        self.env
+            .call_method(self.internal, "removeMessages", "(I)V", &[what.into()])?;
+        Ok(())
+    }
+
     /// Creates an object that can be used to spawn async functions. The
     /// returned object implements [`Spawn`] and [`LocalSpawn`].
     pub fn spawner(self) -> JHandlerSpawn<'a, 'b> {
-        JHandlerSpawn(self)
+        let options = SpawnOptions {
+            asynchronous: self.asynchronous,
+            ..Default::default()
+        };
+        JHandlerSpawn {
+            handler: self,
+            poll_budget: None,
+            options,
+            counters: Default::default(),
+        }
     }
 }
 
+fn duration_millis(duration: Duration) -> jlong {
+    duration.as_millis().min(jlong::MAX as u128) as jlong
+}
+
 impl<'a: 'b, 'b> From<JHandler<'a, 'b>> for JObject<'a> {
     fn from(handler: JHandler<'a, 'b>) -> Self {
         handler.internal
@@ -87,97 +371,103 @@ impl<'a: 'b, 'b> ::std::ops::Deref for JHandler<'a, 'b> {
 
 static FALLBACK_SENDER: OnceCell<Mutex<Option<Sender<Arc<HandlerWaker>>>>> = OnceCell::new();
 
-fn init_fallback_thread<'a: 'b, 'b>(env: &'b JNIEnv<'a>) {
-    FALLBACK_SENDER.get_or_init(|| {
+fn init_fallback_thread<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<()> {
+    FALLBACK_SENDER.get_or_try_init(|| -> Result<_> {
         let (sender, receiver) = channel::<Arc<HandlerWaker>>();
 
-        let thread_class = env.auto_local(env.find_class("java/lang/Thread").unwrap());
-        let thread_ctor = env
-            .get_method_id(
-                &thread_class,
-                "<init>",
-                "(Ljava/lang/Runnable;Ljava/lang/String;)V",
-            )
-            .unwrap();
-
-        let shutdown_runnable = env.auto_local(
-            jni_utils::ops::fn_once_runnable(env, |_env, _obj| {
-                let mut guard = FALLBACK_SENDER.get().unwrap().lock().unwrap();
-                guard.take();
-            })
-            .unwrap(),
-        );
-        let shutdown_name = env.auto_local(env.new_string("wake-shutdown").unwrap());
-        let shutdown_thread = env.auto_local(
-            env.new_object_unchecked(
-                &thread_class,
-                thread_ctor,
-                &[(&shutdown_runnable).into(), (&shutdown_name).into()],
-            )
-            .unwrap(),
-        );
+        let thread_class = env.auto_local(env.find_class("java/lang/Thread")?);
+        let thread_ctor = env.get_method_id(
+            &thread_class,
+            "<init>",
+            "(Ljava/lang/Runnable;Ljava/lang/String;)V",
+        )?;
+
+        let shutdown_runnable =
+            env.auto_local(jni_utils::ops::fn_once_runnable(env, |_env, _obj| {
+                if let Some(sender) = FALLBACK_SENDER.get() {
+                    sender.lock().unwrap().take();
+                }
+            })?);
+        let shutdown_name = env.auto_local(env.new_string("wake-shutdown")?);
+        let shutdown_thread = env.auto_local(env.new_object_unchecked(
+            &thread_class,
+            thread_ctor,
+            &[(&shutdown_runnable).into(), (&shutdown_name).into()],
+        )?);
         let runtime = env.auto_local(
             env.call_static_method(
                 "java/lang/Runtime",
                 "getRuntime",
                 "()Ljava/lang/Runtime;",
                 &[],
-            )
-            .unwrap()
-            .l()
-            .unwrap(),
+            )?
+            .l()?,
         );
         env.call_method(
             &runtime,
             "addShutdownHook",
             "(Ljava/lang/Thread;)V",
             &[(&shutdown_thread).into()],
-        )
-        .unwrap();
+        )?;
 
-        let fallback_runnable = env.auto_local(
-            jni_utils::ops::fn_once_runnable(env, move |_env, _obj| {
+        let fallback_runnable =
+            env.auto_local(jni_utils::ops::fn_once_runnable(env, move |env, _obj| {
                 for waker in receiver {
-                    waker.wake_direct().unwrap();
+                    if let Err(err) = waker.wake_direct() {
+                        log::error!("Failed to wake task from fallback thread: {}", err);
+                    }
+                    drop(waker);
+                    flush_refs(env);
                 }
-            })
-            .unwrap(),
-        );
-        let fallback_name = env.auto_local(env.new_string("wake-fallback").unwrap());
-        let fallback_thread = env.auto_local(
-            env.new_object_unchecked(
-                &thread_class,
-                thread_ctor,
-                &[(&fallback_runnable).into(), (&fallback_name).into()],
-            )
-            .unwrap(),
-        );
-        env.call_method(&fallback_thread, "setDaemon", "(Z)V", &[true.into()])
-            .unwrap();
-        env.call_method(&fallback_thread, "start", "()V", &[])
-            .unwrap();
+            })?);
+        let fallback_name = env.auto_local(env.new_string("wake-fallback")?);
+        let fallback_thread = env.auto_local(env.new_object_unchecked(
+            &thread_class,
+            thread_ctor,
+            &[(&fallback_runnable).into(), (&fallback_name).into()],
+        )?);
+        env.call_method(&fallback_thread, "setDaemon", "(Z)V", &[true.into()])?;
+        env.call_method(&fallback_thread, "start", "()V", &[])?;
 
-        Mutex::new(Some(sender))
-    });
+        Ok(Mutex::new(Some(sender)))
+    })?;
+    Ok(())
 }
 
 struct HandlerWaker {
-    vm: JavaVM,
-    handler: GlobalRef,
-    runnable: GlobalRef,
+    state: Arc<TaskState>,
+    runnable: DeferredGlobalRef,
+    counters: Arc<Counters>,
 }
 
 impl HandlerWaker {
     fn wake_direct(&self) -> Result<()> {
-        let env = self.vm.get_env()?;
-        let handler = JHandler::from_env(&env, self.handler.as_obj())?;
-        handler.post(self.runnable.as_obj())?;
+        let env = crate::env()?;
+        post_with_options(
+            &env,
+            self.state.handler.as_obj(),
+            self.runnable.as_obj(),
+            &self.state.options,
+        )?;
         Ok(())
     }
 
     fn wake_fallback(self: Arc<Self>) {
-        let guard = FALLBACK_SENDER.get().unwrap().lock().unwrap();
-        guard.as_ref().unwrap().send(self).unwrap();
+        self.counters.fallback_wakes.fetch_add(1, Ordering::Relaxed);
+        let guard = match FALLBACK_SENDER.get() {
+            Some(sender) => sender.lock().unwrap(),
+            None => {
+                log::error!("Failed to wake task: fallback thread is not running");
+                return;
+            }
+        };
+        // The sender is gone once the JVM is shutting down, at which point
+        // the task will never run again anyway.
+        if let Some(sender) = guard.as_ref() {
+            if sender.send(self).is_err() {
+                log::error!("Failed to wake task: fallback thread has exited");
+            }
+        }
     }
 }
 
@@ -195,6 +485,306 @@ impl Wake for HandlerWaker {
     }
 }
 
+#[derive(Default)]
+struct Counters {
+    spawned: AtomicU64,
+    pending: AtomicU64,
+    polls: AtomicU64,
+    poll_nanos: AtomicU64,
+    fallback_wakes: AtomicU64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> HandlerMetrics {
+        let polls = self.polls.load(Ordering::Relaxed);
+        let poll_nanos = self.poll_nanos.load(Ordering::Relaxed);
+        HandlerMetrics {
+            tasks_spawned: self.spawned.load(Ordering::Relaxed),
+            tasks_pending: self.pending.load(Ordering::Relaxed),
+            polls,
+            average_poll_duration: Duration::from_nanos(poll_nanos.checked_div(polls).unwrap_or(0)),
+            fallback_wakes: self.fallback_wakes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct PendingTask(Arc<Counters>);
+
+impl PendingTask {
+    fn new(counters: Arc<Counters>) -> Self {
+        counters.spawned.fetch_add(1, Ordering::Relaxed);
+        counters.pending.fetch_add(1, Ordering::Relaxed);
+        Self(counters)
+    }
+}
+
+impl Drop for PendingTask {
+    fn drop(&mut self) {
+        self.0.pending.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of the counters of a [`JHandlerSpawn`]. Obtained by calling
+/// [`JHandlerSpawn::metrics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HandlerMetrics {
+    /// Number of tasks that have been spawned.
+    pub tasks_spawned: u64,
+    /// Number of tasks that have been spawned but have not completed or been
+    /// aborted yet.
+    pub tasks_pending: u64,
+    /// Number of times a task has been polled.
+    pub polls: u64,
+    /// Average time taken by a poll.
+    pub average_poll_duration: Duration,
+    /// Number of wakes that went through the fallback thread because the
+    /// waking thread could not post to the `Handler` directly.
+    pub fallback_wakes: u64,
+}
+
+/// Logs the metrics of a [`JHandlerSpawn`] periodically. Obtained by calling
+/// [`JHandlerSpawn::log_metrics`]. Logging stops when this object is dropped.
+pub struct MetricsLogger {
+    _stop: Sender<()>,
+}
+
+/// Options applied each time a task spawned by a [`JHandlerSpawn`] is posted
+/// to the `Handler`, both when it is spawned and when it is woken. Set them
+/// with [`JHandlerSpawn::with_options`].
+#[derive(Clone, Default)]
+pub struct SpawnOptions {
+    /// Post the task with `Handler.sendMessageAtFrontOfQueue()` instead of
+    /// at the back of the queue.
+    pub front_of_queue: bool,
+    /// Mark the task's messages as asynchronous with
+    /// `Message.setAsynchronous()`, so that they are not held back by
+    /// synchronization barriers.
+    pub asynchronous: bool,
+    /// Delay each post of the task by this amount. Ignored if
+    /// [`front_of_queue`](Self::front_of_queue) is set.
+    pub delay: Option<Duration>,
+    /// Token to register the task with, so that it can be cancelled along
+    /// with all other tasks registered with the same token.
+    pub token: Option<SpawnToken>,
+}
+
+impl SpawnOptions {
+    fn is_plain(&self) -> bool {
+        !self.front_of_queue && !self.asynchronous && self.delay.is_none() && self.token.is_none()
+    }
+}
+
+struct TokenInner {
+    obj: GlobalRef,
+    tasks: Mutex<Vec<Weak<TaskState>>>,
+}
+
+/// Token which can be used to cancel a group of tasks at once. Tasks are
+/// registered with a token through [`SpawnOptions::token`], and their
+/// messages carry the token's Java object in `Message.obj`.
+#[derive(Clone)]
+pub struct SpawnToken(Arc<TokenInner>);
+
+impl SpawnToken {
+    /// Create a new token.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn new<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env.new_object("java/lang/Object", "()V", &[])?;
+        Ok(Self(Arc::new(TokenInner {
+            obj: env.new_global_ref(obj)?,
+            tasks: Mutex::new(Vec::new()),
+        })))
+    }
+
+    /// Get the Java object of this token.
+    pub fn as_obj(&self) -> JObject<'_> {
+        self.0.obj.as_obj()
+    }
+
+    /// Abort all of the tasks registered with this token, as if by
+    /// [`AbortHandle::abort`].
+    pub fn cancel(&self) -> Result<()> {
+        let tasks = std::mem::take(&mut *self.0.tasks.lock().unwrap());
+        for task in tasks.into_iter().filter_map(|task| task.upgrade()) {
+            AbortHandle(task).abort()?;
+        }
+        Ok(())
+    }
+
+    fn register(&self, task: &Arc<TaskState>) {
+        let mut tasks = self.0.tasks.lock().unwrap();
+        tasks.retain(|task| task.strong_count() > 0);
+        tasks.push(Arc::downgrade(task));
+    }
+}
+
+fn post_with_options<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    handler: JObject<'a>,
+    runnable: JObject<'a>,
+    options: &SpawnOptions,
+) -> Result<bool> {
+    if options.is_plain() {
+        return JHandler::from_env(env, handler)?.post(runnable);
+    }
+
+    let message = env.auto_local(
+        env.call_static_method(
+            "android/os/Message",
+            "obtain",
+            "(Landroid/os/Handler;Ljava/lang/Runnable;)Landroid/os/Message;",
+            &[handler.into(), runnable.into()],
+        )?
+        .l()?,
+    );
+    if options.asynchronous {
+        env.call_method(&message, "setAsynchronous", "(Z)V", &[true.into()])?;
+    }
+    if let Some(token) = &options.token {
+        env.set_field(&message, "obj", "Ljava/lang/Object;", token.as_obj().into())?;
+    }
+    if options.front_of_queue {
+        env.call_method(
+            handler,
+            "sendMessageAtFrontOfQueue",
+            "(Landroid/os/Message;)Z",
+            &[(&message).into()],
+        )?
+        .z()
+    } else {
+        let delay = duration_millis(options.delay.unwrap_or_default());
+        env.call_method(
+            handler,
+            "sendMessageDelayed",
+            "(Landroid/os/Message;J)Z",
+            &[(&message).into(), delay.into()],
+        )?
+        .z()
+    }
+}
+
+struct TaskState {
+    aborted: AtomicBool,
+    handler: DeferredGlobalRef,
+    runnable: OnceCell<DeferredGlobalRef>,
+    options: SpawnOptions,
+}
+
+/// Token for a `java.lang.Runnable` posted with
+/// [`JHandler::post_cancellable`]. Use [`JHandlerSpawn::spawn_abortable`]
+/// to get the equivalent for spawned futures.
+pub struct PostToken {
+    handler: DeferredGlobalRef,
+    runnable: DeferredGlobalRef,
+}
+
+impl PostToken {
+    /// Remove the `Runnable` from the `Handler` if it hasn't run yet. Does
+    /// nothing if it already ran.
+    pub fn cancel(&self) -> Result<()> {
+        let env = crate::env()?;
+        JHandler::from_env(&env, self.handler.as_obj())?.remove_callbacks(self.runnable.as_obj())
+    }
+}
+
+/// Handle to a task spawned with [`JHandlerSpawn::spawn_abortable`] or
+/// [`JHandlerSpawn::spawn_local_abortable`].
+///
+/// Aborting the task removes its pending `Runnable` from the `Handler` with
+/// `Handler.removeCallbacks()`, and closes the `Runnable` on the `Handler`'s
+/// thread so that the future and the references it holds are dropped
+/// instead of lingering in the `MessageQueue`.
+#[derive(Clone)]
+pub struct AbortHandle(Arc<TaskState>);
+
+impl AbortHandle {
+    /// Abort the task. If the task is currently being polled, it will finish
+    /// its current poll and then be dropped. Does nothing if the task has
+    /// already been aborted.
+    pub fn abort(&self) -> Result<()> {
+        if self.0.aborted.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let runnable = match self.0.runnable.get() {
+            Some(runnable) => runnable.clone(),
+            None => return Ok(()),
+        };
+        let env = crate::env()?;
+        let handler = self.0.handler.as_obj();
+        env.call_method(
+            handler,
+            "removeCallbacks",
+            "(Ljava/lang/Runnable;)V",
+            &[runnable.as_obj().into()],
+        )?;
+        let close = jni_utils::ops::fn_once_runnable(&env, move |env, _obj| {
+            let _ = env.call_method(runnable.as_obj(), "close", "()V", &[]);
+        })?;
+        JHandler::from_env(&env, handler)?.post(close)?;
+        Ok(())
+    }
+
+    /// Check whether [`abort`](Self::abort) has been called.
+    pub fn is_aborted(&self) -> bool {
+        self.0.aborted.load(Ordering::SeqCst)
+    }
+}
+
+/// Handle to the output of a task spawned with
+/// [`JHandlerSpawn::spawn_with_handle`] or
+/// [`JHandlerSpawn::spawn_local_with_handle`]. Awaiting it resolves to the
+/// task's output, or to [`Canceled`](oneshot::Canceled) if the task was
+/// aborted or dropped before it finished.
+///
+/// Dropping the handle aborts the task if it hasn't finished yet. Call
+/// [`detach`](Self::detach) to let the task keep running without the handle.
+pub struct JoinHandle<T> {
+    receiver: oneshot::Receiver<T>,
+    abort: AbortHandle,
+    detached: bool,
+}
+
+impl<T> JoinHandle<T> {
+    /// Get the [`AbortHandle`] of the task.
+    pub fn abort_handle(&self) -> &AbortHandle {
+        &self.abort
+    }
+
+    /// Abort the task, as if by [`AbortHandle::abort`].
+    pub fn abort(&self) -> Result<()> {
+        self.abort.abort()
+    }
+
+    /// Drop the handle without aborting the task, letting it run to
+    /// completion in the background. Returns the [`AbortHandle`] of the
+    /// task, which can still be used to abort it.
+    pub fn detach(mut self) -> AbortHandle {
+        self.detached = true;
+        self.abort.clone()
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        // The sender is only still around if the task hasn't finished.
+        if !self.detached && matches!(self.receiver.try_recv(), Ok(None)) {
+            let _ = self.abort.abort();
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = std::result::Result<T, oneshot::Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx)
+    }
+}
+
 /// Object that implements [`Spawn`] and [`LocalSpawn`] for [`JHandler`].
 /// Obtained by calling [`JHandler::spawner`].
 ///
@@ -206,40 +796,243 @@ impl Wake for HandlerWaker {
 /// async tasks to the `Handler` on behalf of native threads. This fallback
 /// thread will be started upon spawning an async task from a [`JHandlerSpawn`]
 /// for the first time, and will be shut down when the JVM shuts down.
-pub struct JHandlerSpawn<'a: 'b, 'b>(JHandler<'a, 'b>);
+///
+/// JNI errors while spawning, polling or waking a task don't panic. They are
+/// logged with the [`log`] crate, and spawning reports them as
+/// [`SpawnError::shutdown()`].
+pub struct JHandlerSpawn<'a: 'b, 'b> {
+    handler: JHandler<'a, 'b>,
+    poll_budget: Option<Duration>,
+    options: SpawnOptions,
+    counters: Arc<Counters>,
+}
 
 impl<'a: 'b, 'b> JHandlerSpawn<'a, 'b> {
+    /// Set a budget for each poll of the tasks spawned by this object. Once
+    /// a poll has taken longer than the budget, [`consume_budget`] yields
+    /// back to the `Handler`, so that long-running tasks don't starve other
+    /// messages on the same `Looper`.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Maximum time a poll should take.
+    pub fn with_poll_budget(mut self, budget: Duration) -> Self {
+        self.poll_budget = Some(budget);
+        self
+    }
+
+    /// Set the [`SpawnOptions`] used to post the tasks spawned by this
+    /// object. [`SpawnOptions::asynchronous`] is always set if the
+    /// [`JHandler`] is asynchronous.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Options to post tasks with.
+    pub fn with_options(mut self, options: SpawnOptions) -> Self {
+        self.options = SpawnOptions {
+            asynchronous: options.asynchronous || self.handler.asynchronous,
+            ..options
+        };
+        self
+    }
+
+    /// Get a snapshot of the counters of the tasks spawned by this object.
+    pub fn metrics(&self) -> HandlerMetrics {
+        self.counters.snapshot()
+    }
+
+    /// Log the counters of the tasks spawned by this object at the `info`
+    /// level every `interval`, using the [`log`] crate. Logging stops when
+    /// the returned object is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Time between each log message.
+    pub fn log_metrics(&self, interval: Duration) -> MetricsLogger {
+        let (stop, receiver) = channel::<()>();
+        let counters = self.counters.clone();
+        std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(interval) {
+                log::info!("{:?}", counters.snapshot());
+            }
+        });
+        MetricsLogger { _stop: stop }
+    }
+
+    fn task_state(&self) -> Result<Arc<TaskState>> {
+        let state = Arc::new(TaskState {
+            aborted: AtomicBool::new(false),
+            handler: DeferredGlobalRef::new(
+                self.handler.env.new_global_ref(self.handler.internal)?,
+            ),
+            runnable: OnceCell::new(),
+            options: self.options.clone(),
+        });
+        if let Some(token) = &self.options.token {
+            token.register(&state);
+        }
+        Ok(state)
+    }
+
     fn wrap_future(
         &self,
         mut fut: impl Future<Output = ()> + Unpin,
+        state: Arc<TaskState>,
     ) -> impl for<'c, 'd> FnMut(&'d JNIEnv<'c>, JObject<'c>) {
         let waker_cell = OnceCell::new();
-        let handler = self.0.env.new_global_ref(self.0.internal).unwrap();
+        let poll_budget = self.poll_budget;
+        let counters = self.counters.clone();
+        let pending = PendingTask::new(counters.clone());
         move |env, obj| {
-            let handler = handler.clone();
-            let waker = waker_cell.get_or_init(move || {
-                let runnable = env.new_global_ref(obj).unwrap();
+            let _pending = &pending;
+            if state.aborted.load(Ordering::SeqCst) {
+                close_runnable(env, obj);
+                return;
+            }
+
+            let waker_state = state.clone();
+            let waker_counters = counters.clone();
+            let waker = match waker_cell.get_or_try_init(move || -> Result<_> {
+                let runnable = DeferredGlobalRef::new(env.new_global_ref(obj)?);
                 let arc = Arc::new(HandlerWaker {
-                    vm: env.get_java_vm().unwrap(),
-                    handler,
+                    state: waker_state,
                     runnable,
+                    counters: waker_counters,
                 });
-                Waker::from(arc)
-            });
+                Ok(Waker::from(arc))
+            }) {
+                Ok(waker) => waker,
+                Err(err) => {
+                    // Without a waker, the task could never be woken again.
+                    log::error!("Failed to create waker, dropping task: {}", err);
+                    state.aborted.store(true, Ordering::SeqCst);
+                    close_runnable(env, obj);
+                    return;
+                }
+            };
             let mut context = Context::from_waker(waker);
             let pin = Pin::new(&mut fut);
-            match pin.poll(&mut context) {
-                Poll::Ready(()) => {
-                    env.call_method(obj, "close", "()V", &[]).unwrap();
-                }
+            let start = Instant::now();
+            let poll = {
+                let _deadline = PollDeadline::set(poll_budget.map(|b| start + b));
+                pin.poll(&mut context)
+            };
+            counters.polls.fetch_add(1, Ordering::Relaxed);
+            counters
+                .poll_nanos
+                .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+            match poll {
+                Poll::Ready(()) => close_runnable(env, obj),
                 Poll::Pending => {}
             }
+            flush_refs(env);
         }
     }
 
-    fn post_spawn(&self, runnable: JObject<'a>) -> std::result::Result<(), SpawnError> {
-        init_fallback_thread(self.0.env);
-        if self.0.post(runnable).unwrap() {
+    fn post_task(
+        &self,
+        runnable: JObject<'a>,
+        state: Arc<TaskState>,
+    ) -> std::result::Result<AbortHandle, SpawnError> {
+        let runnable_ref = self
+            .handler
+            .env
+            .new_global_ref(runnable)
+            .map_err(spawn_error)?;
+        let _ = state.runnable.set(DeferredGlobalRef::new(runnable_ref));
+        self.post_spawn(runnable, &state.options)?;
+        Ok(AbortHandle(state))
+    }
+
+    /// Spawn a future on the `Handler`, returning an [`AbortHandle`] which
+    /// can be used to cancel it.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn_abortable(
+        &self,
+        fut: impl Future<Output = ()> + Send + 'static,
+    ) -> std::result::Result<AbortHandle, SpawnError> {
+        let state = self.task_state().map_err(spawn_error)?;
+        let runnable = jni_utils::ops::fn_mut_runnable(
+            self.handler.env,
+            self.wrap_future(FutureObj::new(Box::new(fut)), state.clone()),
+        )
+        .map_err(spawn_error)?;
+        self.post_task(runnable, state)
+    }
+
+    /// Spawn a non-[`Send`] future on the `Handler`, returning an
+    /// [`AbortHandle`] which can be used to cancel it.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn_local_abortable(
+        &self,
+        fut: impl Future<Output = ()> + 'static,
+    ) -> std::result::Result<AbortHandle, SpawnError> {
+        let state = self.task_state().map_err(spawn_error)?;
+        let runnable = jni_utils::ops::fn_mut_runnable_local(
+            self.handler.env,
+            self.wrap_future(LocalFutureObj::new(Box::new(fut)), state.clone()),
+        )
+        .map_err(spawn_error)?;
+        self.post_task(runnable, state)
+    }
+
+    /// Spawn a future on the `Handler`, returning a [`JoinHandle`] which can
+    /// be awaited to get its output.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn_with_handle<T: Send + 'static>(
+        &self,
+        fut: impl Future<Output = T> + Send + 'static,
+    ) -> std::result::Result<JoinHandle<T>, SpawnError> {
+        let (sender, receiver) = oneshot::channel();
+        let abort = self.spawn_abortable(async move {
+            let _ = sender.send(fut.await);
+        })?;
+        Ok(JoinHandle {
+            receiver,
+            abort,
+            detached: false,
+        })
+    }
+
+    /// Spawn a non-[`Send`] future on the `Handler`, returning a
+    /// [`JoinHandle`] which can be awaited to get its output.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn_local_with_handle<T: 'static>(
+        &self,
+        fut: impl Future<Output = T> + 'static,
+    ) -> std::result::Result<JoinHandle<T>, SpawnError> {
+        let (sender, receiver) = oneshot::channel();
+        let abort = self.spawn_local_abortable(async move {
+            let _ = sender.send(fut.await);
+        })?;
+        Ok(JoinHandle {
+            receiver,
+            abort,
+            detached: false,
+        })
+    }
+
+    fn post_spawn(
+        &self,
+        runnable: JObject<'a>,
+        options: &SpawnOptions,
+    ) -> std::result::Result<(), SpawnError> {
+        init_fallback_thread(self.handler.env).map_err(spawn_error)?;
+        if post_with_options(self.handler.env, self.handler.internal, runnable, options)
+            .map_err(spawn_error)?
+        {
             Ok(())
         } else {
             Err(SpawnError::shutdown())
@@ -247,9 +1040,22 @@ impl<'a: 'b, 'b> JHandlerSpawn<'a, 'b> {
     }
 }
 
+/// Log a JNI error that prevented a task from being spawned. [`SpawnError`]
+/// can't carry a cause, so the error is reported as a shutdown.
+fn spawn_error(err: jni::errors::Error) -> SpawnError {
+    log::error!("Failed to spawn task on Handler: {}", err);
+    SpawnError::shutdown()
+}
+
+fn close_runnable<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) {
+    if let Err(err) = env.call_method(obj, "close", "()V", &[]) {
+        log::error!("Failed to close task runnable: {}", err);
+    }
+}
+
 impl<'a: 'b, 'b> From<JHandlerSpawn<'a, 'b>> for JHandler<'a, 'b> {
     fn from(spawn: JHandlerSpawn<'a, 'b>) -> Self {
-        spawn.0
+        spawn.handler
     }
 }
 
@@ -257,14 +1063,13 @@ impl<'a: 'b, 'b> ::std::ops::Deref for JHandlerSpawn<'a, 'b> {
     type Target = JHandler<'a, 'b>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.handler
     }
 }
 
 impl<'a: 'b, 'b> Spawn for JHandlerSpawn<'a, 'b> {
     fn spawn_obj(&self, fut: FutureObj<'static, ()>) -> std::result::Result<(), SpawnError> {
-        let runnable = jni_utils::ops::fn_mut_runnable(self.0.env, self.wrap_future(fut)).unwrap();
-        self.post_spawn(runnable)
+        self.spawn_abortable(fut).map(|_| ())
     }
 }
 
@@ -273,12 +1078,66 @@ impl<'a: 'b, 'b> LocalSpawn for JHandlerSpawn<'a, 'b> {
         &self,
         fut: LocalFutureObj<'static, ()>,
     ) -> std::result::Result<(), SpawnError> {
-        let runnable =
-            jni_utils::ops::fn_mut_runnable_local(self.0.env, self.wrap_future(fut)).unwrap();
-        self.post_spawn(runnable)
+        self.spawn_local_abortable(fut).map(|_| ())
+    }
+}
+
+thread_local! {
+    static POLL_DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+struct PollDeadline(Option<Instant>);
+
+impl PollDeadline {
+    fn set(deadline: Option<Instant>) -> Self {
+        Self(POLL_DEADLINE.with(|cell| cell.replace(deadline)))
+    }
+}
+
+impl Drop for PollDeadline {
+    fn drop(&mut self) {
+        POLL_DEADLINE.with(|cell| cell.set(self.0));
+    }
+}
+
+/// Future returned by [`yield_now`] and [`consume_budget`].
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
     }
 }
 
+/// Yield back to the executor. When awaited on a task spawned by a
+/// [`JHandlerSpawn`], the task is reposted to the back of the `Handler`'s
+/// queue, allowing other messages to run before it is polled again.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+/// Yield back to the executor if the current poll has exceeded the budget
+/// set with [`JHandlerSpawn::with_poll_budget`]. Otherwise, completes
+/// immediately. Long-running tasks should await this between chunks of
+/// work.
+pub fn consume_budget() -> YieldNow {
+    let exceeded = POLL_DEADLINE.with(|cell| match cell.get() {
+        Some(deadline) => Instant::now() >= deadline,
+        None => false,
+    });
+    YieldNow { yielded: !exceeded }
+}
+
 /// Create an `android.os.Handler.Callback` and an accompanying `Stream` which
 /// can be used to asynchronously get messages as the callback is called.
 pub fn async_handler_callback<'a: 'b, 'b>(
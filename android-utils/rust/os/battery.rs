@@ -0,0 +1,351 @@
+//! Battery state from the `android.intent.action.BATTERY_CHANGED` sticky
+//! broadcast and `android.os.BatteryManager`.
+//!
+//! [`battery_state`] and [`battery_changes`] read the broadcast, which is
+//! available on every API level. [`property`] and [`is_charging`] query
+//! `BatteryManager` directly, which is cheaper but only covers a few values.
+
+use crate::{
+    content::broadcast_stream,
+    util::{sdk_int, system_service},
+};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    sys::jint,
+    JNIEnv,
+};
+use std::time::Duration;
+
+const ACTION_BATTERY_CHANGED: &str = "android.intent.action.BATTERY_CHANGED";
+
+/// Charging status of the battery, from `BatteryManager.EXTRA_STATUS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatteryStatus {
+    /// `BatteryManager.BATTERY_STATUS_UNKNOWN`.
+    Unknown,
+    /// `BatteryManager.BATTERY_STATUS_CHARGING`.
+    Charging,
+    /// `BatteryManager.BATTERY_STATUS_DISCHARGING`.
+    Discharging,
+    /// `BatteryManager.BATTERY_STATUS_NOT_CHARGING`. Plugged in, but not
+    /// charging.
+    NotCharging,
+    /// `BatteryManager.BATTERY_STATUS_FULL`.
+    Full,
+}
+
+impl BatteryStatus {
+    fn from_raw(raw: jint) -> Self {
+        match raw {
+            2 => Self::Charging,
+            3 => Self::Discharging,
+            4 => Self::NotCharging,
+            5 => Self::Full,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Health of the battery, from `BatteryManager.EXTRA_HEALTH`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatteryHealth {
+    /// `BatteryManager.BATTERY_HEALTH_UNKNOWN`.
+    Unknown,
+    /// `BatteryManager.BATTERY_HEALTH_GOOD`.
+    Good,
+    /// `BatteryManager.BATTERY_HEALTH_OVERHEAT`.
+    Overheat,
+    /// `BatteryManager.BATTERY_HEALTH_DEAD`.
+    Dead,
+    /// `BatteryManager.BATTERY_HEALTH_OVER_VOLTAGE`.
+    OverVoltage,
+    /// `BatteryManager.BATTERY_HEALTH_UNSPECIFIED_FAILURE`.
+    UnspecifiedFailure,
+    /// `BatteryManager.BATTERY_HEALTH_COLD`.
+    Cold,
+}
+
+impl BatteryHealth {
+    fn from_raw(raw: jint) -> Self {
+        match raw {
+            2 => Self::Good,
+            3 => Self::Overheat,
+            4 => Self::Dead,
+            5 => Self::OverVoltage,
+            6 => Self::UnspecifiedFailure,
+            7 => Self::Cold,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Power source the device is plugged into, from
+/// `BatteryManager.EXTRA_PLUGGED`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerSource {
+    /// `BatteryManager.BATTERY_PLUGGED_AC`.
+    Ac,
+    /// `BatteryManager.BATTERY_PLUGGED_USB`.
+    Usb,
+    /// `BatteryManager.BATTERY_PLUGGED_WIRELESS`.
+    Wireless,
+    /// `BatteryManager.BATTERY_PLUGGED_DOCK`.
+    Dock,
+}
+
+impl PowerSource {
+    fn from_raw(raw: jint) -> Option<Self> {
+        match raw {
+            1 => Some(Self::Ac),
+            2 => Some(Self::Usb),
+            4 => Some(Self::Wireless),
+            8 => Some(Self::Dock),
+            _ => None,
+        }
+    }
+}
+
+/// Snapshot of the battery. Obtained from [`battery_state`] or
+/// [`battery_changes`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatteryState {
+    /// Whether the device has a battery.
+    pub present: bool,
+    /// Current charge level, from 0 to [`scale`](Self::scale).
+    pub level: i32,
+    /// Maximum charge level.
+    pub scale: i32,
+    /// Charging status.
+    pub status: BatteryStatus,
+    /// Health of the battery.
+    pub health: BatteryHealth,
+    /// Power source the device is plugged into, or [`None`] if it is running
+    /// on battery.
+    pub plugged: Option<PowerSource>,
+    /// Voltage, in millivolts.
+    pub voltage: i32,
+    /// Temperature, in tenths of a degree Celsius.
+    pub temperature: i32,
+    /// Technology of the battery, such as `Li-ion`.
+    pub technology: Option<String>,
+}
+
+impl BatteryState {
+    /// Get the charge level as a fraction from `0.0` to `1.0`, or [`None`]
+    /// if the level is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        if self.level < 0 || self.scale <= 0 {
+            None
+        } else {
+            Some(self.level as f32 / self.scale as f32)
+        }
+    }
+}
+
+fn battery_state_from_intent<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    intent: JObject<'a>,
+) -> Result<BatteryState> {
+    let int = |name: &str, default: jint| -> Result<jint> {
+        let name = env.new_string(name)?;
+        env.call_method(
+            intent,
+            "getIntExtra",
+            "(Ljava/lang/String;I)I",
+            &[name.into(), default.into()],
+        )?
+        .i()
+    };
+    let name = env.new_string("present")?;
+    let present = env
+        .call_method(
+            intent,
+            "getBooleanExtra",
+            "(Ljava/lang/String;Z)Z",
+            &[name.into(), true.into()],
+        )?
+        .z()?;
+    let name = env.new_string("technology")?;
+    let technology = env
+        .call_method(
+            intent,
+            "getStringExtra",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            &[name.into()],
+        )?
+        .l()?;
+
+    Ok(BatteryState {
+        present,
+        level: int("level", -1)?,
+        scale: int("scale", -1)?,
+        status: BatteryStatus::from_raw(int("status", 1)?),
+        health: BatteryHealth::from_raw(int("health", 1)?),
+        plugged: PowerSource::from_raw(int("plugged", 0)?),
+        voltage: int("voltage", 0)?,
+        temperature: int("temperature", 0)?,
+        technology: if technology.is_null() {
+            None
+        } else {
+            Some(env.get_string(JString::from(technology))?.into())
+        },
+    })
+}
+
+/// Get the current battery state from the sticky
+/// `android.intent.action.BATTERY_CHANGED` broadcast. Returns [`None`] if it
+/// has not been sent yet.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to read the broadcast from.
+pub fn battery_state<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<BatteryState>> {
+    let action = env.new_string(ACTION_BATTERY_CHANGED)?;
+    let filter = env.new_object(
+        "android/content/IntentFilter",
+        "(Ljava/lang/String;)V",
+        &[action.into()],
+    )?;
+    let intent = env
+        .call_method(
+            context,
+            "registerReceiver",
+            "(Landroid/content/BroadcastReceiver;Landroid/content/IntentFilter;)Landroid/content/Intent;",
+            &[JObject::null().into(), filter.into()],
+        )?
+        .l()?;
+    if intent.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(battery_state_from_intent(env, intent)?))
+}
+
+/// Get a stream of battery states, starting with the current one, each time
+/// `android.intent.action.BATTERY_CHANGED` is received. The receiver is
+/// unregistered when the stream is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the receiver on.
+pub fn battery_changes<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<impl Stream<Item = Result<BatteryState>> + Send> {
+    let stream = broadcast_stream(env, context, &[ACTION_BATTERY_CHANGED])?;
+
+    let vm = env.get_java_vm()?;
+    Ok(stream.map(move |intent| {
+        let intent = intent?;
+        let env = vm.get_env()?;
+        battery_state_from_intent(&env, intent.as_obj())
+    }))
+}
+
+/// Property of the battery which can be queried with [`property`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatteryProperty {
+    /// `BatteryManager.BATTERY_PROPERTY_CHARGE_COUNTER`. Remaining capacity,
+    /// in microampere-hours.
+    ChargeCounter,
+    /// `BatteryManager.BATTERY_PROPERTY_CURRENT_NOW`. Instantaneous current,
+    /// in microamperes. Positive while charging.
+    CurrentNow,
+    /// `BatteryManager.BATTERY_PROPERTY_CURRENT_AVERAGE`. Average current,
+    /// in microamperes. Positive while charging.
+    CurrentAverage,
+    /// `BatteryManager.BATTERY_PROPERTY_CAPACITY`. Remaining capacity, as a
+    /// percentage.
+    Capacity,
+    /// `BatteryManager.BATTERY_PROPERTY_ENERGY_COUNTER`. Remaining energy,
+    /// in nanowatt-hours.
+    EnergyCounter,
+}
+
+impl BatteryProperty {
+    fn id(self) -> jint {
+        match self {
+            Self::ChargeCounter => 1,
+            Self::CurrentNow => 2,
+            Self::CurrentAverage => 3,
+            Self::Capacity => 4,
+            Self::EnergyCounter => 5,
+        }
+    }
+}
+
+/// Query a battery property with `BatteryManager.getLongProperty()`.
+/// Returns [`None`] if the device doesn't support the property. Some older
+/// devices return `0` for unsupported properties instead.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `BatteryManager` from.
+/// * `property` - Property to query.
+pub fn property<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    property: BatteryProperty,
+) -> Result<Option<i64>> {
+    let manager = system_service(env, context, "batterymanager")?;
+    let value = env
+        .call_method(manager, "getLongProperty", "(I)J", &[property.id().into()])?
+        .j()?;
+    Ok(if value == i64::MIN { None } else { Some(value) })
+}
+
+/// Check whether the battery is charging with
+/// `BatteryManager.isCharging()`. Before API level 23, this falls back to
+/// [`battery_state`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `BatteryManager` from.
+pub fn is_charging<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<bool> {
+    if sdk_int(env)? < 23 {
+        return Ok(matches!(
+            battery_state(env, context)?,
+            Some(BatteryState {
+                status: BatteryStatus::Charging,
+                ..
+            })
+        ));
+    }
+    let manager = system_service(env, context, "batterymanager")?;
+    env.call_method(manager, "isCharging", "()Z", &[])?.z()
+}
+
+/// Get the estimated time until the battery is fully charged with
+/// `BatteryManager.computeChargeTimeRemaining()`. Returns [`None`] if the
+/// battery isn't charging, the time can't be estimated, or the API level is
+/// older than 28.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `BatteryManager` from.
+pub fn charge_time_remaining<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<Duration>> {
+    if sdk_int(env)? < 28 {
+        return Ok(None);
+    }
+    let manager = system_service(env, context, "batterymanager")?;
+    let millis = env
+        .call_method(manager, "computeChargeTimeRemaining", "()J", &[])?
+        .j()?;
+    Ok(if millis < 0 {
+        None
+    } else {
+        Some(Duration::from_millis(millis as u64))
+    })
+}
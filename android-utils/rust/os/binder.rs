@@ -0,0 +1,220 @@
+use super::JParcel;
+use crate::{ops::void_callback_future, util::RemoveListenerOnDrop};
+use jni::{errors::Result, objects::JObject, sys::jint, JNIEnv};
+use std::future::Future;
+
+/// `android.os.IBinder.FIRST_CALL_TRANSACTION`.
+pub const FIRST_CALL_TRANSACTION: jint = 0x00000001;
+
+/// `android.os.IBinder.LAST_CALL_TRANSACTION`.
+pub const LAST_CALL_TRANSACTION: jint = 0x00ffffff;
+
+/// `android.os.IBinder.FLAG_ONEWAY`.
+pub const FLAG_ONEWAY: jint = 0x00000001;
+
+/// Wrapper for [`JObject`]s that implement `android.os.IBinder`, such as the
+/// `service` of
+/// [`ServiceConnectionEvent::ServiceConnected`](crate::service::ServiceConnectionEvent::ServiceConnected).
+pub struct JBinder<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JBinder<'a, 'b> {
+    /// Create a [`JBinder`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Perform a transaction with `IBinder.transact()`. Returns the reply
+    /// `Parcel`, positioned at its start, or [`None`] if the binder did not
+    /// understand the transaction code. The caller is responsible for
+    /// recycling the reply.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Transaction code, between [`FIRST_CALL_TRANSACTION`] and
+    ///   [`LAST_CALL_TRANSACTION`] for user-defined transactions.
+    /// * `data` - Data to send.
+    /// * `flags` - Either `0` or [`FLAG_ONEWAY`].
+    pub fn transact(
+        &self,
+        code: jint,
+        data: &JParcel<'a, '_>,
+        flags: jint,
+    ) -> Result<Option<JParcel<'a, 'b>>> {
+        let reply = JParcel::obtain(self.env)?;
+        // If the call throws, the reply is left to the garbage collector,
+        // since it can't be recycled while an exception is pending.
+        let handled = self
+            .env
+            .call_method(
+                self.internal,
+                "transact",
+                "(ILandroid/os/Parcel;Landroid/os/Parcel;I)Z",
+                &[code.into(), (**data).into(), (*reply).into(), flags.into()],
+            )?
+            .z()?;
+        if handled {
+            reply.set_data_position(0)?;
+            Ok(Some(reply))
+        } else {
+            reply.recycle()?;
+            Ok(None)
+        }
+    }
+
+    /// Check whether the binder's process still exists with
+    /// `IBinder.pingBinder()`.
+    pub fn ping_binder(&self) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "pingBinder", "()Z", &[])?
+            .z()
+    }
+
+    /// Check whether the binder's process is still alive with
+    /// `IBinder.isBinderAlive()`.
+    pub fn is_binder_alive(&self) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "isBinderAlive", "()Z", &[])?
+            .z()
+    }
+
+    /// Get the name of the interface supported by the binder with
+    /// `IBinder.getInterfaceDescriptor()`.
+    pub fn interface_descriptor(&self) -> Result<Option<String>> {
+        let descriptor = self
+            .env
+            .call_method(
+                self.internal,
+                "getInterfaceDescriptor",
+                "()Ljava/lang/String;",
+                &[],
+            )?
+            .l()?;
+        Ok(if descriptor.is_null() {
+            None
+        } else {
+            Some(self.env.get_string(descriptor.into())?.into())
+        })
+    }
+
+    /// Register an `android.os.IBinder.DeathRecipient` with
+    /// `IBinder.linkToDeath()`, and get a future which resolves when the
+    /// binder's process dies. The recipient is unlinked when the future is
+    /// dropped.
+    ///
+    /// Returns an error with a pending `android.os.RemoteException` if the
+    /// binder's process is already dead. Death notifications are only
+    /// delivered for remote binders; the future never resolves for a local
+    /// `Binder`.
+    pub fn death_notifications(&self) -> Result<impl Future<Output = Result<()>> + Send> {
+        let interface = self.env.find_class("android/os/IBinder$DeathRecipient")?;
+        let (recipient, future) = void_callback_future(self.env, interface)?;
+        self.env.call_method(
+            self.internal,
+            "linkToDeath",
+            "(Landroid/os/IBinder$DeathRecipient;I)V",
+            &[recipient.into(), 0.into()],
+        )?;
+        let guard = RemoveListenerOnDrop::new(
+            self.env,
+            self.internal,
+            recipient,
+            "unlinkToDeath",
+            "(Landroid/os/IBinder$DeathRecipient;I)Z",
+        )?
+        .with_flags(0);
+        Ok(async move {
+            let _guard = guard;
+            future.await
+        })
+    }
+}
+
+impl<'a: 'b, 'b> From<JBinder<'a, 'b>> for JObject<'a> {
+    fn from(binder: JBinder<'a, 'b>) -> Self {
+        binder.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JBinder<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
+
+/// Trait for Rust implementations of `android.os.Binder`. Create a binder
+/// from it with [`rust_binder`], for example to return it from
+/// [`RustService::on_bind`](crate::service::RustService::on_bind).
+pub trait RustBinder: Send + Sync {
+    /// Called by `Binder.onTransact()`. Return `true` if the transaction was
+    /// handled, or `false` to let `Binder` handle it, which fails for
+    /// user-defined transaction codes.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `code` - Transaction code.
+    /// * `data` - Data sent by the caller.
+    /// * `reply` - Reply to write the result to.
+    /// * `flags` - Either `0` or [`FLAG_ONEWAY`].
+    fn on_transact<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        code: jint,
+        data: &JParcel<'a, 'b>,
+        reply: &JParcel<'a, 'b>,
+        flags: jint,
+    ) -> bool;
+}
+
+/// Create an `android.os.Binder` whose transactions are handled by a
+/// [`RustBinder`]. The binder holds on to the [`RustBinder`] until its
+/// `close()` method is called; after that, it no longer handles any
+/// user-defined transactions.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `binder` - Implementation of the binder.
+pub fn rust_binder<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    binder: impl RustBinder + 'static,
+) -> Result<JObject<'a>> {
+    let hook = env.auto_local(jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+        let transact = || -> Result<JObject> {
+            let code = env.get_field(arg, "code", "I")?.i()?;
+            let data = env.get_field(arg, "data", "Landroid/os/Parcel;")?.l()?;
+            let reply = env.get_field(arg, "reply", "Landroid/os/Parcel;")?.l()?;
+            let flags = env.get_field(arg, "flags", "I")?.i()?;
+            let handled = binder.on_transact(
+                env,
+                code,
+                &JParcel::from_env(env, data)?,
+                &JParcel::from_env(env, reply)?,
+                flags,
+            );
+            env.call_static_method(
+                "java/lang/Boolean",
+                "valueOf",
+                "(Z)Ljava/lang/Boolean;",
+                &[handled.into()],
+            )?
+            .l()
+        };
+        transact().unwrap_or_else(|_| JObject::null())
+    })?);
+    env.new_object(
+        "io/github/gedgygedgy/rust/android/os/RustBinder",
+        "(Lio/github/gedgygedgy/rust/ops/FnFunction;)V",
+        &[(&hook).into()],
+    )
+}
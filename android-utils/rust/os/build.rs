@@ -0,0 +1,120 @@
+//! Information about the device and OS build from `android.os.Build`, read
+//! once and cached for the lifetime of the process.
+//!
+//! The values are read by [`init()`](crate::init), or on first use if it
+//! has not been called.
+
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    JNIEnv,
+};
+use once_cell::sync::OnceCell;
+
+static INFO: OnceCell<BuildInfo> = OnceCell::new();
+
+/// Snapshot of `android.os.Build` and `android.os.Build.VERSION`. Obtained
+/// from [`info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// `Build.VERSION.SDK_INT`, the API level of the OS.
+    pub sdk_int: i32,
+    /// `Build.VERSION.RELEASE`, the user-visible version, such as `12`.
+    pub release: String,
+    /// `Build.VERSION.CODENAME`, which is `REL` for release builds.
+    pub codename: String,
+    /// `Build.MANUFACTURER`.
+    pub manufacturer: String,
+    /// `Build.BRAND`.
+    pub brand: String,
+    /// `Build.MODEL`, the end-user-visible name of the device.
+    pub model: String,
+    /// `Build.DEVICE`, the name of the industrial design.
+    pub device: String,
+    /// `Build.PRODUCT`.
+    pub product: String,
+    /// `Build.HARDWARE`.
+    pub hardware: String,
+    /// `Build.FINGERPRINT`, which uniquely identifies the build.
+    pub fingerprint: String,
+    /// `Build.SUPPORTED_ABIS`, in order of preference.
+    pub supported_abis: Vec<String>,
+}
+
+fn string(env: &JNIEnv, obj: JObject) -> Result<String> {
+    Ok(if obj.is_null() {
+        String::new()
+    } else {
+        env.get_string(JString::from(obj))?.into()
+    })
+}
+
+impl BuildInfo {
+    fn read(env: &JNIEnv) -> Result<Self> {
+        let build_field = |name: &str| -> Result<String> {
+            string(
+                env,
+                env.get_static_field("android/os/Build", name, "Ljava/lang/String;")?
+                    .l()?,
+            )
+        };
+        let version_field = |name: &str| -> Result<String> {
+            string(
+                env,
+                env.get_static_field("android/os/Build$VERSION", name, "Ljava/lang/String;")?
+                    .l()?,
+            )
+        };
+
+        let abis = env
+            .get_static_field("android/os/Build", "SUPPORTED_ABIS", "[Ljava/lang/String;")?
+            .l()?;
+        let mut supported_abis = Vec::new();
+        if !abis.is_null() {
+            for i in 0..env.get_array_length(abis.into_inner())? {
+                let abi = env.get_object_array_element(abis.into_inner(), i)?;
+                supported_abis.push(string(env, abi)?);
+                env.delete_local_ref(abi)?;
+            }
+        }
+
+        Ok(Self {
+            sdk_int: env
+                .get_static_field("android/os/Build$VERSION", "SDK_INT", "I")?
+                .i()?,
+            release: version_field("RELEASE")?,
+            codename: version_field("CODENAME")?,
+            manufacturer: build_field("MANUFACTURER")?,
+            brand: build_field("BRAND")?,
+            model: build_field("MODEL")?,
+            device: build_field("DEVICE")?,
+            product: build_field("PRODUCT")?,
+            hardware: build_field("HARDWARE")?,
+            fingerprint: build_field("FINGERPRINT")?,
+            supported_abis,
+        })
+    }
+}
+
+pub(crate) fn init(env: &JNIEnv) -> Result<()> {
+    info(env)?;
+    Ok(())
+}
+
+/// Get the cached [`BuildInfo`], reading it first if needed.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn info(env: &JNIEnv) -> Result<&'static BuildInfo> {
+    INFO.get_or_try_init(|| BuildInfo::read(env))
+}
+
+/// Get the cached `Build.VERSION.SDK_INT`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn sdk_int(env: &JNIEnv) -> Result<i32> {
+    Ok(info(env)?.sdk_int)
+}
@@ -0,0 +1,673 @@
+use jni::{
+    errors::Result,
+    objects::{JObject, JString, JValue},
+    sys::{jbyteArray, jdouble, jint, jlong},
+    JNIEnv,
+};
+use std::{collections::HashMap, ops::Deref};
+
+/// Dynamically typed value of an `android.os.Bundle`, for use when the
+/// schema of the `Bundle` isn't known at compile time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BundleValue {
+    /// `null`.
+    Null,
+    /// `boolean`.
+    Bool(bool),
+    /// `int`, `short` or `byte`.
+    I32(i32),
+    /// `long`.
+    I64(i64),
+    /// `double` or `float`.
+    F64(f64),
+    /// `String` or other `CharSequence`.
+    String(String),
+    /// `byte[]`.
+    Bytes(Vec<u8>),
+    /// `java.util.List` or array other than `byte[]`.
+    List(Vec<BundleValue>),
+    /// Nested `Bundle` or `java.util.Map`.
+    Map(HashMap<String, BundleValue>),
+}
+
+fn is<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>, class: &str) -> Result<bool> {
+    env.is_instance_of(obj, class)
+}
+
+fn string_from<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<String> {
+    let string = JString::from(
+        env.call_method(obj, "toString", "()Ljava/lang/String;", &[])?
+            .l()?,
+    );
+    Ok(env.get_string(string)?.into())
+}
+
+fn list_from_iterable<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    iterable: JObject<'a>,
+) -> Result<Vec<BundleValue>> {
+    let mut values = Vec::new();
+    for item in list_from_iterable_raw(env, iterable)? {
+        values.push(value_from_object(env, item)?);
+        env.delete_local_ref(item)?;
+    }
+    Ok(values)
+}
+
+pub(crate) fn value_from_object<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    obj: JObject<'a>,
+) -> Result<BundleValue> {
+    Ok(if obj.is_null() {
+        BundleValue::Null
+    } else if is(env, obj, "java/lang/Boolean")? {
+        BundleValue::Bool(env.call_method(obj, "booleanValue", "()Z", &[])?.z()?)
+    } else if is(env, obj, "java/lang/Integer")?
+        || is(env, obj, "java/lang/Short")?
+        || is(env, obj, "java/lang/Byte")?
+    {
+        BundleValue::I32(env.call_method(obj, "intValue", "()I", &[])?.i()?)
+    } else if is(env, obj, "java/lang/Long")? {
+        BundleValue::I64(env.call_method(obj, "longValue", "()J", &[])?.j()?)
+    } else if is(env, obj, "java/lang/Number")? {
+        BundleValue::F64(env.call_method(obj, "doubleValue", "()D", &[])?.d()?)
+    } else if is(env, obj, "java/lang/CharSequence")? {
+        BundleValue::String(string_from(env, obj)?)
+    } else if is(env, obj, "[B")? {
+        BundleValue::Bytes(env.convert_byte_array(obj.into_inner() as jbyteArray)?)
+    } else if is(env, obj, "android/os/BaseBundle")? {
+        BundleValue::Map(bundle_to_map(env, obj)?)
+    } else if is(env, obj, "java/util/Map")? {
+        let entries = env
+            .call_method(obj, "entrySet", "()Ljava/util/Set;", &[])?
+            .l()?;
+        let mut map = HashMap::new();
+        for entry in list_from_iterable_raw(env, entries)? {
+            let key = env
+                .call_method(entry, "getKey", "()Ljava/lang/Object;", &[])?
+                .l()?;
+            let value = env
+                .call_method(entry, "getValue", "()Ljava/lang/Object;", &[])?
+                .l()?;
+            map.insert(string_from(env, key)?, value_from_object(env, value)?);
+        }
+        BundleValue::Map(map)
+    } else if is(env, obj, "java/lang/Iterable")? {
+        BundleValue::List(list_from_iterable(env, obj)?)
+    } else if env
+        .call_method(env.get_object_class(obj)?, "isArray", "()Z", &[])?
+        .z()?
+    {
+        let list = env
+            .call_static_method(
+                "io/github/gedgygedgy/rust/android/os/RustBundleValue",
+                "arrayToList",
+                "(Ljava/lang/Object;)Ljava/util/List;",
+                &[obj.into()],
+            )?
+            .l()?;
+        BundleValue::List(list_from_iterable(env, list)?)
+    } else {
+        BundleValue::String(string_from(env, obj)?)
+    })
+}
+
+fn list_from_iterable_raw<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    iterable: JObject<'a>,
+) -> Result<Vec<JObject<'a>>> {
+    let iter = env
+        .call_method(iterable, "iterator", "()Ljava/util/Iterator;", &[])?
+        .l()?;
+    let mut items = Vec::new();
+    while env.call_method(iter, "hasNext", "()Z", &[])?.z()? {
+        items.push(
+            env.call_method(iter, "next", "()Ljava/lang/Object;", &[])?
+                .l()?,
+        );
+    }
+    Ok(items)
+}
+
+pub(crate) fn object_from_value<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    value: &BundleValue,
+) -> Result<JObject<'a>> {
+    Ok(match value {
+        BundleValue::Null => JObject::null(),
+        BundleValue::Bool(b) => env.new_object("java/lang/Boolean", "(Z)V", &[(*b).into()])?,
+        BundleValue::I32(i) => {
+            env.new_object("java/lang/Integer", "(I)V", &[(*i as jint).into()])?
+        }
+        BundleValue::I64(l) => env.new_object("java/lang/Long", "(J)V", &[(*l as jlong).into()])?,
+        BundleValue::F64(d) => {
+            env.new_object("java/lang/Double", "(D)V", &[(*d as jdouble).into()])?
+        }
+        BundleValue::String(s) => env.new_string(s)?.into(),
+        BundleValue::Bytes(b) => JObject::from(env.byte_array_from_slice(b)?),
+        BundleValue::List(values) => {
+            let list = env.new_object("java/util/ArrayList", "()V", &[])?;
+            for value in values {
+                let item = object_from_value(env, value)?;
+                env.call_method(list, "add", "(Ljava/lang/Object;)Z", &[item.into()])?;
+                env.delete_local_ref(item)?;
+            }
+            list
+        }
+        BundleValue::Map(map) => map_to_bundle(env, map)?,
+    })
+}
+
+/// Convert an `android.os.Bundle` into a map of [`BundleValue`]s, looking up
+/// the type of each value with `Bundle.get()`. Values of types which have no
+/// [`BundleValue`] equivalent are converted with `toString()`. This also
+/// accepts an `android.os.PersistableBundle`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `bundle` - `Bundle` to convert.
+pub fn bundle_to_map<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    bundle: JObject<'a>,
+) -> Result<HashMap<String, BundleValue>> {
+    let keys = env
+        .call_method(bundle, "keySet", "()Ljava/util/Set;", &[])?
+        .l()?;
+    let mut map = HashMap::new();
+    for key in list_from_iterable_raw(env, keys)? {
+        let value = env
+            .call_method(
+                bundle,
+                "get",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[key.into()],
+            )?
+            .l()?;
+        map.insert(string_from(env, key)?, value_from_object(env, value)?);
+        env.delete_local_ref(value)?;
+        env.delete_local_ref(key)?;
+    }
+    Ok(map)
+}
+
+/// Convert a map of [`BundleValue`]s into a new `android.os.Bundle`. Nested
+/// maps become nested `Bundle`s, and lists become `java.util.ArrayList`s of
+/// boxed values stored with `Bundle.putSerializable()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `map` - Map to convert.
+pub fn map_to_bundle<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    map: &HashMap<String, BundleValue>,
+) -> Result<JObject<'a>> {
+    let bundle = env.new_object("android/os/Bundle", "()V", &[])?;
+    for (key, value) in map {
+        put_value(env, bundle, key, value)?;
+    }
+    Ok(bundle)
+}
+
+// Put a value into a Bundle with the putter for its type.
+fn put_value<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    bundle: JObject<'a>,
+    key: &str,
+    value: &BundleValue,
+) -> Result<()> {
+    let key = env.new_string(key)?;
+    match value {
+        BundleValue::Null => {
+            env.call_method(
+                bundle,
+                "putString",
+                "(Ljava/lang/String;Ljava/lang/String;)V",
+                &[key.into(), JObject::null().into()],
+            )?;
+        }
+        BundleValue::Bool(b) => {
+            env.call_method(
+                bundle,
+                "putBoolean",
+                "(Ljava/lang/String;Z)V",
+                &[key.into(), (*b).into()],
+            )?;
+        }
+        BundleValue::I32(i) => {
+            env.call_method(
+                bundle,
+                "putInt",
+                "(Ljava/lang/String;I)V",
+                &[key.into(), (*i as jint).into()],
+            )?;
+        }
+        BundleValue::I64(l) => {
+            env.call_method(
+                bundle,
+                "putLong",
+                "(Ljava/lang/String;J)V",
+                &[key.into(), (*l as jlong).into()],
+            )?;
+        }
+        BundleValue::F64(d) => {
+            env.call_method(
+                bundle,
+                "putDouble",
+                "(Ljava/lang/String;D)V",
+                &[key.into(), (*d as jdouble).into()],
+            )?;
+        }
+        BundleValue::String(s) => {
+            let s = env.new_string(s)?;
+            env.call_method(
+                bundle,
+                "putString",
+                "(Ljava/lang/String;Ljava/lang/String;)V",
+                &[key.into(), s.into()],
+            )?;
+        }
+        BundleValue::Bytes(b) => {
+            let b = JObject::from(env.byte_array_from_slice(b)?);
+            env.call_method(
+                bundle,
+                "putByteArray",
+                "(Ljava/lang/String;[B)V",
+                &[key.into(), b.into()],
+            )?;
+        }
+        BundleValue::List(_) => {
+            let list = object_from_value(env, value)?;
+            env.call_method(
+                bundle,
+                "putSerializable",
+                "(Ljava/lang/String;Ljava/io/Serializable;)V",
+                &[key.into(), list.into()],
+            )?;
+        }
+        BundleValue::Map(map) => {
+            let nested = map_to_bundle(env, map)?;
+            env.call_method(
+                bundle,
+                "putBundle",
+                "(Ljava/lang/String;Landroid/os/Bundle;)V",
+                &[key.into(), nested.into()],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Wrapper for [`JObject`]s that contain `android.os.Bundle`, with typed
+/// methods to put and get values. Used for the extras of
+/// [`JIntent`](crate::content::JIntent)s, the data of
+/// [`JMessage`](super::JMessage)s, and other payloads.
+///
+/// With the `serde` feature, structs can be converted to and from `Bundle`s
+/// with [`from_serde`](Self::from_serde) and [`to_serde`](Self::to_serde).
+pub struct JBundle<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JBundle<'a, 'b> {
+    /// Create a [`JBundle`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Create a new, empty `Bundle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn new(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env.new_object("android/os/Bundle", "()V", &[])?;
+        Self::from_env(env, obj)
+    }
+
+    /// Create a new `Bundle` from a map of [`BundleValue`]s with
+    /// [`map_to_bundle`].
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `map` - Map to convert.
+    pub fn from_map(env: &'b JNIEnv<'a>, map: &HashMap<String, BundleValue>) -> Result<Self> {
+        Self::from_env(env, map_to_bundle(env, map)?)
+    }
+
+    /// Convert this `Bundle` into a map of [`BundleValue`]s with
+    /// [`bundle_to_map`].
+    pub fn to_map(&self) -> Result<HashMap<String, BundleValue>> {
+        bundle_to_map(self.env, self.internal)
+    }
+
+    /// Create a new `Bundle` by serializing a value, which must serialize to
+    /// a map or struct. See [`bundle_serde`](super::bundle_serde). Requires
+    /// the `serde` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `value` - Value to serialize.
+    #[cfg(feature = "serde")]
+    pub fn from_serde<T: serde::Serialize + ?Sized>(
+        env: &'b JNIEnv<'a>,
+        value: &T,
+    ) -> super::bundle_serde::Result<Self> {
+        let obj = super::bundle_serde::to_bundle(env, value)?;
+        Ok(Self::from_env(env, obj)?)
+    }
+
+    /// Deserialize a value from this `Bundle`. See
+    /// [`bundle_serde`](super::bundle_serde). Requires the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn to_serde<T: serde::de::DeserializeOwned>(&self) -> super::bundle_serde::Result<T> {
+        super::bundle_serde::from_bundle(self.env, self.internal)
+    }
+
+    fn call_put(&self, method: &str, sig: &str, key: &str, value: JValue<'a>) -> Result<()> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(self.internal, method, sig, &[key.into(), value])?;
+        Ok(())
+    }
+
+    fn call_get(
+        &self,
+        method: &str,
+        sig: &str,
+        key: &str,
+        default: JValue<'a>,
+    ) -> Result<JValue<'a>> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(self.internal, method, sig, &[key.into(), default])
+    }
+
+    fn call_get_object(&self, method: &str, sig: &str, key: &str) -> Result<JObject<'a>> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(self.internal, method, sig, &[key.into()])?
+            .l()
+    }
+
+    /// Put a [`BundleValue`] with the `put*()` method for its type.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put(&self, key: &str, value: &BundleValue) -> Result<()> {
+        put_value(self.env, self.internal, key, value)
+    }
+
+    /// Put a boolean with `Bundle.putBoolean()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put_bool(&self, key: &str, value: bool) -> Result<()> {
+        self.call_put("putBoolean", "(Ljava/lang/String;Z)V", key, value.into())
+    }
+
+    /// Put an int with `Bundle.putInt()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put_int(&self, key: &str, value: jint) -> Result<()> {
+        self.call_put("putInt", "(Ljava/lang/String;I)V", key, value.into())
+    }
+
+    /// Put a long with `Bundle.putLong()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put_long(&self, key: &str, value: jlong) -> Result<()> {
+        self.call_put("putLong", "(Ljava/lang/String;J)V", key, value.into())
+    }
+
+    /// Put a double with `Bundle.putDouble()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put_double(&self, key: &str, value: jdouble) -> Result<()> {
+        self.call_put("putDouble", "(Ljava/lang/String;D)V", key, value.into())
+    }
+
+    /// Put a string with `Bundle.putString()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put_string(&self, key: &str, value: &str) -> Result<()> {
+        let value = self.env.new_string(value)?;
+        self.call_put(
+            "putString",
+            "(Ljava/lang/String;Ljava/lang/String;)V",
+            key,
+            value.into(),
+        )
+    }
+
+    /// Put a byte array with `Bundle.putByteArray()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put_bytes(&self, key: &str, value: &[u8]) -> Result<()> {
+        let value = JObject::from(self.env.byte_array_from_slice(value)?);
+        self.call_put("putByteArray", "(Ljava/lang/String;[B)V", key, value.into())
+    }
+
+    /// Put a nested `Bundle` with `Bundle.putBundle()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - `Bundle` to put.
+    pub fn put_bundle(&self, key: &str, value: &JBundle<'a, '_>) -> Result<()> {
+        self.call_put(
+            "putBundle",
+            "(Ljava/lang/String;Landroid/os/Bundle;)V",
+            key,
+            value.internal.into(),
+        )
+    }
+
+    /// Get a value as a [`BundleValue`], or [`None`] if there is no value
+    /// with the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    pub fn get(&self, key: &str) -> Result<Option<BundleValue>> {
+        if !self.contains_key(key)? {
+            return Ok(None);
+        }
+        let value = self.call_get_object("get", "(Ljava/lang/String;)Ljava/lang/Object;", key)?;
+        value_from_object(self.env, value).map(Some)
+    }
+
+    /// Get a boolean with `Bundle.getBoolean()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    /// * `default` - Value to return if there is no boolean with the key.
+    pub fn get_bool(&self, key: &str, default: bool) -> Result<bool> {
+        self.call_get("getBoolean", "(Ljava/lang/String;Z)Z", key, default.into())?
+            .z()
+    }
+
+    /// Get an int with `Bundle.getInt()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    /// * `default` - Value to return if there is no int with the key.
+    pub fn get_int(&self, key: &str, default: jint) -> Result<jint> {
+        self.call_get("getInt", "(Ljava/lang/String;I)I", key, default.into())?
+            .i()
+    }
+
+    /// Get a long with `Bundle.getLong()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    /// * `default` - Value to return if there is no long with the key.
+    pub fn get_long(&self, key: &str, default: jlong) -> Result<jlong> {
+        self.call_get("getLong", "(Ljava/lang/String;J)J", key, default.into())?
+            .j()
+    }
+
+    /// Get a double with `Bundle.getDouble()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    /// * `default` - Value to return if there is no double with the key.
+    pub fn get_double(&self, key: &str, default: jdouble) -> Result<jdouble> {
+        self.call_get("getDouble", "(Ljava/lang/String;D)D", key, default.into())?
+            .d()
+    }
+
+    /// Get a string with `Bundle.getString()`, or [`None`] if there is no
+    /// string with the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    pub fn get_string(&self, key: &str) -> Result<Option<String>> {
+        let value =
+            self.call_get_object("getString", "(Ljava/lang/String;)Ljava/lang/String;", key)?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(self.env.get_string(JString::from(value))?.into()))
+    }
+
+    /// Get a byte array with `Bundle.getByteArray()`, or [`None`] if there is
+    /// no byte array with the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    pub fn get_bytes(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let value = self.call_get_object("getByteArray", "(Ljava/lang/String;)[B", key)?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.env
+                .convert_byte_array(value.into_inner() as jbyteArray)?,
+        ))
+    }
+
+    /// Get a nested `Bundle` with `Bundle.getBundle()`, or [`None`] if there
+    /// is no `Bundle` with the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key of the value.
+    pub fn get_bundle(&self, key: &str) -> Result<Option<Self>> {
+        let value =
+            self.call_get_object("getBundle", "(Ljava/lang/String;)Landroid/os/Bundle;", key)?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        Self::from_env(self.env, value).map(Some)
+    }
+
+    /// Check whether there is a value with a key with
+    /// `Bundle.containsKey()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to check.
+    pub fn contains_key(&self, key: &str) -> Result<bool> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(
+                self.internal,
+                "containsKey",
+                "(Ljava/lang/String;)Z",
+                &[key.into()],
+            )?
+            .z()
+    }
+
+    /// Remove the value with a key with `Bundle.remove()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to remove.
+    pub fn remove(&self, key: &str) -> Result<()> {
+        let key = self.env.new_string(key)?;
+        self.env.call_method(
+            self.internal,
+            "remove",
+            "(Ljava/lang/String;)V",
+            &[key.into()],
+        )?;
+        Ok(())
+    }
+
+    /// Get the keys with `Bundle.keySet()`.
+    pub fn keys(&self) -> Result<Vec<String>> {
+        let keys = self
+            .env
+            .call_method(self.internal, "keySet", "()Ljava/util/Set;", &[])?
+            .l()?;
+        list_from_iterable_raw(self.env, keys)?
+            .into_iter()
+            .map(|key| string_from(self.env, key))
+            .collect()
+    }
+
+    /// Get the number of values with `Bundle.size()`.
+    pub fn len(&self) -> Result<usize> {
+        Ok(self
+            .env
+            .call_method(self.internal, "size", "()I", &[])?
+            .i()? as usize)
+    }
+
+    /// Check whether there are no values with `Bundle.isEmpty()`.
+    pub fn is_empty(&self) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "isEmpty", "()Z", &[])?
+            .z()
+    }
+}
+
+impl<'a: 'b, 'b> From<JBundle<'a, 'b>> for JObject<'a> {
+    fn from(bundle: JBundle<'a, 'b>) -> Self {
+        bundle.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JBundle<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,506 @@
+//! [`serde`] support for `android.os.Bundle`. The `Bundle` is first
+//! converted into [`BundleValue`]s with [`bundle_to_map`], which are then
+//! deserialized like a self-describing format such as JSON. Struct fields
+//! are looked up by key, and keys which are not in the struct are ignored.
+//! Serialization goes the other way, through [`map_to_bundle`].
+//! Numbers are checked against the type they are read into, so an `int`
+//! which does not fit in a `u8` field is an error rather than being
+//! truncated.
+//!
+//! Enums are read from a string holding the variant name, or from a nested
+//! `Bundle` with a single key naming the variant, and are written the same
+//! way.
+
+use super::bundle::{bundle_to_map, map_to_bundle, BundleValue};
+use jni::{objects::JObject, JNIEnv};
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor,
+    },
+    ser::{self, Serialize},
+};
+use std::{collections::HashMap, convert::TryFrom};
+
+pub use super::parcel_serde::{Error, Result};
+
+/// Deserialize a value from the entries of a `Bundle`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `bundle` - `Bundle` to read. A null `Bundle` is read as an empty one.
+pub fn from_bundle<'a: 'b, 'b, T: DeserializeOwned>(
+    env: &'b JNIEnv<'a>,
+    bundle: JObject<'a>,
+) -> Result<T> {
+    let map = if bundle.is_null() {
+        Default::default()
+    } else {
+        bundle_to_map(env, bundle)?
+    };
+    from_value(BundleValue::Map(map))
+}
+
+/// Deserialize a value from a [`BundleValue`].
+///
+/// # Arguments
+///
+/// * `value` - Value to read.
+pub fn from_value<T: DeserializeOwned>(value: BundleValue) -> Result<T> {
+    T::deserialize(value)
+}
+
+/// Serialize a value into a new `Bundle`. The value must serialize to a map
+/// or struct.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `value` - Value to write.
+pub fn to_bundle<'a: 'b, 'b, T: Serialize + ?Sized>(
+    env: &'b JNIEnv<'a>,
+    value: &T,
+) -> Result<JObject<'a>> {
+    match to_value(value)? {
+        BundleValue::Map(map) => Ok(map_to_bundle(env, &map)?),
+        _ => Err(ser::Error::custom("expected a map or struct for a Bundle")),
+    }
+}
+
+/// Serialize a value into a [`BundleValue`].
+///
+/// # Arguments
+///
+/// * `value` - Value to write.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<BundleValue> {
+    value.serialize(Serializer)
+}
+
+impl<'de> IntoDeserializer<'de, Error> for BundleValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for BundleValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Null => visitor.visit_unit(),
+            Self::Bool(b) => visitor.visit_bool(b),
+            Self::I32(i) => visitor.visit_i32(i),
+            Self::I64(l) => visitor.visit_i64(l),
+            Self::F64(d) => visitor.visit_f64(d),
+            Self::String(s) => visitor.visit_string(s),
+            Self::Bytes(b) => visitor.visit_byte_buf(b),
+            Self::List(values) => {
+                let mut seq = SeqDeserializer::new(values.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Self::Map(map) => {
+                let mut map = MapDeserializer::new(map.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            Self::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Self::Map(map) => {
+                let mut entries = map.into_iter();
+                match (entries.next(), entries.next()) {
+                    (Some((variant, value)), None) => {
+                        visitor.visit_enum(EnumDeserializer { variant, value })
+                    }
+                    _ => Err(de::Error::custom(
+                        "expected a Bundle with a single key for an enum",
+                    )),
+                }
+            }
+            _ => Err(de::Error::custom(
+                "expected a string or a Bundle for an enum",
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: BundleValue,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = BundleValue;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, BundleValue)> {
+        let variant =
+            seed.deserialize(IntoDeserializer::<Error>::into_deserializer(self.variant))?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for BundleValue {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self {
+            Self::Null => Ok(()),
+            _ => Err(de::Error::custom("expected null for a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = BundleValue;
+    type Error = Error;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeVariant<SerializeList>;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeVariant<SerializeMap>;
+
+    fn serialize_bool(self, v: bool) -> Result<BundleValue> {
+        Ok(BundleValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<BundleValue> {
+        Ok(BundleValue::I32(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<BundleValue> {
+        Ok(BundleValue::I32(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<BundleValue> {
+        Ok(BundleValue::I32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<BundleValue> {
+        Ok(BundleValue::I64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<BundleValue> {
+        Ok(BundleValue::I32(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<BundleValue> {
+        Ok(BundleValue::I32(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<BundleValue> {
+        Ok(BundleValue::I64(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<BundleValue> {
+        i64::try_from(v)
+            .map(BundleValue::I64)
+            .map_err(|_| ser::Error::custom(format!("{} is too large for a long", v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<BundleValue> {
+        Ok(BundleValue::F64(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<BundleValue> {
+        Ok(BundleValue::F64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<BundleValue> {
+        Ok(BundleValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<BundleValue> {
+        Ok(BundleValue::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<BundleValue> {
+        Ok(BundleValue::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<BundleValue> {
+        Ok(BundleValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<BundleValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<BundleValue> {
+        Ok(BundleValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<BundleValue> {
+        Ok(BundleValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<BundleValue> {
+        Ok(BundleValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<BundleValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<BundleValue> {
+        Ok(variant_value(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList> {
+        Ok(SerializeList(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeList>> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap {
+            map: HashMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeMap>> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SerializeList(Vec<BundleValue>);
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = BundleValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.0.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<BundleValue> {
+        Ok(BundleValue::List(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = BundleValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<BundleValue> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = BundleValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<BundleValue> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeMap {
+    map: HashMap<String, BundleValue>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = BundleValue;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(Serializer)? {
+            BundleValue::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(ser::Error::custom("Bundle keys must be strings")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| <Error as ser::Error>::custom("value without a key"))?;
+        self.map.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<BundleValue> {
+        Ok(BundleValue::Map(self.map))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = BundleValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.map
+            .insert(key.to_string(), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<BundleValue> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+fn variant_value(variant: &str, value: BundleValue) -> BundleValue {
+    let mut map = HashMap::new();
+    map.insert(variant.to_string(), value);
+    BundleValue::Map(map)
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeList> {
+    type Ok = BundleValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<BundleValue> {
+        Ok(variant_value(self.variant, BundleValue::List(self.inner.0)))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeMap> {
+    type Ok = BundleValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<BundleValue> {
+        Ok(variant_value(
+            self.variant,
+            BundleValue::Map(self.inner.map),
+        ))
+    }
+}
@@ -0,0 +1,171 @@
+use crate::content::broadcast_stream;
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    JNIEnv,
+};
+
+const ACTION_TIME_CHANGED: &str = "android.intent.action.TIME_SET";
+const ACTION_TIMEZONE_CHANGED: &str = "android.intent.action.TIMEZONE_CHANGED";
+const ACTION_DATE_CHANGED: &str = "android.intent.action.DATE_CHANGED";
+
+/// Clock change event. Produced by the stream returned from
+/// [`time_events`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimeEvent {
+    /// `android.intent.action.TIME_SET`. The wall clock was set.
+    TimeChanged,
+    /// `android.intent.action.TIMEZONE_CHANGED`. The time zone changed.
+    TimeZoneChanged {
+        /// Id of the new time zone, such as `Europe/Paris`, if the broadcast
+        /// included it.
+        zone_id: Option<String>,
+    },
+    /// `android.intent.action.DATE_CHANGED`. The date changed, either
+    /// because the clock passed midnight or because it was set.
+    DateChanged,
+}
+
+/// Get a stream of wall clock, time zone and date changes. Code which
+/// schedules work by wall clock time should recompute its deadlines when
+/// these arrive. The receiver is unregistered when the stream is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the receiver on.
+pub fn time_events<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<impl Stream<Item = Result<TimeEvent>> + Send> {
+    let stream = broadcast_stream(
+        env,
+        context,
+        &[
+            ACTION_TIME_CHANGED,
+            ACTION_TIMEZONE_CHANGED,
+            ACTION_DATE_CHANGED,
+        ],
+    )?;
+
+    let vm = env.get_java_vm()?;
+    Ok(stream.filter_map(move |intent| {
+        let event = (|| -> Result<_> {
+            let intent = intent?;
+            let env = vm.get_env()?;
+            let string = |obj: JObject| -> Result<Option<String>> {
+                Ok(if obj.is_null() {
+                    None
+                } else {
+                    Some(env.get_string(JString::from(obj))?.into())
+                })
+            };
+
+            let action = env
+                .call_method(intent.as_obj(), "getAction", "()Ljava/lang/String;", &[])?
+                .l()?;
+            Ok(match string(action)?.as_deref() {
+                Some(ACTION_TIME_CHANGED) => Some(TimeEvent::TimeChanged),
+                Some(ACTION_TIMEZONE_CHANGED) => {
+                    let name = env.new_string("time-zone")?;
+                    let zone_id = env
+                        .call_method(
+                            intent.as_obj(),
+                            "getStringExtra",
+                            "(Ljava/lang/String;)Ljava/lang/String;",
+                            &[name.into()],
+                        )?
+                        .l()?;
+                    Some(TimeEvent::TimeZoneChanged {
+                        zone_id: string(zone_id)?,
+                    })
+                }
+                Some(ACTION_DATE_CHANGED) => Some(TimeEvent::DateChanged),
+                _ => None,
+            })
+        })();
+        futures::future::ready(event.transpose())
+    }))
+}
+
+/// Get `System.currentTimeMillis()`, the wall clock time in milliseconds
+/// since the epoch.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn current_time_millis(env: &JNIEnv) -> Result<i64> {
+    env.call_static_method("java/lang/System", "currentTimeMillis", "()J", &[])?
+        .j()
+}
+
+fn default_time_zone<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<JObject<'a>> {
+    env.call_static_method(
+        "java/util/TimeZone",
+        "getDefault",
+        "()Ljava/util/TimeZone;",
+        &[],
+    )?
+    .l()
+}
+
+/// Get the id of the default `java.util.TimeZone`, such as `Europe/Paris`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn time_zone_id(env: &JNIEnv) -> Result<String> {
+    let zone = default_time_zone(env)?;
+    let id = env
+        .call_method(zone, "getID", "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok(env.get_string(JString::from(id))?.into())
+}
+
+/// Get the offset of the default `java.util.TimeZone` from UTC at a given
+/// time, in seconds, including daylight saving time.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `millis` - Time to get the offset at, in milliseconds since the epoch.
+pub fn time_zone_offset(env: &JNIEnv, millis: i64) -> Result<i32> {
+    let zone = default_time_zone(env)?;
+    let offset = env
+        .call_method(zone, "getOffset", "(J)I", &[millis.into()])?
+        .i()?;
+    Ok(offset / 1000)
+}
+
+/// Get the current wall clock time in the default time zone as a
+/// [`chrono::DateTime`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+#[cfg(feature = "chrono")]
+pub fn chrono_now(env: &JNIEnv) -> Result<chrono::DateTime<chrono::FixedOffset>> {
+    use chrono::{FixedOffset, TimeZone};
+
+    let millis = current_time_millis(env)?;
+    let offset = FixedOffset::east_opt(time_zone_offset(env, millis)?)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    Ok(offset.timestamp_millis_opt(millis).unwrap())
+}
+
+/// Get the current wall clock time in the default time zone as a
+/// [`time::OffsetDateTime`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+#[cfg(feature = "time")]
+pub fn time_now(env: &JNIEnv) -> Result<time::OffsetDateTime> {
+    let millis = current_time_millis(env)?;
+    let offset = time::UtcOffset::from_whole_seconds(time_zone_offset(env, millis)?)
+        .unwrap_or(time::UtcOffset::UTC);
+    let time = time::OffsetDateTime::from_unix_timestamp_nanos(millis as i128 * 1_000_000)
+        .unwrap_or(time::OffsetDateTime::UNIX_EPOCH);
+    Ok(time.to_offset(offset))
+}
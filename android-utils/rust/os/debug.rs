@@ -0,0 +1,85 @@
+use futures::{channel::mpsc, Stream};
+use jni::{errors::Result, JNIEnv};
+use std::time::Duration;
+
+/// Memory usage of the current process. Obtained from [`memory_info`].
+///
+/// PSS and private dirty sizes come from `android.os.Debug.MemoryInfo` and
+/// are in kilobytes. Native heap sizes come from `android.os.Debug` and are
+/// in bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryInfo {
+    /// Total proportional set size, in kilobytes.
+    pub total_pss: i32,
+    /// Total private dirty memory, in kilobytes.
+    pub total_private_dirty: i32,
+    /// Proportional set size of the native heap, in kilobytes.
+    pub native_pss: i32,
+    /// Private dirty memory of the native heap, in kilobytes.
+    pub native_private_dirty: i32,
+    /// Proportional set size of the Dalvik heap, in kilobytes.
+    pub dalvik_pss: i32,
+    /// Private dirty memory of the Dalvik heap, in kilobytes.
+    pub dalvik_private_dirty: i32,
+    /// Allocated size of the native heap, in bytes.
+    pub native_heap_allocated: i64,
+    /// Total size of the native heap, in bytes.
+    pub native_heap_size: i64,
+}
+
+/// Take a snapshot of the memory usage of the current process, using
+/// `Debug.getMemoryInfo()` and `Debug.getNativeHeapAllocatedSize()`.
+///
+/// `Debug.getMemoryInfo()` reads `/proc` and can take tens of milliseconds,
+/// so avoid calling this on the main thread.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn memory_info(env: &JNIEnv) -> Result<MemoryInfo> {
+    let info = env.new_object("android/os/Debug$MemoryInfo", "()V", &[])?;
+    env.call_static_method(
+        "android/os/Debug",
+        "getMemoryInfo",
+        "(Landroid/os/Debug$MemoryInfo;)V",
+        &[info.into()],
+    )?;
+    let field = |name: &str| -> Result<i32> { env.get_field(info, name, "I")?.i() };
+    let method = |name: &str| -> Result<i32> { env.call_method(info, name, "()I", &[])?.i() };
+    let debug_long = |name: &str| -> Result<i64> {
+        env.call_static_method("android/os/Debug", name, "()J", &[])?
+            .j()
+    };
+
+    Ok(MemoryInfo {
+        total_pss: method("getTotalPss")?,
+        total_private_dirty: method("getTotalPrivateDirty")?,
+        native_pss: field("nativePss")?,
+        native_private_dirty: field("nativePrivateDirty")?,
+        dalvik_pss: field("dalvikPss")?,
+        dalvik_private_dirty: field("dalvikPrivateDirty")?,
+        native_heap_allocated: debug_long("getNativeHeapAllocatedSize")?,
+        native_heap_size: debug_long("getNativeHeapSize")?,
+    })
+}
+
+/// Get a stream of [`MemoryInfo`] snapshots, taken every `interval` on a
+/// background thread. The first snapshot is taken immediately. The thread
+/// stops after the stream is dropped.
+///
+/// # Arguments
+///
+/// * `interval` - Time between each snapshot.
+pub fn memory_samples(interval: Duration) -> impl Stream<Item = Result<MemoryInfo>> + Send {
+    let (sender, receiver) = mpsc::unbounded();
+    std::thread::spawn(move || {
+        while !sender.is_closed() {
+            let sample = crate::env().and_then(|env| memory_info(&env));
+            if sender.unbounded_send(sample).is_err() {
+                break;
+            }
+            std::thread::sleep(interval);
+        }
+    });
+    receiver
+}
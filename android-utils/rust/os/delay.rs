@@ -0,0 +1,85 @@
+use super::{JHandler, PostToken};
+use jni::errors::Result;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+struct DelayState {
+    done: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl DelayState {
+    fn fire(&self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Future returned by [`delay`]. Dropping it before it completes removes its
+/// `java.lang.Runnable` from the `Handler`.
+pub struct Delay {
+    state: Arc<DelayState>,
+    token: Option<PostToken>,
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.state.done.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+        *self.state.waker.lock().unwrap() = Some(cx.waker().clone());
+        // Check again in case the Runnable ran before the waker was stored.
+        if self.state.done.load(Ordering::SeqCst) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        if let Some(token) = self.token.take() {
+            if !self.state.done.load(Ordering::SeqCst) {
+                let _ = token.cancel();
+            }
+        }
+    }
+}
+
+/// Create a future which completes after a delay, using
+/// `Handler.postDelayed()`. This lets futures spawned on a `Handler` wait
+/// without a separate timer thread. If the `Handler`'s `Looper` is quitting,
+/// the future completes immediately.
+///
+/// # Arguments
+///
+/// * `handler` - Handler to post the wakeup to.
+/// * `duration` - Time to wait.
+pub fn delay<'a: 'b, 'b>(handler: &JHandler<'a, 'b>, duration: Duration) -> Result<Delay> {
+    let state = Arc::new(DelayState {
+        done: AtomicBool::new(false),
+        waker: Mutex::new(None),
+    });
+    let state2 = state.clone();
+    let runnable = jni_utils::ops::fn_once_runnable(handler.env, move |_env, _obj| {
+        state2.fire();
+    })?;
+    let token = handler.post_cancellable(runnable, duration)?;
+    if token.is_none() {
+        state.fire();
+    }
+    Ok(Delay { state, token })
+}
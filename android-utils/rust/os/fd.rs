@@ -0,0 +1,223 @@
+use jni::{
+    errors::{Error, Result},
+    objects::{JObject, JString},
+    sys::jint,
+    JNIEnv,
+};
+use std::os::unix::{
+    io::{FromRawFd, IntoRawFd, OwnedFd},
+    net::UnixStream,
+};
+
+/// `android.os.Parcelable.PARCELABLE_WRITE_RETURN_VALUE`.
+const PARCELABLE_WRITE_RETURN_VALUE: jint = 1;
+
+/// Wrap a file descriptor in an `android.os.ParcelFileDescriptor`. The
+/// `ParcelFileDescriptor` takes ownership of the file descriptor.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `fd` - File descriptor to wrap.
+pub fn parcel_fd_from_owned<'a: 'b, 'b>(env: &'b JNIEnv<'a>, fd: OwnedFd) -> Result<JObject<'a>> {
+    env.call_static_method(
+        "android/os/ParcelFileDescriptor",
+        "adoptFd",
+        "(I)Landroid/os/ParcelFileDescriptor;",
+        &[fd.into_raw_fd().into()],
+    )?
+    .l()
+}
+
+/// Take the file descriptor out of an `android.os.ParcelFileDescriptor` with
+/// `ParcelFileDescriptor.detachFd()`. The `ParcelFileDescriptor` no longer
+/// owns the file descriptor afterwards.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `pfd` - `ParcelFileDescriptor` to take the file descriptor from.
+pub fn parcel_fd_into_owned<'a: 'b, 'b>(env: &'b JNIEnv<'a>, pfd: JObject<'a>) -> Result<OwnedFd> {
+    let fd = env.call_method(pfd, "detachFd", "()I", &[])?.i()?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Put a file descriptor into an `android.os.Bundle` as a
+/// `ParcelFileDescriptor`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `bundle` - `Bundle` to put the file descriptor into.
+/// * `key` - Key to put the file descriptor under.
+/// * `fd` - File descriptor to put.
+pub fn bundle_put_fd<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    bundle: JObject<'a>,
+    key: &str,
+    fd: OwnedFd,
+) -> Result<()> {
+    let key = env.new_string(key)?;
+    let pfd = parcel_fd_from_owned(env, fd)?;
+    env.call_method(
+        bundle,
+        "putParcelable",
+        "(Ljava/lang/String;Landroid/os/Parcelable;)V",
+        &[key.into(), pfd.into()],
+    )?;
+    Ok(())
+}
+
+/// Take a file descriptor out of an `android.os.Bundle`. The
+/// `ParcelFileDescriptor` is removed from the `Bundle`. Returns [`None`] if
+/// there is no `ParcelFileDescriptor` under the key.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `bundle` - `Bundle` to take the file descriptor from.
+/// * `key` - Key the file descriptor is under.
+pub fn bundle_take_fd<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    bundle: JObject<'a>,
+    key: &str,
+) -> Result<Option<OwnedFd>> {
+    let key: JString = env.new_string(key)?;
+    let pfd = env
+        .call_method(
+            bundle,
+            "getParcelable",
+            "(Ljava/lang/String;)Landroid/os/Parcelable;",
+            &[key.into()],
+        )?
+        .l()?;
+    if pfd.is_null() || !env.is_instance_of(pfd, "android/os/ParcelFileDescriptor")? {
+        return Ok(None);
+    }
+    env.call_method(bundle, "remove", "(Ljava/lang/String;)V", &[key.into()])?;
+    parcel_fd_into_owned(env, pfd).map(Some)
+}
+
+fn message_data<'a: 'b, 'b>(env: &'b JNIEnv<'a>, message: JObject<'a>) -> Result<JObject<'a>> {
+    env.call_method(message, "getData", "()Landroid/os/Bundle;", &[])?
+        .l()
+}
+
+/// Put a file descriptor into the data `android.os.Bundle` of an
+/// `android.os.Message`, so that it can be sent with an
+/// `android.os.Messenger`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `message` - `Message` to put the file descriptor into.
+/// * `key` - Key to put the file descriptor under.
+/// * `fd` - File descriptor to put.
+pub fn message_put_fd<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    message: JObject<'a>,
+    key: &str,
+    fd: OwnedFd,
+) -> Result<()> {
+    let data = message_data(env, message)?;
+    bundle_put_fd(env, data, key, fd)
+}
+
+/// Take a file descriptor out of the data `android.os.Bundle` of an
+/// `android.os.Message`. Returns [`None`] if there is no
+/// `ParcelFileDescriptor` under the key.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `message` - `Message` to take the file descriptor from.
+/// * `key` - Key the file descriptor is under.
+pub fn message_take_fd<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    message: JObject<'a>,
+    key: &str,
+) -> Result<Option<OwnedFd>> {
+    let data = message_data(env, message)?;
+    bundle_take_fd(env, data, key)
+}
+
+/// Write a file descriptor to an `android.os.Parcel` as a
+/// `ParcelFileDescriptor`. The file descriptor is closed once it has been
+/// written.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `parcel` - `Parcel` to write to.
+/// * `fd` - File descriptor to write.
+pub fn parcel_write_fd<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    parcel: JObject<'a>,
+    fd: OwnedFd,
+) -> Result<()> {
+    let pfd = parcel_fd_from_owned(env, fd)?;
+    env.call_method(
+        parcel,
+        "writeParcelable",
+        "(Landroid/os/Parcelable;I)V",
+        &[pfd.into(), PARCELABLE_WRITE_RETURN_VALUE.into()],
+    )?;
+    Ok(())
+}
+
+/// Read a file descriptor written by [`parcel_write_fd`] from an
+/// `android.os.Parcel`. Returns [`None`] if a null `ParcelFileDescriptor`
+/// was written.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `parcel` - `Parcel` to read from.
+pub fn parcel_read_fd<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    parcel: JObject<'a>,
+) -> Result<Option<OwnedFd>> {
+    let class = env.find_class("android/os/ParcelFileDescriptor")?;
+    let loader = env
+        .call_method(class, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+        .l()?;
+    let pfd = env
+        .call_method(
+            parcel,
+            "readParcelable",
+            "(Ljava/lang/ClassLoader;)Landroid/os/Parcelable;",
+            &[loader.into()],
+        )?
+        .l()?;
+    if pfd.is_null() {
+        return Ok(None);
+    }
+    parcel_fd_into_owned(env, pfd).map(Some)
+}
+
+/// Create a connected pair of Unix domain sockets with
+/// `ParcelFileDescriptor.createSocketPair()`. One end is returned as a
+/// [`UnixStream`] to keep in this process, and the other as a
+/// `ParcelFileDescriptor` to send to the other side, for example with
+/// [`message_put_fd`] over a bound service's `Messenger`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn socket_pair<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<(UnixStream, JObject<'a>)> {
+    let pair = env
+        .call_static_method(
+            "android/os/ParcelFileDescriptor",
+            "createSocketPair",
+            "()[Landroid/os/ParcelFileDescriptor;",
+            &[],
+        )?
+        .l()?;
+    let local = env.get_object_array_element(pair.into_inner(), 0)?;
+    let remote = env.get_object_array_element(pair.into_inner(), 1)?;
+    if local.is_null() || remote.is_null() {
+        return Err(Error::NullPtr("createSocketPair"));
+    }
+    let local = parcel_fd_into_owned(env, local)?;
+    Ok((UnixStream::from(local), remote))
+}
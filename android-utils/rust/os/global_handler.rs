@@ -0,0 +1,119 @@
+use super::{AbortHandle, JHandler};
+use futures::task::{FutureObj, LocalFutureObj, LocalSpawn, Spawn, SpawnError};
+use jni::{
+    errors::Result,
+    objects::{GlobalRef, JObject},
+    JNIEnv, JavaVM,
+};
+use std::{future::Future, sync::Arc};
+
+/// Owned, `'static` version of [`JHandler`], which can be stored in
+/// long-lived Rust state and sent between threads. The current thread is
+/// attached to the JVM as a daemon thread whenever it needs to call into
+/// Java.
+///
+/// Implements [`Spawn`] and [`LocalSpawn`] like [`JHandlerSpawn`](super::JHandlerSpawn)
+/// with default options.
+#[derive(Clone)]
+pub struct GlobalHandler {
+    handler: GlobalRef,
+    asynchronous: bool,
+    vm: Arc<JavaVM>,
+}
+
+impl GlobalHandler {
+    /// Create a [`GlobalHandler`] referring to the same `android.os.Handler`
+    /// as a [`JHandler`].
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Handler to refer to.
+    pub fn new(handler: &JHandler) -> Result<Self> {
+        Ok(Self {
+            handler: handler.env.new_global_ref(handler.internal)?,
+            asynchronous: handler.asynchronous,
+            vm: Arc::new(handler.env.get_java_vm()?),
+        })
+    }
+
+    /// Get the underlying `android.os.Handler`.
+    pub fn as_obj(&self) -> JObject<'_> {
+        self.handler.as_obj()
+    }
+
+    /// Call a function with a [`JHandler`] for the current thread, attaching
+    /// the thread if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function to call.
+    pub fn with_handler<R>(
+        &self,
+        f: impl for<'a, 'b> FnOnce(&'b JNIEnv<'a>, JHandler<'a, 'b>) -> R,
+    ) -> Result<R> {
+        let env = self.vm.attach_current_thread_as_daemon()?;
+        let handler =
+            JHandler::from_env(&env, self.handler.as_obj())?.with_async(self.asynchronous);
+        Ok(f(&env, handler))
+    }
+
+    /// Post a Rust closure to the `Handler`, wrapped in a
+    /// `java.lang.Runnable`.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Closure to run on the `Handler`'s thread.
+    pub fn post(
+        &self,
+        f: impl for<'a, 'b> FnOnce(&'b JNIEnv<'a>, JObject<'a>) + Send + 'static,
+    ) -> Result<bool> {
+        self.with_handler(|env, handler| {
+            let runnable = jni_utils::ops::fn_once_runnable(env, f)?;
+            handler.post(runnable)
+        })?
+    }
+
+    /// Spawn a future on the `Handler`, returning an [`AbortHandle`] which
+    /// can be used to cancel it.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn_abortable(
+        &self,
+        fut: impl Future<Output = ()> + Send + 'static,
+    ) -> std::result::Result<AbortHandle, SpawnError> {
+        self.with_handler(|_env, handler| handler.spawner().spawn_abortable(fut))
+            .map_err(|_| SpawnError::shutdown())?
+    }
+
+    /// Spawn a non-[`Send`] future on the `Handler`, returning an
+    /// [`AbortHandle`] which can be used to cancel it. The future must be
+    /// spawned from the `Handler`'s own thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn_local_abortable(
+        &self,
+        fut: impl Future<Output = ()> + 'static,
+    ) -> std::result::Result<AbortHandle, SpawnError> {
+        self.with_handler(|_env, handler| handler.spawner().spawn_local_abortable(fut))
+            .map_err(|_| SpawnError::shutdown())?
+    }
+}
+
+impl Spawn for GlobalHandler {
+    fn spawn_obj(&self, fut: FutureObj<'static, ()>) -> std::result::Result<(), SpawnError> {
+        self.spawn_abortable(fut).map(|_| ())
+    }
+}
+
+impl LocalSpawn for GlobalHandler {
+    fn spawn_local_obj(
+        &self,
+        fut: LocalFutureObj<'static, ()>,
+    ) -> std::result::Result<(), SpawnError> {
+        self.spawn_local_abortable(fut).map(|_| ())
+    }
+}
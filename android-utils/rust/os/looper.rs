@@ -0,0 +1,152 @@
+use super::{JHandler, JMessageQueue};
+use jni::{errors::Result, objects::JObject, JNIEnv};
+
+/// Wrapper for [`JObject`]s that contain `android.os.Looper`. Covers the
+/// common `Looper` lifecycle: getting the main or current `Looper`,
+/// preparing and running a `Looper` on the current thread, and quitting it.
+pub struct JLooper<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JLooper<'a, 'b> {
+    /// Create a [`JLooper`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Get the `Looper` of the application's main thread with
+    /// `Looper.getMainLooper()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn main(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env
+            .call_static_method(
+                "android/os/Looper",
+                "getMainLooper",
+                "()Landroid/os/Looper;",
+                &[],
+            )?
+            .l()?;
+        Self::from_env(env, obj)
+    }
+
+    /// Get the `Looper` of the current thread with `Looper.myLooper()`.
+    /// Returns [`None`] if the current thread has no `Looper`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn my_looper(env: &'b JNIEnv<'a>) -> Result<Option<Self>> {
+        let obj = env
+            .call_static_method(
+                "android/os/Looper",
+                "myLooper",
+                "()Landroid/os/Looper;",
+                &[],
+            )?
+            .l()?;
+        Ok(if obj.is_null() {
+            None
+        } else {
+            Some(Self::from_env(env, obj)?)
+        })
+    }
+
+    /// Create a `Looper` for the current thread with `Looper.prepare()`, and
+    /// return it. Call [`loop_`](Self::loop_) afterwards to run it. Returns
+    /// an error with a pending `java.lang.RuntimeException` if the current
+    /// thread already has a `Looper`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn prepare(env: &'b JNIEnv<'a>) -> Result<Self> {
+        env.call_static_method("android/os/Looper", "prepare", "()V", &[])?;
+        Ok(Self::my_looper(env)?.unwrap())
+    }
+
+    /// Run the current thread's `Looper` with `Looper.loop()`. This blocks
+    /// until the `Looper` is quit.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn loop_(env: &JNIEnv) -> Result<()> {
+        env.call_static_method("android/os/Looper", "loop", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Quit the `Looper` with `Looper.quit()`, discarding any pending
+    /// messages.
+    pub fn quit(&self) -> Result<()> {
+        self.env.call_method(self.internal, "quit", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Quit the `Looper` with `Looper.quitSafely()`, after delivering all
+    /// messages which are already due.
+    pub fn quit_safely(&self) -> Result<()> {
+        self.env
+            .call_method(self.internal, "quitSafely", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Check whether this is the `Looper` of the current thread.
+    pub fn is_current_thread(&self) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "isCurrentThread", "()Z", &[])?
+            .z()
+    }
+
+    /// Get this `Looper`'s `android.os.MessageQueue` with `Looper.getQueue()`.
+    pub fn queue(&self) -> Result<JMessageQueue<'a, 'b>> {
+        let queue = self
+            .env
+            .call_method(
+                self.internal,
+                "getQueue",
+                "()Landroid/os/MessageQueue;",
+                &[],
+            )?
+            .l()?;
+        JMessageQueue::from_env(self.env, queue)
+    }
+
+    /// Create a new `android.os.Handler` for this `Looper`.
+    pub fn handler(&self) -> Result<JHandler<'a, 'b>> {
+        let handler = self.env.new_object(
+            "android/os/Handler",
+            "(Landroid/os/Looper;)V",
+            &[self.internal.into()],
+        )?;
+        JHandler::from_env(self.env, handler)
+    }
+
+    /// Create a new asynchronous `android.os.Handler` for this `Looper`. See
+    /// [`JHandler::create_async`].
+    pub fn async_handler(&self) -> Result<JHandler<'a, 'b>> {
+        JHandler::create_async(self.env, self.internal)
+    }
+}
+
+impl<'a: 'b, 'b> From<JLooper<'a, 'b>> for JObject<'a> {
+    fn from(looper: JLooper<'a, 'b>) -> Self {
+        looper.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JLooper<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
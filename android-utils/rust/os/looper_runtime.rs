@@ -0,0 +1,114 @@
+use super::{AbortHandle, GlobalHandler};
+use futures::task::{FutureObj, LocalFutureObj, LocalSpawn, Spawn, SpawnError};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::runtime::{Builder, Handle, Runtime};
+
+/// Runs tokio tasks on an `android.os.Handler`, usually the one for the main
+/// `Looper`.
+///
+/// Tasks spawned through a [`LooperRuntime`] are polled on the `Handler`'s
+/// thread, inside the runtime's context, so they can use tokio's timers, IO
+/// and other resources. Those resources are driven by a single background
+/// driver thread, which wakes the tasks by posting them back to the
+/// `Handler`. Futures spawned directly with [`Handle::spawn()`] still run on
+/// the driver thread.
+pub struct LooperRuntime {
+    runtime: Runtime,
+    handler: GlobalHandler,
+}
+
+impl LooperRuntime {
+    /// Create a [`LooperRuntime`] which polls its tasks on a `Handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Handler to poll tasks on.
+    pub fn new(handler: GlobalHandler) -> io::Result<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("looper-runtime-driver")
+            .enable_all()
+            .build()?;
+        Ok(Self { runtime, handler })
+    }
+
+    /// Get a [`Handle`] to the underlying tokio runtime.
+    pub fn handle(&self) -> &Handle {
+        self.runtime.handle()
+    }
+
+    /// Get the [`GlobalHandler`] that tasks are polled on.
+    pub fn handler(&self) -> &GlobalHandler {
+        &self.handler
+    }
+
+    /// Spawn a future on the `Handler`, returning an [`AbortHandle`] which
+    /// can be used to cancel it.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn(
+        &self,
+        fut: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<AbortHandle, SpawnError> {
+        self.handler
+            .spawn_abortable(InRuntime::new(self.handle().clone(), fut))
+    }
+
+    /// Spawn a non-[`Send`] future on the `Handler`, returning an
+    /// [`AbortHandle`] which can be used to cancel it. The future must be
+    /// spawned from the `Handler`'s own thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `fut` - Future to spawn.
+    pub fn spawn_local(
+        &self,
+        fut: impl Future<Output = ()> + 'static,
+    ) -> Result<AbortHandle, SpawnError> {
+        self.handler
+            .spawn_local_abortable(InRuntime::new(self.handle().clone(), fut))
+    }
+}
+
+impl Spawn for LooperRuntime {
+    fn spawn_obj(&self, fut: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.spawn(fut).map(|_| ())
+    }
+}
+
+impl LocalSpawn for LooperRuntime {
+    fn spawn_local_obj(&self, fut: LocalFutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.spawn_local(fut).map(|_| ())
+    }
+}
+
+/// Enters the tokio runtime's context every time the future is polled.
+struct InRuntime<F> {
+    handle: Handle,
+    fut: Pin<Box<F>>,
+}
+
+impl<F> InRuntime<F> {
+    fn new(handle: Handle, fut: F) -> Self {
+        Self {
+            handle,
+            fut: Box::pin(fut),
+        }
+    }
+}
+
+impl<F: Future> Future for InRuntime<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _guard = self.handle.enter();
+        self.fut.as_mut().poll(cx)
+    }
+}
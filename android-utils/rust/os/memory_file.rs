@@ -0,0 +1,210 @@
+use super::{fd::parcel_fd_into_owned, shared_memory::size_to_jint};
+use jni::{
+    errors::{Error, Result},
+    objects::JObject,
+    JNIEnv,
+};
+use std::{
+    ops::Deref,
+    os::unix::io::{AsRawFd, OwnedFd},
+    ptr,
+};
+
+/// Memory mapping of a file descriptor, such as the one of a
+/// [`JMemoryFile`]. Dereferences to the mapped bytes. The region is unmapped
+/// when this object is dropped.
+pub struct FdMapping {
+    ptr: *mut u8,
+    len: usize,
+    writable: bool,
+}
+
+unsafe impl Send for FdMapping {}
+unsafe impl Sync for FdMapping {}
+
+impl FdMapping {
+    /// Map `len` bytes of a file descriptor into memory. This can be used on
+    /// the receiving side of a file descriptor obtained from
+    /// [`JMemoryFile::parcel_fd`].
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - File descriptor to map.
+    /// * `len` - Number of bytes to map.
+    /// * `writable` - Whether to map the region for writing as well as
+    ///   reading.
+    pub fn new(fd: &impl AsRawFd, len: usize, writable: bool) -> std::io::Result<Self> {
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                prot,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr as *mut u8,
+            len,
+            writable,
+        })
+    }
+
+    /// Get the mapped bytes for writing. Returns [`None`] if the region was
+    /// mapped read-only.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        if self.writable {
+            Some(unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) })
+        } else {
+            None
+        }
+    }
+}
+
+impl Deref for FdMapping {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for FdMapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+/// Wrapper for [`JObject`]s that contain `android.os.MemoryFile`. Provides
+/// the same methods as [`JSharedMemory`](super::JSharedMemory) for devices
+/// older than API level 27, where `SharedMemory` isn't available.
+///
+/// The file descriptor of the `MemoryFile` is obtained with the hidden
+/// `MemoryFile.getFileDescriptor()` method, which is accessible on those
+/// versions.
+pub struct JMemoryFile<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JMemoryFile<'a, 'b> {
+    /// Create a [`JMemoryFile`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Create a new `MemoryFile`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `name` - Name of the region, for debugging purposes.
+    /// * `size` - Size of the region in bytes. Sizes which do not fit in a
+    ///   Java `int` throw `IllegalArgumentException`.
+    pub fn create(env: &'b JNIEnv<'a>, name: Option<&str>, size: usize) -> Result<Self> {
+        let name = match name {
+            Some(name) => env.new_string(name)?.into(),
+            None => JObject::null(),
+        };
+        let size = size_to_jint(env, size)?;
+        let obj = env.new_object(
+            "android/os/MemoryFile",
+            "(Ljava/lang/String;I)V",
+            &[name.into(), size.into()],
+        )?;
+        Self::from_env(env, obj)
+    }
+
+    /// Get the size of the region in bytes.
+    pub fn size(&self) -> Result<usize> {
+        Ok(self
+            .env
+            .call_method(self.internal, "length", "()I", &[])?
+            .i()? as usize)
+    }
+
+    /// Get a `android.os.ParcelFileDescriptor` for a duplicate of the file
+    /// descriptor of the region, which can be sent across Binder and mapped
+    /// on the other side with [`FdMapping::new`].
+    pub fn parcel_fd(&self) -> Result<JObject<'a>> {
+        let fd = self
+            .env
+            .call_method(
+                self.internal,
+                "getFileDescriptor",
+                "()Ljava/io/FileDescriptor;",
+                &[],
+            )?
+            .l()?;
+        self.env
+            .call_static_method(
+                "android/os/ParcelFileDescriptor",
+                "dup",
+                "(Ljava/io/FileDescriptor;)Landroid/os/ParcelFileDescriptor;",
+                &[fd.into()],
+            )?
+            .l()
+    }
+
+    /// Get a duplicate of the file descriptor of the region.
+    pub fn fd(&self) -> Result<OwnedFd> {
+        parcel_fd_into_owned(self.env, self.parcel_fd()?)
+    }
+
+    fn map(&self, writable: bool) -> Result<FdMapping> {
+        let fd = self.fd()?;
+        FdMapping::new(&fd, self.size()?, writable).or_else(|err| {
+            self.env.throw_new("java/io/IOException", err.to_string())?;
+            Err(Error::JavaException)
+        })
+    }
+
+    /// Map the region for reading and writing. If mapping fails, a
+    /// `java.io.IOException` is thrown.
+    pub fn map_read_write(&self) -> Result<FdMapping> {
+        self.map(true)
+    }
+
+    /// Map the region for reading. If mapping fails, a `java.io.IOException`
+    /// is thrown.
+    pub fn map_read_only(&self) -> Result<FdMapping> {
+        self.map(false)
+    }
+
+    /// Close the region with `MemoryFile.close()`. Existing mappings stay
+    /// valid until they are dropped.
+    pub fn close(&self) -> Result<()> {
+        self.env.call_method(self.internal, "close", "()V", &[])?;
+        Ok(())
+    }
+}
+
+impl<'a: 'b, 'b> From<JMemoryFile<'a, 'b>> for JObject<'a> {
+    fn from(memory_file: JMemoryFile<'a, 'b>) -> Self {
+        memory_file.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JMemoryFile<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,207 @@
+use super::JHandler;
+use jni::{errors::Result, objects::JObject, sys::jint, JNIEnv};
+
+/// Wrapper for [`JObject`]s that contain `android.os.Message`. Provides
+/// accessors for the public fields of the `Message`. Send it with
+/// [`JHandler::send_message`].
+pub struct JMessage<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JMessage<'a, 'b> {
+    /// Create a [`JMessage`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Get a new `Message` from the global pool with `Message.obtain()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn obtain(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env
+            .call_static_method(
+                "android/os/Message",
+                "obtain",
+                "()Landroid/os/Message;",
+                &[],
+            )?
+            .l()?;
+        Self::from_env(env, obj)
+    }
+
+    /// Get a new `Message` from the global pool, targeting a `Handler` and
+    /// with all of its fields set, with `Message.obtain()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - `Handler` to target.
+    /// * `what` - Value of the `what` field.
+    /// * `arg1` - Value of the `arg1` field.
+    /// * `arg2` - Value of the `arg2` field.
+    /// * `obj` - Value of the `obj` field.
+    pub fn obtain_with(
+        handler: &JHandler<'a, 'b>,
+        what: jint,
+        arg1: jint,
+        arg2: jint,
+        obj: JObject<'a>,
+    ) -> Result<Self> {
+        let env = handler.env;
+        let msg = env
+            .call_static_method(
+                "android/os/Message",
+                "obtain",
+                "(Landroid/os/Handler;IIILjava/lang/Object;)Landroid/os/Message;",
+                &[
+                    handler.internal.into(),
+                    what.into(),
+                    arg1.into(),
+                    arg2.into(),
+                    obj.into(),
+                ],
+            )?
+            .l()?;
+        Self::from_env(env, msg)
+    }
+
+    fn int_field(&self, name: &str) -> Result<jint> {
+        self.env.get_field(self.internal, name, "I")?.i()
+    }
+
+    fn set_int_field(&self, name: &str, value: jint) -> Result<()> {
+        self.env.set_field(self.internal, name, "I", value.into())
+    }
+
+    /// Get the `what` field, which identifies the kind of message.
+    pub fn what(&self) -> Result<jint> {
+        self.int_field("what")
+    }
+
+    /// Set the `what` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `what` - New value.
+    pub fn set_what(&self, what: jint) -> Result<()> {
+        self.set_int_field("what", what)
+    }
+
+    /// Get the `arg1` field.
+    pub fn arg1(&self) -> Result<jint> {
+        self.int_field("arg1")
+    }
+
+    /// Set the `arg1` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg1` - New value.
+    pub fn set_arg1(&self, arg1: jint) -> Result<()> {
+        self.set_int_field("arg1", arg1)
+    }
+
+    /// Get the `arg2` field.
+    pub fn arg2(&self) -> Result<jint> {
+        self.int_field("arg2")
+    }
+
+    /// Set the `arg2` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg2` - New value.
+    pub fn set_arg2(&self, arg2: jint) -> Result<()> {
+        self.set_int_field("arg2", arg2)
+    }
+
+    /// Get the `obj` field.
+    pub fn obj(&self) -> Result<JObject<'a>> {
+        self.env
+            .get_field(self.internal, "obj", "Ljava/lang/Object;")?
+            .l()
+    }
+
+    /// Set the `obj` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `obj` - New value.
+    pub fn set_obj(&self, obj: JObject<'a>) -> Result<()> {
+        self.env
+            .set_field(self.internal, "obj", "Ljava/lang/Object;", obj.into())
+    }
+
+    /// Get the data `android.os.Bundle` with `Message.getData()`, creating
+    /// an empty one if the message has none.
+    pub fn data(&self) -> Result<JObject<'a>> {
+        self.env
+            .call_method(self.internal, "getData", "()Landroid/os/Bundle;", &[])?
+            .l()
+    }
+
+    /// Get the data `android.os.Bundle` as a [`JBundle`](super::JBundle).
+    /// Changes to it are reflected in the message.
+    pub fn bundle_data(&self) -> Result<super::JBundle<'a, 'b>> {
+        super::JBundle::from_env(self.env, self.data()?)
+    }
+
+    /// Set the data `android.os.Bundle` with `Message.setData()`. Unlike
+    /// the `obj` field, the data can be sent to another process.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - New value.
+    pub fn set_data(&self, data: JObject<'a>) -> Result<()> {
+        self.env.call_method(
+            self.internal,
+            "setData",
+            "(Landroid/os/Bundle;)V",
+            &[data.into()],
+        )?;
+        Ok(())
+    }
+
+    /// Get the `replyTo` field, the `android.os.Messenger` that replies to
+    /// this message should be sent to.
+    pub fn reply_to(&self) -> Result<JObject<'a>> {
+        self.env
+            .get_field(self.internal, "replyTo", "Landroid/os/Messenger;")?
+            .l()
+    }
+
+    /// Set the `replyTo` field.
+    ///
+    /// # Arguments
+    ///
+    /// * `messenger` - New value.
+    pub fn set_reply_to(&self, messenger: JObject<'a>) -> Result<()> {
+        self.env.set_field(
+            self.internal,
+            "replyTo",
+            "Landroid/os/Messenger;",
+            messenger.into(),
+        )
+    }
+}
+
+impl<'a: 'b, 'b> From<JMessage<'a, 'b>> for JObject<'a> {
+    fn from(msg: JMessage<'a, 'b>) -> Self {
+        msg.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JMessage<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,118 @@
+use crate::util::{CloseOnDrop, CloseOnDropStream};
+use futures::{channel::mpsc, Stream};
+use jni::{errors::Result, objects::JObject, JNIEnv};
+
+/// Wrapper for [`JObject`]s that contain `android.os.MessageQueue`. Provides
+/// idle handlers, which let work be scheduled for when the `Looper` has
+/// nothing else to do.
+pub struct JMessageQueue<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JMessageQueue<'a, 'b> {
+    /// Create a [`JMessageQueue`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Get the `MessageQueue` of the current thread's `Looper` with
+    /// `Looper.myQueue()`. The current thread must have a `Looper`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn my_queue(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env
+            .call_static_method(
+                "android/os/Looper",
+                "myQueue",
+                "()Landroid/os/MessageQueue;",
+                &[],
+            )?
+            .l()?;
+        Self::from_env(env, obj)
+    }
+
+    /// Check whether the `Looper` is idle with `MessageQueue.isIdle()`.
+    pub fn is_idle(&self) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "isIdle", "()Z", &[])?
+            .z()
+    }
+
+    /// Add a Rust closure as an `android.os.MessageQueue.IdleHandler`. The
+    /// closure is called on the `Looper`'s thread each time the queue runs
+    /// out of messages. It stays registered as long as it returns `true`, and
+    /// is removed once it returns `false`.
+    ///
+    /// Returns the `IdleHandler`, which can be passed to
+    /// [`remove_idle_handler`](Self::remove_idle_handler).
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Closure to call when the queue is idle.
+    pub fn add_idle_handler(
+        &self,
+        mut f: impl for<'c, 'd> FnMut(&'d JNIEnv<'c>) -> bool + Send + 'static,
+    ) -> Result<JObject<'a>> {
+        let function = jni_utils::ops::fn_mut_function(self.env, move |env, _obj, _arg| {
+            let keep = f(env);
+            env.call_static_method(
+                "java/lang/Boolean",
+                "valueOf",
+                "(Z)Ljava/lang/Boolean;",
+                &[keep.into()],
+            )
+            .and_then(|b| b.l())
+            .unwrap_or_else(|_| JObject::null())
+        })?;
+        self.env.new_object(
+            "io/github/gedgygedgy/rust/android/os/RustIdleHandler",
+            "(Landroid/os/MessageQueue;Lio/github/gedgygedgy/rust/ops/FnFunction;)V",
+            &[self.internal.into(), function.into()],
+        )
+    }
+
+    /// Remove an `IdleHandler` returned by
+    /// [`add_idle_handler`](Self::add_idle_handler), and free its closure.
+    /// Does nothing if it has already been removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - `IdleHandler` to remove.
+    pub fn remove_idle_handler(&self, handler: JObject<'a>) -> Result<()> {
+        self.env.call_method(handler, "close", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Get a stream which yields an item each time the queue becomes idle.
+    /// The `IdleHandler` is removed when the stream is dropped.
+    pub fn idle_events(&self) -> Result<impl Stream<Item = ()> + Send> {
+        let (sender, receiver) = mpsc::unbounded();
+        let handler = self.add_idle_handler(move |_env| sender.unbounded_send(()).is_ok())?;
+        Ok(CloseOnDropStream::new(
+            receiver,
+            CloseOnDrop::new(self.env, handler)?,
+        ))
+    }
+}
+
+impl<'a: 'b, 'b> From<JMessageQueue<'a, 'b>> for JObject<'a> {
+    fn from(queue: JMessageQueue<'a, 'b>) -> Self {
+        queue.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JMessageQueue<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,130 @@
+use super::{JHandler, JLooper, JMessage};
+use jni::{errors::Result, objects::JObject, JNIEnv};
+use jni_utils::stream::JStream;
+
+/// Wrapper for [`JObject`]s that contain `android.os.Messenger`. Provides
+/// `Messenger`-based IPC with a remote service, or with any other `Handler`.
+pub struct JMessenger<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JMessenger<'a, 'b> {
+    /// Create a [`JMessenger`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Create a new `Messenger` which delivers messages to a `Handler`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Handler to deliver messages to.
+    pub fn new(handler: &JHandler<'a, 'b>) -> Result<Self> {
+        let obj = handler.env.new_object(
+            "android/os/Messenger",
+            "(Landroid/os/Handler;)V",
+            &[handler.internal.into()],
+        )?;
+        Self::from_env(handler.env, obj)
+    }
+
+    /// Create a `Messenger` from an `android.os.IBinder`, such as the one
+    /// received in `ServiceConnection.onServiceConnected()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `binder` - Binder of the remote `Messenger`.
+    pub fn from_binder(env: &'b JNIEnv<'a>, binder: JObject<'a>) -> Result<Self> {
+        let obj = env.new_object(
+            "android/os/Messenger",
+            "(Landroid/os/IBinder;)V",
+            &[binder.into()],
+        )?;
+        Self::from_env(env, obj)
+    }
+
+    /// Send a message with `Messenger.send()`. Returns an error with a
+    /// pending `android.os.RemoteException` if the target no longer exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - Message to send.
+    pub fn send(&self, msg: &JMessage<'a, '_>) -> Result<()> {
+        self.env.call_method(
+            self.internal,
+            "send",
+            "(Landroid/os/Message;)V",
+            &[(**msg).into()],
+        )?;
+        Ok(())
+    }
+
+    /// Get the `android.os.IBinder` of this `Messenger` with
+    /// `Messenger.getBinder()`, for example to return it from
+    /// `Service.onBind()`.
+    pub fn get_binder(&self) -> Result<JObject<'a>> {
+        self.env
+            .call_method(self.internal, "getBinder", "()Landroid/os/IBinder;", &[])?
+            .l()
+    }
+}
+
+impl<'a: 'b, 'b> From<JMessenger<'a, 'b>> for JObject<'a> {
+    fn from(messenger: JMessenger<'a, 'b>) -> Self {
+        messenger.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JMessenger<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
+
+/// Create a local `android.os.Messenger` and a stream of the messages it
+/// receives, delivered on a `Looper`. Set it as the `replyTo` of messages
+/// sent to a remote [`JMessenger`] to get an async send/receive channel.
+///
+/// Unlike [`async_handler_callback`](super::async_handler_callback), the
+/// stream yields copies of the messages, since the originals are recycled
+/// by the `Looper` as soon as they have been handled.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `looper` - Looper to receive the messages on.
+pub fn async_messenger<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    looper: &JLooper<'a, 'b>,
+) -> Result<(JMessenger<'a, 'b>, JStream<'a, 'b>)> {
+    let callback = env.new_object(
+        "io/github/gedgygedgy/rust/android/os/RustHandlerCallback",
+        "(Z)V",
+        &[true.into()],
+    )?;
+    let stream = env
+        .call_method(
+            callback,
+            "getMessageStream",
+            "()Lio/github/gedgygedgy/rust/stream/Stream;",
+            &[],
+        )?
+        .l()?;
+    let stream = JStream::from_env(env, stream)?;
+    let handler = env.new_object(
+        "android/os/Handler",
+        "(Landroid/os/Looper;Landroid/os/Handler$Callback;)V",
+        &[(**looper).into(), callback.into()],
+    )?;
+    let handler = JHandler::from_env(env, handler)?;
+    Ok((JMessenger::new(&handler)?, stream))
+}
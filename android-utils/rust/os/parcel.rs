@@ -0,0 +1,262 @@
+use jni::{
+    errors::Result,
+    objects::{JObject, JValue},
+    sys::{jdouble, jfloat, jint, jlong},
+    JNIEnv,
+};
+
+/// Wrapper for [`JObject`]s that contain `android.os.Parcel`. Used as the data
+/// and reply of binder transactions made with
+/// [`JBinder::transact`](super::JBinder::transact).
+///
+/// Values must be read back in the same order and with the same types as
+/// they were written.
+pub struct JParcel<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JParcel<'a, 'b> {
+    /// Create a [`JParcel`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Get a new `Parcel` from the global pool with `Parcel.obtain()`. Call
+    /// [`recycle`](Self::recycle) once it is no longer needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn obtain(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env
+            .call_static_method("android/os/Parcel", "obtain", "()Landroid/os/Parcel;", &[])?
+            .l()?;
+        Self::from_env(env, obj)
+    }
+
+    /// Return the `Parcel` to the global pool with `Parcel.recycle()`. It
+    /// must not be used afterwards.
+    pub fn recycle(self) -> Result<()> {
+        self.env.call_method(self.internal, "recycle", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Get the amount of data in the `Parcel` with `Parcel.dataSize()`.
+    pub fn data_size(&self) -> Result<jint> {
+        self.env
+            .call_method(self.internal, "dataSize", "()I", &[])?
+            .i()
+    }
+
+    /// Get the current read and write position with
+    /// `Parcel.dataPosition()`.
+    pub fn data_position(&self) -> Result<jint> {
+        self.env
+            .call_method(self.internal, "dataPosition", "()I", &[])?
+            .i()
+    }
+
+    /// Move the current read and write position with
+    /// `Parcel.setDataPosition()`. Set it to `0` to read back data that was
+    /// just written.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - New position.
+    pub fn set_data_position(&self, pos: jint) -> Result<()> {
+        self.env
+            .call_method(self.internal, "setDataPosition", "(I)V", &[pos.into()])?;
+        Ok(())
+    }
+
+    /// Create a `Parcel` from bytes returned by [`marshall`](Self::marshall),
+    /// positioned at its start.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `bytes` - Raw bytes of the `Parcel`.
+    pub fn from_bytes(env: &'b JNIEnv<'a>, bytes: &[u8]) -> Result<Self> {
+        let parcel = Self::obtain(env)?;
+        parcel.unmarshall(bytes)?;
+        parcel.set_data_position(0)?;
+        Ok(parcel)
+    }
+
+    /// Get the raw bytes of the `Parcel` with `Parcel.marshall()`. The bytes
+    /// must not be persisted, since their format may change between Android
+    /// versions, and `Parcel`s containing binders can't be marshalled.
+    pub fn marshall(&self) -> Result<Vec<u8>> {
+        let bytes = self
+            .env
+            .call_method(self.internal, "marshall", "()[B", &[])?
+            .l()?;
+        self.env.convert_byte_array(bytes.into_inner())
+    }
+
+    /// Append raw bytes returned by [`marshall`](Self::marshall) at the
+    /// current position with `Parcel.unmarshall()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Raw bytes to append.
+    pub fn unmarshall(&self, bytes: &[u8]) -> Result<()> {
+        let array = self.env.byte_array_from_slice(bytes)?;
+        self.env.call_method(
+            self.internal,
+            "unmarshall",
+            "([BII)V",
+            &[array.into(), 0.into(), (bytes.len() as jint).into()],
+        )?;
+        Ok(())
+    }
+
+    fn write(&self, name: &str, sig: &str, value: JValue<'a>) -> Result<()> {
+        self.env.call_method(self.internal, name, sig, &[value])?;
+        Ok(())
+    }
+
+    fn read(&self, name: &str, sig: &str) -> Result<JValue<'a>> {
+        self.env.call_method(self.internal, name, sig, &[])
+    }
+
+    /// Write an `int` with `Parcel.writeInt()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value to write.
+    pub fn write_int(&self, value: jint) -> Result<()> {
+        self.write("writeInt", "(I)V", value.into())
+    }
+
+    /// Read an `int` with `Parcel.readInt()`.
+    pub fn read_int(&self) -> Result<jint> {
+        self.read("readInt", "()I")?.i()
+    }
+
+    /// Write a `long` with `Parcel.writeLong()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value to write.
+    pub fn write_long(&self, value: jlong) -> Result<()> {
+        self.write("writeLong", "(J)V", value.into())
+    }
+
+    /// Read a `long` with `Parcel.readLong()`.
+    pub fn read_long(&self) -> Result<jlong> {
+        self.read("readLong", "()J")?.j()
+    }
+
+    /// Write a `float` with `Parcel.writeFloat()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value to write.
+    pub fn write_float(&self, value: jfloat) -> Result<()> {
+        self.write("writeFloat", "(F)V", value.into())
+    }
+
+    /// Read a `float` with `Parcel.readFloat()`.
+    pub fn read_float(&self) -> Result<jfloat> {
+        self.read("readFloat", "()F")?.f()
+    }
+
+    /// Write a `double` with `Parcel.writeDouble()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value to write.
+    pub fn write_double(&self, value: jdouble) -> Result<()> {
+        self.write("writeDouble", "(D)V", value.into())
+    }
+
+    /// Read a `double` with `Parcel.readDouble()`.
+    pub fn read_double(&self) -> Result<jdouble> {
+        self.read("readDouble", "()D")?.d()
+    }
+
+    /// Write a `String`, or `null`, with `Parcel.writeString()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value to write.
+    pub fn write_string(&self, value: Option<&str>) -> Result<()> {
+        let value = match value {
+            Some(value) => self.env.new_string(value)?.into(),
+            None => JObject::null(),
+        };
+        self.write("writeString", "(Ljava/lang/String;)V", value.into())
+    }
+
+    /// Read a `String` with `Parcel.readString()`. Returns [`None`] if
+    /// `null` was written.
+    pub fn read_string(&self) -> Result<Option<String>> {
+        let value = self.read("readString", "()Ljava/lang/String;")?.l()?;
+        Ok(if value.is_null() {
+            None
+        } else {
+            Some(self.env.get_string(value.into())?.into())
+        })
+    }
+
+    /// Write a `byte[]`, or `null`, with `Parcel.writeByteArray()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Value to write.
+    pub fn write_byte_array(&self, value: Option<&[u8]>) -> Result<()> {
+        let value = match value {
+            Some(value) => JObject::from(self.env.byte_array_from_slice(value)?),
+            None => JObject::null(),
+        };
+        self.write("writeByteArray", "([B)V", value.into())
+    }
+
+    /// Read a `byte[]` with `Parcel.createByteArray()`. Returns [`None`] if
+    /// `null` was written.
+    pub fn read_byte_array(&self) -> Result<Option<Vec<u8>>> {
+        let value = self.read("createByteArray", "()[B")?.l()?;
+        Ok(if value.is_null() {
+            None
+        } else {
+            Some(self.env.convert_byte_array(value.into_inner())?)
+        })
+    }
+
+    /// Write an `android.os.IBinder`, or `null`, with
+    /// `Parcel.writeStrongBinder()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Binder to write.
+    pub fn write_binder(&self, value: JObject<'a>) -> Result<()> {
+        self.write("writeStrongBinder", "(Landroid/os/IBinder;)V", value.into())
+    }
+
+    /// Read an `android.os.IBinder` with `Parcel.readStrongBinder()`. The
+    /// result is a null reference if `null` was written.
+    pub fn read_binder(&self) -> Result<JObject<'a>> {
+        self.read("readStrongBinder", "()Landroid/os/IBinder;")?.l()
+    }
+}
+
+impl<'a: 'b, 'b> From<JParcel<'a, 'b>> for JObject<'a> {
+    fn from(parcel: JParcel<'a, 'b>) -> Self {
+        parcel.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JParcel<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,634 @@
+//! [`serde`] support for [`JParcel`]. Values are written as a flat sequence
+//! of `Parcel` primitives, without any field names or type information, so
+//! they must be read back into the same type they were written from.
+//!
+//! Integers up to 32 bits, `bool` and `char` are written with
+//! `Parcel.writeInt()`, 64-bit integers with `Parcel.writeLong()`, strings
+//! with `Parcel.writeString()` and byte buffers with
+//! `Parcel.writeByteArray()`. Options, enum variants, sequences and maps are
+//! prefixed with an `int` tag, index or length.
+
+use super::JParcel;
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor},
+    ser::{self, Serialize},
+};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display},
+};
+
+/// Error returned by [`to_parcel`] and [`from_parcel`], and by
+/// [`from_bundle`](super::bundle_serde::from_bundle).
+#[derive(Debug)]
+pub enum Error {
+    /// A JNI call failed.
+    Jni(jni::errors::Error),
+    /// The value could not be serialized or deserialized.
+    Message(String),
+}
+
+/// Result of [`to_parcel`] and [`from_parcel`], and of
+/// [`from_bundle`](super::bundle_serde::from_bundle).
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jni(err) => write!(f, "JNI error: {}", err),
+            Self::Message(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Jni(err) => Some(err),
+            Self::Message(_) => None,
+        }
+    }
+}
+
+impl From<jni::errors::Error> for Error {
+    fn from(err: jni::errors::Error) -> Self {
+        Self::Jni(err)
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+/// Write a value to a `Parcel` at its current position.
+///
+/// # Arguments
+///
+/// * `parcel` - Parcel to write to.
+/// * `value` - Value to write.
+pub fn to_parcel<T: Serialize + ?Sized>(parcel: &JParcel, value: &T) -> Result<()> {
+    value.serialize(&mut Serializer { parcel })
+}
+
+/// Read a value from a `Parcel` at its current position.
+///
+/// # Arguments
+///
+/// * `parcel` - Parcel to read from.
+pub fn from_parcel<T: DeserializeOwned>(parcel: &JParcel) -> Result<T> {
+    T::deserialize(&mut Deserializer { parcel })
+}
+
+fn len_to_int(len: usize) -> Result<i32> {
+    i32::try_from(len).map_err(|_| Error::Message(format!("length {} is too large", len)))
+}
+
+struct Serializer<'p, 'a: 'b, 'b> {
+    parcel: &'p JParcel<'a, 'b>,
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::Serializer for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        Ok(self.parcel.write_int(v as i32)?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        Ok(self.parcel.write_int(v.into())?)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        Ok(self.parcel.write_int(v.into())?)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        Ok(self.parcel.write_int(v)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        Ok(self.parcel.write_long(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        Ok(self.parcel.write_int(v.into())?)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        Ok(self.parcel.write_int(v.into())?)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        Ok(self.parcel.write_int(v as i32)?)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        Ok(self.parcel.write_long(v as i64)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        Ok(self.parcel.write_float(v)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        Ok(self.parcel.write_double(v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        Ok(self.parcel.write_int(v as i32)?)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        Ok(self.parcel.write_string(Some(v))?)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        Ok(self.parcel.write_byte_array(Some(v))?)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(self.parcel.write_int(0)?)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.parcel.write_int(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        let len = len.ok_or_else(|| Error::Message("sequence length is required".into()))?;
+        self.parcel.write_int(len_to_int(len)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        let len = len.ok_or_else(|| Error::Message("map length is required".into()))?;
+        self.parcel.write_int(len_to_int(len)?)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::SerializeSeq for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::SerializeTuple for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::SerializeTupleStruct for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::SerializeTupleVariant for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::SerializeMap for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::SerializeStruct for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'s, 'p, 'a: 'b, 'b> ser::SerializeStructVariant for &'s mut Serializer<'p, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct Deserializer<'p, 'a: 'b, 'b> {
+    parcel: &'p JParcel<'a, 'b>,
+}
+
+impl<'p, 'a: 'b, 'b> Deserializer<'p, 'a, 'b> {
+    fn read_len(&mut self) -> Result<usize> {
+        let len = self.parcel.read_int()?;
+        usize::try_from(len).map_err(|_| Error::Message(format!("invalid length {}", len)))
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        self.parcel
+            .read_string()?
+            .ok_or_else(|| Error::Message("unexpected null string".into()))
+    }
+
+    fn read_narrow<T: TryFrom<i32>>(&mut self, expected: &'static str) -> Result<T> {
+        let v = self.parcel.read_int()?;
+        T::try_from(v)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Signed(v.into()), &expected))
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        self.parcel
+            .read_byte_array()?
+            .ok_or_else(|| Error::Message("unexpected null byte array".into()))
+    }
+}
+
+impl<'de, 's, 'p, 'a: 'b, 'b> de::Deserializer<'de> for &'s mut Deserializer<'p, 'a, 'b> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Message(
+            "Parcel data is not self-describing, so the type must be known".into(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.parcel.read_int()? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.read_narrow("an i8")?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(self.read_narrow("an i16")?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(self.parcel.read_int()?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(self.parcel.read_long()?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.read_narrow("a u8")?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(self.read_narrow("a u16")?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // serialize_u32() stores the bits of the u32 in an int, so this is
+        // the lossless inverse rather than a narrowing cast.
+        visitor.visit_u32(self.parcel.read_int()? as u32)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Likewise for serialize_u64().
+        visitor.visit_u64(self.parcel.read_long()? as u64)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(self.parcel.read_float()?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(self.parcel.read_double()?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let c = self.parcel.read_int()?;
+        match u32::try_from(c).ok().and_then(char::from_u32) {
+            Some(c) => visitor.visit_char(c),
+            None => Err(de::Error::invalid_value(
+                de::Unexpected::Signed(c.into()),
+                &"a char",
+            )),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.parcel.read_int()? != 0 {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_map(Access { de: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Access {
+            de: self,
+            len: fields.len(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct Access<'s, 'p, 'a: 'b, 'b> {
+    de: &'s mut Deserializer<'p, 'a, 'b>,
+    len: usize,
+}
+
+impl<'de, 's, 'p, 'a: 'b, 'b> de::SeqAccess<'de> for Access<'s, 'p, 'a, 'b> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 's, 'p, 'a: 'b, 'b> de::MapAccess<'de> for Access<'s, 'p, 'a, 'b> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 's, 'p, 'a: 'b, 'b> de::EnumAccess<'de> for &'s mut Deserializer<'p, 'a, 'b> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index: u32 = self.read_narrow("a variant index")?;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 's, 'p, 'a: 'b, 'b> de::VariantAccess<'de> for &'s mut Deserializer<'p, 'a, 'b> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Access {
+            de: self,
+            len: fields.len(),
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    content::JContext,
+    util::{sdk_int, CloseOnDrop},
+};
+use jni::{
+    errors::Result,
+    objects::JObject,
+    sys::{jint, jlong, jsize},
+    JNIEnv,
+};
+use std::time::{Duration, Instant};
+
+fn nanos(duration: Duration) -> jlong {
+    duration.as_nanos().min(jlong::MAX as u128) as jlong
+}
+
+/// Wrapper for an `android.os.PerformanceHintManager.Session`. Sessions let
+/// the system tune CPU performance to meet a target duration for a recurring
+/// piece of work, such as a frame. The session is closed when dropped.
+pub struct Session {
+    session: CloseOnDrop,
+}
+
+impl Session {
+    /// Create a hint session for a set of threads with
+    /// `PerformanceHintManager.createHintSession()`. Returns [`None`] before
+    /// API level 31, or if the device doesn't support performance hints.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `context` - `android.content.Context` to get the
+    ///   `PerformanceHintManager` from.
+    /// * `tids` - Ids of the threads doing the work. Use `gettid()` to get
+    ///   the id of the current thread.
+    /// * `target` - Initial target duration of the work.
+    pub fn new<'a: 'b, 'b>(
+        env: &'b JNIEnv<'a>,
+        context: JObject<'a>,
+        tids: &[jint],
+        target: Duration,
+    ) -> Result<Option<Self>> {
+        if sdk_int(env)? < 31 {
+            return Ok(None);
+        }
+
+        let manager = JContext::from_env(env, context)?.system_service("performance_hint")?;
+        if manager.is_null() {
+            return Ok(None);
+        }
+
+        let tid_array = env.new_int_array(tids.len() as jsize)?;
+        env.set_int_array_region(tid_array, 0, tids)?;
+        let session = env
+            .call_method(
+                manager,
+                "createHintSession",
+                "([IJ)Landroid/os/PerformanceHintManager$Session;",
+                &[JObject::from(tid_array).into(), nanos(target).into()],
+            )?
+            .l()?;
+        if session.is_null() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            session: CloseOnDrop::new(env, session)?,
+        }))
+    }
+
+    /// Report how long the most recent piece of work actually took.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `duration` - Duration of the work.
+    pub fn report_actual_work_duration(&self, env: &JNIEnv, duration: Duration) -> Result<()> {
+        env.call_method(
+            self.session.as_obj(),
+            "reportActualWorkDuration",
+            "(J)V",
+            &[nanos(duration).into()],
+        )?;
+        Ok(())
+    }
+
+    /// Update the target duration of the work.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `target` - New target duration.
+    pub fn update_target_work_duration(&self, env: &JNIEnv, target: Duration) -> Result<()> {
+        env.call_method(
+            self.session.as_obj(),
+            "updateTargetWorkDuration",
+            "(J)V",
+            &[nanos(target).into()],
+        )?;
+        Ok(())
+    }
+
+    /// Start timing a piece of work. The time until the returned
+    /// [`WorkTimer`] is dropped is reported to the session.
+    pub fn start_work(&self) -> WorkTimer<'_> {
+        WorkTimer {
+            session: self,
+            start: Instant::now(),
+        }
+    }
+}
+
+/// Times a piece of work for a [`Session`], and reports the duration when
+/// dropped. Created by [`Session::start_work`].
+///
+/// The duration is reported with [`env()`](crate::env), and errors are
+/// ignored.
+pub struct WorkTimer<'s> {
+    session: &'s Session,
+    start: Instant,
+}
+
+impl WorkTimer<'_> {
+    /// Time elapsed since the work started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for WorkTimer<'_> {
+    fn drop(&mut self) {
+        if let Ok(env) = crate::env() {
+            let _ = self
+                .session
+                .report_actual_work_duration(&env, self.start.elapsed());
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use super::bundle::{bundle_to_map, map_to_bundle, BundleValue};
+use jni::{
+    errors::Result,
+    objects::{JObject, JValue},
+    sys::{jint, jlong, jsize},
+    JNIEnv,
+};
+use std::{collections::HashMap, ops::Deref};
+
+/// Wrapper for [`JObject`]s that contain `android.os.PersistableBundle`.
+///
+/// A `PersistableBundle` can only hold a subset of the types a `Bundle` can:
+/// booleans, ints, longs, doubles and strings, arrays of those, and nested
+/// `PersistableBundle`s. It is what persisted jobs, such as the extras of an
+/// `android.app.job.JobInfo`, carry.
+pub struct JPersistableBundle<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JPersistableBundle<'a, 'b> {
+    /// Create a [`JPersistableBundle`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Create a new, empty `PersistableBundle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    pub fn new(env: &'b JNIEnv<'a>) -> Result<Self> {
+        let obj = env.new_object("android/os/PersistableBundle", "()V", &[])?;
+        Self::from_env(env, obj)
+    }
+
+    /// Create a new `PersistableBundle` from a map of [`BundleValue`]s.
+    /// Values which a `PersistableBundle` can't hold, such as byte arrays and
+    /// lists of mixed types, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `map` - Map to convert.
+    pub fn from_map(env: &'b JNIEnv<'a>, map: &HashMap<String, BundleValue>) -> Result<Self> {
+        let bundle = Self::new(env)?;
+        for (key, value) in map {
+            bundle.put(key, value)?;
+        }
+        Ok(bundle)
+    }
+
+    /// Create a deep copy of an `android.os.Bundle` as a `PersistableBundle`.
+    /// Values which a `PersistableBundle` can't hold are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `bundle` - `Bundle` to copy.
+    pub fn from_bundle(env: &'b JNIEnv<'a>, bundle: JObject<'a>) -> Result<Self> {
+        Self::from_map(env, &bundle_to_map(env, bundle)?)
+    }
+
+    /// Convert this `PersistableBundle` into a map of [`BundleValue`]s.
+    pub fn to_map(&self) -> Result<HashMap<String, BundleValue>> {
+        bundle_to_map(self.env, self.internal)
+    }
+
+    /// Create a deep copy of this `PersistableBundle` as an
+    /// `android.os.Bundle`. Nested `PersistableBundle`s become nested
+    /// `Bundle`s.
+    pub fn to_bundle(&self) -> Result<JObject<'a>> {
+        map_to_bundle(self.env, &self.to_map()?)
+    }
+
+    fn call_put(&self, method: &str, sig: &str, key: &str, value: JValue<'a>) -> Result<()> {
+        let key = self.env.new_string(key)?;
+        self.env
+            .call_method(self.internal, method, sig, &[key.into(), value])?;
+        Ok(())
+    }
+
+    fn put_array<T: Copy>(
+        &self,
+        key: &str,
+        values: &[BundleValue],
+        extract: impl Fn(&BundleValue) -> Option<T>,
+        new_array: impl Fn(&JNIEnv<'a>, &[T]) -> Result<JObject<'a>>,
+        method: &str,
+        sig: &str,
+    ) -> Result<bool> {
+        let items: Option<Vec<T>> = values.iter().map(extract).collect();
+        match items {
+            Some(items) => {
+                let array = new_array(self.env, &items)?;
+                self.call_put(method, sig, key, array.into())?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Put a [`BundleValue`] into this `PersistableBundle`. Returns `false`
+    /// if the value can't be held by a `PersistableBundle`, in which case
+    /// nothing is put.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Key to put the value under.
+    /// * `value` - Value to put.
+    pub fn put(&self, key: &str, value: &BundleValue) -> Result<bool> {
+        match value {
+            BundleValue::Null => self.call_put(
+                "putString",
+                "(Ljava/lang/String;Ljava/lang/String;)V",
+                key,
+                JObject::null().into(),
+            )?,
+            BundleValue::Bool(b) => {
+                self.call_put("putBoolean", "(Ljava/lang/String;Z)V", key, (*b).into())?
+            }
+            BundleValue::I32(i) => {
+                self.call_put("putInt", "(Ljava/lang/String;I)V", key, (*i as jint).into())?
+            }
+            BundleValue::I64(l) => self.call_put(
+                "putLong",
+                "(Ljava/lang/String;J)V",
+                key,
+                (*l as jlong).into(),
+            )?,
+            BundleValue::F64(d) => {
+                self.call_put("putDouble", "(Ljava/lang/String;D)V", key, (*d).into())?
+            }
+            BundleValue::String(s) => {
+                let s = self.env.new_string(s)?;
+                self.call_put(
+                    "putString",
+                    "(Ljava/lang/String;Ljava/lang/String;)V",
+                    key,
+                    s.into(),
+                )?
+            }
+            BundleValue::Bytes(_) => return Ok(false),
+            BundleValue::List(values) => return self.put_list(key, values),
+            BundleValue::Map(map) => {
+                let nested = Self::from_map(self.env, map)?;
+                self.call_put(
+                    "putPersistableBundle",
+                    "(Ljava/lang/String;Landroid/os/PersistableBundle;)V",
+                    key,
+                    nested.internal.into(),
+                )?
+            }
+        }
+        Ok(true)
+    }
+
+    fn put_list(&self, key: &str, values: &[BundleValue]) -> Result<bool> {
+        let len = values.len() as jsize;
+        if self.put_array(
+            key,
+            values,
+            |v| match v {
+                BundleValue::I32(i) => Some(*i),
+                _ => None,
+            },
+            |env, items| {
+                let array = env.new_int_array(len)?;
+                env.set_int_array_region(array, 0, items)?;
+                Ok(array.into())
+            },
+            "putIntArray",
+            "(Ljava/lang/String;[I)V",
+        )? || self.put_array(
+            key,
+            values,
+            |v| match v {
+                BundleValue::I32(i) => Some(*i as i64),
+                BundleValue::I64(l) => Some(*l),
+                _ => None,
+            },
+            |env, items| {
+                let array = env.new_long_array(len)?;
+                env.set_long_array_region(array, 0, items)?;
+                Ok(array.into())
+            },
+            "putLongArray",
+            "(Ljava/lang/String;[J)V",
+        )? || self.put_array(
+            key,
+            values,
+            |v| match v {
+                BundleValue::F64(d) => Some(*d),
+                _ => None,
+            },
+            |env, items| {
+                let array = env.new_double_array(len)?;
+                env.set_double_array_region(array, 0, items)?;
+                Ok(array.into())
+            },
+            "putDoubleArray",
+            "(Ljava/lang/String;[D)V",
+        )? || self.put_array(
+            key,
+            values,
+            |v| match v {
+                BundleValue::Bool(b) => Some(*b as u8),
+                _ => None,
+            },
+            |env, items| {
+                let array = env.new_boolean_array(len)?;
+                env.set_boolean_array_region(array, 0, items)?;
+                Ok(array.into())
+            },
+            "putBooleanArray",
+            "(Ljava/lang/String;[Z)V",
+        )? {
+            return Ok(true);
+        }
+
+        let strings: Option<Vec<&str>> = values
+            .iter()
+            .map(|v| match v {
+                BundleValue::String(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect();
+        match strings {
+            Some(strings) => {
+                let array = self
+                    .env
+                    .new_object_array(len, "java/lang/String", JObject::null())?;
+                for (i, s) in strings.into_iter().enumerate() {
+                    let s = self.env.new_string(s)?;
+                    self.env.set_object_array_element(array, i as jsize, s)?;
+                }
+                self.call_put(
+                    "putStringArray",
+                    "(Ljava/lang/String;[Ljava/lang/String;)V",
+                    key,
+                    JObject::from(array).into(),
+                )?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+impl<'a: 'b, 'b> From<JPersistableBundle<'a, 'b>> for JObject<'a> {
+    fn from(bundle: JPersistableBundle<'a, 'b>) -> Self {
+        bundle.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JPersistableBundle<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
@@ -0,0 +1,154 @@
+//! Wrappers around `android.os.Process`.
+//!
+//! Threads created from Rust get the default priority, which is the same as
+//! the main thread's. Background workers should lower theirs with
+//! [`set_thread_priority`] so that they don't compete with the UI.
+
+use crate::util::sdk_int;
+use jni::{errors::Result, objects::JString, sys::jint, JNIEnv};
+
+/// `Process.THREAD_PRIORITY_URGENT_AUDIO`.
+pub const THREAD_PRIORITY_URGENT_AUDIO: jint = -19;
+/// `Process.THREAD_PRIORITY_AUDIO`.
+pub const THREAD_PRIORITY_AUDIO: jint = -16;
+/// `Process.THREAD_PRIORITY_VIDEO`.
+pub const THREAD_PRIORITY_VIDEO: jint = -10;
+/// `Process.THREAD_PRIORITY_URGENT_DISPLAY`.
+pub const THREAD_PRIORITY_URGENT_DISPLAY: jint = -8;
+/// `Process.THREAD_PRIORITY_DISPLAY`.
+pub const THREAD_PRIORITY_DISPLAY: jint = -4;
+/// `Process.THREAD_PRIORITY_FOREGROUND`.
+pub const THREAD_PRIORITY_FOREGROUND: jint = -2;
+/// `Process.THREAD_PRIORITY_MORE_FAVORABLE`. Add to a priority to make it
+/// slightly more favorable.
+pub const THREAD_PRIORITY_MORE_FAVORABLE: jint = -1;
+/// `Process.THREAD_PRIORITY_DEFAULT`.
+pub const THREAD_PRIORITY_DEFAULT: jint = 0;
+/// `Process.THREAD_PRIORITY_LESS_FAVORABLE`. Add to a priority to make it
+/// slightly less favorable.
+pub const THREAD_PRIORITY_LESS_FAVORABLE: jint = 1;
+/// `Process.THREAD_PRIORITY_BACKGROUND`.
+pub const THREAD_PRIORITY_BACKGROUND: jint = 10;
+/// `Process.THREAD_PRIORITY_LOWEST`.
+pub const THREAD_PRIORITY_LOWEST: jint = 19;
+
+fn call_int(env: &JNIEnv, name: &str) -> Result<jint> {
+    env.call_static_method("android/os/Process", name, "()I", &[])?
+        .i()
+}
+
+/// Get `Process.myPid()`, the id of the current process.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn my_pid(env: &JNIEnv) -> Result<jint> {
+    call_int(env, "myPid")
+}
+
+/// Get `Process.myTid()`, the id of the current thread.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn my_tid(env: &JNIEnv) -> Result<jint> {
+    call_int(env, "myTid")
+}
+
+/// Get `Process.myUid()`, the Linux user id of the app.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn my_uid(env: &JNIEnv) -> Result<jint> {
+    call_int(env, "myUid")
+}
+
+/// Set the priority of the current thread with
+/// `Process.setThreadPriority()`. Use one of the `THREAD_PRIORITY_*`
+/// constants.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `priority` - Priority to set, from -20 (highest) to 19 (lowest).
+pub fn set_thread_priority(env: &JNIEnv, priority: jint) -> Result<()> {
+    env.call_static_method(
+        "android/os/Process",
+        "setThreadPriority",
+        "(I)V",
+        &[priority.into()],
+    )?;
+    Ok(())
+}
+
+/// Set the priority of another thread of the current process with
+/// `Process.setThreadPriority()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `tid` - Id of the thread, as returned by [`my_tid`] on that thread.
+/// * `priority` - Priority to set, from -20 (highest) to 19 (lowest).
+pub fn set_thread_priority_of(env: &JNIEnv, tid: jint, priority: jint) -> Result<()> {
+    env.call_static_method(
+        "android/os/Process",
+        "setThreadPriority",
+        "(II)V",
+        &[tid.into(), priority.into()],
+    )?;
+    Ok(())
+}
+
+/// Get the priority of a thread with `Process.getThreadPriority()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `tid` - Id of the thread, as returned by [`my_tid`] on that thread.
+pub fn thread_priority(env: &JNIEnv, tid: jint) -> Result<jint> {
+    env.call_static_method(
+        "android/os/Process",
+        "getThreadPriority",
+        "(I)I",
+        &[tid.into()],
+    )?
+    .i()
+}
+
+/// Get the name of the current process. Uses
+/// `Application.getProcessName()` on API level 28 and later, and reads
+/// `/proc/self/cmdline` on older versions. Returns [`None`] if the name
+/// could not be determined.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn process_name(env: &JNIEnv) -> Result<Option<String>> {
+    if sdk_int(env)? >= 28 {
+        let name = env
+            .call_static_method(
+                "android/app/Application",
+                "getProcessName",
+                "()Ljava/lang/String;",
+                &[],
+            )?
+            .l()?;
+        return Ok(if name.is_null() {
+            None
+        } else {
+            Some(env.get_string(JString::from(name))?.into())
+        });
+    }
+
+    Ok(std::fs::read("/proc/self/cmdline")
+        .ok()
+        .and_then(|cmdline| {
+            let name = cmdline.split(|b| *b == 0).next()?;
+            if name.is_empty() {
+                None
+            } else {
+                Some(String::from_utf8_lossy(name).into_owned())
+            }
+        }))
+}
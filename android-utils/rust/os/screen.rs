@@ -0,0 +1,90 @@
+use crate::{content::broadcast_stream, util::system_service};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    JNIEnv,
+};
+
+const ACTION_SCREEN_ON: &str = "android.intent.action.SCREEN_ON";
+const ACTION_SCREEN_OFF: &str = "android.intent.action.SCREEN_OFF";
+const ACTION_USER_PRESENT: &str = "android.intent.action.USER_PRESENT";
+
+/// Screen or keyguard event. Produced by the stream returned from
+/// [`screen_events`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreenEvent {
+    /// `android.intent.action.SCREEN_ON`. The device became interactive.
+    On,
+    /// `android.intent.action.SCREEN_OFF`. The device stopped being
+    /// interactive.
+    Off,
+    /// `android.intent.action.USER_PRESENT`. The user unlocked the device.
+    UserPresent,
+}
+
+/// Snapshot of the screen and keyguard state. Obtained from
+/// [`screen_state`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenState {
+    /// Result of `PowerManager.isInteractive()`.
+    pub interactive: bool,
+    /// Result of `KeyguardManager.isKeyguardLocked()`.
+    pub keyguard_locked: bool,
+}
+
+/// Get whether the device is interactive and whether the keyguard is
+/// locked.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the system services from.
+pub fn screen_state<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<ScreenState> {
+    let power = system_service(env, context, "power")?;
+    let keyguard = system_service(env, context, "keyguard")?;
+    Ok(ScreenState {
+        interactive: env.call_method(power, "isInteractive", "()Z", &[])?.z()?,
+        keyguard_locked: env
+            .call_method(keyguard, "isKeyguardLocked", "()Z", &[])?
+            .z()?,
+    })
+}
+
+/// Get a stream of screen on, screen off and user present events. Combine
+/// with [`screen_state`] to get the state at the time the stream was
+/// created. The receiver is unregistered when the stream is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the receiver on.
+pub fn screen_events<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<impl Stream<Item = Result<ScreenEvent>> + Send> {
+    let stream = broadcast_stream(
+        env,
+        context,
+        &[ACTION_SCREEN_ON, ACTION_SCREEN_OFF, ACTION_USER_PRESENT],
+    )?;
+
+    let vm = env.get_java_vm()?;
+    Ok(stream.filter_map(move |intent| {
+        let event = (|| -> Result<_> {
+            let intent = intent?;
+            let env = vm.get_env()?;
+            let action = env
+                .call_method(intent.as_obj(), "getAction", "()Ljava/lang/String;", &[])?
+                .l()?;
+            let action: String = env.get_string(JString::from(action))?.into();
+            Ok(match action.as_str() {
+                ACTION_SCREEN_ON => Some(ScreenEvent::On),
+                ACTION_SCREEN_OFF => Some(ScreenEvent::Off),
+                ACTION_USER_PRESENT => Some(ScreenEvent::UserPresent),
+                _ => None,
+            })
+        })();
+        futures::future::ready(event.transpose())
+    }))
+}
@@ -0,0 +1,294 @@
+use super::JParcel;
+use jni::{
+    errors::{Error, Result},
+    objects::{JByteBuffer, JObject},
+    sys::jint,
+    JNIEnv,
+};
+use std::{convert::TryFrom, ops::Deref};
+
+/// `android.system.OsConstants.PROT_NONE`.
+pub const PROT_NONE: jint = 0;
+
+/// `android.system.OsConstants.PROT_READ`.
+pub const PROT_READ: jint = 1;
+
+/// `android.system.OsConstants.PROT_WRITE`.
+pub const PROT_WRITE: jint = 2;
+
+/// `android.system.OsConstants.PROT_EXEC`.
+pub const PROT_EXEC: jint = 4;
+
+/// Convert a region size into a Java `int`, throwing
+/// `IllegalArgumentException` if it is too large.
+pub(crate) fn size_to_jint(env: &JNIEnv, size: usize) -> Result<jint> {
+    jint::try_from(size).map_err(|_| {
+        let _ = env.throw_new(
+            "java/lang/IllegalArgumentException",
+            format!("size {} does not fit in an int", size),
+        );
+        Error::JavaException
+    })
+}
+
+/// Wrapper for [`JObject`]s that contain `android.os.SharedMemory`. Provides
+/// methods to map the region into Rust slices and to send it across Binder.
+///
+/// `SharedMemory` was added in API level 27. On older versions, use
+/// [`JMemoryFile`](super::JMemoryFile), which provides the same methods.
+pub struct JSharedMemory<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JSharedMemory<'a, 'b> {
+    /// Create a [`JSharedMemory`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Create a new shared memory region with `SharedMemory.create()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `name` - Name of the region, for debugging purposes.
+    /// * `size` - Size of the region in bytes. Sizes which do not fit in a
+    ///   Java `int` throw `IllegalArgumentException`.
+    pub fn create(env: &'b JNIEnv<'a>, name: Option<&str>, size: usize) -> Result<Self> {
+        let name = match name {
+            Some(name) => env.new_string(name)?.into(),
+            None => JObject::null(),
+        };
+        let size = size_to_jint(env, size)?;
+        let obj = env
+            .call_static_method(
+                "android/os/SharedMemory",
+                "create",
+                "(Ljava/lang/String;I)Landroid/os/SharedMemory;",
+                &[name.into(), size.into()],
+            )?
+            .l()?;
+        Self::from_env(env, obj)
+    }
+
+    /// Get the shared memory region stored under `key` in an
+    /// `android.os.Bundle`, such as one received across Binder. Returns
+    /// [`None`] if there is no region under the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `bundle` - `Bundle` to get the region from.
+    /// * `key` - Key the region is under.
+    pub fn from_bundle(
+        env: &'b JNIEnv<'a>,
+        bundle: JObject<'a>,
+        key: &str,
+    ) -> Result<Option<Self>> {
+        let key = env.new_string(key)?;
+        let obj = env
+            .call_method(
+                bundle,
+                "getParcelable",
+                "(Ljava/lang/String;)Landroid/os/Parcelable;",
+                &[key.into()],
+            )?
+            .l()?;
+        if obj.is_null() || !env.is_instance_of(obj, "android/os/SharedMemory")? {
+            return Ok(None);
+        }
+        Self::from_env(env, obj).map(Some)
+    }
+
+    /// Put the shared memory region into an `android.os.Bundle` under `key`,
+    /// so that it can be sent across Binder.
+    ///
+    /// # Arguments
+    ///
+    /// * `bundle` - `Bundle` to put the region into.
+    /// * `key` - Key to put the region under.
+    pub fn put_in_bundle(&self, bundle: JObject<'a>, key: &str) -> Result<()> {
+        let key = self.env.new_string(key)?;
+        self.env.call_method(
+            bundle,
+            "putParcelable",
+            "(Ljava/lang/String;Landroid/os/Parcelable;)V",
+            &[key.into(), self.internal.into()],
+        )?;
+        Ok(())
+    }
+
+    /// Read a shared memory region written with
+    /// [`write_to_parcel`](Self::write_to_parcel) from an
+    /// `android.os.Parcel`, such as the data of a
+    /// [`RustBinder`](super::RustBinder) transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `parcel` - `Parcel` to read the region from.
+    pub fn from_parcel(env: &'b JNIEnv<'a>, parcel: &JParcel<'a, '_>) -> Result<Self> {
+        let creator = env
+            .get_static_field(
+                "android/os/SharedMemory",
+                "CREATOR",
+                "Landroid/os/Parcelable$Creator;",
+            )?
+            .l()?;
+        let obj = env
+            .call_method(
+                creator,
+                "createFromParcel",
+                "(Landroid/os/Parcel;)Ljava/lang/Object;",
+                &[(**parcel).into()],
+            )?
+            .l()?;
+        Self::from_env(env, obj)
+    }
+
+    /// Write the shared memory region to an `android.os.Parcel` with
+    /// `SharedMemory.writeToParcel()`, so that it can be sent in a binder
+    /// transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `parcel` - `Parcel` to write the region to.
+    pub fn write_to_parcel(&self, parcel: &JParcel<'a, '_>) -> Result<()> {
+        self.env.call_method(
+            self.internal,
+            "writeToParcel",
+            "(Landroid/os/Parcel;I)V",
+            &[(**parcel).into(), 0.into()],
+        )?;
+        Ok(())
+    }
+
+    /// Get the size of the region in bytes.
+    pub fn size(&self) -> Result<usize> {
+        Ok(self
+            .env
+            .call_method(self.internal, "getSize", "()I", &[])?
+            .i()? as usize)
+    }
+
+    /// Restrict the protection of the region with `SharedMemory.setProtect()`.
+    /// Protection can only be removed, never added back. Returns whether the
+    /// protection was applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `prot` - Combination of the `PROT_*` constants.
+    pub fn set_protect(&self, prot: jint) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "setProtect", "(I)Z", &[prot.into()])?
+            .z()
+    }
+
+    fn map(&self, method: &str, writable: bool) -> Result<SharedMemoryMapping<'a, 'b>> {
+        let buffer = JByteBuffer::from(
+            self.env
+                .call_method(self.internal, method, "()Ljava/nio/ByteBuffer;", &[])?
+                .l()?,
+        );
+        let slice = self.env.get_direct_buffer_address(buffer)?;
+        Ok(SharedMemoryMapping {
+            buffer,
+            ptr: slice.as_mut_ptr(),
+            len: slice.len(),
+            writable,
+            env: self.env,
+        })
+    }
+
+    /// Map the region for reading and writing with
+    /// `SharedMemory.mapReadWrite()`.
+    pub fn map_read_write(&self) -> Result<SharedMemoryMapping<'a, 'b>> {
+        self.map("mapReadWrite", true)
+    }
+
+    /// Map the region for reading with `SharedMemory.mapReadOnly()`.
+    pub fn map_read_only(&self) -> Result<SharedMemoryMapping<'a, 'b>> {
+        self.map("mapReadOnly", false)
+    }
+
+    /// Close the region with `SharedMemory.close()`. Existing mappings stay
+    /// valid until they are dropped.
+    pub fn close(&self) -> Result<()> {
+        self.env.call_method(self.internal, "close", "()V", &[])?;
+        Ok(())
+    }
+}
+
+impl<'a: 'b, 'b> From<JSharedMemory<'a, 'b>> for JObject<'a> {
+    fn from(shared_memory: JSharedMemory<'a, 'b>) -> Self {
+        shared_memory.internal
+    }
+}
+
+impl<'a: 'b, 'b> Deref for JSharedMemory<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
+
+/// Mapping of a shared memory region, obtained from
+/// [`JSharedMemory::map_read_write`] or [`JSharedMemory::map_read_only`].
+/// Dereferences to the mapped bytes. The region is unmapped with
+/// `SharedMemory.unmap()` when this object is dropped.
+pub struct SharedMemoryMapping<'a: 'b, 'b> {
+    buffer: JByteBuffer<'a>,
+    ptr: *mut u8,
+    len: usize,
+    writable: bool,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> SharedMemoryMapping<'a, 'b> {
+    /// Get the mapped bytes for writing. Returns [`None`] if the region was
+    /// mapped read-only.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        if self.writable {
+            Some(unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) })
+        } else {
+            None
+        }
+    }
+
+    /// Get the `java.nio.ByteBuffer` of the mapping.
+    pub fn byte_buffer(&self) -> JByteBuffer<'a> {
+        self.buffer
+    }
+}
+
+impl<'a: 'b, 'b> Deref for SharedMemoryMapping<'a, 'b> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'a: 'b, 'b> Drop for SharedMemoryMapping<'a, 'b> {
+    fn drop(&mut self) {
+        if self
+            .env
+            .call_static_method(
+                "android/os/SharedMemory",
+                "unmap",
+                "(Ljava/nio/ByteBuffer;)V",
+                &[self.buffer.into()],
+            )
+            .is_err()
+        {
+            let _ = self.env.exception_clear();
+        }
+    }
+}
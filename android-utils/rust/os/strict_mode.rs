@@ -0,0 +1,311 @@
+use crate::{
+    ops::listener_stream,
+    util::{direct_executor, sdk_int},
+};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    JNIEnv,
+};
+
+/// StrictMode violation reported by the stream returned from [`violations`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViolationInfo {
+    /// Fully qualified name of the `android.os.strictmode.Violation`
+    /// subclass, such as `android.os.strictmode.DiskReadViolation`.
+    pub class_name: String,
+    /// Message of the violation, if any.
+    pub message: Option<String>,
+    /// Stack trace of the violation, as formatted by
+    /// `Log.getStackTraceString()`.
+    pub stack: String,
+}
+
+fn violation_info<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    violation: JObject<'a>,
+) -> Result<ViolationInfo> {
+    let class = env
+        .call_method(violation, "getClass", "()Ljava/lang/Class;", &[])?
+        .l()?;
+    let class_name = env
+        .call_method(class, "getName", "()Ljava/lang/String;", &[])?
+        .l()?;
+    let message = env
+        .call_method(violation, "getMessage", "()Ljava/lang/String;", &[])?
+        .l()?;
+    let stack = env
+        .call_static_method(
+            "android/util/Log",
+            "getStackTraceString",
+            "(Ljava/lang/Throwable;)Ljava/lang/String;",
+            &[violation.into()],
+        )?
+        .l()?;
+
+    Ok(ViolationInfo {
+        class_name: env.get_string(JString::from(class_name))?.into(),
+        message: if message.is_null() {
+            None
+        } else {
+            Some(env.get_string(JString::from(message))?.into())
+        },
+        stack: env.get_string(JString::from(stack))?.into(),
+    })
+}
+
+/// Get a stream of StrictMode violations on the current thread, typically
+/// the main thread. The thread policy is replaced with one which detects
+/// everything the current policy does plus all other violations, and
+/// reports them to the stream with `penaltyListener()`. Returns [`None`]
+/// before API level 28.
+///
+/// Dropping the stream does not restore the previous policy; call
+/// `StrictMode.setThreadPolicy()` from the same thread to do that.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn violations<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+) -> Result<Option<impl Stream<Item = Result<ViolationInfo>> + Send>> {
+    if sdk_int(env)? < 28 {
+        return Ok(None);
+    }
+
+    let interface = env.find_class("android/os/StrictMode$OnThreadViolationListener")?;
+    let (listener, stream) = listener_stream(env, interface)?;
+
+    let policy = env
+        .call_static_method(
+            "android/os/StrictMode",
+            "getThreadPolicy",
+            "()Landroid/os/StrictMode$ThreadPolicy;",
+            &[],
+        )?
+        .l()?;
+    let builder = env.new_object(
+        "android/os/StrictMode$ThreadPolicy$Builder",
+        "(Landroid/os/StrictMode$ThreadPolicy;)V",
+        &[policy.into()],
+    )?;
+    env.call_method(
+        builder,
+        "detectAll",
+        "()Landroid/os/StrictMode$ThreadPolicy$Builder;",
+        &[],
+    )?;
+    let executor = direct_executor(env)?;
+    env.call_method(
+        builder,
+        "penaltyListener",
+        "(Ljava/util/concurrent/Executor;Landroid/os/StrictMode$OnThreadViolationListener;)Landroid/os/StrictMode$ThreadPolicy$Builder;",
+        &[executor.into(), listener.into()],
+    )?;
+    let policy = env
+        .call_method(
+            builder,
+            "build",
+            "()Landroid/os/StrictMode$ThreadPolicy;",
+            &[],
+        )?
+        .l()?;
+    env.call_static_method(
+        "android/os/StrictMode",
+        "setThreadPolicy",
+        "(Landroid/os/StrictMode$ThreadPolicy;)V",
+        &[policy.into()],
+    )?;
+
+    let vm = env.get_java_vm()?;
+    Ok(Some(stream.map(move |call| {
+        let call = call?;
+        let env = vm.get_env()?;
+        violation_info(&env, call.args[0].as_obj())
+    })))
+}
+
+/// Thread policy to set with [`set_thread_policy`]. Each field enables the
+/// `StrictMode.ThreadPolicy.Builder` method of the same name. Detections
+/// which the OS doesn't support are skipped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreadPolicy {
+    /// `detectAll()`.
+    pub detect_all: bool,
+    /// `detectCustomSlowCalls()`.
+    pub detect_custom_slow_calls: bool,
+    /// `detectDiskReads()`.
+    pub detect_disk_reads: bool,
+    /// `detectDiskWrites()`.
+    pub detect_disk_writes: bool,
+    /// `detectNetwork()`.
+    pub detect_network: bool,
+    /// `detectResourceMismatches()`. API level 23.
+    pub detect_resource_mismatches: bool,
+    /// `detectUnbufferedIo()`. API level 26.
+    pub detect_unbuffered_io: bool,
+    /// `penaltyDeath()`.
+    pub penalty_death: bool,
+    /// `penaltyDeathOnNetwork()`.
+    pub penalty_death_on_network: bool,
+    /// `penaltyDialog()`.
+    pub penalty_dialog: bool,
+    /// `penaltyFlashScreen()`.
+    pub penalty_flash_screen: bool,
+    /// `penaltyLog()`.
+    pub penalty_log: bool,
+}
+
+/// VM policy to set with [`set_vm_policy`]. Each field enables the
+/// `StrictMode.VmPolicy.Builder` method of the same name. Detections which
+/// the OS doesn't support are skipped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VmPolicy {
+    /// `detectAll()`.
+    pub detect_all: bool,
+    /// `detectActivityLeaks()`.
+    pub detect_activity_leaks: bool,
+    /// `detectCleartextNetwork()`. API level 23.
+    pub detect_cleartext_network: bool,
+    /// `detectContentUriWithoutPermission()`. API level 26.
+    pub detect_content_uri_without_permission: bool,
+    /// `detectFileUriExposure()`.
+    pub detect_file_uri_exposure: bool,
+    /// `detectLeakedClosableObjects()`.
+    pub detect_leaked_closable_objects: bool,
+    /// `detectLeakedRegistrationObjects()`.
+    pub detect_leaked_registration_objects: bool,
+    /// `detectLeakedSqlLiteObjects()`.
+    pub detect_leaked_sql_lite_objects: bool,
+    /// `detectUntaggedSockets()`. API level 26.
+    pub detect_untagged_sockets: bool,
+    /// `penaltyDeath()`.
+    pub penalty_death: bool,
+    /// `penaltyLog()`.
+    pub penalty_log: bool,
+}
+
+fn build_policy<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    class: &str,
+    methods: &[(bool, &str, i32)],
+) -> Result<JObject<'a>> {
+    let builder_class = format!("{}$Builder", class);
+    let builder_sig = format!("()L{};", builder_class);
+    let builder = env.new_object(builder_class.as_str(), "()V", &[])?;
+    let sdk_int = sdk_int(env)?;
+    for (enabled, name, min_sdk) in methods {
+        if *enabled && sdk_int >= *min_sdk {
+            env.call_method(builder, *name, builder_sig.as_str(), &[])?;
+        }
+    }
+    env.call_method(builder, "build", format!("()L{};", class), &[])?
+        .l()
+}
+
+/// Set the StrictMode policy of the current thread with
+/// `StrictMode.setThreadPolicy()`. Call this from the thread to apply it to,
+/// typically the main thread that [`JHandlerSpawn`](super::JHandlerSpawn)
+/// posts to.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `policy` - Policy to set.
+pub fn set_thread_policy(env: &JNIEnv, policy: &ThreadPolicy) -> Result<()> {
+    let policy = build_policy(
+        env,
+        "android/os/StrictMode$ThreadPolicy",
+        &[
+            (policy.detect_all, "detectAll", 0),
+            (policy.detect_custom_slow_calls, "detectCustomSlowCalls", 0),
+            (policy.detect_disk_reads, "detectDiskReads", 0),
+            (policy.detect_disk_writes, "detectDiskWrites", 0),
+            (policy.detect_network, "detectNetwork", 0),
+            (
+                policy.detect_resource_mismatches,
+                "detectResourceMismatches",
+                23,
+            ),
+            (policy.detect_unbuffered_io, "detectUnbufferedIo", 26),
+            (policy.penalty_death, "penaltyDeath", 0),
+            (policy.penalty_death_on_network, "penaltyDeathOnNetwork", 0),
+            (policy.penalty_dialog, "penaltyDialog", 0),
+            (policy.penalty_flash_screen, "penaltyFlashScreen", 0),
+            (policy.penalty_log, "penaltyLog", 0),
+        ],
+    )?;
+    env.call_static_method(
+        "android/os/StrictMode",
+        "setThreadPolicy",
+        "(Landroid/os/StrictMode$ThreadPolicy;)V",
+        &[policy.into()],
+    )?;
+    Ok(())
+}
+
+/// Set the StrictMode policy of the whole process with
+/// `StrictMode.setVmPolicy()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `policy` - Policy to set.
+pub fn set_vm_policy(env: &JNIEnv, policy: &VmPolicy) -> Result<()> {
+    let policy = build_policy(
+        env,
+        "android/os/StrictMode$VmPolicy",
+        &[
+            (policy.detect_all, "detectAll", 0),
+            (policy.detect_activity_leaks, "detectActivityLeaks", 0),
+            (
+                policy.detect_cleartext_network,
+                "detectCleartextNetwork",
+                23,
+            ),
+            (
+                policy.detect_content_uri_without_permission,
+                "detectContentUriWithoutPermission",
+                26,
+            ),
+            (policy.detect_file_uri_exposure, "detectFileUriExposure", 0),
+            (
+                policy.detect_leaked_closable_objects,
+                "detectLeakedClosableObjects",
+                0,
+            ),
+            (
+                policy.detect_leaked_registration_objects,
+                "detectLeakedRegistrationObjects",
+                0,
+            ),
+            (
+                policy.detect_leaked_sql_lite_objects,
+                "detectLeakedSqlLiteObjects",
+                0,
+            ),
+            (policy.detect_untagged_sockets, "detectUntaggedSockets", 26),
+            (policy.penalty_death, "penaltyDeath", 0),
+            (policy.penalty_log, "penaltyLog", 0),
+        ],
+    )?;
+    env.call_static_method(
+        "android/os/StrictMode",
+        "setVmPolicy",
+        "(Landroid/os/StrictMode$VmPolicy;)V",
+        &[policy.into()],
+    )?;
+    Ok(())
+}
+
+/// Call `StrictMode.enableDefaults()`, which detects everything on the
+/// current thread and the VM and logs violations.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn enable_defaults(env: &JNIEnv) -> Result<()> {
+    env.call_static_method("android/os/StrictMode", "enableDefaults", "()V", &[])?;
+    Ok(())
+}
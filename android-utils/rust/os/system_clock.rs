@@ -0,0 +1,72 @@
+//! Wrappers around `android.os.SystemClock`.
+//!
+//! Android has several clocks, and APIs which take timestamps expect them on
+//! a particular one:
+//!
+//! * [`uptime`] stops in deep sleep. It is the time base of
+//!   `Handler.postAtTime()` and `MotionEvent` timestamps.
+//! * [`elapsed_realtime`] keeps counting in deep sleep. It is the time base
+//!   of `AlarmManager.ELAPSED_REALTIME` alarms and `Location` timestamps.
+//! * [`current_thread_time`] only counts time the current thread spent
+//!   running.
+//!
+//! All of them are measured from boot and returned as a [`Duration`].
+
+use jni::{errors::Result, JNIEnv};
+use std::time::Duration;
+
+fn call_long(env: &JNIEnv, name: &str) -> Result<i64> {
+    env.call_static_method("android/os/SystemClock", name, "()J", &[])?
+        .j()
+}
+
+/// Get `SystemClock.uptimeMillis()`, the time since boot not counting deep
+/// sleep, which is the time base of
+/// [`JHandler::post_at_time`](super::JHandler::post_at_time).
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn uptime(env: &JNIEnv) -> Result<Duration> {
+    Ok(Duration::from_millis(call_long(env, "uptimeMillis")? as u64))
+}
+
+/// Get `SystemClock.elapsedRealtimeNanos()`, the time since boot including
+/// deep sleep.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn elapsed_realtime(env: &JNIEnv) -> Result<Duration> {
+    Ok(Duration::from_nanos(
+        call_long(env, "elapsedRealtimeNanos")? as u64,
+    ))
+}
+
+/// Get `SystemClock.currentThreadTimeMillis()`, the time the current thread
+/// has spent running.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn current_thread_time(env: &JNIEnv) -> Result<Duration> {
+    Ok(Duration::from_millis(
+        call_long(env, "currentThreadTimeMillis")? as u64,
+    ))
+}
+
+/// Call `SystemClock.sleep()`, which blocks the current thread for the given
+/// duration on the [`uptime`] clock, ignoring interrupts.
+///
+/// Unlike [`std::thread::sleep`], this is aware of test environments such as
+/// Robolectric which replace the system clock.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `duration` - Time to sleep for. Rounded down to whole milliseconds.
+pub fn sleep(env: &JNIEnv, duration: Duration) -> Result<()> {
+    let millis = duration.as_millis().min(i64::MAX as u128) as i64;
+    env.call_static_method("android/os/SystemClock", "sleep", "(J)V", &[millis.into()])?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use crate::{
+    content::JContext,
+    ops::listener_stream,
+    util::{direct_executor, sdk_int, RemoveListenerOnDrop},
+};
+use futures::{Stream, StreamExt};
+use jni::{
+    errors::Result,
+    objects::JObject,
+    sys::{jfloat, jint},
+    JNIEnv,
+};
+use std::time::Duration;
+
+/// `android.os.PowerManager.THERMAL_STATUS_NONE`.
+pub const THERMAL_STATUS_NONE: jint = 0;
+
+/// `android.os.PowerManager.THERMAL_STATUS_LIGHT`.
+pub const THERMAL_STATUS_LIGHT: jint = 1;
+
+/// `android.os.PowerManager.THERMAL_STATUS_MODERATE`.
+pub const THERMAL_STATUS_MODERATE: jint = 2;
+
+/// `android.os.PowerManager.THERMAL_STATUS_SEVERE`.
+pub const THERMAL_STATUS_SEVERE: jint = 3;
+
+/// `android.os.PowerManager.THERMAL_STATUS_CRITICAL`.
+pub const THERMAL_STATUS_CRITICAL: jint = 4;
+
+/// `android.os.PowerManager.THERMAL_STATUS_EMERGENCY`.
+pub const THERMAL_STATUS_EMERGENCY: jint = 5;
+
+/// `android.os.PowerManager.THERMAL_STATUS_SHUTDOWN`.
+pub const THERMAL_STATUS_SHUTDOWN: jint = 6;
+
+fn power_manager<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service("power")
+}
+
+/// Get the current thermal status of the device. Returns one of the
+/// `THERMAL_STATUS_*` constants, or [`None`] before API level 29.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PowerManager` from.
+pub fn thermal_status<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<jint>> {
+    if sdk_int(env)? < 29 {
+        return Ok(None);
+    }
+
+    let manager = power_manager(env, context)?;
+    Ok(Some(
+        env.call_method(manager, "getCurrentThermalStatus", "()I", &[])?
+            .i()?,
+    ))
+}
+
+/// Get a stream of thermal status changes, using
+/// `PowerManager.addThermalStatusListener()`. The current status is reported
+/// as soon as the listener is added. The listener is removed when the stream
+/// is dropped. Returns [`None`] before API level 29.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PowerManager` from.
+pub fn thermal_events<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+) -> Result<Option<impl Stream<Item = Result<jint>> + Send>> {
+    if sdk_int(env)? < 29 {
+        return Ok(None);
+    }
+
+    let manager = power_manager(env, context)?;
+    let interface = env.find_class("android/os/PowerManager$OnThermalStatusChangedListener")?;
+    let (listener, stream) = listener_stream(env, interface)?;
+    let executor = direct_executor(env)?;
+    env.call_method(
+        manager,
+        "addThermalStatusListener",
+        "(Ljava/util/concurrent/Executor;Landroid/os/PowerManager$OnThermalStatusChangedListener;)V",
+        &[executor.into(), listener.into()],
+    )?;
+    let guard = RemoveListenerOnDrop::new(
+        env,
+        manager,
+        listener,
+        "removeThermalStatusListener",
+        "(Landroid/os/PowerManager$OnThermalStatusChangedListener;)V",
+    )?;
+
+    let vm = env.get_java_vm()?;
+    Ok(Some(stream.map(move |call| {
+        let _ = &guard;
+        let call = call?;
+        let env = vm.get_env()?;
+        env.call_method(call.args[0].as_obj(), "intValue", "()I", &[])?
+            .i()
+    })))
+}
+
+/// Get the thermal headroom forecast for `forecast` from now, using
+/// `PowerManager.getThermalHeadroom()`. A value of `1.0` means the device is
+/// expected to reach [`THERMAL_STATUS_SEVERE`], and `0.0` means no thermal
+/// pressure. Returns [`None`] before API level 30, or if the device doesn't
+/// support it or it was called too often.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `PowerManager` from.
+/// * `forecast` - How far ahead to forecast, between zero and 60 seconds.
+pub fn thermal_headroom<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    forecast: Duration,
+) -> Result<Option<jfloat>> {
+    if sdk_int(env)? < 30 {
+        return Ok(None);
+    }
+
+    let manager = power_manager(env, context)?;
+    let headroom = env
+        .call_method(
+            manager,
+            "getThermalHeadroom",
+            "(I)F",
+            &[(forecast.as_secs() as jint).into()],
+        )?
+        .f()?;
+    Ok(if headroom.is_nan() {
+        None
+    } else {
+        Some(headroom)
+    })
+}
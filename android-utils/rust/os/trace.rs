@@ -0,0 +1,198 @@
+//! Wrappers around `android.os.Trace`, so that Rust code shows up in systrace
+//! and Perfetto traces alongside Java code.
+//!
+//! Synchronous sections, created with [`section`], must begin and end on the
+//! same thread and nest properly. Asynchronous sections, created with
+//! [`async_section`] or [`traced`], may end on any thread and overlap, which
+//! suits futures that move between threads or are polled in pieces. They are
+//! matched up by name and cookie. Asynchronous sections were added in API
+//! level 29, and are silently skipped on older versions.
+//!
+//! Section names must be at most 127 characters long, or `Trace` throws an
+//! `IllegalArgumentException`.
+
+use crate::util::sdk_int;
+use jni::{errors::Result, JNIEnv, JavaVM};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicI32, Ordering},
+    task::{Context, Poll},
+};
+
+const TRACE_CLASS: &str = "android/os/Trace";
+
+static NEXT_COOKIE: AtomicI32 = AtomicI32::new(1);
+
+/// Check whether a trace is being captured, using `Trace.isEnabled()`. On
+/// API levels older than 29, this always returns `true`, since the check is
+/// not available.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+pub fn is_enabled(env: &JNIEnv) -> Result<bool> {
+    if sdk_int(env)? < 29 {
+        return Ok(true);
+    }
+    env.call_static_method(TRACE_CLASS, "isEnabled", "()Z", &[])?
+        .z()
+}
+
+/// Synchronous trace section. Created by [`section`]. Calls
+/// `Trace.endSection()` when dropped.
+pub struct Section<'a: 'b, 'b> {
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> Drop for Section<'a, 'b> {
+    fn drop(&mut self) {
+        let _ = self
+            .env
+            .call_static_method(TRACE_CLASS, "endSection", "()V", &[]);
+    }
+}
+
+/// Begin a synchronous trace section with `Trace.beginSection()`. The
+/// section ends when the returned [`Section`] is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `name` - Name of the section.
+pub fn section<'a: 'b, 'b>(env: &'b JNIEnv<'a>, name: &str) -> Result<Section<'a, 'b>> {
+    let name = env.new_string(name)?;
+    env.call_static_method(
+        TRACE_CLASS,
+        "beginSection",
+        "(Ljava/lang/String;)V",
+        &[name.into()],
+    )?;
+    Ok(Section { env })
+}
+
+fn call_async_section(env: &JNIEnv, method: &str, name: &str, cookie: i32) -> Result<()> {
+    let name = env.new_string(name)?;
+    env.call_static_method(
+        TRACE_CLASS,
+        method,
+        "(Ljava/lang/String;I)V",
+        &[name.into(), cookie.into()],
+    )?;
+    Ok(())
+}
+
+/// Call `Trace.beginAsyncSection()`. Does nothing on API levels older than
+/// 29.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `name` - Name of the section.
+/// * `cookie` - Cookie distinguishing this section from others of the same
+///   name.
+pub fn begin_async_section(env: &JNIEnv, name: &str, cookie: i32) -> Result<()> {
+    if sdk_int(env)? < 29 {
+        return Ok(());
+    }
+    call_async_section(env, "beginAsyncSection", name, cookie)
+}
+
+/// Call `Trace.endAsyncSection()`. Does nothing on API levels older than 29.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `name` - Name of the section.
+/// * `cookie` - Cookie passed to [`begin_async_section`].
+pub fn end_async_section(env: &JNIEnv, name: &str, cookie: i32) -> Result<()> {
+    if sdk_int(env)? < 29 {
+        return Ok(());
+    }
+    call_async_section(env, "endAsyncSection", name, cookie)
+}
+
+/// Asynchronous trace section. Created by [`async_section`]. Ends the
+/// section when dropped, attaching the current thread if needed.
+pub struct AsyncSection {
+    name: String,
+    cookie: i32,
+    vm: Option<JavaVM>,
+}
+
+impl AsyncSection {
+    /// Get the name of the section.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the cookie of the section.
+    pub fn cookie(&self) -> i32 {
+        self.cookie
+    }
+}
+
+impl Drop for AsyncSection {
+    fn drop(&mut self) {
+        if let Some(vm) = &self.vm {
+            if let Ok(env) = vm.attach_current_thread() {
+                let _ = call_async_section(&env, "endAsyncSection", &self.name, self.cookie);
+            }
+        }
+    }
+}
+
+/// Begin an asynchronous trace section with a unique cookie. The section
+/// ends when the returned [`AsyncSection`] is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `name` - Name of the section.
+pub fn async_section(env: &JNIEnv, name: &str) -> Result<AsyncSection> {
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    let vm = if sdk_int(env)? >= 29 {
+        call_async_section(env, "beginAsyncSection", name, cookie)?;
+        Some(env.get_java_vm()?)
+    } else {
+        None
+    };
+    Ok(AsyncSection {
+        name: name.into(),
+        cookie,
+        vm,
+    })
+}
+
+/// Future returned by [`traced`].
+pub struct Traced<F: Future> {
+    future: Pin<Box<F>>,
+    section: Option<AsyncSection>,
+}
+
+impl<F: Future> Future for Traced<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = self.future.as_mut().poll(cx);
+        if result.is_ready() {
+            self.section.take();
+        }
+        result
+    }
+}
+
+/// Wrap a future in an asynchronous trace section, which begins immediately
+/// and ends when the future completes or is dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `name` - Name of the section.
+/// * `future` - Future to trace.
+pub fn traced<F: Future>(env: &JNIEnv, name: &str, future: F) -> Result<Traced<F>> {
+    Ok(Traced {
+        future: Box::pin(future),
+        section: Some(async_section(env, name)?),
+    })
+}
@@ -0,0 +1,75 @@
+use jni::{objects::GlobalRef, JNIEnv};
+use std::{mem, sync::Mutex};
+
+static DEFERRED: Mutex<Vec<GlobalRef>> = Mutex::new(Vec::new());
+
+fn is_attached() -> bool {
+    match crate::java_vm() {
+        Some(vm) => vm.get_env().is_ok(),
+        None => true,
+    }
+}
+
+/// Wrapper for [`GlobalRef`] which defers deleting the reference if it is
+/// dropped on a thread that is not attached to the Java VM.
+///
+/// Dropping a plain [`GlobalRef`] on such a thread attaches and detaches the
+/// thread for every reference, which is very slow when many references are
+/// dropped at once. Instead, this wrapper adds the reference to a queue which
+/// is flushed on an attached thread, either by the `Handler` executor or
+/// explicitly with [`flush_refs`].
+#[derive(Clone)]
+pub struct DeferredGlobalRef(Option<GlobalRef>);
+
+impl DeferredGlobalRef {
+    /// Wrap a [`GlobalRef`].
+    ///
+    /// # Arguments
+    ///
+    /// * `global_ref` - Reference to wrap.
+    pub fn new(global_ref: GlobalRef) -> Self {
+        Self(Some(global_ref))
+    }
+
+    /// Unwrap the [`GlobalRef`]. The returned reference is deleted
+    /// immediately when dropped.
+    pub fn into_inner(mut self) -> GlobalRef {
+        self.0.take().unwrap()
+    }
+}
+
+impl From<GlobalRef> for DeferredGlobalRef {
+    fn from(global_ref: GlobalRef) -> Self {
+        Self::new(global_ref)
+    }
+}
+
+impl ::std::ops::Deref for DeferredGlobalRef {
+    type Target = GlobalRef;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl Drop for DeferredGlobalRef {
+    fn drop(&mut self) {
+        if let Some(global_ref) = self.0.take() {
+            if !is_attached() {
+                DEFERRED.lock().unwrap().push(global_ref);
+            }
+        }
+    }
+}
+
+/// Delete all of the references whose deletion was deferred by
+/// [`DeferredGlobalRef`]. This is called automatically by the `Handler`
+/// executor, but may also be called manually from any attached thread.
+///
+/// # Arguments
+///
+/// * `env` - Java environment of the current thread.
+pub fn flush_refs(_env: &JNIEnv) {
+    let refs = mem::take(&mut *DEFERRED.lock().unwrap());
+    drop(refs);
+}
@@ -1,28 +1,49 @@
-use futures::{Stream, StreamExt};
+use crate::{
+    content::JContext,
+    os::{AbortHandle, JLooper, JPersistableBundle},
+    refs::DeferredGlobalRef,
+    util::{sdk_int, system_service},
+};
+use futures::{channel::mpsc, future::BoxFuture, task::SpawnError, Future, Stream, StreamExt};
 use jni::{
     descriptors::Desc,
-    errors::Result,
+    errors::{Error, Result},
     objects::{GlobalRef, JClass, JObject},
-    sys::jint,
-    JNIEnv,
+    sys::{jboolean, jint},
+    JNIEnv, JavaVM, NativeMethod,
 };
 use jni_utils::stream::{JSendStream, JStream};
-use std::{convert::TryFrom, sync::Arc};
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    ffi::c_void,
+    fmt::{self, Display},
+    ops::BitOr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "serde")]
+pub mod rpc;
 
 /// Represents events that have been captured by an
 /// `android.content.ServiceConnection`.
 pub enum ServiceConnectionEvent {
     /// Created by `ServiceConnection.onBindingDied()`.
-    BindingDied { component_name: GlobalRef },
+    BindingDied { component_name: DeferredGlobalRef },
     /// Created by `ServiceConnection.onNullBinding()`.
-    NullBinding { component_name: GlobalRef },
+    NullBinding { component_name: DeferredGlobalRef },
     /// Created by `ServiceConnection.onServiceConnected()`.
     ServiceConnected {
-        component_name: GlobalRef,
-        service: GlobalRef,
+        component_name: DeferredGlobalRef,
+        service: DeferredGlobalRef,
     },
     /// Created by `ServiceConnection.onServiceDisconnected()`.
-    ServiceDisconnected { component_name: GlobalRef },
+    ServiceDisconnected { component_name: DeferredGlobalRef },
 }
 
 /// Creates an `android.content.ServiceConnection` and an accompanying stream
@@ -34,6 +55,7 @@ pub fn async_service_connection<'a: 'b, 'b>(
     impl Stream<Item = Result<ServiceConnectionEvent>> + Send,
 )> {
     let vm = env.get_java_vm()?;
+    let classes = crate::cache::service_connection_classes(env)?;
     let conn = env.new_object(
         "io/github/gedgygedgy/rust/android/content/RustServiceConnection",
         "()V",
@@ -54,52 +76,50 @@ pub fn async_service_connection<'a: 'b, 'b>(
         let item = item.as_obj();
         let env = vm.get_env()?;
 
-        if env.is_instance_of(
-            item,
-            "io/github/gedgygedgy/rust/android/content/RustServiceConnection$BindingDiedEvent",
-        )? {
-            let name = env.new_global_ref(
-                env.get_field(item, "name", "Landroid/content/ComponentName;")?
-                    .l()?,
-            )?;
+        if env.is_instance_of(item, &classes.binding_died)? {
+            let name = DeferredGlobalRef::new(
+                env.new_global_ref(
+                    env.get_field(item, "name", "Landroid/content/ComponentName;")?
+                        .l()?,
+                )?,
+            );
             Ok(ServiceConnectionEvent::BindingDied {
                 component_name: name,
             })
-        } else if env.is_instance_of(
-            item,
-            "io/github/gedgygedgy/rust/android/content/RustServiceConnection$NullBindingEvent",
-        )? {
-            let name = env.new_global_ref(
-                env.get_field(item, "name", "Landroid/content/ComponentName;")?
-                    .l()?,
-            )?;
+        } else if env.is_instance_of(item, &classes.null_binding)? {
+            let name = DeferredGlobalRef::new(
+                env.new_global_ref(
+                    env.get_field(item, "name", "Landroid/content/ComponentName;")?
+                        .l()?,
+                )?,
+            );
             Ok(ServiceConnectionEvent::NullBinding {
                 component_name: name,
             })
-        } else if env.is_instance_of(
-            item,
-            "io/github/gedgygedgy/rust/android/content/RustServiceConnection$ServiceConnectedEvent",
-        )? {
-            let name = env.new_global_ref(
-                env.get_field(item, "name", "Landroid/content/ComponentName;")?
-                    .l()?,
-            )?;
-            let service = env.new_global_ref(
-                env.get_field(item, "service", "Landroid/os/IBinder;")?
-                    .l()?,
-            )?;
+        } else if env.is_instance_of(item, &classes.service_connected)? {
+            let name = DeferredGlobalRef::new(
+                env.new_global_ref(
+                    env.get_field(item, "name", "Landroid/content/ComponentName;")?
+                        .l()?,
+                )?,
+            );
+            let service = DeferredGlobalRef::new(
+                env.new_global_ref(
+                    env.get_field(item, "service", "Landroid/os/IBinder;")?
+                        .l()?,
+                )?,
+            );
             Ok(ServiceConnectionEvent::ServiceConnected {
                 component_name: name,
                 service,
             })
-        } else if env.is_instance_of(
-            item,
-            "io/github/gedgygedgy/rust/android/content/RustServiceConnection$ServiceDisconnectedEvent",
-        )? {
-            let name = env.new_global_ref(
-                env.get_field(item, "name", "Landroid/content/ComponentName;")?
-                    .l()?,
-            )?;
+        } else if env.is_instance_of(item, &classes.service_disconnected)? {
+            let name = DeferredGlobalRef::new(
+                env.new_global_ref(
+                    env.get_field(item, "name", "Landroid/content/ComponentName;")?
+                        .l()?,
+                )?,
+            );
             Ok(ServiceConnectionEvent::ServiceDisconnected {
                 component_name: name,
             })
@@ -111,67 +131,1329 @@ pub fn async_service_connection<'a: 'b, 'b>(
     Ok((conn, mapped_stream))
 }
 
-/// `android.app.Service.START_FLAG_REDELIVERY`.
-pub const START_FLAG_REDELIVERY: jint = 1;
+/// `android.content.Context.BIND_AUTO_CREATE`.
+pub const BIND_AUTO_CREATE: jint = 0x0001;
+
+/// `android.content.Context.BIND_DEBUG_UNBIND`.
+pub const BIND_DEBUG_UNBIND: jint = 0x0002;
+
+/// `android.content.Context.BIND_NOT_FOREGROUND`.
+pub const BIND_NOT_FOREGROUND: jint = 0x0004;
+
+/// `android.content.Context.BIND_ABOVE_CLIENT`.
+pub const BIND_ABOVE_CLIENT: jint = 0x0008;
+
+/// `android.content.Context.BIND_ALLOW_OOM_MANAGEMENT`.
+pub const BIND_ALLOW_OOM_MANAGEMENT: jint = 0x0010;
+
+/// `android.content.Context.BIND_WAIVE_PRIORITY`.
+pub const BIND_WAIVE_PRIORITY: jint = 0x0020;
+
+/// `android.content.Context.BIND_IMPORTANT`.
+pub const BIND_IMPORTANT: jint = 0x0040;
+
+/// `android.content.Context.BIND_ADJUST_WITH_ACTIVITY`.
+pub const BIND_ADJUST_WITH_ACTIVITY: jint = 0x0080;
+
+/// `android.content.Context.BIND_NOT_PERCEPTIBLE`. Requires API level 29.
+pub const BIND_NOT_PERCEPTIBLE: jint = 0x0100;
+
+/// `android.content.Context.BIND_INCLUDE_CAPABILITIES`. Requires API level
+/// 29.
+pub const BIND_INCLUDE_CAPABILITIES: jint = 0x1000;
+
+/// `android.content.Context.BIND_EXTERNAL_SERVICE`. Requires API level 24.
+pub const BIND_EXTERNAL_SERVICE: jint = 0x8000_0000_u32 as jint;
+
+/// Bind to a service with `Context.bindService()`. Returns `false` if the
+/// service could not be found or the caller is not allowed to bind to it, in
+/// which case `conn` should still be passed to [`unbind_service`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to bind from.
+/// * `intent` - `android.content.Intent` identifying the service.
+/// * `conn` - `android.content.ServiceConnection` to notify, such as the one
+///   created by [`async_service_connection`].
+/// * `flags` - Combination of the `BIND_*` flags.
+pub fn bind_service<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    intent: JObject<'a>,
+    conn: JObject<'a>,
+    flags: jint,
+) -> Result<bool> {
+    JContext::from_env(env, context)?.bind_service(intent, conn, flags)
+}
+
+/// Unbind from a service with `Context.unbindService()`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` which was passed to
+///   [`bind_service`].
+/// * `conn` - `android.content.ServiceConnection` which was passed to
+///   [`bind_service`].
+pub fn unbind_service<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    conn: JObject<'a>,
+) -> Result<()> {
+    env.call_method(
+        context,
+        "unbindService",
+        "(Landroid/content/ServiceConnection;)V",
+        &[conn.into()],
+    )?;
+    Ok(())
+}
+
+/// Error returned by [`connect_service`].
+#[derive(Debug)]
+pub enum ConnectServiceError {
+    /// A JNI call failed.
+    Jni(jni::errors::Error),
+    /// `Context.bindService()` returned `false`.
+    BindFailed,
+    /// The service returned a null binder from `onBind()`.
+    NullBinding,
+    /// The binding died before the service connected.
+    BindingDied,
+}
+
+impl Display for ConnectServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jni(err) => write!(f, "JNI error: {}", err),
+            Self::BindFailed => f.write_str("could not bind to service"),
+            Self::NullBinding => f.write_str("service returned a null binding"),
+            Self::BindingDied => f.write_str("service binding died"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectServiceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Jni(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<jni::errors::Error> for ConnectServiceError {
+    fn from(err: jni::errors::Error) -> Self {
+        Self::Jni(err)
+    }
+}
+
+/// Bind to a service and wait for it to connect. Returns the
+/// `android.content.ServiceConnection` that was bound, and a future which
+/// resolves to the service's `android.os.IBinder` once
+/// `ServiceConnection.onServiceConnected()` is called.
+///
+/// The connection stays bound after the future resolves or fails, and must
+/// be passed to [`unbind_service`] when it is no longer needed. If
+/// `Context.bindService()` fails, the connection is unbound and
+/// [`ConnectServiceError::BindFailed`] is returned.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to bind from.
+/// * `intent` - `android.content.Intent` identifying the service.
+/// * `flags` - Combination of the `BIND_*` flags.
+pub fn connect_service<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    intent: JObject<'a>,
+    flags: jint,
+) -> std::result::Result<
+    (
+        JObject<'a>,
+        impl Future<Output = std::result::Result<DeferredGlobalRef, ConnectServiceError>> + Send,
+    ),
+    ConnectServiceError,
+> {
+    let (conn, mut stream) = async_service_connection(env)?;
+    if !bind_service(env, context, intent, conn, flags)? {
+        unbind_service(env, context, conn)?;
+        return Err(ConnectServiceError::BindFailed);
+    }
+
+    Ok((conn, async move {
+        loop {
+            match stream.next().await {
+                Some(event) => match event? {
+                    ServiceConnectionEvent::ServiceConnected { service, .. } => return Ok(service),
+                    ServiceConnectionEvent::NullBinding { .. } => {
+                        return Err(ConnectServiceError::NullBinding)
+                    }
+                    ServiceConnectionEvent::BindingDied { .. } => {
+                        return Err(ConnectServiceError::BindingDied)
+                    }
+                    ServiceConnectionEvent::ServiceDisconnected { .. } => {}
+                },
+                // The connection's stream is never closed while it is alive.
+                None => return Err(ConnectServiceError::BindingDied),
+            }
+        }
+    }))
+}
+
+/// Availability of the service of a [`ResilientConnection`].
+pub enum BinderState {
+    /// The service is connected, with the given `android.os.IBinder`.
+    Available(DeferredGlobalRef),
+    /// The service was disconnected or its binding died. The connection is
+    /// rebound after the backoff delay.
+    Unavailable,
+}
+
+/// Delays between attempts of a [`ResilientConnection`] to rebind its
+/// service. The delay starts at `initial`, is multiplied by `multiplier`
+/// after each attempt that fails to connect, up to `max`, and is reset once
+/// the service connects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay before the first attempt to rebind.
+    pub initial: Duration,
+    /// Maximum delay between attempts.
+    pub max: Duration,
+    /// Factor to multiply the delay by after each failed attempt.
+    pub multiplier: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            multiplier: 2,
+        }
+    }
+}
+
+struct ResilientState {
+    closed: bool,
+    conn: Option<GlobalRef>,
+}
+
+/// Connection to a service which rebinds it when it is disconnected or its
+/// binding dies, for example because its process crashed. Created with
+/// [`ResilientConnection::new`], along with a stream of [`BinderState`]s.
+///
+/// Dropping the connection unbinds the service and ends the stream.
+pub struct ResilientConnection {
+    context: GlobalRef,
+    state: Arc<Mutex<ResilientState>>,
+    task: AbortHandle,
+    vm: JavaVM,
+}
+
+impl ResilientConnection {
+    /// Bind a service, and keep rebinding it with [`bind_service`] whenever
+    /// `ServiceConnection.onServiceDisconnected()` or
+    /// `ServiceConnection.onBindingDied()` is called, or binding fails. The
+    /// returned stream yields [`BinderState::Available`] each time the
+    /// service connects, and [`BinderState::Unavailable`] each time it is
+    /// lost. Returns an error with a pending
+    /// `java.lang.IllegalStateException` if the connection could not be
+    /// spawned on `looper`, for example because it has quit.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `context` - `android.content.Context` to bind from.
+    /// * `intent` - `android.content.Intent` identifying the service.
+    /// * `flags` - Combination of the `BIND_*` flags.
+    /// * `backoff` - Delays between attempts to rebind.
+    /// * `looper` - Looper to run the connection on. Binder states are
+    ///   received on it, and it must not be blocked while waiting for them.
+    pub fn new<'a: 'b, 'b>(
+        env: &'b JNIEnv<'a>,
+        context: JObject<'a>,
+        intent: JObject<'a>,
+        flags: jint,
+        backoff: Backoff,
+        looper: &JLooper<'a, 'b>,
+    ) -> Result<(Self, impl Stream<Item = BinderState> + Send)> {
+        let context = env.new_global_ref(context)?;
+        let intent = env.new_global_ref(intent)?;
+        let looper_ref = env.new_global_ref(**looper)?;
+        let state = Arc::new(Mutex::new(ResilientState {
+            closed: false,
+            conn: None,
+        }));
+        let (sender, receiver) = mpsc::unbounded();
+        let vm = env.get_java_vm()?;
+
+        let task_context = context.clone();
+        let task_state = state.clone();
+        let task = async move {
+            let mut retry_delay = backoff.initial;
+            loop {
+                let stream = crate::env().and_then(|env| {
+                    Self::bind(
+                        &env,
+                        &task_state,
+                        task_context.as_obj(),
+                        intent.as_obj(),
+                        flags,
+                    )
+                });
+                match stream {
+                    Ok(Some(mut stream)) => {
+                        let mut connected = false;
+                        while let Some(event) = stream.next().await {
+                            match event {
+                                Ok(ServiceConnectionEvent::ServiceConnected {
+                                    service, ..
+                                }) => {
+                                    connected = true;
+                                    retry_delay = backoff.initial;
+                                    let _ = sender.unbounded_send(BinderState::Available(service));
+                                }
+                                Ok(ServiceConnectionEvent::NullBinding { .. }) => {}
+                                Ok(_) => break,
+                                Err(err) => {
+                                    log::error!("Service connection failed: {}", err);
+                                    break;
+                                }
+                            }
+                        }
+                        if connected {
+                            let _ = sender.unbounded_send(BinderState::Unavailable);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => log::error!("Failed to bind service: {}", err),
+                }
+
+                let unbound = crate::env()
+                    .and_then(|env| Self::unbind(&env, &task_state, task_context.as_obj()));
+                if let Err(err) = unbound {
+                    log::error!("Failed to unbind service: {}", err);
+                }
+
+                let delay = crate::env().and_then(|env| {
+                    let looper = JLooper::from_env(&env, looper_ref.as_obj())?;
+                    crate::os::delay(&looper.handler()?, retry_delay)
+                });
+                match delay {
+                    Ok(delay) => delay.await,
+                    Err(err) => {
+                        log::error!("Failed to wait before rebinding service: {}", err);
+                        return;
+                    }
+                }
+                retry_delay = retry_delay
+                    .checked_mul(backoff.multiplier)
+                    .map_or(backoff.max, |delay| delay.min(backoff.max));
+            }
+        };
+        let task = looper
+            .handler()?
+            .spawner()
+            .spawn_abortable(task)
+            .map_err(|err| {
+                let _ = env.throw_new("java/lang/IllegalStateException", err.to_string());
+                Error::JavaException
+            })?;
+
+        Ok((
+            Self {
+                context,
+                state,
+                task,
+                vm,
+            },
+            receiver,
+        ))
+    }
+
+    fn bind<'a: 'b, 'b>(
+        env: &'b JNIEnv<'a>,
+        state: &Mutex<ResilientState>,
+        context: JObject<'a>,
+        intent: JObject<'a>,
+        flags: jint,
+    ) -> Result<Option<impl Stream<Item = Result<ServiceConnectionEvent>> + Send>> {
+        let mut state = state.lock().unwrap();
+        if state.closed {
+            return Ok(None);
+        }
+        let (conn, stream) = async_service_connection(env)?;
+        if !bind_service(env, context, intent, conn, flags)? {
+            unbind_service(env, context, conn)?;
+            return Ok(None);
+        }
+        state.conn = Some(env.new_global_ref(conn)?);
+        Ok(Some(stream))
+    }
+
+    fn unbind<'a: 'b, 'b>(
+        env: &'b JNIEnv<'a>,
+        state: &Mutex<ResilientState>,
+        context: JObject<'a>,
+    ) -> Result<()> {
+        match state.lock().unwrap().conn.take() {
+            Some(conn) => unbind_service(env, context, conn.as_obj()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ResilientConnection {
+    fn drop(&mut self) {
+        let _ = self.task.abort();
+        self.state.lock().unwrap().closed = true;
+        if let Ok(env) = self.vm.attach_current_thread() {
+            if Self::unbind(&env, &self.state, self.context.as_obj()).is_err() {
+                let _ = env.exception_clear();
+            }
+        }
+    }
+}
+
+/// Flags passed to [`RustService::on_start_command`], from the `START_FLAG_*`
+/// constants of `android.app.Service`. These are bit flags, so more than one
+/// can be set at a time; check them with [`contains`](Self::contains).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StartFlags(jint);
+
+impl StartFlags {
+    /// No flags. The start command is being delivered for the first time.
+    pub const NONE: Self = Self(0);
+    /// `Service.START_FLAG_REDELIVERY`. The intent is being redelivered
+    /// because the service returned [`ServiceStartResult::RedeliverIntent`]
+    /// and was killed before stopping itself.
+    pub const REDELIVERY: Self = Self(1);
+    /// `Service.START_FLAG_RETRY`. The start command is being retried
+    /// because the service never returned from `onStartCommand()`.
+    pub const RETRY: Self = Self(2);
+
+    /// Get the raw `flags` value passed to `onStartCommand()`.
+    pub fn bits(self) -> jint {
+        self.0
+    }
+
+    /// Check whether all of the flags in `other` are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Flags to check for.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl From<jint> for StartFlags {
+    fn from(raw: jint) -> Self {
+        Self(raw)
+    }
+}
+
+impl From<StartFlags> for jint {
+    fn from(flags: StartFlags) -> Self {
+        flags.0
+    }
+}
+
+impl BitOr for StartFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Value returned from [`RustService::on_start_command`], from the
+/// `START_*` constants of `android.app.Service`. Tells the system what to do
+/// if the service is killed while it is started.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceStartResult {
+    /// `Service.START_STICKY_COMPATIBILITY`. Like [`Self::Sticky`], but the
+    /// service might not be restarted.
+    StickyCompatibility = 0,
+    /// `Service.START_STICKY`. Restart the service, with a null intent if
+    /// there are no pending start commands.
+    Sticky = 1,
+    /// `Service.START_NOT_STICKY`. Don't restart the service unless there
+    /// are pending start commands.
+    NotSticky = 2,
+    /// `Service.START_REDELIVER_INTENT`. Restart the service and redeliver
+    /// the last intent it received.
+    RedeliverIntent = 3,
+}
+
+impl From<ServiceStartResult> for jint {
+    fn from(result: ServiceStartResult) -> Self {
+        result as jint
+    }
+}
+
+impl TryFrom<jint> for ServiceStartResult {
+    type Error = jint;
+
+    fn try_from(raw: jint) -> std::result::Result<Self, jint> {
+        match raw {
+            0 => Ok(Self::StickyCompatibility),
+            1 => Ok(Self::Sticky),
+            2 => Ok(Self::NotSticky),
+            3 => Ok(Self::RedeliverIntent),
+            _ => Err(raw),
+        }
+    }
+}
+
+/// `android.content.ComponentCallbacks2.TRIM_MEMORY_RUNNING_MODERATE`.
+pub const TRIM_MEMORY_RUNNING_MODERATE: jint = 5;
+
+/// `android.content.ComponentCallbacks2.TRIM_MEMORY_RUNNING_LOW`.
+pub const TRIM_MEMORY_RUNNING_LOW: jint = 10;
+
+/// `android.content.ComponentCallbacks2.TRIM_MEMORY_RUNNING_CRITICAL`.
+pub const TRIM_MEMORY_RUNNING_CRITICAL: jint = 15;
+
+/// `android.content.ComponentCallbacks2.TRIM_MEMORY_UI_HIDDEN`.
+pub const TRIM_MEMORY_UI_HIDDEN: jint = 20;
+
+/// `android.content.ComponentCallbacks2.TRIM_MEMORY_BACKGROUND`.
+pub const TRIM_MEMORY_BACKGROUND: jint = 40;
+
+/// `android.content.ComponentCallbacks2.TRIM_MEMORY_MODERATE`.
+pub const TRIM_MEMORY_MODERATE: jint = 60;
+
+/// `android.content.ComponentCallbacks2.TRIM_MEMORY_COMPLETE`.
+pub const TRIM_MEMORY_COMPLETE: jint = 80;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_MANIFEST`. Use all
+/// of the types declared in the manifest.
+pub const FOREGROUND_SERVICE_TYPE_MANIFEST: jint = -1;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_NONE`.
+pub const FOREGROUND_SERVICE_TYPE_NONE: jint = 0;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_DATA_SYNC`.
+pub const FOREGROUND_SERVICE_TYPE_DATA_SYNC: jint = 1 << 0;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_MEDIA_PLAYBACK`.
+pub const FOREGROUND_SERVICE_TYPE_MEDIA_PLAYBACK: jint = 1 << 1;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_PHONE_CALL`.
+pub const FOREGROUND_SERVICE_TYPE_PHONE_CALL: jint = 1 << 2;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_LOCATION`.
+pub const FOREGROUND_SERVICE_TYPE_LOCATION: jint = 1 << 3;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_CONNECTED_DEVICE`.
+pub const FOREGROUND_SERVICE_TYPE_CONNECTED_DEVICE: jint = 1 << 4;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_MEDIA_PROJECTION`.
+pub const FOREGROUND_SERVICE_TYPE_MEDIA_PROJECTION: jint = 1 << 5;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_CAMERA`.
+pub const FOREGROUND_SERVICE_TYPE_CAMERA: jint = 1 << 6;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_MICROPHONE`.
+pub const FOREGROUND_SERVICE_TYPE_MICROPHONE: jint = 1 << 7;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_HEALTH`.
+pub const FOREGROUND_SERVICE_TYPE_HEALTH: jint = 1 << 8;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_REMOTE_MESSAGING`.
+pub const FOREGROUND_SERVICE_TYPE_REMOTE_MESSAGING: jint = 1 << 9;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_SYSTEM_EXEMPTED`.
+pub const FOREGROUND_SERVICE_TYPE_SYSTEM_EXEMPTED: jint = 1 << 10;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_SHORT_SERVICE`.
+pub const FOREGROUND_SERVICE_TYPE_SHORT_SERVICE: jint = 1 << 11;
+
+/// `android.content.pm.ServiceInfo.FOREGROUND_SERVICE_TYPE_SPECIAL_USE`.
+pub const FOREGROUND_SERVICE_TYPE_SPECIAL_USE: jint = 1 << 30;
+
+/// `android.app.Service.STOP_FOREGROUND_REMOVE`.
+pub const STOP_FOREGROUND_REMOVE: jint = 1;
+
+/// `android.app.Service.STOP_FOREGROUND_DETACH`.
+pub const STOP_FOREGROUND_DETACH: jint = 2;
+
+/// Wrapper for [`JObject`]s that contain `android.app.Service`. Provides
+/// methods that a service calls on itself, such as moving itself to the
+/// foreground.
+pub struct ServiceContext<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> ServiceContext<'a, 'b> {
+    /// Create a [`ServiceContext`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self { internal: obj, env })
+    }
+
+    /// Make this a foreground service with `Service.startForeground()`,
+    /// showing a notification while it runs. On API level 29 and later, the
+    /// three-argument overload is used with `foreground_service_type`;
+    /// older versions ignore it.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the notification. Must not be 0.
+    /// * `notification` - `android.app.Notification` to show.
+    /// * `foreground_service_type` - Combination of the
+    ///   `FOREGROUND_SERVICE_TYPE_*` constants, which must be a subset of the
+    ///   types declared in the manifest.
+    pub fn start_foreground(
+        &self,
+        id: jint,
+        notification: JObject<'a>,
+        foreground_service_type: jint,
+    ) -> Result<()> {
+        if sdk_int(self.env)? >= 29 {
+            self.env.call_method(
+                self.internal,
+                "startForeground",
+                "(ILandroid/app/Notification;I)V",
+                &[
+                    id.into(),
+                    notification.into(),
+                    foreground_service_type.into(),
+                ],
+            )?;
+        } else {
+            self.env.call_method(
+                self.internal,
+                "startForeground",
+                "(ILandroid/app/Notification;)V",
+                &[id.into(), notification.into()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Like [`start_foreground`](Self::start_foreground), but takes the
+    /// `android.app.Notification.Builder` of the notification, and returns a
+    /// [`ForegroundNotification`] which can be used to update the
+    /// notification afterwards, for example to show progress. The builder is
+    /// set to only alert once, so updates don't make a sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the notification. Must not be 0.
+    /// * `builder` - `android.app.Notification.Builder` to build the
+    ///   notification with. It is kept by the [`ForegroundNotification`].
+    /// * `foreground_service_type` - Combination of the
+    ///   `FOREGROUND_SERVICE_TYPE_*` constants, which must be a subset of the
+    ///   types declared in the manifest.
+    pub fn start_foreground_notification(
+        &self,
+        id: jint,
+        builder: JObject<'a>,
+        foreground_service_type: jint,
+    ) -> Result<ForegroundNotification> {
+        self.env.call_method(
+            builder,
+            "setOnlyAlertOnce",
+            "(Z)Landroid/app/Notification$Builder;",
+            &[true.into()],
+        )?;
+        let notification = self
+            .env
+            .call_method(builder, "build", "()Landroid/app/Notification;", &[])?
+            .l()?;
+        self.start_foreground(id, notification, foreground_service_type)?;
+        Ok(ForegroundNotification {
+            service: self.handle()?,
+            builder: self.env.new_global_ref(builder)?,
+            id,
+        })
+    }
+
+    /// Take this service out of the foreground with
+    /// `Service.stopForeground()`. Before API level 24, only
+    /// [`STOP_FOREGROUND_REMOVE`] is honored.
+    ///
+    /// # Arguments
+    ///
+    /// * `flags` - Combination of the `STOP_FOREGROUND_*` constants.
+    pub fn stop_foreground(&self, flags: jint) -> Result<()> {
+        if sdk_int(self.env)? >= 24 {
+            self.env
+                .call_method(self.internal, "stopForeground", "(I)V", &[flags.into()])?;
+        } else {
+            self.env.call_method(
+                self.internal,
+                "stopForeground",
+                "(Z)V",
+                &[(flags & STOP_FOREGROUND_REMOVE != 0).into()],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Stop this service with `Service.stopSelf()`.
+    pub fn stop_self(&self) -> Result<()> {
+        self.env
+            .call_method(self.internal, "stopSelf", "()V", &[])?;
+        Ok(())
+    }
+
+    /// Stop this service with `Service.stopSelfResult()`, unless it has been
+    /// started again since the start command with `start_id`. Returns
+    /// whether the service will be stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_id` - Id of the most recent start command that was handled,
+    ///   as passed to [`RustService::on_start_command`].
+    pub fn stop_self_result(&self, start_id: jint) -> Result<bool> {
+        self.env
+            .call_method(self.internal, "stopSelfResult", "(I)Z", &[start_id.into()])?
+            .z()
+    }
+
+    /// Get a [`ServiceHandle`] for this service, which can be kept and used
+    /// from any thread, for example when async work started by the service
+    /// completes.
+    pub fn handle(&self) -> Result<ServiceHandle> {
+        Ok(ServiceHandle {
+            service: self.env.new_global_ref(self.internal)?,
+            vm: Arc::new(self.env.get_java_vm()?),
+        })
+    }
+
+    /// Get the identifier of this instance of the service, or [`None`] if it
+    /// was not created by [`register_service`] or has been destroyed.
+    pub fn instance_id(&self) -> Result<Option<ServiceInstanceId>> {
+        for (id, entry) in INSTANCES.lock().unwrap().iter() {
+            if self
+                .env
+                .is_same_object(self.internal, entry.handle.as_obj())?
+            {
+                return Ok(Some(*id));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a: 'b, 'b> From<ServiceContext<'a, 'b>> for JObject<'a> {
+    fn from(service: ServiceContext<'a, 'b>) -> Self {
+        service.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for ServiceContext<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
+
+/// Owned, `'static` handle to an `android.app.Service`, which can be stored
+/// in a [`RustService`] implementation or moved into futures it spawns. The
+/// current thread is attached to the JVM as a daemon thread whenever it needs
+/// to call into Java. Obtained from [`ServiceContext::handle`].
+#[derive(Clone)]
+pub struct ServiceHandle {
+    service: GlobalRef,
+    vm: Arc<JavaVM>,
+}
+
+impl ServiceHandle {
+    /// Get the underlying `android.app.Service`.
+    pub fn as_obj(&self) -> JObject<'_> {
+        self.service.as_obj()
+    }
+
+    /// Call a function with a [`ServiceContext`] for the current thread,
+    /// attaching the thread if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function to call.
+    pub fn with_context<R>(
+        &self,
+        f: impl for<'a, 'b> FnOnce(&'b JNIEnv<'a>, ServiceContext<'a, 'b>) -> R,
+    ) -> Result<R> {
+        let env = self.vm.attach_current_thread_as_daemon()?;
+        let service = ServiceContext::from_env(&env, self.service.as_obj())?;
+        Ok(f(&env, service))
+    }
+
+    /// Stop the service with `Service.stopSelf()`.
+    pub fn stop_self(&self) -> Result<()> {
+        self.with_context(|_env, service| service.stop_self())?
+    }
+
+    /// Stop the service with `Service.stopSelfResult()`. See
+    /// [`ServiceContext::stop_self_result`].
+    ///
+    /// # Arguments
+    ///
+    /// * `start_id` - Id of the most recent start command that was handled.
+    pub fn stop_self_result(&self, start_id: jint) -> Result<bool> {
+        self.with_context(|_env, service| service.stop_self_result(start_id))?
+    }
+}
+
+/// Ongoing notification of a foreground service, which can be updated while
+/// the service runs in the foreground. Each update rebuilds the notification
+/// from its `android.app.Notification.Builder` and posts it with
+/// `NotificationManager.notify()`. Like [`ServiceHandle`], it can be used from
+/// any thread. Obtained from
+/// [`ServiceContext::start_foreground_notification`].
+pub struct ForegroundNotification {
+    service: ServiceHandle,
+    builder: GlobalRef,
+    id: jint,
+}
+
+impl ForegroundNotification {
+    /// Get the id of the notification.
+    pub fn id(&self) -> jint {
+        self.id
+    }
+
+    /// Modify the `android.app.Notification.Builder` of the notification
+    /// and post the rebuilt notification.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Function which modifies the builder passed to it.
+    pub fn update(
+        &self,
+        f: impl for<'a, 'b> FnOnce(&'b JNIEnv<'a>, JObject<'a>) -> Result<()>,
+    ) -> Result<()> {
+        self.service.with_context(|env, service| {
+            let builder = env.new_local_ref::<JObject>(self.builder.as_obj())?;
+            f(env, builder)?;
+            let notification = env
+                .call_method(builder, "build", "()Landroid/app/Notification;", &[])?
+                .l()?;
+            let manager = system_service(env, *service, "notification")?;
+            env.call_method(
+                manager,
+                "notify",
+                "(ILandroid/app/Notification;)V",
+                &[self.id.into(), notification.into()],
+            )?;
+            Ok(())
+        })?
+    }
+
+    /// Show a progress bar in the notification with
+    /// `Notification.Builder.setProgress()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - Maximum progress.
+    /// * `progress` - Current progress, from 0 to `max`.
+    /// * `indeterminate` - Whether to show an indeterminate progress bar,
+    ///   in which case `max` and `progress` are ignored.
+    pub fn set_progress(&self, max: jint, progress: jint, indeterminate: bool) -> Result<()> {
+        self.update(|env, builder| {
+            env.call_method(
+                builder,
+                "setProgress",
+                "(IIZ)Landroid/app/Notification$Builder;",
+                &[max.into(), progress.into(), indeterminate.into()],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Remove the progress bar from the notification.
+    pub fn clear_progress(&self) -> Result<()> {
+        self.set_progress(0, 0, false)
+    }
+
+    /// Set the title of the notification with
+    /// `Notification.Builder.setContentTitle()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` - New title.
+    pub fn set_content_title(&self, title: &str) -> Result<()> {
+        self.set_char_sequence("setContentTitle", title)
+    }
+
+    /// Set the text of the notification with
+    /// `Notification.Builder.setContentText()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - New text.
+    pub fn set_content_text(&self, text: &str) -> Result<()> {
+        self.set_char_sequence("setContentText", text)
+    }
+
+    fn set_char_sequence(&self, method: &str, value: &str) -> Result<()> {
+        self.update(|env, builder| {
+            let value = env.new_string(value)?;
+            env.call_method(
+                builder,
+                method,
+                "(Ljava/lang/CharSequence;)Landroid/app/Notification$Builder;",
+                &[value.into()],
+            )?;
+            Ok(())
+        })
+    }
+}
+
+/// Job to schedule with [`enqueue_work`] when a foreground service can't be
+/// started.
+pub struct ExpeditedJob<'a> {
+    /// Id of the job, as passed to `android.app.job.JobInfo.Builder`.
+    pub job_id: jint,
+    /// `android.content.ComponentName` of the `android.app.job.JobService`
+    /// which runs the job.
+    pub service: JObject<'a>,
+}
+
+/// How [`enqueue_work`] started the work.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnqueuedWork {
+    /// The service was started with `Context.startForegroundService()`, or
+    /// `Context.startService()` before API level 26. On API level 26 and
+    /// later, the service must call [`ServiceContext::start_foreground`]
+    /// shortly after it starts.
+    ForegroundService,
+    /// The app was not allowed to start a foreground service, so the job
+    /// was scheduled as an expedited job instead.
+    ExpeditedJob,
+}
+
+/// Error returned by [`enqueue_work`].
+#[derive(Debug)]
+pub enum EnqueueWorkError {
+    /// A JNI call failed.
+    Jni(jni::errors::Error),
+    /// `JobScheduler.schedule()` failed to schedule the expedited job.
+    ScheduleFailed,
+}
+
+impl Display for EnqueueWorkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jni(err) => write!(f, "JNI error: {}", err),
+            Self::ScheduleFailed => f.write_str("failed to schedule expedited job"),
+        }
+    }
+}
+
+impl std::error::Error for EnqueueWorkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Jni(err) => Some(err),
+            Self::ScheduleFailed => None,
+        }
+    }
+}
+
+impl From<jni::errors::Error> for EnqueueWorkError {
+    fn from(err: jni::errors::Error) -> Self {
+        Self::Jni(err)
+    }
+}
 
-/// `android.app.Service.START_FLAG_RETRY`.
-pub const START_FLAG_RETRY: jint = 2;
+fn schedule_expedited_job<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    intent: JObject<'a>,
+    job: &ExpeditedJob<'a>,
+) -> std::result::Result<(), EnqueueWorkError> {
+    let builder = env.new_object(
+        "android/app/job/JobInfo$Builder",
+        "(ILandroid/content/ComponentName;)V",
+        &[job.job_id.into(), job.service.into()],
+    )?;
+    env.call_method(
+        builder,
+        "setExpedited",
+        "(Z)Landroid/app/job/JobInfo$Builder;",
+        &[true.into()],
+    )?;
+    let extras = env
+        .call_method(intent, "getExtras", "()Landroid/os/Bundle;", &[])?
+        .l()?;
+    if !extras.is_null() {
+        let extras = JPersistableBundle::from_bundle(env, extras)?;
+        env.call_method(
+            builder,
+            "setExtras",
+            "(Landroid/os/PersistableBundle;)Landroid/app/job/JobInfo$Builder;",
+            &[(*extras).into()],
+        )?;
+    }
+    let job_info = env
+        .call_method(builder, "build", "()Landroid/app/job/JobInfo;", &[])?
+        .l()?;
 
-/// `android.app.Service.START_STICKY_COMPATIBILITY`.
-pub const START_STICKY_COMPATIBIILITY: jint = 0;
+    let scheduler = system_service(env, context, "jobscheduler")?;
+    let result = env
+        .call_method(
+            scheduler,
+            "schedule",
+            "(Landroid/app/job/JobInfo;)I",
+            &[job_info.into()],
+        )?
+        .i()?;
+    // JobScheduler.RESULT_SUCCESS
+    if result == 1 {
+        Ok(())
+    } else {
+        Err(EnqueueWorkError::ScheduleFailed)
+    }
+}
 
-/// `android.app.Service.START_STICKY`.
-pub const START_STICKY: jint = 1;
+/// Start background work which must run soon, even with the background
+/// start restrictions of Android 12 (API level 31) and later.
+///
+/// The service identified by `intent` is started as a foreground service.
+/// If the app is not allowed to start a foreground service, because it is
+/// in the background on API level 31 or later, the job is scheduled as an
+/// expedited `android.app.job.JobInfo` instead, with the extras of `intent`
+/// as its extras. Extras which an `android.os.PersistableBundle` can't hold
+/// are dropped, so the job should only rely on primitive and string extras.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to start the work from.
+/// * `intent` - `android.content.Intent` identifying the service.
+/// * `job` - Job to schedule if the service can't be started.
+pub fn enqueue_work<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    intent: JObject<'a>,
+    job: &ExpeditedJob<'a>,
+) -> std::result::Result<EnqueuedWork, EnqueueWorkError> {
+    let sdk_int = sdk_int(env)?;
+    if sdk_int < 26 {
+        JContext::from_env(env, context)?.start_service(intent)?;
+        return Ok(EnqueuedWork::ForegroundService);
+    }
 
-/// `android.app.Service.START_NOT_STICKY`.
-pub const START_NOT_STICKY: jint = 2;
+    let result = env.call_method(
+        context,
+        "startForegroundService",
+        "(Landroid/content/Intent;)Landroid/content/ComponentName;",
+        &[intent.into()],
+    );
+    match result {
+        Ok(_) => Ok(EnqueuedWork::ForegroundService),
+        Err(jni::errors::Error::JavaException) if sdk_int >= 31 => {
+            let exception = env.exception_occurred()?;
+            env.exception_clear()?;
+            if !env.is_instance_of(
+                exception,
+                "android/app/ForegroundServiceStartNotAllowedException",
+            )? {
+                env.throw(exception)?;
+                return Err(jni::errors::Error::JavaException.into());
+            }
+            schedule_expedited_job(env, context, intent, job)?;
+            Ok(EnqueuedWork::ExpeditedJob)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
 
-/// `android.app.Service.START_REDELIVER_INTENT`.
-pub const START_REDELIVER_INTENT: jint = 3;
+/// Deserialize the extras of an `android.content.Intent`, such as the one
+/// passed to [`RustService::on_start_command`], into a Rust value with
+/// [`from_bundle`](crate::os::bundle_serde::from_bundle). An intent without
+/// extras is read as an empty `Bundle`.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `intent` - Intent to read the extras of.
+#[cfg(feature = "serde")]
+pub fn intent_extras<'a: 'b, 'b, T: serde::de::DeserializeOwned>(
+    env: &'b JNIEnv<'a>,
+    intent: JObject<'a>,
+) -> crate::os::bundle_serde::Result<T> {
+    let extras = if intent.is_null() {
+        JObject::null()
+    } else {
+        env.call_method(intent, "getExtras", "()Landroid/os/Bundle;", &[])?
+            .l()?
+    };
+    crate::os::bundle_serde::from_bundle(env, extras)
+}
 
 /// Trait for Rust implementations of `android.app.Service`. Register your
 /// Rust service using [`register_service`].
+///
+/// Each callback receives a [`ServiceContext`] for the `Service` object,
+/// which can be used as its `android.content.Context`.
 #[allow(unused_variables)]
 pub trait RustService: Send + Sync {
     /// Called by `Service.onStartCommand()`.
     fn on_start_command<'a: 'b, 'b>(
         &self,
         env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
         intent: JObject<'a>,
-        flags: jint,
+        flags: StartFlags,
         start_id: jint,
-    ) -> jint {
-        START_STICKY
+    ) -> ServiceStartResult {
+        ServiceStartResult::Sticky
     }
 
     /// Called by `Service.onBind()`.
-    fn on_bind<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, intent: JObject<'a>) -> JObject<'a>;
+    fn on_bind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) -> JObject<'a>;
 
     /// Called by `Service.onUnbind()`.
-    fn on_unbind<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, intent: JObject<'a>) -> bool {
+    fn on_unbind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) -> bool {
         false
     }
 
     /// Called by `Service.onRebind()`.
-    fn on_rebind<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, intent: JObject<'a>) {}
+    fn on_rebind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) {
+    }
+
+    /// Called by `Service.onDestroy()`, before the service object is
+    /// dropped. Release any Java resources held by the service here, since
+    /// [`Drop`] has no [`JNIEnv`] to do it with.
+    fn on_destroy<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, service: &ServiceContext<'a, 'b>) {}
+
+    /// Called by `Service.onTaskRemoved()`, when the user removes a task
+    /// that belongs to the app.
+    fn on_task_removed<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        root_intent: JObject<'a>,
+    ) {
+    }
+
+    /// Called by `Service.onTrimMemory()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - One of the `TRIM_MEMORY_*` constants.
+    fn on_trim_memory<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        level: jint,
+    ) {
+    }
+
+    /// Called by `Service.onLowMemory()`.
+    fn on_low_memory<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, service: &ServiceContext<'a, 'b>) {}
+
+    /// Called by `Service.onConfigurationChanged()` with the new
+    /// `android.content.res.Configuration`.
+    fn on_configuration_changed<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        new_config: JObject<'a>,
+    ) {
+    }
+}
+
+fn service_context<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    handle: &ServiceHandle,
+) -> ServiceContext<'a, 'b> {
+    let obj = env
+        .new_local_ref::<JObject>(handle.as_obj().into_inner().into())
+        .unwrap();
+    ServiceContext { internal: obj, env }
+}
+
+/// Identifier of a running instance of a service registered with
+/// [`register_service`]. Each `Service` object gets a new identifier when
+/// `Service.onCreate()` is called, which is never reused within the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServiceInstanceId(u64);
+
+impl Display for ServiceInstanceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Running instance of a service, obtained from [`service_instance`] or
+/// [`service_instances`]. Holds the Rust service object and a handle to the
+/// Java `Service` object, so it can outlive the instance itself, but the
+/// service should not be used after it has been destroyed.
+pub struct ServiceInstance<T> {
+    id: ServiceInstanceId,
+    handle: ServiceHandle,
+    service: Arc<T>,
+}
+
+impl<T> ServiceInstance<T> {
+    /// Get the identifier of the instance.
+    pub fn id(&self) -> ServiceInstanceId {
+        self.id
+    }
+
+    /// Get a handle to the `android.app.Service` object of the instance.
+    pub fn handle(&self) -> &ServiceHandle {
+        &self.handle
+    }
+
+    /// Get the Rust service object of the instance, as created by the
+    /// factory passed to [`register_service`].
+    pub fn service(&self) -> &Arc<T> {
+        &self.service
+    }
+}
+
+impl<T> Clone for ServiceInstance<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            handle: self.handle.clone(),
+            service: self.service.clone(),
+        }
+    }
+}
+
+struct InstanceEntry {
+    handle: ServiceHandle,
+    service: Arc<dyn Any + Send + Sync>,
+}
+
+impl InstanceEntry {
+    fn downcast<T: Send + Sync + 'static>(
+        &self,
+        id: ServiceInstanceId,
+    ) -> Option<ServiceInstance<T>> {
+        let service = self.service.clone().downcast().ok()?;
+        Some(ServiceInstance {
+            id,
+            handle: self.handle.clone(),
+            service,
+        })
+    }
+}
+
+static NEXT_INSTANCE_ID: AtomicU64 = AtomicU64::new(1);
+static INSTANCES: Mutex<BTreeMap<ServiceInstanceId, InstanceEntry>> = Mutex::new(BTreeMap::new());
+
+/// Look up a running instance of a service. Returns [`None`] if the instance
+/// has been destroyed, or if its service object is not a `T`.
+///
+/// For services registered with [`register_async_service`] or
+/// [`register_intent_service`], `T` is the type of the object created by the
+/// factory.
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the instance.
+pub fn service_instance<T: Send + Sync + 'static>(
+    id: ServiceInstanceId,
+) -> Option<ServiceInstance<T>> {
+    INSTANCES.lock().unwrap().get(&id)?.downcast(id)
+}
+
+/// Get all running instances of services whose service object is a `T`, in
+/// the order they were created. See [`service_instance`].
+pub fn service_instances<T: Send + Sync + 'static>() -> Vec<ServiceInstance<T>> {
+    INSTANCES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|(id, entry)| entry.downcast(*id))
+        .collect()
+}
+
+fn on_create_hooks<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<JObject<'a>> {
+    env.get_static_field(
+        "io/github/gedgygedgy/rust/android/app/RustService",
+        "onCreateHooks",
+        "Ljava/util/HashMap;",
+    )?
+    .l()
 }
 
 /// Register a service as an
 /// `io.github.gedgygedgy.rust.android.app.RustService`. The `factory` closure
 /// is called when `Service.onCreate()` is called, and the object created by it
-/// is dropped when `Service.onDestroy()` is called.
+/// is dropped after [`RustService::on_destroy`] when `Service.onDestroy()` is
+/// called.
+///
+/// The service stays registered until the returned [`ServiceRegistration`]
+/// is dropped. Its running instances can be enumerated with
+/// [`service_instances`].
+///
+/// Registrations only apply to the process they were made in. Services
+/// which run in another process, such as those declared with
+/// `android:isolatedProcess`, must be registered in that process, which
+/// usually has no other entry point than `JNI_OnLoad()`. Use
+/// [`register_service_by_name`] there, which defers the registration until
+/// the service is created.
 pub fn register_service<'a: 'b, 'b, T: RustService + 'static>(
     env: &'b JNIEnv<'a>,
     class: impl Desc<'a, JClass<'a>>,
     factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
-) -> Result<()> {
+) -> Result<ServiceRegistration> {
+    register_service_with(env, class, factory, |service| service.clone())
+}
+
+// Like register_service(), but with a function which returns the object to
+// expose through service_instances(), so that adapters can expose the
+// service they wrap rather than themselves.
+fn register_service_with<'a: 'b, 'b, T: RustService + 'static>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
+    expose: fn(&Arc<T>) -> Arc<dyn Any + Send + Sync>,
+) -> Result<ServiceRegistration> {
     let class = env.auto_local(class.lookup(env)?);
 
     let on_create_hook =
         env.auto_local(jni_utils::ops::fn_function(env, move |env, _obj, arg| {
             let service = Arc::new(factory(env, arg));
+            let handle = ServiceContext::from_env(env, arg)
+                .and_then(|service| service.handle())
+                .unwrap();
+            let id = ServiceInstanceId(NEXT_INSTANCE_ID.fetch_add(1, Ordering::Relaxed));
+            INSTANCES.lock().unwrap().insert(
+                id,
+                InstanceEntry {
+                    handle: handle.clone(),
+                    service: expose(&service),
+                },
+            );
 
             let service_clone = service.clone();
+            let handle_clone = handle.clone();
             let on_start_command_hook = env.auto_local(
                 jni_utils::ops::fn_function(env, move |env, _obj, arg| {
                     let intent = env.auto_local(
@@ -181,12 +1463,18 @@ pub fn register_service<'a: 'b, 'b, T: RustService + 'static>(
                             .unwrap(),
                     );
                     let flags = env.get_field(arg, "flags", "I").unwrap().i().unwrap();
+                    let flags = StartFlags::from(flags);
                     let start_id = env.get_field(arg, "startId", "I").unwrap().i().unwrap();
 
-                    let result =
-                        service_clone.on_start_command(env, (&intent).into(), flags, start_id);
+                    let result = service_clone.on_start_command(
+                        env,
+                        &service_context(env, &handle_clone),
+                        (&intent).into(),
+                        flags,
+                        start_id,
+                    );
                     let result_obj = env
-                        .new_object("java/lang/Integer", "(I)V", &[result.into()])
+                        .new_object("java/lang/Integer", "(I)V", &[jint::from(result).into()])
                         .unwrap();
                     result_obj
                 })
@@ -201,9 +1489,10 @@ pub fn register_service<'a: 'b, 'b, T: RustService + 'static>(
             .unwrap();
 
             let service_clone = service.clone();
+            let handle_clone = handle.clone();
             let on_bind_hook = env.auto_local(
                 jni_utils::ops::fn_function(env, move |env, _obj, arg| {
-                    service_clone.on_bind(env, arg)
+                    service_clone.on_bind(env, &service_context(env, &handle_clone), arg)
                 })
                 .unwrap(),
             );
@@ -216,9 +1505,11 @@ pub fn register_service<'a: 'b, 'b, T: RustService + 'static>(
             .unwrap();
 
             let service_clone = service.clone();
+            let handle_clone = handle.clone();
             let on_unbind_hook = env.auto_local(
                 jni_utils::ops::fn_function(env, move |env, _obj, arg| {
-                    let result = service_clone.on_unbind(env, arg);
+                    let result =
+                        service_clone.on_unbind(env, &service_context(env, &handle_clone), arg);
                     let result_obj = env
                         .new_object("java/lang/Boolean", "(Z)V", &[result.into()])
                         .unwrap();
@@ -234,9 +1525,11 @@ pub fn register_service<'a: 'b, 'b, T: RustService + 'static>(
             )
             .unwrap();
 
+            let service_clone = service.clone();
+            let handle_clone = handle.clone();
             let on_rebind_hook = env.auto_local(
                 jni_utils::ops::fn_function(env, move |env, _obj, arg| {
-                    service.on_rebind(env, arg);
+                    service_clone.on_rebind(env, &service_context(env, &handle_clone), arg);
                     JObject::null()
                 })
                 .unwrap(),
@@ -249,17 +1542,103 @@ pub fn register_service<'a: 'b, 'b, T: RustService + 'static>(
             )
             .unwrap();
 
+            let service_clone = service.clone();
+            let handle_clone = handle.clone();
+            let on_task_removed_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    service_clone.on_task_removed(env, &service_context(env, &handle_clone), arg);
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onTaskRemovedHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_task_removed_hook).into(),
+            )
+            .unwrap();
+
+            let service_clone = service.clone();
+            let handle_clone = handle.clone();
+            let on_trim_memory_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    let level = env
+                        .call_method(arg, "intValue", "()I", &[])
+                        .unwrap()
+                        .i()
+                        .unwrap();
+                    service_clone.on_trim_memory(env, &service_context(env, &handle_clone), level);
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onTrimMemoryHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_trim_memory_hook).into(),
+            )
+            .unwrap();
+
+            let service_clone = service.clone();
+            let handle_clone = handle.clone();
+            let on_low_memory_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, _arg| {
+                    service_clone.on_low_memory(env, &service_context(env, &handle_clone));
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onLowMemoryHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_low_memory_hook).into(),
+            )
+            .unwrap();
+
+            let service_clone = service.clone();
+            let handle_clone = handle.clone();
+            let on_configuration_changed_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    service_clone.on_configuration_changed(
+                        env,
+                        &service_context(env, &handle_clone),
+                        arg,
+                    );
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onConfigurationChangedHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_configuration_changed_hook).into(),
+            )
+            .unwrap();
+
+            let on_destroy_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, _arg| {
+                    INSTANCES.lock().unwrap().remove(&id);
+                    service.on_destroy(env, &service_context(env, &handle));
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onDestroyHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_destroy_hook).into(),
+            )
+            .unwrap();
+
             JObject::null()
         })?);
 
-    let on_create_hooks = env.auto_local(
-        env.get_static_field(
-            "io/github/gedgygedgy/rust/android/app/RustService",
-            "onCreateHooks",
-            "Ljava/util/HashMap;",
-        )?
-        .l()?,
-    );
+    let on_create_hooks = env.auto_local(on_create_hooks(env)?);
     env.call_method(
         &on_create_hooks,
         "put",
@@ -267,25 +1646,459 @@ pub fn register_service<'a: 'b, 'b, T: RustService + 'static>(
         &[(&class).into(), (&on_create_hook).into()],
     )?;
 
-    Ok(())
+    Ok(ServiceRegistration {
+        class: env.new_global_ref(&class)?,
+        hook: env.new_global_ref(&on_create_hook)?,
+        vm: env.get_java_vm()?,
+    })
+}
+
+/// Registration of a service, returned by [`register_service`]. The service
+/// is unregistered when this is dropped, unless another service has been
+/// registered for the same class since. Instances of the service which are
+/// already running are not affected.
+#[must_use = "the service is unregistered when the registration is dropped"]
+pub struct ServiceRegistration {
+    class: GlobalRef,
+    hook: GlobalRef,
+    vm: JavaVM,
+}
+
+impl ServiceRegistration {
+    /// Keep the service registered for the rest of the process' lifetime.
+    pub fn forget(self) {
+        std::mem::forget(self);
+    }
+
+    fn unregister(&self, env: &JNIEnv) -> Result<()> {
+        let on_create_hooks = env.auto_local(on_create_hooks(env)?);
+        let current_hook = env
+            .call_method(
+                &on_create_hooks,
+                "get",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[self.class.as_obj().into()],
+            )?
+            .l()?;
+        if env.is_same_object(current_hook, self.hook.as_obj())? {
+            env.call_method(
+                &on_create_hooks,
+                "remove",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[self.class.as_obj().into()],
+            )?;
+        }
+        env.call_method(self.hook.as_obj(), "close", "()V", &[])?;
+        Ok(())
+    }
+}
+
+impl Drop for ServiceRegistration {
+    fn drop(&mut self) {
+        if let Ok(env) = self.vm.attach_current_thread() {
+            if self.unregister(&env).is_err() {
+                let _ = env.exception_clear();
+            }
+        }
+    }
+}
+
+/// Extension of [`RustService`] for services whose start commands are
+/// handled by futures. Register it with [`register_async_service`].
+///
+/// Each start command's future is spawned on the main `Handler`. When it
+/// completes, `Service.stopSelfResult()` is called with its start id, so the
+/// service stops once the most recent start command is done. Futures which
+/// are still running when the service is destroyed are aborted.
+pub trait AsyncRustService: RustService {
+    /// Called by `Service.onStartCommand()` to create the future handling
+    /// the start command.
+    fn on_start_command_async<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+        flags: StartFlags,
+        start_id: jint,
+    ) -> BoxFuture<'static, ()>;
+
+    /// Value to return from `Service.onStartCommand()`.
+    fn start_mode(&self) -> ServiceStartResult {
+        ServiceStartResult::NotSticky
+    }
+}
+
+struct AsyncServiceAdapter<T> {
+    inner: Arc<T>,
+    handle: ServiceHandle,
+    // Tasks which are still running, by start id.
+    tasks: Arc<Mutex<HashMap<jint, AbortHandle>>>,
+}
+
+impl<T: AsyncRustService> RustService for AsyncServiceAdapter<T> {
+    fn on_start_command<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+        flags: StartFlags,
+        start_id: jint,
+    ) -> ServiceStartResult {
+        let future = self
+            .inner
+            .on_start_command_async(env, service, intent, flags, start_id);
+        let handle = self.handle.clone();
+        let tasks = self.tasks.clone();
+        let task = async move {
+            future.await;
+            if let Err(err) = handle.stop_self_result(start_id) {
+                log::error!("Failed to stop service: {}", err);
+            }
+            tasks.lock().unwrap().remove(&start_id);
+        };
+        let spawned = JLooper::main(env)
+            .and_then(|looper| looper.handler())
+            .map_err(|_| SpawnError::shutdown())
+            .and_then(|handler| handler.spawner().spawn_abortable(task));
+        match spawned {
+            // This runs on the main thread, so the task can't have finished
+            // and removed its entry yet.
+            Ok(abort) => {
+                self.tasks.lock().unwrap().insert(start_id, abort);
+            }
+            Err(err) => log::error!("Failed to spawn start command: {}", err),
+        }
+        self.inner.start_mode()
+    }
+
+    fn on_bind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) -> JObject<'a> {
+        self.inner.on_bind(env, service, intent)
+    }
+
+    fn on_unbind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) -> bool {
+        self.inner.on_unbind(env, service, intent)
+    }
+
+    fn on_rebind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) {
+        self.inner.on_rebind(env, service, intent)
+    }
+
+    fn on_destroy<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, service: &ServiceContext<'a, 'b>) {
+        for (_, task) in self.tasks.lock().unwrap().drain() {
+            let _ = task.abort();
+        }
+        self.inner.on_destroy(env, service)
+    }
+
+    fn on_task_removed<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        root_intent: JObject<'a>,
+    ) {
+        self.inner.on_task_removed(env, service, root_intent)
+    }
+
+    fn on_trim_memory<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        level: jint,
+    ) {
+        self.inner.on_trim_memory(env, service, level)
+    }
+
+    fn on_low_memory<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, service: &ServiceContext<'a, 'b>) {
+        self.inner.on_low_memory(env, service)
+    }
+
+    fn on_configuration_changed<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        new_config: JObject<'a>,
+    ) {
+        self.inner
+            .on_configuration_changed(env, service, new_config)
+    }
+}
+
+/// Like [`register_service`], but for an [`AsyncRustService`].
+pub fn register_async_service<'a: 'b, 'b, T: AsyncRustService + 'static>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
+) -> Result<ServiceRegistration> {
+    register_service_with(
+        env,
+        class,
+        move |env, obj| AsyncServiceAdapter {
+            inner: Arc::new(factory(env, obj)),
+            handle: ServiceContext::from_env(env, obj)
+                .and_then(|service| service.handle())
+                .unwrap(),
+            tasks: Arc::new(Mutex::new(HashMap::new())),
+        },
+        |adapter| adapter.inner.clone(),
+    )
+}
+
+/// Trait for services which handle their start commands one at a time on a
+/// background thread, like `android.app.IntentService`. Register it with
+/// [`register_intent_service`].
+///
+/// Each start command's intent is queued and passed to
+/// [`on_handle_intent`](RustIntentService::on_handle_intent) once the
+/// previous one's future has completed. The futures run on the `Looper` of
+/// a dedicated `android.os.HandlerThread`, so they may block. The service
+/// stops itself once the queue is empty.
+#[allow(unused_variables)]
+pub trait RustIntentService: Send + Sync {
+    /// Called on the background thread to create the future handling an
+    /// intent. The intent is null if the service was restarted after its
+    /// process died, and [`redeliver_intent`](Self::redeliver_intent)
+    /// returned `false`.
+    fn on_handle_intent<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) -> BoxFuture<'static, ()>;
+
+    /// Called by `Service.onBind()`. Returns null by default, since intent
+    /// services are usually only started.
+    fn on_bind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) -> JObject<'a> {
+        JObject::null()
+    }
+
+    /// Called by `Service.onDestroy()`, after the background thread has been
+    /// told to quit. Intents which were still queued are dropped.
+    fn on_destroy<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, service: &ServiceContext<'a, 'b>) {}
+
+    /// Whether intents should be redelivered if the process dies while
+    /// handling them, like `IntentService.setIntentRedelivery()`.
+    fn redeliver_intent(&self) -> bool {
+        false
+    }
+}
+
+struct IntentServiceAdapter<T> {
+    inner: Arc<T>,
+    thread: GlobalRef,
+    queue: mpsc::UnboundedSender<(Option<GlobalRef>, jint)>,
+    task: AbortHandle,
+}
+
+impl<T: RustIntentService + 'static> IntentServiceAdapter<T> {
+    fn new<'a: 'b, 'b>(env: &'b JNIEnv<'a>, service: JObject<'a>, inner: T) -> Result<Self> {
+        let handle = ServiceContext::from_env(env, service)?.handle()?;
+        let name = env.new_string("RustIntentService")?;
+        let thread = env.new_object(
+            "android/os/HandlerThread",
+            "(Ljava/lang/String;)V",
+            &[name.into()],
+        )?;
+        env.call_method(thread, "start", "()V", &[])?;
+        let looper = env
+            .call_method(thread, "getLooper", "()Landroid/os/Looper;", &[])?
+            .l()?;
+
+        let inner = Arc::new(inner);
+        let inner_clone = inner.clone();
+        let (queue, mut receiver) = mpsc::unbounded::<(Option<GlobalRef>, jint)>();
+        let task = async move {
+            while let Some((intent, start_id)) = receiver.next().await {
+                let future = handle.with_context(|env, service| {
+                    let intent = intent
+                        .as_ref()
+                        .map_or_else(JObject::null, |intent| intent.as_obj().into_inner().into());
+                    inner_clone.on_handle_intent(env, &service, intent)
+                });
+                match future {
+                    Ok(future) => future.await,
+                    Err(err) => log::error!("Failed to handle intent: {}", err),
+                }
+                if let Err(err) = handle.stop_self_result(start_id) {
+                    log::error!("Failed to stop service: {}", err);
+                }
+            }
+        };
+        let task = JLooper::from_env(env, looper)?
+            .handler()?
+            .spawner()
+            .spawn_abortable(task)
+            .expect("Failed to spawn intent service task");
+
+        Ok(Self {
+            inner,
+            thread: env.new_global_ref(thread)?,
+            queue,
+            task,
+        })
+    }
+}
+
+#[allow(unused_variables)]
+impl<T: RustIntentService + 'static> RustService for IntentServiceAdapter<T> {
+    fn on_start_command<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+        flags: StartFlags,
+        start_id: jint,
+    ) -> ServiceStartResult {
+        let intent = if intent.is_null() {
+            None
+        } else {
+            Some(env.new_global_ref(intent).unwrap())
+        };
+        let _ = self.queue.unbounded_send((intent, start_id));
+        if self.inner.redeliver_intent() {
+            ServiceStartResult::RedeliverIntent
+        } else {
+            ServiceStartResult::NotSticky
+        }
+    }
+
+    fn on_bind<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        service: &ServiceContext<'a, 'b>,
+        intent: JObject<'a>,
+    ) -> JObject<'a> {
+        self.inner.on_bind(env, service, intent)
+    }
+
+    fn on_destroy<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, service: &ServiceContext<'a, 'b>) {
+        self.queue.close_channel();
+        let _ = self.task.abort();
+        if let Err(err) = env.call_method(self.thread.as_obj(), "quit", "()Z", &[]) {
+            log::error!("Failed to quit intent service thread: {}", err);
+        }
+        self.inner.on_destroy(env, service)
+    }
+}
+
+/// Like [`register_service`], but for a [`RustIntentService`]. A new
+/// `android.os.HandlerThread` is started for each instance of the service.
+pub fn register_intent_service<'a: 'b, 'b, T: RustIntentService + 'static>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
+) -> Result<ServiceRegistration> {
+    register_service_with(
+        env,
+        class,
+        move |env, obj| IntentServiceAdapter::new(env, obj, factory(env, obj)).unwrap(),
+        |adapter| adapter.inner.clone(),
+    )
+}
+
+type NamedService =
+    Box<dyn for<'a, 'b> FnOnce(&'b JNIEnv<'a>, JClass<'a>) -> Result<ServiceRegistration> + Send>;
+
+static NAMED_SERVICES: Mutex<BTreeMap<String, NamedService>> = Mutex::new(BTreeMap::new());
+
+/// Register a service by the binary name of its class, such as
+/// `com.example.IsolatedService`. Unlike [`register_service`], this doesn't
+/// need a [`JNIEnv`] or the class, so it can be called from `JNI_OnLoad()`
+/// in a process where nothing else runs Rust code, such as the process of a
+/// service declared with `android:isolatedProcess`.
+///
+/// When an instance of the class is created and no service is registered
+/// for it, `RustService.onCreate()` calls into Rust by class name, and
+/// `register` is called to register it. The resulting registration is kept
+/// for the rest of the process' lifetime. [`init`](crate::init) must have
+/// been called in the process, and the library must be loaded before the
+/// service is created, for example from a static initializer of the class.
+///
+/// # Arguments
+///
+/// * `class_name` - Binary name of the class, as returned by
+///   `Class.getName()`.
+/// * `register` - Function which registers the service, for example by
+///   calling [`register_service`] with the class passed to it.
+pub fn register_service_by_name(
+    class_name: &str,
+    register: impl for<'a, 'b> FnOnce(&'b JNIEnv<'a>, JClass<'a>) -> Result<ServiceRegistration>
+        + Send
+        + 'static,
+) {
+    NAMED_SERVICES
+        .lock()
+        .unwrap()
+        .insert(class_name.to_string(), Box::new(register));
+}
+
+fn register_named_service<'a: 'b, 'b>(env: &'b JNIEnv<'a>, class: JClass<'a>) -> Result<bool> {
+    let name = env
+        .call_method(class, "getName", "()Ljava/lang/String;", &[])?
+        .l()?;
+    let name: String = env.get_string(name.into())?.into();
+    let register = match NAMED_SERVICES.lock().unwrap().remove(&name) {
+        Some(register) => register,
+        None => return Ok(false),
+    };
+    register(env, class)?.forget();
+    Ok(true)
+}
+
+extern "system" fn native_register_service<'a>(
+    env: JNIEnv<'a>,
+    _class: JClass<'a>,
+    service_class: JClass<'a>,
+) -> jboolean {
+    match register_named_service(&env, service_class) {
+        Ok(registered) => registered.into(),
+        Err(err) => {
+            log::error!("Failed to register service: {}", err);
+            false.into()
+        }
+    }
+}
+
+pub(crate) fn init<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<()> {
+    env.register_native_methods(
+        "io/github/gedgygedgy/rust/android/app/RustService",
+        &[NativeMethod {
+            name: "nativeRegisterService".into(),
+            sig: "(Ljava/lang/Class;)Z".into(),
+            fn_ptr: native_register_service as *mut c_void,
+        }],
+    )
 }
 
 /// Unregister a service as an
-/// `io.github.gedgygedgy.rust.android.app.RustService`.
-pub fn unregister_service<'a: 'b, 'b, T: RustService + 'static>(
+/// `io.github.gedgygedgy.rust.android.app.RustService`, regardless of which
+/// [`ServiceRegistration`] registered it.
+pub fn unregister_service<'a: 'b, 'b>(
     env: &'b JNIEnv<'a>,
     class: impl Desc<'a, JClass<'a>>,
 ) -> Result<()> {
     let class = env.auto_local(class.lookup(env)?);
 
-    let on_create_hooks = env.auto_local(
-        env.get_static_field(
-            "io/github/gedgygedgy/rust/android/app/RustService",
-            "onCreateHooks",
-            "Ljava/util/HashMap;",
-        )?
-        .l()?,
-    );
+    let on_create_hooks = env.auto_local(on_create_hooks(env)?);
     let on_create_hook = env
         .call_method(
             &on_create_hooks,
@@ -294,7 +2107,9 @@ pub fn unregister_service<'a: 'b, 'b, T: RustService + 'static>(
             &[(&class).into()],
         )?
         .l()?;
-    env.call_method(on_create_hook, "close", "()V", &[])?;
+    if !on_create_hook.is_null() {
+        env.call_method(on_create_hook, "close", "()V", &[])?;
+    }
 
     Ok(())
 }
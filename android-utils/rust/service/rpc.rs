@@ -0,0 +1,292 @@
+//! Typed request/response calls over `android.os.Messenger`, for talking to
+//! a bound service, possibly in another process, without AIDL.
+//!
+//! Each request is sent as an `android.os.Message` whose data `Bundle`
+//! holds the request serialized with [`to_parcel`], and whose `replyTo` is a
+//! `Messenger` created for that call. The server deserializes the request,
+//! passes it to a [`MessengerService`], and sends the serialized response
+//! back to `replyTo`. Both sides must use the same request and response
+//! types, and the same version of this crate, since the format of the data is
+//! not stable.
+//!
+//! On the server side, return the binder of the `Messenger` from [`serve`]
+//! in `RustService::on_bind()`. On the client side, pass the binder received
+//! from [`connect_service`](super::connect_service) to
+//! [`MessengerClient::from_binder`].
+
+use crate::os::{
+    async_messenger,
+    parcel_serde::{self, from_parcel, to_parcel},
+    AbortHandle, JLooper, JMessage, JMessenger, JParcel,
+};
+use futures::{task::SpawnError, Future, StreamExt};
+use jni::{
+    objects::{GlobalRef, JObject},
+    sys::jint,
+    JNIEnv,
+};
+use jni_utils::stream::JSendStream;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display},
+};
+
+const WHAT_REQUEST: jint = 0x5250_4351;
+const WHAT_RESPONSE: jint = 0x5250_4352;
+const PAYLOAD_KEY: &str = "io.github.gedgygedgy.rust.android.service.rpc.PAYLOAD";
+
+/// Error returned by RPC calls.
+#[derive(Debug)]
+pub enum Error {
+    /// A JNI call failed.
+    Jni(jni::errors::Error),
+    /// The request or response could not be serialized or deserialized.
+    Message(String),
+    /// The task handling requests could not be spawned.
+    Spawn(SpawnError),
+    /// The reply `Messenger` was closed before a response arrived.
+    NoReply,
+}
+
+/// Result of RPC calls.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jni(err) => write!(f, "JNI error: {}", err),
+            Self::Message(msg) => f.write_str(msg),
+            Self::Spawn(err) => write!(f, "spawn error: {}", err),
+            Self::NoReply => f.write_str("no reply was received"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Jni(err) => Some(err),
+            Self::Spawn(err) => Some(err),
+            Self::Message(_) | Self::NoReply => None,
+        }
+    }
+}
+
+impl From<jni::errors::Error> for Error {
+    fn from(err: jni::errors::Error) -> Self {
+        Self::Jni(err)
+    }
+}
+
+impl From<parcel_serde::Error> for Error {
+    fn from(err: parcel_serde::Error) -> Self {
+        match err {
+            parcel_serde::Error::Jni(err) => Self::Jni(err),
+            parcel_serde::Error::Message(msg) => Self::Message(msg),
+        }
+    }
+}
+
+impl From<SpawnError> for Error {
+    fn from(err: SpawnError) -> Self {
+        Self::Spawn(err)
+    }
+}
+
+fn encode<'a: 'b, 'b, T: Serialize + ?Sized>(
+    env: &'b JNIEnv<'a>,
+    msg: &JMessage<'a, 'b>,
+    value: &T,
+) -> Result<()> {
+    let parcel = JParcel::obtain(env)?;
+    let bytes = to_parcel(&parcel, value)
+        .map_err(Error::from)
+        .and_then(|()| Ok(parcel.marshall()?));
+    parcel.recycle()?;
+    let bytes = env.byte_array_from_slice(&bytes?)?;
+
+    let bundle = env.new_object("android/os/Bundle", "()V", &[])?;
+    let key = env.new_string(PAYLOAD_KEY)?;
+    env.call_method(
+        bundle,
+        "putByteArray",
+        "(Ljava/lang/String;[B)V",
+        &[key.into(), JObject::from(bytes).into()],
+    )?;
+    msg.set_data(bundle).map_err(Error::from)
+}
+
+fn decode<'a: 'b, 'b, T: DeserializeOwned>(
+    env: &'b JNIEnv<'a>,
+    msg: &JMessage<'a, 'b>,
+) -> Result<T> {
+    let key = env.new_string(PAYLOAD_KEY)?;
+    let bytes = env
+        .call_method(
+            msg.data()?,
+            "getByteArray",
+            "(Ljava/lang/String;)[B",
+            &[key.into()],
+        )?
+        .l()?;
+    if bytes.is_null() {
+        return Err(Error::Message("message has no payload".to_string()));
+    }
+    let bytes = env.convert_byte_array(bytes.into_inner())?;
+
+    let parcel = JParcel::from_bytes(env, &bytes)?;
+    let value = from_parcel(&parcel);
+    parcel.recycle()?;
+    Ok(value?)
+}
+
+/// Client for a service which handles requests with [`serve`].
+#[derive(Clone)]
+pub struct MessengerClient {
+    messenger: GlobalRef,
+    looper: GlobalRef,
+}
+
+impl MessengerClient {
+    /// Create a client from the `android.os.IBinder` of the service's
+    /// `Messenger`, such as the one received in
+    /// `ServiceConnection.onServiceConnected()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `binder` - Binder of the service's `Messenger`.
+    /// * `looper` - Looper to receive responses on. It must not be blocked
+    ///   while waiting for a response.
+    pub fn from_binder<'a: 'b, 'b>(
+        env: &'b JNIEnv<'a>,
+        binder: JObject<'a>,
+        looper: &JLooper<'a, 'b>,
+    ) -> Result<Self> {
+        let messenger = JMessenger::from_binder(env, binder)?;
+        Ok(Self {
+            messenger: env.new_global_ref(*messenger)?,
+            looper: env.new_global_ref(**looper)?,
+        })
+    }
+
+    /// Send a request, and get a future which resolves to the response.
+    ///
+    /// The future never resolves if the service dies before it responds, so
+    /// callers which talk to another process should add a timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `request` - Request to send.
+    pub fn call<'a: 'b, 'b, Req: Serialize + ?Sized, Resp: DeserializeOwned>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        request: &Req,
+    ) -> Result<impl Future<Output = Result<Resp>> + Send> {
+        let looper = JLooper::from_env(env, env.new_local_ref::<JObject>(self.looper.as_obj())?)?;
+        let (reply_to, stream) = async_messenger(env, &looper)?;
+        let mut stream = JSendStream::try_from(stream)?;
+
+        let msg = JMessage::obtain(env)?;
+        msg.set_what(WHAT_REQUEST)?;
+        encode(env, &msg, request)?;
+        msg.set_reply_to(*reply_to)?;
+        JMessenger::from_env(env, env.new_local_ref::<JObject>(self.messenger.as_obj())?)?
+            .send(&msg)?;
+
+        let vm = env.get_java_vm()?;
+        Ok(async move {
+            while let Some(msg) = stream.next().await {
+                let msg = msg?;
+                let env = vm.get_env()?;
+                let msg = JMessage::from_env(&env, msg.as_obj())?;
+                if msg.what()? == WHAT_RESPONSE {
+                    return decode(&env, &msg);
+                }
+            }
+            Err(Error::NoReply)
+        })
+    }
+}
+
+/// Handler for requests sent by a [`MessengerClient`]. Passed to [`serve`].
+pub trait MessengerService: Send + Sync {
+    /// Type of the requests.
+    type Request: DeserializeOwned;
+
+    /// Type of the responses.
+    type Response: Serialize;
+
+    /// Called on the `Looper` passed to [`serve`] for each request.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `request` - Request which was received.
+    fn handle(&self, env: &JNIEnv, request: Self::Request) -> Self::Response;
+}
+
+fn handle_request<'a: 'b, 'b, T: MessengerService>(
+    env: &'b JNIEnv<'a>,
+    service: &T,
+    msg: &JMessage<'a, 'b>,
+) -> Result<()> {
+    if msg.what()? != WHAT_REQUEST {
+        return Ok(());
+    }
+    let reply_to = msg.reply_to()?;
+    if reply_to.is_null() {
+        return Err(Error::Message("request has no replyTo".to_string()));
+    }
+
+    let response = service.handle(env, decode(env, msg)?);
+    let reply = JMessage::obtain(env)?;
+    reply.set_what(WHAT_RESPONSE)?;
+    encode(env, &reply, &response)?;
+    JMessenger::from_env(env, reply_to)?.send(&reply)?;
+    Ok(())
+}
+
+/// Create an `android.os.Messenger` which handles requests from
+/// [`MessengerClient`]s with a [`MessengerService`]. Returns the
+/// `Messenger`, whose binder should be returned from
+/// `RustService::on_bind()`, and an [`AbortHandle`] which stops handling
+/// requests, for example in `RustService::on_destroy()`.
+///
+/// Requests which can't be handled are logged and dropped.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `looper` - Looper to handle the requests on.
+/// * `service` - Handler for the requests.
+pub fn serve<'a: 'b, 'b, T: MessengerService + 'static>(
+    env: &'b JNIEnv<'a>,
+    looper: &JLooper<'a, 'b>,
+    service: T,
+) -> Result<(JMessenger<'a, 'b>, AbortHandle)> {
+    let (messenger, stream) = async_messenger(env, looper)?;
+    let mut stream = JSendStream::try_from(stream)?;
+
+    let vm = env.get_java_vm()?;
+    let task = async move {
+        while let Some(msg) = stream.next().await {
+            let result = msg.map_err(Error::from).and_then(|msg| {
+                let env = vm.get_env()?;
+                let result =
+                    handle_request(&env, &service, &JMessage::from_env(&env, msg.as_obj())?);
+                if result.is_err() {
+                    env.exception_clear()?;
+                }
+                result
+            });
+            if let Err(err) = result {
+                log::error!("Failed to handle RPC request: {}", err);
+            }
+        }
+    };
+    let handle = looper.handler()?.spawner().spawn_abortable(task)?;
+    Ok((messenger, handle))
+}
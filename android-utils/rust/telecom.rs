@@ -0,0 +1,689 @@
+use crate::content::JContext;
+use futures::{Stream, StreamExt};
+use jni::{
+    descriptors::Desc,
+    errors::Result,
+    objects::{JClass, JObject, JValue},
+    sys::jint,
+    JNIEnv,
+};
+use jni_utils::stream::{JSendStream, JStream};
+use std::{convert::TryFrom, sync::Arc};
+
+/// `android.telecom.PhoneAccount.CAPABILITY_CONNECTION_MANAGER`.
+pub const CAPABILITY_CONNECTION_MANAGER: jint = 0x1;
+
+/// `android.telecom.PhoneAccount.CAPABILITY_CALL_PROVIDER`.
+pub const CAPABILITY_CALL_PROVIDER: jint = 0x2;
+
+/// `android.telecom.PhoneAccount.CAPABILITY_VIDEO_CALLING`.
+pub const CAPABILITY_VIDEO_CALLING: jint = 0x8;
+
+/// `android.telecom.PhoneAccount.CAPABILITY_SELF_MANAGED`.
+pub const CAPABILITY_SELF_MANAGED: jint = 0x800;
+
+/// `android.telecom.Connection.STATE_INITIALIZING`.
+pub const STATE_INITIALIZING: jint = 0;
+
+/// `android.telecom.Connection.STATE_NEW`.
+pub const STATE_NEW: jint = 1;
+
+/// `android.telecom.Connection.STATE_RINGING`.
+pub const STATE_RINGING: jint = 2;
+
+/// `android.telecom.Connection.STATE_DIALING`.
+pub const STATE_DIALING: jint = 3;
+
+/// `android.telecom.Connection.STATE_ACTIVE`.
+pub const STATE_ACTIVE: jint = 4;
+
+/// `android.telecom.Connection.STATE_HOLDING`.
+pub const STATE_HOLDING: jint = 5;
+
+/// `android.telecom.Connection.STATE_DISCONNECTED`.
+pub const STATE_DISCONNECTED: jint = 6;
+
+/// `android.telecom.Connection.CAPABILITY_HOLD`.
+pub const CONNECTION_CAPABILITY_HOLD: jint = 0x1;
+
+/// `android.telecom.Connection.CAPABILITY_SUPPORT_HOLD`.
+pub const CONNECTION_CAPABILITY_SUPPORT_HOLD: jint = 0x2;
+
+/// `android.telecom.Connection.CAPABILITY_MUTE`.
+pub const CONNECTION_CAPABILITY_MUTE: jint = 0x40;
+
+/// `android.telecom.Connection.PROPERTY_SELF_MANAGED`.
+pub const CONNECTION_PROPERTY_SELF_MANAGED: jint = 0x80;
+
+/// `android.telecom.CallAudioState.ROUTE_EARPIECE`.
+pub const ROUTE_EARPIECE: jint = 0x1;
+
+/// `android.telecom.CallAudioState.ROUTE_BLUETOOTH`.
+pub const ROUTE_BLUETOOTH: jint = 0x2;
+
+/// `android.telecom.CallAudioState.ROUTE_WIRED_HEADSET`.
+pub const ROUTE_WIRED_HEADSET: jint = 0x4;
+
+/// `android.telecom.CallAudioState.ROUTE_SPEAKER`.
+pub const ROUTE_SPEAKER: jint = 0x8;
+
+/// `android.telecom.DisconnectCause.UNKNOWN`.
+pub const DISCONNECT_UNKNOWN: jint = 0;
+
+/// `android.telecom.DisconnectCause.ERROR`.
+pub const DISCONNECT_ERROR: jint = 1;
+
+/// `android.telecom.DisconnectCause.LOCAL`.
+pub const DISCONNECT_LOCAL: jint = 2;
+
+/// `android.telecom.DisconnectCause.REMOTE`.
+pub const DISCONNECT_REMOTE: jint = 3;
+
+/// `android.telecom.DisconnectCause.CANCELED`.
+pub const DISCONNECT_CANCELED: jint = 4;
+
+/// `android.telecom.DisconnectCause.MISSED`.
+pub const DISCONNECT_MISSED: jint = 5;
+
+/// `android.telecom.DisconnectCause.REJECTED`.
+pub const DISCONNECT_REJECTED: jint = 6;
+
+/// `android.telecom.DisconnectCause.BUSY`.
+pub const DISCONNECT_BUSY: jint = 7;
+
+/// Represents events that have been captured by a connection created with
+/// [`async_connection`].
+pub enum ConnectionEvent {
+    /// Created by `Connection.onStateChanged()`.
+    StateChanged { state: jint },
+    /// Created by `Connection.onAnswer()`.
+    Answer { video_state: jint },
+    /// Created by `Connection.onReject()`.
+    Reject,
+    /// Created by `Connection.onDisconnect()`.
+    Disconnect,
+    /// Created by `Connection.onAbort()`.
+    Abort,
+    /// Created by `Connection.onHold()`.
+    Hold,
+    /// Created by `Connection.onUnhold()`.
+    Unhold,
+    /// Created by `Connection.onShowIncomingCallUi()`.
+    ShowIncomingCallUi,
+    /// Created by `Connection.onPlayDtmfTone()`.
+    PlayDtmfTone { digit: char },
+    /// Created by `Connection.onStopDtmfTone()`.
+    StopDtmfTone,
+    /// Created by `Connection.onCallAudioStateChanged()`.
+    CallAudioStateChanged {
+        route: jint,
+        supported_route_mask: jint,
+        muted: bool,
+    },
+}
+
+/// Wrapper for [`JObject`]s that contain `android.telecom.Connection`.
+/// Provides methods to control the state and audio route of the connection.
+pub struct JConnection<'a: 'b, 'b> {
+    internal: JObject<'a>,
+    env: &'b JNIEnv<'a>,
+}
+
+impl<'a: 'b, 'b> JConnection<'a, 'b> {
+    /// Create a [`JConnection`] from the environment and an object.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `obj` - Object to wrap.
+    pub fn from_env(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Self {
+        Self { internal: obj, env }
+    }
+
+    fn call_void(&self, name: &str, sig: &str, args: &[JValue]) -> Result<()> {
+        self.env.call_method(self.internal, name, sig, args)?;
+        Ok(())
+    }
+
+    /// Call `Connection.setInitialized()`.
+    pub fn set_initialized(&self) -> Result<()> {
+        self.call_void("setInitialized", "()V", &[])
+    }
+
+    /// Call `Connection.setRinging()`.
+    pub fn set_ringing(&self) -> Result<()> {
+        self.call_void("setRinging", "()V", &[])
+    }
+
+    /// Call `Connection.setDialing()`.
+    pub fn set_dialing(&self) -> Result<()> {
+        self.call_void("setDialing", "()V", &[])
+    }
+
+    /// Call `Connection.setActive()`.
+    pub fn set_active(&self) -> Result<()> {
+        self.call_void("setActive", "()V", &[])
+    }
+
+    /// Call `Connection.setOnHold()`.
+    pub fn set_on_hold(&self) -> Result<()> {
+        self.call_void("setOnHold", "()V", &[])
+    }
+
+    /// Call `Connection.setDisconnected()` with a new
+    /// `android.telecom.DisconnectCause`.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Disconnect code. One of the `DISCONNECT_*` constants.
+    pub fn set_disconnected(&self, code: jint) -> Result<()> {
+        let cause = self.env.auto_local(self.env.new_object(
+            "android/telecom/DisconnectCause",
+            "(I)V",
+            &[code.into()],
+        )?);
+        self.call_void(
+            "setDisconnected",
+            "(Landroid/telecom/DisconnectCause;)V",
+            &[(&cause).into()],
+        )
+    }
+
+    /// Call `Connection.destroy()`. This should be called after the
+    /// connection has been disconnected.
+    pub fn destroy(&self) -> Result<()> {
+        self.call_void("destroy", "()V", &[])
+    }
+
+    /// Call `Connection.setAudioRoute()`. Requires API 26.
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - New audio route. One of the `ROUTE_*` constants.
+    pub fn set_audio_route(&self, route: jint) -> Result<()> {
+        self.call_void("setAudioRoute", "(I)V", &[route.into()])
+    }
+
+    /// Call `Connection.setAudioModeIsVoip()`.
+    pub fn set_audio_mode_is_voip(&self, is_voip: bool) -> Result<()> {
+        self.call_void("setAudioModeIsVoip", "(Z)V", &[is_voip.into()])
+    }
+
+    /// Call `Connection.setConnectionCapabilities()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `capabilities` - Bitmask of `CONNECTION_CAPABILITY_*` constants.
+    pub fn set_connection_capabilities(&self, capabilities: jint) -> Result<()> {
+        self.call_void("setConnectionCapabilities", "(I)V", &[capabilities.into()])
+    }
+
+    /// Call `Connection.setConnectionProperties()`. Requires API 25.
+    ///
+    /// # Arguments
+    ///
+    /// * `properties` - Bitmask of `CONNECTION_PROPERTY_*` constants.
+    pub fn set_connection_properties(&self, properties: jint) -> Result<()> {
+        self.call_void("setConnectionProperties", "(I)V", &[properties.into()])
+    }
+
+    /// Call `Connection.setAddress()` with
+    /// `TelecomManager.PRESENTATION_ALLOWED`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - `android.net.Uri` of the remote party.
+    pub fn set_address(&self, address: JObject<'a>) -> Result<()> {
+        self.call_void(
+            "setAddress",
+            "(Landroid/net/Uri;I)V",
+            &[address.into(), 1.into()],
+        )
+    }
+
+    /// Call `Connection.setCallerDisplayName()` with
+    /// `TelecomManager.PRESENTATION_ALLOWED`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Display name of the remote party.
+    pub fn set_caller_display_name(&self, name: &str) -> Result<()> {
+        let name = self.env.auto_local(self.env.new_string(name)?);
+        self.call_void(
+            "setCallerDisplayName",
+            "(Ljava/lang/String;I)V",
+            &[(&name).into(), 1.into()],
+        )
+    }
+}
+
+impl<'a: 'b, 'b> From<JConnection<'a, 'b>> for JObject<'a> {
+    fn from(connection: JConnection<'a, 'b>) -> Self {
+        connection.internal
+    }
+}
+
+impl<'a: 'b, 'b> ::std::ops::Deref for JConnection<'a, 'b> {
+    type Target = JObject<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.internal
+    }
+}
+
+/// Creates an `android.telecom.Connection` and an accompanying stream of
+/// [`ConnectionEvent`]s captured by it. The stream ends once the connection
+/// has been disconnected. Return the connection from
+/// [`RustConnectionService::on_create_incoming_connection`] or
+/// [`RustConnectionService::on_create_outgoing_connection`], and control it
+/// with [`JConnection`].
+pub fn async_connection<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+) -> Result<(
+    JConnection<'a, 'b>,
+    impl Stream<Item = Result<ConnectionEvent>> + Send,
+)> {
+    let vm = env.get_java_vm()?;
+    let connection = env.new_object(
+        "io/github/gedgygedgy/rust/android/telecom/RustConnection",
+        "()V",
+        &[],
+    )?;
+    let stream = env
+        .call_method(
+            connection,
+            "getEventStream",
+            "()Lio/github/gedgygedgy/rust/stream/Stream;",
+            &[],
+        )?
+        .l()?;
+    let stream = JSendStream::try_from(JStream::from_env(env, stream)?)?;
+
+    let mapped_stream = stream.map(move |item| -> Result<_> {
+        let item = item?;
+        let item = item.as_obj();
+        let env = vm.get_env()?;
+
+        let is_event = |name: &str| {
+            env.is_instance_of(
+                item,
+                format!(
+                    "io/github/gedgygedgy/rust/android/telecom/RustConnection${}",
+                    name
+                ),
+            )
+        };
+
+        if is_event("StateChangedEvent")? {
+            let state = env.get_field(item, "state", "I")?.i()?;
+            Ok(ConnectionEvent::StateChanged { state })
+        } else if is_event("AnswerEvent")? {
+            let video_state = env.get_field(item, "videoState", "I")?.i()?;
+            Ok(ConnectionEvent::Answer { video_state })
+        } else if is_event("RejectEvent")? {
+            Ok(ConnectionEvent::Reject)
+        } else if is_event("DisconnectEvent")? {
+            Ok(ConnectionEvent::Disconnect)
+        } else if is_event("AbortEvent")? {
+            Ok(ConnectionEvent::Abort)
+        } else if is_event("HoldEvent")? {
+            Ok(ConnectionEvent::Hold)
+        } else if is_event("UnholdEvent")? {
+            Ok(ConnectionEvent::Unhold)
+        } else if is_event("ShowIncomingCallUiEvent")? {
+            Ok(ConnectionEvent::ShowIncomingCallUi)
+        } else if is_event("PlayDtmfToneEvent")? {
+            let c = env.get_field(item, "c", "C")?.c()?;
+            Ok(ConnectionEvent::PlayDtmfTone {
+                digit: std::char::from_u32(c as u32).unwrap_or('\u{fffd}'),
+            })
+        } else if is_event("StopDtmfToneEvent")? {
+            Ok(ConnectionEvent::StopDtmfTone)
+        } else if is_event("CallAudioStateChangedEvent")? {
+            let route = env.get_field(item, "route", "I")?.i()?;
+            let supported_route_mask = env.get_field(item, "supportedRouteMask", "I")?.i()?;
+            let muted = env.get_field(item, "muted", "Z")?.z()?;
+            Ok(ConnectionEvent::CallAudioStateChanged {
+                route,
+                supported_route_mask,
+                muted,
+            })
+        } else {
+            panic!("Unknown Event class");
+        }
+    });
+
+    Ok((JConnection::from_env(env, connection), mapped_stream))
+}
+
+/// Trait for Rust implementations of `android.telecom.ConnectionService`.
+/// Register your Rust connection service using
+/// [`register_connection_service`].
+#[allow(unused_variables)]
+pub trait RustConnectionService: Send + Sync {
+    /// Called by `ConnectionService.onCreateIncomingConnection()`. Return a
+    /// connection created by [`async_connection`], or `null` to fail the
+    /// call with `DisconnectCause.ERROR`.
+    fn on_create_incoming_connection<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        phone_account: JObject<'a>,
+        request: JObject<'a>,
+    ) -> JObject<'a> {
+        JObject::null()
+    }
+
+    /// Called by `ConnectionService.onCreateOutgoingConnection()`. Return a
+    /// connection created by [`async_connection`], or `null` to fail the
+    /// call with `DisconnectCause.ERROR`.
+    fn on_create_outgoing_connection<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        phone_account: JObject<'a>,
+        request: JObject<'a>,
+    ) -> JObject<'a> {
+        JObject::null()
+    }
+
+    /// Called by `ConnectionService.onCreateIncomingConnectionFailed()`.
+    fn on_create_incoming_connection_failed<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        phone_account: JObject<'a>,
+        request: JObject<'a>,
+    ) {
+    }
+
+    /// Called by `ConnectionService.onCreateOutgoingConnectionFailed()`.
+    fn on_create_outgoing_connection_failed<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        phone_account: JObject<'a>,
+        request: JObject<'a>,
+    ) {
+    }
+}
+
+fn create_connection_arguments<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    arg: JObject<'a>,
+) -> (JObject<'a>, JObject<'a>) {
+    let phone_account = env
+        .get_field(arg, "phoneAccount", "Landroid/telecom/PhoneAccountHandle;")
+        .unwrap()
+        .l()
+        .unwrap();
+    let request = env
+        .get_field(arg, "request", "Landroid/telecom/ConnectionRequest;")
+        .unwrap()
+        .l()
+        .unwrap();
+    (phone_account, request)
+}
+
+/// Register a connection service as an
+/// `io.github.gedgygedgy.rust.android.telecom.RustConnectionService`. The
+/// `factory` closure is called when `ConnectionService.onCreate()` is called,
+/// and the object created by it is dropped when
+/// `ConnectionService.onDestroy()` is called.
+pub fn register_connection_service<'a: 'b, 'b, T: RustConnectionService + 'static>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
+) -> Result<()> {
+    let class = env.auto_local(class.lookup(env)?);
+
+    let on_create_hook =
+        env.auto_local(jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+            let service = Arc::new(factory(env, arg));
+
+            let service_clone = service.clone();
+            let on_create_incoming_connection_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    let (phone_account, request) = create_connection_arguments(env, arg);
+                    service_clone.on_create_incoming_connection(env, phone_account, request)
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onCreateIncomingConnectionHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_create_incoming_connection_hook).into(),
+            )
+            .unwrap();
+
+            let service_clone = service.clone();
+            let on_create_outgoing_connection_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    let (phone_account, request) = create_connection_arguments(env, arg);
+                    service_clone.on_create_outgoing_connection(env, phone_account, request)
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onCreateOutgoingConnectionHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_create_outgoing_connection_hook).into(),
+            )
+            .unwrap();
+
+            let service_clone = service.clone();
+            let on_create_incoming_connection_failed_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    let (phone_account, request) = create_connection_arguments(env, arg);
+                    service_clone.on_create_incoming_connection_failed(env, phone_account, request);
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onCreateIncomingConnectionFailedHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_create_incoming_connection_failed_hook).into(),
+            )
+            .unwrap();
+
+            let on_create_outgoing_connection_failed_hook = env.auto_local(
+                jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+                    let (phone_account, request) = create_connection_arguments(env, arg);
+                    service.on_create_outgoing_connection_failed(env, phone_account, request);
+                    JObject::null()
+                })
+                .unwrap(),
+            );
+            env.set_field(
+                arg,
+                "onCreateOutgoingConnectionFailedHook",
+                "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+                (&on_create_outgoing_connection_failed_hook).into(),
+            )
+            .unwrap();
+
+            JObject::null()
+        })?);
+
+    let on_create_hooks = env.auto_local(
+        env.get_static_field(
+            "io/github/gedgygedgy/rust/android/telecom/RustConnectionService",
+            "onCreateHooks",
+            "Ljava/util/HashMap;",
+        )?
+        .l()?,
+    );
+    env.call_method(
+        &on_create_hooks,
+        "put",
+        "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        &[(&class).into(), (&on_create_hook).into()],
+    )?;
+
+    Ok(())
+}
+
+/// Unregister a connection service as an
+/// `io.github.gedgygedgy.rust.android.telecom.RustConnectionService`.
+pub fn unregister_connection_service<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+) -> Result<()> {
+    let class = env.auto_local(class.lookup(env)?);
+
+    let on_create_hooks = env.auto_local(
+        env.get_static_field(
+            "io/github/gedgygedgy/rust/android/telecom/RustConnectionService",
+            "onCreateHooks",
+            "Ljava/util/HashMap;",
+        )?
+        .l()?,
+    );
+    let on_create_hook = env
+        .call_method(
+            &on_create_hooks,
+            "remove",
+            "(Ljava/lang/Object;)Ljava/lang/Object;",
+            &[(&class).into()],
+        )?
+        .l()?;
+    env.call_method(on_create_hook, "close", "()V", &[])?;
+
+    Ok(())
+}
+
+fn telecom_manager<'a: 'b, 'b>(env: &'b JNIEnv<'a>, context: JObject<'a>) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service("telecom")
+}
+
+/// Register an `android.telecom.PhoneAccount` for a connection service with
+/// `TelecomManager.registerPhoneAccount()`, and return its
+/// `android.telecom.PhoneAccountHandle`.
+///
+/// Self-managed accounts require the `MANAGE_OWN_CALLS` permission.
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to register the account with.
+/// * `class` - Class of the connection service, which must be declared in
+///   the manifest.
+/// * `id` - Identifier of the account, unique within the connection service.
+/// * `label` - User-visible label of the account.
+/// * `capabilities` - Bitmask of `CAPABILITY_*` constants.
+pub fn register_phone_account<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    id: &str,
+    label: &str,
+    capabilities: jint,
+) -> Result<JObject<'a>> {
+    let class = env.auto_local(class.lookup(env)?);
+    let component_name = env.auto_local(env.new_object(
+        "android/content/ComponentName",
+        "(Landroid/content/Context;Ljava/lang/Class;)V",
+        &[context.into(), (&class).into()],
+    )?);
+    let id = env.auto_local(env.new_string(id)?);
+    let handle = env.new_object(
+        "android/telecom/PhoneAccountHandle",
+        "(Landroid/content/ComponentName;Ljava/lang/String;)V",
+        &[(&component_name).into(), (&id).into()],
+    )?;
+
+    let label = env.auto_local(env.new_string(label)?);
+    let builder = env.auto_local(
+        env.call_static_method(
+            "android/telecom/PhoneAccount",
+            "builder",
+            "(Landroid/telecom/PhoneAccountHandle;Ljava/lang/CharSequence;)Landroid/telecom/PhoneAccount$Builder;",
+            &[handle.into(), (&label).into()],
+        )?
+        .l()?,
+    );
+    env.call_method(
+        &builder,
+        "setCapabilities",
+        "(I)Landroid/telecom/PhoneAccount$Builder;",
+        &[capabilities.into()],
+    )?;
+    let account = env.auto_local(
+        env.call_method(&builder, "build", "()Landroid/telecom/PhoneAccount;", &[])?
+            .l()?,
+    );
+
+    let manager = env.auto_local(telecom_manager(env, context)?);
+    env.call_method(
+        &manager,
+        "registerPhoneAccount",
+        "(Landroid/telecom/PhoneAccount;)V",
+        &[(&account).into()],
+    )?;
+
+    Ok(handle)
+}
+
+/// Report a new incoming call with `TelecomManager.addNewIncomingCall()`.
+/// Telecom will then call
+/// [`RustConnectionService::on_create_incoming_connection`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `TelecomManager` from.
+/// * `phone_account` - `android.telecom.PhoneAccountHandle` of the account
+///   receiving the call.
+/// * `extras` - `android.os.Bundle` of extras to pass to the connection
+///   request, or `null`.
+pub fn add_new_incoming_call<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    phone_account: JObject<'a>,
+    extras: JObject<'a>,
+) -> Result<()> {
+    let manager = env.auto_local(telecom_manager(env, context)?);
+    env.call_method(
+        &manager,
+        "addNewIncomingCall",
+        "(Landroid/telecom/PhoneAccountHandle;Landroid/os/Bundle;)V",
+        &[phone_account.into(), extras.into()],
+    )?;
+    Ok(())
+}
+
+/// Place an outgoing call through a phone account with
+/// `TelecomManager.placeCall()`. Telecom will then call
+/// [`RustConnectionService::on_create_outgoing_connection`].
+///
+/// # Arguments
+///
+/// * `env` - Java environment to use.
+/// * `context` - `android.content.Context` to get the `TelecomManager` from.
+/// * `phone_account` - `android.telecom.PhoneAccountHandle` of the account
+///   placing the call.
+/// * `address` - `android.net.Uri` of the remote party.
+pub fn place_call<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    phone_account: JObject<'a>,
+    address: JObject<'a>,
+) -> Result<()> {
+    let extras = env.auto_local(env.new_object("android/os/Bundle", "()V", &[])?);
+    let key = env.auto_local(env.new_string("android.telecom.extra.PHONE_ACCOUNT_HANDLE")?);
+    env.call_method(
+        &extras,
+        "putParcelable",
+        "(Ljava/lang/String;Landroid/os/Parcelable;)V",
+        &[(&key).into(), phone_account.into()],
+    )?;
+
+    let manager = env.auto_local(telecom_manager(env, context)?);
+    env.call_method(
+        &manager,
+        "placeCall",
+        "(Landroid/net/Uri;Landroid/os/Bundle;)V",
+        &[address.into(), (&extras).into()],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use crate::content::JContext;
+pub(crate) use crate::os::build::sdk_int;
+use futures::Stream;
+use jni::{
+    errors::Result,
+    objects::{GlobalRef, JObject},
+    sys::{jboolean, jdouble, jfloat, jint, jlong},
+    JNIEnv, JavaVM,
+};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Calls `close()` on a Java object when dropped. Used to tear down
+/// listeners, receivers and other registrations owned by Rust futures and
+/// streams.
+pub(crate) struct CloseOnDrop {
+    obj: GlobalRef,
+    vm: JavaVM,
+}
+
+impl CloseOnDrop {
+    pub(crate) fn new<'a: 'b, 'b>(env: &'b JNIEnv<'a>, obj: JObject<'a>) -> Result<Self> {
+        Ok(Self {
+            obj: env.new_global_ref(obj)?,
+            vm: env.get_java_vm()?,
+        })
+    }
+
+    pub(crate) fn vm(&self) -> &JavaVM {
+        &self.vm
+    }
+
+    pub(crate) fn as_obj(&self) -> JObject<'_> {
+        self.obj.as_obj()
+    }
+}
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        if let Ok(env) = self.vm.attach_current_thread() {
+            let _ = env.call_method(self.obj.as_obj(), "close", "()V", &[]);
+        }
+    }
+}
+
+/// Calls a method on a Java object to remove a listener when dropped. Used
+/// for listeners registered with system services, which are removed with a
+/// `remove*Listener()` method rather than `close()`.
+pub(crate) struct RemoveListenerOnDrop {
+    obj: GlobalRef,
+    listener: GlobalRef,
+    method: &'static str,
+    sig: &'static str,
+    flags: Option<jint>,
+    vm: JavaVM,
+}
+
+impl RemoveListenerOnDrop {
+    pub(crate) fn new<'a: 'b, 'b>(
+        env: &'b JNIEnv<'a>,
+        obj: JObject<'a>,
+        listener: JObject<'a>,
+        method: &'static str,
+        sig: &'static str,
+    ) -> Result<Self> {
+        Ok(Self {
+            obj: env.new_global_ref(obj)?,
+            listener: env.new_global_ref(listener)?,
+            method,
+            sig,
+            flags: None,
+            vm: env.get_java_vm()?,
+        })
+    }
+
+    /// Pass an extra `int` flags argument to the remove method, as
+    /// `IBinder.unlinkToDeath()` requires.
+    pub(crate) fn with_flags(mut self, flags: jint) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+}
+
+impl Drop for RemoveListenerOnDrop {
+    fn drop(&mut self) {
+        if let Ok(env) = self.vm.attach_current_thread() {
+            let listener = self.listener.as_obj().into();
+            let _ = match self.flags {
+                Some(flags) => env.call_method(
+                    self.obj.as_obj(),
+                    self.method,
+                    self.sig,
+                    &[listener, flags.into()],
+                ),
+                None => env.call_method(self.obj.as_obj(), self.method, self.sig, &[listener]),
+            };
+        }
+    }
+}
+
+/// Stream which keeps a [`CloseOnDrop`] alive for as long as the stream
+/// itself.
+pub(crate) struct CloseOnDropStream<S> {
+    stream: S,
+    _guard: CloseOnDrop,
+}
+
+impl<S> CloseOnDropStream<S> {
+    pub(crate) fn new(stream: S, guard: CloseOnDrop) -> Self {
+        Self {
+            stream,
+            _guard: guard,
+        }
+    }
+}
+
+impl<S: Stream + Unpin> Stream for CloseOnDropStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.stream).poll_next(context)
+    }
+}
+
+/// Create a `java.util.concurrent.Executor` which runs commands directly on
+/// the thread that submits them.
+pub(crate) fn direct_executor<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> Result<JObject<'a>> {
+    env.new_object(
+        "io/github/gedgygedgy/rust/android/ops/RustDirectExecutor",
+        "()V",
+        &[],
+    )
+}
+
+macro_rules! define_array_field {
+    ($name:ident, $elem:ty, $sig:literal, $get:ident) => {
+        pub(crate) fn $name<'a: 'b, 'b>(
+            env: &'b JNIEnv<'a>,
+            obj: JObject<'a>,
+            name: &str,
+        ) -> Result<Vec<$elem>> {
+            let array = env.get_field(obj, name, $sig)?.l()?.into_inner();
+            let mut result = vec![Default::default(); env.get_array_length(array)? as usize];
+            env.$get(array, 0, &mut result)?;
+            Ok(result)
+        }
+    };
+}
+
+define_array_field!(int_array_field, jint, "[I", get_int_array_region);
+define_array_field!(long_array_field, jlong, "[J", get_long_array_region);
+define_array_field!(float_array_field, jfloat, "[F", get_float_array_region);
+define_array_field!(double_array_field, jdouble, "[D", get_double_array_region);
+define_array_field!(
+    boolean_array_field,
+    jboolean,
+    "[Z",
+    get_boolean_array_region
+);
+
+/// Get a system service with `Context.getSystemService()`.
+pub(crate) fn system_service<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    context: JObject<'a>,
+    name: &str,
+) -> Result<JObject<'a>> {
+    JContext::from_env(env, context)?.system_service(name)
+}
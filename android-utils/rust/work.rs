@@ -0,0 +1,267 @@
+//! Integration with AndroidX WorkManager, for deferrable work written in
+//! Rust. Requires the `work` feature, and the `androidx.work:work-runtime`
+//! library in the app.
+//!
+//! Extend `io.github.gedgygedgy.rust.android.work.RustListenableWorker` in
+//! Java, register it with [`register_worker`], and enqueue it with
+//! WorkManager as usual. The worker's input `androidx.work.Data` is
+//! deserialized into [`RustWorker::Input`] with
+//! [`bundle_serde`](crate::os::bundle_serde), and its output is serialized
+//! the same way. `Data` only holds primitives, strings and arrays of them,
+//! so the output must be a flat map or struct.
+
+use crate::os::{
+    bundle::{object_from_value, value_from_object, BundleValue},
+    bundle_serde::{from_value, to_value, Error},
+    JLooper,
+};
+use futures::future::BoxFuture;
+use jni::{
+    descriptors::Desc,
+    errors::Result,
+    objects::{JClass, JObject},
+    JNIEnv,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+
+/// Result of a [`RustWorker`]. Mirrors `ListenableWorker.Result`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WorkResult<T> {
+    /// The work succeeded, with the given output.
+    Success(T),
+    /// The work failed permanently, with the given output.
+    Failure(T),
+    /// The work failed and should be retried with the backoff policy of the
+    /// `WorkRequest`.
+    Retry,
+}
+
+/// Trait for Rust implementations of `androidx.work.ListenableWorker`.
+/// Register your worker using [`register_worker`].
+#[allow(unused_variables)]
+pub trait RustWorker: Send + Sync {
+    /// Type of the worker's input data.
+    type Input: DeserializeOwned;
+
+    /// Type of the worker's output data.
+    type Output: Serialize;
+
+    /// Called by `ListenableWorker.startWork()` on the main thread to create
+    /// the future doing the work. The future is spawned on the main
+    /// `Handler`, so it should hand long blocking work off to another
+    /// thread, for example with [`blocking`](crate::blocking).
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - Java environment to use.
+    /// * `worker` - The `RustListenableWorker`, which can be used to call
+    ///   `ListenableWorker` methods such as `getApplicationContext()`.
+    /// * `input` - Input data of the work.
+    fn start_work<'a: 'b, 'b>(
+        &self,
+        env: &'b JNIEnv<'a>,
+        worker: JObject<'a>,
+        input: Self::Input,
+    ) -> BoxFuture<'static, WorkResult<Self::Output>>;
+
+    /// Called by `ListenableWorker.onStopped()` when the work is cancelled
+    /// or its constraints are no longer met. The future returned by
+    /// [`start_work`](RustWorker::start_work) has already been aborted.
+    fn on_stopped(&self, env: &JNIEnv) {}
+}
+
+const WORKER_CLASS: &str = "io/github/gedgygedgy/rust/android/work/RustListenableWorker";
+const RESULT_CLASS: &str = "androidx/work/ListenableWorker$Result";
+
+fn data<'a: 'b, 'b, T: Serialize>(
+    env: &'b JNIEnv<'a>,
+    value: &T,
+) -> std::result::Result<JObject<'a>, Error> {
+    let map = match to_value(value)? {
+        BundleValue::Map(map) => map,
+        BundleValue::Null => Default::default(),
+        _ => {
+            return Err(Error::Message(
+                "worker output must be a map or struct".to_string(),
+            ))
+        }
+    };
+
+    let java_map = env.new_object("java/util/HashMap", "()V", &[])?;
+    for (key, value) in &map {
+        let key = env.new_string(key)?;
+        let value = object_from_value(env, value)?;
+        env.call_method(
+            java_map,
+            "put",
+            "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+            &[key.into(), value.into()],
+        )?;
+    }
+    Ok(env
+        .call_static_method(
+            WORKER_CLASS,
+            "toData",
+            "(Ljava/util/Map;)Landroidx/work/Data;",
+            &[java_map.into()],
+        )?
+        .l()?)
+}
+
+fn complete<'a: 'b, 'b, T: Serialize>(
+    env: &'b JNIEnv<'a>,
+    worker: JObject<'a>,
+    result: WorkResult<T>,
+) -> std::result::Result<(), Error> {
+    let (method, output) = match &result {
+        WorkResult::Success(output) => ("success", Some(output)),
+        WorkResult::Failure(output) => ("failure", Some(output)),
+        WorkResult::Retry => ("retry", None),
+    };
+    let result = match output {
+        Some(output) => {
+            let data = data(env, output)?;
+            env.call_static_method(
+                RESULT_CLASS,
+                method,
+                "(Landroidx/work/Data;)Landroidx/work/ListenableWorker$Result;",
+                &[data.into()],
+            )?
+        }
+        None => env.call_static_method(
+            RESULT_CLASS,
+            method,
+            "()Landroidx/work/ListenableWorker$Result;",
+            &[],
+        )?,
+    }
+    .l()?;
+    env.call_method(
+        worker,
+        "complete",
+        "(Landroidx/work/ListenableWorker$Result;)V",
+        &[result.into()],
+    )?;
+    Ok(())
+}
+
+fn fail<'a: 'b, 'b>(env: &'b JNIEnv<'a>, worker: JObject<'a>, err: Error) {
+    log::error!("Worker failed: {}", err);
+    let _ = env.exception_clear();
+    let _ = complete(env, worker, WorkResult::<()>::Failure(()));
+}
+
+fn start_work<'a: 'b, 'b, T: RustWorker + 'static>(
+    env: &'b JNIEnv<'a>,
+    worker: JObject<'a>,
+    rust_worker: T,
+) -> std::result::Result<(), Error> {
+    let input = env
+        .call_method(worker, "getInputData", "()Landroidx/work/Data;", &[])?
+        .l()?;
+    let input = env
+        .call_method(input, "getKeyValueMap", "()Ljava/util/Map;", &[])?
+        .l()?;
+    let input = from_value(value_from_object(env, input)?)?;
+
+    let rust_worker = Arc::new(rust_worker);
+    let future = rust_worker.start_work(env, worker, input);
+    let worker_ref = env.new_global_ref(worker)?;
+    let vm = env.get_java_vm()?;
+    let task = async move {
+        let result = future.await;
+        match vm.attach_current_thread_as_daemon() {
+            Ok(env) => {
+                if let Err(err) = complete(&env, worker_ref.as_obj(), result) {
+                    fail(&env, worker_ref.as_obj(), err);
+                }
+            }
+            Err(err) => log::error!("Failed to attach worker thread: {}", err),
+        }
+    };
+    let abort = JLooper::main(env)?
+        .handler()?
+        .spawner()
+        .spawn_abortable(task)
+        .map_err(|err| Error::Message(err.to_string()))?;
+
+    let on_stopped_hook =
+        env.auto_local(jni_utils::ops::fn_function(env, move |env, _obj, _arg| {
+            let _ = abort.abort();
+            rust_worker.on_stopped(env);
+            JObject::null()
+        })?);
+    env.set_field(
+        worker,
+        "onStoppedHook",
+        "Lio/github/gedgygedgy/rust/ops/FnFunction;",
+        (&on_stopped_hook).into(),
+    )?;
+    Ok(())
+}
+
+/// Register a worker as an
+/// `io.github.gedgygedgy.rust.android.work.RustListenableWorker`. The
+/// `factory` closure is called with the worker object each time
+/// `ListenableWorker.startWork()` is called, and the object created by it is
+/// dropped once the work has completed or been stopped.
+///
+/// If the input data can't be deserialized, or the output can't be
+/// serialized, the error is logged and the work fails.
+pub fn register_worker<'a: 'b, 'b, T: RustWorker + 'static>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+    factory: impl for<'c, 'd> Fn(&'d JNIEnv<'c>, JObject<'c>) -> T + Send + Sync + 'static,
+) -> Result<()> {
+    let class = env.auto_local(class.lookup(env)?);
+
+    let start_work_hook =
+        env.auto_local(jni_utils::ops::fn_function(env, move |env, _obj, arg| {
+            if let Err(err) = start_work(env, arg, factory(env, arg)) {
+                fail(env, arg, err);
+            }
+            JObject::null()
+        })?);
+
+    let start_work_hooks = env.auto_local(
+        env.get_static_field(WORKER_CLASS, "startWorkHooks", "Ljava/util/HashMap;")?
+            .l()?,
+    );
+    env.call_method(
+        &start_work_hooks,
+        "put",
+        "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        &[(&class).into(), (&start_work_hook).into()],
+    )?;
+
+    Ok(())
+}
+
+/// Unregister a worker as an
+/// `io.github.gedgygedgy.rust.android.work.RustListenableWorker`. Work which
+/// is started afterwards fails.
+pub fn unregister_worker<'a: 'b, 'b>(
+    env: &'b JNIEnv<'a>,
+    class: impl Desc<'a, JClass<'a>>,
+) -> Result<()> {
+    let class = env.auto_local(class.lookup(env)?);
+
+    let start_work_hooks = env.auto_local(
+        env.get_static_field(WORKER_CLASS, "startWorkHooks", "Ljava/util/HashMap;")?
+            .l()?,
+    );
+    let start_work_hook = env
+        .call_method(
+            &start_work_hooks,
+            "remove",
+            "(Ljava/lang/Object;)Ljava/lang/Object;",
+            &[(&class).into()],
+        )?
+        .l()?;
+    if !start_work_hook.is_null() {
+        env.call_method(start_work_hook, "close", "()V", &[])?;
+    }
+
+    Ok(())
+}
@@ -31,4 +31,190 @@ public class AndroidTest {
     }
 
     private native void testPanicInternal();
+
+    @Test
+    public native void testDeferredGlobalRef();
+
+    @Test
+    public native void testListenerStream();
+
+    @Test
+    public native void testCallbackFuture();
+
+    @Test
+    public native void testSpawnBlocking();
+
+    @Test
+    public native void testSharedMemory();
+
+    @Test
+    public native void testMemoryFile();
+
+    @Test
+    public native void testBundleValue();
+
+    @Test
+    public native void testPersistableBundle();
+
+    @Test
+    public native void testIntentUri();
+
+    @Test
+    public native void testRouter();
+
+    @Test
+    public native void testProcessState();
+
+    @Test
+    public native void testExitReasons();
+
+    @Test
+    public native void testUsageStats();
+
+    @Test
+    public native void testScreenEvents();
+
+    @Test
+    public native void testLocales();
+
+    @Test
+    public native void testTimeEvents();
+
+    @Test
+    public native void testBinder();
+
+    @Test
+    public native void testParcel();
+
+    @Test
+    public native void testParcelSerde();
+
+    @Test
+    public native void testBundleSerde();
+
+    @Test
+    public native void testFileDescriptors();
+
+    @Test
+    public native void testRustBinder();
+
+    @Test
+    public native void testSystemClock();
+
+    @Test
+    public native void testTraceSection();
+
+    @Test
+    public native void testBuildInfo();
+
+    @Test
+    public native void testProcess();
+
+    @Test
+    public native void testDebugMemoryInfo();
+
+    @Test
+    public native void testThermalStatus();
+
+    @Test
+    public native void testPerformanceHintSession();
+
+    @Test
+    public native void testStrictModePolicy();
+
+    @Test
+    public native void testStrictModeViolations();
+
+    @Test
+    public native void testVibrator();
+
+    @Test
+    public native void testSensors();
+
+    @Test
+    public native void testProximityAlert();
+
+    @Test
+    public native void testGnssStatus();
+
+    @Test
+    public native void testBatteryState();
+
+    @Test
+    public native void testStorageDirs();
+
+    @Test
+    public native void testCrashReports();
+
+    @Test
+    public native void testKeyAttestation();
+
+    @Test
+    public native void testLogThrowable();
+
+    @Test
+    public native void testAndroidLogger();
+
+    @Test
+    public native void testLoggerNativeThread();
+
+    @Test
+    public native void testTagPolicy();
+
+    @Test
+    public native void testPanicHook();
+
+    @Test
+    public native void testBufferedLogger();
+
+    @Test
+    public native void testBufferedLoggerManyRecords();
+
+    @Test
+    public native void testLogFilter();
+
+    @Test
+    public native void testLoggerBuilder();
+
+    @Test
+    public native void testLogBridge();
+
+    @Test
+    public native void testTracingLayer();
+
+    @Test
+    public native void testLogKeyValues();
+
+    @Test
+    public native void testJContext();
+
+    @Test
+    public native void testJIntent();
+
+    @Test
+    public native void testJBundle();
+
+    @Test
+    public native void testAsyncBroadcastReceiver();
+
+    @Test
+    public native void testRegisterReceiver();
+
+    @Test
+    public native void testJSharedPreferences();
+
+    @Test
+    public native void testClipboard();
+
+    @Test
+    public native void testPackageManager();
+
+    @Test
+    public native void testJPendingIntent();
+
+    @Test
+    public native void testBluetoothProfileProxy();
+
+    @Test
+    public native void testTelecomConnection();
 }
\ No newline at end of file
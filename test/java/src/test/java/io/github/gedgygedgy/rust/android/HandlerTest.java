@@ -1,5 +1,9 @@
 package io.github.gedgygedgy.rust.android;
 
+import android.os.Handler;
+import android.os.Looper;
+import android.os.Message;
+
 import org.junit.Test;
 import org.junit.runner.RunWith;
 
@@ -7,6 +11,17 @@ import org.robolectric.RobolectricTestRunner;
 
 @RunWith(RobolectricTestRunner.class)
 public class HandlerTest {
+    private static class ThrowingHandler extends Handler {
+        public ThrowingHandler(Looper looper) {
+            super(looper);
+        }
+
+        @Override
+        public boolean sendMessageAtTime(Message msg, long uptimeMillis) {
+            throw new IllegalStateException("ThrowingHandler");
+        }
+    }
+
     static {
         AndroidTest.loadAndroidUtilsTestLibrary();
     }
@@ -14,6 +29,39 @@ public class HandlerTest {
     @Test
     public native void testPost();
 
+    @Test
+    public native void testPostDelayed();
+
+    @Test
+    public native void testSendMessage();
+
+    @Test
+    public native void testHandlerNativeThread();
+
+    @Test
+    public native void testPostToken();
+
+    @Test
+    public native void testLooper();
+
+    @Test
+    public native void testGlobalHandler();
+
+    @Test
+    public native void testDelay();
+
+    @Test
+    public native void testIdleHandler();
+
+    @Test
+    public native void testAsyncHandler();
+
+    @Test
+    public native void testSpawnWithHandle();
+
+    @Test
+    public native void testMessenger();
+
     @Test
     public native void testSpawn();
 
@@ -23,6 +71,9 @@ public class HandlerTest {
     @Test
     public native void testSpawnAsyncSleep();
 
+    @Test
+    public native void testLooperRuntime();
+
     @Test
     public native void testSpawnThread();
 
@@ -32,6 +83,21 @@ public class HandlerTest {
     @Test
     public native void testSpawnLocalThread();
 
+    @Test
+    public native void testSpawnAbortable();
+
+    @Test
+    public native void testYieldNow();
+
+    @Test
+    public native void testSpawnMetrics();
+
+    @Test
+    public native void testSpawnOptions();
+
+    @Test
+    public native void testSpawnJniError();
+
     @Test
     public native void testRustHandlerCallback();
 }
@@ -0,0 +1,19 @@
+package io.github.gedgygedgy.rust.android;
+
+import org.junit.Test;
+import org.junit.runner.RunWith;
+
+import org.robolectric.RobolectricTestRunner;
+
+@RunWith(RobolectricTestRunner.class)
+public class NoInitTest {
+    static {
+        // Each test class runs in its own JVM, so this only affects the
+        // tests below.
+        System.setProperty("android_utils.test.no_init", "true");
+        AndroidTest.loadAndroidUtilsTestLibrary();
+    }
+
+    @Test
+    public native void testSpawnWithoutInit();
+}
@@ -1,16 +1,58 @@
 package io.github.gedgygedgy.rust.android;
 
+import android.app.Service;
+import android.content.Context;
+import android.content.Intent;
+import android.os.IBinder;
+
+import androidx.work.WorkerParameters;
+
 import org.junit.Test;
 import org.junit.runner.RunWith;
 
+import org.robolectric.Robolectric;
 import org.robolectric.RobolectricTestRunner;
 
+import io.github.gedgygedgy.rust.android.app.RustContentProvider;
 import io.github.gedgygedgy.rust.android.app.RustService;
+import io.github.gedgygedgy.rust.android.nfc.cardemulation.RustHostApduService;
+import io.github.gedgygedgy.rust.android.telecom.RustConnectionService;
+import io.github.gedgygedgy.rust.android.work.RustListenableWorker;
 
 @RunWith(RobolectricTestRunner.class)
 public class ServiceTest {
     private static class TestRustService extends RustService {}
 
+    private static class TestAsyncRustService extends RustService {}
+
+    private static class TestRustIntentService extends RustService {}
+
+    private static class TestNamedRustService extends RustService {}
+
+    private static class TestRpcService extends RustService {}
+
+    private static class UnregisteredRustService extends RustService {}
+
+    private static class TestRustContentProvider extends RustContentProvider {}
+
+    private static class TestRustConnectionService extends RustConnectionService {}
+
+    private static class TestRustHostApduService extends RustHostApduService {}
+
+    // WorkerFactory creates workers by reflection, so this must be public.
+    public static class TestRustWorker extends RustListenableWorker {
+        public TestRustWorker(Context context, WorkerParameters params) {
+            super(context, params);
+        }
+    }
+
+    private static class TestPlainService extends Service {
+        @Override
+        public IBinder onBind(Intent intent) {
+            return null;
+        }
+    }
+
     static {
         AndroidTest.loadAndroidUtilsTestLibrary();
     }
@@ -18,6 +60,62 @@ public class ServiceTest {
     @Test
     public native void testRustServiceConnection();
 
+    @Test(expected = IllegalStateException.class)
+    public void testUnregisteredRustService() {
+        Robolectric.buildService(UnregisteredRustService.class).create();
+    }
+
     @Test
     public native void testRustService();
+
+    @Test
+    public native void testRustHostApduService();
+
+    @Test
+    public native void testServiceForeground();
+
+    @Test
+    public native void testForegroundNotification();
+
+    @Test
+    public native void testServiceHandle();
+
+    @Test
+    public native void testAsyncRustService();
+
+    @Test
+    public native void testAsyncRustServiceManyStarts();
+
+    @Test
+    public native void testBindService();
+
+    @Test
+    public native void testConnectService();
+
+    @Test
+    public native void testResilientConnection();
+
+    @Test
+    public native void testResilientConnectionQuitLooper();
+
+    @Test
+    public native void testMessengerRpc();
+
+    @Test
+    public native void testEnqueueWork();
+
+    @Test
+    public native void testRustIntentService();
+
+    @Test
+    public native void testRegisterServiceByName();
+
+    @Test
+    public native void testRustContentProvider();
+
+    @Test
+    public native void testRustConnectionService();
+
+    @Test
+    public native void testRustWorker();
 }
@@ -1,6 +1,13 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use android_utils::{
-    os::{async_handler_callback, JHandler},
-    service::{async_service_connection, register_service, RustService, ServiceConnectionEvent},
+    ops::{callback_future, int_callback_future, listener_stream, void_callback_future},
+    os::{async_handler_callback, GlobalHandler, JHandler, JLooper, JMessenger},
+    refs::{flush_refs, DeferredGlobalRef},
+    service::{
+        async_service_connection, register_service, service_instance, service_instances,
+        RustService, ServiceConnectionEvent, ServiceContext, ServiceStartResult, StartFlags,
+    },
 };
 use futures::StreamExt;
 use jni::{
@@ -22,7 +29,20 @@ use std::{
 #[no_mangle]
 pub extern "C" fn JNI_OnLoad(vm: JavaVM, _res: *const c_void) -> jint {
     let env = vm.get_env().unwrap();
-    android_utils::init(&env).unwrap();
+    let no_init = env.new_string("android_utils.test.no_init").unwrap();
+    let no_init = env
+        .call_static_method(
+            "java/lang/Boolean",
+            "getBoolean",
+            "(Ljava/lang/String;)Z",
+            &[no_init.into()],
+        )
+        .unwrap()
+        .z()
+        .unwrap();
+    if !no_init {
+        android_utils::init(&env).unwrap();
+    }
     log::set_max_level(log::LevelFilter::Off);
     jni_utils::init(&env).unwrap();
     jni::JNIVersion::V6.into()
@@ -39,828 +59,1325 @@ pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testPanicIn
 }
 
 fn shadow_looper_and_handler<'a: 'b, 'b>(env: &'b JNIEnv<'a>) -> (JObject<'a>, JHandler<'a, 'b>) {
-    let looper = env
-        .call_static_method(
-            "android/os/Looper",
-            "getMainLooper",
-            "()Landroid/os/Looper;",
-            &[],
-        )
-        .unwrap()
-        .l()
-        .unwrap();
+    let looper = JLooper::main(env).unwrap();
     let shadow_looper = env
         .call_static_method(
             "org/robolectric/Shadows",
             "shadowOf",
             "(Landroid/os/Looper;)Lorg/robolectric/shadows/ShadowLooper;",
-            &[looper.into()],
+            &[(*looper).into()],
         )
         .unwrap()
         .l()
         .unwrap();
 
-    let handler = env
-        .new_object(
-            "android/os/Handler",
-            "(Landroid/os/Looper;)V",
-            &[looper.into()],
+    (shadow_looper, looper.handler().unwrap())
+}
+
+fn shadow_logs_for_tag(env: &JNIEnv, tag: &str) -> Vec<(i32, String)> {
+    let tag = env.new_string(tag).unwrap();
+    let logs = env
+        .call_static_method(
+            "org/robolectric/shadows/ShadowLog",
+            "getLogsForTag",
+            "(Ljava/lang/String;)Ljava/util/List;",
+            &[tag.into()],
         )
+        .unwrap()
+        .l()
         .unwrap();
-    let handler = JHandler::from_env(&env, handler).unwrap();
-
-    (shadow_looper, handler)
+    let size = env
+        .call_method(logs, "size", "()I", &[])
+        .unwrap()
+        .i()
+        .unwrap();
+    (0..size)
+        .map(|i| {
+            let item = env
+                .call_method(logs, "get", "(I)Ljava/lang/Object;", &[i.into()])
+                .unwrap()
+                .l()
+                .unwrap();
+            let msg = env
+                .get_field(item, "msg", "Ljava/lang/String;")
+                .unwrap()
+                .l()
+                .unwrap();
+            (
+                env.get_field(item, "type", "I").unwrap().i().unwrap(),
+                env.get_string(msg.into()).unwrap().into(),
+            )
+        })
+        .collect()
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testPost(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testDeferredGlobalRef(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let obj = env.new_object("java/lang/Object", "()V", &[]).unwrap();
+        let deferred = DeferredGlobalRef::new(env.new_global_ref(obj).unwrap());
+        let clone = deferred.clone();
 
-        let arc = Arc::new(Mutex::new(false));
-        let arc2 = arc.clone();
-        let runnable = jni_utils::ops::fn_once_runnable(&env, move |_e, _o| {
-            let mut guard = arc2.lock().unwrap();
-            *guard = true;
+        std::thread::spawn(move || {
+            drop(deferred);
         })
+        .join()
         .unwrap();
+        flush_refs(&env);
 
-        handler.post(runnable).unwrap();
-        {
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, false);
-        }
-
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
-        {
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, true);
-        }
+        assert!(env.is_same_object(clone.as_obj(), obj).unwrap());
+        drop(clone);
+        flush_refs(&env);
     });
 }
 
+android_utils::listener_stream! {
+    enum ConsumerEvent: "java/util/function/Consumer" {
+        Accept = "accept"(value),
+    }
+}
+
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawn(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testListenerStream(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::{channel::oneshot::channel, task::SpawnExt};
+        use futures::executor::block_on;
 
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let runnable_class = env.find_class("java/lang/Runnable").unwrap();
+        let (runnable, mut stream) = listener_stream(&env, runnable_class).unwrap();
+        assert!(env.is_instance_of(runnable, runnable_class).unwrap());
+        env.call_method(runnable, "run", "()V", &[]).unwrap();
+        let hash_code = env
+            .call_method(runnable, "hashCode", "()I", &[])
+            .unwrap()
+            .i()
+            .unwrap();
+        let identity_hash_code = env
+            .call_static_method(
+                "java/lang/System",
+                "identityHashCode",
+                "(Ljava/lang/Object;)I",
+                &[runnable.into()],
+            )
+            .unwrap()
+            .i()
+            .unwrap();
+        assert_eq!(hash_code, identity_hash_code);
+        env.call_method(runnable, "run", "()V", &[]).unwrap();
 
-        let (sender, receiver) = channel::<()>();
+        for _ in 0..2 {
+            let call = block_on(stream.next()).unwrap().unwrap();
+            assert_eq!(call.method, "run");
+            assert!(call.args.is_empty());
+        }
 
-        let arc = Arc::new(Mutex::new(0));
-        let arc2 = arc.clone();
+        let (consumer, mut stream) = ConsumerEvent::listener(&env).unwrap();
+        let value = env.new_string("value").unwrap();
+        env.call_method(consumer, "accept", "(Ljava/lang/Object;)V", &[value.into()])
+            .unwrap();
+        env.call_method(
+            consumer,
+            "andThen",
+            "(Ljava/util/function/Consumer;)Ljava/util/function/Consumer;",
+            &[consumer.into()],
+        )
+        .unwrap();
+        env.call_method(
+            consumer,
+            "accept",
+            "(Ljava/lang/Object;)V",
+            &[JObject::null().into()],
+        )
+        .unwrap();
 
-        let closure = async move {
-            {
-                let mut guard = arc2.lock().unwrap();
-                *guard = 1;
-            }
-            receiver.await.unwrap();
-            {
-                let mut guard = arc2.lock().unwrap();
-                *guard = 2;
-            }
-        };
+        let ConsumerEvent::Accept { value: received } = block_on(stream.next()).unwrap().unwrap();
+        assert!(env.is_same_object(received.as_obj(), value).unwrap());
+        let ConsumerEvent::Accept { value: received } = block_on(stream.next()).unwrap().unwrap();
+        assert!(received.as_obj().is_null());
+    });
+}
 
-        let handler_spawn = handler.spawner();
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testCallbackFuture(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::executor::block_on;
 
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
-        handler_spawn.spawn(closure).unwrap();
-        {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 0);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
-        );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+        let consumer_class = env.find_class("java/util/function/Consumer").unwrap();
+        let (consumer, future) = callback_future(&env, consumer_class).unwrap();
+        let value = env.new_string("value").unwrap();
+        let other = env.new_string("other").unwrap();
+        env.call_method(consumer, "accept", "(Ljava/lang/Object;)V", &[value.into()])
             .unwrap();
-        {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 1);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
-        sender.send(()).unwrap();
-        {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 1);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
-        );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+        env.call_method(consumer, "accept", "(Ljava/lang/Object;)V", &[other.into()])
             .unwrap();
-        {
-            assert_eq!(Arc::strong_count(&arc), 1);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 2);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
+        let received = block_on(future).unwrap();
+        assert!(env.is_same_object(received.as_obj(), value).unwrap());
+
+        let int_consumer_class = env.find_class("java/util/function/IntConsumer").unwrap();
+        let (int_consumer, future) = int_callback_future(&env, int_consumer_class).unwrap();
+        env.call_method(int_consumer, "accept", "(I)V", &[42.into()])
+            .unwrap();
+        assert_eq!(block_on(future).unwrap(), 42);
+
+        let runnable_class = env.find_class("java/lang/Runnable").unwrap();
+        let (runnable, future) = void_callback_future(&env, runnable_class).unwrap();
+        env.call_method(runnable, "run", "()V", &[]).unwrap();
+        block_on(future).unwrap();
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnAsyncSleep(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testSpawnBlocking(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::task::SpawnExt;
-        use std::time::Duration;
+        use android_utils::blocking::{blocking_pool_metrics, spawn_blocking};
+        use futures::executor::block_on;
 
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let result = block_on(spawn_blocking(|env| {
+            let string = env.new_string("blocking").unwrap();
+            let string: String = env.get_string(string).unwrap().into();
+            (string, std::thread::current().name().map(String::from))
+        }))
+        .unwrap();
+        assert_eq!(result.0, "blocking");
+        assert!(result.1.unwrap().starts_with("android-utils-blocking-"));
 
-        let arc = Arc::new(Mutex::new(0));
-        let arc2 = arc.clone();
+        let panicked = block_on(spawn_blocking(|_env| -> () {
+            panic!("job panicked");
+        }));
+        assert!(panicked.is_none());
 
-        let closure = async move {
-            {
-                let mut guard = arc2.lock().unwrap();
-                *guard = 1;
-            }
-            async_std::task::sleep(Duration::from_millis(500)).await;
-            {
-                let mut guard = arc2.lock().unwrap();
-                *guard = 2;
-            }
-        };
+        let metrics = blocking_pool_metrics();
+        assert!(metrics.threads > 0);
+        assert_eq!(metrics.queued, 0);
+    });
+}
 
-        let handler_spawn = handler.spawner();
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testSharedMemory(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{JParcel, JSharedMemory};
+
+        let shared_memory = JSharedMemory::create(&env, Some("test"), 16).unwrap();
+        assert_eq!(shared_memory.size().unwrap(), 16);
 
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
-        handler_spawn.spawn(closure).unwrap();
         {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 0);
+            let mut mapping = shared_memory.map_read_write().unwrap();
+            assert_eq!(mapping.len(), 16);
+            mapping.as_mut_slice().unwrap()[..4].copy_from_slice(&[1, 2, 3, 4]);
         }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
-        );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
         {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 1);
+            let mut mapping = shared_memory.map_read_only().unwrap();
+            assert_eq!(&mapping[..4], &[1, 2, 3, 4]);
+            assert!(mapping.as_mut_slice().is_none());
         }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
 
-        // Wait until after the sleep() has completed. This is admittedly a bit
-        // racy.
-        std::thread::sleep(Duration::from_millis(1000));
+        let bundle = env.new_object("android/os/Bundle", "()V", &[]).unwrap();
+        shared_memory.put_in_bundle(bundle, "memory").unwrap();
+        let received = JSharedMemory::from_bundle(&env, bundle, "memory")
+            .unwrap()
+            .unwrap();
+        assert!(env.is_same_object(*received, *shared_memory).unwrap());
+        assert!(JSharedMemory::from_bundle(&env, bundle, "missing")
+            .unwrap()
+            .is_none());
+
+        let parcel = JParcel::obtain(&env).unwrap();
+        shared_memory.write_to_parcel(&parcel).unwrap();
+        parcel.set_data_position(0).unwrap();
+        let received = JSharedMemory::from_parcel(&env, &parcel).unwrap();
+        assert_eq!(received.size().unwrap(), 16);
+        parcel.recycle().unwrap();
+
+        assert!(matches!(
+            JSharedMemory::create(&env, None, usize::MAX),
+            Err(jni::errors::Error::JavaException)
+        ));
+        let exception = env.exception_occurred().unwrap();
+        env.exception_clear().unwrap();
+        assert!(env
+            .is_instance_of(exception, "java/lang/IllegalArgumentException")
+            .unwrap());
+
+        shared_memory.close().unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testMemoryFile(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{FdMapping, JMemoryFile};
+        use std::io::Write;
+
+        let memory_file = JMemoryFile::create(&env, Some("test"), 16).unwrap();
+        assert_eq!(memory_file.size().unwrap(), 16);
+
+        assert!(matches!(
+            JMemoryFile::create(&env, None, usize::MAX),
+            Err(jni::errors::Error::JavaException)
+        ));
+        let exception = env.exception_occurred().unwrap();
+        env.exception_clear().unwrap();
+        assert!(env
+            .is_instance_of(exception, "java/lang/IllegalArgumentException")
+            .unwrap());
 
+        memory_file.close().unwrap();
+
+        let path = std::env::temp_dir().join(format!("fd-mapping-{}", std::process::id()));
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(&[0; 8]).unwrap();
         {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 1);
+            let mut mapping = FdMapping::new(&file, 8, true).unwrap();
+            assert_eq!(mapping.len(), 8);
+            mapping.as_mut_slice().unwrap()[..4].copy_from_slice(&[5, 6, 7, 8]);
         }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
-        );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
         {
-            assert_eq!(Arc::strong_count(&arc), 1);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 2);
+            let mut mapping = FdMapping::new(&file, 8, false).unwrap();
+            assert_eq!(&mapping[..], &[5, 6, 7, 8, 0, 0, 0, 0]);
+            assert!(mapping.as_mut_slice().is_none());
         }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnNativeThreadWake(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBundleValue(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::{channel::oneshot::channel, task::SpawnExt};
-        use std::time::Duration;
+        use android_utils::os::bundle::{bundle_to_map, map_to_bundle, BundleValue};
+        use std::collections::HashMap;
 
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let mut nested = HashMap::new();
+        nested.insert("inner".to_string(), BundleValue::I64(1 << 40));
 
-        let (sender, receiver) = channel::<()>();
+        let mut map = HashMap::new();
+        map.insert("null".to_string(), BundleValue::Null);
+        map.insert("bool".to_string(), BundleValue::Bool(true));
+        map.insert("i32".to_string(), BundleValue::I32(-5));
+        map.insert("i64".to_string(), BundleValue::I64(1 << 40));
+        map.insert("f64".to_string(), BundleValue::F64(1.5));
+        map.insert("string".to_string(), BundleValue::String("value".into()));
+        map.insert("bytes".to_string(), BundleValue::Bytes(vec![1, 2, 3]));
+        map.insert(
+            "list".to_string(),
+            BundleValue::List(vec![
+                BundleValue::I32(1),
+                BundleValue::String("two".into()),
+                BundleValue::Map(nested.clone()),
+            ]),
+        );
+        map.insert("map".to_string(), BundleValue::Map(nested));
 
-        let arc = Arc::new(Mutex::new(0));
-        let arc2 = arc.clone();
+        let bundle = map_to_bundle(&env, &map).unwrap();
+        let value = env
+            .call_method(
+                bundle,
+                "getInt",
+                "(Ljava/lang/String;)I",
+                &[JObject::from(env.new_string("i32").unwrap()).into()],
+            )
+            .unwrap()
+            .i()
+            .unwrap();
+        assert_eq!(value, -5);
+        assert_eq!(bundle_to_map(&env, bundle).unwrap(), map);
 
-        let closure = async move {
-            {
-                let mut guard = arc2.lock().unwrap();
-                *guard = 1;
-            }
-            receiver.await.unwrap();
-            {
-                let mut guard = arc2.lock().unwrap();
-                *guard = 2;
-            }
+        let bundle = env.new_object("android/os/Bundle", "()V", &[]).unwrap();
+        let key = env.new_string("ints").unwrap();
+        let ints = env.new_int_array(2).unwrap();
+        env.set_int_array_region(ints, 0, &[3, 4]).unwrap();
+        env.call_method(
+            bundle,
+            "putIntArray",
+            "(Ljava/lang/String;[I)V",
+            &[key.into(), JObject::from(ints).into()],
+        )
+        .unwrap();
+        let key = env.new_string("float").unwrap();
+        env.call_method(
+            bundle,
+            "putFloat",
+            "(Ljava/lang/String;F)V",
+            &[key.into(), 0.25f32.into()],
+        )
+        .unwrap();
+        let map = bundle_to_map(&env, bundle).unwrap();
+        assert_eq!(
+            map["ints"],
+            BundleValue::List(vec![BundleValue::I32(3), BundleValue::I32(4)])
+        );
+        assert_eq!(map["float"], BundleValue::F64(0.25));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testPersistableBundle(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{
+            bundle::{bundle_to_map, map_to_bundle, BundleValue},
+            JPersistableBundle,
         };
+        use std::collections::HashMap;
 
-        let handler_spawn = handler.spawner();
+        let mut nested = HashMap::new();
+        nested.insert("inner".to_string(), BundleValue::F64(0.5));
 
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
-        handler_spawn.spawn(closure).unwrap();
-        {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 0);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
+        let mut map = HashMap::new();
+        map.insert("bool".to_string(), BundleValue::Bool(true));
+        map.insert("i32".to_string(), BundleValue::I32(-5));
+        map.insert("i64".to_string(), BundleValue::I64(1 << 40));
+        map.insert("string".to_string(), BundleValue::String("value".into()));
+        map.insert(
+            "strings".to_string(),
+            BundleValue::List(vec![
+                BundleValue::String("one".into()),
+                BundleValue::String("two".into()),
+            ]),
         );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
-        {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 1);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
+        map.insert(
+            "longs".to_string(),
+            BundleValue::List(vec![BundleValue::I64(1), BundleValue::I64(2)]),
         );
+        map.insert("map".to_string(), BundleValue::Map(nested));
 
-        std::thread::spawn(|| {
-            sender.send(()).unwrap();
-        })
-        .join()
-        .unwrap();
+        let persistable = JPersistableBundle::from_map(&env, &map).unwrap();
+        assert_eq!(persistable.to_map().unwrap(), map);
 
-        // Wait until after the Runnable has posted. This is admittedly a bit
-        // racy.
-        std::thread::sleep(Duration::from_millis(500));
+        let bundle = persistable.to_bundle().unwrap();
+        assert!(env.is_instance_of(bundle, "android/os/Bundle").unwrap());
+        assert_eq!(bundle_to_map(&env, bundle).unwrap(), map);
 
-        {
-            assert_eq!(Arc::strong_count(&arc), 2);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 1);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
-        );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
-        {
-            assert_eq!(Arc::strong_count(&arc), 1);
-            let guard = arc.lock().unwrap();
-            assert_eq!(*guard, 2);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
+        let mut unsupported = map.clone();
+        unsupported.insert("bytes".to_string(), BundleValue::Bytes(vec![1, 2, 3]));
+        unsupported.insert(
+            "mixed".to_string(),
+            BundleValue::List(vec![BundleValue::I32(1), BundleValue::String("two".into())]),
         );
+        let bundle = map_to_bundle(&env, &unsupported).unwrap();
+        let persistable = JPersistableBundle::from_bundle(&env, bundle).unwrap();
+        assert_eq!(persistable.to_map().unwrap(), map);
+        assert!(!persistable
+            .put("bytes", &BundleValue::Bytes(vec![]))
+            .unwrap());
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnThread(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testIntentUri(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::task::SpawnExt;
-
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
-        let handler_ref = env.new_global_ref(handler).unwrap();
-
-        let runnable = fn_once_runnable(&env, move |env, _obj| {
-            let handler = JHandler::from_env(env, handler_ref.as_obj()).unwrap();
-            let handler_spawn = handler.spawner();
+        use android_utils::content::{
+            check_intent, intent_from_uri, intent_to_uri, sanitize_intent, UnsafeIntent,
+            URI_INTENT_SCHEME,
+        };
 
-            handler_spawn.spawn(async {}).unwrap();
-        })
-        .unwrap();
-        let thread = env
-            .new_object(
-                "java/lang/Thread",
-                "(Ljava/lang/Runnable;)V",
-                &[runnable.into()],
+        let uri =
+            "intent://example.com/path#Intent;scheme=https;action=android.intent.action.VIEW;end";
+        let intent = intent_from_uri(&env, uri, URI_INTENT_SCHEME).unwrap();
+        assert_eq!(check_intent(&env, intent).unwrap(), Ok(()));
+        let formatted = intent_to_uri(&env, intent, URI_INTENT_SCHEME).unwrap();
+        let intent2 = intent_from_uri(&env, &formatted, URI_INTENT_SCHEME).unwrap();
+        assert!(env
+            .call_method(
+                intent,
+                "filterEquals",
+                "(Landroid/content/Intent;)Z",
+                &[intent2.into()],
             )
-            .unwrap();
-        env.call_method(thread, "start", "()V", &[]).unwrap();
-        env.call_method(thread, "join", "()V", &[]).unwrap();
+            .unwrap()
+            .z()
+            .unwrap());
 
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
+        let uri = "intent:#Intent;component=com.example/.Secret;launchFlags=0x1;end";
+        let intent = intent_from_uri(&env, uri, URI_INTENT_SCHEME).unwrap();
+        assert_eq!(
+            check_intent(&env, intent).unwrap(),
+            Err(UnsafeIntent::Component)
+        );
+        sanitize_intent(&env, intent).unwrap();
+        assert_eq!(check_intent(&env, intent).unwrap(), Ok(()));
+
+        assert!(intent_from_uri(
+            &env,
+            "intent:#Intent;launchFlags=zzz;end",
+            URI_INTENT_SCHEME
+        )
+        .is_err());
+        env.exception_clear().unwrap();
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnLocal(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testRouter(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::{channel::oneshot::channel, task::LocalSpawnExt};
-        use std::{cell::RefCell, rc::Rc};
-
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
-
-        let (sender, receiver) = channel::<()>();
+        use android_utils::content::router::{RouteValue, Router};
 
-        let rc = Rc::new(RefCell::new(0));
-        let rc2 = rc.clone();
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let hits1 = hits.clone();
+        let hits2 = hits.clone();
+        let router = Router::new()
+            .route("myapp://users/{id:int}", move |_env, _intent, params| {
+                hits1.lock().unwrap().push((
+                    params.get("id").cloned(),
+                    params.query("tab").map(String::from),
+                ));
+                Ok(())
+            })
+            .route(
+                "*://example.com/posts/{slug}",
+                move |_env, _intent, params| {
+                    hits2
+                        .lock()
+                        .unwrap()
+                        .push((params.get("slug").cloned(), None));
+                    Ok(())
+                },
+            );
 
-        let closure = async move {
-            {
-                let mut guard = rc2.borrow_mut();
-                *guard = 1;
-            }
-            receiver.await.unwrap();
-            {
-                let mut guard = rc2.borrow_mut();
-                *guard = 2;
-            }
+        let intent = |uri: &str| {
+            let uri = env.new_string(uri).unwrap();
+            let uri = env
+                .call_static_method(
+                    "android/net/Uri",
+                    "parse",
+                    "(Ljava/lang/String;)Landroid/net/Uri;",
+                    &[uri.into()],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            let action = env.new_string("android.intent.action.VIEW").unwrap();
+            env.new_object(
+                "android/content/Intent",
+                "(Ljava/lang/String;Landroid/net/Uri;)V",
+                &[action.into(), uri.into()],
+            )
+            .unwrap()
         };
 
-        let handler_spawn = handler.spawner();
-
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
-        handler_spawn.spawn_local(closure).unwrap();
-        {
-            assert_eq!(Rc::strong_count(&rc), 2);
-            let guard = rc.borrow();
-            assert_eq!(*guard, 0);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
-        );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
-        {
-            assert_eq!(Rc::strong_count(&rc), 2);
-            let guard = rc.borrow();
-            assert_eq!(*guard, 1);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
-        );
-        sender.send(()).unwrap();
-        {
-            assert_eq!(Rc::strong_count(&rc), 2);
-            let guard = rc.borrow();
-            assert_eq!(*guard, 1);
-        }
-        assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            false
-        );
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+        assert!(router
+            .dispatch(&env, intent("MyApp://users/42?tab=posts"))
+            .unwrap());
+        assert!(router
+            .dispatch(&env, intent("https://example.com/posts/hello%20world"))
+            .unwrap());
+        assert!(!router.dispatch(&env, intent("myapp://users/abc")).unwrap());
+        assert!(!router
+            .dispatch(&env, intent("myapp://users/42/extra"))
+            .unwrap());
+        let empty = env
+            .new_object("android/content/Intent", "()V", &[])
             .unwrap();
-        {
-            assert_eq!(Rc::strong_count(&rc), 1);
-            let guard = rc.borrow();
-            assert_eq!(*guard, 2);
-        }
+        assert!(!router.dispatch(&env, empty).unwrap());
+
         assert_eq!(
-            env.call_method(shadow_looper, "isIdle", "()Z", &[])
-                .unwrap()
-                .z()
-                .unwrap(),
-            true
+            *hits.lock().unwrap(),
+            vec![
+                (Some(RouteValue::Int(42)), Some("posts".to_string())),
+                (Some(RouteValue::Str("hello world".to_string())), None),
+            ]
         );
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnLocalThread(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testProcessState(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::task::LocalSpawnExt;
-
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
-        let handler_ref = env.new_global_ref(handler).unwrap();
+        use android_utils::app::{process_state, IMPORTANCE_GONE};
 
-        let runnable = fn_once_runnable(&env, move |env, _obj| {
-            let handler = JHandler::from_env(env, handler_ref.as_obj()).unwrap();
-            let handler_spawn = handler.spawner();
-
-            handler_spawn.spawn_local(async {}).unwrap();
-        })
-        .unwrap();
-        let thread = env
-            .new_object(
-                "java/lang/Thread",
-                "(Ljava/lang/Runnable;)V",
-                &[runnable.into()],
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
             )
+            .unwrap()
+            .l()
             .unwrap();
-        env.call_method(thread, "start", "()V", &[]).unwrap();
-        env.call_method(thread, "join", "()V", &[]).unwrap();
-
-        let result = try_block(&env, || {
-            env.call_method(shadow_looper, "runOneTask", "()V", &[])?;
-            Ok(false)
-        })
-        .catch(
-            "io/github/gedgygedgy/rust/thread/LocalThreadException",
-            |_ex| Ok(true),
-        )
-        .result();
-        assert_eq!(result.unwrap(), true);
+        let state = process_state(&env, context).unwrap();
+        assert!(state.importance < IMPORTANCE_GONE);
+        assert!(!state.background_restricted);
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testRustHandlerCallback(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testExitReasons(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::task::SpawnExt;
-
-        let handler_data = Arc::new(Mutex::new(None));
-
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        use android_utils::app::{exit_reasons, REASON_CRASH, REASON_LOW_MEMORY};
 
-        let (callback, stream) = async_handler_callback(&env).unwrap();
-        let mut stream = JSendStream::try_from(stream).unwrap();
-
-        let handler_data_clone = handler_data.clone();
-        let task = async move {
-            let msg = stream.next().await.unwrap().unwrap();
-            let mut guard = handler_data_clone.lock().unwrap();
-            *guard = Some(msg);
-        };
-
-        handler.spawner().spawn(task).unwrap();
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
-        {
-            let guard = handler_data.lock().unwrap();
-            assert!(guard.is_none());
-        }
-
-        let message = env
+        let context = env
             .call_static_method(
-                "android/os/Message",
-                "obtain",
-                "()Landroid/os/Message;",
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
                 &[],
             )
             .unwrap()
             .l()
             .unwrap();
-        let result = env
+        assert!(exit_reasons(&env, context, 0).unwrap().is_empty());
+
+        let name = env.new_string("activity").unwrap();
+        let manager = env
             .call_method(
-                callback,
-                "handleMessage",
-                "(Landroid/os/Message;)Z",
-                &[message.into()],
+                context,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[name.into()],
             )
             .unwrap()
-            .z()
+            .l()
             .unwrap();
-        assert_eq!(result, false);
-
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+        let shadow_manager = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[manager.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        for (process, pid, reason, status) in [
+            ("io.github.gedgygedgy", 100, REASON_CRASH, 1),
+            ("io.github.gedgygedgy:remote", 200, REASON_LOW_MEMORY, 0),
+        ] {
+            let process = env.new_string(process).unwrap();
+            env.call_method(
+                shadow_manager,
+                "addApplicationExitInfo",
+                "(Ljava/lang/String;III)V",
+                &[process.into(), pid.into(), reason.into(), status.into()],
+            )
             .unwrap();
-        {
-            let guard = handler_data.lock().unwrap();
-            assert!(env
-                .is_same_object(message, guard.as_ref().unwrap().as_obj())
-                .unwrap());
         }
+
+        let mut reasons = exit_reasons(&env, context, 0).unwrap();
+        reasons.sort_by_key(|info| info.pid);
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons[0].pid, 100);
+        assert_eq!(reasons[0].reason, REASON_CRASH);
+        assert_eq!(reasons[0].status, 1);
+        assert_eq!(reasons[0].process_name, "io.github.gedgygedgy");
+        assert_eq!(reasons[0].trace, None);
+        assert_eq!(reasons[1].pid, 200);
+        assert_eq!(reasons[1].reason, REASON_LOW_MEMORY);
+        assert_eq!(reasons[1].process_name, "io.github.gedgygedgy:remote");
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustServiceConnection(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testUsageStats(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        use futures::task::SpawnExt;
-
-        let (conn, mut stream) = async_service_connection(&env).unwrap();
+        use android_utils::app::usage::{standby_bucket, usage_events, STANDBY_BUCKET_ACTIVE};
 
-        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
-
-        let pkg = env.new_string("io.github.gedgygedgy.rust.android").unwrap();
-        let cls = env
-            .new_string("io.github.gedgygedgy.rust.android.ServiceTest$TestService")
-            .unwrap();
-        let component_name = env
-            .new_object(
-                "android/content/ComponentName",
-                "(Ljava/lang/String;Ljava/lang/String;)V",
-                &[pkg.into(), cls.into()],
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
             )
+            .unwrap()
+            .l()
             .unwrap();
-        let component_name_ref = env.new_global_ref(component_name).unwrap();
+        assert_eq!(
+            standby_bucket(&env, context).unwrap(),
+            Some(STANDBY_BUCKET_ACTIVE)
+        );
+        assert!(usage_events(&env, context, 0, i64::MAX).unwrap().is_empty());
+    });
+}
 
-        let messenger = env
-            .new_object(
-                "android/os/Messenger",
-                "(Landroid/os/Handler;)V",
-                &[handler.clone().into()],
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testScreenEvents(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{screen_events, ScreenEvent};
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
             )
-            .unwrap();
-        let service = env
-            .call_method(messenger, "getBinder", "()Landroid/os/IBinder;", &[])
             .unwrap()
             .l()
             .unwrap();
-        let service_ref = env.new_global_ref(service).unwrap();
+        let mut stream = screen_events(&env, context).unwrap();
 
-        let vm = env.get_java_vm().unwrap();
-        let finished = Arc::new(Mutex::new(false));
-        let finished_clone = finished.clone();
-        let task = async move {
-            let item = stream.next().await.unwrap().unwrap();
-            if let ServiceConnectionEvent::BindingDied { component_name } = item {
-                let env = vm.get_env().unwrap();
-                assert!(env
-                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
-                    .unwrap());
-            } else {
-                panic!("Expected BindingDied");
-            }
+        for action in &[
+            "android.intent.action.SCREEN_OFF",
+            "android.intent.action.USER_PRESENT",
+        ] {
+            let action = env.new_string(action).unwrap();
+            let intent = env
+                .new_object(
+                    "android/content/Intent",
+                    "(Ljava/lang/String;)V",
+                    &[action.into()],
+                )
+                .unwrap();
+            env.call_method(
+                context,
+                "sendBroadcast",
+                "(Landroid/content/Intent;)V",
+                &[intent.into()],
+            )
+            .unwrap();
+        }
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
 
-            let item = stream.next().await.unwrap().unwrap();
-            if let ServiceConnectionEvent::NullBinding { component_name } = item {
-                let env = vm.get_env().unwrap();
-                assert!(env
-                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
-                    .unwrap());
-            } else {
-                panic!("Expected NullBinding");
-            }
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), ScreenEvent::Off);
+        assert_eq!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ScreenEvent::UserPresent
+        );
+    });
+}
 
-            let item = stream.next().await.unwrap().unwrap();
-            if let ServiceConnectionEvent::ServiceConnected {
-                component_name,
-                service,
-            } = item
-            {
-                let env = vm.get_env().unwrap();
-                assert!(env
-                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
-                    .unwrap());
-                assert!(env
-                    .is_same_object(service.as_obj(), service_ref.as_obj())
-                    .unwrap());
-            } else {
-                panic!("Expected ServiceConnected");
-            }
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testLocales(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::content::locales;
 
-            let item = stream.next().await.unwrap().unwrap();
-            if let ServiceConnectionEvent::ServiceDisconnected { component_name } = item {
-                let env = vm.get_env().unwrap();
-                assert!(env
-                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
-                    .unwrap());
-            } else {
-                panic!("Expected ServiceDisconnected");
-            }
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(locales(&env, context).unwrap(), vec!["en-US".to_string()]);
+    });
+}
 
-            let mut guard = finished_clone.lock().unwrap();
-            *guard = true;
-        };
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testTimeEvents(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{time_events, TimeEvent};
+        use futures::executor::block_on;
 
-        handler.spawner().spawn(task).unwrap();
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let mut stream = time_events(&env, context).unwrap();
 
+        let action = env
+            .new_string("android.intent.action.TIMEZONE_CHANGED")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Ljava/lang/String;)V",
+                &[action.into()],
+            )
+            .unwrap();
+        let name = env.new_string("time-zone").unwrap();
+        let zone = env.new_string("Europe/Paris").unwrap();
         env.call_method(
-            conn,
-            "onBindingDied",
-            "(Landroid/content/ComponentName;)V",
-            &[component_name.into()],
-        )
-        .unwrap();
-        env.call_method(
-            conn,
-            "onNullBinding",
-            "(Landroid/content/ComponentName;)V",
-            &[component_name.into()],
-        )
-        .unwrap();
-        env.call_method(
-            conn,
-            "onServiceConnected",
-            "(Landroid/content/ComponentName;Landroid/os/IBinder;)V",
-            &[component_name.into(), service.into()],
+            intent,
+            "putExtra",
+            "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/Intent;",
+            &[name.into(), zone.into()],
         )
         .unwrap();
         env.call_method(
-            conn,
-            "onServiceDisconnected",
-            "(Landroid/content/ComponentName;)V",
-            &[component_name.into()],
+            context,
+            "sendBroadcast",
+            "(Landroid/content/Intent;)V",
+            &[intent.into()],
         )
         .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
 
-        env.call_method(shadow_looper, "runOneTask", "()V", &[])
-            .unwrap();
-        {
-            let guard = finished.lock().unwrap();
-            assert!(*guard);
-        }
+        assert_eq!(
+            block_on(stream.next()).unwrap().unwrap(),
+            TimeEvent::TimeZoneChanged {
+                zone_id: Some("Europe/Paris".to_string())
+            }
+        );
     });
 }
 
 #[no_mangle]
-pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustService(
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBinder(
     env: JNIEnv,
     _obj: JObject,
 ) {
     let _ = throw_unwind(&env, || {
-        struct TestServiceData {
-            created: bool,
-            binder: GlobalRef,
-            intent: Option<GlobalRef>,
-            rebound: bool,
-            start_flags: Option<jint>,
-            start_id: Option<jint>,
-        }
+        use android_utils::os::{JBinder, JParcel, FIRST_CALL_TRANSACTION};
+        use futures::FutureExt;
 
-        struct TestService(Arc<Mutex<TestServiceData>>);
+        let obj = env.new_object("android/os/Binder", "()V", &[]).unwrap();
+        let binder = JBinder::from_env(&env, obj).unwrap();
+        assert!(binder.ping_binder().unwrap());
+        assert!(binder.is_binder_alive().unwrap());
+        assert_eq!(binder.interface_descriptor().unwrap(), None);
 
-        impl Drop for TestService {
-            fn drop(&mut self) {
-                let mut guard = self.0.lock().unwrap();
-                guard.created = false;
-            }
-        }
+        let data = JParcel::obtain(&env).unwrap();
+        assert!(binder
+            .transact(FIRST_CALL_TRANSACTION, &data, 0)
+            .unwrap()
+            .is_none());
+        data.recycle().unwrap();
 
-        impl RustService for TestService {
-            fn on_bind<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, intent: JObject<'a>) -> JObject<'a> {
-                let mut guard = self.0.lock().unwrap();
-                guard.intent = Some(env.new_global_ref(intent).unwrap());
-                guard.binder.as_obj().into_inner().into()
-            }
+        let mut death = Box::pin(binder.death_notifications().unwrap());
+        assert!(death.as_mut().now_or_never().is_none());
+        drop(death);
+    });
+}
 
-            fn on_unbind<'a: 'b, 'b>(&self, _env: &'b JNIEnv<'a>, _intent: JObject<'a>) -> bool {
-                let mut guard = self.0.lock().unwrap();
-                guard.intent = None;
-                true
-            }
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testParcel(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::JParcel;
 
-            fn on_rebind<'a: 'b, 'b>(&self, env: &'b JNIEnv<'a>, intent: JObject<'a>) {
-                let mut guard = self.0.lock().unwrap();
-                guard.intent = Some(env.new_global_ref(intent).unwrap());
-                guard.rebound = true;
-            }
+        let binder = env.new_object("android/os/Binder", "()V", &[]).unwrap();
+        let parcel = JParcel::obtain(&env).unwrap();
+        parcel.write_int(42).unwrap();
+        parcel.write_long(-7).unwrap();
+        parcel.write_float(1.5).unwrap();
+        parcel.write_double(2.25).unwrap();
+        parcel.write_string(Some("hello")).unwrap();
+        parcel.write_string(None).unwrap();
+        parcel.write_byte_array(Some(&[1, 2, 3])).unwrap();
+        parcel.write_byte_array(None).unwrap();
+        parcel.write_binder(binder).unwrap();
 
-            fn on_start_command<'a: 'b, 'b>(
+        parcel.set_data_position(0).unwrap();
+        assert_eq!(parcel.read_int().unwrap(), 42);
+        assert_eq!(parcel.read_long().unwrap(), -7);
+        assert_eq!(parcel.read_float().unwrap(), 1.5);
+        assert_eq!(parcel.read_double().unwrap(), 2.25);
+        assert_eq!(parcel.read_string().unwrap().as_deref(), Some("hello"));
+        assert_eq!(parcel.read_string().unwrap(), None);
+        assert_eq!(parcel.read_byte_array().unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(parcel.read_byte_array().unwrap(), None);
+        assert!(env
+            .is_same_object(parcel.read_binder().unwrap(), binder)
+            .unwrap());
+        parcel.recycle().unwrap();
+
+        let parcel = JParcel::obtain(&env).unwrap();
+        parcel.write_int(5).unwrap();
+        parcel.write_string(Some("marshalled")).unwrap();
+        let bytes = parcel.marshall().unwrap();
+        parcel.recycle().unwrap();
+
+        let parcel = JParcel::from_bytes(&env, &bytes).unwrap();
+        assert_eq!(parcel.data_size().unwrap() as usize, bytes.len());
+        assert_eq!(parcel.read_int().unwrap(), 5);
+        assert_eq!(parcel.read_string().unwrap().as_deref(), Some("marshalled"));
+        parcel.recycle().unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testParcelSerde(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{
+            parcel_serde::{from_parcel, to_parcel, Error},
+            JParcel,
+        };
+        use serde::{Deserialize, Serialize};
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Rect { width: u16, height: u16 },
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Drawing {
+            name: String,
+            layer: i8,
+            opacity: u8,
+            id: u32,
+            size: u64,
+            marker: char,
+            parent: Option<i64>,
+            shapes: Vec<Shape>,
+            tags: BTreeMap<String, bool>,
+        }
+
+        let drawing = Drawing {
+            name: "sketch".into(),
+            layer: -3,
+            opacity: 255,
+            id: u32::MAX,
+            size: u64::MAX,
+            marker: 'ß',
+            parent: None,
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(0.5),
+                Shape::Rect {
+                    width: 640,
+                    height: 480,
+                },
+            ],
+            tags: vec![("draft".to_string(), true)].into_iter().collect(),
+        };
+
+        let parcel = JParcel::obtain(&env).unwrap();
+        to_parcel(&parcel, &drawing).unwrap();
+        parcel.set_data_position(0).unwrap();
+        assert_eq!(from_parcel::<Drawing>(&parcel).unwrap(), drawing);
+        parcel.recycle().unwrap();
+
+        let parcel = JParcel::obtain(&env).unwrap();
+        parcel.write_int(256).unwrap();
+        parcel.write_int(-129).unwrap();
+        parcel.write_int(-1).unwrap();
+        parcel.write_int(7).unwrap();
+        parcel.write_int(0xD800).unwrap();
+        parcel.set_data_position(0).unwrap();
+        assert!(matches!(from_parcel::<u8>(&parcel), Err(Error::Message(_))));
+        assert!(matches!(from_parcel::<i8>(&parcel), Err(Error::Message(_))));
+        assert!(matches!(
+            from_parcel::<Shape>(&parcel),
+            Err(Error::Message(_))
+        ));
+        assert!(matches!(
+            from_parcel::<Shape>(&parcel),
+            Err(Error::Message(_))
+        ));
+        assert!(matches!(
+            from_parcel::<char>(&parcel),
+            Err(Error::Message(_))
+        ));
+        parcel.recycle().unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBundleSerde(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::bundle_serde::{from_bundle, to_bundle, Error};
+        use serde::{Deserialize, Serialize};
+        use std::collections::HashMap;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Rect { width: u16, height: u16 },
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Settings {
+            name: String,
+            level: u8,
+            id: u32,
+            parent: Option<i64>,
+            owner: Option<String>,
+            shapes: HashMap<String, Shape>,
+            limits: HashMap<String, HashMap<String, i32>>,
+        }
+
+        let settings = Settings {
+            name: "sketch".into(),
+            level: 200,
+            id: u32::MAX,
+            parent: None,
+            owner: Some("me".into()),
+            shapes: vec![
+                ("origin".to_string(), Shape::Point),
+                ("dot".to_string(), Shape::Circle(0.5)),
+                (
+                    "frame".to_string(),
+                    Shape::Rect {
+                        width: 640,
+                        height: 480,
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            limits: vec![(
+                "cpu".to_string(),
+                vec![("min".to_string(), -1), ("max".to_string(), 8)]
+                    .into_iter()
+                    .collect(),
+            )]
+            .into_iter()
+            .collect(),
+        };
+
+        let bundle = to_bundle(&env, &settings).unwrap();
+        assert_eq!(from_bundle::<Settings>(&env, bundle).unwrap(), settings);
+
+        #[derive(Debug, Deserialize)]
+        struct Level {
+            #[allow(dead_code)]
+            level: u8,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Id {
+            #[allow(dead_code)]
+            id: u32,
+        }
+
+        let bundle = env.new_object("android/os/Bundle", "()V", &[]).unwrap();
+        let key = env.new_string("level").unwrap();
+        env.call_method(
+            bundle,
+            "putInt",
+            "(Ljava/lang/String;I)V",
+            &[key.into(), 300.into()],
+        )
+        .unwrap();
+        let key = env.new_string("id").unwrap();
+        env.call_method(
+            bundle,
+            "putLong",
+            "(Ljava/lang/String;J)V",
+            &[key.into(), (-1i64).into()],
+        )
+        .unwrap();
+        assert!(matches!(
+            from_bundle::<Level>(&env, bundle),
+            Err(Error::Message(_))
+        ));
+        assert!(matches!(
+            from_bundle::<Id>(&env, bundle),
+            Err(Error::Message(_))
+        ));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testFileDescriptors(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{
+            fd::{bundle_take_fd, message_take_fd, parcel_read_fd},
+            JParcel,
+        };
+
+        let bundle = env.new_object("android/os/Bundle", "()V", &[]).unwrap();
+        assert!(bundle_take_fd(&env, bundle, "missing").unwrap().is_none());
+
+        // Parcelables other than ParcelFileDescriptor are left in place.
+        let nested = env.new_object("android/os/Bundle", "()V", &[]).unwrap();
+        let key = env.new_string("nested").unwrap();
+        env.call_method(
+            bundle,
+            "putParcelable",
+            "(Ljava/lang/String;Landroid/os/Parcelable;)V",
+            &[key.into(), nested.into()],
+        )
+        .unwrap();
+        assert!(bundle_take_fd(&env, bundle, "nested").unwrap().is_none());
+        assert!(env
+            .call_method(
+                bundle,
+                "containsKey",
+                "(Ljava/lang/String;)Z",
+                &[key.into()]
+            )
+            .unwrap()
+            .z()
+            .unwrap());
+
+        let message = env
+            .call_static_method(
+                "android/os/Message",
+                "obtain",
+                "()Landroid/os/Message;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(message_take_fd(&env, message, "fd").unwrap().is_none());
+
+        let parcel = JParcel::obtain(&env).unwrap();
+        env.call_method(
+            *parcel,
+            "writeParcelable",
+            "(Landroid/os/Parcelable;I)V",
+            &[JObject::null().into(), 0.into()],
+        )
+        .unwrap();
+        parcel.set_data_position(0).unwrap();
+        assert!(parcel_read_fd(&env, *parcel).unwrap().is_none());
+        parcel.recycle().unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testRustBinder(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{
+            rust_binder, JBinder, JParcel, RustBinder, FIRST_CALL_TRANSACTION,
+        };
+
+        struct Doubler;
+
+        impl RustBinder for Doubler {
+            fn on_transact<'a: 'b, 'b>(
                 &self,
                 _env: &'b JNIEnv<'a>,
-                _intent: JObject<'a>,
-                flags: jint,
-                start_id: jint,
-            ) -> jint {
-                let mut guard = self.0.lock().unwrap();
-                guard.start_id = Some(start_id);
-                guard.start_flags = Some(flags);
-                android_utils::service::START_STICKY
+                code: jint,
+                data: &JParcel<'a, 'b>,
+                reply: &JParcel<'a, 'b>,
+                _flags: jint,
+            ) -> bool {
+                if code != FIRST_CALL_TRANSACTION {
+                    return false;
+                }
+                let value = data.read_int().unwrap();
+                reply.write_int(value * 2).unwrap();
+                true
             }
         }
 
-        let (_shadow_looper, handler) = shadow_looper_and_handler(&env);
-        let messenger = env
-            .new_object(
-                "android/os/Messenger",
-                "(Landroid/os/Handler;)V",
-                &[handler.into()],
-            )
-            .unwrap();
-        let binder = env
-            .call_method(messenger, "getBinder", "()Landroid/os/IBinder;", &[])
+        let obj = rust_binder(&env, Doubler).unwrap();
+        let binder = JBinder::from_env(&env, obj).unwrap();
+
+        let data = JParcel::obtain(&env).unwrap();
+        data.write_int(21).unwrap();
+        data.set_data_position(0).unwrap();
+        let reply = binder
+            .transact(FIRST_CALL_TRANSACTION, &data, 0)
             .unwrap()
-            .l()
             .unwrap();
-        let binder_ref = env.new_global_ref(binder).unwrap();
+        assert_eq!(reply.read_int().unwrap(), 42);
+        reply.recycle().unwrap();
+        assert!(binder
+            .transact(FIRST_CALL_TRANSACTION + 1, &data, 0)
+            .unwrap()
+            .is_none());
 
-        let data = Arc::new(Mutex::new(TestServiceData {
-            created: false,
-            binder: binder_ref.clone(),
-            intent: None,
-            rebound: false,
-            start_flags: None,
-            start_id: None,
-        }));
-        let data_clone = data.clone();
+        env.call_method(obj, "close", "()V", &[]).unwrap();
+        data.set_data_position(0).unwrap();
+        assert!(binder
+            .transact(FIRST_CALL_TRANSACTION, &data, 0)
+            .unwrap()
+            .is_none());
+        data.recycle().unwrap();
+    });
+}
 
-        let factory = move |_env: &JNIEnv, _obj: JObject| {
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testSystemClock(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::system_clock::{elapsed_realtime, sleep, uptime};
+        use std::time::Duration;
+
+        let start = uptime(&env).unwrap();
+        assert!(elapsed_realtime(&env).unwrap() >= start);
+
+        sleep(&env, Duration::from_millis(100)).unwrap();
+        assert!(uptime(&env).unwrap() >= start + Duration::from_millis(100));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testTraceSection(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::trace::section;
+
+        let current_sections = || {
+            let sections = env
+                .call_static_method(
+                    "org/robolectric/shadows/ShadowTrace",
+                    "getCurrentSections",
+                    "()Ljava/util/Deque;",
+                    &[],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            env.call_method(sections, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap()
+        };
+
+        {
+            let _outer = section(&env, "outer").unwrap();
+            assert_eq!(current_sections(), 1);
             {
-                let mut guard = data_clone.lock().unwrap();
-                guard.created = true;
+                let _inner = section(&env, "inner").unwrap();
+                assert_eq!(current_sections(), 2);
             }
-            TestService(data_clone.clone())
-        };
+            assert_eq!(current_sections(), 1);
+        }
+        assert_eq!(current_sections(), 0);
+    });
+}
 
-        let class = env
-            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustService")
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBuildInfo(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::build;
+
+        let sdk_int = env
+            .get_static_field("android/os/Build$VERSION", "SDK_INT", "I")
+            .unwrap()
+            .i()
             .unwrap();
-        register_service(&env, class, factory).unwrap();
+        let info = build::info(&env).unwrap();
+        assert_eq!(info.sdk_int, sdk_int);
+        assert_eq!(build::sdk_int(&env).unwrap(), sdk_int);
+        assert!(std::ptr::eq(info, build::info(&env).unwrap()));
+    });
+}
 
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.created, false);
-            assert!(guard.intent.is_none());
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testProcess(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::process::{
+            my_pid, my_tid, set_thread_priority, thread_priority, THREAD_PRIORITY_BACKGROUND,
+        };
+
+        assert!(my_pid(&env).unwrap() > 0);
+        let tid = my_tid(&env).unwrap();
+        set_thread_priority(&env, THREAD_PRIORITY_BACKGROUND).unwrap();
+        assert_eq!(
+            thread_priority(&env, tid).unwrap(),
+            THREAD_PRIORITY_BACKGROUND
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testDebugMemoryInfo(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::debug::{memory_info, memory_samples};
+        use futures::{executor::block_on, StreamExt};
+        use std::time::Duration;
+
+        let info = memory_info(&env).unwrap();
+        assert!(info.total_pss >= 0);
+        assert!(info.native_heap_allocated >= 0);
+        assert!(info.native_heap_size >= 0);
+
+        let samples = block_on(
+            memory_samples(Duration::from_millis(10))
+                .take(2)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(samples.len(), 2);
+        for sample in samples {
+            let sample = sample.unwrap();
+            assert!(sample.total_pss >= 0);
+            assert!(sample.native_heap_size >= 0);
         }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testThermalStatus(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::thermal::{
+            thermal_events, thermal_status, THERMAL_STATUS_NONE, THERMAL_STATUS_SEVERE,
+        };
+        use futures::{FutureExt, StreamExt};
 
         let context = env
             .call_static_method(
@@ -872,71 +1389,4182 @@ pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustSer
             .unwrap()
             .l()
             .unwrap();
-        let class = env
-            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustService")
+        let name = env.new_string("power").unwrap();
+        let manager = env
+            .call_method(
+                context,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[name.into()],
+            )
+            .unwrap()
+            .l()
             .unwrap();
-        let intent = env
-            .new_object(
-                "android/content/Intent",
-                "(Landroid/content/Context;Ljava/lang/Class;)V",
-                &[context.into(), class.into()],
+        let shadow_manager = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[manager.into()],
             )
+            .unwrap()
+            .l()
             .unwrap();
+        assert_eq!(
+            thermal_status(&env, context).unwrap(),
+            Some(THERMAL_STATUS_NONE)
+        );
 
-        let service = env.new_object(class, "()V", &[]).unwrap();
-
-        let service_controller = env.call_static_method(
-            "org/robolectric/android/controller/ServiceController",
-            "of",
-            "(Landroid/app/Service;Landroid/content/Intent;)Lorg/robolectric/android/controller/ServiceController;",
-            &[service.into(), intent.into()],
-        )
-           .unwrap().l().unwrap();
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.created, false);
-            assert!(guard.intent.is_none());
-        }
-
+        let mut stream = Box::pin(thermal_events(&env, context).unwrap().unwrap());
         env.call_method(
-            service_controller,
-            "create",
-            "()Lorg/robolectric/android/controller/ServiceController;",
-            &[],
+            shadow_manager,
+            "setThermalStatus",
+            "(I)V",
+            &[THERMAL_STATUS_SEVERE.into()],
         )
         .unwrap();
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.created, true);
-            assert!(guard.intent.is_none());
-            assert!(guard.start_id.is_none());
-        }
+        assert_eq!(
+            thermal_status(&env, context).unwrap(),
+            Some(THERMAL_STATUS_SEVERE)
+        );
 
-        env.call_method(
-            service_controller,
-            "bind",
-            "()Lorg/robolectric/android/controller/ServiceController;",
-            &[],
-        )
-        .unwrap();
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.created, true);
-            assert!(env
-                .is_same_object(guard.intent.as_ref().unwrap(), intent)
-                .unwrap());
-            assert_eq!(guard.rebound, false);
-            assert!(guard.start_id.is_none());
+        // Depending on the platform, the status at registration time may be
+        // reported first, so only check the most recent one.
+        let mut last = None;
+        while let Some(Some(status)) = stream.next().now_or_never() {
+            last = Some(status.unwrap());
         }
+        assert_eq!(last, Some(THERMAL_STATUS_SEVERE));
 
+        // Dropping the stream removes the listener.
+        std::mem::drop(stream);
         env.call_method(
-            service_controller,
-            "unbind",
-            "()Lorg/robolectric/android/controller/ServiceController;",
-            &[],
+            shadow_manager,
+            "setThermalStatus",
+            "(I)V",
+            &[THERMAL_STATUS_NONE.into()],
         )
         .unwrap();
-        {
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testPerformanceHintSession(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{build, perf_hint::Session, process::my_tid};
+        use std::time::Duration;
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let tid = my_tid(&env).unwrap();
+        let session = Session::new(&env, context, &[tid], Duration::from_millis(16)).unwrap();
+        if build::sdk_int(&env).unwrap() < 31 {
+            assert!(session.is_none());
+            return;
+        }
+
+        // Devices without performance hint support still return None.
+        if let Some(session) = session {
+            session
+                .update_target_work_duration(&env, Duration::from_millis(8))
+                .unwrap();
+            session
+                .report_actual_work_duration(&env, Duration::from_millis(5))
+                .unwrap();
+            let timer = session.start_work();
+            std::thread::sleep(Duration::from_millis(1));
+            assert!(timer.elapsed() >= Duration::from_millis(1));
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testStrictModePolicy(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::strict_mode::{set_thread_policy, ThreadPolicy};
+
+        let policy_string = || {
+            let policy = env
+                .call_static_method(
+                    "android/os/StrictMode",
+                    "getThreadPolicy",
+                    "()Landroid/os/StrictMode$ThreadPolicy;",
+                    &[],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            let string = env
+                .call_method(policy, "toString", "()Ljava/lang/String;", &[])
+                .unwrap()
+                .l()
+                .unwrap();
+            String::from(env.get_string(string.into()).unwrap())
+        };
+
+        set_thread_policy(&env, &ThreadPolicy::default()).unwrap();
+        let lax = policy_string();
+        set_thread_policy(
+            &env,
+            &ThreadPolicy {
+                detect_disk_reads: true,
+                penalty_log: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_ne!(policy_string(), lax);
+
+        set_thread_policy(&env, &ThreadPolicy::default()).unwrap();
+        assert_eq!(policy_string(), lax);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testStrictModeViolations(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::strict_mode::{set_thread_policy, violations, ThreadPolicy};
+        use futures::{executor::block_on, FutureExt};
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let mut stream = Box::pin(violations(&env).unwrap().unwrap());
+        assert!(stream.next().now_or_never().is_none());
+
+        let name = env.new_string("testStrictModeViolations").unwrap();
+        env.call_static_method(
+            "android/os/StrictMode",
+            "noteSlowCall",
+            "(Ljava/lang/String;)V",
+            &[name.into()],
+        )
+        .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        let violation = block_on(stream.next()).unwrap().unwrap();
+        assert_eq!(
+            violation.class_name,
+            "android.os.strictmode.CustomViolation"
+        );
+        assert!(violation
+            .message
+            .unwrap()
+            .contains("testStrictModeViolations"));
+        assert!(violation.stack.contains("noteSlowCall"));
+
+        set_thread_policy(&env, &ThreadPolicy::default()).unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testVibrator(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::hardware::vibrator::{has_vibrator, vibrate};
+        use std::time::Duration;
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(has_vibrator(&env, context).unwrap());
+        vibrate(&env, context, Duration::from_millis(100)).unwrap();
+
+        let vibrator = env
+            .call_method(
+                context,
+                "getSystemService",
+                "(Ljava/lang/Class;)Ljava/lang/Object;",
+                &[env.find_class("android/os/Vibrator").unwrap().into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[vibrator.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(env
+            .call_method(shadow, "isVibrating", "()Z", &[])
+            .unwrap()
+            .z()
+            .unwrap());
+        assert_eq!(
+            env.call_method(shadow, "getMilliseconds", "()J", &[])
+                .unwrap()
+                .j()
+                .unwrap(),
+            100
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testSensors(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::hardware::sensors::{
+            step_count, trigger, TYPE_SIGNIFICANT_MOTION, TYPE_STEP_COUNTER,
+        };
+        use futures::FutureExt;
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(trigger(&env, context, TYPE_SIGNIFICANT_MOTION)
+            .unwrap()
+            .is_none());
+        assert!(step_count(&env, context).unwrap().is_none());
+
+        let name = env.new_string("sensor").unwrap();
+        let manager = env
+            .call_method(
+                context,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[name.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow_manager = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[manager.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let sensor = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowSensor",
+                "newInstance",
+                "(I)Landroid/hardware/Sensor;",
+                &[TYPE_STEP_COUNTER.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            shadow_manager,
+            "addSensor",
+            "(Landroid/hardware/Sensor;)V",
+            &[sensor.into()],
+        )
+        .unwrap();
+
+        let mut count = Box::pin(step_count(&env, context).unwrap().unwrap());
+        assert!((&mut count).now_or_never().is_none());
+
+        let event = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowSensorManager",
+                "createSensorEvent",
+                "(I)Landroid/hardware/SensorEvent;",
+                &[1.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.set_field(event, "sensor", "Landroid/hardware/Sensor;", sensor.into())
+            .unwrap();
+        env.set_field(event, "timestamp", "J", 1234i64.into())
+            .unwrap();
+        let values = env.get_field(event, "values", "[F").unwrap().l().unwrap();
+        env.set_float_array_region(values.into_inner(), 0, &[42.0])
+            .unwrap();
+        env.call_method(
+            shadow_manager,
+            "sendSensorEventToListeners",
+            "(Landroid/hardware/SensorEvent;)V",
+            &[event.into()],
+        )
+        .unwrap();
+
+        let count = count.now_or_never().unwrap().unwrap();
+        assert_eq!(count.steps, 42);
+        assert_eq!(count.timestamp, 1234);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testProximityAlert(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::location::{add_proximity_alert, GeofenceEvent};
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow_application = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[context.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let permissions = env
+            .new_object_array(
+                1,
+                "java/lang/String",
+                env.new_string("android.permission.ACCESS_FINE_LOCATION")
+                    .unwrap(),
+            )
+            .unwrap();
+        env.call_method(
+            shadow_application,
+            "grantPermissions",
+            "([Ljava/lang/String;)V",
+            &[JObject::from(permissions).into()],
+        )
+        .unwrap();
+
+        // Find the action of the receiver registered for the alert.
+        let alert_action = || -> Option<JObject> {
+            let receivers = env
+                .call_method(
+                    shadow_application,
+                    "getRegisteredReceivers",
+                    "()Ljava/util/List;",
+                    &[],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            let size = env
+                .call_method(receivers, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap();
+            (0..size).find_map(|i| {
+                let wrapper = env
+                    .call_method(receivers, "get", "(I)Ljava/lang/Object;", &[i.into()])
+                    .unwrap()
+                    .l()
+                    .unwrap();
+                let receiver = env
+                    .get_field(
+                        wrapper,
+                        "broadcastReceiver",
+                        "Landroid/content/BroadcastReceiver;",
+                    )
+                    .unwrap()
+                    .l()
+                    .unwrap();
+                if !env
+                    .is_instance_of(
+                        receiver,
+                        "io/github/gedgygedgy/rust/android/location/RustProximityAlertReceiver",
+                    )
+                    .unwrap()
+                {
+                    return None;
+                }
+                let filter = env
+                    .get_field(wrapper, "intentFilter", "Landroid/content/IntentFilter;")
+                    .unwrap()
+                    .l()
+                    .unwrap();
+                Some(
+                    env.call_method(filter, "getAction", "(I)Ljava/lang/String;", &[0.into()])
+                        .unwrap()
+                        .l()
+                        .unwrap(),
+                )
+            })
+        };
+
+        let mut stream =
+            Box::pin(add_proximity_alert(&env, context, 47.6, -122.3, 100.0, None).unwrap());
+        let action = alert_action().unwrap();
+
+        for entering in &[true, false] {
+            let intent = env
+                .new_object(
+                    "android/content/Intent",
+                    "(Ljava/lang/String;)V",
+                    &[action.into()],
+                )
+                .unwrap();
+            let key = env.new_string("entering").unwrap();
+            env.call_method(
+                intent,
+                "putExtra",
+                "(Ljava/lang/String;Z)Landroid/content/Intent;",
+                &[key.into(), (*entering).into()],
+            )
+            .unwrap();
+            env.call_method(
+                context,
+                "sendBroadcast",
+                "(Landroid/content/Intent;)V",
+                &[intent.into()],
+            )
+            .unwrap();
+        }
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        assert_eq!(
+            block_on(stream.next()).unwrap().unwrap(),
+            GeofenceEvent::Entered
+        );
+        assert_eq!(
+            block_on(stream.next()).unwrap().unwrap(),
+            GeofenceEvent::Exited
+        );
+
+        std::mem::drop(stream);
+        assert!(alert_action().is_none());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testGnssStatus(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::location::{gnss_status, GnssStatus};
+        use futures::{executor::block_on, FutureExt};
+        use std::time::Duration;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow_application = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[context.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let permissions = env
+            .new_object_array(
+                1,
+                "java/lang/String",
+                env.new_string("android.permission.ACCESS_FINE_LOCATION")
+                    .unwrap(),
+            )
+            .unwrap();
+        env.call_method(
+            shadow_application,
+            "grantPermissions",
+            "([Ljava/lang/String;)V",
+            &[JObject::from(permissions).into()],
+        )
+        .unwrap();
+        let name = env.new_string("location").unwrap();
+        let manager = env
+            .call_method(
+                context,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[name.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow_manager = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[manager.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let mut stream = Box::pin(gnss_status(&env, context).unwrap().unwrap());
+        env.call_method(shadow_manager, "simulateGnssStatusStarted", "()V", &[])
+            .unwrap();
+        env.call_method(
+            shadow_manager,
+            "simulateGnssStatusFirstFix",
+            "(I)V",
+            &[1500.into()],
+        )
+        .unwrap();
+        env.call_method(shadow_manager, "simulateGnssStatusStopped", "()V", &[])
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        let started = block_on(stream.next()).unwrap().unwrap();
+        assert!(matches!(started, GnssStatus::Started));
+        assert_eq!(started.satellite_count(), None);
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            GnssStatus::FirstFix { ttff } if ttff == Duration::from_millis(1500)
+        ));
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            GnssStatus::Stopped
+        ));
+
+        // Dropping the stream unregisters the callback, so later events are
+        // not delivered to it.
+        std::mem::drop(stream);
+        env.call_method(shadow_manager, "simulateGnssStatusStarted", "()V", &[])
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        let mut stream = Box::pin(gnss_status(&env, context).unwrap().unwrap());
+        assert!(stream.next().now_or_never().is_none());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBatteryState(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::battery::{battery_state, BatteryStatus, PowerSource};
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let action = env
+            .new_string("android.intent.action.BATTERY_CHANGED")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Ljava/lang/String;)V",
+                &[action.into()],
+            )
+            .unwrap();
+        for (name, value) in [("level", 50), ("scale", 100), ("status", 2), ("plugged", 2)] {
+            let name = env.new_string(name).unwrap();
+            env.call_method(
+                intent,
+                "putExtra",
+                "(Ljava/lang/String;I)Landroid/content/Intent;",
+                &[name.into(), value.into()],
+            )
+            .unwrap();
+        }
+        env.call_method(
+            context,
+            "sendStickyBroadcast",
+            "(Landroid/content/Intent;)V",
+            &[intent.into()],
+        )
+        .unwrap();
+
+        let state = battery_state(&env, context).unwrap().unwrap();
+        assert_eq!(state.level, 50);
+        assert_eq!(state.scale, 100);
+        assert_eq!(state.fraction(), Some(0.5));
+        assert_eq!(state.status, BatteryStatus::Charging);
+        assert_eq!(state.plugged, Some(PowerSource::Usb));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testStorageDirs(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::content::storage::{cache_dir, files_dir};
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let files = files_dir(&env, context).unwrap();
+        assert!(files.is_absolute());
+        std::fs::write(files.join("storage-test"), b"hello").unwrap();
+        assert_eq!(std::fs::read(files.join("storage-test")).unwrap(), b"hello");
+        assert_ne!(cache_dir(&env, context).unwrap(), files);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testCrashReports(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{content::storage::cache_dir, crash};
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let dir = cache_dir(&env, context).unwrap().join("crash-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(crash::take_reports().unwrap().is_empty());
+
+        // The handlers are installed in child processes, since the JVM uses
+        // SIGSEGV internally.
+        let run_child = |f: &dyn Fn() -> bool| -> libc::c_int {
+            match unsafe { libc::fork() } {
+                0 => {
+                    let ok = catch_unwind(AssertUnwindSafe(f)).unwrap_or(false);
+                    unsafe { libc::_exit(if ok { 0 } else { 1 }) }
+                }
+                pid => {
+                    assert!(pid > 0);
+                    let mut status = 0;
+                    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                    status
+                }
+            }
+        };
+
+        let status = run_child(&|| {
+            unsafe { libc::signal(libc::SIGSEGV, libc::SIG_DFL) };
+            crash::install_with_dir(&dir).unwrap();
+            assert_eq!(
+                crash::install_with_dir(&dir).unwrap_err().kind(),
+                std::io::ErrorKind::AlreadyExists
+            );
+            assert!(catch_unwind(|| panic!("Reported panic")).is_err());
+            unsafe { libc::raise(libc::SIGSEGV) };
+            false
+        });
+        assert!(libc::WIFSIGNALED(status));
+        assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+
+        let status = run_child(&|| {
+            crash::install_with_dir(&dir).unwrap();
+            let reports = crash::take_reports().unwrap();
+            assert_eq!(reports.len(), 2);
+            let crash = reports
+                .iter()
+                .find(|report| report.starts_with("*** Rust native crash ***"))
+                .unwrap();
+            assert!(crash.contains(&format!("signal {} (SIGSEGV)", libc::SIGSEGV)));
+            let panic = reports
+                .iter()
+                .find(|report| report.starts_with("*** Rust panic ***"))
+                .unwrap();
+            assert!(panic.contains("Reported panic"));
+            assert!(panic.contains("backtrace:"));
+            crash::take_reports().unwrap().is_empty()
+        });
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testKeyAttestation(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::keystore::{certificate_chain, AttestationRecord, SecurityLevel};
+
+        fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+            let mut result = vec![tag];
+            if content.len() < 0x80 {
+                result.push(content.len() as u8);
+            } else {
+                result.extend_from_slice(&[0x81, content.len() as u8]);
+            }
+            result.extend_from_slice(content);
+            result
+        }
+
+        let description = tlv(
+            0x30,
+            &[
+                tlv(0x02, &[3]),
+                tlv(0x0a, &[1]),
+                tlv(0x02, &[4]),
+                tlv(0x0a, &[2]),
+                tlv(0x04, b"challenge"),
+                tlv(0x04, &[]),
+                // Authorization lists, which are skipped.
+                tlv(0x30, &[0; 200]),
+                tlv(0x30, &[]),
+            ]
+            .concat(),
+        );
+        let expected = AttestationRecord {
+            attestation_version: 3,
+            attestation_security_level: SecurityLevel::TrustedEnvironment,
+            keymaster_version: 4,
+            keymaster_security_level: SecurityLevel::StrongBox,
+            attestation_challenge: b"challenge".to_vec(),
+            unique_id: Vec::new(),
+        };
+        assert_eq!(
+            AttestationRecord::from_extension(&description),
+            Some(expected.clone())
+        );
+
+        let oid = [
+            0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x01, 0x11,
+        ];
+        let extension = [&oid[..], &tlv(0x01, &[0xff]), &tlv(0x04, &description)].concat();
+        let certificate = [&[0x30, 0x03, 0x02, 0x01, 0x02][..], &extension].concat();
+        assert_eq!(
+            AttestationRecord::from_certificate(&certificate),
+            Some(expected.clone())
+        );
+        let non_critical = [&oid[..], &tlv(0x04, &description)].concat();
+        assert_eq!(
+            AttestationRecord::from_certificate(&non_critical),
+            Some(expected)
+        );
+
+        assert_eq!(AttestationRecord::from_certificate(&[0x30, 0x00]), None);
+        assert_eq!(
+            AttestationRecord::from_extension(&description[..description.len() - 20]),
+            None
+        );
+        assert_eq!(
+            AttestationRecord::from_extension(&tlv(0x30, &tlv(0x0a, &[1]))),
+            None
+        );
+
+        // Robolectric has no AndroidKeyStore provider, in which case the
+        // error is left pending as a Java exception.
+        match certificate_chain(&env, "missing") {
+            Ok(chain) => assert_eq!(chain, None),
+            Err(jni::errors::Error::JavaException) => {
+                env.exception_clear().unwrap();
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testLogThrowable(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{error_throwable, log_error, Priority};
+        use std::{error::Error, fmt};
+
+        #[derive(Debug)]
+        struct Inner;
+
+        impl fmt::Display for Inner {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("inner error")
+            }
+        }
+
+        impl Error for Inner {}
+
+        #[derive(Debug)]
+        struct Outer(Inner);
+
+        impl fmt::Display for Outer {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("outer error")
+            }
+        }
+
+        impl Error for Outer {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let get_message = |throwable: JObject| -> String {
+            let msg = env
+                .call_method(throwable, "getMessage", "()Ljava/lang/String;", &[])
+                .unwrap()
+                .l()
+                .unwrap();
+            env.get_string(msg.into()).unwrap().into()
+        };
+        let log_item = |tag: &str| {
+            let tag = env.new_string(tag).unwrap();
+            let logs = env
+                .call_static_method(
+                    "org/robolectric/shadows/ShadowLog",
+                    "getLogsForTag",
+                    "(Ljava/lang/String;)Ljava/util/List;",
+                    &[tag.into()],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            assert_eq!(
+                env.call_method(logs, "size", "()I", &[])
+                    .unwrap()
+                    .i()
+                    .unwrap(),
+                1
+            );
+            let item = env
+                .call_method(logs, "get", "(I)Ljava/lang/Object;", &[0.into()])
+                .unwrap()
+                .l()
+                .unwrap();
+            let priority = env.get_field(item, "type", "I").unwrap().i().unwrap();
+            let throwable = env
+                .get_field(item, "throwable", "Ljava/lang/Throwable;")
+                .unwrap()
+                .l()
+                .unwrap();
+            (priority, throwable)
+        };
+
+        let msg = env.new_string("Java exception").unwrap();
+        let exception = env
+            .new_object(
+                "java/lang/RuntimeException",
+                "(Ljava/lang/String;)V",
+                &[msg.into()],
+            )
+            .unwrap();
+        error_throwable(&env, "TestThrowable", "Failed", exception.into()).unwrap();
+        let (priority, throwable) = log_item("TestThrowable");
+        assert_eq!(priority, Priority::Error as i32);
+        assert!(env.is_same_object(throwable, exception).unwrap());
+
+        log_error(&env, Priority::Warn, "TestError", "Failed", &Outer(Inner)).unwrap();
+        let (priority, throwable) = log_item("TestError");
+        assert_eq!(priority, Priority::Warn as i32);
+        assert_eq!(get_message(throwable), "outer error");
+        let cause = env
+            .call_method(throwable, "getCause", "()Ljava/lang/Throwable;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(get_message(cause), "inner error");
+        assert!(env
+            .call_method(cause, "getCause", "()Ljava/lang/Throwable;", &[])
+            .unwrap()
+            .l()
+            .unwrap()
+            .is_null());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testAndroidLogger(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{AndroidLogger, Backend, Priority};
+        use log::Log;
+
+        let logger = AndroidLogger::new(Backend::Jni);
+        logger.log(
+            &log::Record::builder()
+                .args(format_args!("Logged {}", 42))
+                .level(log::Level::Warn)
+                .target("TestLogger")
+                .build(),
+        );
+
+        let tag = env.new_string("TestLogger").unwrap();
+        let logs = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowLog",
+                "getLogsForTag",
+                "(Ljava/lang/String;)Ljava/util/List;",
+                &[tag.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(logs, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            1
+        );
+        let item = env
+            .call_method(logs, "get", "(I)Ljava/lang/Object;", &[0.into()])
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.get_field(item, "type", "I").unwrap().i().unwrap(),
+            Priority::Warn as i32
+        );
+        let msg = env
+            .get_field(item, "msg", "Ljava/lang/String;")
+            .unwrap()
+            .l()
+            .unwrap();
+        let msg: String = env.get_string(msg.into()).unwrap().into();
+        assert_eq!(msg, "Logged 42");
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testLoggerNativeThread(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{AndroidLogger, Backend};
+        use log::Log;
+
+        // Log from a thread which never returns to Java, so that leaked local
+        // references would never be freed.
+        std::thread::spawn(|| {
+            let logger = AndroidLogger::new(Backend::Jni);
+            for i in 0..1000 {
+                logger.log(
+                    &log::Record::builder()
+                        .args(format_args!("Logged {}", i))
+                        .level(log::Level::Info)
+                        .target("TestLoggerThread")
+                        .build(),
+                );
+            }
+        })
+        .join()
+        .unwrap();
+
+        let tag = env.new_string("TestLoggerThread").unwrap();
+        let logs = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowLog",
+                "getLogsForTag",
+                "(Ljava/lang/String;)Ljava/util/List;",
+                &[tag.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(logs, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            1000
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testTagPolicy(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{AndroidLogger, Backend, TagPolicy};
+        use log::Log;
+
+        fn logged(env: &JNIEnv, tag: &str) -> jint {
+            let tag = env.new_string(tag).unwrap();
+            let logs = env
+                .call_static_method(
+                    "org/robolectric/shadows/ShadowLog",
+                    "getLogsForTag",
+                    "(Ljava/lang/String;)Ljava/util/List;",
+                    &[tag.into()],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            env.call_method(logs, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap()
+        }
+
+        let log = |policy: TagPolicy| {
+            AndroidLogger::new(Backend::Jni)
+                .with_tag_policy(policy)
+                .log(
+                    &log::Record::builder()
+                        .args(format_args!("Tagged"))
+                        .level(log::Level::Info)
+                        .target("tag_crate::a_rather_long_module::name")
+                        .build(),
+                );
+        };
+
+        log(TagPolicy::Target);
+        assert_eq!(logged(&env, "tag_crate::a_rather_lon"), 1);
+        log(TagPolicy::Crate);
+        assert_eq!(logged(&env, "tag_crate"), 1);
+        log(TagPolicy::Fixed("FixedTag".to_string()));
+        assert_eq!(logged(&env, "FixedTag"), 1);
+        log(TagPolicy::Custom(Arc::new(|target| {
+            target.rsplit("::").next().unwrap().to_uppercase()
+        })));
+        assert_eq!(logged(&env, "NAME"), 1);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testPanicHook(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{install_panic_hook, Backend, Priority};
+
+        install_panic_hook("TestPanicHook", Backend::Jni);
+        let result = std::panic::catch_unwind(|| panic!("Logged panic"));
+        // Restore the default hook for the other tests.
+        let _ = std::panic::take_hook();
+        assert!(result.is_err());
+
+        let tag = env.new_string("TestPanicHook").unwrap();
+        let logs = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowLog",
+                "getLogsForTag",
+                "(Ljava/lang/String;)Ljava/util/List;",
+                &[tag.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let size = env
+            .call_method(logs, "size", "()I", &[])
+            .unwrap()
+            .i()
+            .unwrap();
+        let mut lines = Vec::new();
+        for i in 0..size {
+            let item = env
+                .call_method(logs, "get", "(I)Ljava/lang/Object;", &[i.into()])
+                .unwrap()
+                .l()
+                .unwrap();
+            assert_eq!(
+                env.get_field(item, "type", "I").unwrap().i().unwrap(),
+                Priority::Error as i32
+            );
+            let msg = env
+                .get_field(item, "msg", "Ljava/lang/String;")
+                .unwrap()
+                .l()
+                .unwrap();
+            lines.push(String::from(env.get_string(msg.into()).unwrap()));
+        }
+        assert!(lines[0].contains("panicked at"));
+        assert!(lines.iter().any(|line| line.contains("Logged panic")));
+        assert!(lines.iter().any(|line| line == "backtrace:"));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBufferedLogger(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{AndroidLogger, Backend, OverflowPolicy};
+        use log::Log;
+
+        let logger = AndroidLogger::new(Backend::Jni)
+            .with_buffer(2, OverflowPolicy::Block)
+            .unwrap();
+        for i in 0..5 {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("Buffered {}", i))
+                    .level(log::Level::Info)
+                    .target("TestBufferedLogger")
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        let tag = env.new_string("TestBufferedLogger").unwrap();
+        let logs = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowLog",
+                "getLogsForTag",
+                "(Ljava/lang/String;)Ljava/util/List;",
+                &[tag.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(logs, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            5
+        );
+        let item = env
+            .call_method(logs, "get", "(I)Ljava/lang/Object;", &[4.into()])
+            .unwrap()
+            .l()
+            .unwrap();
+        let msg = env
+            .get_field(item, "msg", "Ljava/lang/String;")
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            String::from(env.get_string(msg.into()).unwrap()),
+            "Buffered 4"
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBufferedLoggerManyRecords(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{AndroidLogger, Backend, OverflowPolicy};
+        use log::Log;
+
+        // More records than the 512 local references the JNI spec guarantees,
+        // all written from the logger's own thread.
+        let logger = AndroidLogger::new(Backend::Jni)
+            .with_buffer(16, OverflowPolicy::Block)
+            .unwrap();
+        for i in 0..1000 {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("Buffered {}", i))
+                    .level(log::Level::Info)
+                    .target("TestBufferedMany")
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        let tag = env.new_string("TestBufferedMany").unwrap();
+        let logs = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowLog",
+                "getLogsForTag",
+                "(Ljava/lang/String;)Ljava/util/List;",
+                &[tag.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(logs, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            1000
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testLogFilter(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{AndroidLogger, Backend, Filter};
+        use log::{Level, LevelFilter, Log, Metadata};
+
+        let filter: Filter = "info, my_crate=debug, my_crate::noisy=error, dep=off"
+            .parse()
+            .unwrap();
+        assert_eq!(filter.level("other"), LevelFilter::Info);
+        assert_eq!(filter.level("my_crate"), LevelFilter::Debug);
+        assert_eq!(filter.level("my_crate::module"), LevelFilter::Debug);
+        assert_eq!(filter.level("my_crate::noisy::inner"), LevelFilter::Error);
+        assert_eq!(filter.level("my_crate_two"), LevelFilter::Info);
+        assert_eq!(filter.level("dep"), LevelFilter::Off);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+        assert!("my_crate=loud".parse::<Filter>().is_err());
+        assert!("=debug".parse::<Filter>().is_err());
+        assert_eq!(Filter::from_property("log.tag.rust"), Ok(None));
+
+        let logger = AndroidLogger::new(Backend::Jni).with_filter(filter);
+        let enabled = |target, level| {
+            logger.enabled(&Metadata::builder().target(target).level(level).build())
+        };
+        assert!(enabled("my_crate::module", Level::Debug));
+        assert!(!enabled("my_crate::module", Level::Trace));
+        assert!(!enabled("my_crate::noisy", Level::Warn));
+        assert!(!enabled("dep", Level::Error));
+        assert!(enabled("other", Level::Info));
+        assert!(!enabled("other", Level::Debug));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testLoggerBuilder(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{Backend, LoggerBuilder, Priority, TagPolicy};
+        use log::{Level, LevelFilter, Log};
+
+        let logger = LoggerBuilder::new(Backend::Jni)
+            .with_level(LevelFilter::Warn)
+            .with_tag_policy(TagPolicy::Fixed("TestLoggerBuilder".to_string()))
+            .with_is_loggable(false)
+            .build()
+            .unwrap();
+        for level in [Level::Info, Level::Warn] {
+            logger.log(
+                &log::Record::builder()
+                    .args(format_args!("Built {}", level))
+                    .level(level)
+                    .target("test_crate")
+                    .build(),
+            );
+        }
+
+        let tag = env.new_string("TestLoggerBuilder").unwrap();
+        let logs = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowLog",
+                "getLogsForTag",
+                "(Ljava/lang/String;)Ljava/util/List;",
+                &[tag.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(logs, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            1
+        );
+        let item = env
+            .call_method(logs, "get", "(I)Ljava/lang/Object;", &[0.into()])
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.get_field(item, "type", "I").unwrap().i().unwrap(),
+            Priority::Warn as i32
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testLogBridge(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{bridge, Priority};
+
+        let mut records = bridge::stream(&env).unwrap();
+        let tag = env.new_string("TestLogBridge").unwrap();
+        let msg = env.new_string("Bridged").unwrap();
+        let throwable = env
+            .new_object(
+                "java/lang/Exception",
+                "(Ljava/lang/String;)V",
+                &[env.new_string("Bridged exception").unwrap().into()],
+            )
+            .unwrap();
+        env.call_static_method(
+            "io/github/gedgygedgy/rust/android/util/RustLog",
+            "w",
+            "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/Throwable;)I",
+            &[tag.into(), msg.into(), throwable.into()],
+        )
+        .unwrap();
+        bridge::clear_handler(&env).unwrap();
+        env.call_static_method(
+            "io/github/gedgygedgy/rust/android/util/RustLog",
+            "i",
+            "(Ljava/lang/String;Ljava/lang/String;)I",
+            &[tag.into(), msg.into()],
+        )
+        .unwrap();
+
+        let record = futures::executor::block_on(records.next()).unwrap();
+        assert_eq!(record.priority, Priority::Warn);
+        assert_eq!(record.tag, "TestLogBridge");
+        assert_eq!(record.msg, "Bridged");
+        assert!(record
+            .stack_trace
+            .unwrap()
+            .starts_with("java.lang.Exception: Bridged exception"));
+        assert!(futures::executor::block_on(records.next()).is_none());
+
+        let logs = env
+            .call_static_method(
+                "org/robolectric/shadows/ShadowLog",
+                "getLogsForTag",
+                "(Ljava/lang/String;)Ljava/util/List;",
+                &[tag.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(logs, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            2
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testTracingLayer(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{
+            tracing::AndroidLayer, Backend, KeyValueFormat, Priority, TagPolicy,
+        };
+        use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+        let log_events = || {
+            let connect = tracing::info_span!("connect", host = "example.com");
+            let _connect = connect.enter();
+            let request = tracing::info_span!("request", id = tracing::field::Empty);
+            request.record("id", 3);
+            let _request = request.enter();
+            tracing::warn!(len = 42, "sending {}", "body");
+            tracing::error!(done = true);
+        };
+
+        let layer = AndroidLayer::new(Backend::Jni)
+            .with_tag_policy(TagPolicy::Fixed("TestTracing".to_string()));
+        tracing::subscriber::with_default(Registry::default().with(layer), log_events);
+        assert_eq!(
+            shadow_logs_for_tag(&env, "TestTracing"),
+            vec![
+                (
+                    Priority::Warn as i32,
+                    "connect{host=\"example.com\"}: request{id=3}: sending body len=42".to_string()
+                ),
+                (
+                    Priority::Error as i32,
+                    "connect{host=\"example.com\"}: request{id=3}: done=true".to_string()
+                ),
+            ]
+        );
+
+        let layer = AndroidLayer::new(Backend::Jni)
+            .with_tag_policy(TagPolicy::Fixed("TestTracingJson".to_string()))
+            .with_key_values(KeyValueFormat::Json);
+        tracing::subscriber::with_default(Registry::default().with(layer), log_events);
+        assert_eq!(
+            shadow_logs_for_tag(&env, "TestTracingJson"),
+            vec![
+                (
+                    Priority::Warn as i32,
+                    "connect{\"host\":\"example.com\"}: request{\"id\":3}: sending body {\"len\":42}"
+                        .to_string()
+                ),
+                (
+                    Priority::Error as i32,
+                    "connect{\"host\":\"example.com\"}: request{\"id\":3}: {\"done\":true}"
+                        .to_string()
+                ),
+            ]
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testLogKeyValues(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::log::{AndroidLogger, Backend, KeyValueFormat, TagPolicy};
+        use log::{kv::Value, Log};
+
+        let log_all = |tag: &str, format: KeyValueFormat| {
+            let logger = AndroidLogger::new(Backend::Jni)
+                .with_tag_policy(TagPolicy::Fixed(tag.to_string()))
+                .with_key_values(format);
+            let path = "/tmp/x";
+            let sources: [&[(&str, Value)]; 4] = [
+                &[
+                    ("id", 3u64.into()),
+                    ("ok", true.into()),
+                    ("name", "a \"b\"\n".into()),
+                    ("ratio", 0.5.into()),
+                    ("nan", f64::NAN.into()),
+                    ("inf", f64::INFINITY.into()),
+                    ("path", Value::from_display(&path)),
+                    ("ctl\"key", "\u{1}".into()),
+                ],
+                &[
+                    ("count", 7u64.into()),
+                    ("delta", (-2i64).into()),
+                    ("up", true.into()),
+                    ("temp", f64::NEG_INFINITY.into()),
+                    ("msg", "x=\"1\"".into()),
+                ],
+                &[
+                    ("plain", "plain".into()),
+                    ("quote", r#"say "hi""#.into()),
+                    ("backslash", r"C:\dir".into()),
+                    ("whitespace", "a\nb\rc\td".into()),
+                    ("control", "\u{0}\u{1b}\u{7f}".into()),
+                    ("unicode", "ünï €".into()),
+                ],
+                &[],
+            ];
+            for source in sources {
+                logger.log(
+                    &log::Record::builder()
+                        .args(format_args!("Logged"))
+                        .level(log::Level::Info)
+                        .key_values(&source)
+                        .build(),
+                );
+            }
+            shadow_logs_for_tag(&env, tag)
+                .into_iter()
+                .map(|(_, msg)| msg)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            log_all("TestKeyValues", KeyValueFormat::KeyValue),
+            vec![
+                r#"Logged id=3 ok=true name="a \"b\"\n" ratio=0.5 nan=NaN inf=inf path=/tmp/x ctl"key="\u{1}""#,
+                r#"Logged count=7 delta=-2 up=true temp=-inf msg="x=\"1\"""#,
+                r#"Logged plain="plain" quote="say \"hi\"" backslash="C:\\dir" whitespace="a\nb\rc\td" control="\0\u{1b}\u{7f}" unicode="ünï €""#,
+                "Logged",
+            ]
+        );
+        assert_eq!(
+            log_all("TestKeyValuesJson", KeyValueFormat::Json),
+            vec![
+                r#"Logged {"id":3,"ok":true,"name":"a \"b\"\n","ratio":0.5,"nan":"NaN","inf":"inf","path":"/tmp/x","ctl\"key":"\u0001"}"#,
+                r#"Logged {"count":7,"delta":-2,"up":true,"temp":"-inf","msg":"x=\"1\""}"#,
+                r#"Logged {"plain":"plain","quote":"say \"hi\"","backslash":"C:\\dir","whitespace":"a\nb\rc\td","control":"\u0000\u001b\u007f","unicode":"ünï €"}"#,
+                "Logged",
+            ]
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testJContext(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::content::JContext;
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let context = JContext::from_env(&env, context).unwrap();
+
+        let app = context.application_context().unwrap();
+        assert!(env.is_same_object(*app, *context).unwrap());
+
+        let power = context.system_service("power").unwrap();
+        assert!(env
+            .is_instance_of(power, "android/os/PowerManager")
+            .unwrap());
+        assert!(context.system_service("no_such_service").unwrap().is_null());
+
+        assert!(!context
+            .check_self_permission("android.permission.CAMERA")
+            .unwrap());
+
+        let action = env.new_string("io.github.gedgygedgy.TEST").unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Ljava/lang/String;)V",
+                &[action.into()],
+            )
+            .unwrap();
+        context.send_broadcast(intent).unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testJIntent(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            content::{JIntent, FLAG_ACTIVITY_NEW_TASK},
+            os::bundle::BundleValue,
+        };
+        use std::collections::HashMap;
+
+        let mut nested = HashMap::new();
+        nested.insert("inner".to_string(), BundleValue::I64(7));
+        let intent = JIntent::new(&env)
+            .unwrap()
+            .with_action("io.github.gedgygedgy.TEST")
+            .unwrap()
+            .with_data("https://example.com/path")
+            .unwrap()
+            .with_component("io.github.gedgygedgy", "io.github.gedgygedgy.Test")
+            .unwrap()
+            .with_category("android.intent.category.DEFAULT")
+            .unwrap()
+            .with_flags(FLAG_ACTIVITY_NEW_TASK)
+            .unwrap()
+            .with_extra("string", &BundleValue::String("value".to_string()))
+            .unwrap()
+            .with_extra("int", &BundleValue::I32(42))
+            .unwrap()
+            .with_extra("bool", &BundleValue::Bool(true))
+            .unwrap()
+            .with_extra(
+                "list",
+                &BundleValue::List(vec![BundleValue::I32(1), BundleValue::I32(2)]),
+            )
+            .unwrap()
+            .with_extra("map", &BundleValue::Map(nested.clone()))
+            .unwrap();
+
+        // Read it back through a fresh wrapper, like a received intent.
+        let intent = JIntent::from_env(&env, *intent).unwrap();
+        assert_eq!(
+            intent.action().unwrap().as_deref(),
+            Some("io.github.gedgygedgy.TEST")
+        );
+        assert_eq!(
+            intent.data().unwrap().as_deref(),
+            Some("https://example.com/path")
+        );
+        assert_eq!(
+            intent.component().unwrap(),
+            Some((
+                "io.github.gedgygedgy".to_string(),
+                "io.github.gedgygedgy.Test".to_string()
+            ))
+        );
+        assert_eq!(
+            intent.categories().unwrap(),
+            vec!["android.intent.category.DEFAULT".to_string()]
+        );
+        assert_eq!(intent.flags().unwrap(), FLAG_ACTIVITY_NEW_TASK);
+        assert_eq!(
+            intent.string_extra("string").unwrap().as_deref(),
+            Some("value")
+        );
+        assert_eq!(intent.int_extra("int", 0).unwrap(), 42);
+        assert_eq!(intent.int_extra("missing", -1).unwrap(), -1);
+        assert!(intent.bool_extra("bool", false).unwrap());
+        assert_eq!(
+            intent.extra("list").unwrap(),
+            Some(BundleValue::List(vec![
+                BundleValue::I32(1),
+                BundleValue::I32(2)
+            ]))
+        );
+        assert_eq!(intent.extra("missing").unwrap(), None);
+        let extras = intent.extras().unwrap();
+        assert_eq!(extras.len(), 5);
+        assert_eq!(extras["map"], BundleValue::Map(nested));
+
+        let empty = JIntent::new(&env).unwrap();
+        assert_eq!(empty.action().unwrap(), None);
+        assert_eq!(empty.component().unwrap(), None);
+        assert!(empty.categories().unwrap().is_empty());
+        assert!(empty.extras().unwrap().is_empty());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testJBundle(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            content::{JBundle, JIntent},
+            os::bundle::BundleValue,
+        };
+
+        let bundle = JBundle::new(&env).unwrap();
+        assert!(bundle.is_empty().unwrap());
+        bundle.put_bool("bool", true).unwrap();
+        bundle.put_int("int", 42).unwrap();
+        bundle.put_long("long", 1 << 40).unwrap();
+        bundle.put_double("double", 1.5).unwrap();
+        bundle.put_string("string", "value").unwrap();
+        bundle.put_bytes("bytes", &[1, 2, 3]).unwrap();
+        bundle
+            .put("list", &BundleValue::List(vec![BundleValue::I32(1)]))
+            .unwrap();
+        let nested = JBundle::new(&env).unwrap();
+        nested.put_int("inner", 7).unwrap();
+        bundle.put_bundle("nested", &nested).unwrap();
+
+        assert_eq!(bundle.len().unwrap(), 8);
+        assert!(bundle.get_bool("bool", false).unwrap());
+        assert_eq!(bundle.get_int("int", 0).unwrap(), 42);
+        assert_eq!(bundle.get_int("missing", -1).unwrap(), -1);
+        assert_eq!(bundle.get_long("long", 0).unwrap(), 1 << 40);
+        assert_eq!(bundle.get_double("double", 0.0).unwrap(), 1.5);
+        assert_eq!(
+            bundle.get_string("string").unwrap().as_deref(),
+            Some("value")
+        );
+        assert_eq!(bundle.get_string("missing").unwrap(), None);
+        assert_eq!(bundle.get_bytes("bytes").unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(
+            bundle.get("list").unwrap(),
+            Some(BundleValue::List(vec![BundleValue::I32(1)]))
+        );
+        assert_eq!(bundle.get("missing").unwrap(), None);
+        let nested = bundle.get_bundle("nested").unwrap().unwrap();
+        assert_eq!(nested.get_int("inner", 0).unwrap(), 7);
+
+        let mut keys = bundle.keys().unwrap();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["bool", "bytes", "double", "int", "list", "long", "nested", "string"]
+        );
+
+        bundle.remove("bool").unwrap();
+        assert!(!bundle.contains_key("bool").unwrap());
+
+        let intent = JIntent::new(&env).unwrap().with_extras(&bundle).unwrap();
+        let extras = intent.bundle_extras().unwrap().unwrap();
+        assert_eq!(extras.to_map().unwrap(), bundle.to_map().unwrap());
+        assert!(JIntent::new(&env)
+            .unwrap()
+            .bundle_extras()
+            .unwrap()
+            .is_none());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testAsyncBroadcastReceiver(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::content::{async_broadcast_receiver, JContext, JIntent};
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let context = JContext::from_env(&env, context).unwrap();
+        let (receiver, mut stream) = async_broadcast_receiver(&env).unwrap();
+
+        let action = env.new_string("io.github.gedgygedgy.TEST").unwrap();
+        let filter = env
+            .new_object(
+                "android/content/IntentFilter",
+                "(Ljava/lang/String;)V",
+                &[action.into()],
+            )
+            .unwrap();
+        env.call_method(
+            *context,
+            "registerReceiver",
+            "(Landroid/content/BroadcastReceiver;Landroid/content/IntentFilter;)Landroid/content/Intent;",
+            &[receiver.into(), filter.into()],
+        )
+        .unwrap();
+
+        let intent = JIntent::new(&env)
+            .unwrap()
+            .with_action("io.github.gedgygedgy.TEST")
+            .unwrap();
+        context.send_broadcast(*intent).unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        let event = block_on(stream.next()).unwrap().unwrap();
+        let intent = JIntent::from_env(&env, event.intent.as_obj()).unwrap();
+        assert_eq!(
+            intent.action().unwrap().as_deref(),
+            Some("io.github.gedgygedgy.TEST")
+        );
+        assert!(!event.context.as_obj().is_null());
+
+        env.call_method(
+            *context,
+            "unregisterReceiver",
+            "(Landroid/content/BroadcastReceiver;)V",
+            &[receiver.into()],
+        )
+        .unwrap();
+        env.call_method(receiver, "close", "()V", &[]).unwrap();
+        assert!(block_on(stream.next()).is_none());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testRegisterReceiver(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::content::{
+            register_receiver, JContext, JIntent, JIntentFilter, RECEIVER_NOT_EXPORTED,
+        };
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let filter = JIntentFilter::new(&env)
+            .unwrap()
+            .with_action("io.github.gedgygedgy.TEST")
+            .unwrap()
+            .with_category("android.intent.category.DEFAULT")
+            .unwrap()
+            .with_data_scheme("test")
+            .unwrap()
+            .with_priority(10)
+            .unwrap();
+        assert_eq!(
+            filter.actions().unwrap(),
+            vec!["io.github.gedgygedgy.TEST".to_string()]
+        );
+        assert_eq!(
+            filter.categories().unwrap(),
+            vec!["android.intent.category.DEFAULT".to_string()]
+        );
+        assert_eq!(filter.data_schemes().unwrap(), vec!["test".to_string()]);
+        assert_eq!(filter.priority().unwrap(), 10);
+
+        let (registration, mut stream) =
+            register_receiver(&env, context, &filter, RECEIVER_NOT_EXPORTED).unwrap();
+        let intent = JIntent::new(&env)
+            .unwrap()
+            .with_action("io.github.gedgygedgy.TEST")
+            .unwrap()
+            .with_category("android.intent.category.DEFAULT")
+            .unwrap()
+            .with_data("test:data")
+            .unwrap();
+        let context = JContext::from_env(&env, context).unwrap();
+        context.send_broadcast(*intent).unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        let event = block_on(stream.next()).unwrap().unwrap();
+        let intent = JIntent::from_env(&env, event.intent.as_obj()).unwrap();
+        assert_eq!(intent.data().unwrap().as_deref(), Some("test:data"));
+
+        drop(registration);
+        assert!(block_on(stream.next()).is_none());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testJSharedPreferences(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            content::{JSharedPreferences, MODE_PRIVATE},
+            os::bundle::BundleValue,
+        };
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let prefs = JSharedPreferences::new(&env, context, "test", MODE_PRIVATE).unwrap();
+        let mut changes = prefs.changes().unwrap();
+
+        assert!(prefs
+            .edit()
+            .unwrap()
+            .put_string("string", "value")
+            .unwrap()
+            .put_string_set("set", &["a"])
+            .unwrap()
+            .put_int("int", 42)
+            .unwrap()
+            .put_long("long", 1 << 40)
+            .unwrap()
+            .put_float("float", 1.5)
+            .unwrap()
+            .put_bool("bool", true)
+            .unwrap()
+            .commit()
+            .unwrap());
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        assert_eq!(
+            prefs.get_string("string").unwrap().as_deref(),
+            Some("value")
+        );
+        assert_eq!(prefs.get_string("missing").unwrap(), None);
+        assert_eq!(
+            prefs.get_string_set("set").unwrap(),
+            Some(vec!["a".to_string()])
+        );
+        assert_eq!(prefs.get_int("int", 0).unwrap(), 42);
+        assert_eq!(prefs.get_int("missing", -1).unwrap(), -1);
+        assert_eq!(prefs.get_long("long", 0).unwrap(), 1 << 40);
+        assert_eq!(prefs.get_float("float", 0.0).unwrap(), 1.5);
+        assert!(prefs.get_bool("bool", false).unwrap());
+        assert!(prefs.contains("int").unwrap());
+        assert_eq!(prefs.all().unwrap().get("int"), Some(&BundleValue::I32(42)));
+
+        let mut changed = Vec::new();
+        for _ in 0..6 {
+            changed.push(block_on(changes.next()).unwrap().unwrap().unwrap());
+        }
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec!["bool", "float", "int", "long", "set", "string"]
+        );
+
+        prefs
+            .edit()
+            .unwrap()
+            .remove("int")
+            .unwrap()
+            .apply()
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(!prefs.contains("int").unwrap());
+        assert_eq!(
+            block_on(changes.next()).unwrap().unwrap().as_deref(),
+            Some("int")
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testClipboard(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::content::clipboard::{
+            clear_primary_clip, clip_changes, has_primary_clip, primary_clip, primary_clip_text,
+            set_primary_clip_text, set_primary_clip_uri,
+        };
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let mut changes = clip_changes(&env, context).unwrap();
+
+        set_primary_clip_text(&env, context, "label", "copied").unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        block_on(changes.next()).unwrap().unwrap();
+        assert!(has_primary_clip(&env, context).unwrap());
+        let clip = primary_clip(&env, context).unwrap().unwrap();
+        assert_eq!(clip.label.as_deref(), Some("label"));
+        assert_eq!(clip.mime_types, vec!["text/plain".to_string()]);
+        assert_eq!(clip.items.len(), 1);
+        assert_eq!(clip.items[0].text.as_deref(), Some("copied"));
+        assert_eq!(clip.items[0].uri, None);
+        assert_eq!(clip.items[0].coerced_text, "copied");
+
+        set_primary_clip_uri(&env, context, "link", "https://example.com/").unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        block_on(changes.next()).unwrap().unwrap();
+        let clip = primary_clip(&env, context).unwrap().unwrap();
+        assert_eq!(clip.items[0].uri.as_deref(), Some("https://example.com/"));
+        assert_eq!(
+            primary_clip_text(&env, context).unwrap().as_deref(),
+            Some("https://example.com/")
+        );
+
+        if clear_primary_clip(&env, context).unwrap() {
+            assert!(!has_primary_clip(&env, context).unwrap());
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testPackageManager(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::content::{
+            pm::{
+                check_permissions, get_installed_packages, get_package_info, has_system_feature,
+                resolve_activity, resolve_service, GET_PERMISSIONS, MATCH_DEFAULT_ONLY,
+            },
+            JIntent,
+        };
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let package_name = env
+            .call_method(context, "getPackageName", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let package_name: String = env.get_string(package_name.into()).unwrap().into();
+
+        let info = get_package_info(&env, context, &package_name, GET_PERMISSIONS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.package_name, package_name);
+        assert!(
+            get_package_info(&env, context, "io.github.gedgygedgy.missing", 0)
+                .unwrap()
+                .is_none()
+        );
+        assert!(get_installed_packages(&env, context, 0)
+            .unwrap()
+            .iter()
+            .any(|info| info.package_name == package_name));
+
+        assert!(!has_system_feature(&env, context, "io.github.gedgygedgy.feature").unwrap());
+
+        let intent = JIntent::new(&env)
+            .unwrap()
+            .with_action("io.github.gedgygedgy.UNHANDLED")
+            .unwrap();
+        assert_eq!(
+            resolve_activity(&env, context, *intent, MATCH_DEFAULT_ONLY).unwrap(),
+            None
+        );
+        assert_eq!(resolve_service(&env, context, *intent, 0).unwrap(), None);
+
+        let states = check_permissions(
+            &env,
+            context,
+            &["io.github.gedgygedgy.permission.MISSING"],
+            &package_name,
+        )
+        .unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].name, "io.github.gedgygedgy.permission.MISSING");
+        assert!(!states[0].granted);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testJPendingIntent(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            app::{JPendingIntent, Mutability, FLAG_NO_CREATE, FLAG_UPDATE_CURRENT},
+            content::{register_receiver, JIntent, JIntentFilter, RECEIVER_NOT_EXPORTED},
+        };
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let filter = JIntentFilter::new(&env)
+            .unwrap()
+            .with_action("io.github.gedgygedgy.PENDING")
+            .unwrap();
+        let (_registration, mut stream) =
+            register_receiver(&env, context, &filter, RECEIVER_NOT_EXPORTED).unwrap();
+
+        let intent = JIntent::new(&env)
+            .unwrap()
+            .with_action("io.github.gedgygedgy.PENDING")
+            .unwrap();
+        assert!(JPendingIntent::get_broadcast(
+            &env,
+            context,
+            1,
+            *intent,
+            FLAG_NO_CREATE,
+            Mutability::Immutable
+        )
+        .unwrap()
+        .is_none());
+        let pending_intent = JPendingIntent::get_broadcast(
+            &env,
+            context,
+            1,
+            *intent,
+            FLAG_UPDATE_CURRENT,
+            Mutability::Immutable,
+        )
+        .unwrap()
+        .unwrap();
+        let package = env
+            .call_method(context, "getPackageName", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let package: String = env.get_string(package.into()).unwrap().into();
+        assert_eq!(pending_intent.creator_package().unwrap(), Some(package));
+
+        assert!(pending_intent.send().unwrap());
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        let event = block_on(stream.next()).unwrap().unwrap();
+        let received = JIntent::from_env(&env, event.intent.as_obj()).unwrap();
+        assert_eq!(
+            received.action().unwrap().as_deref(),
+            Some("io.github.gedgygedgy.PENDING")
+        );
+
+        pending_intent.cancel().unwrap();
+        assert!(!pending_intent.send().unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testBluetoothProfileProxy(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            bluetooth::{get_profile_proxy, Profile, STATE_CONNECTED, STATE_CONNECTING},
+            content::JIntent,
+            os::bundle::BundleValue,
+        };
+        use futures::executor::block_on;
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let adapter = env
+            .call_static_method(
+                "android/bluetooth/BluetoothAdapter",
+                "getDefaultAdapter",
+                "()Landroid/bluetooth/BluetoothAdapter;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow_adapter = env
+            .call_static_method(
+                "org/robolectric/Shadows",
+                "shadowOf",
+                "(Landroid/bluetooth/BluetoothAdapter;)Lorg/robolectric/shadows/ShadowBluetoothAdapter;",
+                &[adapter.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        // The shadow adapter has no proxies until they are set.
+        assert!(get_profile_proxy(&env, context, Profile::A2dp)
+            .unwrap()
+            .is_none());
+
+        let headset_class = env
+            .find_class("android/bluetooth/BluetoothHeadset")
+            .unwrap();
+        let headset = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "newInstanceOf",
+                "(Ljava/lang/Class;)Ljava/lang/Object;",
+                &[headset_class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            shadow_adapter,
+            "setProfileProxy",
+            "(ILandroid/bluetooth/BluetoothProfile;)V",
+            &[Profile::Headset.id().into(), headset.into()],
+        )
+        .unwrap();
+
+        let proxy = block_on(
+            get_profile_proxy(&env, context, Profile::Headset)
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(proxy.profile(), Profile::Headset);
+        assert!(env.is_same_object(proxy.as_obj(), headset).unwrap());
+        assert!(proxy.connected_devices(&env).unwrap().is_empty());
+
+        let address = env.new_string("00:11:22:33:44:55").unwrap();
+        let device = env
+            .call_method(
+                adapter,
+                "getRemoteDevice",
+                "(Ljava/lang/String;)Landroid/bluetooth/BluetoothDevice;",
+                &[address.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow_headset = env
+            .call_static_method(
+                "org/robolectric/Shadows",
+                "shadowOf",
+                "(Landroid/bluetooth/BluetoothHeadset;)Lorg/robolectric/shadows/ShadowBluetoothHeadset;",
+                &[headset.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            shadow_headset,
+            "addConnectedDevice",
+            "(Landroid/bluetooth/BluetoothDevice;)V",
+            &[device.into()],
+        )
+        .unwrap();
+        assert_eq!(proxy.connected_devices(&env).unwrap().len(), 1);
+        assert_eq!(
+            proxy.connection_state(&env, device).unwrap(),
+            STATE_CONNECTED
+        );
+
+        let mut stream = proxy.connection_state_changes(&env, context).unwrap();
+        let intent = JIntent::new(&env)
+            .unwrap()
+            .with_action("android.bluetooth.headset.profile.action.CONNECTION_STATE_CHANGED")
+            .unwrap()
+            .with_extra(
+                "android.bluetooth.profile.extra.STATE",
+                &BundleValue::I32(STATE_CONNECTED),
+            )
+            .unwrap()
+            .with_extra(
+                "android.bluetooth.profile.extra.PREVIOUS_STATE",
+                &BundleValue::I32(STATE_CONNECTING),
+            )
+            .unwrap();
+        let device_name = env
+            .new_string("android.bluetooth.device.extra.DEVICE")
+            .unwrap();
+        env.call_method(
+            *intent,
+            "putExtra",
+            "(Ljava/lang/String;Landroid/os/Parcelable;)Landroid/content/Intent;",
+            &[device_name.into(), device.into()],
+        )
+        .unwrap();
+        env.call_method(
+            context,
+            "sendBroadcast",
+            "(Landroid/content/Intent;)V",
+            &[(*intent).into()],
+        )
+        .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        let change = block_on(stream.next()).unwrap().unwrap();
+        assert_eq!(change.state, STATE_CONNECTED);
+        assert_eq!(change.previous_state, STATE_CONNECTING);
+        assert!(env
+            .call_method(
+                change.device.as_obj(),
+                "equals",
+                "(Ljava/lang/Object;)Z",
+                &[device.into()],
+            )
+            .unwrap()
+            .z()
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_AndroidTest_testTelecomConnection(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::telecom::{
+            async_connection, ConnectionEvent, DISCONNECT_LOCAL, ROUTE_EARPIECE, ROUTE_SPEAKER,
+            STATE_ACTIVE, STATE_DISCONNECTED, STATE_HOLDING, STATE_RINGING,
+        };
+        use futures::executor::block_on;
+
+        let (connection, stream) = async_connection(&env).unwrap();
+        let mut stream = Box::pin(stream);
+        let state = |connection: JObject| {
+            env.call_method(connection, "getState", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap()
+        };
+
+        connection.set_ringing().unwrap();
+        assert_eq!(state(*connection), STATE_RINGING);
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::StateChanged {
+                state: STATE_RINGING
+            }
+        ));
+
+        env.call_method(*connection, "onAnswer", "(I)V", &[0.into()])
+            .unwrap();
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::Answer { video_state: 0 }
+        ));
+
+        connection.set_active().unwrap();
+        assert_eq!(state(*connection), STATE_ACTIVE);
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::StateChanged {
+                state: STATE_ACTIVE
+            }
+        ));
+
+        env.call_method(*connection, "onHold", "()V", &[]).unwrap();
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::Hold
+        ));
+        connection.set_on_hold().unwrap();
+        assert_eq!(state(*connection), STATE_HOLDING);
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::StateChanged {
+                state: STATE_HOLDING
+            }
+        ));
+
+        env.call_method(
+            *connection,
+            "onPlayDtmfTone",
+            "(C)V",
+            &[jni::objects::JValue::Char('5' as u16)],
+        )
+        .unwrap();
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::PlayDtmfTone { digit: '5' }
+        ));
+        env.call_method(*connection, "onStopDtmfTone", "()V", &[])
+            .unwrap();
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::StopDtmfTone
+        ));
+
+        let audio_state = env
+            .new_object(
+                "android/telecom/CallAudioState",
+                "(ZII)V",
+                &[
+                    true.into(),
+                    ROUTE_SPEAKER.into(),
+                    (ROUTE_SPEAKER | ROUTE_EARPIECE).into(),
+                ],
+            )
+            .unwrap();
+        env.call_method(
+            *connection,
+            "onCallAudioStateChanged",
+            "(Landroid/telecom/CallAudioState;)V",
+            &[audio_state.into()],
+        )
+        .unwrap();
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::CallAudioStateChanged {
+                route: ROUTE_SPEAKER,
+                supported_route_mask,
+                muted: true,
+            } if supported_route_mask == ROUTE_SPEAKER | ROUTE_EARPIECE
+        ));
+
+        connection.set_caller_display_name("Alice").unwrap();
+        let name = env
+            .call_method(
+                *connection,
+                "getCallerDisplayName",
+                "()Ljava/lang/String;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(String::from(env.get_string(name.into()).unwrap()), "Alice");
+
+        connection.set_disconnected(DISCONNECT_LOCAL).unwrap();
+        assert_eq!(state(*connection), STATE_DISCONNECTED);
+        let cause = env
+            .call_method(
+                *connection,
+                "getDisconnectCause",
+                "()Landroid/telecom/DisconnectCause;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(cause, "getCode", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            DISCONNECT_LOCAL
+        );
+        assert!(matches!(
+            block_on(stream.next()).unwrap().unwrap(),
+            ConnectionEvent::StateChanged {
+                state: STATE_DISCONNECTED
+            }
+        ));
+
+        env.call_method(*connection, "onHold", "()V", &[]).unwrap();
+        assert!(block_on(stream.next()).is_none());
+        connection.destroy().unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testPost(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let arc = Arc::new(Mutex::new(false));
+        let arc2 = arc.clone();
+        let runnable = jni_utils::ops::fn_once_runnable(&env, move |_e, _o| {
+            let mut guard = arc2.lock().unwrap();
+            *guard = true;
+        })
+        .unwrap();
+
+        handler.post(runnable).unwrap();
+        {
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, false);
+        }
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, true);
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testPostDelayed(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::uptime;
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let millis = env
+            .get_static_field(
+                "java/util/concurrent/TimeUnit",
+                "MILLISECONDS",
+                "Ljava/util/concurrent/TimeUnit;",
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let idle_for = |millis_count: i64| {
+            env.call_method(
+                shadow_looper,
+                "idleFor",
+                "(JLjava/util/concurrent/TimeUnit;)V",
+                &[millis_count.into(), millis.into()],
+            )
+            .unwrap();
+        };
+
+        let count = Arc::new(Mutex::new(0));
+        let runnable = |count: Arc<Mutex<i32>>| {
+            jni_utils::ops::fn_once_runnable(&env, move |_e, _o| {
+                *count.lock().unwrap() += 1;
+            })
+            .unwrap()
+        };
+
+        assert!(handler
+            .post_delayed(runnable(count.clone()), Duration::from_millis(100))
+            .unwrap());
+        let at = uptime(&env).unwrap() + Duration::from_millis(300);
+        assert!(handler.post_at_time(runnable(count.clone()), at).unwrap());
+
+        idle_for(50);
+        assert_eq!(*count.lock().unwrap(), 0);
+        idle_for(100);
+        assert_eq!(*count.lock().unwrap(), 1);
+        idle_for(200);
+        assert_eq!(*count.lock().unwrap(), 2);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSendMessage(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::JMessage;
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let has_messages = |what: jint| {
+            env.call_method(*handler, "hasMessages", "(I)Z", &[what.into()])
+                .unwrap()
+                .z()
+                .unwrap()
+        };
+
+        let value = env.new_string("value").unwrap();
+        let msg = JMessage::obtain_with(&handler, 1, 2, 3, value.into()).unwrap();
+        assert_eq!(msg.what().unwrap(), 1);
+        assert_eq!(msg.arg1().unwrap(), 2);
+        assert_eq!(msg.arg2().unwrap(), 3);
+        assert!(env.is_same_object(msg.obj().unwrap(), value).unwrap());
+        msg.set_what(4).unwrap();
+        msg.set_arg1(5).unwrap();
+        msg.set_arg2(6).unwrap();
+        msg.set_obj(JObject::null()).unwrap();
+        assert_eq!(msg.what().unwrap(), 4);
+        assert_eq!(msg.arg1().unwrap(), 5);
+        assert_eq!(msg.arg2().unwrap(), 6);
+        assert!(msg.obj().unwrap().is_null());
+
+        assert!(handler.send_message(&msg).unwrap());
+        assert!(handler.send_empty_message(7).unwrap());
+        let delayed = JMessage::obtain(&env).unwrap();
+        delayed.set_what(8).unwrap();
+        assert!(handler
+            .send_message_delayed(&delayed, Duration::from_secs(10))
+            .unwrap());
+        assert!(has_messages(4));
+        assert!(has_messages(7));
+        assert!(has_messages(8));
+
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(!has_messages(4));
+        assert!(!has_messages(7));
+        assert!(has_messages(8));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testHandlerNativeThread(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::JHandler;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let handler_ref = env.new_global_ref(*handler).unwrap();
+
+        // Threads created by Rust can't find the app's classes with
+        // FindClass(), so this only works with the IDs cached by init().
+        std::thread::spawn(move || {
+            let env = android_utils::env().unwrap();
+            let handler = JHandler::from_env(&env, handler_ref.as_obj()).unwrap();
+            assert!(handler.send_empty_message(7).unwrap());
+        })
+        .join()
+        .unwrap();
+
+        let has_messages = |what: i32| {
+            env.call_method(*handler, "hasMessages", "(I)Z", &[what.into()])
+                .unwrap()
+                .z()
+                .unwrap()
+        };
+        assert!(has_messages(7));
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(!has_messages(7));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testPostToken(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let ran = Arc::new(Mutex::new(Vec::new()));
+        let runnable = |id: i32| {
+            let ran = ran.clone();
+            jni_utils::ops::fn_once_runnable(&env, move |_e, _o| {
+                ran.lock().unwrap().push(id);
+            })
+            .unwrap()
+        };
+
+        let token1 = handler
+            .post_cancellable(runnable(1), Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        let token2 = handler
+            .post_cancellable(runnable(2), Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        token2.cancel().unwrap();
+        assert!(handler.send_empty_message(3).unwrap());
+        handler.remove_messages(3).unwrap();
+        assert!(!env
+            .call_method(*handler, "hasMessages", "(I)Z", &[3.into()])
+            .unwrap()
+            .z()
+            .unwrap());
+
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert_eq!(*ran.lock().unwrap(), vec![1]);
+        token1.cancel().unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testLooper(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        let main = JLooper::main(&env).unwrap();
+        assert!(main.is_current_thread().unwrap());
+
+        let vm = env.get_java_vm().unwrap();
+        let ran = Arc::new(Mutex::new(false));
+        let ran2 = ran.clone();
+        std::thread::spawn(move || {
+            let env = vm.attach_current_thread().unwrap();
+            assert!(JLooper::my_looper(&env).unwrap().is_none());
+            let looper = JLooper::prepare(&env).unwrap();
+            assert!(JLooper::my_looper(&env).unwrap().is_some());
+
+            let looper_ref = env.new_global_ref(*looper).unwrap();
+            let runnable = jni_utils::ops::fn_once_runnable(&env, move |env, _o| {
+                *ran2.lock().unwrap() = true;
+                JLooper::from_env(env, looper_ref.as_obj())
+                    .unwrap()
+                    .quit()
+                    .unwrap();
+            })
+            .unwrap();
+            looper.handler().unwrap().post(runnable).unwrap();
+            JLooper::loop_(&env).unwrap();
+        })
+        .join()
+        .unwrap();
+        assert!(*ran.lock().unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testGlobalHandler(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::{channel::oneshot::channel, task::SpawnExt};
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let global = GlobalHandler::new(&handler).unwrap();
+
+        let ran = Arc::new(Mutex::new(false));
+        let ran2 = ran.clone();
+        let global2 = global.clone();
+        let posted = std::thread::spawn(move || {
+            global2
+                .post(move |_e, _o| {
+                    *ran2.lock().unwrap() = true;
+                })
+                .unwrap()
+        })
+        .join()
+        .unwrap();
+        assert!(posted);
+        assert!(!*ran.lock().unwrap());
+
+        let (tx, mut rx) = channel();
+        global
+            .spawn(async move {
+                tx.send(()).unwrap();
+            })
+            .unwrap();
+
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(*ran.lock().unwrap());
+        assert_eq!(rx.try_recv().unwrap(), Some(()));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testDelay(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::delay;
+        use futures::task::SpawnExt;
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let millis = env
+            .get_static_field(
+                "java/util/concurrent/TimeUnit",
+                "MILLISECONDS",
+                "Ljava/util/concurrent/TimeUnit;",
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let idle_for = |millis_count: i64| {
+            env.call_method(
+                shadow_looper,
+                "idleFor",
+                "(JLjava/util/concurrent/TimeUnit;)V",
+                &[millis_count.into(), millis.into()],
+            )
+            .unwrap();
+        };
+
+        let done = Arc::new(Mutex::new(false));
+        let done2 = done.clone();
+        let sleep = delay(&handler, Duration::from_millis(500)).unwrap();
+        let cancelled = delay(&handler, Duration::from_millis(100)).unwrap();
+        drop(cancelled);
+        handler
+            .spawner()
+            .spawn(async move {
+                sleep.await;
+                *done2.lock().unwrap() = true;
+            })
+            .unwrap();
+
+        idle_for(400);
+        assert!(!*done.lock().unwrap());
+        idle_for(100);
+        assert!(*done.lock().unwrap());
+        assert!(env
+            .call_method(shadow_looper, "isIdle", "()Z", &[])
+            .unwrap()
+            .z()
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testIdleHandler(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::FutureExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let queue = JLooper::main(&env).unwrap().queue().unwrap();
+        let idle = || {
+            let runnable = jni_utils::ops::fn_once_runnable(&env, |_e, _o| {}).unwrap();
+            handler.post(runnable).unwrap();
+            env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        };
+
+        let count = Arc::new(Mutex::new(0));
+        let count2 = count.clone();
+        let idle_handler = queue
+            .add_idle_handler(move |_env| {
+                *count2.lock().unwrap() += 1;
+                true
+            })
+            .unwrap();
+        let mut events = queue.idle_events().unwrap();
+
+        idle();
+        let seen = *count.lock().unwrap();
+        assert!(seen > 0);
+        assert_eq!(events.next().now_or_never(), Some(Some(())));
+
+        queue.remove_idle_handler(idle_handler).unwrap();
+        idle();
+        assert_eq!(*count.lock().unwrap(), seen);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testAsyncHandler(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::{channel::oneshot::channel, task::SpawnExt};
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        assert!(!handler.is_async());
+
+        let handler = JLooper::main(&env).unwrap().async_handler().unwrap();
+        assert!(handler.is_async());
+        assert!(env
+            .get_field(*handler, "mAsynchronous", "Z")
+            .unwrap()
+            .z()
+            .unwrap());
+
+        let (tx, mut rx) = channel();
+        handler
+            .spawner()
+            .spawn(async move {
+                tx.send(()).unwrap();
+            })
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), Some(()));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnWithHandle(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::FutureExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let spawner = handler.spawner();
+
+        let mut result = spawner.spawn_with_handle(async { 42 }).unwrap();
+        let mut aborted = spawner
+            .spawn_local_with_handle(futures::future::pending::<i32>())
+            .unwrap();
+        assert!((&mut result).now_or_never().is_none());
+
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert_eq!(result.now_or_never(), Some(Ok(42)));
+        assert!((&mut aborted).now_or_never().is_none());
+
+        aborted.abort().unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(aborted.abort_handle().is_aborted());
+        assert!(matches!(aborted.now_or_never(), Some(Err(_))));
+
+        struct SetOnDrop(Arc<Mutex<bool>>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                *self.0.lock().unwrap() = true;
+            }
+        }
+
+        let dropped = Arc::new(Mutex::new(false));
+        let guard = SetOnDrop(dropped.clone());
+        let (sender, receiver) = futures::channel::oneshot::channel::<()>();
+        let handle = spawner
+            .spawn_with_handle(async move {
+                let _guard = guard;
+                receiver.await
+            })
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert_eq!(*dropped.lock().unwrap(), false);
+        std::mem::drop(handle);
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert_eq!(*dropped.lock().unwrap(), true);
+        assert!(sender.is_canceled());
+
+        let (sender, receiver) = futures::channel::oneshot::channel::<()>();
+        let (done_sender, mut done) = futures::channel::oneshot::channel::<()>();
+        let abort = spawner
+            .spawn_with_handle(async move {
+                receiver.await.unwrap();
+                done_sender.send(()).unwrap();
+            })
+            .unwrap()
+            .detach();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        sender.send(()).unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(!abort.is_aborted());
+        assert_eq!(done.try_recv().unwrap(), Some(()));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testMessenger(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{async_messenger, JMessage};
+        use futures::FutureExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let looper = JLooper::main(&env).unwrap();
+        let (local, stream) = async_messenger(&env, &looper).unwrap();
+        let mut stream = JSendStream::try_from(stream).unwrap();
+
+        let remote = JMessenger::from_binder(&env, local.get_binder().unwrap()).unwrap();
+        let msg = JMessage::obtain(&env).unwrap();
+        msg.set_what(5).unwrap();
+        msg.set_reply_to(*local).unwrap();
+        remote.send(&msg).unwrap();
+
+        let vm = env.get_java_vm().unwrap();
+        let what = handler
+            .spawner()
+            .spawn_with_handle(async move {
+                let msg = stream.next().await.unwrap().unwrap();
+                let env = vm.get_env().unwrap();
+                let msg = JMessage::from_env(&env, msg.as_obj()).unwrap();
+                assert!(!msg.reply_to().unwrap().is_null());
+                msg.what().unwrap()
+            })
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert_eq!(what.now_or_never(), Some(Ok(5)));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawn(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::{channel::oneshot::channel, task::SpawnExt};
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let (sender, receiver) = channel::<()>();
+
+        let arc = Arc::new(Mutex::new(0));
+        let arc2 = arc.clone();
+
+        let closure = async move {
+            {
+                let mut guard = arc2.lock().unwrap();
+                *guard = 1;
+            }
+            receiver.await.unwrap();
+            {
+                let mut guard = arc2.lock().unwrap();
+                *guard = 2;
+            }
+        };
+
+        let handler_spawn = handler.spawner();
+
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+        handler_spawn.spawn(closure).unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 0);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+        sender.send(()).unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 1);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 2);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnAsyncSleep(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::task::SpawnExt;
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let arc = Arc::new(Mutex::new(0));
+        let arc2 = arc.clone();
+
+        let closure = async move {
+            {
+                let mut guard = arc2.lock().unwrap();
+                *guard = 1;
+            }
+            async_std::task::sleep(Duration::from_millis(500)).await;
+            {
+                let mut guard = arc2.lock().unwrap();
+                *guard = 2;
+            }
+        };
+
+        let handler_spawn = handler.spawner();
+
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+        handler_spawn.spawn(closure).unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 0);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+
+        // Wait until after the sleep() has completed. This is admittedly a bit
+        // racy.
+        std::thread::sleep(Duration::from_millis(1000));
+
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 1);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 2);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testLooperRuntime(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::LooperRuntime;
+        use futures::channel::oneshot::channel;
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let runtime = LooperRuntime::new(GlobalHandler::new(&handler).unwrap()).unwrap();
+        let main_thread = std::thread::current().id();
+
+        let (tx, mut rx) = channel();
+        runtime
+            .spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                tx.send(std::thread::current().id()).unwrap();
+            })
+            .unwrap();
+
+        let (aborted_tx, mut aborted_rx) = channel::<()>();
+        let abort = runtime
+            .spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                aborted_tx.send(()).unwrap();
+            })
+            .unwrap();
+        abort.abort().unwrap();
+
+        // The timer fires on the driver thread, which posts the task back
+        // to the main looper.
+        let mut result = None;
+        for _ in 0..200 {
+            env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+            result = rx.try_recv().unwrap();
+            if result.is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(result, Some(main_thread));
+        assert!(!matches!(aborted_rx.try_recv(), Ok(Some(()))));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnNativeThreadWake(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::{channel::oneshot::channel, task::SpawnExt};
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let (sender, receiver) = channel::<()>();
+
+        let arc = Arc::new(Mutex::new(0));
+        let arc2 = arc.clone();
+
+        let closure = async move {
+            {
+                let mut guard = arc2.lock().unwrap();
+                *guard = 1;
+            }
+            receiver.await.unwrap();
+            {
+                let mut guard = arc2.lock().unwrap();
+                *guard = 2;
+            }
+        };
+
+        let handler_spawn = handler.spawner();
+
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+        handler_spawn.spawn(closure).unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 0);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+
+        std::thread::spawn(|| {
+            sender.send(()).unwrap();
+        })
+        .join()
+        .unwrap();
+
+        // Wait until after the Runnable has posted. This is admittedly a bit
+        // racy.
+        std::thread::sleep(Duration::from_millis(500));
+
+        {
+            assert_eq!(Arc::strong_count(&arc), 2);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Arc::strong_count(&arc), 1);
+            let guard = arc.lock().unwrap();
+            assert_eq!(*guard, 2);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnThread(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::task::SpawnExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let handler_ref = env.new_global_ref(handler).unwrap();
+
+        let runnable = fn_once_runnable(&env, move |env, _obj| {
+            let handler = JHandler::from_env(env, handler_ref.as_obj()).unwrap();
+            let handler_spawn = handler.spawner();
+
+            handler_spawn.spawn(async {}).unwrap();
+        })
+        .unwrap();
+        let thread = env
+            .new_object(
+                "java/lang/Thread",
+                "(Ljava/lang/Runnable;)V",
+                &[runnable.into()],
+            )
+            .unwrap();
+        env.call_method(thread, "start", "()V", &[]).unwrap();
+        env.call_method(thread, "join", "()V", &[]).unwrap();
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnLocal(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::{channel::oneshot::channel, task::LocalSpawnExt};
+        use std::{cell::RefCell, rc::Rc};
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let (sender, receiver) = channel::<()>();
+
+        let rc = Rc::new(RefCell::new(0));
+        let rc2 = rc.clone();
+
+        let closure = async move {
+            {
+                let mut guard = rc2.borrow_mut();
+                *guard = 1;
+            }
+            receiver.await.unwrap();
+            {
+                let mut guard = rc2.borrow_mut();
+                *guard = 2;
+            }
+        };
+
+        let handler_spawn = handler.spawner();
+
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+        handler_spawn.spawn_local(closure).unwrap();
+        {
+            assert_eq!(Rc::strong_count(&rc), 2);
+            let guard = rc.borrow();
+            assert_eq!(*guard, 0);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Rc::strong_count(&rc), 2);
+            let guard = rc.borrow();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+        sender.send(()).unwrap();
+        {
+            assert_eq!(Rc::strong_count(&rc), 2);
+            let guard = rc.borrow();
+            assert_eq!(*guard, 1);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            false
+        );
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            assert_eq!(Rc::strong_count(&rc), 1);
+            let guard = rc.borrow();
+            assert_eq!(*guard, 2);
+        }
+        assert_eq!(
+            env.call_method(shadow_looper, "isIdle", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap(),
+            true
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnLocalThread(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::task::LocalSpawnExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let handler_ref = env.new_global_ref(handler).unwrap();
+
+        let runnable = fn_once_runnable(&env, move |env, _obj| {
+            let handler = JHandler::from_env(env, handler_ref.as_obj()).unwrap();
+            let handler_spawn = handler.spawner();
+
+            handler_spawn.spawn_local(async {}).unwrap();
+        })
+        .unwrap();
+        let thread = env
+            .new_object(
+                "java/lang/Thread",
+                "(Ljava/lang/Runnable;)V",
+                &[runnable.into()],
+            )
+            .unwrap();
+        env.call_method(thread, "start", "()V", &[]).unwrap();
+        env.call_method(thread, "join", "()V", &[]).unwrap();
+
+        let result = try_block(&env, || {
+            env.call_method(shadow_looper, "runOneTask", "()V", &[])?;
+            Ok(false)
+        })
+        .catch(
+            "io/github/gedgygedgy/rust/thread/LocalThreadException",
+            |_ex| Ok(true),
+        )
+        .result();
+        assert_eq!(result.unwrap(), true);
+    });
+}
+
+struct DropFlag(Arc<Mutex<bool>>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap() = true;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnAbortable(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let spawner = handler.spawner();
+
+        let polled = Arc::new(Mutex::new(0));
+        let dropped = Arc::new(Mutex::new(false));
+        let polled_clone = polled.clone();
+        let flag = DropFlag(dropped.clone());
+        let abort_handle = spawner
+            .spawn_abortable(futures::future::poll_fn(move |_cx| {
+                let _flag = &flag;
+                *polled_clone.lock().unwrap() += 1;
+                std::task::Poll::Pending
+            }))
+            .unwrap();
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*polled.lock().unwrap(), 1);
+        assert!(!*dropped.lock().unwrap());
+
+        assert!(!abort_handle.is_aborted());
+        abort_handle.abort().unwrap();
+        assert!(abort_handle.is_aborted());
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*polled.lock().unwrap(), 1);
+        assert!(*dropped.lock().unwrap());
+
+        let polled = Arc::new(Mutex::new(0));
+        let dropped = Arc::new(Mutex::new(false));
+        let polled_clone = polled.clone();
+        let flag = DropFlag(dropped.clone());
+        let abort_handle = spawner
+            .spawn_local_abortable(futures::future::poll_fn(move |_cx| {
+                let _flag = &flag;
+                *polled_clone.lock().unwrap() += 1;
+                std::task::Poll::Pending
+            }))
+            .unwrap();
+        abort_handle.abort().unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*polled.lock().unwrap(), 0);
+        assert!(*dropped.lock().unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testYieldNow(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{consume_budget, yield_now};
+        use futures::task::SpawnExt;
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let spawner = handler.spawner();
+
+        let progress = Arc::new(Mutex::new(0));
+        let progress_clone = progress.clone();
+        spawner
+            .spawn(async move {
+                *progress_clone.lock().unwrap() = 1;
+                consume_budget().await;
+                *progress_clone.lock().unwrap() = 2;
+                yield_now().await;
+                *progress_clone.lock().unwrap() = 3;
+            })
+            .unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*progress.lock().unwrap(), 2);
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*progress.lock().unwrap(), 3);
+
+        let spawner = spawner.with_poll_budget(Duration::from_secs(0));
+        let progress = Arc::new(Mutex::new(0));
+        let progress_clone = progress.clone();
+        spawner
+            .spawn(async move {
+                *progress_clone.lock().unwrap() = 1;
+                consume_budget().await;
+                *progress_clone.lock().unwrap() = 2;
+            })
+            .unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*progress.lock().unwrap(), 1);
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*progress.lock().unwrap(), 2);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnMetrics(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::yield_now;
+        use futures::task::SpawnExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let spawner = handler.spawner();
+
+        let metrics = spawner.metrics();
+        assert_eq!(metrics.tasks_spawned, 0);
+        assert_eq!(metrics.tasks_pending, 0);
+        assert_eq!(metrics.polls, 0);
+
+        spawner
+            .spawn(async move {
+                yield_now().await;
+            })
+            .unwrap();
+        let metrics = spawner.metrics();
+        assert_eq!(metrics.tasks_spawned, 1);
+        assert_eq!(metrics.tasks_pending, 1);
+        assert_eq!(metrics.polls, 0);
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        let metrics = spawner.metrics();
+        assert_eq!(metrics.tasks_pending, 1);
+        assert_eq!(metrics.polls, 1);
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        let metrics = spawner.metrics();
+        assert_eq!(metrics.tasks_spawned, 1);
+        assert_eq!(metrics.tasks_pending, 0);
+        assert_eq!(metrics.polls, 2);
+        assert_eq!(metrics.fallback_wakes, 0);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnOptions(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::os::{SpawnOptions, SpawnToken};
+        use futures::task::SpawnExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let order_clone = order.clone();
+        let runnable = fn_once_runnable(&env, move |_env, _obj| {
+            order_clone.lock().unwrap().push("runnable");
+        })
+        .unwrap();
+        assert!(handler.post(runnable).unwrap());
+
+        let token = SpawnToken::new(&env).unwrap();
+        let spawner = handler.spawner().with_options(SpawnOptions {
+            front_of_queue: true,
+            asynchronous: true,
+            token: Some(token.clone()),
+            ..Default::default()
+        });
+        let order_clone = order.clone();
+        spawner
+            .spawn(async move {
+                order_clone.lock().unwrap().push("task");
+            })
+            .unwrap();
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*order.lock().unwrap(), vec!["task", "runnable"]);
+
+        let polled = Arc::new(Mutex::new(0));
+        let mut dropped = Vec::new();
+        for _ in 0..2 {
+            let polled_clone = polled.clone();
+            let flag = Arc::new(Mutex::new(false));
+            let drop_flag = DropFlag(flag.clone());
+            dropped.push(flag);
+            spawner
+                .spawn(futures::future::poll_fn(move |_cx| {
+                    let _flag = &drop_flag;
+                    *polled_clone.lock().unwrap() += 1;
+                    std::task::Poll::<()>::Pending
+                }))
+                .unwrap();
+        }
+        token.cancel().unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert_eq!(*polled.lock().unwrap(), 0);
+        for flag in dropped {
+            assert!(*flag.lock().unwrap());
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testSpawnJniError(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::{channel::oneshot::channel, task::SpawnExt};
+
+        let looper = JLooper::main(&env).unwrap();
+        let obj = env
+            .new_object(
+                "io/github/gedgygedgy/rust/android/HandlerTest$ThrowingHandler",
+                "(Landroid/os/Looper;)V",
+                &[(*looper).into()],
+            )
+            .unwrap();
+        let handler_spawn = JHandler::from_env(&env, obj).unwrap().spawner();
+
+        let (tx, mut rx) = channel::<()>();
+        let err = handler_spawn
+            .spawn(async move {
+                tx.send(()).unwrap();
+            })
+            .unwrap_err();
+        assert!(err.is_shutdown());
+        assert!(!matches!(rx.try_recv(), Ok(Some(()))));
+
+        let exception = env.exception_occurred().unwrap();
+        env.exception_clear().unwrap();
+        assert!(env
+            .is_instance_of(exception, "java/lang/IllegalStateException")
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_HandlerTest_testRustHandlerCallback(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::task::SpawnExt;
+
+        let handler_data = Arc::new(Mutex::new(None));
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let (callback, stream) = async_handler_callback(&env).unwrap();
+        let mut stream = JSendStream::try_from(stream).unwrap();
+
+        let handler_data_clone = handler_data.clone();
+        let task = async move {
+            let msg = stream.next().await.unwrap().unwrap();
+            let mut guard = handler_data_clone.lock().unwrap();
+            *guard = Some(msg);
+        };
+
+        handler.spawner().spawn(task).unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            let guard = handler_data.lock().unwrap();
+            assert!(guard.is_none());
+        }
+
+        let message = env
+            .call_static_method(
+                "android/os/Message",
+                "obtain",
+                "()Landroid/os/Message;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let result = env
+            .call_method(
+                callback,
+                "handleMessage",
+                "(Landroid/os/Message;)Z",
+                &[message.into()],
+            )
+            .unwrap()
+            .z()
+            .unwrap();
+        assert_eq!(result, false);
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            let guard = handler_data.lock().unwrap();
+            assert!(env
+                .is_same_object(message, guard.as_ref().unwrap().as_obj())
+                .unwrap());
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustServiceConnection(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::task::SpawnExt;
+
+        let (conn, mut stream) = async_service_connection(&env).unwrap();
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+
+        let pkg = env.new_string("io.github.gedgygedgy.rust.android").unwrap();
+        let cls = env
+            .new_string("io.github.gedgygedgy.rust.android.ServiceTest$TestService")
+            .unwrap();
+        let component_name = env
+            .new_object(
+                "android/content/ComponentName",
+                "(Ljava/lang/String;Ljava/lang/String;)V",
+                &[pkg.into(), cls.into()],
+            )
+            .unwrap();
+        let component_name_ref = env.new_global_ref(component_name).unwrap();
+
+        let service = JMessenger::new(&handler).unwrap().get_binder().unwrap();
+        let service_ref = env.new_global_ref(service).unwrap();
+
+        let vm = env.get_java_vm().unwrap();
+        let finished = Arc::new(Mutex::new(false));
+        let finished_clone = finished.clone();
+        let task = async move {
+            let item = stream.next().await.unwrap().unwrap();
+            if let ServiceConnectionEvent::BindingDied { component_name } = item {
+                let env = vm.get_env().unwrap();
+                assert!(env
+                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
+                    .unwrap());
+            } else {
+                panic!("Expected BindingDied");
+            }
+
+            let item = stream.next().await.unwrap().unwrap();
+            if let ServiceConnectionEvent::NullBinding { component_name } = item {
+                let env = vm.get_env().unwrap();
+                assert!(env
+                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
+                    .unwrap());
+            } else {
+                panic!("Expected NullBinding");
+            }
+
+            let item = stream.next().await.unwrap().unwrap();
+            if let ServiceConnectionEvent::ServiceConnected {
+                component_name,
+                service,
+            } = item
+            {
+                let env = vm.get_env().unwrap();
+                assert!(env
+                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
+                    .unwrap());
+                assert!(env
+                    .is_same_object(service.as_obj(), service_ref.as_obj())
+                    .unwrap());
+            } else {
+                panic!("Expected ServiceConnected");
+            }
+
+            let item = stream.next().await.unwrap().unwrap();
+            if let ServiceConnectionEvent::ServiceDisconnected { component_name } = item {
+                let env = vm.get_env().unwrap();
+                assert!(env
+                    .is_same_object(component_name.as_obj(), component_name_ref.as_obj())
+                    .unwrap());
+            } else {
+                panic!("Expected ServiceDisconnected");
+            }
+
+            let mut guard = finished_clone.lock().unwrap();
+            *guard = true;
+        };
+
+        handler.spawner().spawn(task).unwrap();
+
+        env.call_method(
+            conn,
+            "onBindingDied",
+            "(Landroid/content/ComponentName;)V",
+            &[component_name.into()],
+        )
+        .unwrap();
+        env.call_method(
+            conn,
+            "onNullBinding",
+            "(Landroid/content/ComponentName;)V",
+            &[component_name.into()],
+        )
+        .unwrap();
+        env.call_method(
+            conn,
+            "onServiceConnected",
+            "(Landroid/content/ComponentName;Landroid/os/IBinder;)V",
+            &[component_name.into(), service.into()],
+        )
+        .unwrap();
+        env.call_method(
+            conn,
+            "onServiceDisconnected",
+            "(Landroid/content/ComponentName;)V",
+            &[component_name.into()],
+        )
+        .unwrap();
+
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        {
+            let guard = finished.lock().unwrap();
+            assert!(*guard);
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustService(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        struct TestServiceData {
+            created: bool,
+            binder: GlobalRef,
+            intent: Option<GlobalRef>,
+            rebound: bool,
+            start_flags: Option<StartFlags>,
+            start_id: Option<jint>,
+            destroyed: bool,
+            trim_level: Option<jint>,
+            low_memory: bool,
+        }
+
+        struct TestService(Arc<Mutex<TestServiceData>>);
+
+        impl Drop for TestService {
+            fn drop(&mut self) {
+                let mut guard = self.0.lock().unwrap();
+                guard.created = false;
+            }
+        }
+
+        impl RustService for TestService {
+            fn on_bind<'a: 'b, 'b>(
+                &self,
+                env: &'b JNIEnv<'a>,
+                service: &ServiceContext<'a, 'b>,
+                intent: JObject<'a>,
+            ) -> JObject<'a> {
+                assert!(env
+                    .is_instance_of(
+                        **service,
+                        "io/github/gedgygedgy/rust/android/ServiceTest$TestRustService"
+                    )
+                    .unwrap());
+                let mut guard = self.0.lock().unwrap();
+                guard.intent = Some(env.new_global_ref(intent).unwrap());
+                guard.binder.as_obj().into_inner().into()
+            }
+
+            fn on_unbind<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+            ) -> bool {
+                let mut guard = self.0.lock().unwrap();
+                guard.intent = None;
+                true
+            }
+
+            fn on_rebind<'a: 'b, 'b>(
+                &self,
+                env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                intent: JObject<'a>,
+            ) {
+                let mut guard = self.0.lock().unwrap();
+                guard.intent = Some(env.new_global_ref(intent).unwrap());
+                guard.rebound = true;
+            }
+
+            fn on_start_command<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+                flags: StartFlags,
+                start_id: jint,
+            ) -> ServiceStartResult {
+                let mut guard = self.0.lock().unwrap();
+                guard.start_id = Some(start_id);
+                guard.start_flags = Some(flags);
+                ServiceStartResult::Sticky
+            }
+
+            fn on_destroy<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+            ) {
+                let mut guard = self.0.lock().unwrap();
+                assert!(guard.created);
+                guard.destroyed = true;
+            }
+
+            fn on_trim_memory<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                level: jint,
+            ) {
+                let mut guard = self.0.lock().unwrap();
+                guard.trim_level = Some(level);
+            }
+
+            fn on_low_memory<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+            ) {
+                let mut guard = self.0.lock().unwrap();
+                guard.low_memory = true;
+            }
+        }
+
+        let (_shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let binder = JMessenger::new(&handler).unwrap().get_binder().unwrap();
+        let binder_ref = env.new_global_ref(binder).unwrap();
+
+        let data = Arc::new(Mutex::new(TestServiceData {
+            created: false,
+            binder: binder_ref.clone(),
+            intent: None,
+            rebound: false,
+            start_flags: None,
+            start_id: None,
+            destroyed: false,
+            trim_level: None,
+            low_memory: false,
+        }));
+        let data_clone = data.clone();
+
+        let factory = move |_env: &JNIEnv, _obj: JObject| {
+            {
+                let mut guard = data_clone.lock().unwrap();
+                guard.created = true;
+            }
+            TestService(data_clone.clone())
+        };
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustService")
+            .unwrap();
+        let registration = register_service(&env, class, factory).unwrap();
+
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.created, false);
+            assert!(guard.intent.is_none());
+        }
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustService")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Landroid/content/Context;Ljava/lang/Class;)V",
+                &[context.into(), class.into()],
+            )
+            .unwrap();
+
+        let service = env.new_object(class, "()V", &[]).unwrap();
+
+        let service_controller = env.call_static_method(
+            "org/robolectric/android/controller/ServiceController",
+            "of",
+            "(Landroid/app/Service;Landroid/content/Intent;)Lorg/robolectric/android/controller/ServiceController;",
+            &[service.into(), intent.into()],
+        )
+           .unwrap().l().unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.created, false);
+            assert!(guard.intent.is_none());
+        }
+
+        env.call_method(
+            service_controller,
+            "create",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.created, true);
+            assert!(guard.intent.is_none());
+            assert!(guard.start_id.is_none());
+        }
+        let instance_id = {
+            let instances = service_instances::<TestService>();
+            assert_eq!(instances.len(), 1);
+            assert!(env
+                .is_same_object(instances[0].handle().as_obj(), service)
+                .unwrap());
+            assert_eq!(
+                ServiceContext::from_env(&env, service)
+                    .unwrap()
+                    .instance_id()
+                    .unwrap(),
+                Some(instances[0].id())
+            );
+            instances[0].id()
+        };
+
+        env.call_method(
+            service_controller,
+            "bind",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.created, true);
+            assert!(env
+                .is_same_object(guard.intent.as_ref().unwrap(), intent)
+                .unwrap());
+            assert_eq!(guard.rebound, false);
+            assert!(guard.start_id.is_none());
+        }
+
+        env.call_method(
+            service_controller,
+            "unbind",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
             let guard = data.lock().unwrap();
             assert_eq!(guard.created, true);
             assert!(guard.intent.is_none());
@@ -945,52 +5573,1960 @@ pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustSer
         }
 
         env.call_method(
-            service_controller,
-            "rebind",
-            "()Lorg/robolectric/android/controller/ServiceController;",
-            &[],
+            service_controller,
+            "rebind",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.created, true);
+            assert!(env
+                .is_same_object(guard.intent.as_ref().unwrap(), intent)
+                .unwrap());
+            assert_eq!(guard.rebound, true);
+            assert!(guard.start_id.is_none());
+        }
+
+        env.call_method(
+            service_controller,
+            "unbind",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.created, true);
+            assert!(guard.intent.is_none());
+            assert_eq!(guard.rebound, true);
+            assert!(guard.start_id.is_none());
+        }
+
+        env.call_method(
+            service_controller,
+            "startCommand",
+            "(II)Lorg/robolectric/android/controller/ServiceController;",
+            &[jint::from(StartFlags::RETRY).into(), 42.into()],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.start_id.unwrap(), 42);
+            assert_eq!(guard.start_flags.unwrap(), StartFlags::RETRY);
+        }
+
+        env.call_method(
+            service_controller,
+            "startCommand",
+            "(II)Lorg/robolectric/android/controller/ServiceController;",
+            &[
+                jint::from(StartFlags::REDELIVERY | StartFlags::RETRY).into(),
+                43.into(),
+            ],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.start_id.unwrap(), 43);
+            let flags = guard.start_flags.unwrap();
+            assert!(flags.contains(StartFlags::REDELIVERY));
+            assert!(flags.contains(StartFlags::RETRY));
+            assert_eq!(flags.bits(), 3);
+        }
+
+        let service_obj = env
+            .call_method(service_controller, "get", "()Ljava/lang/Object;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            service_obj,
+            "onTrimMemory",
+            "(I)V",
+            &[android_utils::service::TRIM_MEMORY_BACKGROUND.into()],
+        )
+        .unwrap();
+        env.call_method(service_obj, "onLowMemory", "()V", &[])
+            .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(
+                guard.trim_level,
+                Some(android_utils::service::TRIM_MEMORY_BACKGROUND)
+            );
+            assert!(guard.low_memory);
+        }
+
+        env.call_method(
+            service_controller,
+            "destroy",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.created, false);
+            assert!(guard.destroyed);
+        }
+        assert!(service_instance::<TestService>(instance_id).is_none());
+        assert!(service_instances::<TestService>().is_empty());
+
+        let on_create_hooks = env
+            .get_static_field(
+                "io/github/gedgygedgy/rust/android/app/RustService",
+                "onCreateHooks",
+                "Ljava/util/HashMap;",
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let contains_class = |env: &JNIEnv| {
+            env.call_method(
+                on_create_hooks,
+                "containsKey",
+                "(Ljava/lang/Object;)Z",
+                &[class.into()],
+            )
+            .unwrap()
+            .z()
+            .unwrap()
+        };
+        assert!(contains_class(&env));
+        drop(registration);
+        assert!(!contains_class(&env));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustHostApduService(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::nfc::{register_host_apdu_service, RustHostApduService};
+
+        struct TestServiceData {
+            created: bool,
+            apdu: Option<Vec<u8>>,
+            deactivated: Option<jint>,
+        }
+
+        struct TestService(Arc<Mutex<TestServiceData>>);
+
+        impl Drop for TestService {
+            fn drop(&mut self) {
+                let mut guard = self.0.lock().unwrap();
+                guard.created = false;
+            }
+        }
+
+        impl RustHostApduService for TestService {
+            fn process_command_apdu<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                apdu: &[u8],
+            ) -> Vec<u8> {
+                let mut guard = self.0.lock().unwrap();
+                guard.apdu = Some(apdu.to_vec());
+                vec![0x90, 0x00]
+            }
+
+            fn on_deactivated<'a: 'b, 'b>(&self, _env: &'b JNIEnv<'a>, reason: jint) {
+                let mut guard = self.0.lock().unwrap();
+                guard.deactivated = Some(reason);
+            }
+        }
+
+        let data = Arc::new(Mutex::new(TestServiceData {
+            created: false,
+            apdu: None,
+            deactivated: None,
+        }));
+        let data_clone = data.clone();
+
+        let factory = move |_env: &JNIEnv, _obj: JObject| {
+            {
+                let mut guard = data_clone.lock().unwrap();
+                guard.created = true;
+            }
+            TestService(data_clone.clone())
+        };
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustHostApduService")
+            .unwrap();
+        register_host_apdu_service(&env, class, factory).unwrap();
+
+        let service = env.new_object(class, "()V", &[]).unwrap();
+        let service_controller = env
+            .call_static_method(
+                "org/robolectric/android/controller/ServiceController",
+                "of",
+                "(Landroid/app/Service;Landroid/content/Intent;)Lorg/robolectric/android/controller/ServiceController;",
+                &[service.into(), JObject::null().into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        env.call_method(
+            service_controller,
+            "create",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert!(guard.created);
+            assert!(guard.apdu.is_none());
+        }
+
+        let apdu = env
+            .byte_array_from_slice(&[0x00, 0xa4, 0x04, 0x00])
+            .unwrap();
+        let response = env
+            .call_method(
+                service,
+                "processCommandApdu",
+                "([BLandroid/os/Bundle;)[B",
+                &[JObject::from(apdu).into(), JObject::null().into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.convert_byte_array(response.into_inner()).unwrap(),
+            vec![0x90, 0x00]
+        );
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(guard.apdu.as_deref(), Some(&[0x00, 0xa4, 0x04, 0x00][..]));
+            assert!(guard.deactivated.is_none());
+        }
+
+        env.call_method(
+            service,
+            "onDeactivated",
+            "(I)V",
+            &[android_utils::nfc::DEACTIVATION_DESELECTED.into()],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert_eq!(
+                guard.deactivated,
+                Some(android_utils::nfc::DEACTIVATION_DESELECTED)
+            );
+        }
+
+        env.call_method(
+            service_controller,
+            "destroy",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        {
+            let guard = data.lock().unwrap();
+            assert!(!guard.created);
+        }
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testServiceForeground(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{FOREGROUND_SERVICE_TYPE_DATA_SYNC, STOP_FOREGROUND_REMOVE};
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let service = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "setupService",
+                "(Ljava/lang/Class;)Landroid/app/Service;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[service.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let notification = env
+            .new_object("android/app/Notification", "()V", &[])
+            .unwrap();
+        let service = ServiceContext::from_env(&env, service).unwrap();
+        service
+            .start_foreground(1, notification, FOREGROUND_SERVICE_TYPE_DATA_SYNC)
+            .unwrap();
+        assert_eq!(
+            env.call_method(shadow, "getLastForegroundNotificationId", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            1
+        );
+
+        service.stop_foreground(STOP_FOREGROUND_REMOVE).unwrap();
+        assert!(env
+            .call_method(shadow, "isForegroundStopped", "()Z", &[])
+            .unwrap()
+            .z()
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testForegroundNotification(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::FOREGROUND_SERVICE_TYPE_DATA_SYNC;
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let service = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "setupService",
+                "(Ljava/lang/Class;)Landroid/app/Service;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let builder = env
+            .new_object(
+                "android/app/Notification$Builder",
+                "(Landroid/content/Context;)V",
+                &[service.into()],
+            )
+            .unwrap();
+
+        let notification = ServiceContext::from_env(&env, service)
+            .unwrap()
+            .start_foreground_notification(2, builder, FOREGROUND_SERVICE_TYPE_DATA_SYNC)
+            .unwrap();
+        assert_eq!(notification.id(), 2);
+        notification.set_progress(100, 42, false).unwrap();
+        notification.set_content_text("Working").unwrap();
+
+        let name = env.new_string("notification").unwrap();
+        let manager = env
+            .call_method(
+                service,
+                "getSystemService",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[name.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[manager.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let posted = env
+            .call_method(
+                shadow,
+                "getNotification",
+                "(I)Landroid/app/Notification;",
+                &[2.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let extras = env
+            .get_field(posted, "extras", "Landroid/os/Bundle;")
+            .unwrap()
+            .l()
+            .unwrap();
+        let get_int = |key: &str| {
+            let key = env.new_string(key).unwrap();
+            env.call_method(extras, "getInt", "(Ljava/lang/String;)I", &[key.into()])
+                .unwrap()
+                .i()
+                .unwrap()
+        };
+        assert_eq!(get_int("android.progress"), 42);
+        assert_eq!(get_int("android.progressMax"), 100);
+        let key = env.new_string("android.text").unwrap();
+        let text = env
+            .call_method(
+                extras,
+                "getCharSequence",
+                "(Ljava/lang/String;)Ljava/lang/CharSequence;",
+                &[key.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let text = env
+            .call_method(text, "toString", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let text: String = env.get_string(text.into()).unwrap().into();
+        assert_eq!(text, "Working");
+
+        notification.clear_progress().unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testServiceHandle(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let service = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "setupService",
+                "(Ljava/lang/Class;)Landroid/app/Service;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[service.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let handle = ServiceContext::from_env(&env, service)
+            .unwrap()
+            .handle()
+            .unwrap();
+        std::thread::spawn(move || handle.stop_self().unwrap())
+            .join()
+            .unwrap();
+        assert!(env
+            .call_method(shadow, "isStoppedBySelf", "()Z", &[])
+            .unwrap()
+            .z()
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testAsyncRustService(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{register_async_service, AsyncRustService};
+        use futures::{channel::oneshot, future::BoxFuture, FutureExt};
+
+        struct TestService(Mutex<Option<oneshot::Receiver<()>>>);
+
+        impl RustService for TestService {
+            fn on_bind<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+            ) -> JObject<'a> {
+                JObject::null()
+            }
+        }
+
+        impl AsyncRustService for TestService {
+            fn on_start_command_async<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+                _flags: StartFlags,
+                _start_id: jint,
+            ) -> BoxFuture<'static, ()> {
+                let receiver = self.0.lock().unwrap().take().unwrap();
+                async move {
+                    let _ = receiver.await;
+                }
+                .boxed()
+            }
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let receiver = Mutex::new(Some(receiver));
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestAsyncRustService")
+            .unwrap();
+        let _registration = register_async_service(&env, class, move |_env, _obj| {
+            TestService(Mutex::new(receiver.lock().unwrap().take()))
+        })
+        .unwrap();
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let service_controller = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "buildService",
+                "(Ljava/lang/Class;)Lorg/robolectric/android/controller/ServiceController;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            service_controller,
+            "create",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        env.call_method(
+            service_controller,
+            "startCommand",
+            "(II)Lorg/robolectric/android/controller/ServiceController;",
+            &[0.into(), 1.into()],
+        )
+        .unwrap();
+        let service = env
+            .call_method(service_controller, "get", "()Ljava/lang/Object;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[service.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let stopped = || {
+            env.call_method(shadow, "isStoppedBySelf", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap()
+        };
+
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(!stopped());
+
+        sender.send(()).unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(stopped());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testAsyncRustServiceManyStarts(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{register_async_service, AsyncRustService};
+        use futures::{future::BoxFuture, FutureExt};
+
+        struct TestService;
+
+        impl RustService for TestService {
+            fn on_bind<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+            ) -> JObject<'a> {
+                JObject::null()
+            }
+        }
+
+        impl AsyncRustService for TestService {
+            fn on_start_command_async<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+                _flags: StartFlags,
+                _start_id: jint,
+            ) -> BoxFuture<'static, ()> {
+                async {}.boxed()
+            }
+
+            fn start_mode(&self) -> ServiceStartResult {
+                ServiceStartResult::Sticky
+            }
+        }
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestAsyncRustService")
+            .unwrap();
+        let _registration = register_async_service(&env, class, |_env, _obj| TestService).unwrap();
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let queue = env
+            .call_method(
+                *JLooper::main(&env).unwrap(),
+                "getQueue",
+                "()Landroid/os/MessageQueue;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+
+        let service_controller = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "buildService",
+                "(Ljava/lang/Class;)Lorg/robolectric/android/controller/ServiceController;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            service_controller,
+            "create",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+
+        let service = env
+            .call_method(service_controller, "get", "()Ljava/lang/Object;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+
+        // Keep a weak reference to the runnable of each task. The adapter
+        // holds a global reference to it for as long as it tracks the task.
+        let mut runnables = Vec::new();
+        for start_id in 1..=100 {
+            // Call onStartCommand() directly, since ServiceController may
+            // idle the main looper afterwards.
+            env.call_method(
+                service,
+                "onStartCommand",
+                "(Landroid/content/Intent;II)I",
+                &[JObject::null().into(), 0.into(), start_id.into()],
+            )
+            .unwrap();
+            let message = env
+                .get_field(queue, "mMessages", "Landroid/os/Message;")
+                .unwrap()
+                .l()
+                .unwrap();
+            let runnable = env
+                .get_field(message, "callback", "Ljava/lang/Runnable;")
+                .unwrap()
+                .l()
+                .unwrap();
+            assert!(!runnable.is_null());
+            let weak = env
+                .new_object(
+                    "java/lang/ref/WeakReference",
+                    "(Ljava/lang/Object;)V",
+                    &[runnable.into()],
+                )
+                .unwrap();
+            runnables.push(env.new_global_ref(weak).unwrap());
+            env.delete_local_ref(weak).unwrap();
+            env.delete_local_ref(runnable).unwrap();
+            env.delete_local_ref(message).unwrap();
+            env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        }
+
+        let collected = || {
+            runnables.iter().all(|weak| {
+                let runnable = env
+                    .call_method(weak.as_obj(), "get", "()Ljava/lang/Object;", &[])
+                    .unwrap()
+                    .l()
+                    .unwrap();
+                let collected = runnable.is_null();
+                env.delete_local_ref(runnable).unwrap();
+                collected
+            })
+        };
+        let mut all_collected = false;
+        for _ in 0..10 {
+            env.call_static_method("java/lang/System", "gc", "()V", &[])
+                .unwrap();
+            all_collected = collected();
+            if all_collected {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(all_collected);
+
+        env.call_method(
+            service_controller,
+            "destroy",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testBindService(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{bind_service, unbind_service, BIND_AUTO_CREATE};
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[context.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Landroid/content/Context;Ljava/lang/Class;)V",
+                &[context.into(), class.into()],
+            )
+            .unwrap();
+        let (conn, _stream) = async_service_connection(&env).unwrap();
+
+        assert!(bind_service(&env, context, intent, conn, BIND_AUTO_CREATE).unwrap());
+        let bound = env
+            .call_method(
+                shadow,
+                "getBoundServiceConnections",
+                "()Ljava/util/List;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(env
+            .call_method(bound, "contains", "(Ljava/lang/Object;)Z", &[conn.into()])
+            .unwrap()
+            .z()
+            .unwrap());
+
+        unbind_service(&env, context, conn).unwrap();
+        let unbound = env
+            .call_method(
+                shadow,
+                "getUnboundServiceConnections",
+                "()Ljava/util/List;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(env
+            .call_method(unbound, "contains", "(Ljava/lang/Object;)Z", &[conn.into()])
+            .unwrap()
+            .z()
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testConnectService(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{
+            connect_service, unbind_service, ConnectServiceError, BIND_AUTO_CREATE,
+        };
+        use futures::task::SpawnExt;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[context.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Landroid/content/Context;Ljava/lang/Class;)V",
+                &[context.into(), class.into()],
+            )
+            .unwrap();
+        let component_name = env
+            .call_method(
+                intent,
+                "getComponent",
+                "()Landroid/content/ComponentName;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let binder = JMessenger::new(&handler).unwrap().get_binder().unwrap();
+        let binder_ref = env.new_global_ref(binder).unwrap();
+        env.call_method(
+            shadow,
+            "setComponentNameAndServiceForBindServiceForIntent",
+            "(Landroid/content/Intent;Landroid/content/ComponentName;Landroid/os/IBinder;)V",
+            &[intent.into(), component_name.into(), binder.into()],
+        )
+        .unwrap();
+
+        let (conn, future) = connect_service(&env, context, intent, BIND_AUTO_CREATE).unwrap();
+        let result = Arc::new(Mutex::new(None));
+        let result_clone = result.clone();
+        handler
+            .spawner()
+            .spawn(async move {
+                *result_clone.lock().unwrap() = Some(future.await);
+            })
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        {
+            let guard = result.lock().unwrap();
+            let service = guard.as_ref().unwrap().as_ref().unwrap();
+            assert!(env
+                .is_same_object(service.as_obj(), binder_ref.as_obj())
+                .unwrap());
+        }
+        unbind_service(&env, context, conn).unwrap();
+
+        let action = env
+            .new_string("io.github.gedgygedgy.rust.android.UNBINDABLE")
+            .unwrap();
+        env.call_method(
+            shadow,
+            "declareActionUnbindable",
+            "(Ljava/lang/String;)V",
+            &[action.into()],
+        )
+        .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Ljava/lang/String;)V",
+                &[action.into()],
+            )
+            .unwrap();
+        assert!(matches!(
+            connect_service(&env, context, intent, BIND_AUTO_CREATE),
+            Err(ConnectServiceError::BindFailed)
+        ));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testResilientConnection(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{Backoff, BinderState, ResilientConnection, BIND_AUTO_CREATE};
+        use futures::task::SpawnExt;
+        use std::time::Duration;
+
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[context.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Landroid/content/Context;Ljava/lang/Class;)V",
+                &[context.into(), class.into()],
+            )
+            .unwrap();
+        let component_name = env
+            .call_method(
+                intent,
+                "getComponent",
+                "()Landroid/content/ComponentName;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let binder = JMessenger::new(&handler).unwrap().get_binder().unwrap();
+        let binder_ref = env.new_global_ref(binder).unwrap();
+        env.call_method(
+            shadow,
+            "setComponentNameAndServiceForBindServiceForIntent",
+            "(Landroid/content/Intent;Landroid/content/ComponentName;Landroid/os/IBinder;)V",
+            &[intent.into(), component_name.into(), binder.into()],
+        )
+        .unwrap();
+
+        let backoff = Backoff {
+            initial: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let looper = JLooper::main(&env).unwrap();
+        let (conn, mut stream) =
+            ResilientConnection::new(&env, context, intent, BIND_AUTO_CREATE, backoff, &looper)
+                .unwrap();
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let finished = Arc::new(Mutex::new(false));
+        let states_clone = states.clone();
+        let finished_clone = finished.clone();
+        handler
+            .spawner()
+            .spawn(async move {
+                while let Some(state) = stream.next().await {
+                    states_clone.lock().unwrap().push(state);
+                }
+                *finished_clone.lock().unwrap() = true;
+            })
+            .unwrap();
+
+        let idle = || {
+            for _ in 0..5 {
+                env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+            }
+        };
+        let is_available = |state: &BinderState| match state {
+            BinderState::Available(service) => {
+                assert!(env
+                    .is_same_object(service.as_obj(), binder_ref.as_obj())
+                    .unwrap());
+                true
+            }
+            BinderState::Unavailable => false,
+        };
+
+        idle();
+        {
+            let guard = states.lock().unwrap();
+            assert_eq!(guard.len(), 1);
+            assert!(is_available(&guard[0]));
+        }
+
+        let bound = env
+            .call_method(
+                shadow,
+                "getBoundServiceConnections",
+                "()Ljava/util/List;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let size = env
+            .call_method(bound, "size", "()I", &[])
+            .unwrap()
+            .i()
+            .unwrap();
+        let service_connection = env
+            .call_method(bound, "get", "(I)Ljava/lang/Object;", &[(size - 1).into()])
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            service_connection,
+            "onServiceDisconnected",
+            "(Landroid/content/ComponentName;)V",
+            &[component_name.into()],
+        )
+        .unwrap();
+
+        idle();
+        {
+            let guard = states.lock().unwrap();
+            assert_eq!(guard.len(), 3);
+            assert!(!is_available(&guard[1]));
+            assert!(is_available(&guard[2]));
+        }
+        let unbound = env
+            .call_method(
+                shadow,
+                "getUnboundServiceConnections",
+                "()Ljava/util/List;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(env
+            .call_method(
+                unbound,
+                "contains",
+                "(Ljava/lang/Object;)Z",
+                &[service_connection.into()]
+            )
+            .unwrap()
+            .z()
+            .unwrap());
+
+        drop(conn);
+        idle();
+        assert!(*finished.lock().unwrap());
+        let unbound = env
+            .call_method(
+                shadow,
+                "getUnboundServiceConnections",
+                "()Ljava/util/List;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(unbound, "size", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            2
+        );
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testResilientConnectionQuitLooper(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            os::JLooper,
+            service::{Backoff, ResilientConnection, BIND_AUTO_CREATE},
+        };
+        use jni::errors::Error;
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Landroid/content/Context;Ljava/lang/Class;)V",
+                &[context.into(), class.into()],
+            )
+            .unwrap();
+
+        let name = env.new_string("QuitLooper").unwrap();
+        let thread = env
+            .new_object(
+                "android/os/HandlerThread",
+                "(Ljava/lang/String;)V",
+                &[name.into()],
+            )
+            .unwrap();
+        env.call_method(thread, "start", "()V", &[]).unwrap();
+        let looper = env
+            .call_method(thread, "getLooper", "()Landroid/os/Looper;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let looper = JLooper::from_env(&env, looper).unwrap();
+        looper.quit().unwrap();
+
+        let result = ResilientConnection::new(
+            &env,
+            context,
+            intent,
+            BIND_AUTO_CREATE,
+            Backoff::default(),
+            &looper,
+        );
+        assert!(matches!(result, Err(Error::JavaException)));
+        let exception = env.exception_occurred().unwrap();
+        env.exception_clear().unwrap();
+        assert!(env
+            .is_instance_of(exception, "java/lang/IllegalStateException")
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testMessengerRpc(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            os::AbortHandle,
+            service::rpc::{serve, MessengerClient, MessengerService},
+        };
+        use futures::FutureExt;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize)]
+        enum Request {
+            Add(i32, i32),
+            Greet { name: String },
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Response {
+            Sum(i32),
+            Greeting(String),
+        }
+
+        struct Calculator;
+
+        impl MessengerService for Calculator {
+            type Request = Request;
+            type Response = Response;
+
+            fn handle(&self, _env: &JNIEnv, request: Request) -> Response {
+                match request {
+                    Request::Add(a, b) => Response::Sum(a + b),
+                    Request::Greet { name } => Response::Greeting(format!("Hello, {}!", name)),
+                }
+            }
+        }
+
+        struct RpcService(Mutex<Option<AbortHandle>>);
+
+        impl RustService for RpcService {
+            fn on_bind<'a: 'b, 'b>(
+                &self,
+                env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+            ) -> JObject<'a> {
+                let looper = JLooper::main(env).unwrap();
+                let (messenger, abort) = serve(env, &looper, Calculator).unwrap();
+                *self.0.lock().unwrap() = Some(abort);
+                messenger.get_binder().unwrap()
+            }
+
+            fn on_destroy<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+            ) {
+                if let Some(abort) = self.0.lock().unwrap().take() {
+                    abort.abort().unwrap();
+                }
+            }
+        }
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRpcService")
+            .unwrap();
+        let _registration =
+            register_service(&env, class, |_env, _obj| RpcService(Mutex::new(None))).unwrap();
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let service_controller = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "buildService",
+                "(Ljava/lang/Class;)Lorg/robolectric/android/controller/ServiceController;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            service_controller,
+            "create",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        let service = env
+            .call_method(service_controller, "get", "()Ljava/lang/Object;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let intent = env
+            .new_object("android/content/Intent", "()V", &[])
+            .unwrap();
+        let binder = env
+            .call_method(
+                service,
+                "onBind",
+                "(Landroid/content/Intent;)Landroid/os/IBinder;",
+                &[intent.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let looper = JLooper::main(&env).unwrap();
+        let client = MessengerClient::from_binder(&env, binder, &looper).unwrap();
+        let sum = client
+            .call::<_, Response>(&env, &Request::Add(2, 3))
+            .unwrap();
+        let greeting = client
+            .call::<_, Response>(
+                &env,
+                &Request::Greet {
+                    name: "Rust".into(),
+                },
+            )
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert_eq!(sum.now_or_never().unwrap().unwrap(), Response::Sum(5));
+        assert_eq!(
+            greeting.now_or_never().unwrap().unwrap(),
+            Response::Greeting("Hello, Rust!".into())
+        );
+
+        env.call_method(
+            service_controller,
+            "destroy",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        let unanswered = client
+            .call::<_, Response>(&env, &Request::Add(1, 1))
+            .unwrap();
+        env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+        assert!(unanswered.now_or_never().is_none());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testEnqueueWork(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{enqueue_work, EnqueuedWork, ExpeditedJob};
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[context.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestPlainService")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Landroid/content/Context;Ljava/lang/Class;)V",
+                &[context.into(), class.into()],
+            )
+            .unwrap();
+        let component_name = env
+            .call_method(
+                intent,
+                "getComponent",
+                "()Landroid/content/ComponentName;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let job = ExpeditedJob {
+            job_id: 1,
+            service: component_name,
+        };
+        assert_eq!(
+            enqueue_work(&env, context, intent, &job).unwrap(),
+            EnqueuedWork::ForegroundService
+        );
+        let started = env
+            .call_method(
+                shadow,
+                "getNextStartedService",
+                "()Landroid/content/Intent;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(env
+            .call_method(
+                intent,
+                "filterEquals",
+                "(Landroid/content/Intent;)Z",
+                &[started.into()]
+            )
+            .unwrap()
+            .z()
+            .unwrap());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustIntentService(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::{register_intent_service, RustIntentService};
+        use futures::{future::BoxFuture, FutureExt};
+        use std::{sync::mpsc, time::Duration};
+
+        struct TestService(Mutex<mpsc::Sender<String>>);
+
+        impl RustIntentService for TestService {
+            fn on_handle_intent<'a: 'b, 'b>(
+                &self,
+                env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                intent: JObject<'a>,
+            ) -> BoxFuture<'static, ()> {
+                let action = env
+                    .call_method(intent, "getAction", "()Ljava/lang/String;", &[])
+                    .unwrap()
+                    .l()
+                    .unwrap();
+                let action: String = env.get_string(action.into()).unwrap().into();
+                let sender = self.0.lock().unwrap().clone();
+                async move {
+                    sender.send(action).unwrap();
+                }
+                .boxed()
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustIntentService")
+            .unwrap();
+        let _registration = register_intent_service(&env, class, move |_env, _obj| {
+            TestService(Mutex::new(sender.lock().unwrap().clone()))
+        })
+        .unwrap();
+
+        let action = env
+            .new_string("io.github.gedgygedgy.rust.android.TEST")
+            .unwrap();
+        let intent = env
+            .new_object(
+                "android/content/Intent",
+                "(Ljava/lang/String;)V",
+                &[action.into()],
+            )
+            .unwrap();
+        let service_controller = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "buildService",
+                "(Ljava/lang/Class;Landroid/content/Intent;)Lorg/robolectric/android/controller/ServiceController;",
+                &[class.into(), intent.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        env.call_method(
+            service_controller,
+            "create",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        for start_id in 1..=2 {
+            env.call_method(
+                service_controller,
+                "startCommand",
+                "(II)Lorg/robolectric/android/controller/ServiceController;",
+                &[0.into(), start_id.into()],
+            )
+            .unwrap();
+        }
+        for _ in 0..2 {
+            assert_eq!(
+                receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+                "io.github.gedgygedgy.rust.android.TEST"
+            );
+        }
+
+        let service = env
+            .call_method(service_controller, "get", "()Ljava/lang/Object;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let shadow = env
+            .call_static_method(
+                "org/robolectric/shadow/api/Shadow",
+                "extract",
+                "(Ljava/lang/Object;)Ljava/lang/Object;",
+                &[service.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let mut stopped = false;
+        for _ in 0..50 {
+            stopped = env
+                .call_method(shadow, "isStoppedBySelf", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap();
+            if stopped {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert!(stopped);
+
+        env.call_method(
+            service_controller,
+            "destroy",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRegisterServiceByName(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::service::register_service_by_name;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        struct TestService;
+
+        impl RustService for TestService {
+            fn on_bind<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _service: &ServiceContext<'a, 'b>,
+                _intent: JObject<'a>,
+            ) -> JObject<'a> {
+                JObject::null()
+            }
+        }
+
+        let created = Arc::new(AtomicBool::new(false));
+        let created_clone = created.clone();
+        register_service_by_name(
+            "io.github.gedgygedgy.rust.android.ServiceTest$TestNamedRustService",
+            move |env, class| {
+                register_service(env, class, move |_env, _obj| {
+                    created_clone.store(true, Ordering::SeqCst);
+                    TestService
+                })
+            },
+        );
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestNamedRustService")
+            .unwrap();
+        let service_controller = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "buildService",
+                "(Ljava/lang/Class;)Lorg/robolectric/android/controller/ServiceController;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(!created.load(Ordering::SeqCst));
+        env.call_method(
+            service_controller,
+            "create",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        assert!(created.load(Ordering::SeqCst));
+        assert_eq!(service_instances::<TestService>().len(), 1);
+
+        env.call_method(
+            service_controller,
+            "destroy",
+            "()Lorg/robolectric/android/controller/ServiceController;",
+            &[],
+        )
+        .unwrap();
+        assert!(service_instances::<TestService>().is_empty());
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustContentProvider(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::{
+            app::{register_content_provider, RustContentProvider, Selection},
+            os::bundle::BundleValue,
+        };
+        use std::collections::HashMap;
+
+        struct TestProvider(Mutex<Vec<String>>);
+
+        impl RustContentProvider for TestProvider {
+            fn query<'a: 'b, 'b>(
+                &self,
+                env: &'b JNIEnv<'a>,
+                _provider: JObject<'a>,
+                uri: &str,
+                projection: Option<&[String]>,
+                selection: &Selection,
+                sort_order: Option<&str>,
+            ) -> JObject<'a> {
+                assert_eq!(uri, "content://io.github.gedgygedgy.test/names");
+                assert_eq!(projection, Some(&["name".to_string()][..]));
+                assert_eq!(selection.selection.as_deref(), Some("name = ?"));
+                assert_eq!(selection.args, vec!["a".to_string()]);
+                assert_eq!(sort_order, None);
+
+                let columns = env
+                    .new_object_array(1, "java/lang/String", env.new_string("name").unwrap())
+                    .unwrap();
+                let cursor = env
+                    .new_object(
+                        "android/database/MatrixCursor",
+                        "([Ljava/lang/String;)V",
+                        &[JObject::from(columns).into()],
+                    )
+                    .unwrap();
+                for name in self.0.lock().unwrap().iter() {
+                    let row = env
+                        .new_object_array(1, "java/lang/Object", env.new_string(name).unwrap())
+                        .unwrap();
+                    env.call_method(
+                        cursor,
+                        "addRow",
+                        "([Ljava/lang/Object;)V",
+                        &[JObject::from(row).into()],
+                    )
+                    .unwrap();
+                }
+                cursor
+            }
+
+            fn get_type<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _provider: JObject<'a>,
+                uri: &str,
+            ) -> Option<String> {
+                uri.ends_with("/names")
+                    .then(|| "vnd.android.cursor.dir/name".to_string())
+            }
+
+            fn insert<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _provider: JObject<'a>,
+                uri: &str,
+                values: HashMap<String, BundleValue>,
+            ) -> Option<String> {
+                let name = match values.get("name") {
+                    Some(BundleValue::String(name)) => name.clone(),
+                    _ => return None,
+                };
+                let mut names = self.0.lock().unwrap();
+                names.push(name);
+                Some(format!("{}/{}", uri, names.len()))
+            }
+
+            fn delete<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _provider: JObject<'a>,
+                _uri: &str,
+                selection: &Selection,
+            ) -> jint {
+                assert_eq!(selection, &Selection::default());
+                let mut names = self.0.lock().unwrap();
+                let count = names.len();
+                names.clear();
+                count as jint
+            }
+        }
+
+        let _registration = register_content_provider(
+            &env,
+            "io/github/gedgygedgy/rust/android/ServiceTest$TestRustContentProvider",
+            |_env, _obj| TestProvider(Mutex::new(Vec::new())),
+        )
+        .unwrap();
+
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustContentProvider")
+            .unwrap();
+        let authority = env.new_string("io.github.gedgygedgy.test").unwrap();
+        env.call_static_method(
+            "org/robolectric/Robolectric",
+            "setupContentProvider",
+            "(Ljava/lang/Class;Ljava/lang/String;)Landroid/content/ContentProvider;",
+            &[class.into(), authority.into()],
+        )
+        .unwrap();
+
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let resolver = env
+            .call_method(
+                context,
+                "getContentResolver",
+                "()Landroid/content/ContentResolver;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let uri = env
+            .new_string("content://io.github.gedgygedgy.test/names")
+            .unwrap();
+        let uri = env
+            .call_static_method(
+                "android/net/Uri",
+                "parse",
+                "(Ljava/lang/String;)Landroid/net/Uri;",
+                &[uri.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let ty = env
+            .call_method(
+                resolver,
+                "getType",
+                "(Landroid/net/Uri;)Ljava/lang/String;",
+                &[uri.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let ty: String = env.get_string(ty.into()).unwrap().into();
+        assert_eq!(ty, "vnd.android.cursor.dir/name");
+
+        let values = env
+            .new_object("android/content/ContentValues", "()V", &[])
+            .unwrap();
+        let key = env.new_string("name").unwrap();
+        let value = env.new_string("a").unwrap();
+        env.call_method(
+            values,
+            "put",
+            "(Ljava/lang/String;Ljava/lang/String;)V",
+            &[key.into(), value.into()],
         )
         .unwrap();
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.created, true);
-            assert!(env
-                .is_same_object(guard.intent.as_ref().unwrap(), intent)
-                .unwrap());
-            assert_eq!(guard.rebound, true);
-            assert!(guard.start_id.is_none());
+        let inserted = env
+            .call_method(
+                resolver,
+                "insert",
+                "(Landroid/net/Uri;Landroid/content/ContentValues;)Landroid/net/Uri;",
+                &[uri.into(), values.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let inserted = env
+            .call_method(inserted, "toString", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+        let inserted: String = env.get_string(inserted.into()).unwrap().into();
+        assert_eq!(inserted, "content://io.github.gedgygedgy.test/names/1");
+
+        let projection = env
+            .new_object_array(1, "java/lang/String", env.new_string("name").unwrap())
+            .unwrap();
+        let selection = env.new_string("name = ?").unwrap();
+        let selection_args = env
+            .new_object_array(1, "java/lang/String", env.new_string("a").unwrap())
+            .unwrap();
+        let cursor = env
+            .call_method(
+                resolver,
+                "query",
+                "(Landroid/net/Uri;[Ljava/lang/String;Ljava/lang/String;[Ljava/lang/String;Ljava/lang/String;)Landroid/database/Cursor;",
+                &[
+                    uri.into(),
+                    JObject::from(projection).into(),
+                    selection.into(),
+                    JObject::from(selection_args).into(),
+                    JObject::null().into(),
+                ],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(cursor, "getCount", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            1
+        );
+
+        let deleted = env
+            .call_method(
+                resolver,
+                "delete",
+                "(Landroid/net/Uri;Ljava/lang/String;[Ljava/lang/String;)I",
+                &[uri.into(), JObject::null().into(), JObject::null().into()],
+            )
+            .unwrap()
+            .i()
+            .unwrap();
+        assert_eq!(deleted, 1);
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustConnectionService(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::telecom::{
+            async_connection, register_connection_service, unregister_connection_service,
+            RustConnectionService, DISCONNECT_ERROR, STATE_DISCONNECTED,
+        };
+
+        struct TestConnectionService(Arc<Mutex<bool>>);
+
+        impl RustConnectionService for TestConnectionService {
+            fn on_create_incoming_connection<'a: 'b, 'b>(
+                &self,
+                env: &'b JNIEnv<'a>,
+                _phone_account: JObject<'a>,
+                _request: JObject<'a>,
+            ) -> JObject<'a> {
+                let (connection, _stream) = async_connection(env).unwrap();
+                connection.set_ringing().unwrap();
+                connection.into()
+            }
+
+            fn on_create_outgoing_connection_failed<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _phone_account: JObject<'a>,
+                _request: JObject<'a>,
+            ) {
+                *self.0.lock().unwrap() = true;
+            }
         }
 
+        let failed = Arc::new(Mutex::new(false));
+        let failed_clone = failed.clone();
+        let class = env
+            .find_class("io/github/gedgygedgy/rust/android/ServiceTest$TestRustConnectionService")
+            .unwrap();
+        register_connection_service(&env, class, move |_env, _obj| {
+            TestConnectionService(failed_clone.clone())
+        })
+        .unwrap();
+
+        let service_controller = env
+            .call_static_method(
+                "org/robolectric/Robolectric",
+                "buildService",
+                "(Ljava/lang/Class;)Lorg/robolectric/android/controller/ServiceController;",
+                &[class.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
         env.call_method(
             service_controller,
-            "unbind",
+            "create",
             "()Lorg/robolectric/android/controller/ServiceController;",
             &[],
         )
         .unwrap();
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.created, true);
-            assert!(guard.intent.is_none());
-            assert_eq!(guard.rebound, true);
-            assert!(guard.start_id.is_none());
-        }
+        let service = env
+            .call_method(service_controller, "get", "()Ljava/lang/Object;", &[])
+            .unwrap()
+            .l()
+            .unwrap();
+
+        let incoming = env
+            .call_method(
+                service,
+                "onCreateIncomingConnection",
+                "(Landroid/telecom/PhoneAccountHandle;Landroid/telecom/ConnectionRequest;)Landroid/telecom/Connection;",
+                &[JObject::null().into(), JObject::null().into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert!(env
+            .is_instance_of(
+                incoming,
+                "io/github/gedgygedgy/rust/android/telecom/RustConnection"
+            )
+            .unwrap());
+        assert_eq!(
+            env.call_method(incoming, "getState", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            android_utils::telecom::STATE_RINGING
+        );
+
+        let outgoing = env
+            .call_method(
+                service,
+                "onCreateOutgoingConnection",
+                "(Landroid/telecom/PhoneAccountHandle;Landroid/telecom/ConnectionRequest;)Landroid/telecom/Connection;",
+                &[JObject::null().into(), JObject::null().into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(outgoing, "getState", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            STATE_DISCONNECTED
+        );
+        let cause = env
+            .call_method(
+                outgoing,
+                "getDisconnectCause",
+                "()Landroid/telecom/DisconnectCause;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        assert_eq!(
+            env.call_method(cause, "getCode", "()I", &[])
+                .unwrap()
+                .i()
+                .unwrap(),
+            DISCONNECT_ERROR
+        );
 
         env.call_method(
-            service_controller,
-            "startCommand",
-            "(II)Lorg/robolectric/android/controller/ServiceController;",
-            &[android_utils::service::START_FLAG_RETRY.into(), 42.into()],
+            service,
+            "onCreateOutgoingConnectionFailed",
+            "(Landroid/telecom/PhoneAccountHandle;Landroid/telecom/ConnectionRequest;)V",
+            &[JObject::null().into(), JObject::null().into()],
         )
         .unwrap();
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.start_id.unwrap(), 42);
-            assert_eq!(
-                guard.start_flags.unwrap(),
-                android_utils::service::START_FLAG_RETRY
-            );
-        }
+        assert_eq!(*failed.lock().unwrap(), true);
 
         env.call_method(
             service_controller,
@@ -999,9 +7535,248 @@ pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustSer
             &[],
         )
         .unwrap();
-        {
-            let guard = data.lock().unwrap();
-            assert_eq!(guard.created, false);
+        unregister_connection_service(&env, class).unwrap();
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_ServiceTest_testRustWorker(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use android_utils::work::{register_worker, unregister_worker, RustWorker, WorkResult};
+        use futures::future::{pending, BoxFuture, FutureExt};
+        use serde::{Deserialize, Serialize};
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        #[derive(Deserialize)]
+        struct Input {
+            a: i32,
+            b: i32,
+        }
+
+        #[derive(Serialize)]
+        struct Output {
+            sum: i32,
+        }
+
+        struct AddWorker {
+            stopped: Arc<AtomicBool>,
+        }
+
+        impl RustWorker for AddWorker {
+            type Input = Input;
+            type Output = Output;
+
+            fn start_work<'a: 'b, 'b>(
+                &self,
+                _env: &'b JNIEnv<'a>,
+                _worker: JObject<'a>,
+                input: Input,
+            ) -> BoxFuture<'static, WorkResult<Output>> {
+                match (input.a, input.b) {
+                    (0, 0) => async { WorkResult::Retry }.boxed(),
+                    (-1, _) => pending().boxed(),
+                    (a, b) if a + b < 0 => {
+                        async move { WorkResult::Failure(Output { sum: a + b }) }.boxed()
+                    }
+                    (a, b) => async move { WorkResult::Success(Output { sum: a + b }) }.boxed(),
+                }
+            }
+
+            fn on_stopped(&self, _env: &JNIEnv) {
+                self.stopped.store(true, Ordering::SeqCst);
+            }
         }
+
+        const WORKER_CLASS: &str = "io/github/gedgygedgy/rust/android/ServiceTest$TestRustWorker";
+        const RESULT_CLASS: &str = "androidx/work/ListenableWorker$Result";
+
+        let (shadow_looper, _handler) = shadow_looper_and_handler(&env);
+        let context = env
+            .call_static_method(
+                "androidx/test/core/app/ApplicationProvider",
+                "getApplicationContext",
+                "()Landroid/content/Context;",
+                &[],
+            )
+            .unwrap()
+            .l()
+            .unwrap();
+        let data = |values: &[(&str, i32)]| {
+            let builder = env
+                .new_object("androidx/work/Data$Builder", "()V", &[])
+                .unwrap();
+            for (key, value) in values {
+                let key = env.new_string(key).unwrap();
+                env.call_method(
+                    builder,
+                    "putInt",
+                    "(Ljava/lang/String;I)Landroidx/work/Data$Builder;",
+                    &[key.into(), (*value).into()],
+                )
+                .unwrap();
+            }
+            env.call_method(builder, "build", "()Landroidx/work/Data;", &[])
+                .unwrap()
+                .l()
+                .unwrap()
+        };
+        let result = |method: &str, output: Option<JObject>| match output {
+            Some(output) => env
+                .call_static_method(
+                    RESULT_CLASS,
+                    method,
+                    "(Landroidx/work/Data;)Landroidx/work/ListenableWorker$Result;",
+                    &[output.into()],
+                )
+                .unwrap()
+                .l()
+                .unwrap(),
+            None => env
+                .call_static_method(
+                    RESULT_CLASS,
+                    method,
+                    "()Landroidx/work/ListenableWorker$Result;",
+                    &[],
+                )
+                .unwrap()
+                .l()
+                .unwrap(),
+        };
+        let worker_class = env.find_class(WORKER_CLASS).unwrap();
+        let start = |input: JObject<'_>| {
+            let builder = env
+                .call_static_method(
+                    "androidx/work/testing/TestListenableWorkerBuilder",
+                    "from",
+                    "(Landroid/content/Context;Ljava/lang/Class;)Landroidx/work/testing/TestListenableWorkerBuilder;",
+                    &[context.into(), worker_class.into()],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            env.call_method(
+                builder,
+                "setInputData",
+                "(Landroidx/work/Data;)Landroidx/work/testing/TestListenableWorkerBuilder;",
+                &[input.into()],
+            )
+            .unwrap();
+            let worker = env
+                .call_method(builder, "build", "()Landroidx/work/ListenableWorker;", &[])
+                .unwrap()
+                .l()
+                .unwrap();
+            let future = env
+                .call_method(
+                    worker,
+                    "startWork",
+                    "()Lcom/google/common/util/concurrent/ListenableFuture;",
+                    &[],
+                )
+                .unwrap()
+                .l()
+                .unwrap();
+            env.call_method(shadow_looper, "idle", "()V", &[]).unwrap();
+            (worker, future)
+        };
+        let get = |future| {
+            assert!(env
+                .call_method(future, "isDone", "()Z", &[])
+                .unwrap()
+                .z()
+                .unwrap());
+            env.call_method(future, "get", "()Ljava/lang/Object;", &[])
+                .unwrap()
+                .l()
+                .unwrap()
+        };
+        let assert_result = |actual: JObject<'_>, expected: JObject<'_>| {
+            assert!(env
+                .call_method(
+                    actual,
+                    "equals",
+                    "(Ljava/lang/Object;)Z",
+                    &[expected.into()]
+                )
+                .unwrap()
+                .z()
+                .unwrap());
+        };
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stopped2 = stopped.clone();
+        register_worker(&env, WORKER_CLASS, move |_env, _worker| AddWorker {
+            stopped: stopped2.clone(),
+        })
+        .unwrap();
+
+        let (_worker, future) = start(data(&[("a", 2), ("b", 3)]));
+        assert_result(get(future), result("success", Some(data(&[("sum", 5)]))));
+
+        let (_worker, future) = start(data(&[("a", 2), ("b", -5)]));
+        assert_result(get(future), result("failure", Some(data(&[("sum", -3)]))));
+
+        let (_worker, future) = start(data(&[("a", 0), ("b", 0)]));
+        assert_result(get(future), result("retry", None));
+
+        // Input which doesn't match the input type fails the work.
+        let (_worker, future) = start(data(&[("a", 2)]));
+        assert_result(get(future), result("failure", None));
+
+        let (worker, future) = start(data(&[("a", -1), ("b", 0)]));
+        assert!(!env
+            .call_method(future, "isDone", "()Z", &[])
+            .unwrap()
+            .z()
+            .unwrap());
+        assert!(!stopped.load(Ordering::SeqCst));
+        env.call_method(worker, "stop", "()V", &[]).unwrap();
+        assert!(stopped.load(Ordering::SeqCst));
+
+        unregister_worker(&env, WORKER_CLASS).unwrap();
+        let (_worker, future) = start(data(&[("a", 2), ("b", 3)]));
+        assert_result(get(future), result("failure", None));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_io_github_gedgygedgy_rust_android_NoInitTest_testSpawnWithoutInit(
+    env: JNIEnv,
+    _obj: JObject,
+) {
+    let _ = throw_unwind(&env, || {
+        use futures::{channel::oneshot::channel, task::SpawnExt};
+
+        assert!(android_utils::java_vm().is_none());
+        let (shadow_looper, handler) = shadow_looper_and_handler(&env);
+        assert!(android_utils::java_vm().is_some());
+
+        let (sender, receiver) = channel::<()>();
+        let done = Arc::new(Mutex::new(false));
+        let done2 = done.clone();
+        let handler_spawn = handler.spawner();
+        handler_spawn
+            .spawn(async move {
+                receiver.await.unwrap();
+                *done2.lock().unwrap() = true;
+            })
+            .unwrap();
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert!(!*done.lock().unwrap());
+
+        // Wake the task from a thread which has never been attached.
+        std::thread::spawn(|| {
+            sender.send(()).unwrap();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(handler_spawn.metrics().fallback_wakes, 0);
+        env.call_method(shadow_looper, "runOneTask", "()V", &[])
+            .unwrap();
+        assert!(*done.lock().unwrap());
     });
 }